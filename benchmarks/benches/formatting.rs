@@ -33,12 +33,12 @@ fn bench_formatting(c: &mut criterion::Criterion) {
     ];
 
     let format_options = &[
-        FormatOptions { highlight: false, crop: None },
-        FormatOptions { highlight: true, crop: None },
-        FormatOptions { highlight: false, crop: Some(10) },
-        FormatOptions { highlight: true, crop: Some(10) },
-        FormatOptions { highlight: false, crop: Some(20) },
-        FormatOptions { highlight: true, crop: Some(20) },
+        FormatOptions { highlight: false, crop: None, ..Default::default() },
+        FormatOptions { highlight: true, crop: None, ..Default::default() },
+        FormatOptions { highlight: false, crop: Some(10), ..Default::default() },
+        FormatOptions { highlight: true, crop: Some(10), ..Default::default() },
+        FormatOptions { highlight: false, crop: Some(20), ..Default::default() },
+        FormatOptions { highlight: true, crop: Some(20), ..Default::default() },
     ];
 
     for option in format_options {
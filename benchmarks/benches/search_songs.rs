@@ -155,6 +155,12 @@ fn bench_songs(c: &mut criterion::Criterion) {
             filter: Some("released-timestamp != 1262347200 AND (NOT (released-timestamp = 946728000)) AND (duration-float = 1 OR (duration-float 1.1 TO 1.5 AND released-timestamp > 315576000))"),
             ..BASE_CONF
         },
+        utils::Conf {
+            group_name: "repeated filter",
+            // 19 nodes and 10 leaves, only 5 of them are distinct
+            filter: Some("(genre = rock AND released-timestamp > 315576000) OR (genre = rock AND duration-float 1.1 TO 1.5) OR (genre = jazz AND released-timestamp > 315576000) OR (genre = jazz AND duration-float 1.1 TO 1.5) OR (genre = rock AND genre = jazz)"),
+            ..BASE_CONF
+        },
 
         /* the we bench some global / normal search with all the default criterion in the default
          * order */
@@ -3,15 +3,23 @@
 //! ```text
 //! condition      = value ("==" | ">" ...) value
 //! to             = value value TO value
+//! in             = value "IN" "[" (value ("," value)*)? "]"
+//! exists         = value "EXISTS"
+//! not_exists     = value "NOT" WS+ "EXISTS"
+//! is_empty       = value "IS" WS+ "EMPTY"
+//! is_not_empty   = value "IS" WS+ "NOT" WS+ "EMPTY"
+//! contains       = value "CONTAINS" value
 //! ```
 
 use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::combinator::cut;
-use nom::sequence::tuple;
+use nom::character::complete::{char, multispace1};
+use nom::combinator::{cut, peek};
+use nom::multi::separated_list0;
+use nom::sequence::{delimited, terminated, tuple};
 use Condition::*;
 
-use crate::{parse_value, FilterCondition, IResult, Span, Token};
+use crate::{parse_value, ws, Error, ErrorKind, FilterCondition, IResult, Span, Token};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Condition<'a> {
@@ -65,3 +73,73 @@ pub fn parse_to(input: Span) -> IResult<FilterCondition> {
 
     Ok((input, FilterCondition::Condition { fid: key, op: Between { from, to } }))
 }
+
+/// in             = value "IN" "[" (value ("," value)*)? "]"
+/// If we parse `IN` we MUST parse a list of values, `IN` must be followed by a whitespace or
+/// the list to not be the beginning of another word.
+pub fn parse_in(input: Span) -> IResult<FilterCondition> {
+    let in_keyword = terminated(tag("IN"), peek(alt((multispace1, tag("[")))));
+    let (input, (fid, _)) = tuple((parse_value, in_keyword))(input)?;
+
+    // if we were able to parse `IN` and can't parse the list of values we return a failure
+    let (input, els) =
+        cut(delimited(ws(char('[')), separated_list0(char(','), parse_value), ws(char(']'))))(
+            input,
+        )
+        .map_err(|e| {
+            e.map(|error| {
+                // a missing bracket means the user did not write a list of values
+                if matches!(error.kind(), ErrorKind::Char(_)) {
+                    Error::new_from_kind(input, ErrorKind::InList)
+                } else {
+                    error
+                }
+            })
+        })?;
+
+    Ok((input, FilterCondition::In { fid, els }))
+}
+
+/// exists         = value "EXISTS"
+pub fn parse_exists(input: Span) -> IResult<FilterCondition> {
+    let (input, (fid, _)) = tuple((parse_value, tag("EXISTS")))(input)?;
+
+    Ok((input, FilterCondition::Exists { fid }))
+}
+
+/// not_exists     = value "NOT" WS+ "EXISTS"
+pub fn parse_not_exists(input: Span) -> IResult<FilterCondition> {
+    let (input, (fid, _, _, _)) =
+        tuple((parse_value, tag("NOT"), multispace1, tag("EXISTS")))(input)?;
+
+    Ok((input, FilterCondition::NotExists { fid }))
+}
+
+/// is_empty       = value "IS" WS+ "EMPTY"
+/// The field is in the document but without any value, e.g. an empty array.
+pub fn parse_is_empty(input: Span) -> IResult<FilterCondition> {
+    let (input, (fid, _, _, _)) =
+        tuple((parse_value, tag("IS"), multispace1, tag("EMPTY")))(input)?;
+
+    Ok((input, FilterCondition::IsEmpty { fid }))
+}
+
+/// is_not_empty   = value "IS" WS+ "NOT" WS+ "EMPTY"
+/// The field has at least one value, a document without the field is neither empty nor not empty.
+pub fn parse_is_not_empty(input: Span) -> IResult<FilterCondition> {
+    let (input, (fid, _, _, _, _, _)) =
+        tuple((parse_value, tag("IS"), multispace1, tag("NOT"), multispace1, tag("EMPTY")))(input)?;
+
+    Ok((input, FilterCondition::IsNotEmpty { fid }))
+}
+
+/// contains       = value "CONTAINS" value
+/// If we parse `CONTAINS` we MUST parse the substring, `CONTAINS` must be followed by a whitespace
+/// or a quote to not be the beginning of another word.
+pub fn parse_contains(input: Span) -> IResult<FilterCondition> {
+    let contains_keyword =
+        terminated(tag("CONTAINS"), peek(alt((multispace1, tag("'"), tag("\"")))));
+    let (input, (fid, _, value)) = tuple((parse_value, contains_keyword, cut(parse_value)))(input)?;
+
+    Ok((input, FilterCondition::Contains { fid, value }))
+}
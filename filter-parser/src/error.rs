@@ -52,7 +52,12 @@ pub struct Error<'a> {
 pub enum ErrorKind<'a> {
     ReservedGeo(&'a str),
     Geo,
+    GeoBoundingBox,
+    GeoPolygon,
     MisusedGeo,
+    MisusedGeoBoundingBox,
+    MisusedGeoPolygon,
+    InList,
     InvalidPrimary,
     ExpectedEof,
     ExpectedValue,
@@ -128,10 +133,10 @@ impl<'a> Display for Error<'a> {
                 writeln!(f, "Was expecting a value but instead got `{}`.", escaped_input)?
             }
             ErrorKind::InvalidPrimary if input.trim().is_empty() => {
-                writeln!(f, "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO` or `_geoRadius` but instead got nothing.")?
+                writeln!(f, "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO`, `IN`, `NOT IN`, `EXISTS`, `NOT EXISTS`, `IS EMPTY`, `IS NOT EMPTY`, `CONTAINS`, `_geoRadius`, `_geoBoundingBox` or `_geoPolygon` but instead got nothing.")?
             }
             ErrorKind::InvalidPrimary => {
-                writeln!(f, "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO` or `_geoRadius` at `{}`.", escaped_input)?
+                writeln!(f, "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO`, `IN`, `NOT IN`, `EXISTS`, `NOT EXISTS`, `IS EMPTY`, `IS NOT EMPTY`, `CONTAINS`, `_geoRadius`, `_geoBoundingBox` or `_geoPolygon` at `{}`.", escaped_input)?
             }
             ErrorKind::ExpectedEof => {
                 writeln!(f, "Found unexpected characters at the end of the filter: `{}`. You probably forgot an `OR` or an `AND` rule.", escaped_input)?
@@ -139,12 +144,27 @@ impl<'a> Display for Error<'a> {
             ErrorKind::Geo => {
                 writeln!(f, "The `_geoRadius` filter expects three arguments: `_geoRadius(latitude, longitude, radius)`.")?
             }
+            ErrorKind::GeoBoundingBox => {
+                writeln!(f, "The `_geoBoundingBox` filter expects two pairs of arguments: `_geoBoundingBox([latitude, longitude], [latitude, longitude])`.")?
+            }
+            ErrorKind::GeoPolygon => {
+                writeln!(f, "The `_geoPolygon` filter expects a list of at least three points: `_geoPolygon([[latitude, longitude], [latitude, longitude], [latitude, longitude]])`.")?
+            }
             ErrorKind::ReservedGeo(name) => {
                 writeln!(f, "`{}` is a reserved keyword and thus can't be used as a filter expression. Use the `_geoRadius(latitude, longitude, distance) built-in rule to filter on `_geo` coordinates.", name.escape_debug())?
             }
             ErrorKind::MisusedGeo => {
                 writeln!(f, "The `_geoRadius` filter is an operation and can't be used as a value.")?
             }
+            ErrorKind::MisusedGeoBoundingBox => {
+                writeln!(f, "The `_geoBoundingBox` filter is an operation and can't be used as a value.")?
+            }
+            ErrorKind::MisusedGeoPolygon => {
+                writeln!(f, "The `_geoPolygon` filter is an operation and can't be used as a value.")?
+            }
+            ErrorKind::InList => {
+                writeln!(f, "The `IN` operator expects a list of values: `field IN [value, value]`.")?
+            }
             ErrorKind::Char(c) => {
                 panic!("Tried to display a char error with `{}`", c)
             }
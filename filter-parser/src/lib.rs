@@ -6,16 +6,27 @@
 //! or             = and (~ "OR" ~ and)
 //! and            = not (~ "AND" not)*
 //! not            = ("NOT" ~ not) | primary
-//! primary        = (WS* ~ "("  expression ")" ~ WS*) | geoRadius | condition | to
+//! primary        = (WS* ~ "("  expression ")" ~ WS*) | geoRadius | geoBoundingBox | geoPolygon | condition | to | in | exists | not_exists | is_empty | is_not_empty | contains
 //! condition      = value ("==" | ">" ...) value
 //! to             = value value TO value
+//! in             = value "IN" "[" (value ("," value)*)? "]"
+//! exists         = value "EXISTS"
+//! not_exists     = value "NOT" WS+ "EXISTS"
+//! is_empty       = value "IS" WS+ "EMPTY"
+//! is_not_empty   = value "IS" WS+ "NOT" WS+ "EMPTY"
+//! contains       = value "CONTAINS" value
 //! value          = WS* ~ ( word | singleQuoted | doubleQuoted) ~ WS*
 //! singleQuoted   = "'" .* all but quotes "'"
 //! doubleQuoted   = "\"" .* all but double quotes "\""
 //! word           = (alphanumeric | _ | - | .)+
 //! geoRadius      = WS* ~ "_geoRadius(" ~ WS* ~ float ~ WS* ~ "," ~ WS* ~ float ~ WS* ~ "," float ~ WS* ~ ")"
+//! geoBoundingBox = WS* ~ "_geoBoundingBox(" ~ WS* ~ "[" ~ float ~ "," ~ float ~ "]" ~ WS* ~ "," ~ WS* ~ "[" ~ float ~ "," ~ float ~ "]" ~ WS* ~ ")"
+//! geoPolygon     = WS* ~ "_geoPolygon(" ~ WS* ~ "[" ~ ("[" ~ float ~ "," ~ float ~ "]" ~ ",")* ~ "]" ~ WS* ~ ")"
 //! ```
 //!
+//! The unquoted `true` and `false` words are the booleans when compared with `=`, `!=` and `IN`,
+//! the quoted `"true"` and `'false'` values are always strings.
+//!
 //! Other BNF grammar used to handle some specific errors:
 //! ```text
 //! geoPoint       = WS* ~ "_geoPoint(" ~ (float ~ ",")* ~ ")"
@@ -42,7 +53,10 @@ mod value;
 use std::fmt::Debug;
 use std::str::FromStr;
 
-pub use condition::{parse_condition, parse_to, Condition};
+pub use condition::{
+    parse_condition, parse_contains, parse_exists, parse_in, parse_is_empty, parse_is_not_empty,
+    parse_not_exists, parse_to, Condition,
+};
 use error::{cut_with_err, NomErrorExt};
 pub use error::{Error, ErrorKind};
 use nom::branch::alt;
@@ -88,6 +102,13 @@ impl<'a> Token<'a> {
         self.value.as_ref().map_or(&self.span, |value| value)
     }
 
+    /// Returns `true` if the token was written between quotes in the filter,
+    /// e.g. to tell the string `"true"` apart from the boolean `true`.
+    pub fn is_quoted(&self) -> bool {
+        let before = self.span.extra.get(..self.span.location_offset()).unwrap_or_default();
+        before.ends_with('\'') || before.ends_with('"')
+    }
+
     pub fn as_external_error(&self, error: impl std::error::Error) -> Error<'a> {
         Error::new_from_external(self.span, error)
     }
@@ -110,10 +131,22 @@ impl<'a> From<Span<'a>> for Token<'a> {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FilterCondition<'a> {
     Condition { fid: Token<'a>, op: Condition<'a> },
+    In { fid: Token<'a>, els: Vec<Token<'a>> },
+    NotIn { fid: Token<'a>, els: Vec<Token<'a>> },
+    Exists { fid: Token<'a> },
+    NotExists { fid: Token<'a> },
+    IsEmpty { fid: Token<'a> },
+    IsNotEmpty { fid: Token<'a> },
+    Contains { fid: Token<'a>, value: Token<'a> },
+    NotContains { fid: Token<'a>, value: Token<'a> },
     Or(Box<Self>, Box<Self>),
     And(Box<Self>, Box<Self>),
     GeoLowerThan { point: [Token<'a>; 2], radius: Token<'a> },
     GeoGreaterThan { point: [Token<'a>; 2], radius: Token<'a> },
+    GeoBoundingBox { top_right_point: [Token<'a>; 2], bottom_left_point: [Token<'a>; 2] },
+    GeoOutsideBoundingBox { top_right_point: [Token<'a>; 2], bottom_left_point: [Token<'a>; 2] },
+    GeoPolygon { points: Vec<[Token<'a>; 2]> },
+    GeoOutsidePolygon { points: Vec<[Token<'a>; 2]> },
 }
 
 impl<'a> FilterCondition<'a> {
@@ -121,6 +154,14 @@ impl<'a> FilterCondition<'a> {
     pub fn token_at_depth(&self, depth: usize) -> Option<&Token> {
         match self {
             FilterCondition::Condition { fid, .. } if depth == 0 => Some(fid),
+            FilterCondition::In { fid, .. } if depth == 0 => Some(fid),
+            FilterCondition::NotIn { fid, .. } if depth == 0 => Some(fid),
+            FilterCondition::Exists { fid } if depth == 0 => Some(fid),
+            FilterCondition::NotExists { fid } if depth == 0 => Some(fid),
+            FilterCondition::IsEmpty { fid } if depth == 0 => Some(fid),
+            FilterCondition::IsNotEmpty { fid } if depth == 0 => Some(fid),
+            FilterCondition::Contains { fid, .. } if depth == 0 => Some(fid),
+            FilterCondition::NotContains { fid, .. } if depth == 0 => Some(fid),
             FilterCondition::Or(left, right) => {
                 let depth = depth.saturating_sub(1);
                 right.token_at_depth(depth).or_else(|| left.token_at_depth(depth))
@@ -131,6 +172,20 @@ impl<'a> FilterCondition<'a> {
             }
             FilterCondition::GeoLowerThan { point: [point, _], .. } if depth == 0 => Some(point),
             FilterCondition::GeoGreaterThan { point: [point, _], .. } if depth == 0 => Some(point),
+            FilterCondition::GeoBoundingBox { top_right_point: [point, _], .. } if depth == 0 => {
+                Some(point)
+            }
+            FilterCondition::GeoOutsideBoundingBox { top_right_point: [point, _], .. }
+                if depth == 0 =>
+            {
+                Some(point)
+            }
+            FilterCondition::GeoPolygon { points }
+            | FilterCondition::GeoOutsidePolygon { points }
+                if depth == 0 =>
+            {
+                points.first().map(|[point, _]| point)
+            }
             _ => None,
         }
     }
@@ -146,10 +201,26 @@ impl<'a> FilterCondition<'a> {
                     Condition { fid, op: b }.into(),
                 ),
             },
+            In { fid, els } => NotIn { fid, els },
+            NotIn { fid, els } => In { fid, els },
+            Exists { fid } => NotExists { fid },
+            NotExists { fid } => Exists { fid },
+            IsEmpty { fid } => IsNotEmpty { fid },
+            IsNotEmpty { fid } => IsEmpty { fid },
+            Contains { fid, value } => NotContains { fid, value },
+            NotContains { fid, value } => Contains { fid, value },
             Or(a, b) => And(a.negate().into(), b.negate().into()),
             And(a, b) => Or(a.negate().into(), b.negate().into()),
             GeoLowerThan { point, radius } => GeoGreaterThan { point, radius },
             GeoGreaterThan { point, radius } => GeoLowerThan { point, radius },
+            GeoBoundingBox { top_right_point, bottom_left_point } => {
+                GeoOutsideBoundingBox { top_right_point, bottom_left_point }
+            }
+            GeoOutsideBoundingBox { top_right_point, bottom_left_point } => {
+                GeoBoundingBox { top_right_point, bottom_left_point }
+            }
+            GeoPolygon { points } => GeoOutsidePolygon { points },
+            GeoOutsidePolygon { points } => GeoPolygon { points },
         }
     }
 
@@ -221,6 +292,73 @@ fn parse_geo_radius(input: Span) -> IResult<FilterCondition> {
     Ok((input, res))
 }
 
+/// geoBoundingBox = WS* ~ "_geoBoundingBox([float ~ "," ~ float], [float ~ "," ~ float])"
+/// If we parse `_geoBoundingBox` we MUST parse the rest of the expression.
+fn parse_geo_bounding_box(input: Span) -> IResult<FilterCondition> {
+    // we want to forbid space BEFORE the _geoBoundingBox but not after
+    let parsed = preceded(
+        tuple((multispace0, tag("_geoBoundingBox"))),
+        // if we were able to parse `_geoBoundingBox` and can't parse the rest of the input we return a failure
+        cut(delimited(
+            char('('),
+            separated_list1(
+                tag(","),
+                ws(delimited(char('['), separated_list1(tag(","), ws(recognize_float)), char(']'))),
+            ),
+            char(')'),
+        )),
+    )(input)
+    .map_err(|e| e.map(|_| Error::new_from_kind(input, ErrorKind::GeoBoundingBox)));
+
+    let (input, args) = parsed?;
+
+    if args.len() != 2 || args.iter().any(|point| point.len() != 2) {
+        return Err(nom::Err::Failure(Error::new_from_kind(input, ErrorKind::GeoBoundingBox)));
+    }
+
+    let res = FilterCondition::GeoBoundingBox {
+        top_right_point: [args[0][0].into(), args[0][1].into()],
+        bottom_left_point: [args[1][0].into(), args[1][1].into()],
+    };
+    Ok((input, res))
+}
+
+/// geoPolygon     = WS* ~ "_geoPolygon([[float ~ "," ~ float], [float ~ "," ~ float], ...])"
+/// If we parse `_geoPolygon` we MUST parse the rest of the expression.
+fn parse_geo_polygon(input: Span) -> IResult<FilterCondition> {
+    // we want to forbid space BEFORE the _geoPolygon but not after
+    let parsed = preceded(
+        tuple((multispace0, tag("_geoPolygon"))),
+        // if we were able to parse `_geoPolygon` and can't parse the rest of the input we return a failure
+        cut(delimited(
+            char('('),
+            ws(delimited(
+                char('['),
+                separated_list1(
+                    tag(","),
+                    ws(delimited(
+                        char('['),
+                        separated_list1(tag(","), ws(recognize_float)),
+                        char(']'),
+                    )),
+                ),
+                char(']'),
+            )),
+            char(')'),
+        )),
+    )(input)
+    .map_err(|e| e.map(|_| Error::new_from_kind(input, ErrorKind::GeoPolygon)));
+
+    let (input, args) = parsed?;
+
+    if args.len() < 3 || args.iter().any(|point| point.len() != 2) {
+        return Err(nom::Err::Failure(Error::new_from_kind(input, ErrorKind::GeoPolygon)));
+    }
+
+    let points = args.into_iter().map(|point| [point[0].into(), point[1].into()]).collect();
+    Ok((input, FilterCondition::GeoPolygon { points }))
+}
+
 /// geoPoint      = WS* ~ "_geoPoint(float ~ "," ~ float ~ "," float)
 fn parse_geo_point(input: Span) -> IResult<FilterCondition> {
     // we want to forbid space BEFORE the _geoPoint but not after
@@ -235,7 +373,7 @@ fn parse_geo_point(input: Span) -> IResult<FilterCondition> {
     Err(nom::Err::Failure(Error::new_from_kind(input, ErrorKind::ReservedGeo("_geoPoint"))))
 }
 
-/// primary        = (WS* ~ "("  expression ")" ~ WS*) | geoRadius | condition | to
+/// primary        = (WS* ~ "("  expression ")" ~ WS*) | geoRadius | geoBoundingBox | geoPolygon | condition | to | in | exists | not_exists | is_empty | is_not_empty | contains
 fn parse_primary(input: Span) -> IResult<FilterCondition> {
     alt((
         // if we find a first parenthesis, then we must parse an expression and find the closing parenthesis
@@ -247,8 +385,16 @@ fn parse_primary(input: Span) -> IResult<FilterCondition> {
             }),
         ),
         parse_geo_radius,
+        parse_geo_bounding_box,
+        parse_geo_polygon,
         parse_condition,
         parse_to,
+        parse_in,
+        parse_exists,
+        parse_not_exists,
+        parse_is_empty,
+        parse_is_not_empty,
+        parse_contains,
         // the next lines are only for error handling and are written at the end to have the less possible performance impact
         parse_geo_point,
     ))(input)
@@ -445,6 +591,91 @@ pub mod tests {
                     .into(),
                 ),
             ),
+            (
+                "colour IN [green, 'dark blue']",
+                Fc::In {
+                    fid: rtok("", "colour"),
+                    els: vec![
+                        rtok("colour IN [", "green"),
+                        rtok("colour IN [green, '", "dark blue"),
+                    ],
+                },
+            ),
+            (
+                "size IN[12,13]",
+                Fc::In {
+                    fid: rtok("", "size"),
+                    els: vec![rtok("size IN[", "12"), rtok("size IN[12,", "13")],
+                },
+            ),
+            (
+                "colour IN []",
+                Fc::In { fid: rtok("", "colour"), els: vec![] },
+            ),
+            (
+                "NOT colour IN [green]",
+                Fc::NotIn {
+                    fid: rtok("NOT ", "colour"),
+                    els: vec![rtok("NOT colour IN [", "green")],
+                },
+            ),
+            (
+                "price EXISTS",
+                Fc::Exists { fid: rtok("", "price") },
+            ),
+            (
+                "'details.warranty' NOT EXISTS",
+                Fc::NotExists { fid: rtok("'", "details.warranty") },
+            ),
+            (
+                "NOT price EXISTS",
+                Fc::NotExists { fid: rtok("NOT ", "price") },
+            ),
+            (
+                "NOT price NOT EXISTS",
+                Fc::Exists { fid: rtok("NOT ", "price") },
+            ),
+            (
+                "price NOT EXISTS AND colour = green",
+                Fc::And(
+                    Fc::NotExists { fid: rtok("", "price") }.into(),
+                    Fc::Condition {
+                        fid: rtok("price NOT EXISTS AND ", "colour"),
+                        op: Condition::Equal(rtok("price NOT EXISTS AND colour = ", "green")),
+                    }
+                    .into(),
+                ),
+            ),
+            (
+                "images IS EMPTY",
+                Fc::IsEmpty { fid: rtok("", "images") },
+            ),
+            (
+                "images IS NOT EMPTY",
+                Fc::IsNotEmpty { fid: rtok("", "images") },
+            ),
+            (
+                "NOT images IS EMPTY",
+                Fc::IsNotEmpty { fid: rtok("NOT ", "images") },
+            ),
+            (
+                "images IS EMPTY OR images EXISTS",
+                Fc::Or(
+                    Fc::IsEmpty { fid: rtok("", "images") }.into(),
+                    Fc::Exists { fid: rtok("images IS EMPTY OR ", "images") }.into(),
+                ),
+            ),
+            (
+                "name CONTAINS pro",
+                Fc::Contains { fid: rtok("", "name"), value: rtok("name CONTAINS ", "pro") },
+            ),
+            (
+                "NOT name CONTAINS 'pro max'",
+                Fc::NotContains {
+                    fid: rtok("NOT ", "name"),
+                    value: rtok("NOT name CONTAINS '", "pro max"),
+                },
+            ),
             (
                 "_geoRadius(12, 13, 14)",
                 Fc::GeoLowerThan {
@@ -459,6 +690,64 @@ pub mod tests {
                     radius: rtok("NOT _geoRadius(12, 13, ", "14"),
                 },
             ),
+            (
+                "_geoBoundingBox([12, 13], [14, 15])",
+                Fc::GeoBoundingBox {
+                    top_right_point: [
+                        rtok("_geoBoundingBox([", "12"),
+                        rtok("_geoBoundingBox([12, ", "13"),
+                    ],
+                    bottom_left_point: [
+                        rtok("_geoBoundingBox([12, 13], [", "14"),
+                        rtok("_geoBoundingBox([12, 13], [14, ", "15"),
+                    ],
+                },
+            ),
+            (
+                "NOT _geoBoundingBox([12, 13], [14, 15])",
+                Fc::GeoOutsideBoundingBox {
+                    top_right_point: [
+                        rtok("NOT _geoBoundingBox([", "12"),
+                        rtok("NOT _geoBoundingBox([12, ", "13"),
+                    ],
+                    bottom_left_point: [
+                        rtok("NOT _geoBoundingBox([12, 13], [", "14"),
+                        rtok("NOT _geoBoundingBox([12, 13], [14, ", "15"),
+                    ],
+                },
+            ),
+            (
+                "_geoPolygon([[12, 13], [14, 15], [16, 17]])",
+                Fc::GeoPolygon {
+                    points: vec![
+                        [rtok("_geoPolygon([[", "12"), rtok("_geoPolygon([[12, ", "13")],
+                        [
+                            rtok("_geoPolygon([[12, 13], [", "14"),
+                            rtok("_geoPolygon([[12, 13], [14, ", "15"),
+                        ],
+                        [
+                            rtok("_geoPolygon([[12, 13], [14, 15], [", "16"),
+                            rtok("_geoPolygon([[12, 13], [14, 15], [16, ", "17"),
+                        ],
+                    ],
+                },
+            ),
+            (
+                "NOT _geoPolygon([[12, 13], [14, 15], [16, 17]])",
+                Fc::GeoOutsidePolygon {
+                    points: vec![
+                        [rtok("NOT _geoPolygon([[", "12"), rtok("NOT _geoPolygon([[12, ", "13")],
+                        [
+                            rtok("NOT _geoPolygon([[12, 13], [", "14"),
+                            rtok("NOT _geoPolygon([[12, 13], [14, ", "15"),
+                        ],
+                        [
+                            rtok("NOT _geoPolygon([[12, 13], [14, 15], [", "16"),
+                            rtok("NOT _geoPolygon([[12, 13], [14, 15], [16, ", "17"),
+                        ],
+                    ],
+                },
+            ),
             // test simple `or` and `and`
             (
                 "channel = ponce AND 'dog race' != 'bernese mountain'",
@@ -577,15 +866,31 @@ pub mod tests {
             ("channel =    ", "Was expecting a value but instead got nothing."),
             ("channel = 🐻", "Was expecting a value but instead got `🐻`."),
             ("channel = 🐻 AND followers < 100", "Was expecting a value but instead got `🐻`."),
-            ("OR", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO` or `_geoRadius` at `OR`."),
-            ("AND", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO` or `_geoRadius` at `AND`."),
-            ("channel Ponce", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO` or `_geoRadius` at `channel Ponce`."),
-            ("channel = Ponce OR", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO` or `_geoRadius` but instead got nothing."),
+            ("OR", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO`, `IN`, `NOT IN`, `EXISTS`, `NOT EXISTS`, `IS EMPTY`, `IS NOT EMPTY`, `CONTAINS`, `_geoRadius`, `_geoBoundingBox` or `_geoPolygon` at `OR`."),
+            ("AND", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO`, `IN`, `NOT IN`, `EXISTS`, `NOT EXISTS`, `IS EMPTY`, `IS NOT EMPTY`, `CONTAINS`, `_geoRadius`, `_geoBoundingBox` or `_geoPolygon` at `AND`."),
+            ("channel Ponce", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO`, `IN`, `NOT IN`, `EXISTS`, `NOT EXISTS`, `IS EMPTY`, `IS NOT EMPTY`, `CONTAINS`, `_geoRadius`, `_geoBoundingBox` or `_geoPolygon` at `channel Ponce`."),
+            ("channel = Ponce OR", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO`, `IN`, `NOT IN`, `EXISTS`, `NOT EXISTS`, `IS EMPTY`, `IS NOT EMPTY`, `CONTAINS`, `_geoRadius`, `_geoBoundingBox` or `_geoPolygon` but instead got nothing."),
             ("_geoRadius", "The `_geoRadius` filter expects three arguments: `_geoRadius(latitude, longitude, radius)`."),
             ("_geoRadius = 12", "The `_geoRadius` filter expects three arguments: `_geoRadius(latitude, longitude, radius)`."),
             ("_geoPoint(12, 13, 14)", "`_geoPoint` is a reserved keyword and thus can't be used as a filter expression. Use the `_geoRadius(latitude, longitude, distance) built-in rule to filter on `_geo` coordinates."),
             ("position <= _geoPoint(12, 13, 14)", "`_geoPoint` is a reserved keyword and thus can't be used as a filter expression. Use the `_geoRadius(latitude, longitude, distance) built-in rule to filter on `_geo` coordinates."),
             ("position <= _geoRadius(12, 13, 14)", "The `_geoRadius` filter is an operation and can't be used as a value."),
+            ("_geoBoundingBox", "The `_geoBoundingBox` filter expects two pairs of arguments: `_geoBoundingBox([latitude, longitude], [latitude, longitude])`."),
+            ("_geoBoundingBox = 12", "The `_geoBoundingBox` filter expects two pairs of arguments: `_geoBoundingBox([latitude, longitude], [latitude, longitude])`."),
+            ("_geoBoundingBox(12, 13, 14, 15)", "The `_geoBoundingBox` filter expects two pairs of arguments: `_geoBoundingBox([latitude, longitude], [latitude, longitude])`."),
+            ("_geoBoundingBox([12, 13])", "The `_geoBoundingBox` filter expects two pairs of arguments: `_geoBoundingBox([latitude, longitude], [latitude, longitude])`."),
+            ("_geoBoundingBox([12, 13, 14], [15, 16])", "The `_geoBoundingBox` filter expects two pairs of arguments: `_geoBoundingBox([latitude, longitude], [latitude, longitude])`."),
+            ("position <= _geoBoundingBox([12, 13], [14, 15])", "The `_geoBoundingBox` filter is an operation and can't be used as a value."),
+            ("_geoPolygon", "The `_geoPolygon` filter expects a list of at least three points: `_geoPolygon([[latitude, longitude], [latitude, longitude], [latitude, longitude]])`."),
+            ("_geoPolygon([12, 13], [14, 15], [16, 17])", "The `_geoPolygon` filter expects a list of at least three points: `_geoPolygon([[latitude, longitude], [latitude, longitude], [latitude, longitude]])`."),
+            ("_geoPolygon([[12, 13], [14, 15]])", "The `_geoPolygon` filter expects a list of at least three points: `_geoPolygon([[latitude, longitude], [latitude, longitude], [latitude, longitude]])`."),
+            ("_geoPolygon([[12, 13], [14, 15, 16], [17, 18]])", "The `_geoPolygon` filter expects a list of at least three points: `_geoPolygon([[latitude, longitude], [latitude, longitude], [latitude, longitude]])`."),
+            ("position <= _geoPolygon([[12, 13], [14, 15], [16, 17]])", "The `_geoPolygon` filter is an operation and can't be used as a value."),
+            ("colour IN green", "The `IN` operator expects a list of values: `field IN [value, value]`."),
+            ("colour IN [green, blue", "The `IN` operator expects a list of values: `field IN [value, value]`."),
+            ("colour IN [green,]", "The `IN` operator expects a list of values: `field IN [value, value]`."),
+            ("colour INDIGO", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO`, `IN`, `NOT IN`, `EXISTS`, `NOT EXISTS`, `IS EMPTY`, `IS NOT EMPTY`, `CONTAINS`, `_geoRadius`, `_geoBoundingBox` or `_geoPolygon` at `colour INDIGO`."),
+            ("name CONTAINSpro", "Was expecting an operation `=`, `!=`, `>=`, `>`, `<=`, `<`, `TO`, `IN`, `NOT IN`, `EXISTS`, `NOT EXISTS`, `IS EMPTY`, `IS NOT EMPTY`, `CONTAINS`, `_geoRadius`, `_geoBoundingBox` or `_geoPolygon` at `name CONTAINSpro`."),
             ("channel = 'ponce", "Expression `\\'ponce` is missing the following closing delimiter: `'`."),
             ("channel = \"ponce", "Expression `\\\"ponce` is missing the following closing delimiter: `\"`."),
             ("channel = mv OR (followers >= 1000", "Expression `(followers >= 1000` is missing the following closing delimiter: `)`."),
@@ -611,4 +916,16 @@ pub mod tests {
         let filter = FilterCondition::parse("account_ids=1 OR account_ids=2 OR account_ids=3 OR account_ids=4 OR account_ids=5 OR account_ids=6").unwrap().unwrap();
         assert!(filter.token_at_depth(5).is_some());
     }
+
+    #[test]
+    fn quoted_values() {
+        let values = |input| match FilterCondition::parse(input).unwrap().unwrap() {
+            FilterCondition::Condition { op: Condition::Equal(value), .. } => value,
+            condition => panic!("unexpected condition {:?}", condition),
+        };
+        assert!(!values("available = true").is_quoted());
+        assert!(values("available = \"true\"").is_quoted());
+        assert!(values("available = 'true'").is_quoted());
+        assert!(!values("'available' = true").is_quoted());
+    }
 }
@@ -156,7 +156,8 @@ impl<'s, A: AsRef<[u8]>> Highlighter<'s, A> {
             Value::String(old_string) => {
                 let mut matcher = self.matcher_builder.build(&old_string);
 
-                let format_options = FormatOptions { highlight: true, crop: Some(10) };
+                let format_options =
+                    FormatOptions { highlight: true, crop: Some(10), ..Default::default() };
 
                 Value::String(matcher.format(format_options).to_string())
             }
@@ -346,6 +347,7 @@ async fn main() -> anyhow::Result<()> {
                     let update_method = match method.as_str() {
                         "replace" => IndexDocumentsMethod::ReplaceDocuments,
                         "update" => IndexDocumentsMethod::UpdateDocuments,
+                        "update-merge-arrays" => IndexDocumentsMethod::UpdateDocumentsMergeArrays,
                         otherwise => panic!("invalid indexing method {:?}", otherwise),
                     };
                     let indexing_config = IndexDocumentsConfig {
@@ -769,7 +771,7 @@ async fn main() -> anyhow::Result<()> {
                 search.sort_criteria(vec![sort.parse().map_err(SortError::from).unwrap()]);
             }
 
-            let SearchResult { matching_words, candidates, documents_ids } =
+            let SearchResult { matching_words, candidates, documents_ids, .. } =
                 search.execute().unwrap();
 
             let number_of_candidates = candidates.len();
@@ -794,6 +796,7 @@ async fn main() -> anyhow::Result<()> {
                 MatcherBuilder::new(matching_words, TokenizerBuilder::default().build());
             matcher_builder.highlight_prefix("<mark>".to_string());
             matcher_builder.highlight_suffix("</mark>".to_string());
+            matcher_builder.index_segmentation(&rtxn, &index).unwrap();
             let highlighter = Highlighter::new(matcher_builder);
             for (_id, obkv) in index.documents(&rtxn, documents_ids).unwrap() {
                 let mut object = obkv_to_json(&displayed_fields, &fields_ids_map, obkv).unwrap();
@@ -869,6 +872,7 @@ async fn main() -> anyhow::Result<()> {
         let method = match update_method.as_deref() {
             Some("replace") => String::from("replace"),
             Some("update") => String::from("update"),
+            Some("update-merge-arrays") => String::from("update-merge-arrays"),
             _ => String::from("replace"),
         };
 
@@ -31,6 +31,7 @@ const ALL_DATABASE_NAMES: &[&str] = &[
     FIELD_ID_DOCID_FACET_STRINGS,
     EXACT_WORD_DOCIDS,
     EXACT_WORD_PREFIX_DOCIDS,
+    STOP_WORD_DOCIDS,
     DOCUMENTS,
 ];
 
@@ -986,6 +987,7 @@ fn size_of_databases(index: &Index, rtxn: &heed::RoTxn, names: Vec<String>) -> a
         field_id_docid_facet_strings,
         exact_word_prefix_docids,
         exact_word_docids,
+        stop_word_docids,
         ..
     } = index;
 
@@ -1011,6 +1013,7 @@ fn size_of_databases(index: &Index, rtxn: &heed::RoTxn, names: Vec<String>) -> a
             FIELD_ID_DOCID_FACET_STRINGS => field_id_docid_facet_strings.as_polymorph(),
             EXACT_WORD_DOCIDS => exact_word_docids.as_polymorph(),
             EXACT_WORD_PREFIX_DOCIDS => exact_word_prefix_docids.as_polymorph(),
+            STOP_WORD_DOCIDS => stop_word_docids.as_polymorph(),
 
             unknown => anyhow::bail!("unknown database {:?}", unknown),
         };
@@ -55,6 +55,12 @@ impl From<AscDescError> for CriterionError {
             AscDescError::ReservedKeyword { name } if name.starts_with("_geoRadius") => {
                 CriterionError::ReservedNameForFilter { name: "_geoRadius".to_string() }
             }
+            AscDescError::ReservedKeyword { name } if name.starts_with("_geoBoundingBox") => {
+                CriterionError::ReservedNameForFilter { name: "_geoBoundingBox".to_string() }
+            }
+            AscDescError::ReservedKeyword { name } if name.starts_with("_geoPolygon") => {
+                CriterionError::ReservedNameForFilter { name: "_geoPolygon".to_string() }
+            }
             AscDescError::ReservedKeyword { name } => CriterionError::ReservedName { name },
         }
     }
@@ -64,6 +70,8 @@ impl From<AscDescError> for CriterionError {
 pub enum Member {
     Field(String),
     Geo([f64; 2]),
+    /// Sort by the distance to the closest of these points.
+    GeoPoints(Vec<[f64; 2]>),
 }
 
 impl FromStr for Member {
@@ -71,6 +79,18 @@ impl FromStr for Member {
 
     fn from_str(text: &str) -> Result<Member, Self::Err> {
         match text.strip_prefix("_geoPoint(").and_then(|text| text.strip_suffix(")")) {
+            Some(points) if points.trim_start().starts_with('[') => {
+                let mut points = parse_geo_points(points)
+                    .ok_or_else(|| AscDescError::ReservedKeyword { name: text.to_string() })?;
+                for &[lat, lng] in &points {
+                    validate_lat_lng(lat, lng)?;
+                }
+                if points.len() == 1 {
+                    Ok(Member::Geo(points.remove(0)))
+                } else {
+                    Ok(Member::GeoPoints(points))
+                }
+            }
             Some(point) => {
                 let (lat, lng) = point
                     .split_once(',')
@@ -81,15 +101,15 @@ impl FromStr for Member {
                             .and_then(|lat| lng.trim().parse().map(|lng| (lat, lng)))
                             .map_err(|_| AscDescError::ReservedKeyword { name: text.to_string() })
                     })?;
-                if !(-90.0..=90.0).contains(&lat) {
-                    return Err(AscDescError::InvalidLatitude)?;
-                } else if !(-180.0..=180.0).contains(&lng) {
-                    return Err(AscDescError::InvalidLongitude)?;
-                }
+                validate_lat_lng(lat, lng)?;
                 Ok(Member::Geo([lat, lng]))
             }
             None => {
-                if is_reserved_keyword(text) || text.starts_with("_geoRadius(") {
+                if is_reserved_keyword(text)
+                    || text.starts_with("_geoRadius(")
+                    || text.starts_with("_geoBoundingBox(")
+                    || text.starts_with("_geoPolygon(")
+                {
                     return Err(AscDescError::ReservedKeyword { name: text.to_string() })?;
                 }
                 Ok(Member::Field(text.to_string()))
@@ -98,11 +118,48 @@ impl FromStr for Member {
     }
 }
 
+/// Parses a list of points formatted like `[lat, lng], [lat, lng]`.
+fn parse_geo_points(text: &str) -> Option<Vec<[f64; 2]>> {
+    let mut points = Vec::new();
+    let mut text = text.trim();
+    loop {
+        let (point, remaining) = text.strip_prefix('[')?.split_once(']')?;
+        let (lat, lng) = point.split_once(',')?;
+        points.push([lat.trim().parse().ok()?, lng.trim().parse().ok()?]);
+
+        text = remaining.trim_start();
+        if text.is_empty() {
+            return Some(points);
+        }
+        text = text.strip_prefix(',')?.trim_start();
+    }
+}
+
+fn validate_lat_lng(lat: f64, lng: f64) -> Result<(), AscDescError> {
+    if !(-90.0..=90.0).contains(&lat) {
+        Err(AscDescError::InvalidLatitude)
+    } else if !(-180.0..=180.0).contains(&lng) {
+        Err(AscDescError::InvalidLongitude)
+    } else {
+        Ok(())
+    }
+}
+
 impl fmt::Display for Member {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Member::Field(name) => f.write_str(name),
             Member::Geo([lat, lng]) => write!(f, "_geoPoint({}, {})", lat, lng),
+            Member::GeoPoints(points) => {
+                f.write_str("_geoPoint(")?;
+                for (i, [lat, lng]) in points.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "[{}, {}]", lat, lng)?;
+                }
+                f.write_str(")")
+            }
         }
     }
 }
@@ -111,13 +168,22 @@ impl Member {
     pub fn field(&self) -> Option<&str> {
         match self {
             Member::Field(field) => Some(field),
-            Member::Geo(_) => None,
+            Member::Geo(_) | Member::GeoPoints(_) => None,
         }
     }
 
     pub fn geo_point(&self) -> Option<&[f64; 2]> {
         match self {
             Member::Geo(point) => Some(point),
+            Member::Field(_) | Member::GeoPoints(_) => None,
+        }
+    }
+
+    /// Returns the points to sort by distance from, it can be one or multiple points.
+    pub fn geo_points(&self) -> Option<&[[f64; 2]]> {
+        match self {
+            Member::Geo(point) => Some(std::slice::from_ref(point)),
+            Member::GeoPoints(points) => Some(points),
             Member::Field(_) => None,
         }
     }
@@ -142,6 +208,21 @@ impl AscDesc {
     }
 }
 
+/// Where the documents without any value for the sorted field are placed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MissingValues {
+    /// Before the documents having a value, whatever the direction of the sort.
+    First,
+    /// After the documents having a value, whatever the direction of the sort.
+    Last,
+}
+
+impl Default for MissingValues {
+    fn default() -> Self {
+        Self::Last
+    }
+}
+
 impl FromStr for AscDesc {
     type Err = AscDescError;
 
@@ -190,6 +271,12 @@ impl From<AscDescError> for SortError {
             AscDescError::ReservedKeyword { name } if name.starts_with("_geoRadius") => {
                 SortError::ReservedNameForFilter { name: String::from("_geoRadius") }
             }
+            AscDescError::ReservedKeyword { name } if name.starts_with("_geoBoundingBox") => {
+                SortError::ReservedNameForFilter { name: String::from("_geoBoundingBox") }
+            }
+            AscDescError::ReservedKeyword { name } if name.starts_with("_geoPolygon") => {
+                SortError::ReservedNameForFilter { name: String::from("_geoPolygon") }
+            }
             AscDescError::ReservedKeyword { name } => SortError::ReservedName { name },
         }
     }
@@ -227,6 +314,15 @@ mod tests {
             ("_geoPoint(-90, -180.0000000000):asc", Asc(Geo([-90., -180.]))),
             ("_geoPoint(42.0002, 59.895):desc", Desc(Geo([42.0002, 59.895]))),
             ("_geoPoint(42., 59.):desc", Desc(Geo([42., 59.]))),
+            ("_geoPoint([42, 59]):asc", Asc(Geo([42., 59.]))),
+            (
+                "_geoPoint([42, 59], [-12.5, 13]):asc",
+                Asc(GeoPoints(vec![[42., 59.], [-12.5, 13.]])),
+            ),
+            (
+                "_geoPoint( [42,59] ,[1, 2], [3, 4] ):desc",
+                Desc(GeoPoints(vec![[42., 59.], [1., 2.], [3., 4.]])),
+            ),
             ("truc(12, 13):desc", Desc(Field(S("truc(12, 13)")))),
         ];
 
@@ -273,6 +369,17 @@ mod tests {
             ("_geoPoint(0, -180.000001):desc", InvalidLongitude),
             ("_geoPoint(159.256, 130):asc", InvalidLatitude),
             ("_geoPoint(12, -2021):desc", InvalidLongitude),
+            (
+                "_geoPoint([42, 59], [12]):asc",
+                ReservedKeyword { name: S("_geoPoint([42, 59], [12])") },
+            ),
+            (
+                "_geoPoint([42, 59] [12, 13]):asc",
+                ReservedKeyword { name: S("_geoPoint([42, 59] [12, 13])") },
+            ),
+            ("_geoPoint([42, 59], ):asc", ReservedKeyword { name: S("_geoPoint([42, 59], )") }),
+            ("_geoPoint([42, 59], [200, 13]):asc", InvalidLatitude),
+            ("_geoPoint([42, 200], [12, 13]):desc", InvalidLongitude),
         ];
 
         for (req, expected_error) in invalid_req {
@@ -8,7 +8,7 @@ use crate::{AscDesc, Member};
 
 #[derive(Error, Debug)]
 pub enum CriterionError {
-    #[error("`{name}` ranking rule is invalid. Valid ranking rules are words, typo, sort, proximity, attribute, exactness and custom ranking rules.")]
+    #[error("`{name}` ranking rule is invalid. Valid ranking rules are words, typo, sort, proximity, attribute, exactness, wordCount, score, prefixCompletion and custom ranking rules.")]
     InvalidName { name: String },
     #[error("`{name}` is a reserved keyword and thus can't be used as a ranking rule")]
     ReservedName { name: String },
@@ -41,6 +41,14 @@ pub enum Criterion {
     Sort,
     /// Sorted by the similarity of the matched words with the query words.
     Exactness,
+    /// Sorted by increasing number of words of the shortest attribute matching the query,
+    /// only the attributes of at most 10 words are counted. Not part of the default criteria.
+    WordCount,
+    /// Sorted by decreasing BM25 score of the matched words. Not part of the default criteria.
+    Score,
+    /// Sorted by increasing number of characters added by the matched words to the prefix
+    /// words of the query, e.g. `app` then `apple`. Not part of the default criteria.
+    PrefixCompletion,
     /// Sorted by the increasing value of the field specified.
     Asc(String),
     /// Sorted by the decreasing value of the field specified.
@@ -68,10 +76,14 @@ impl FromStr for Criterion {
             "attribute" => Ok(Criterion::Attribute),
             "sort" => Ok(Criterion::Sort),
             "exactness" => Ok(Criterion::Exactness),
+            "wordCount" => Ok(Criterion::WordCount),
+            "score" => Ok(Criterion::Score),
+            "prefixCompletion" => Ok(Criterion::PrefixCompletion),
             text => match AscDesc::from_str(text)? {
                 AscDesc::Asc(Member::Field(field)) => Ok(Criterion::Asc(field)),
                 AscDesc::Desc(Member::Field(field)) => Ok(Criterion::Desc(field)),
-                AscDesc::Asc(Member::Geo(_)) | AscDesc::Desc(Member::Geo(_)) => {
+                AscDesc::Asc(Member::Geo(_) | Member::GeoPoints(_))
+                | AscDesc::Desc(Member::Geo(_) | Member::GeoPoints(_)) => {
                     Err(CriterionError::ReservedNameForSort { name: "_geoPoint".to_string() })?
                 }
             },
@@ -101,6 +113,9 @@ impl fmt::Display for Criterion {
             Attribute => f.write_str("attribute"),
             Sort => f.write_str("sort"),
             Exactness => f.write_str("exactness"),
+            WordCount => f.write_str("wordCount"),
+            Score => f.write_str("score"),
+            PrefixCompletion => f.write_str("prefixCompletion"),
             Asc(attr) => write!(f, "{}:asc", attr),
             Desc(attr) => write!(f, "{}:desc", attr),
         }
@@ -123,6 +138,9 @@ mod tests {
             ("attribute", Criterion::Attribute),
             ("sort", Criterion::Sort),
             ("exactness", Criterion::Exactness),
+            ("wordCount", Criterion::WordCount),
+            ("score", Criterion::Score),
+            ("prefixCompletion", Criterion::PrefixCompletion),
             ("price:asc", Criterion::Asc(S("price"))),
             ("price:desc", Criterion::Desc(S("price"))),
             ("price:asc:desc", Criterion::Desc(S("price:asc"))),
@@ -159,6 +177,12 @@ mod tests {
             ("_geoPoint(42, 75):asc", ReservedNameForSort { name: S("_geoPoint") }),
             ("_geoRadius:asc", ReservedNameForFilter { name: S("_geoRadius") }),
             ("_geoRadius(42, 75, 59):asc", ReservedNameForFilter { name: S("_geoRadius") }),
+            ("_geoBoundingBox:asc", ReservedNameForFilter { name: S("_geoBoundingBox") }),
+            (
+                "_geoBoundingBox([42, 75], [59, 12]):asc",
+                ReservedNameForFilter { name: S("_geoBoundingBox") },
+            ),
+            ("_geoPolygon:asc", ReservedNameForFilter { name: S("_geoPolygon") }),
         ];
 
         for (input, expected) in invalid_criteria {
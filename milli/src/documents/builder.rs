@@ -1,9 +1,10 @@
 use std::collections::BTreeMap;
 use std::io;
-use std::io::{Cursor, Write};
+use std::io::{BufRead, Cursor, Write};
 
 use byteorder::{BigEndian, WriteBytesExt};
-use serde::Deserializer;
+use serde::{Deserialize, Deserializer};
+use serde_json::value::RawValue;
 use serde_json::Value;
 
 use super::serde_impl::DocumentVisitor;
@@ -77,8 +78,29 @@ impl<W: io::Write + io::Seek> DocumentBatchBuilder<W> {
         Ok(count)
     }
 
-    /// Extends the builder with json documents from a reader.
+    /// Extends the builder with json documents from a reader. The bytes of each document are
+    /// kept in the batch along with it.
     pub fn extend_from_json<R: io::Read>(&mut self, reader: R) -> Result<(), Error> {
+        let mut reader = io::BufReader::new(reader);
+        // the elements of a sequence are read one at a time, a single document is read at once.
+        let is_sequence = loop {
+            let buffer = reader.fill_buf()?;
+            if buffer.is_empty() {
+                break false;
+            }
+            match buffer.iter().position(|byte| !byte.is_ascii_whitespace()) {
+                Some(position) => {
+                    let is_sequence = buffer[position] == b'[';
+                    reader.consume(position);
+                    break is_sequence;
+                }
+                None => {
+                    let len = buffer.len();
+                    reader.consume(len);
+                }
+            }
+        };
+
         let mut de = serde_json::Deserializer::from_reader(reader);
 
         let mut visitor = DocumentVisitor {
@@ -90,7 +112,45 @@ impl<W: io::Write + io::Seek> DocumentBatchBuilder<W> {
             count: &mut self.count,
         };
 
-        de.deserialize_any(&mut visitor).map_err(Error::JsonError)?
+        if is_sequence {
+            de.deserialize_seq(&mut visitor).map_err(Error::JsonError)?
+        } else {
+            let document = Box::<RawValue>::deserialize(&mut de).map_err(Error::JsonError)?;
+            visitor.write_document(document.get()).map_err(Error::JsonError)?
+        }
+    }
+
+    /// Extends the builder with newline-delimited json documents from a reader, each non-empty
+    /// line must contain one json object. The lines are read and written one at a time.
+    pub fn extend_from_ndjson<R: io::BufRead>(&mut self, mut reader: R) -> Result<(), Error> {
+        let mut line = String::new();
+        let mut line_number = 0;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            line_number += 1;
+            let document = line.trim();
+            if document.is_empty() {
+                continue;
+            }
+
+            // the line is fully parsed before being written, a malformed line leaves
+            // nothing in the batch.
+            let mut visitor = DocumentVisitor {
+                inner: &mut self.inner,
+                index: &mut self.index,
+                obkv_buffer: &mut self.obkv_buffer,
+                value_buffer: &mut self.value_buffer,
+                values: &mut self.values,
+                count: &mut self.count,
+            };
+            match visitor.write_document(document) {
+                Ok(result) => result?,
+                Err(error) => return Err(Error::NdjsonError { error, line: line_number }),
+            }
+        }
     }
 
     /// Creates a builder from a reader of CSV documents.
@@ -131,6 +191,31 @@ impl<W: io::Write + io::Seek> DocumentBatchBuilder<W> {
                             })?
                         }
                     }
+                    AllowedType::Boolean => {
+                        if value.trim().is_empty() {
+                            Value::Null
+                        } else {
+                            value.trim().parse::<bool>().map(Value::from).map_err(|error| {
+                                Error::ParseBool {
+                                    error,
+                                    // +1 for the header offset.
+                                    line: i + 1,
+                                    value: value.to_string(),
+                                }
+                            })?
+                        }
+                    }
+                    AllowedType::StringArray => {
+                        if value.trim().is_empty() {
+                            Value::Null
+                        } else {
+                            let values = value
+                                .split(CSV_ARRAY_SEPARATOR)
+                                .map(|value| Value::String(value.trim().to_string()))
+                                .collect();
+                            Value::Array(values)
+                        }
+                    }
                     AllowedType::String => {
                         if value.is_empty() {
                             Value::Null
@@ -147,6 +232,8 @@ impl<W: io::Write + io::Seek> DocumentBatchBuilder<W> {
 
             this.inner.write_u32::<BigEndian>(this.obkv_buffer.len() as u32)?;
             this.inner.write_all(&this.obkv_buffer)?;
+            // the csv records are not json, they are written without their bytes.
+            this.inner.write_u32::<BigEndian>(0)?;
 
             this.count += 1;
         }
@@ -155,10 +242,15 @@ impl<W: io::Write + io::Seek> DocumentBatchBuilder<W> {
     }
 }
 
+/// The separator of the values of the `string[]` columns.
+const CSV_ARRAY_SEPARATOR: char = ',';
+
 #[derive(Debug)]
 enum AllowedType {
     String,
     Number,
+    Boolean,
+    StringArray,
 }
 
 fn parse_csv_header(header: &str) -> (String, AllowedType) {
@@ -167,6 +259,8 @@ fn parse_csv_header(header: &str) -> (String, AllowedType) {
         Some((field_name, field_type)) => match field_type {
             "string" => (field_name.to_string(), AllowedType::String),
             "number" => (field_name.to_string(), AllowedType::Number),
+            "boolean" => (field_name.to_string(), AllowedType::Boolean),
+            "string[]" => (field_name.to_string(), AllowedType::StringArray),
             // if the pattern isn't reconized, we keep the whole field.
             _otherwise => (header.to_string(), AllowedType::String),
         },
@@ -271,6 +365,76 @@ mod test {
         assert!(reader.next_document_with_index().unwrap().is_none());
     }
 
+    #[test]
+    fn add_documents_ndjson() {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut builder = DocumentBatchBuilder::new(&mut cursor).unwrap();
+
+        let ndjson = r#"{ "id": 1, "field": "hello!" }
+{ "blabla": false, "field": "hello!", "id": 2 }
+{ "id": 3 }"#;
+
+        builder.extend_from_ndjson(ndjson.as_bytes()).unwrap();
+
+        assert_eq!(builder.len(), 3);
+
+        builder.finish().unwrap();
+
+        cursor.set_position(0);
+
+        let mut reader = DocumentBatchReader::from_reader(cursor).unwrap();
+
+        let (index, document) = reader.next_document_with_index().unwrap().unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(document.iter().count(), 2);
+        assert_eq!(obkv_to_value(&document, index), json!({ "id": 1, "field": "hello!" }));
+
+        let (_index, document) = reader.next_document_with_index().unwrap().unwrap();
+        assert_eq!(document.iter().count(), 3);
+
+        let (_index, document) = reader.next_document_with_index().unwrap().unwrap();
+        assert_eq!(document.iter().count(), 1);
+
+        assert!(reader.next_document_with_index().unwrap().is_none());
+    }
+
+    #[test]
+    fn ndjson_with_blank_lines() {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut builder = DocumentBatchBuilder::new(&mut cursor).unwrap();
+
+        let ndjson = "\n{ \"id\": 1 }\n   \n\r\n{ \"id\": 2 }\n\n";
+
+        builder.extend_from_ndjson(ndjson.as_bytes()).unwrap();
+
+        assert_eq!(builder.len(), 2);
+    }
+
+    #[test]
+    fn malformed_ndjson_line() {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut builder = DocumentBatchBuilder::new(&mut cursor).unwrap();
+
+        let ndjson = r#"{ "id": 1 }
+
+{ "id": 2 "field": "missing comma" }
+{ "id": 3 }"#;
+
+        let error = builder.extend_from_ndjson(ndjson.as_bytes()).unwrap_err();
+        assert!(matches!(error, Error::NdjsonError { line: 3, .. }));
+
+        // a line must contain a single object.
+        let error =
+            builder.extend_from_ndjson(r#"{ "id": 4 } { "id": 5 }"#.as_bytes()).unwrap_err();
+        assert!(matches!(error, Error::NdjsonError { line: 1, .. }));
+
+        let error = builder.extend_from_ndjson(r#"[{ "id": 6 }]"#.as_bytes()).unwrap_err();
+        assert!(matches!(error, Error::NdjsonError { line: 1, .. }));
+
+        // the documents of the lines before the malformed one are kept.
+        assert_eq!(builder.len(), 1);
+    }
+
     #[test]
     fn add_documents_csv() {
         let mut cursor = Cursor::new(Vec::new());
@@ -321,6 +485,76 @@ mod test {
         assert!(reader.next_document_with_index().unwrap().is_none());
     }
 
+    #[test]
+    fn boolean_in_field() {
+        let documents = r#"city,country,capital:boolean
+"Boston","United States","false"
+"Paris","France"," true "
+"Nowhere","Nowhere","#;
+
+        let mut buf = Vec::new();
+        DocumentBatchBuilder::from_csv(documents.as_bytes(), Cursor::new(&mut buf))
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut reader = DocumentBatchReader::from_reader(Cursor::new(buf)).unwrap();
+        let (index, doc) = reader.next_document_with_index().unwrap().unwrap();
+        let val = obkv_to_value(&doc, index);
+        assert_eq!(val, json!({ "city": "Boston", "country": "United States", "capital": false }));
+
+        let (index, doc) = reader.next_document_with_index().unwrap().unwrap();
+        let val = obkv_to_value(&doc, index);
+        assert_eq!(val, json!({ "city": "Paris", "country": "France", "capital": true }));
+
+        let (index, doc) = reader.next_document_with_index().unwrap().unwrap();
+        let val = obkv_to_value(&doc, index);
+        assert_eq!(val, json!({ "city": "Nowhere", "country": "Nowhere", "capital": null }));
+    }
+
+    #[test]
+    fn string_array_in_field() {
+        let documents = r#"city,tags:string[]
+"Boston","harbor, university,sports"
+"Paris","capital"
+"Nowhere","#;
+
+        let mut buf = Vec::new();
+        DocumentBatchBuilder::from_csv(documents.as_bytes(), Cursor::new(&mut buf))
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut reader = DocumentBatchReader::from_reader(Cursor::new(buf)).unwrap();
+        let (index, doc) = reader.next_document_with_index().unwrap().unwrap();
+        let val = obkv_to_value(&doc, index);
+        assert_eq!(val, json!({ "city": "Boston", "tags": ["harbor", "university", "sports"] }));
+
+        let (index, doc) = reader.next_document_with_index().unwrap().unwrap();
+        let val = obkv_to_value(&doc, index);
+        assert_eq!(val, json!({ "city": "Paris", "tags": ["capital"] }));
+
+        let (index, doc) = reader.next_document_with_index().unwrap().unwrap();
+        let val = obkv_to_value(&doc, index);
+        assert_eq!(val, json!({ "city": "Nowhere", "tags": null }));
+    }
+
+    #[test]
+    fn bad_typed_values() {
+        let documents = r#"city,pop:number
+"Boston","4628910"
+"Paris","a lot""#;
+
+        let mut buf = Vec::new();
+        let error = DocumentBatchBuilder::from_csv(documents.as_bytes(), Cursor::new(&mut buf));
+        assert!(matches!(error, Err(Error::ParseFloat { line: 2, .. })));
+
+        let documents = r#"city,capital:boolean
+"Boston","no""#;
+
+        let mut buf = Vec::new();
+        let error = DocumentBatchBuilder::from_csv(documents.as_bytes(), Cursor::new(&mut buf));
+        assert!(matches!(error, Err(Error::ParseBool { line: 1, .. })));
+    }
+
     #[test]
     fn coma_in_field() {
         let documents = r#"city,country,pop
@@ -101,9 +101,11 @@ impl<W: io::Write> io::Write for ByteCounter<W> {
 #[derive(Debug)]
 pub enum Error {
     ParseFloat { error: std::num::ParseFloatError, line: usize, value: String },
+    ParseBool { error: std::str::ParseBoolError, line: usize, value: String },
     InvalidDocumentFormat,
     Custom(String),
     JsonError(serde_json::Error),
+    NdjsonError { error: serde_json::Error, line: usize },
     CsvError(csv::Error),
     Serialize(bincode::Error),
     Io(io::Error),
@@ -140,9 +142,15 @@ impl fmt::Display for Error {
             Error::ParseFloat { error, line, value } => {
                 write!(f, "Error parsing number {:?} at line {}: {}", value, line, error)
             }
+            Error::ParseBool { error, line, value } => {
+                write!(f, "Error parsing boolean {:?} at line {}: {}", value, line, error)
+            }
             Error::Custom(s) => write!(f, "Unexpected serialization error: {}", s),
             Error::InvalidDocumentFormat => f.write_str("Invalid document addition format."),
             Error::JsonError(err) => write!(f, "Couldn't serialize document value: {}", err),
+            Error::NdjsonError { error, line } => {
+                write!(f, "Couldn't parse the document at line {}: {}", line, error)
+            }
             Error::Io(e) => write!(f, "{}", e),
             Error::DocumentTooLarge => f.write_str("Provided document is too large (>2Gib)"),
             Error::Serialize(e) => write!(f, "{}", e),
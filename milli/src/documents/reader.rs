@@ -17,6 +17,7 @@ pub struct DocumentBatchReader<R> {
     reader: BufReader<R>,
     metadata: DocumentsMetadata,
     buffer: Vec<u8>,
+    raw_buffer: Vec<u8>,
     seen_documents: usize,
 }
 
@@ -38,7 +39,7 @@ impl<R: io::Read + io::Seek> DocumentBatchReader<R> {
 
         let reader = BufReader::new(reader);
 
-        Ok(Self { reader, metadata, buffer, seen_documents: 0 })
+        Ok(Self { reader, metadata, buffer, raw_buffer: Vec::new(), seen_documents: 0 })
     }
 
     /// Returns the next document in the reader, and wraps it in an `obkv::KvReader`, along with a
@@ -46,14 +47,27 @@ impl<R: io::Read + io::Seek> DocumentBatchReader<R> {
     pub fn next_document_with_index<'a>(
         &'a mut self,
     ) -> io::Result<Option<(&'a DocumentsBatchIndex, KvReader<'a, FieldId>)>> {
+        Ok(self.next_document_with_raw()?.map(|(index, document, _raw)| (index, document)))
+    }
+
+    /// Returns the next document in the reader like `next_document_with_index`, along with the
+    /// json bytes it was parsed from. There are no bytes for the documents read from a CSV.
+    pub fn next_document_with_raw<'a>(
+        &'a mut self,
+    ) -> io::Result<Option<(&'a DocumentsBatchIndex, KvReader<'a, FieldId>, Option<&'a [u8]>)>>
+    {
         if self.seen_documents < self.metadata.count {
             let doc_len = self.reader.read_u32::<BigEndian>()?;
             self.buffer.resize(doc_len as usize, 0);
             self.reader.read_exact(&mut self.buffer)?;
+            let raw_len = self.reader.read_u32::<BigEndian>()?;
+            self.raw_buffer.resize(raw_len as usize, 0);
+            self.reader.read_exact(&mut self.raw_buffer)?;
             self.seen_documents += 1;
 
             let reader = KvReader::new(&self.buffer);
-            Ok(Some((&self.metadata.index, reader)))
+            let raw = if self.raw_buffer.is_empty() { None } else { Some(&self.raw_buffer[..]) };
+            Ok(Some((&self.metadata.index, reader, raw)))
         } else {
             Ok(None)
         }
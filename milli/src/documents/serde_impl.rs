@@ -4,7 +4,8 @@ use std::io::{Cursor, Write};
 
 use byteorder::WriteBytesExt;
 use serde::de::{DeserializeSeed, MapAccess, SeqAccess, Visitor};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use serde_json::value::RawValue;
 use serde_json::Value;
 
 use super::{ByteCounter, DocumentsBatchIndex, Error};
@@ -69,6 +70,38 @@ pub struct DocumentVisitor<'a, W> {
     pub count: &'a mut usize,
 }
 
+impl<'a, W: Write> DocumentVisitor<'a, W> {
+    /// Parses a json object and writes it in the batch, followed by the bytes it was parsed
+    /// from. Nothing is written if the object is malformed.
+    pub fn write_document(&mut self, raw: &str) -> Result<Result<(), Error>, serde_json::Error> {
+        self.values.clear();
+        let mut de = serde_json::Deserializer::from_str(raw);
+        tri!(de.deserialize_map(&mut *self)?);
+        de.end()?;
+
+        self.obkv_buffer.clear();
+        let mut obkv = obkv::KvWriter::new(Cursor::new(&mut *self.obkv_buffer));
+        for (key, value) in self.values.iter() {
+            self.value_buffer.clear();
+            // This is guaranteed to work
+            tri!(serde_json::to_writer(Cursor::new(&mut *self.value_buffer), value));
+            tri!(obkv.insert(*key, &self.value_buffer));
+        }
+
+        let reader = tri!(obkv.into_inner()).into_inner();
+
+        tri!(self.inner.write_u32::<byteorder::BigEndian>(reader.len() as u32));
+        tri!(self.inner.write_all(reader));
+        tri!(self.inner.write_u32::<byteorder::BigEndian>(raw.len() as u32));
+        tri!(self.inner.write_all(raw.as_bytes()));
+
+        *self.count += 1;
+        self.values.clear();
+
+        Ok(Ok(()))
+    }
+}
+
 impl<'a, 'de, W: Write> Visitor<'de> for &mut DocumentVisitor<'a, W> {
     /// This Visitor value is nothing, since it write the value to a file.
     type Value = Result<(), Error>;
@@ -77,8 +110,9 @@ impl<'a, 'de, W: Write> Visitor<'de> for &mut DocumentVisitor<'a, W> {
     where
         A: SeqAccess<'de>,
     {
-        while let Some(v) = seq.next_element_seed(&mut *self)? {
-            tri!(v)
+        // the documents are kept as they are in the sequence, to be parsed again one by one.
+        while let Some(raw) = seq.next_element::<Box<RawValue>>()? {
+            tri!(tri!(self.write_document(raw.get())))
         }
 
         Ok(Ok(()))
@@ -94,23 +128,6 @@ impl<'a, 'de, W: Write> Visitor<'de> for &mut DocumentVisitor<'a, W> {
             self.values.insert(key, value);
         }
 
-        self.obkv_buffer.clear();
-        let mut obkv = obkv::KvWriter::new(Cursor::new(&mut *self.obkv_buffer));
-        for (key, value) in self.values.iter() {
-            self.value_buffer.clear();
-            // This is guaranteed to work
-            tri!(serde_json::to_writer(Cursor::new(&mut *self.value_buffer), value));
-            tri!(obkv.insert(*key, &self.value_buffer));
-        }
-
-        let reader = tri!(obkv.into_inner()).into_inner();
-
-        tri!(self.inner.write_u32::<byteorder::BigEndian>(reader.len() as u32));
-        tri!(self.inner.write_all(reader));
-
-        *self.count += 1;
-        self.values.clear();
-
         Ok(Ok(()))
     }
 
@@ -118,17 +135,3 @@ impl<'a, 'de, W: Write> Visitor<'de> for &mut DocumentVisitor<'a, W> {
         write!(f, "a documents, or a sequence of documents.")
     }
 }
-
-impl<'a, 'de, W> DeserializeSeed<'de> for &mut DocumentVisitor<'a, W>
-where
-    W: Write,
-{
-    type Value = Result<(), Error>;
-
-    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        deserializer.deserialize_map(self)
-    }
-}
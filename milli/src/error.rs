@@ -12,7 +12,8 @@ use crate::{CriterionError, DocumentId, FieldId, SortError};
 pub type Object = Map<String, Value>;
 
 pub fn is_reserved_keyword(keyword: &str) -> bool {
-    ["_geo", "_geoDistance", "_geoPoint", "_geoRadius"].contains(&keyword)
+    ["_geo", "_geoDistance", "_geoPoint", "_geoRadius", "_geoBoundingBox", "_geoPolygon"]
+        .contains(&keyword)
 }
 
 #[derive(Error, Debug)]
@@ -87,22 +88,42 @@ pub enum UserError {
     AttributeLimitReached,
     #[error(transparent)]
     CriterionError(#[from] CriterionError),
+    #[error("Cursor pagination is only supported by the searches without query words, distinct attribute nor geo sort.")]
+    CursorPaginationUnsupported,
+    #[error("The document id `{document_id}` is already used by another document.")]
+    DocumentIdAlreadyUsed { document_id: String },
+    #[error("The document id `{document_id}` is used by the documents at positions {} and {} in the batch.", .positions.0, .positions.1)]
+    DuplicateDocumentId { document_id: String, positions: (usize, usize) },
     #[error("Maximum number of documents reached.")]
     DocumentLimitReached,
     #[error(
-        "Document identifier `{}` is invalid. \
+        "Document identifier `{}`{} is invalid. \
 A document identifier can be of type integer or string, \
-only composed of alphanumeric characters (a-z A-Z 0-9), hyphens (-) and underscores (_).", .document_id.to_string()
+only composed of alphanumeric characters (a-z A-Z 0-9), hyphens (-) and underscores (_), \
+and can't be longer than 511 bytes.", .document_id.to_string(),
+        .position.map(|position| format!(" of the document at position {} in the batch", position)).unwrap_or_default()
     )]
-    InvalidDocumentId { document_id: Value },
+    InvalidDocumentId { document_id: Value, position: Option<usize> },
     #[error("Invalid facet distribution, the fields `{}` are not set as filterable.",
         .invalid_facets_name.iter().map(AsRef::as_ref).collect::<Vec<_>>().join(", ")
      )]
     InvalidFacetsDistribution { invalid_facets_name: BTreeSet<String> },
+    #[error("Attribute `{}` is not filterable and its values can't be searched. {}",
+        .field,
+        match .valid_fields.is_empty() {
+            true => "This index does not have configured filterable attributes.".to_string(),
+            false => format!("Available filterable attributes are: `{}`.",
+                    valid_fields.iter().map(AsRef::as_ref).collect::<Vec<_>>().join(", ")
+                ),
+        }
+    )]
+    InvalidFacetSearchAttribute { field: String, valid_fields: BTreeSet<String> },
     #[error(transparent)]
     InvalidGeoField(#[from] GeoError),
     #[error("{0}")]
     InvalidFilter(String),
+    #[error("The geo buckets must be a non-empty list of strictly increasing distances but found `{boundaries:?}`.")]
+    InvalidGeoBuckets { boundaries: Vec<usize> },
     #[error("Attribute `{}` is not sortable. {}",
         .field,
         match .valid_fields.is_empty() {
@@ -113,8 +134,42 @@ only composed of alphanumeric characters (a-z A-Z 0-9), hyphens (-) and undersco
         }
     )]
     InvalidSortableAttribute { field: String, valid_fields: BTreeSet<String> },
+    #[error("Attribute `{}` is not searchable. {}",
+        .field,
+        match .valid_fields.is_empty() {
+            true => "This index does not have any searchable attributes.".to_string(),
+            false => format!("Available searchable attributes are: `{}`.",
+                    valid_fields.iter().map(AsRef::as_ref).collect::<Vec<_>>().join(", ")
+                ),
+        }
+    )]
+    InvalidSearchableAttribute { field: String, valid_fields: BTreeSet<String> },
+    #[error("Attribute `{}` can't be selected, no document contains it. {}",
+        .field,
+        match .valid_fields.is_empty() {
+            true => "This index does not contain any attribute.".to_string(),
+            false => format!("Available attributes are: `{}`.",
+                    valid_fields.iter().map(AsRef::as_ref).collect::<Vec<_>>().join(", ")
+                ),
+        }
+    )]
+    InvalidSelectedAttribute { field: String, valid_fields: BTreeSet<String> },
+    #[error("The cursor `{cursor}` is invalid or does not correspond to the sort of this search.")]
+    InvalidSearchCursor { cursor: String },
+    #[error("The JSON of the documents can't be returned, this index doesn't store it.")]
+    DocumentsJsonNotStored,
+    #[error("The search has been aborted.")]
+    SearchAborted,
+    #[error("The search can't require at least {min_words} of the {words} words of the query, there are too many combinations of them.")]
+    TooManyAtLeastCombinations { min_words: usize, words: usize },
     #[error("The sort ranking rule must be specified in the ranking rules settings to use the sort parameter at search time.")]
     SortRankingRuleMissing,
+    #[error("The `{criterion}` ranking rule or filter can't be used because the `{database}` databases of this index are disabled.")]
+    CriterionDatabaseDisabled { criterion: String, database: &'static str },
+    #[error(
+        "The disabled databases of an index can only be chosen before any document is indexed."
+    )]
+    DisabledDatabasesCannotBeChanged,
     #[error("The database file is in an invalid state.")]
     InvalidStoreFile,
     #[error("Maximum database size has been reached.")]
@@ -123,6 +178,8 @@ only composed of alphanumeric characters (a-z A-Z 0-9), hyphens (-) and undersco
     MissingDocumentId { primary_key: String, document: Object },
     #[error("The primary key inference process failed because the engine did not find any fields containing `id` substring in their name. If your document identifier does not contain any `id` substring, you can set the primary key of the index.")]
     MissingPrimaryKey,
+    #[error("The primary key inference process failed because no field is named `id` or ends with `id`, the fields `{}` contain the `id` substring but can't be chosen automatically. You can set the primary key of the index.", .candidates.join(", "))]
+    AmbiguousPrimaryKey { candidates: Vec<String> },
     #[error("There is no more space left on the device. Consider increasing the size of the disk/partition.")]
     NoSpaceLeftOnDevice,
     #[error("Index already has a primary key: `{0}`.")]
@@ -131,6 +188,8 @@ only composed of alphanumeric characters (a-z A-Z 0-9), hyphens (-) and undersco
     SerdeJson(serde_json::Error),
     #[error(transparent)]
     SortError(#[from] SortError),
+    #[error("An unknown external document id have been used: `{document_id}`.")]
+    UnknownExternalDocumentId { document_id: String },
     #[error("An unknown internal document id have been used: `{document_id}`.")]
     UnknownInternalDocumentId { document_id: DocumentId },
     #[error("`minWordSizeForTypos` setting is invalid. `oneTypo` and `twoTypos` fields should be between `0` and `255`, and `twoTypos` should be greater or equals to `oneTypo` but found `oneTypo: {0}` and twoTypos: {1}`.")]
@@ -147,6 +206,14 @@ pub enum GeoError {
     BadLatitude { document_id: Value, value: Value },
     #[error("Could not parse longitude in the document with the id: `{document_id}`. Was expecting a number but instead got `{value}`.")]
     BadLongitude { document_id: Value, value: Value },
+    #[error("Could not index the latitude in the document with the id: `{document_id}`. Latitude must be contained between -90 and 90 degrees but instead got `{value}`.")]
+    LatitudeOutOfRange { document_id: Value, value: f64 },
+    #[error("Could not index the longitude in the document with the id: `{document_id}`. Longitude must be contained between -180 and 180 degrees but instead got `{value}`.")]
+    LongitudeOutOfRange { document_id: Value, value: f64 },
+    #[error("Could not index the geometry in the document with the id: `{document_id}`. Only the GeoJSON `Point` geometry is supported but instead got `{geometry}`.")]
+    UnsupportedGeometry { document_id: Value, geometry: Value },
+    #[error("Could not parse the coordinates in the document with the id: `{document_id}`. Was expecting a `[longitude, latitude]` array of numbers but instead got `{value}`.")]
+    BadCoordinates { document_id: Value, value: Value },
 }
 
 /// A little macro helper to autogenerate From implementation that needs two `Into`.
@@ -1,10 +1,11 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::convert::TryInto;
 use std::{fmt, str};
 
 use fst::map::IndexedValue;
-use fst::{IntoStreamer, Streamer};
+use fst::raw::{CompiledAddr, Fst};
+use fst::{Automaton, IntoStreamer, Streamer};
 
 const DELETED_ID: u64 = u64::MAX;
 
@@ -41,6 +42,30 @@ impl<'a> ExternalDocumentsIds<'a> {
         }
     }
 
+    /// Returns the internal ids of the given external ids, in the same order, the hard and soft
+    /// maps are traversed once and only along the external ids that were asked for.
+    pub fn get_many<A: AsRef<[u8]>>(&self, external_ids: &[A]) -> fst::Result<Vec<Option<u32>>> {
+        let requested: BTreeSet<&[u8]> = external_ids.iter().map(|id| id.as_ref()).collect();
+        let requested = fst::Set::from_iter(requested)?;
+
+        // the soft map is read last, its entries override the ones of the hard map.
+        let mut found = HashMap::new();
+        for map in [&self.hard, &self.soft].iter() {
+            let mut stream = map.search(ExactKeys(requested.as_fst())).into_stream();
+            while let Some((external_id, id)) = stream.next() {
+                found.insert(external_id.to_vec(), id);
+            }
+        }
+
+        Ok(external_ids
+            .iter()
+            .map(|external_id| match found.get(external_id.as_ref()) {
+                Some(&id) if id != DELETED_ID => Some(id.try_into().unwrap()),
+                _otherwise => None,
+            })
+            .collect())
+    }
+
     pub fn delete_ids<A: AsRef<[u8]>>(&mut self, other: fst::Set<A>) -> fst::Result<()> {
         let other = fst::Map::from(other.into_fst());
         let union_op = self.soft.op().add(&other).r#union();
@@ -138,6 +163,31 @@ impl Default for ExternalDocumentsIds<'static> {
     }
 }
 
+/// An automaton that only accepts the keys of an fst, the state is the address
+/// of the node reached in this fst, `None` once the input left it.
+struct ExactKeys<'f, D>(&'f Fst<D>);
+
+impl<D: AsRef<[u8]>> Automaton for ExactKeys<'_, D> {
+    type State = Option<CompiledAddr>;
+
+    fn start(&self) -> Self::State {
+        Some(self.0.root().addr())
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        state.map_or(false, |addr| self.0.node(addr).is_final())
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        state.is_some()
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        let node = self.0.node((*state)?);
+        node.find_input(byte).map(|i| node.transition_addr(i))
+    }
+}
+
 /// Returns the value of the `IndexedValue` with the highest _index_.
 fn indexed_last_value(indexed_values: &[IndexedValue]) -> Option<u64> {
     indexed_values.iter().copied().max_by_key(|iv| iv.index).map(|iv| iv.value)
@@ -194,6 +244,33 @@ mod tests {
         assert_eq!(external_documents_ids.get("h"), Some(8));
     }
 
+    #[test]
+    fn get_many_ids() {
+        let mut external_documents_ids = ExternalDocumentsIds::default();
+
+        let new_ids = fst::Map::from_iter(vec![("a", 1), ("ab", 2), ("b", 3), ("c", 4)]).unwrap();
+        external_documents_ids.insert_ids(&new_ids).unwrap();
+        let del_ids = fst::Set::from_iter(vec!["b"]).unwrap();
+        external_documents_ids.delete_ids(del_ids).unwrap();
+        let new_ids = fst::Map::from_iter(vec![("d", 5)]).unwrap();
+        external_documents_ids.insert_ids(&new_ids).unwrap();
+
+        let ids = external_documents_ids.get_many(&["c", "abc", "a", "b", "d", "", "x"]).unwrap();
+        assert_eq!(ids, vec![Some(4), None, Some(1), None, Some(5), None, None]);
+    }
+
+    #[test]
+    fn get_many_duplicate_ids() {
+        let mut external_documents_ids = ExternalDocumentsIds::default();
+
+        let new_ids = fst::Map::from_iter(vec![("a", 1), ("b", 2)]).unwrap();
+        external_documents_ids.insert_ids(&new_ids).unwrap();
+
+        let ids = external_documents_ids.get_many(&["b", "a", "b", "z", "z"]).unwrap();
+        assert_eq!(ids, vec![Some(2), Some(1), Some(2), None, None]);
+        assert!(external_documents_ids.get_many::<&str>(&[]).unwrap().is_empty());
+    }
+
     #[test]
     fn strange_delete_insert_ids() {
         let mut external_documents_ids = ExternalDocumentsIds::default();
@@ -5,6 +5,7 @@ use serde::{Serialize, Serializer};
 pub enum FacetValue {
     String(String),
     Number(OrderedFloat<f64>),
+    Bool(bool),
 }
 
 impl From<String> for FacetValue {
@@ -31,6 +32,12 @@ impl From<OrderedFloat<f64>> for FacetValue {
     }
 }
 
+impl From<bool> for FacetValue {
+    fn from(boolean: bool) -> FacetValue {
+        FacetValue::Bool(boolean)
+    }
+}
+
 impl From<i64> for FacetValue {
     fn from(integer: i64) -> FacetValue {
         FacetValue::Number(OrderedFloat(integer as f64))
@@ -51,6 +58,7 @@ impl Serialize for FacetValue {
                 let string = number.to_string();
                 serializer.serialize_str(&string)
             }
+            FacetValue::Bool(boolean) => serializer.serialize_str(&boolean.to_string()),
         }
     }
 }
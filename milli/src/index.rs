@@ -1,51 +1,313 @@
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
+use std::io::{Read, Seek};
 use std::mem::size_of;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use heed::flags::Flags;
 use heed::types::*;
 use heed::{CompactionOption, Database, PolyDatabase, RoTxn, RwTxn};
 use roaring::RoaringBitmap;
 use rstar::RTree;
+use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
+use crate::documents::DocumentBatchReader;
 use crate::error::{InternalError, UserError};
+use crate::facet::FacetValue;
 use crate::fields_ids_map::FieldsIdsMap;
 use crate::heed_codec::facet::{
     FacetLevelValueF64Codec, FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec,
     FieldDocIdFacetF64Codec, FieldDocIdFacetStringCodec,
 };
+use crate::update::{
+    self, DeleteDocuments, DocumentAdditionResult, DocumentDeletionResult, IndexDocuments,
+    IndexDocumentsConfig, IndexerConfig, UpdateIndexingStep,
+};
 use crate::{
-    default_criteria, BEU32StrCodec, BoRoaringBitmapCodec, CboRoaringBitmapCodec, Criterion,
-    DocumentId, ExternalDocumentsIds, FacetDistribution, FieldDistribution, FieldId,
-    FieldIdWordCountCodec, GeoPoint, ObkvCodec, Result, RoaringBitmapCodec, RoaringBitmapLenCodec,
-    Search, StrBEU32Codec, StrStrU8Codec, BEU32,
+    default_criteria, distance_between_two_points, planar_distance_between_two_points,
+    BEU32StrCodec, BoRoaringBitmapCodec, CboRoaringBitmapCodec, Criterion, DocumentId,
+    ExternalDocumentsIds, FacetDistribution, FieldDistribution, FieldId, FieldIdWordCountCodec,
+    Filter, GeoPoint, ObkvCodec, Result, RoaringBitmapCodec, RoaringBitmapLenCodec, Search,
+    StrBEU32Codec, StrStrU8Codec, BEU32,
 };
 
 pub const DEFAULT_MIN_WORD_LEN_ONE_TYPO: u8 = 5;
 pub const DEFAULT_MIN_WORD_LEN_TWO_TYPOS: u8 = 9;
+pub const DEFAULT_MIN_WORD_SIZE_FOR_PREFIX: u8 = 1;
+
+/// The typo tolerance of an attribute, overriding the global typo settings
+/// when matching the query words against this attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttributeTypoTolerance {
+    /// Whether typos are allowed at all in this attribute.
+    pub enabled: bool,
+    /// The minimum number of chars of a query word to accept one typo.
+    pub min_word_len_one_typo: u8,
+    /// The minimum number of chars of a query word to accept two typos.
+    pub min_word_len_two_typos: u8,
+}
+
+/// How the number of typos allowed on a query word is derived from its length.
+///
+/// The typo tolerance overrides of the attributes keep using their own thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "mode")]
+pub enum TypoBudget {
+    /// One typo from the minimum word length for one typo,
+    /// two typos from the minimum word length for two typos.
+    Thresholds,
+    /// One typo every `chars_per_typo` chars of the word, up to `max_typos`.
+    #[serde(rename_all = "camelCase")]
+    Length { chars_per_typo: u8, max_typos: u8 },
+}
+
+impl TypoBudget {
+    /// Returns the number of typos allowed on a word of `word_len` chars,
+    /// `thresholds` being the minimum word lengths for one and two typos.
+    pub fn max_typos(&self, word_len: u8, (one, two): (u8, u8)) -> u8 {
+        match *self {
+            TypoBudget::Thresholds if word_len < one => 0,
+            TypoBudget::Thresholds if word_len < two => 1,
+            TypoBudget::Thresholds => 2,
+            TypoBudget::Length { chars_per_typo, max_typos } => {
+                word_len.checked_div(chars_per_typo).unwrap_or(0).min(max_typos)
+            }
+        }
+    }
+}
+
+impl Default for TypoBudget {
+    fn default() -> TypoBudget {
+        TypoBudget::Thresholds
+    }
+}
+
+/// The maximum number of chars of the indexed words and what is done with the longer words,
+/// the limit is applied the same way to the words of the documents and of the queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaxWordLength {
+    pub max_chars: usize,
+    pub long_words: LongWords,
+}
+
+/// What is done with the words longer than the [`MaxWordLength`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LongWords {
+    /// Only the first chars of the words are kept, the words starting
+    /// the same way are then indexed and searched as the same word.
+    Truncate,
+    /// The words are not indexed and are ignored in the queries.
+    Skip,
+}
+
+/// How the distances between the geo points are computed by the `_geoPoint`
+/// sort and the `_geoRadius` filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GeoDistance {
+    /// The haversine distance on the sphere, accurate at any distance.
+    GreatCircle,
+    /// The distance on a plane, to return and compare the same distances as an application
+    /// computing them on a flat map, it is close to the haversine one for the points of a
+    /// small area, e.g. the shops of a city.
+    ///
+    /// The planar distances are not ordered like the points of the rtree: the `_geoPoint`
+    /// sort computes and sorts the distances of all its candidates instead of only visiting
+    /// the closest points, and the `_geoRadius` filter checks the points of the box of
+    /// latitudes and longitudes that can be within the radius.
+    Planar,
+}
+
+impl Default for GeoDistance {
+    fn default() -> GeoDistance {
+        GeoDistance::GreatCircle
+    }
+}
+
+impl GeoDistance {
+    /// Returns the distance in meters between two `[latitude, longitude]` points.
+    pub fn distance(self, a: &[f64; 2], b: &[f64; 2]) -> f64 {
+        match self {
+            GeoDistance::GreatCircle => distance_between_two_points(a, b),
+            GeoDistance::Planar => planar_distance_between_two_points(a, b),
+        }
+    }
+}
+
+/// The auxiliary databases that are never built for an index, they are chosen when the index
+/// is created and the ranking rules relying on them can't be used.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisabledDatabases {
+    /// The word pair proximity databases, required by the `proximity` ranking rule.
+    pub proximity: bool,
+    /// The words prefixes databases, the prefixes are then derived from the words fst.
+    pub prefix: bool,
+    /// The geo points rtree, required by the `_geoPoint` sort.
+    pub geo: bool,
+}
+
+/// The attributes of a category of settings, with the ids the `FieldsIdsMap` gives them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AttributesSet {
+    /// Whether every field is part of this category, the attributes are then all the
+    /// fields of the `FieldsIdsMap`.
+    pub wildcard: bool,
+    /// The attributes names with their field id, `None` when no document has this field yet.
+    pub attributes: BTreeMap<String, Option<FieldId>>,
+}
+
+impl AttributesSet {
+    fn new<'a>(
+        attributes: Option<impl IntoIterator<Item = &'a str>>,
+        fields_ids_map: &FieldsIdsMap,
+    ) -> AttributesSet {
+        match attributes {
+            Some(attributes) => AttributesSet {
+                wildcard: false,
+                attributes: attributes
+                    .into_iter()
+                    .map(|name| (name.to_string(), fields_ids_map.id(name)))
+                    .collect(),
+            },
+            None => AttributesSet {
+                wildcard: true,
+                attributes: fields_ids_map
+                    .iter()
+                    .map(|(id, name)| (name.to_string(), Some(id)))
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// A snapshot of the size of an index, returned by [`Index::stats`].
+///
+/// The numbers of entries of the facet databases count the entries of every facet level.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IndexStats {
+    pub number_of_documents: u64,
+    /// The number of fields in the `FieldsIdsMap`, the nested fields included.
+    pub number_of_fields: usize,
+    pub word_docids_entries: u64,
+    pub word_pair_proximity_docids_entries: u64,
+    pub facet_id_string_docids_entries: u64,
+    pub facet_id_f64_docids_entries: u64,
+    /// The size of the LMDB data file, it includes the free pages.
+    pub on_disk_size: u64,
+    pub has_geo_rtree: bool,
+}
+
+/// The databases that can be read in advance by [`Index::warmup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmupDatabase {
+    /// The main database, it contains the settings and the words FSTs.
+    Main,
+    /// The word and exact word databases.
+    WordDocids,
+    /// The word prefix and exact word prefix databases.
+    WordPrefixDocids,
+    /// The word pair and word prefix pair proximity databases.
+    WordPairProximityDocids,
+    /// The word and word prefix position databases.
+    WordPositionDocids,
+    /// The number and string facet databases.
+    FacetDocids,
+    /// The rtree of the geo points and the documents ids with a `_geo` field.
+    Geo,
+    /// The documents themselves, with their JSON when it is stored.
+    Documents,
+}
+
+/// What was read by [`Index::warmup`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WarmupReport {
+    /// The number of bytes of the keys and values read.
+    pub bytes_touched: u64,
+    /// The number of memory pages a byte was read from, the consecutive
+    /// entries stored in the same page only count it once.
+    pub pages_touched: u64,
+    /// Whether the warmup was cancelled before reading all the databases.
+    pub aborted: bool,
+}
+
+/// The size of the memory pages, a single byte of the pages is enough to load them.
+const WARMUP_PAGE_SIZE: usize = 4096;
+
+impl WarmupReport {
+    fn touch(&mut self, bytes: &[u8], last_page: &mut Option<usize>) {
+        if bytes.is_empty() {
+            return;
+        }
+        let start = bytes.as_ptr() as usize;
+        let (first, last) =
+            (start / WARMUP_PAGE_SIZE, (start + bytes.len() - 1) / WARMUP_PAGE_SIZE);
+        for page in first..=last {
+            let offset = (page * WARMUP_PAGE_SIZE).saturating_sub(start);
+            // safety: the offset is inside of the slice and the volatile read is never optimized
+            // away, it is what makes the OS load the page of the memory mapped database.
+            let _ = unsafe { std::ptr::read_volatile(bytes.as_ptr().add(offset)) };
+            if *last_page != Some(page) {
+                self.pages_touched += 1;
+                *last_page = Some(page);
+            }
+        }
+        self.bytes_touched += bytes.len() as u64;
+    }
+}
+
+/// The searchable, filterable, sortable and displayed attributes of an index, returned by
+/// [`Index::attributes`]. The filterable and sortable attributes are always listed explicitly.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IndexAttributes {
+    pub searchable: AttributesSet,
+    pub filterable: AttributesSet,
+    pub sortable: AttributesSet,
+    pub displayed: AttributesSet,
+}
 
 pub mod main_key {
     pub const CRITERIA_KEY: &str = "criteria";
+    pub const DICTIONARY_KEY: &str = "dictionary";
+    pub const DISABLED_DATABASES_KEY: &str = "disabled-databases";
     pub const DISPLAYED_FIELDS_KEY: &str = "displayed-fields";
     pub const DISTINCT_FIELD_KEY: &str = "distinct-field-key";
+    pub const DISTINCT_FIELDS_KEY: &str = "distinct-fields-key";
     pub const DOCUMENTS_IDS_KEY: &str = "documents-ids";
+    pub const DOCUMENTS_WORDS_COUNT_KEY: &str = "documents-words-count";
     pub const HIDDEN_FACETED_FIELDS_KEY: &str = "hidden-faceted-fields";
     pub const FILTERABLE_FIELDS_KEY: &str = "filterable-fields";
+    pub const NORMALIZED_FILTERABLE_FIELDS_KEY: &str = "normalized-filterable-fields";
+    pub const FACET_VALUE_MAPPINGS_KEY: &str = "facet-value-mappings";
     pub const SORTABLE_FIELDS_KEY: &str = "sortable-fields";
     pub const FIELD_DISTRIBUTION_KEY: &str = "fields-distribution";
     pub const FIELDS_IDS_MAP_KEY: &str = "fields-ids-map";
     pub const GEO_FACETED_DOCUMENTS_IDS_KEY: &str = "geo-faceted-documents-ids";
     pub const GEO_RTREE_KEY: &str = "geo-rtree";
+    pub const GEO_RTREE_INSERTIONS_KEY: &str = "geo-rtree-insertions";
+    pub const GEO_DISTANCE_KEY: &str = "geo-distance";
     pub const HARD_EXTERNAL_DOCUMENTS_IDS_KEY: &str = "hard-external-documents-ids";
     pub const NUMBER_FACETED_DOCUMENTS_IDS_PREFIX: &str = "number-faceted-documents-ids";
     pub const PRIMARY_KEY_KEY: &str = "primary-key";
     pub const SEARCHABLE_FIELDS_KEY: &str = "searchable-fields";
+    pub const SEARCHABLE_FIELDS_WEIGHTS_KEY: &str = "searchable-fields-weights";
+    pub const SEPARATORS_KEY: &str = "separators";
+    pub const NON_SEPARATORS_KEY: &str = "non-separators";
+    pub const DIGIT_GROUP_SEPARATORS_KEY: &str = "digit-group-separators";
+    pub const EMPTY_FACETED_DOCUMENTS_IDS_PREFIX: &str = "empty-faceted-documents-ids";
+    pub const FALSE_FACETED_DOCUMENTS_IDS_PREFIX: &str = "false-faceted-documents-ids";
+    pub const TRUE_FACETED_DOCUMENTS_IDS_PREFIX: &str = "true-faceted-documents-ids";
     pub const USER_DEFINED_SEARCHABLE_FIELDS_KEY: &str = "user-defined-searchable-fields";
     pub const SOFT_EXTERNAL_DOCUMENTS_IDS_KEY: &str = "soft-external-documents-ids";
+    pub const SOFT_DELETED_DOCUMENTS_IDS_KEY: &str = "soft-deleted-documents-ids";
     pub const STOP_WORDS_KEY: &str = "stop-words";
+    pub const STORE_DOCUMENTS_JSON_KEY: &str = "store-documents-json";
     pub const STRING_FACETED_DOCUMENTS_IDS_PREFIX: &str = "string-faceted-documents-ids";
     pub const SYNONYMS_KEY: &str = "synonyms";
     pub const WORDS_FST_KEY: &str = "words-fst";
@@ -55,9 +317,14 @@ pub mod main_key {
     pub const AUTHORIZE_TYPOS: &str = "authorize-typos";
     pub const ONE_TYPO_WORD_LEN: &str = "one-typo-word-len";
     pub const TWO_TYPOS_WORD_LEN: &str = "two-typos-word-len";
+    pub const ATTRIBUTES_TYPO_TOLERANCE: &str = "attributes-typo-tolerance";
+    pub const TYPO_BUDGET: &str = "typo-budget";
+    pub const ALLOW_TYPO_ON_FIRST_CHAR: &str = "allow-typo-on-first-char";
     pub const EXACT_WORDS: &str = "exact-words";
     pub const EXACT_ATTRIBUTES: &str = "exact-attributes";
     pub const MAX_VALUES_PER_FACET: &str = "max-values-per-facet";
+    pub const MAX_WORD_LENGTH: &str = "max-word-length";
+    pub const MIN_WORD_SIZE_FOR_PREFIX: &str = "min-word-size-for-prefix";
     pub const PAGINATION_MAX_TOTAL_HITS: &str = "pagination-max-total-hits";
 }
 
@@ -67,6 +334,7 @@ pub mod db_name {
     pub const EXACT_WORD_DOCIDS: &str = "exact-word-docids";
     pub const WORD_PREFIX_DOCIDS: &str = "word-prefix-docids";
     pub const EXACT_WORD_PREFIX_DOCIDS: &str = "exact-word-prefix-docids";
+    pub const STOP_WORD_DOCIDS: &str = "stop-word-docids";
     pub const DOCID_WORD_POSITIONS: &str = "docid-word-positions";
     pub const WORD_PAIR_PROXIMITY_DOCIDS: &str = "word-pair-proximity-docids";
     pub const WORD_PREFIX_PAIR_PROXIMITY_DOCIDS: &str = "word-prefix-pair-proximity-docids";
@@ -78,6 +346,7 @@ pub mod db_name {
     pub const FIELD_ID_DOCID_FACET_F64S: &str = "field-id-docid-facet-f64s";
     pub const FIELD_ID_DOCID_FACET_STRINGS: &str = "field-id-docid-facet-strings";
     pub const DOCUMENTS: &str = "documents";
+    pub const DOCUMENTS_JSON: &str = "documents-json";
 }
 
 #[derive(Clone)]
@@ -100,6 +369,11 @@ pub struct Index {
     /// A prefix of word and all the documents ids containing this prefix, from attributes for which typos are not allowed.
     pub exact_word_prefix_docids: Database<Str, RoaringBitmapCodec>,
 
+    /// A stop word, the field id (u32) it is found in and all the documents ids containing it
+    /// there. The stop words are not part of the other word databases and are only searched when
+    /// a query is only made of stop words or when they are scoped to an attribute.
+    pub stop_word_docids: Database<StrBEU32Codec, RoaringBitmapCodec>,
+
     /// Maps a word and a document id (u32) to all the positions where the given word appears.
     pub docid_word_positions: Database<BEU32StrCodec, BoRoaringBitmapCodec>,
 
@@ -127,13 +401,16 @@ pub struct Index {
 
     /// Maps the document id to the document as an obkv store.
     pub(crate) documents: Database<OwnedType<BEU32>, ObkvCodec>,
+    /// Maps the document id to the JSON of the document, written from its obkv
+    /// store, only when the JSON of the documents is stored.
+    pub(crate) documents_json: Database<OwnedType<BEU32>, ByteSlice>,
 }
 
 impl Index {
     pub fn new<P: AsRef<Path>>(mut options: heed::EnvOpenOptions, path: P) -> Result<Index> {
         use db_name::*;
 
-        options.max_dbs(16);
+        options.max_dbs(18);
         unsafe { options.flag(Flags::MdbAlwaysFreePages) };
 
         let env = options.open(path)?;
@@ -142,6 +419,7 @@ impl Index {
         let exact_word_docids = env.create_database(Some(EXACT_WORD_DOCIDS))?;
         let word_prefix_docids = env.create_database(Some(WORD_PREFIX_DOCIDS))?;
         let exact_word_prefix_docids = env.create_database(Some(EXACT_WORD_PREFIX_DOCIDS))?;
+        let stop_word_docids = env.create_database(Some(STOP_WORD_DOCIDS))?;
         let docid_word_positions = env.create_database(Some(DOCID_WORD_POSITIONS))?;
         let word_pair_proximity_docids = env.create_database(Some(WORD_PAIR_PROXIMITY_DOCIDS))?;
         let word_prefix_pair_proximity_docids =
@@ -155,6 +433,7 @@ impl Index {
         let field_id_docid_facet_strings =
             env.create_database(Some(FIELD_ID_DOCID_FACET_STRINGS))?;
         let documents = env.create_database(Some(DOCUMENTS))?;
+        let documents_json = env.create_database(Some(DOCUMENTS_JSON))?;
 
         Index::initialize_creation_dates(&env, main)?;
 
@@ -165,6 +444,7 @@ impl Index {
             exact_word_docids,
             word_prefix_docids,
             exact_word_prefix_docids,
+            stop_word_docids,
             docid_word_positions,
             word_pair_proximity_docids,
             word_prefix_pair_proximity_docids,
@@ -176,9 +456,36 @@ impl Index {
             field_id_docid_facet_f64s,
             field_id_docid_facet_strings,
             documents,
+            documents_json,
         })
     }
 
+    /// Opens the index like [`Index::new`] and never builds the given databases. They can only
+    /// be chosen before the first documents are indexed, reopening the index with the same
+    /// disabled databases is allowed.
+    pub fn new_with_disabled_databases<P: AsRef<Path>>(
+        options: heed::EnvOpenOptions,
+        path: P,
+        disabled_databases: DisabledDatabases,
+    ) -> Result<Index> {
+        let index = Index::new(options, path)?;
+
+        let mut wtxn = index.write_txn()?;
+        if index.disabled_databases(&wtxn)? != disabled_databases {
+            if !index.documents_ids(&wtxn)?.is_empty() {
+                return Err(UserError::DisabledDatabasesCannotBeChanged.into());
+            }
+            index.main.put::<_, Str, SerdeJson<DisabledDatabases>>(
+                &mut wtxn,
+                main_key::DISABLED_DATABASES_KEY,
+                &disabled_databases,
+            )?;
+            wtxn.commit()?;
+        }
+
+        Ok(index)
+    }
+
     fn initialize_creation_dates(env: &heed::Env, main: PolyDatabase) -> heed::Result<()> {
         let mut txn = env.write_txn()?;
         // The db was just created, we update its metadata with the relevant information.
@@ -215,6 +522,107 @@ impl Index {
         self.env.path()
     }
 
+    /// Returns the numbers of documents, fields and entries of the main databases, they are
+    /// read from the LMDB databases statistics without iterating over the entries.
+    pub fn stats(&self, rtxn: &RoTxn) -> Result<IndexStats> {
+        let has_geo_rtree =
+            self.main.get::<_, Str, DecodeIgnore>(rtxn, main_key::GEO_RTREE_KEY)?.is_some();
+
+        Ok(IndexStats {
+            number_of_documents: self.number_of_documents(rtxn)?,
+            number_of_fields: self.fields_ids_map(rtxn)?.len(),
+            word_docids_entries: self.word_docids.len(rtxn)?,
+            word_pair_proximity_docids_entries: self.word_pair_proximity_docids.len(rtxn)?,
+            facet_id_string_docids_entries: self.facet_id_string_docids.len(rtxn)?,
+            facet_id_f64_docids_entries: self.facet_id_f64_docids.len(rtxn)?,
+            on_disk_size: self.path().join("data.mdb").metadata()?.len(),
+            has_geo_rtree,
+        })
+    }
+
+    /// Sequentially reads the entries of the databases to load them in the OS page cache,
+    /// the first searches after opening an index don't have to wait for the disk.
+    ///
+    /// The warmup stops as soon as the cancellation flag is set, the report then
+    /// only contains what was read until then.
+    pub fn warmup(
+        &self,
+        rtxn: &RoTxn,
+        databases: &[WarmupDatabase],
+        cancellation: Option<&AtomicBool>,
+    ) -> Result<WarmupReport> {
+        let is_cancelled = || cancellation.map_or(false, |flag| flag.load(Ordering::Relaxed));
+        let mut report = WarmupReport::default();
+        let mut last_page = None;
+
+        for &database in databases {
+            if is_cancelled() {
+                report.aborted = true;
+                return Ok(report);
+            }
+
+            let iters = match database {
+                WarmupDatabase::Main => vec![self.main.iter::<_, ByteSlice, ByteSlice>(rtxn)?],
+                WarmupDatabase::WordDocids => vec![
+                    self.word_docids.remap_types::<ByteSlice, ByteSlice>().iter(rtxn)?,
+                    self.exact_word_docids.remap_types::<ByteSlice, ByteSlice>().iter(rtxn)?,
+                ],
+                WarmupDatabase::WordPrefixDocids => vec![
+                    self.word_prefix_docids.remap_types::<ByteSlice, ByteSlice>().iter(rtxn)?,
+                    self.exact_word_prefix_docids
+                        .remap_types::<ByteSlice, ByteSlice>()
+                        .iter(rtxn)?,
+                ],
+                WarmupDatabase::WordPairProximityDocids => vec![
+                    self.word_pair_proximity_docids
+                        .remap_types::<ByteSlice, ByteSlice>()
+                        .iter(rtxn)?,
+                    self.word_prefix_pair_proximity_docids
+                        .remap_types::<ByteSlice, ByteSlice>()
+                        .iter(rtxn)?,
+                ],
+                WarmupDatabase::WordPositionDocids => vec![
+                    self.word_position_docids.remap_types::<ByteSlice, ByteSlice>().iter(rtxn)?,
+                    self.word_prefix_position_docids
+                        .remap_types::<ByteSlice, ByteSlice>()
+                        .iter(rtxn)?,
+                ],
+                WarmupDatabase::FacetDocids => vec![
+                    self.facet_id_f64_docids.remap_types::<ByteSlice, ByteSlice>().iter(rtxn)?,
+                    self.facet_id_string_docids.remap_types::<ByteSlice, ByteSlice>().iter(rtxn)?,
+                ],
+                WarmupDatabase::Geo => {
+                    // the geo data is stored in two entries of the main database.
+                    let keys = [main_key::GEO_RTREE_KEY, main_key::GEO_FACETED_DOCUMENTS_IDS_KEY];
+                    for &key in keys.iter() {
+                        if let Some(value) = self.main.get::<_, Str, ByteSlice>(rtxn, key)? {
+                            report.touch(value, &mut last_page);
+                        }
+                    }
+                    continue;
+                }
+                WarmupDatabase::Documents => vec![
+                    self.documents.remap_types::<ByteSlice, ByteSlice>().iter(rtxn)?,
+                    self.documents_json.remap_key_type::<ByteSlice>().iter(rtxn)?,
+                ],
+            };
+
+            for iter in iters {
+                for result in iter {
+                    if is_cancelled() {
+                        report.aborted = true;
+                        return Ok(report);
+                    }
+                    let (key, value) = result?;
+                    report.touch(key, &mut last_page);
+                    report.touch(value, &mut last_page);
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
     pub fn copy_to_path<P: AsRef<Path>>(&self, path: P, option: CompactionOption) -> Result<File> {
         self.env.copy_to_path(path, option).map_err(Into::into)
     }
@@ -247,6 +655,119 @@ impl Index {
             .unwrap_or_default())
     }
 
+    /* documents words count */
+
+    /// Writes the total number of words positions of the documents, the soft deleted
+    /// documents excluded.
+    pub(crate) fn put_documents_words_count(
+        &self,
+        wtxn: &mut RwTxn,
+        count: u64,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, OwnedType<u64>>(wtxn, main_key::DOCUMENTS_WORDS_COUNT_KEY, &count)
+    }
+
+    /// Returns the total number of words positions of the documents, `None` if the
+    /// documents were indexed before it was counted.
+    pub fn documents_words_count(&self, rtxn: &RoTxn) -> heed::Result<Option<u64>> {
+        self.main.get::<_, Str, OwnedType<u64>>(rtxn, main_key::DOCUMENTS_WORDS_COUNT_KEY)
+    }
+
+    /* soft deleted documents ids */
+
+    /// Writes the documents ids that are soft deleted, these documents are no more
+    /// part of the documents ids but are still present in the other databases.
+    pub(crate) fn put_soft_deleted_documents_ids(
+        &self,
+        wtxn: &mut RwTxn,
+        docids: &RoaringBitmap,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, RoaringBitmapCodec>(
+            wtxn,
+            main_key::SOFT_DELETED_DOCUMENTS_IDS_KEY,
+            docids,
+        )
+    }
+
+    /// Returns the documents ids that are soft deleted and not yet purged.
+    pub fn soft_deleted_documents_ids(&self, rtxn: &RoTxn) -> heed::Result<RoaringBitmap> {
+        Ok(self
+            .main
+            .get::<_, Str, RoaringBitmapCodec>(rtxn, main_key::SOFT_DELETED_DOCUMENTS_IDS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /// Soft deletes the given documents: they are removed from the documents ids and the
+    /// external documents ids but their entries in the other databases are only removed
+    /// by the next hard deletion or by a call to [`Index::purge_soft_deleted_documents`].
+    pub fn soft_delete_documents<'i>(
+        &'i self,
+        wtxn: &mut RwTxn<'i, '_>,
+        docids: &RoaringBitmap,
+    ) -> Result<DocumentDeletionResult> {
+        let mut builder = DeleteDocuments::new(wtxn, self)?;
+        builder.soft_deletion(true);
+        builder.delete_documents(docids);
+        builder.execute()
+    }
+
+    /// Deletes the documents matching the filter, returns the number of deleted documents.
+    pub fn delete_documents_by_filter<'i>(
+        &'i self,
+        wtxn: &mut RwTxn<'i, '_>,
+        filter: &Filter,
+    ) -> Result<u64> {
+        let docids = filter.evaluate(wtxn, self)?;
+        let mut builder = DeleteDocuments::new(wtxn, self)?;
+        builder.delete_documents(&docids);
+        Ok(builder.execute()?.deleted_documents)
+    }
+
+    /// Deletes the documents matching the filter then indexes the new documents, in the same
+    /// transaction: the readers see either the old documents or the new ones. Returns the number
+    /// of deleted documents and the result of the addition.
+    ///
+    /// The new documents are indexed after the deletion, a new document with the id of a deleted
+    /// one is indexed as a new document and nothing is kept from the deleted one. A new document
+    /// with the id of a document that doesn't match the filter replaces or updates it, depending
+    /// on the indexing method of the config.
+    pub fn replace_documents_by_filter<'i, R: Read + Seek, F>(
+        &'i self,
+        wtxn: &mut RwTxn<'i, '_>,
+        filter: &Filter,
+        indexer_config: &IndexerConfig,
+        config: IndexDocumentsConfig,
+        documents: DocumentBatchReader<R>,
+        progress: F,
+    ) -> Result<(u64, DocumentAdditionResult)>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        let deleted = self.delete_documents_by_filter(wtxn, filter)?;
+        let mut builder = IndexDocuments::new(wtxn, self, indexer_config, config, progress)?;
+        builder.add_documents(documents)?;
+        Ok((deleted, builder.execute()?))
+    }
+
+    /// Removes the soft deleted documents from all the databases and makes
+    /// their ids available again, returns the number of purged documents.
+    pub fn purge_soft_deleted_documents<'i>(&'i self, wtxn: &mut RwTxn<'i, '_>) -> Result<u64> {
+        let purged = self.soft_deleted_documents_ids(wtxn)?.len();
+        DeleteDocuments::new(wtxn, self)?.execute()?;
+        Ok(purged)
+    }
+
+    /// Rebuilds the word, proximity and facet databases from the documents stored in the index,
+    /// with the current settings and without sending the documents again, e.g. after a change
+    /// of the tokenization. Returns the number of reindexed documents.
+    pub fn reindex_from_stored<'i>(
+        &'i self,
+        wtxn: &mut RwTxn<'i, '_>,
+        indexer_config: &IndexerConfig,
+    ) -> Result<u64> {
+        update::reindex_stored_documents(wtxn, self, indexer_config, |_| ())
+    }
+
     /// Returns the number of documents indexed in the database.
     pub fn number_of_documents(&self, rtxn: &RoTxn) -> Result<u64> {
         let count =
@@ -314,6 +835,87 @@ impl Index {
         Ok(ExternalDocumentsIds::new(hard, soft))
     }
 
+    /// Changes the external id of a document, the new external id points to the same internal
+    /// id and the document keeps all its indexed data, it isn't deleted and added again.
+    ///
+    /// The primary key of the stored document, and of its JSON if stored, is
+    /// rewritten, the JSON is serialized again. The words and facets of the primary key field keep the old id until the
+    /// document is reindexed.
+    pub fn rekey_document(
+        &self,
+        wtxn: &mut RwTxn,
+        old_external_id: &str,
+        new_external_id: &str,
+    ) -> Result<DocumentId> {
+        let mut external_documents_ids = self.external_documents_ids(wtxn)?.into_static();
+        let docid = external_documents_ids.get(old_external_id).ok_or_else(|| {
+            UserError::UnknownExternalDocumentId { document_id: old_external_id.to_string() }
+        })?;
+        let new_external_id = update::validate_document_id(new_external_id).ok_or_else(|| {
+            let document_id = serde_json::Value::String(new_external_id.to_string());
+            UserError::InvalidDocumentId { document_id, position: None }
+        })?;
+        if new_external_id == old_external_id {
+            return Ok(docid);
+        }
+        if external_documents_ids.get(new_external_id).is_some() {
+            let document_id = new_external_id.to_string();
+            return Err(UserError::DocumentIdAlreadyUsed { document_id }.into());
+        }
+
+        external_documents_ids.delete_ids(fst::Set::from_iter(Some(old_external_id))?)?;
+        let new_ids = fst::Map::from_iter(Some((new_external_id, docid as u64)))?;
+        external_documents_ids.insert_ids(&new_ids)?;
+        self.put_external_documents_ids(wtxn, &external_documents_ids)?;
+
+        let primary_key = match self.primary_key(wtxn)? {
+            Some(primary_key) => primary_key.to_string(),
+            None => return Ok(docid),
+        };
+        let fields_ids_map = self.fields_ids_map(wtxn)?;
+        let primary_key_id = match fields_ids_map.id(&primary_key) {
+            Some(id) => id,
+            None => return Ok(docid),
+        };
+
+        let key = BEU32::new(docid);
+        let mut buffer = Vec::new();
+        if let Some(obkv) = self.documents.get(wtxn, &key)? {
+            let old_value = obkv.get(primary_key_id).and_then(|v| serde_json::from_slice(v).ok());
+            let value = rekeyed_value(old_value.as_ref(), new_external_id);
+            let value = serde_json::to_vec(&value).map_err(InternalError::SerdeJson)?;
+            let mut writer = obkv::KvWriter::new(&mut buffer);
+            for (field_id, field_value) in obkv.iter() {
+                if field_id == primary_key_id {
+                    writer.insert(field_id, &value)?;
+                } else {
+                    writer.insert(field_id, field_value)?;
+                }
+            }
+            writer.finish()?;
+        }
+        if !buffer.is_empty() {
+            self.documents.remap_data_type::<ByteSlice>().put(wtxn, &key, &buffer)?;
+        }
+
+        // the JSON can't keep its bytes once its primary key changes, only its keys order is kept.
+        let json = match self.documents_json.get(wtxn, &key)? {
+            Some(json) => {
+                let mut document: serde_json::Map<String, serde_json::Value> =
+                    serde_json::from_slice(json).map_err(InternalError::SerdeJson)?;
+                let value = rekeyed_value(document.get(&primary_key), new_external_id);
+                document.insert(primary_key, value);
+                Some(serde_json::to_vec(&document).map_err(InternalError::SerdeJson)?)
+            }
+            None => None,
+        };
+        if let Some(json) = json {
+            self.documents_json.put(wtxn, &key, &json)?;
+        }
+
+        Ok(docid)
+    }
+
     /* fields ids map */
 
     /// Writes the fields ids map which associate the documents keys with an internal field id
@@ -337,7 +939,8 @@ impl Index {
 
     /* geo rtree */
 
-    /// Writes the provided `rtree` which associates coordinates to documents ids.
+    /// Writes the provided `rtree` which associates coordinates to documents ids,
+    /// the whole tree is serialized in a single entry.
     pub(crate) fn put_geo_rtree(
         &self,
         wtxn: &mut RwTxn,
@@ -362,6 +965,48 @@ impl Index {
         }
     }
 
+    /// Writes the number of points inserted one by one in the `rtree` since it was last built.
+    pub(crate) fn put_geo_rtree_insertions(
+        &self,
+        wtxn: &mut RwTxn,
+        count: u64,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, OwnedType<u64>>(wtxn, main_key::GEO_RTREE_INSERTIONS_KEY, &count)
+    }
+
+    /// Delete the number of points inserted one by one in the `rtree`.
+    pub(crate) fn delete_geo_rtree_insertions(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::GEO_RTREE_INSERTIONS_KEY)
+    }
+
+    /// Returns the number of points inserted one by one in the `rtree` since it was last built.
+    pub fn geo_rtree_insertions(&self, rtxn: &RoTxn) -> heed::Result<u64> {
+        Ok(self
+            .main
+            .get::<_, Str, OwnedType<u64>>(rtxn, main_key::GEO_RTREE_INSERTIONS_KEY)?
+            .unwrap_or(0))
+    }
+
+    pub(crate) fn put_geo_distance(
+        &self,
+        wtxn: &mut RwTxn,
+        geo_distance: GeoDistance,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::GEO_DISTANCE_KEY, &geo_distance)
+    }
+
+    pub(crate) fn delete_geo_distance(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::GEO_DISTANCE_KEY)
+    }
+
+    /// Returns how the distances between the geo points are computed.
+    pub fn geo_distance(&self, rtxn: &RoTxn) -> heed::Result<GeoDistance> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::GEO_DISTANCE_KEY)?
+            .unwrap_or_default())
+    }
+
     /* geo faceted */
 
     /// Writes the documents ids that are faceted with a _geo field.
@@ -463,6 +1108,30 @@ impl Index {
         }
     }
 
+    /// Returns the searchable, filterable, sortable and displayed attributes
+    /// with their field ids, all read from the same transaction.
+    pub fn attributes(&self, rtxn: &RoTxn) -> Result<IndexAttributes> {
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let filterable = self.filterable_fields(rtxn)?;
+        let sortable = self.sortable_fields(rtxn)?;
+
+        Ok(IndexAttributes {
+            searchable: AttributesSet::new(
+                self.user_defined_searchable_fields(rtxn)?,
+                &fields_ids_map,
+            ),
+            filterable: AttributesSet::new(
+                Some(filterable.iter().map(String::as_str)),
+                &fields_ids_map,
+            ),
+            sortable: AttributesSet::new(
+                Some(sortable.iter().map(String::as_str)),
+                &fields_ids_map,
+            ),
+            displayed: AttributesSet::new(self.displayed_fields(rtxn)?, &fields_ids_map),
+        })
+    }
+
     /* searchable fields */
 
     /// Write the user defined searchable fields and generate the real searchable fields from the specified fields ids map.
@@ -567,6 +1236,56 @@ impl Index {
             .get::<_, Str, SerdeBincode<Vec<_>>>(rtxn, main_key::USER_DEFINED_SEARCHABLE_FIELDS_KEY)
     }
 
+    /// Writes the weights of the user defined searchable fields.
+    pub(crate) fn put_searchable_fields_weights(
+        &self,
+        wtxn: &mut RwTxn,
+        weights: &HashMap<String, u16>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(
+            wtxn,
+            main_key::SEARCHABLE_FIELDS_WEIGHTS_KEY,
+            weights,
+        )
+    }
+
+    /// Deletes the weights of the searchable fields, they are then ranked by their order.
+    pub(crate) fn delete_searchable_fields_weights(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::SEARCHABLE_FIELDS_WEIGHTS_KEY)
+    }
+
+    /// Returns the weights of the user defined searchable fields, empty when
+    /// the searchable fields are only ranked by their order.
+    pub fn searchable_fields_weights(&self, rtxn: &RoTxn) -> heed::Result<HashMap<String, u16>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::SEARCHABLE_FIELDS_WEIGHTS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /// Identical to `searchable_fields_weights`, but returns the ids instead, the
+    /// nested fields have the weight of the searchable field they are part of.
+    pub fn searchable_fields_ids_weights(&self, rtxn: &RoTxn) -> Result<HashMap<FieldId, u16>> {
+        let weights = self.searchable_fields_weights(rtxn)?;
+        if weights.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let mut fields_ids_weights = HashMap::new();
+        for (field_id, name) in fields_ids_map.iter() {
+            let weight = weights
+                .iter()
+                .filter(|(field, _)| crate::is_faceted_by(name, field))
+                .map(|(_, weight)| *weight)
+                .max();
+            if let Some(weight) = weight {
+                fields_ids_weights.insert(field_id, weight);
+            }
+        }
+        Ok(fields_ids_weights)
+    }
+
     /* filterable fields */
 
     /// Writes the filterable fields names in the database.
@@ -606,102 +1325,314 @@ impl Index {
         Ok(fields_ids)
     }
 
-    /* sortable fields */
+    /* normalized filterable fields */
 
-    /// Writes the sortable fields names in the database.
-    pub(crate) fn put_sortable_fields(
+    /// Writes the names of the filterable fields whose values are filtered
+    /// regardless of their case and accents.
+    pub(crate) fn put_normalized_filterable_fields(
         &self,
         wtxn: &mut RwTxn,
         fields: &HashSet<String>,
     ) -> heed::Result<()> {
-        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::SORTABLE_FIELDS_KEY, fields)
+        self.main.put::<_, Str, SerdeJson<_>>(
+            wtxn,
+            main_key::NORMALIZED_FILTERABLE_FIELDS_KEY,
+            fields,
+        )
     }
 
-    /// Deletes the sortable fields ids in the database.
-    pub(crate) fn delete_sortable_fields(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
-        self.main.delete::<_, Str>(wtxn, main_key::SORTABLE_FIELDS_KEY)
+    /// Deletes the normalized filterable fields names in the database.
+    pub(crate) fn delete_normalized_filterable_fields(
+        &self,
+        wtxn: &mut RwTxn,
+    ) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::NORMALIZED_FILTERABLE_FIELDS_KEY)
     }
 
-    /// Returns the sortable fields names.
-    pub fn sortable_fields(&self, rtxn: &RoTxn) -> heed::Result<HashSet<String>> {
+    /// Returns the names of the filterable fields whose values are filtered
+    /// regardless of their case and accents.
+    pub fn normalized_filterable_fields(&self, rtxn: &RoTxn) -> heed::Result<HashSet<String>> {
         Ok(self
             .main
-            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::SORTABLE_FIELDS_KEY)?
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::NORMALIZED_FILTERABLE_FIELDS_KEY)?
             .unwrap_or_default())
     }
 
-    /// Identical to `sortable_fields`, but returns ids instead.
-    pub fn sortable_fields_ids(&self, rtxn: &RoTxn) -> Result<HashSet<FieldId>> {
-        let fields = self.sortable_fields(rtxn)?;
-        let fields_ids_map = self.fields_ids_map(rtxn)?;
-        Ok(fields.into_iter().filter_map(|name| fields_ids_map.id(&name)).collect())
-    }
-
-    /* faceted fields */
+    /* facet value mappings */
 
-    /// Writes the faceted fields in the database.
-    pub(crate) fn put_faceted_fields(
+    /// Writes the canonical values the raw string values of the faceted fields are replaced by,
+    /// by field name then raw value.
+    pub(crate) fn put_facet_value_mappings(
         &self,
         wtxn: &mut RwTxn,
-        fields: &HashSet<String>,
+        mappings: &HashMap<String, HashMap<String, String>>,
     ) -> heed::Result<()> {
-        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::HIDDEN_FACETED_FIELDS_KEY, fields)
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::FACET_VALUE_MAPPINGS_KEY, mappings)
     }
 
-    /// Returns the faceted fields names.
-    pub fn faceted_fields(&self, rtxn: &RoTxn) -> heed::Result<HashSet<String>> {
+    /// Deletes the facet value mappings in the database.
+    pub(crate) fn delete_facet_value_mappings(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::FACET_VALUE_MAPPINGS_KEY)
+    }
+
+    /// Returns the canonical values the raw string values of the faceted fields
+    /// are replaced by, by field name then raw value.
+    pub fn facet_value_mappings(
+        &self,
+        rtxn: &RoTxn,
+    ) -> heed::Result<HashMap<String, HashMap<String, String>>> {
         Ok(self
             .main
-            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::HIDDEN_FACETED_FIELDS_KEY)?
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::FACET_VALUE_MAPPINGS_KEY)?
             .unwrap_or_default())
     }
 
-    /// Identical to `faceted_fields`, but returns ids instead.
-    pub fn faceted_fields_ids(&self, rtxn: &RoTxn) -> Result<HashSet<FieldId>> {
-        let fields = self.faceted_fields(rtxn)?;
+    /// Returns the facet value mappings by field id, the fields that don't appear in any
+    /// document are skipped. The raw values are trimmed and lowercased, the way the facet
+    /// strings are stored, they are compared regardless of their case.
+    pub fn facet_value_mappings_ids(
+        &self,
+        rtxn: &RoTxn,
+    ) -> Result<HashMap<FieldId, HashMap<String, String>>> {
         let fields_ids_map = self.fields_ids_map(rtxn)?;
-
-        let mut fields_ids = HashSet::new();
-        for name in fields {
-            if let Some(field_id) = fields_ids_map.id(&name) {
-                fields_ids.insert(field_id);
+        let mut mappings = HashMap::new();
+        for (name, mapping) in self.facet_value_mappings(rtxn)? {
+            if let Some(fid) = fields_ids_map.id(&name) {
+                let mapping = mapping
+                    .into_iter()
+                    .map(|(raw, canonical)| (raw.trim().to_lowercase(), canonical))
+                    .collect();
+                mappings.insert(fid, mapping);
             }
         }
+        Ok(mappings)
+    }
 
-        Ok(fields_ids)
+    /* separators */
+
+    /// Writes the characters the text is split on, in addition to the default separators.
+    pub(crate) fn put_separators(
+        &self,
+        wtxn: &mut RwTxn,
+        separators: &BTreeSet<char>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::SEPARATORS_KEY, separators)
     }
 
-    /* faceted documents ids */
+    pub(crate) fn delete_separators(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::SEPARATORS_KEY)
+    }
 
-    /// Returns the user defined faceted fields names.
-    ///
-    /// The user faceted fields are the union of all the filterable, sortable, distinct, and Asc/Desc fields.
-    pub fn user_defined_faceted_fields(&self, rtxn: &RoTxn) -> Result<HashSet<String>> {
-        let filterable_fields = self.filterable_fields(rtxn)?;
-        let sortable_fields = self.sortable_fields(rtxn)?;
-        let distinct_field = self.distinct_field(rtxn)?;
-        let asc_desc_fields =
-            self.criteria(rtxn)?.into_iter().filter_map(|criterion| match criterion {
-                Criterion::Asc(field) | Criterion::Desc(field) => Some(field),
-                _otherwise => None,
-            });
+    /// Returns the characters the text is split on, in addition to the default separators.
+    pub fn separators(&self, rtxn: &RoTxn) -> heed::Result<BTreeSet<char>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::SEPARATORS_KEY)?
+            .unwrap_or_default())
+    }
 
-        let mut faceted_fields = filterable_fields;
-        faceted_fields.extend(sortable_fields);
-        faceted_fields.extend(asc_desc_fields);
-        if let Some(field) = distinct_field {
-            faceted_fields.insert(field.to_owned());
-        }
+    /// Writes the default separators that must be kept inside the words.
+    pub(crate) fn put_non_separators(
+        &self,
+        wtxn: &mut RwTxn,
+        non_separators: &BTreeSet<char>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::NON_SEPARATORS_KEY, non_separators)
+    }
 
-        Ok(faceted_fields)
+    pub(crate) fn delete_non_separators(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::NON_SEPARATORS_KEY)
     }
 
-    /// Identical to `user_defined_faceted_fields`, but returns ids instead.
-    pub fn user_defined_faceted_fields_ids(&self, rtxn: &RoTxn) -> Result<HashSet<FieldId>> {
-        let fields = self.faceted_fields(rtxn)?;
-        let fields_ids_map = self.fields_ids_map(rtxn)?;
+    /// Returns the default separators that are kept inside the words.
+    pub fn non_separators(&self, rtxn: &RoTxn) -> heed::Result<BTreeSet<char>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::NON_SEPARATORS_KEY)?
+            .unwrap_or_default())
+    }
 
-        let mut fields_ids = HashSet::new();
+    /// Writes the characters that separate the groups of digits of the numbers.
+    pub(crate) fn put_digit_group_separators(
+        &self,
+        wtxn: &mut RwTxn,
+        separators: &BTreeSet<char>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(
+            wtxn,
+            main_key::DIGIT_GROUP_SEPARATORS_KEY,
+            separators,
+        )
+    }
+
+    pub(crate) fn delete_digit_group_separators(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::DIGIT_GROUP_SEPARATORS_KEY)
+    }
+
+    /// Returns the characters that separate the groups of digits of the numbers,
+    /// the numbers are indexed and searched without them.
+    pub fn digit_group_separators(&self, rtxn: &RoTxn) -> heed::Result<BTreeSet<char>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::DIGIT_GROUP_SEPARATORS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /* dictionary */
+
+    /// Writes the words that the tokenizer must never split.
+    pub(crate) fn put_dictionary(
+        &self,
+        wtxn: &mut RwTxn,
+        dictionary: &BTreeSet<String>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::DICTIONARY_KEY, dictionary)
+    }
+
+    pub(crate) fn delete_dictionary(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::DICTIONARY_KEY)
+    }
+
+    /// Returns the words that the tokenizer never splits.
+    pub fn dictionary(&self, rtxn: &RoTxn) -> heed::Result<BTreeSet<String>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::DICTIONARY_KEY)?
+            .unwrap_or_default())
+    }
+
+    pub(crate) fn put_max_word_length(
+        &self,
+        wtxn: &mut RwTxn,
+        max_word_length: MaxWordLength,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::MAX_WORD_LENGTH, &max_word_length)
+    }
+
+    pub(crate) fn delete_max_word_length(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::MAX_WORD_LENGTH)
+    }
+
+    /// Returns the maximum length of the indexed words, `None` when the words are never cut.
+    pub fn max_word_length(&self, rtxn: &RoTxn) -> heed::Result<Option<MaxWordLength>> {
+        self.main.get::<_, Str, SerdeJson<_>>(rtxn, main_key::MAX_WORD_LENGTH)
+    }
+
+    pub(crate) fn put_min_word_size_for_prefix(
+        &self,
+        wtxn: &mut RwTxn,
+        val: u8,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, OwnedType<u8>>(wtxn, main_key::MIN_WORD_SIZE_FOR_PREFIX, &val)
+    }
+
+    pub(crate) fn delete_min_word_size_for_prefix(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::MIN_WORD_SIZE_FOR_PREFIX)
+    }
+
+    /// Returns the number of chars a word must have to be indexed as prefixes and to match
+    /// the longer words as the last word of a query.
+    pub fn min_word_size_for_prefix(&self, rtxn: &RoTxn) -> heed::Result<u8> {
+        Ok(self
+            .main
+            .get::<_, Str, OwnedType<u8>>(rtxn, main_key::MIN_WORD_SIZE_FOR_PREFIX)?
+            .unwrap_or(DEFAULT_MIN_WORD_SIZE_FOR_PREFIX))
+    }
+
+    /* sortable fields */
+
+    /// Writes the sortable fields names in the database.
+    pub(crate) fn put_sortable_fields(
+        &self,
+        wtxn: &mut RwTxn,
+        fields: &HashSet<String>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::SORTABLE_FIELDS_KEY, fields)
+    }
+
+    /// Deletes the sortable fields ids in the database.
+    pub(crate) fn delete_sortable_fields(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::SORTABLE_FIELDS_KEY)
+    }
+
+    /// Returns the sortable fields names.
+    pub fn sortable_fields(&self, rtxn: &RoTxn) -> heed::Result<HashSet<String>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::SORTABLE_FIELDS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /// Identical to `sortable_fields`, but returns ids instead.
+    pub fn sortable_fields_ids(&self, rtxn: &RoTxn) -> Result<HashSet<FieldId>> {
+        let fields = self.sortable_fields(rtxn)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        Ok(fields.into_iter().filter_map(|name| fields_ids_map.id(&name)).collect())
+    }
+
+    /* faceted fields */
+
+    /// Writes the faceted fields in the database.
+    pub(crate) fn put_faceted_fields(
+        &self,
+        wtxn: &mut RwTxn,
+        fields: &HashSet<String>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(wtxn, main_key::HIDDEN_FACETED_FIELDS_KEY, fields)
+    }
+
+    /// Returns the faceted fields names.
+    pub fn faceted_fields(&self, rtxn: &RoTxn) -> heed::Result<HashSet<String>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(rtxn, main_key::HIDDEN_FACETED_FIELDS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /// Identical to `faceted_fields`, but returns ids instead.
+    pub fn faceted_fields_ids(&self, rtxn: &RoTxn) -> Result<HashSet<FieldId>> {
+        let fields = self.faceted_fields(rtxn)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+
+        let mut fields_ids = HashSet::new();
+        for name in fields {
+            if let Some(field_id) = fields_ids_map.id(&name) {
+                fields_ids.insert(field_id);
+            }
+        }
+
+        Ok(fields_ids)
+    }
+
+    /* faceted documents ids */
+
+    /// Returns the user defined faceted fields names.
+    ///
+    /// The user faceted fields are the union of all the filterable, sortable, distinct, and Asc/Desc fields.
+    pub fn user_defined_faceted_fields(&self, rtxn: &RoTxn) -> Result<HashSet<String>> {
+        let filterable_fields = self.filterable_fields(rtxn)?;
+        let sortable_fields = self.sortable_fields(rtxn)?;
+        let distinct_fields = self.distinct_fields(rtxn)?;
+        let asc_desc_fields =
+            self.criteria(rtxn)?.into_iter().filter_map(|criterion| match criterion {
+                Criterion::Asc(field) | Criterion::Desc(field) => Some(field),
+                _otherwise => None,
+            });
+
+        let mut faceted_fields = filterable_fields;
+        faceted_fields.extend(sortable_fields);
+        faceted_fields.extend(asc_desc_fields);
+        faceted_fields.extend(distinct_fields.into_iter().map(String::from));
+
+        Ok(faceted_fields)
+    }
+
+    /// Identical to `user_defined_faceted_fields`, but returns ids instead.
+    pub fn user_defined_faceted_fields_ids(&self, rtxn: &RoTxn) -> Result<HashSet<FieldId>> {
+        let fields = self.faceted_fields(rtxn)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+
+        let mut fields_ids = HashSet::new();
         for name in fields.into_iter() {
             if let Some(field_id) = fields_ids_map.id(&name) {
                 fields_ids.insert(field_id);
@@ -781,6 +1712,160 @@ impl Index {
         }
     }
 
+    /// Writes the documents ids that are faceted with this boolean under this field id.
+    pub(crate) fn put_boolean_faceted_documents_ids(
+        &self,
+        wtxn: &mut RwTxn,
+        field_id: FieldId,
+        value: bool,
+        docids: &RoaringBitmap,
+    ) -> heed::Result<()> {
+        let prefix = if value {
+            main_key::TRUE_FACETED_DOCUMENTS_IDS_PREFIX
+        } else {
+            main_key::FALSE_FACETED_DOCUMENTS_IDS_PREFIX
+        };
+        let mut key = prefix.as_bytes().to_vec();
+        key.extend_from_slice(&field_id.to_be_bytes());
+        self.main.put::<_, ByteSlice, RoaringBitmapCodec>(wtxn, &key, docids)
+    }
+
+    /// Retrieve all the documents ids that are faceted with this boolean under this field id.
+    /// The booleans are not stored as facet strings, `true` and the string `"true"` differ.
+    pub fn boolean_faceted_documents_ids(
+        &self,
+        rtxn: &RoTxn,
+        field_id: FieldId,
+        value: bool,
+    ) -> heed::Result<RoaringBitmap> {
+        let prefix = if value {
+            main_key::TRUE_FACETED_DOCUMENTS_IDS_PREFIX
+        } else {
+            main_key::FALSE_FACETED_DOCUMENTS_IDS_PREFIX
+        };
+        let mut key = prefix.as_bytes().to_vec();
+        key.extend_from_slice(&field_id.to_be_bytes());
+        match self.main.get::<_, ByteSlice, RoaringBitmapCodec>(rtxn, &key)? {
+            Some(docids) => Ok(docids),
+            None => Ok(RoaringBitmap::new()),
+        }
+    }
+
+    /// Writes the documents ids that contain this field without any facet value.
+    pub(crate) fn put_empty_faceted_documents_ids(
+        &self,
+        wtxn: &mut RwTxn,
+        field_id: FieldId,
+        docids: &RoaringBitmap,
+    ) -> heed::Result<()> {
+        let mut buffer =
+            [0u8; main_key::EMPTY_FACETED_DOCUMENTS_IDS_PREFIX.len() + size_of::<FieldId>()];
+        buffer[..main_key::EMPTY_FACETED_DOCUMENTS_IDS_PREFIX.len()]
+            .copy_from_slice(main_key::EMPTY_FACETED_DOCUMENTS_IDS_PREFIX.as_bytes());
+        buffer[main_key::EMPTY_FACETED_DOCUMENTS_IDS_PREFIX.len()..]
+            .copy_from_slice(&field_id.to_be_bytes());
+        self.main.put::<_, ByteSlice, RoaringBitmapCodec>(wtxn, &buffer, docids)
+    }
+
+    /// Retrieve all the documents ids that contain this field without any facet value:
+    /// `null`, an empty string, an empty array or an empty object.
+    pub fn empty_faceted_documents_ids(
+        &self,
+        rtxn: &RoTxn,
+        field_id: FieldId,
+    ) -> heed::Result<RoaringBitmap> {
+        let mut buffer =
+            [0u8; main_key::EMPTY_FACETED_DOCUMENTS_IDS_PREFIX.len() + size_of::<FieldId>()];
+        buffer[..main_key::EMPTY_FACETED_DOCUMENTS_IDS_PREFIX.len()]
+            .copy_from_slice(main_key::EMPTY_FACETED_DOCUMENTS_IDS_PREFIX.as_bytes());
+        buffer[main_key::EMPTY_FACETED_DOCUMENTS_IDS_PREFIX.len()..]
+            .copy_from_slice(&field_id.to_be_bytes());
+        match self.main.get::<_, ByteSlice, RoaringBitmapCodec>(rtxn, &buffer)? {
+            Some(docids) => Ok(docids),
+            None => Ok(RoaringBitmap::new()),
+        }
+    }
+
+    /// Retrieve all the documents ids that have a number, a string or a boolean facet value
+    /// under this field id.
+    pub fn faceted_documents_ids(
+        &self,
+        rtxn: &RoTxn,
+        field_id: FieldId,
+    ) -> heed::Result<RoaringBitmap> {
+        let mut docids = self.number_faceted_documents_ids(rtxn, field_id)?;
+        docids |= self.string_faceted_documents_ids(rtxn, field_id)?;
+        docids |= self.boolean_faceted_documents_ids(rtxn, field_id, false)?;
+        docids |= self.boolean_faceted_documents_ids(rtxn, field_id, true)?;
+        Ok(docids)
+    }
+
+    /// Returns the values of a filterable field with the documents ids that contain each of
+    /// them, lazily read from the facet databases in the order of their keys: the numbers in
+    /// ascending order, then the strings in the order of their lowercased value and finally
+    /// `false` and `true`. A string is returned as written in the first document containing it.
+    /// The soft deleted documents are left out, a value only they contain is not returned.
+    ///
+    /// Returns an error if the field is not filterable and no value if no document contains it.
+    pub fn facet_values_with_docids<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        field: &str,
+    ) -> Result<Box<dyn Iterator<Item = heed::Result<(FacetValue, RoaringBitmap)>> + 't>> {
+        let filterable_fields = self.filterable_fields(rtxn)?;
+        if !crate::is_faceted(field, &filterable_fields) {
+            return Err(UserError::InvalidFacetSearchAttribute {
+                field: field.to_string(),
+                valid_fields: filterable_fields.into_iter().collect(),
+            }
+            .into());
+        }
+
+        let field_id = match self.fields_ids_map(rtxn)?.id(field) {
+            Some(field_id) => field_id,
+            None => return Ok(Box::new(std::iter::empty())),
+        };
+
+        let left = (field_id, 0, f64::MIN, f64::MIN);
+        let right = (field_id, 0, f64::MAX, f64::MAX);
+        let numbers = self.facet_id_f64_docids.range(rtxn, &(left..=right))?.map(|result| {
+            result.map(|((_, _, value, _), docids)| (FacetValue::from(value), docids))
+        });
+
+        // the level 0 of the facet strings is prefixed by the field id and a zero byte.
+        let mut prefix = field_id.to_be_bytes().to_vec();
+        prefix.push(0);
+        let strings = self
+            .facet_id_string_docids
+            .remap_key_type::<ByteSlice>()
+            .prefix_iter(rtxn, &prefix)?
+            .remap_key_type::<FacetStringLevelZeroCodec>()
+            .map(|result| {
+                result.map(|((_, _normalized), (original, docids))| {
+                    (FacetValue::from(original), docids)
+                })
+            });
+
+        let mut booleans = Vec::new();
+        for boolean in [false, true] {
+            let docids = self.boolean_faceted_documents_ids(rtxn, field_id, boolean)?;
+            booleans.push(Ok((FacetValue::from(boolean), docids)));
+        }
+
+        // the facet databases still contain the soft deleted documents.
+        let soft_deleted_documents_ids = self.soft_deleted_documents_ids(rtxn)?;
+        let values =
+            numbers.chain(strings).chain(booleans).filter_map(move |result| match result {
+                Ok((value, mut docids)) => {
+                    docids -= &soft_deleted_documents_ids;
+                    (!docids.is_empty()).then(|| Ok((value, docids)))
+                }
+                Err(error) => Some(Err(error)),
+            });
+
+        Ok(Box::new(values))
+    }
+
     /* distinct field */
 
     pub(crate) fn put_distinct_field(
@@ -799,6 +1884,34 @@ impl Index {
         self.main.delete::<_, Str>(wtxn, main_key::DISTINCT_FIELD_KEY)
     }
 
+    pub(crate) fn put_distinct_fields(
+        &self,
+        wtxn: &mut RwTxn,
+        distinct_fields: &[&str],
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeBincode<&[&str]>>(
+            wtxn,
+            main_key::DISTINCT_FIELDS_KEY,
+            &distinct_fields,
+        )
+    }
+
+    /// Returns the fields the documents are deduplicated on, which is either
+    /// the composite distinct fields or the single distinct field.
+    pub fn distinct_fields<'t>(&self, rtxn: &'t RoTxn) -> heed::Result<Vec<&'t str>> {
+        match self
+            .main
+            .get::<_, Str, SerdeBincode<Vec<&'t str>>>(rtxn, main_key::DISTINCT_FIELDS_KEY)?
+        {
+            Some(fields) => Ok(fields),
+            None => Ok(self.distinct_field(rtxn)?.into_iter().collect()),
+        }
+    }
+
+    pub(crate) fn delete_distinct_fields(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::DISTINCT_FIELDS_KEY)
+    }
+
     /* criteria */
 
     pub(crate) fn put_criteria(
@@ -923,23 +2036,96 @@ impl Index {
 
     /* documents */
 
+    /// Returns `true` if the JSON of the documents is written at indexing time, to be returned
+    /// without converting the obkv stores again. The absence of a value is `false`.
+    pub fn store_documents_json(&self, rtxn: &RoTxn) -> heed::Result<bool> {
+        let key = main_key::STORE_DOCUMENTS_JSON_KEY;
+        Ok(self.main.get::<_, Str, OwnedType<u8>>(rtxn, key)?.map_or(false, |flag| flag != 0))
+    }
+
+    pub(crate) fn put_store_documents_json(
+        &self,
+        wtxn: &mut RwTxn,
+        flag: bool,
+    ) -> heed::Result<()> {
+        let key = main_key::STORE_DOCUMENTS_JSON_KEY;
+        self.main.put::<_, Str, OwnedType<u8>>(wtxn, key, &(flag as u8))
+    }
+
+    pub(crate) fn delete_store_documents_json(&self, wtxn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(wtxn, main_key::STORE_DOCUMENTS_JSON_KEY)
+    }
+
+    /// Returns the JSON of a document as it was sent to be indexed, `None` if the document
+    /// doesn't exist or if the JSON of the documents is not stored. The documents sent as CSV,
+    /// merged with another version of them, given a generated id or indexed before the JSON
+    /// was stored have their JSON converted from the obkv store.
+    pub fn document_json<'t>(&self, rtxn: &'t RoTxn, id: DocumentId) -> Result<Option<&'t [u8]>> {
+        if self.soft_deleted_documents_ids(rtxn)?.contains(id) {
+            return Ok(None);
+        }
+        Ok(self.documents_json.get(rtxn, &BEU32::new(id))?)
+    }
+
     /// Returns a [`Vec`] of the requested documents. Returns an error if a document is missing.
     pub fn documents<'t>(
         &self,
         rtxn: &'t RoTxn,
         ids: impl IntoIterator<Item = DocumentId>,
     ) -> Result<Vec<(DocumentId, obkv::KvReaderU16<'t>)>> {
-        let mut documents = Vec::new();
+        self.iter_documents(rtxn, ids)?.collect()
+    }
 
-        for id in ids {
-            let kv = self
-                .documents
+    /// Returns an iterator that lazily fetches the requested documents, in the order of the ids.
+    /// The iterator yields an error for each id that doesn't correspond to a document.
+    pub fn iter_documents<'t, I>(
+        &self,
+        rtxn: &'t RoTxn,
+        ids: I,
+    ) -> Result<impl Iterator<Item = Result<(DocumentId, obkv::KvReaderU16<'t>)>> + 't>
+    where
+        I: IntoIterator<Item = DocumentId>,
+        I::IntoIter: 't,
+    {
+        let soft_deleted_documents_ids = self.soft_deleted_documents_ids(rtxn)?;
+        let documents = self.documents;
+
+        Ok(ids.into_iter().map(move |id| {
+            let kv = documents
                 .get(rtxn, &BEU32::new(id))?
-                .ok_or_else(|| UserError::UnknownInternalDocumentId { document_id: id })?;
-            documents.push((id, kv));
+                .filter(|_| !soft_deleted_documents_ids.contains(id))
+                .ok_or(UserError::UnknownInternalDocumentId { document_id: id })?;
+            Ok((id, kv))
+        }))
+    }
+
+    /// Returns the internal ids of the given external ids, in the same order,
+    /// `None` for the external ids that don't correspond to any document.
+    pub fn external_to_internal_ids(
+        &self,
+        rtxn: &RoTxn,
+        external_ids: &[&str],
+    ) -> Result<Vec<Option<DocumentId>>> {
+        Ok(self.external_documents_ids(rtxn)?.get_many(external_ids)?)
+    }
+
+    /// Returns the document with the given external id, or `None` if there is no such document.
+    ///
+    /// The external id is resolved through the [`ExternalDocumentsIds`] without building a search.
+    pub fn document_by_external_id<'t>(
+        &self,
+        rtxn: &'t RoTxn,
+        external_id: &str,
+    ) -> Result<Option<(DocumentId, obkv::KvReaderU16<'t>)>> {
+        let id = match self.external_documents_ids(rtxn)?.get(external_id) {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        if self.soft_deleted_documents_ids(rtxn)?.contains(id) {
+            return Ok(None);
         }
 
-        Ok(documents)
+        Ok(self.documents.get(rtxn, &BEU32::new(id))?.map(|obkv| (id, obkv)))
     }
 
     /// Returns an iterator over all the documents in the index.
@@ -947,11 +2133,17 @@ impl Index {
         &self,
         rtxn: &'t RoTxn,
     ) -> Result<impl Iterator<Item = heed::Result<(DocumentId, obkv::KvReaderU16<'t>)>>> {
+        let soft_deleted_documents_ids = self.soft_deleted_documents_ids(rtxn)?;
         Ok(self
             .documents
             .iter(rtxn)?
             // we cast the BEU32 to a DocumentId
-            .map(|document| document.map(|(id, obkv)| (id.get(), obkv))))
+            .map(|document| document.map(|(id, obkv)| (id.get(), obkv)))
+            // the soft deleted documents are still stored until they are purged
+            .filter(move |document| match document {
+                Ok((id, _)) => !soft_deleted_documents_ids.contains(*id),
+                Err(_) => true,
+            }))
     }
 
     pub fn facets_distribution<'a>(&'a self, rtxn: &'a RoTxn) -> FacetDistribution<'a> {
@@ -1047,20 +2239,78 @@ impl Index {
         Ok(())
     }
 
-    /// List the words on which typo are not allowed
-    pub fn exact_words<'t>(&self, txn: &'t RoTxn) -> Result<Option<fst::Set<Cow<'t, [u8]>>>> {
-        match self.main.get::<_, Str, ByteSlice>(txn, main_key::EXACT_WORDS)? {
-            Some(bytes) => Ok(Some(fst::Set::new(bytes)?.map_data(Cow::Borrowed)?)),
-            None => Ok(None),
-        }
+    /// Returns the typo tolerance overrides, by attribute name.
+    pub fn attributes_typo_tolerance(
+        &self,
+        txn: &RoTxn,
+    ) -> heed::Result<HashMap<String, AttributeTypoTolerance>> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<_>>(txn, main_key::ATTRIBUTES_TYPO_TOLERANCE)?
+            .unwrap_or_default())
     }
 
-    pub(crate) fn put_exact_words<A: AsRef<[u8]>>(
+    pub(crate) fn put_attributes_typo_tolerance(
         &self,
         txn: &mut RwTxn,
-        words: &fst::Set<A>,
-    ) -> Result<()> {
-        self.main.put::<_, Str, ByteSlice>(
+        tolerances: &HashMap<String, AttributeTypoTolerance>,
+    ) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(txn, main_key::ATTRIBUTES_TYPO_TOLERANCE, tolerances)
+    }
+
+    pub(crate) fn delete_attributes_typo_tolerance(&self, txn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(txn, main_key::ATTRIBUTES_TYPO_TOLERANCE)
+    }
+
+    /// Returns how the number of typos allowed on a query word is computed.
+    pub fn typo_budget(&self, txn: &RoTxn) -> heed::Result<TypoBudget> {
+        Ok(self.main.get::<_, Str, SerdeJson<_>>(txn, main_key::TYPO_BUDGET)?.unwrap_or_default())
+    }
+
+    pub(crate) fn put_typo_budget(&self, txn: &mut RwTxn, budget: TypoBudget) -> heed::Result<()> {
+        self.main.put::<_, Str, SerdeJson<_>>(txn, main_key::TYPO_BUDGET, &budget)
+    }
+
+    pub(crate) fn delete_typo_budget(&self, txn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(txn, main_key::TYPO_BUDGET)
+    }
+
+    /// Returns `true` if a query word can match a word with a different first char,
+    /// such a typo always costs two typos. The absence of a value is `true`.
+    pub fn allow_typo_on_first_char(&self, txn: &RoTxn) -> heed::Result<bool> {
+        match self.main.get::<_, Str, OwnedType<u8>>(txn, main_key::ALLOW_TYPO_ON_FIRST_CHAR)? {
+            Some(0) => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    pub(crate) fn put_allow_typo_on_first_char(
+        &self,
+        txn: &mut RwTxn,
+        flag: bool,
+    ) -> heed::Result<()> {
+        let key = main_key::ALLOW_TYPO_ON_FIRST_CHAR;
+        self.main.put::<_, Str, OwnedType<u8>>(txn, key, &(flag as u8))
+    }
+
+    pub(crate) fn delete_allow_typo_on_first_char(&self, txn: &mut RwTxn) -> heed::Result<bool> {
+        self.main.delete::<_, Str>(txn, main_key::ALLOW_TYPO_ON_FIRST_CHAR)
+    }
+
+    /// List the words on which typo are not allowed
+    pub fn exact_words<'t>(&self, txn: &'t RoTxn) -> Result<Option<fst::Set<Cow<'t, [u8]>>>> {
+        match self.main.get::<_, Str, ByteSlice>(txn, main_key::EXACT_WORDS)? {
+            Some(bytes) => Ok(Some(fst::Set::new(bytes)?.map_data(Cow::Borrowed)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn put_exact_words<A: AsRef<[u8]>>(
+        &self,
+        txn: &mut RwTxn,
+        words: &fst::Set<A>,
+    ) -> Result<()> {
+        self.main.put::<_, Str, ByteSlice>(
             txn,
             main_key::EXACT_WORDS,
             words.as_fst().as_bytes(),
@@ -1107,6 +2357,16 @@ impl Index {
         self.main.delete::<_, Str>(txn, main_key::MAX_VALUES_PER_FACET)
     }
 
+    /* disabled databases */
+
+    /// Returns the auxiliary databases that are never built, none by default.
+    pub fn disabled_databases(&self, rtxn: &RoTxn) -> heed::Result<DisabledDatabases> {
+        Ok(self
+            .main
+            .get::<_, Str, SerdeJson<DisabledDatabases>>(rtxn, main_key::DISABLED_DATABASES_KEY)?
+            .unwrap_or_default())
+    }
+
     pub fn pagination_max_total_hits(&self, txn: &RoTxn) -> heed::Result<Option<usize>> {
         self.main.get::<_, Str, OwnedType<usize>>(txn, main_key::PAGINATION_MAX_TOTAL_HITS)
     }
@@ -1124,18 +2384,32 @@ impl Index {
     }
 }
 
+/// Returns the new value of the primary key of a rekeyed document, it stays a number
+/// if it was one and the new external id is written the same way as a number.
+fn rekeyed_value(old_value: Option<&serde_json::Value>, external_id: &str) -> serde_json::Value {
+    match (old_value, external_id.parse::<u64>()) {
+        (Some(serde_json::Value::Number(_)), Ok(number)) if number.to_string() == external_id => {
+            serde_json::Value::from(number)
+        }
+        _ => serde_json::Value::String(external_id.to_string()),
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
+    use std::iter::FromIterator;
     use std::ops::Deref;
 
     use big_s::S;
     use heed::EnvOpenOptions;
-    use maplit::btreemap;
+    use maplit::{btreemap, hashset};
+    use roaring::RoaringBitmap;
     use tempfile::TempDir;
 
+    use crate::facet::FacetValue;
     use crate::index::{DEFAULT_MIN_WORD_LEN_ONE_TYPO, DEFAULT_MIN_WORD_LEN_TWO_TYPOS};
     use crate::update::{self, IndexDocuments, IndexDocumentsConfig, IndexerConfig};
-    use crate::Index;
+    use crate::{Error, Filter, Index, UserError, WarmupDatabase};
 
     pub(crate) struct TempIndex {
         inner: Index,
@@ -1372,4 +2646,620 @@ pub(crate) mod tests {
         let user_defined = index.user_defined_searchable_fields(&rtxn).unwrap().unwrap();
         assert_eq!(user_defined, &["doggo", "name"]);
     }
+
+    #[test]
+    fn iter_documents_lazily() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "name": "kevin" },
+            { "id": 2, "name": "bob" },
+            { "id": 3, "name": "jean" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let name = fields_ids_map.id("name").unwrap();
+
+        // only the documents that are consumed are fetched, the missing ids are never reached.
+        let ids: RoaringBitmap = (0..1_000_000).collect();
+        let mut documents = index.iter_documents(&rtxn, ids).unwrap();
+        for expected in [&b"\"kevin\""[..], b"\"bob\"", b"\"jean\""] {
+            let (_, obkv) = documents.next().unwrap().unwrap();
+            assert_eq!(obkv.get(name), Some(expected));
+        }
+
+        // the next id doesn't correspond to any document.
+        let error = documents.next().unwrap().unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UserError(UserError::UnknownInternalDocumentId { document_id: 3 })
+        ));
+        assert_eq!(documents.count(), 1_000_000 - 4);
+    }
+
+    #[test]
+    fn attributes() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("title"), S("author")]);
+        builder.set_filterable_fields(hashset! { S("genre"), S("price") });
+        builder.set_sortable_fields(hashset! { S("price") });
+        builder.execute(drop).unwrap();
+
+        let content = documents!([
+            { "id": 1, "title": "hamlet", "author": "shakespeare", "price": 12 },
+            { "id": 2, "title": "candide", "author": "voltaire", "price": 8 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let fields_ids_map = index.fields_ids_map(&wtxn).unwrap();
+        let id = |name| fields_ids_map.id(name);
+        let attributes = index.attributes(&wtxn).unwrap();
+
+        assert!(!attributes.searchable.wildcard);
+        assert_eq!(
+            attributes.searchable.attributes,
+            btreemap! { S("author") => id("author"), S("title") => id("title") }
+        );
+        // no document has a genre yet.
+        assert!(!attributes.filterable.wildcard);
+        assert_eq!(
+            attributes.filterable.attributes,
+            btreemap! { S("genre") => None, S("price") => id("price") }
+        );
+        assert_eq!(attributes.sortable.attributes, btreemap! { S("price") => id("price") });
+        // all the fields are displayed.
+        assert!(attributes.displayed.wildcard);
+        assert_eq!(
+            attributes.displayed.attributes,
+            fields_ids_map.iter().map(|(id, name)| (S(name), Some(id))).collect()
+        );
+        assert_eq!(attributes.displayed.attributes.len(), 4);
+
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.reset_searchable_fields();
+        builder.set_displayed_fields(vec![S("title")]);
+        builder.execute(drop).unwrap();
+
+        let attributes = index.attributes(&wtxn).unwrap();
+        assert!(attributes.searchable.wildcard);
+        assert_eq!(attributes.searchable.attributes.len(), 4);
+        assert!(!attributes.displayed.wildcard);
+        assert_eq!(attributes.displayed.attributes, btreemap! { S("title") => id("title") });
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn warmup() {
+        use std::sync::atomic::AtomicBool;
+
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("_geo"), S("genre") });
+        builder.execute(drop).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "kevin bob", "genre": "rock", "_geo": { "lat": 48.86, "lng": 2.35 } },
+            { "id": 1, "name": "jean", "genre": "jazz", "_geo": { "lat": 45.76, "lng": 4.83 } },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let all = [
+            WarmupDatabase::Main,
+            WarmupDatabase::WordDocids,
+            WarmupDatabase::WordPrefixDocids,
+            WarmupDatabase::WordPairProximityDocids,
+            WarmupDatabase::WordPositionDocids,
+            WarmupDatabase::FacetDocids,
+            WarmupDatabase::Geo,
+            WarmupDatabase::Documents,
+        ];
+        let report = index.warmup(&rtxn, &all, None).unwrap();
+        assert!(!report.aborted);
+        assert!(report.pages_touched > 0);
+        assert!(report.bytes_touched >= report.pages_touched);
+
+        // the prefix databases stay empty with so few documents.
+        for database in all.iter().filter(|&&db| db != WarmupDatabase::WordPrefixDocids) {
+            let report = index.warmup(&rtxn, &[*database], None).unwrap();
+            assert!(report.bytes_touched > 0, "nothing read from {:?}", database);
+        }
+
+        // a cancelled warmup reads nothing.
+        let cancelled = AtomicBool::new(true);
+        let report = index.warmup(&rtxn, &all, Some(&cancelled)).unwrap();
+        assert!(report.aborted);
+        assert_eq!(report.bytes_touched, 0);
+    }
+
+    #[test]
+    fn stats() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let rtxn = index.read_txn().unwrap();
+        let stats = index.stats(&rtxn).unwrap();
+        assert_eq!(stats.number_of_documents, 0);
+        assert_eq!(stats.word_docids_entries, 0);
+        assert!(!stats.has_geo_rtree);
+        assert!(stats.on_disk_size > 0);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin bob", "_geo": { "lat": 48.86, "lng": 2.35 } },
+            { "id": 1, "name": "jean", "_geo": { "lat": 45.76, "lng": 4.83 } },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let stats = index.stats(&wtxn).unwrap();
+        assert_eq!(stats.number_of_documents, 2);
+        // id, name, _geo, _geo.lat and _geo.lng.
+        assert_eq!(stats.number_of_fields, 5);
+        let word_docids_entries = stats.word_docids_entries;
+        assert!(word_docids_entries >= 3);
+        assert!(stats.word_pair_proximity_docids_entries > 0);
+        assert_eq!(stats.facet_id_f64_docids_entries, 0);
+        assert!(!stats.has_geo_rtree);
+
+        // the geo points and their facets are only stored once _geo is filterable.
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("_geo") });
+        builder.execute(drop).unwrap();
+
+        let stats = index.stats(&wtxn).unwrap();
+        assert_eq!(stats.number_of_documents, 2);
+        assert_eq!(stats.word_docids_entries, word_docids_entries);
+        assert!(stats.facet_id_f64_docids_entries >= 4);
+        assert_eq!(stats.facet_id_string_docids_entries, 0);
+        assert!(stats.has_geo_rtree);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn delete_documents_by_filter() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("status"), S("_geo") });
+        builder.execute(drop).unwrap();
+
+        let content = documents!([
+            { "id": "paris", "status": "archived", "_geo": { "lat": 48.8566, "lng": 2.3522 } },
+            { "id": "lyon", "status": "active", "_geo": { "lat": 45.7640, "lng": 4.8357 } },
+            { "id": "versailles", "status": "active", "_geo": { "lat": 48.8049, "lng": 2.1204 } },
+            { "id": "nice", "status": "Archived", "_geo": { "lat": 43.7102, "lng": 7.2620 } },
+            { "id": "marseille", "status": "active", "_geo": { "lat": 43.2965, "lng": 5.3698 } },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let external_ids = |rtxn| {
+            let external_ids = index.external_documents_ids(rtxn).unwrap();
+            let mut ids: Vec<_> = external_ids.to_hash_map().into_keys().collect();
+            ids.sort_unstable();
+            ids
+        };
+
+        let filter = Filter::from_str("status = archived").unwrap().unwrap();
+        assert_eq!(index.delete_documents_by_filter(&mut wtxn, &filter).unwrap(), 2);
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 3);
+        assert_eq!(external_ids(&wtxn), vec!["lyon", "marseille", "versailles"]);
+
+        // the documents around Paris, deleting them again doesn't do anything.
+        let filter = Filter::from_str("_geoRadius(48.8566, 2.3522, 30000)").unwrap().unwrap();
+        assert_eq!(index.delete_documents_by_filter(&mut wtxn, &filter).unwrap(), 1);
+        assert_eq!(index.delete_documents_by_filter(&mut wtxn, &filter).unwrap(), 0);
+        assert_eq!(external_ids(&wtxn), vec!["lyon", "marseille"]);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn replace_documents_by_filter() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("source") });
+        builder.execute(drop).unwrap();
+
+        let content = documents!([
+            { "id": 1, "source": "a", "title": "old one" },
+            { "id": 2, "source": "a", "title": "old two" },
+            { "id": 3, "source": "b", "title": "old three" },
+            { "id": 4, "source": "a", "title": "old four" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // the document 2 is deleted then indexed again without its old fields.
+        let content = documents!([
+            { "id": 2, "source": "a" },
+            { "id": 5, "source": "a", "title": "new five" },
+        ]);
+        let mut wtxn = index.write_txn().unwrap();
+        let filter = Filter::from_str("source = a").unwrap().unwrap();
+        let indexing_config = IndexDocumentsConfig {
+            update_method: update::IndexDocumentsMethod::UpdateDocuments,
+            ..Default::default()
+        };
+        let (deleted, result) = index
+            .replace_documents_by_filter(
+                &mut wtxn,
+                &filter,
+                &config,
+                indexing_config,
+                content,
+                drop,
+            )
+            .unwrap();
+        wtxn.commit().unwrap();
+
+        assert_eq!(deleted, 3);
+        assert_eq!(result.indexed_documents, 2);
+        assert_eq!(result.number_of_documents, 3);
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let title = fields_ids_map.id("title").unwrap();
+        let id = fields_ids_map.id("id").unwrap();
+        let docids = filter.evaluate(&rtxn, &index).unwrap();
+        let mut documents: Vec<_> = index
+            .documents(&rtxn, &docids)
+            .unwrap()
+            .into_iter()
+            .map(|(_, obkv)| {
+                let id: serde_json::Value = serde_json::from_slice(obkv.get(id).unwrap()).unwrap();
+                (id.to_string(), obkv.get(title).map(|t| String::from_utf8_lossy(t).into_owned()))
+            })
+            .collect();
+        documents.sort_unstable();
+        assert_eq!(documents, vec![(S("2"), None), (S("5"), Some(S(r#""new five""#)))]);
+
+        let external_ids = index.external_documents_ids(&rtxn).unwrap();
+        assert!(external_ids.get("3").is_some());
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 3);
+    }
+
+    #[test]
+    fn document_by_external_id() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": "kevin", "age": 21 },
+            { "id": "bob", "age": 34 },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let age = index.fields_ids_map(&wtxn).unwrap().id("age").unwrap();
+        let (id, obkv) = index.document_by_external_id(&wtxn, "bob").unwrap().unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(obkv.get(age), Some(&b"34"[..]));
+        assert!(index.document_by_external_id(&wtxn, "jean").unwrap().is_none());
+
+        // the soft deleted documents are still stored but can't be fetched.
+        let docids: RoaringBitmap = std::iter::once(0).collect();
+        index.soft_delete_documents(&mut wtxn, &docids).unwrap();
+        assert!(index.documents.get(&wtxn, &crate::BEU32::new(0)).unwrap().is_some());
+        assert!(index.document_by_external_id(&wtxn, "kevin").unwrap().is_none());
+        assert!(index.document_by_external_id(&wtxn, "bob").unwrap().is_some());
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn facet_values_with_docids() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("value"), S("missing") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "value": "Blue", "name": "kevin" },
+            { "id": 1, "value": ["red", 12] },
+            { "id": 2, "value": "blue " },
+            { "id": 3, "value": [1.5, true, "red"] },
+            { "id": 4, "value": 12 },
+            { "id": 5 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let values: Vec<_> = index
+            .facet_values_with_docids(&rtxn, "value")
+            .unwrap()
+            .map(|result| {
+                let (value, docids) = result.unwrap();
+                (value, docids.into_iter().collect::<Vec<_>>())
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (FacetValue::from(1.5), vec![3]),
+                (FacetValue::from(12.0), vec![1, 4]),
+                (FacetValue::from("Blue"), vec![0, 2]),
+                (FacetValue::from("red"), vec![1, 3]),
+                (FacetValue::from(true), vec![3]),
+            ]
+        );
+
+        assert_eq!(index.facet_values_with_docids(&rtxn, "missing").unwrap().count(), 0);
+
+        // the soft deleted documents are not returned.
+        drop(rtxn);
+        let mut wtxn = index.write_txn().unwrap();
+        index.soft_delete_documents(&mut wtxn, &RoaringBitmap::from_iter([1, 3])).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let values: Vec<_> = index
+            .facet_values_with_docids(&rtxn, "value")
+            .unwrap()
+            .map(|result| {
+                let (value, docids) = result.unwrap();
+                (value, docids.into_iter().collect::<Vec<_>>())
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![(FacetValue::from(12.0), vec![4]), (FacetValue::from("Blue"), vec![0, 2])]
+        );
+
+        let error = index.facet_values_with_docids(&rtxn, "name").err().unwrap();
+        assert!(matches!(
+            error,
+            Error::UserError(UserError::InvalidFacetSearchAttribute { ref field, .. })
+                if field == "name"
+        ));
+    }
+
+    #[test]
+    fn reindex_from_stored_documents() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "name": "kevin", "city": "paris" },
+            { "id": 2, "name": "bob", "city": "lyon" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // the searchable fields are changed without reindexing the documents.
+        let fields_ids_map = index.fields_ids_map(&wtxn).unwrap();
+        index
+            .put_all_searchable_fields_from_fields_ids_map(&mut wtxn, &["name"], &fields_ids_map)
+            .unwrap();
+        let mut search = crate::Search::new(&wtxn, &index);
+        search.query("paris");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![0]);
+
+        let reindexed = index.reindex_from_stored(&mut wtxn, &config).unwrap();
+        assert_eq!(reindexed, 2);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 2);
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.query("paris");
+        assert!(search.execute().unwrap().documents_ids.is_empty());
+        search.query("bob");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![1]);
+    }
+
+    fn index_with_people() -> TempIndex {
+        let index = TempIndex::new();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": "kevin", "name": "kevin" },
+            { "id": "bob", "name": "bob" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+        index
+    }
+
+    #[test]
+    fn rekey_document() {
+        let index = index_with_people();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let docid = index.rekey_document(&mut wtxn, "bob", "robert").unwrap();
+        assert_eq!(docid, 1);
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.document_by_external_id(&rtxn, "bob").unwrap().is_none());
+        let id = index.fields_ids_map(&rtxn).unwrap().id("id").unwrap();
+        let (docid, obkv) = index.document_by_external_id(&rtxn, "robert").unwrap().unwrap();
+        assert_eq!(docid, 1);
+        assert_eq!(obkv.get(id), Some(&br#""robert""#[..]));
+
+        // the document keeps its indexed data.
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.query("bob");
+        assert_eq!(search.execute().unwrap().documents_ids, vec![1]);
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 2);
+    }
+
+    #[test]
+    fn rekey_document_json() {
+        let index = index_with_people();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_store_documents_json(true);
+        builder.execute(|_| ()).unwrap();
+        index.rekey_document(&mut wtxn, "bob", "robert").unwrap();
+        wtxn.commit().unwrap();
+
+        // the JSON keeps the order of the fields of the obkv store.
+        let rtxn = index.read_txn().unwrap();
+        let json = index.document_json(&rtxn, 1).unwrap().unwrap();
+        assert_eq!(json, br#"{"id":"robert","name":"bob"}"#);
+    }
+
+    #[test]
+    fn rekey_document_to_an_existing_id() {
+        let index = index_with_people();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let error = index.rekey_document(&mut wtxn, "bob", "kevin").unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UserError(UserError::DocumentIdAlreadyUsed { ref document_id })
+                if document_id == "kevin"
+        ));
+
+        let external_ids = index.external_documents_ids(&wtxn).unwrap();
+        assert_eq!(external_ids.get("bob"), Some(1));
+        assert_eq!(external_ids.get("kevin"), Some(0));
+    }
+
+    #[test]
+    fn rekey_unknown_document() {
+        let index = index_with_people();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let error = index.rekey_document(&mut wtxn, "jean", "john").unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UserError(UserError::UnknownExternalDocumentId { ref document_id })
+                if document_id == "jean"
+        ));
+        assert!(index.external_documents_ids(&wtxn).unwrap().get("john").is_none());
+    }
+
+    #[test]
+    fn documents_words_count() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let add_documents = |index: &Index, content| {
+            let mut wtxn = index.write_txn().unwrap();
+            let indexing_config = IndexDocumentsConfig::default();
+            let mut builder =
+                IndexDocuments::new(&mut wtxn, index, &config, indexing_config, |_| ()).unwrap();
+            builder.add_documents(content).unwrap();
+            builder.execute().unwrap();
+            wtxn.commit().unwrap();
+        };
+        // the number of words positions of the documents in the database.
+        let positions_count = |rtxn: &heed::RoTxn, docid: Option<crate::DocumentId>| -> u64 {
+            index
+                .docid_word_positions
+                .iter(rtxn)
+                .unwrap()
+                .map(|result| result.unwrap())
+                .filter(|((id, _), _)| docid.map_or(true, |docid| *id == docid))
+                .map(|(_, positions)| positions.len())
+                .sum()
+        };
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.documents_words_count(&rtxn).unwrap(), None);
+        drop(rtxn);
+
+        add_documents(
+            &index,
+            documents!([
+                { "id": 0, "name": "hello world" },
+                { "id": 1, "name": "hello" },
+                { "id": 2, "name": "the quick brown fox" },
+            ]),
+        );
+        let rtxn = index.read_txn().unwrap();
+        let count = positions_count(&rtxn, None);
+        assert!(count > 0);
+        assert_eq!(index.documents_words_count(&rtxn).unwrap(), Some(count));
+        drop(rtxn);
+
+        // the words of the replaced documents are not counted anymore.
+        add_documents(&index, documents!([{ "id": 1, "name": "hello there again" }]));
+        let rtxn = index.read_txn().unwrap();
+        let count = positions_count(&rtxn, None);
+        assert_eq!(index.documents_words_count(&rtxn).unwrap(), Some(count));
+        drop(rtxn);
+
+        // the soft deleted documents are still in the database but not counted.
+        let mut wtxn = index.write_txn().unwrap();
+        let deleted = positions_count(&wtxn, Some(2));
+        let mut builder = update::DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.soft_deletion(true);
+        builder.delete_external_id("2");
+        builder.execute().unwrap();
+        assert_eq!(index.documents_words_count(&wtxn).unwrap(), Some(count - deleted));
+
+        // and they are not removed twice once purged.
+        let mut builder = update::DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("0");
+        builder.execute().unwrap();
+        let count = positions_count(&wtxn, None);
+        assert_eq!(index.documents_words_count(&wtxn).unwrap(), Some(count));
+
+        update::ClearDocuments::new(&mut wtxn, &index).execute().unwrap();
+        assert_eq!(index.documents_words_count(&wtxn).unwrap(), Some(0));
+    }
 }
@@ -11,6 +11,7 @@ pub mod heed_codec;
 pub mod index;
 pub mod proximity;
 mod search;
+mod segmentation;
 pub mod update;
 
 use std::collections::{BTreeMap, HashMap};
@@ -23,7 +24,7 @@ pub use grenad::CompressionType;
 use serde_json::{Map, Value};
 pub use {charabia as tokenizer, heed};
 
-pub use self::asc_desc::{AscDesc, AscDescError, Member, SortError};
+pub use self::asc_desc::{AscDesc, AscDescError, Member, MissingValues, SortError};
 pub use self::criterion::{default_criteria, Criterion, CriterionError};
 pub use self::error::{
     Error, FieldIdMapMissingEntry, InternalError, SerializationError, UserError,
@@ -35,12 +36,19 @@ pub use self::heed_codec::{
     CboRoaringBitmapLenCodec, FieldIdWordCountCodec, ObkvCodec, RoaringBitmapCodec,
     RoaringBitmapLenCodec, StrBEU32Codec, StrStrU8Codec,
 };
-pub use self::index::Index;
+pub use self::index::{
+    AttributeTypoTolerance, AttributesSet, DisabledDatabases, GeoDistance, Index, IndexAttributes,
+    IndexStats, LongWords, MaxWordLength, TypoBudget, WarmupDatabase, WarmupReport,
+};
 pub use self::search::{
-    FacetDistribution, Filter, FormatOptions, MatchBounds, MatcherBuilder, MatchingWord,
-    MatchingWords, Search, SearchResult, DEFAULT_VALUES_PER_FACET,
+    multi_search, reciprocal_rank_fusion, CropStrategy, FacetDistribution, FacetSearch, FacetStats,
+    Filter, FormatOptions, MatchBounds, MatcherBuilder, MatchingWord, MatchingWords,
+    MultiSearchHit, QueryTreeNode, SampledFacetDistribution, Search, SearchResult,
+    TermsMatchingStrategy, DEFAULT_VALUES_PER_FACET,
 };
 
+pub(crate) use self::segmentation::Segmentation;
+
 pub type Result<T> = std::result::Result<T, error::Error>;
 
 pub type FastMap4<K, V> = HashMap<K, V, BuildHasherDefault<FxHasher32>>;
@@ -98,6 +106,29 @@ pub fn obkv_to_json(
         .collect()
 }
 
+/// Writes the JSON object of all the fields of a raw obkv store, the values are
+/// copied as they are stored without being parsed.
+pub fn obkv_to_json_bytes(
+    fields_ids_map: &FieldsIdsMap,
+    obkv: obkv::KvReaderU16,
+) -> Result<Vec<u8>> {
+    let mut json = vec![b'{'];
+    for (i, (id, value)) in obkv.iter().enumerate() {
+        let name = fields_ids_map.name(id).ok_or(error::FieldIdMapMissingEntry::FieldId {
+            field_id: id,
+            process: "obkv_to_json_bytes",
+        })?;
+        if i != 0 {
+            json.push(b',');
+        }
+        serde_json::to_writer(&mut json, name).map_err(error::InternalError::SerdeJson)?;
+        json.push(b':');
+        json.extend_from_slice(value);
+    }
+    json.push(b'}');
+    Ok(json)
+}
+
 /// Transform a JSON value into a string that can be indexed.
 pub fn json_to_string(value: &Value) -> Option<String> {
     fn inner(value: &Value, output: &mut String) -> bool {
@@ -175,6 +206,28 @@ pub fn distance_between_two_points(a: &[f64; 2], b: &[f64; 2]) -> f64 {
     a.haversine_distance_to(&b).meters()
 }
 
+/// The mean radius of the earth in meters.
+pub(crate) const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Return the approximate distance between two points in meters, computed on the
+/// equirectangular projection of the points around their mean latitude.
+///
+/// It is cheaper than [`distance_between_two_points`] and close to it on short distances,
+/// it gets less accurate with the distance and near the poles.
+pub fn planar_distance_between_two_points(a: &[f64; 2], b: &[f64; 2]) -> f64 {
+    let [a_lat, a_lng] = a.map(f64::to_radians);
+    let [b_lat, b_lng] = b.map(f64::to_radians);
+    // the longitudes are compared on the shortest side of the antimeridian.
+    let mut delta_lng = (b_lng - a_lng).abs();
+    if delta_lng > std::f64::consts::PI {
+        delta_lng = 2.0 * std::f64::consts::PI - delta_lng;
+    }
+    let x = delta_lng * ((a_lat + b_lat) / 2.0).cos();
+    let y = b_lat - a_lat;
+
+    EARTH_RADIUS * (x * x + y * y).sqrt()
+}
+
 /// Convert a point expressed in terms of latitude and longitude to a point in the
 /// cartesian coordinate expressed in terms of x, y and z.
 pub fn lat_lng_to_xyz(coord: &[f64; 2]) -> [f64; 3] {
@@ -9,7 +9,7 @@ use super::{Criterion, CriterionParameters, CriterionResult};
 use crate::search::criteria::{resolve_query_tree, CriteriaBuilder};
 use crate::search::facet::{FacetNumberIter, FacetStringIter};
 use crate::search::query_tree::Operation;
-use crate::{FieldId, Index, Result};
+use crate::{FieldId, Index, MissingValues, Result};
 
 /// Threshold on the number of candidates that will make
 /// the system to choose between one algorithm or another.
@@ -21,6 +21,7 @@ pub struct AscDesc<'t> {
     field_name: String,
     field_id: Option<FieldId>,
     is_ascending: bool,
+    missing_values: MissingValues,
     query_tree: Option<Operation>,
     candidates: Box<dyn Iterator<Item = heed::Result<RoaringBitmap>> + 't>,
     allowed_candidates: RoaringBitmap,
@@ -58,11 +59,7 @@ impl<'t> AscDesc<'t> {
         let fields_ids_map = index.fields_ids_map(rtxn)?;
         let field_id = fields_ids_map.id(&field_name);
         let faceted_candidates = match field_id {
-            Some(field_id) => {
-                let number_faceted = index.number_faceted_documents_ids(rtxn, field_id)?;
-                let string_faceted = index.string_faceted_documents_ids(rtxn, field_id)?;
-                number_faceted | string_faceted
-            }
+            Some(field_id) => index.faceted_documents_ids(rtxn, field_id)?,
             None => RoaringBitmap::default(),
         };
 
@@ -72,6 +69,7 @@ impl<'t> AscDesc<'t> {
             field_name,
             field_id,
             is_ascending,
+            missing_values: MissingValues::default(),
             query_tree: None,
             candidates: Box::new(std::iter::empty()),
             allowed_candidates: RoaringBitmap::new(),
@@ -80,6 +78,12 @@ impl<'t> AscDesc<'t> {
             parent,
         })
     }
+
+    /// Places the documents without any value for the field before or after the other ones.
+    pub fn with_missing_values(mut self, missing_values: MissingValues) -> Self {
+        self.missing_values = missing_values;
+        self
+    }
 }
 
 impl<'t> Criterion for AscDesc<'t> {
@@ -145,6 +149,20 @@ impl<'t> Criterion for AscDesc<'t> {
                             )?,
                             None => Box::new(std::iter::empty()),
                         };
+
+                        // the documents without any value are returned at the end by default.
+                        if self.missing_values == MissingValues::First {
+                            let missing = &self.allowed_candidates - &self.faceted_candidates;
+                            if !missing.is_empty() {
+                                self.allowed_candidates -= &missing;
+                                return Ok(Some(CriterionResult {
+                                    query_tree: self.query_tree.clone(),
+                                    candidates: Some(missing),
+                                    filtered_candidates: None,
+                                    bucket_candidates: Some(take(&mut self.bucket_candidates)),
+                                }));
+                            }
+                        }
                     }
                     None => return Ok(None),
                 },
@@ -182,9 +200,17 @@ fn facet_ordered<'t>(
             is_ascending,
             candidates.clone(),
         )?;
-        let string_iter =
-            iterative_facet_string_ordered_iter(index, rtxn, field_id, is_ascending, candidates)?;
-        Ok(Box::new(number_iter.chain(string_iter).map(Ok)) as Box<dyn Iterator<Item = _>>)
+        let string_iter = iterative_facet_string_ordered_iter(
+            index,
+            rtxn,
+            field_id,
+            is_ascending,
+            candidates.clone(),
+        )?;
+        let boolean_iter =
+            facet_boolean_ordered_iter(index, rtxn, field_id, is_ascending, candidates)?;
+        Ok(Box::new(number_iter.chain(string_iter).chain(boolean_iter).map(Ok))
+            as Box<dyn Iterator<Item = _>>)
     } else {
         let facet_number_fn = if is_ascending {
             FacetNumberIter::new_reducing
@@ -199,11 +225,34 @@ fn facet_ordered<'t>(
         } else {
             FacetStringIter::new_reverse_reducing
         };
-        let string_iter = facet_string_fn(rtxn, index, field_id, candidates)?
+        let string_iter = facet_string_fn(rtxn, index, field_id, candidates.clone())?
             .map(|res| res.map(|(_, _, docids)| docids));
 
-        Ok(Box::new(number_iter.chain(string_iter)))
+        let boolean_iter =
+            facet_boolean_ordered_iter(index, rtxn, field_id, is_ascending, candidates)?.map(Ok);
+
+        Ok(Box::new(number_iter.chain(string_iter).chain(boolean_iter)))
+    }
+}
+
+/// Returns the candidates faceted with `false` then with `true`, or the reverse when descending.
+/// The booleans are ordered after the numbers and the strings.
+fn facet_boolean_ordered_iter(
+    index: &Index,
+    rtxn: &heed::RoTxn,
+    field_id: FieldId,
+    is_ascending: bool,
+    candidates: RoaringBitmap,
+) -> Result<impl Iterator<Item = RoaringBitmap>> {
+    let booleans = if is_ascending { [false, true] } else { [true, false] };
+    let mut groups = Vec::with_capacity(booleans.len());
+    for boolean in booleans {
+        let docids = index.boolean_faceted_documents_ids(rtxn, field_id, boolean)? & &candidates;
+        if !docids.is_empty() {
+            groups.push(docids);
+        }
     }
+    Ok(groups.into_iter())
 }
 
 /// Fetch the whole list of candidates facet number values one by one and order them by it.
@@ -1,4 +1,4 @@
-use std::cmp::{self, Ordering};
+use std::cmp::{self, Ordering, Reverse};
 use std::collections::binary_heap::PeekMut;
 use std::collections::{btree_map, BTreeMap, BinaryHeap, HashMap};
 use std::iter::Peekable;
@@ -6,11 +6,14 @@ use std::mem::take;
 
 use roaring::RoaringBitmap;
 
-use super::{resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult};
+use super::{
+    index_word_derivations, query_tree_words, resolve_query_tree, Context, Criterion,
+    CriterionParameters, CriterionResult,
+};
 use crate::search::criteria::Query;
 use crate::search::query_tree::{Operation, QueryKind};
-use crate::search::{build_dfa, word_derivations, WordDerivationsCache};
-use crate::Result;
+use crate::search::{DerivationsDfa, WordDerivationsCache};
+use crate::{relative_from_absolute_position, FieldId, Result};
 
 /// To be able to divide integers by the number of words in the query
 /// we want to find a multiplier that allow us to divide by any number between 1 and 10.
@@ -23,6 +26,11 @@ const CANDIDATES_THRESHOLD: u64 = 500;
 
 type FlattenedQueryTree = Vec<Vec<Vec<Query>>>;
 
+/// Ranks the documents by the attributes the query words are found in and by their positions.
+///
+/// When the searchable attributes have weights, the documents are first split by the best
+/// weight of the attributes they match, the attributes are then ranked by their order and
+/// the positions of the words, inside of each of these buckets.
 pub struct Attribute<'t> {
     ctx: &'t dyn Context<'t>,
     state: Option<(Operation, FlattenedQueryTree, RoaringBitmap)>,
@@ -30,6 +38,8 @@ pub struct Attribute<'t> {
     parent: Box<dyn Criterion + 't>,
     linear_buckets: Option<btree_map::IntoIter<u64, RoaringBitmap>>,
     set_buckets: Option<BinaryHeap<Branch<'t>>>,
+    /// The candidates of the next attributes weights, in reverse order of relevancy.
+    weight_buckets: Vec<RoaringBitmap>,
 }
 
 impl<'t> Attribute<'t> {
@@ -41,6 +51,7 @@ impl<'t> Attribute<'t> {
             parent,
             linear_buckets: None,
             set_buckets: None,
+            weight_buckets: Vec::new(),
         }
     }
 }
@@ -55,7 +66,17 @@ impl<'t> Criterion for Attribute<'t> {
 
         loop {
             match self.state.take() {
-                Some((query_tree, _, allowed_candidates)) if allowed_candidates.is_empty() => {
+                Some((query_tree, flattened_query_tree, allowed_candidates))
+                    if allowed_candidates.is_empty() =>
+                {
+                    // the documents matching attributes of a lower weight are ranked next.
+                    if let Some(candidates) = self.weight_buckets.pop() {
+                        self.state = Some((query_tree, flattened_query_tree, candidates));
+                        self.linear_buckets = None;
+                        self.set_buckets = None;
+                        continue;
+                    }
+
                     return Ok(Some(CriterionResult {
                         query_tree: Some(query_tree),
                         candidates: Some(RoaringBitmap::new()),
@@ -80,12 +101,9 @@ impl<'t> Criterion for Attribute<'t> {
                         match linear_buckets.next() {
                             Some((_score, candidates)) => candidates,
                             None => {
-                                return Ok(Some(CriterionResult {
-                                    query_tree: Some(query_tree),
-                                    candidates: Some(RoaringBitmap::new()),
-                                    filtered_candidates: None,
-                                    bucket_candidates: Some(take(&mut self.bucket_candidates)),
-                                }));
+                                let candidates = RoaringBitmap::new();
+                                self.state = Some((query_tree, flattened_query_tree, candidates));
+                                continue;
                             }
                         }
                     } else {
@@ -105,12 +123,9 @@ impl<'t> Criterion for Attribute<'t> {
                         match set_compute_candidates(&mut set_buckets, &allowed_candidates)? {
                             Some((_score, candidates)) => candidates,
                             None => {
-                                return Ok(Some(CriterionResult {
-                                    query_tree: Some(query_tree),
-                                    candidates: Some(RoaringBitmap::new()),
-                                    filtered_candidates: None,
-                                    bucket_candidates: Some(take(&mut self.bucket_candidates)),
-                                }));
+                                let candidates = RoaringBitmap::new();
+                                self.state = Some((query_tree, flattened_query_tree, candidates));
+                                continue;
                             }
                         }
                     };
@@ -153,6 +168,21 @@ impl<'t> Criterion for Attribute<'t> {
                             None => self.bucket_candidates |= &candidates,
                         }
 
+                        let weights = self.ctx.searchable_fields_weights()?;
+                        if !weights.is_empty() {
+                            let mut buckets = weight_buckets(
+                                self.ctx,
+                                &query_tree,
+                                candidates,
+                                &weights,
+                                params.wdcache,
+                            )?;
+                            buckets.reverse();
+                            candidates = buckets.pop().unwrap_or_default();
+                            self.weight_buckets = buckets;
+                            self.set_buckets = None;
+                        }
+
                         self.state = Some((query_tree, flattened_query_tree, candidates));
                         self.linear_buckets = None;
                     }
@@ -176,6 +206,37 @@ impl<'t> Criterion for Attribute<'t> {
     }
 }
 
+/// Splits the candidates by the best weight of the attributes where one of the query words
+/// is found, the candidates without any weighted matched attribute are in the last bucket.
+fn weight_buckets(
+    ctx: &dyn Context,
+    query_tree: &Operation,
+    mut candidates: RoaringBitmap,
+    weights: &HashMap<FieldId, u16>,
+    wdcache: &mut WordDerivationsCache,
+) -> Result<Vec<RoaringBitmap>> {
+    let mut weights_docids: BTreeMap<Reverse<u16>, RoaringBitmap> = BTreeMap::new();
+    for word in query_tree_words(ctx, query_tree, wdcache)? {
+        for result in ctx.word_position_iterator(&word, false)? {
+            let ((_, position), docids) = result?;
+            let (field_id, _) = relative_from_absolute_position(position);
+            if let Some(weight) = weights.get(&field_id) {
+                *weights_docids.entry(Reverse(*weight)).or_default() |= docids;
+            }
+        }
+    }
+
+    let mut buckets = Vec::with_capacity(weights_docids.len() + 1);
+    for (_weight, docids) in weights_docids {
+        let bucket = &candidates & docids;
+        candidates -= &bucket;
+        buckets.push(bucket);
+    }
+    buckets.push(candidates);
+
+    Ok(buckets)
+}
+
 /// QueryPositionIterator is an Iterator over positions of a Query,
 /// It contains iterators over words positions.
 struct QueryPositionIterator<'t> {
@@ -199,8 +260,7 @@ impl<'t> QueryPositionIterator<'t> {
                         let iter = ctx.word_position_iterator(word, in_prefix_cache)?;
                         inner.push(iter.peekable());
                     } else {
-                        for (word, _) in word_derivations(&word, true, 0, ctx.words_fst(), wdcache)?
-                        {
+                        for (word, _) in index_word_derivations(ctx, &word, true, 0, wdcache)? {
                             let iter = ctx.word_position_iterator(&word, in_prefix_cache)?;
                             inner.push(iter.peekable());
                         }
@@ -208,7 +268,7 @@ impl<'t> QueryPositionIterator<'t> {
                 }
                 QueryKind::Tolerant { typo, word } => {
                     for (word, _) in
-                        word_derivations(&word, query.prefix, *typo, ctx.words_fst(), wdcache)?
+                        index_word_derivations(ctx, &word, query.prefix, *typo, wdcache)?
                     {
                         let iter = ctx.word_position_iterator(&word, in_prefix_cache)?;
                         inner.push(iter.peekable());
@@ -457,6 +517,7 @@ fn initialize_linear_buckets(
 ) -> Result<BTreeMap<u64, RoaringBitmap>> {
     fn compute_candidate_rank(
         branches: &FlattenedQueryTree,
+        first_char_typo: bool,
         words_positions: HashMap<String, RoaringBitmap>,
     ) -> u64 {
         let mut min_rank = u64::max_value();
@@ -470,7 +531,7 @@ fn initialize_linear_buckets(
                     let current_position = match kind {
                         QueryKind::Exact { word, .. } => {
                             if *prefix {
-                                word_derivations(word, true, 0, &words_positions)
+                                word_derivations(word, true, 0, first_char_typo, &words_positions)
                                     .flat_map(|positions| positions.iter().next())
                                     .min()
                             } else {
@@ -480,11 +541,15 @@ fn initialize_linear_buckets(
                                     .flatten()
                             }
                         }
-                        QueryKind::Tolerant { typo, word } => {
-                            word_derivations(word, *prefix, *typo, &words_positions)
-                                .flat_map(|positions| positions.iter().next())
-                                .min()
-                        }
+                        QueryKind::Tolerant { typo, word } => word_derivations(
+                            word,
+                            *prefix,
+                            *typo,
+                            first_char_typo,
+                            &words_positions,
+                        )
+                        .flat_map(|positions| positions.iter().next())
+                        .min(),
                     };
 
                     match (position, current_position) {
@@ -523,22 +588,20 @@ fn initialize_linear_buckets(
         word: &str,
         is_prefix: bool,
         max_typo: u8,
+        first_char_typo: bool,
         words_positions: &'a HashMap<String, RoaringBitmap>,
     ) -> impl Iterator<Item = &'a RoaringBitmap> {
-        let dfa = build_dfa(word, max_typo, is_prefix);
+        let dfa = DerivationsDfa::new(word, max_typo, is_prefix, first_char_typo);
         words_positions.iter().filter_map(move |(document_word, positions)| {
-            use levenshtein_automata::Distance;
-            match dfa.eval(document_word) {
-                Distance::Exact(_) => Some(positions),
-                Distance::AtLeast(_) => None,
-            }
+            dfa.eval(document_word).map(|_| positions)
         })
     }
 
+    let first_char_typo = ctx.attributes_typo_tolerance().allow_typo_on_first_char();
     let mut candidates = BTreeMap::new();
     for docid in allowed_candidates {
         let words_positions = ctx.docid_words_positions(docid)?;
-        let rank = compute_candidate_rank(branches, words_positions);
+        let rank = compute_candidate_rank(branches, first_char_typo, words_positions);
         candidates.entry(rank).or_insert_with(RoaringBitmap::new).insert(docid);
     }
 
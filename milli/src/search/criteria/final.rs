@@ -1,10 +1,16 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
 use log::debug;
 use roaring::RoaringBitmap;
 
+use super::profiling::CriteriaProfile;
 use super::{resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult};
 use crate::search::query_tree::Operation;
 use crate::search::WordDerivationsCache;
-use crate::Result;
+use crate::{DocumentId, Result};
 
 /// The result of a call to the fetcher.
 #[derive(Debug, Clone, PartialEq)]
@@ -22,6 +28,8 @@ pub struct Final<'t> {
     parent: Box<dyn Criterion + 't>,
     wdcache: WordDerivationsCache,
     returned_candidates: RoaringBitmap,
+    geo_distances: HashMap<DocumentId, f64>,
+    profile: Option<Rc<RefCell<CriteriaProfile>>>,
 }
 
 impl<'t> Final<'t> {
@@ -31,9 +39,31 @@ impl<'t> Final<'t> {
             parent,
             wdcache: WordDerivationsCache::new(),
             returned_candidates: RoaringBitmap::new(),
+            geo_distances: HashMap::new(),
+            profile: None,
+        }
+    }
+
+    pub(crate) fn with_profile(mut self, profile: Option<Rc<RefCell<CriteriaProfile>>>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Returns the time spent in each criterion, in the order of the criteria chain,
+    /// this is empty when the criteria were not built with profiling enabled.
+    pub fn criteria_timings(&self) -> Vec<(String, Duration)> {
+        match &self.profile {
+            Some(profile) => profile.borrow().timings().to_vec(),
+            None => Vec::new(),
         }
     }
 
+    /// Returns the distance, in meters, between the document and the geo sort origin,
+    /// this is only available for the documents returned by a geo sort criterion.
+    pub fn geo_distance(&self, docid: DocumentId) -> Option<f64> {
+        self.geo_distances.get(&docid).copied()
+    }
+
     #[logging_timer::time("Final::{}")]
     pub fn next(&mut self, excluded_candidates: &RoaringBitmap) -> Result<Option<FinalResult>> {
         debug!("Final iteration");
@@ -42,6 +72,7 @@ impl<'t> Final<'t> {
             wdcache: &mut self.wdcache,
             // returned_candidates is merged with excluded_candidates to avoid duplicas
             excluded_candidates: &excluded_candidates,
+            geo_distances: &mut self.geo_distances,
         };
 
         match self.parent.next(&mut criterion_parameters)? {
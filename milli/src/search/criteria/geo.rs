@@ -1,22 +1,28 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::iter;
+use std::mem::take;
+use std::rc::Rc;
 
 use roaring::RoaringBitmap;
 use rstar::RTree;
 
 use super::{Criterion, CriterionParameters, CriterionResult};
 use crate::search::criteria::{resolve_query_tree, CriteriaBuilder};
-use crate::{lat_lng_to_xyz, GeoPoint, Index, Result};
+use crate::{lat_lng_to_xyz, DocumentId, GeoDistance, GeoPoint, Index, Result};
 
 pub struct Geo<'t> {
     index: &'t Index,
     rtxn: &'t heed::RoTxn<'t>,
     ascending: bool,
     parent: Box<dyn Criterion + 't>,
-    candidates: Box<dyn Iterator<Item = RoaringBitmap>>,
+    candidates: Box<dyn Iterator<Item = Vec<(DocumentId, f64)>>>,
     allowed_candidates: RoaringBitmap,
     bucket_candidates: RoaringBitmap,
-    rtree: Option<RTree<GeoPoint>>,
-    point: [f64; 2],
+    rtree: Option<Rc<RTree<GeoPoint>>>,
+    metric: GeoDistance,
+    points: Vec<[f64; 2]>,
+    buckets: Option<Vec<usize>>,
 }
 
 impl<'t> Geo<'t> {
@@ -24,31 +30,35 @@ impl<'t> Geo<'t> {
         index: &'t Index,
         rtxn: &'t heed::RoTxn<'t>,
         parent: Box<dyn Criterion + 't>,
-        point: [f64; 2],
+        points: Vec<[f64; 2]>,
+        buckets: Option<Vec<usize>>,
     ) -> Result<Self> {
-        Self::new(index, rtxn, parent, point, true)
+        Self::new(index, rtxn, parent, points, buckets, true)
     }
 
     pub fn desc(
         index: &'t Index,
         rtxn: &'t heed::RoTxn<'t>,
         parent: Box<dyn Criterion + 't>,
-        point: [f64; 2],
+        points: Vec<[f64; 2]>,
+        buckets: Option<Vec<usize>>,
     ) -> Result<Self> {
-        Self::new(index, rtxn, parent, point, false)
+        Self::new(index, rtxn, parent, points, buckets, false)
     }
 
     fn new(
         index: &'t Index,
         rtxn: &'t heed::RoTxn<'t>,
         parent: Box<dyn Criterion + 't>,
-        point: [f64; 2],
+        points: Vec<[f64; 2]>,
+        buckets: Option<Vec<usize>>,
         ascending: bool,
     ) -> Result<Self> {
         let candidates = Box::new(iter::empty());
-        let allowed_candidates = index.geo_faceted_documents_ids(rtxn)?;
+        let allowed_candidates = RoaringBitmap::new();
         let bucket_candidates = RoaringBitmap::new();
-        let rtree = index.geo_rtree(rtxn)?;
+        let rtree = index.geo_rtree(rtxn)?.map(Rc::new);
+        let metric = index.geo_distance(rtxn)?;
 
         Ok(Self {
             index,
@@ -59,19 +69,38 @@ impl<'t> Geo<'t> {
             allowed_candidates,
             bucket_candidates,
             rtree,
-            point,
+            metric,
+            points,
+            buckets,
         })
     }
 }
 
 impl Criterion for Geo<'_> {
     fn next(&mut self, params: &mut CriterionParameters) -> Result<Option<CriterionResult>> {
+        // remove excluded candidates when next is called, instead of doing it in the loop.
+        self.allowed_candidates -= params.excluded_candidates;
         let rtree = self.rtree.as_ref();
 
         loop {
             match self.candidates.next() {
-                Some(mut candidates) => {
-                    candidates -= params.excluded_candidates;
+                // The documents without a valid `_geo` field are returned in a last bucket.
+                None if !self.allowed_candidates.is_empty() => {
+                    return Ok(Some(CriterionResult {
+                        query_tree: None,
+                        candidates: Some(take(&mut self.allowed_candidates)),
+                        filtered_candidates: None,
+                        bucket_candidates: Some(self.bucket_candidates.clone()),
+                    }));
+                }
+                Some(bucket) => {
+                    let mut candidates = RoaringBitmap::new();
+                    for (docid, distance) in bucket {
+                        if !params.excluded_candidates.contains(docid) {
+                            candidates.insert(docid);
+                            params.geo_distances.insert(docid, distance);
+                        }
+                    }
                     self.allowed_candidates -= &candidates;
                     return Ok(Some(CriterionResult {
                         query_tree: None,
@@ -109,14 +138,29 @@ impl Criterion for Geo<'_> {
                             continue;
                         }
                         self.allowed_candidates = &candidates - params.excluded_candidates;
-                        self.candidates = match rtree {
-                            Some(rtree) => geo_point(
+                        self.candidates = match (rtree, self.points.as_slice()) {
+                            // The most common case, sorting by ascending distance to a single
+                            // point, is streamed to only visit the points that are returned.
+                            (Some(rtree), [point])
+                                if self.ascending
+                                    && self.buckets.is_none()
+                                    && self.metric == GeoDistance::GreatCircle =>
+                            {
+                                Box::new(AscendingGeoPoint::new(
+                                    rtree.clone(),
+                                    self.allowed_candidates.clone(),
+                                    *point,
+                                ))
+                            }
+                            (Some(rtree), _) => geo_point(
                                 rtree,
+                                self.metric,
                                 self.allowed_candidates.clone(),
-                                self.point,
+                                &self.points,
+                                self.buckets.as_deref(),
                                 self.ascending,
                             ),
-                            None => Box::new(std::iter::empty()),
+                            (None, _) => Box::new(iter::empty()),
                         };
                     }
                     None => return Ok(None),
@@ -126,27 +170,476 @@ impl Criterion for Geo<'_> {
     }
 }
 
+/// The number of points that are fetched from the rtree the first time, the number
+/// of points fetched is then doubled every time all of them have been returned.
+const ASCENDING_GEO_POINT_FIRST_FETCH: usize = 64;
+
+/// Lazily returns the candidates ordered by ascending distance to the base point,
+/// every document in its own bucket, along with this distance in meters.
+///
+/// The nearest neighbor iterator borrows the rtree and can't be kept between two calls,
+/// it is instead restarted every time the already fetched points have been returned and
+/// fetches twice as many points. It means that only the closest points are visited when
+/// only the first documents are needed and that the total work stays proportional to the
+/// number of points visited.
+///
+/// The walk stops at the last candidate, e.g. the farthest document of a `_geoRadius` filter,
+/// the points beyond it are never visited.
+///
+/// The descending order can't be streamed like this, the farthest points are only known
+/// once all the points have been visited, it is handled by the [`geo_point`] function like
+/// the planar distances that are not ordered like the points of the rtree.
+struct AscendingGeoPoint {
+    rtree: Rc<RTree<GeoPoint>>,
+    candidates: RoaringBitmap,
+    base_point: [f64; 2],
+    xyz_point: [f64; 3],
+    visited: usize,
+    fetch_size: usize,
+    fetched: VecDeque<(DocumentId, f64)>,
+}
+
+impl AscendingGeoPoint {
+    fn new(rtree: Rc<RTree<GeoPoint>>, candidates: RoaringBitmap, base_point: [f64; 2]) -> Self {
+        AscendingGeoPoint {
+            rtree,
+            candidates,
+            base_point,
+            xyz_point: lat_lng_to_xyz(&base_point),
+            visited: 0,
+            fetch_size: ASCENDING_GEO_POINT_FIRST_FETCH,
+            fetched: VecDeque::new(),
+        }
+    }
+}
+
+impl Iterator for AscendingGeoPoint {
+    type Item = Vec<(DocumentId, f64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.fetched.is_empty() {
+            if self.candidates.is_empty() || self.visited >= self.rtree.size() {
+                return None;
+            }
+
+            let points = self.rtree.nearest_neighbor_iter(&self.xyz_point);
+            for point in points.skip(self.visited).take(self.fetch_size) {
+                self.visited += 1;
+                match finite_distance(GeoDistance::GreatCircle, &self.base_point, point) {
+                    Some(distance) if self.candidates.remove(point.data.0) => {
+                        self.fetched.push_back((point.data.0, distance));
+                        if self.candidates.is_empty() {
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+
+            self.fetch_size = self.fetch_size.saturating_mul(2);
+        }
+
+        self.fetched.pop_front().map(|result| vec![result])
+    }
+}
+
+/// Returns the distance in meters between the base point and the point, `None` if the
+/// distance is not a finite number, points with invalid coordinates must never be returned.
+fn finite_distance(metric: GeoDistance, base_point: &[f64; 2], point: &GeoPoint) -> Option<f64> {
+    let distance = metric.distance(base_point, &point.data.1);
+    if distance.is_finite() {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+/// Returns the buckets of candidates ordered by their distance to the closest of the base
+/// points, along with this distance in meters.
+///
+/// The points of the rtree are visited by their great circle distance to the base points,
+/// the planar distances are not ordered like them and are all computed and sorted instead.
+///
+/// Every document is returned in its own bucket unless a list of strictly increasing
+/// boundaries, in meters, is given. In this case the documents between two boundaries are
+/// returned in the same bucket and the last bucket is implicitly extended to `usize::MAX`.
 fn geo_point(
     rtree: &RTree<GeoPoint>,
+    metric: GeoDistance,
     mut candidates: RoaringBitmap,
-    point: [f64; 2],
+    base_points: &[[f64; 2]],
+    buckets: Option<&[usize]>,
     ascending: bool,
-) -> Box<dyn Iterator<Item = RoaringBitmap>> {
-    let point = lat_lng_to_xyz(&point);
-
+) -> Box<dyn Iterator<Item = Vec<(DocumentId, f64)>>> {
     let mut results = Vec::new();
-    for point in rtree.nearest_neighbor_iter(&point) {
-        if candidates.remove(point.data.0) {
-            results.push(std::iter::once(point.data.0).collect());
-            if candidates.is_empty() {
-                break;
+    match (metric, base_points) {
+        (GeoDistance::Planar, base_points) => {
+            for point in rtree.iter() {
+                let distance = base_points
+                    .iter()
+                    .filter_map(|base_point| finite_distance(metric, base_point, point))
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                match distance {
+                    Some(distance) if candidates.remove(point.data.0) => {
+                        results.push((point.data.0, distance));
+                        if candidates.is_empty() {
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            results.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        }
+        (GeoDistance::GreatCircle, [base_point]) => {
+            let point = lat_lng_to_xyz(base_point);
+            for point in rtree.nearest_neighbor_iter(&point) {
+                match finite_distance(metric, base_point, point) {
+                    Some(distance) if candidates.remove(point.data.0) => {
+                        results.push((point.data.0, distance));
+                        if candidates.is_empty() {
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        (GeoDistance::GreatCircle, base_points) => {
+            // Each iterator returns the points ordered by their distance to one of the base
+            // points, by always advancing the iterator with the closest next point we get the
+            // points ordered by their minimal distance, a document is first seen with its
+            // closest base point so we can ignore its next appearances.
+            let xyz_points: Vec<_> = base_points.iter().map(lat_lng_to_xyz).collect();
+            let mut iters: Vec<_> = base_points
+                .iter()
+                .zip(&xyz_points)
+                .map(|(base_point, xyz_point)| {
+                    rtree
+                        .nearest_neighbor_iter(xyz_point)
+                        .filter_map(move |point| {
+                            finite_distance(metric, base_point, point)
+                                .map(|distance| (distance, point))
+                        })
+                        .peekable()
+                })
+                .collect();
+
+            loop {
+                let closest = iters
+                    .iter_mut()
+                    .enumerate()
+                    .filter_map(|(i, iter)| iter.peek().map(|(distance, _)| (i, *distance)))
+                    .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+                let (distance, point) = match closest.and_then(|(i, _)| iters[i].next()) {
+                    Some(next) => next,
+                    None => break,
+                };
+
+                if candidates.remove(point.data.0) {
+                    results.push((point.data.0, distance));
+                    if candidates.is_empty() {
+                        break;
+                    }
+                }
             }
         }
     }
 
+    let results = match buckets {
+        Some(boundaries) => {
+            let mut buckets = Vec::new();
+            let mut bucket = Vec::new();
+            let mut boundaries = boundaries.iter().peekable();
+            for (docid, distance) in results {
+                while boundaries
+                    .next_if(|&&boundary| distance.round() as usize >= boundary)
+                    .is_some()
+                {
+                    if !bucket.is_empty() {
+                        buckets.push(take(&mut bucket));
+                    }
+                }
+                bucket.push((docid, distance));
+            }
+            if !bucket.is_empty() {
+                buckets.push(bucket);
+            }
+            buckets
+        }
+        None => results.into_iter().map(|result| vec![result]).collect(),
+    };
+
     if ascending {
         Box::new(results.into_iter())
     } else {
         Box::new(results.into_iter().rev())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance_between_two_points;
+    use crate::GeoDistance::{GreatCircle, Planar};
+
+    fn build_rtree(points: &[[f64; 2]]) -> RTree<GeoPoint> {
+        let points = points
+            .iter()
+            .enumerate()
+            .map(|(docid, point)| GeoPoint::new(lat_lng_to_xyz(point), (docid as u32, *point)))
+            .collect();
+        RTree::bulk_load(points)
+    }
+
+    #[test]
+    fn geo_point_buckets_are_disjoint() {
+        let rtree = build_rtree(&[
+            [48.8566, 2.3522],
+            [45.7640, 4.8357],
+            [43.2965, 5.3698],
+            [50.6292, 3.0573],
+            [44.8378, -0.5792],
+            [48.8570, 2.3530],
+        ]);
+        // document 4 is not part of the candidates and must never be returned.
+        let candidates: RoaringBitmap = [0, 1, 2, 3, 5].iter().copied().collect();
+
+        for ascending in [true, false] {
+            let mut union = RoaringBitmap::new();
+            let mut total = 0;
+            let buckets = geo_point(
+                &rtree,
+                GreatCircle,
+                candidates.clone(),
+                &[[48.8566, 2.3522]],
+                None,
+                ascending,
+            );
+            for bucket in buckets {
+                let bucket: RoaringBitmap = bucket.into_iter().map(|(docid, _)| docid).collect();
+                assert!((&union & &bucket).is_empty(), "a document was returned twice");
+                total += bucket.len();
+                union |= bucket;
+            }
+            assert_eq!(union, candidates);
+            assert_eq!(total, candidates.len());
+        }
+    }
+
+    #[test]
+    fn geo_point_multiple_origins() {
+        let rtree = build_rtree(&[
+            // close to Paris
+            [48.8570, 2.3530],
+            // close to Lyon
+            [45.7650, 4.8360],
+            // between Paris and Lyon but closer to Lyon
+            [46.5000, 4.0000],
+            // far from both
+            [43.2965, 5.3698],
+            // a bit further from Paris
+            [48.9000, 2.4000],
+        ]);
+        let candidates: RoaringBitmap = (0..5).collect();
+        let origins = [[48.8566, 2.3522], [45.7640, 4.8357]];
+
+        let buckets: Vec<_> =
+            geo_point(&rtree, GreatCircle, candidates.clone(), &origins, None, true)
+                .flatten()
+                .collect();
+        let docids: Vec<_> = buckets.iter().map(|(docid, _)| *docid).collect();
+        assert_eq!(docids, vec![0, 1, 4, 2, 3]);
+
+        for (docid, distance) in &buckets {
+            let point = rtree.iter().find(|p| p.data.0 == *docid).unwrap().data.1;
+            let expected = origins
+                .iter()
+                .map(|origin| distance_between_two_points(origin, &point))
+                .fold(f64::INFINITY, f64::min);
+            assert_eq!(*distance, expected);
+        }
+
+        let docids: Vec<_> = geo_point(&rtree, GreatCircle, candidates, &origins, None, false)
+            .flatten()
+            .map(|(docid, _)| docid)
+            .collect();
+        assert_eq!(docids, vec![3, 2, 4, 1, 0]);
+    }
+
+    #[test]
+    fn geo_point_custom_buckets() {
+        let rtree = build_rtree(&[
+            // ~70m from the origin
+            [48.8570, 2.3530],
+            // ~390km from the origin
+            [45.7640, 4.8357],
+            // ~660km from the origin
+            [43.2965, 5.3698],
+            // ~6km from the origin
+            [48.9000, 2.4000],
+            // ~200km from the origin
+            [50.6292, 3.0573],
+            // ~680m from the origin
+            [48.8600, 2.3600],
+        ]);
+        let candidates: RoaringBitmap = (0..6).collect();
+        let origin = [[48.8566, 2.3522]];
+        let boundaries = [100, 1_000, 10_000, 500_000];
+
+        let buckets: Vec<Vec<_>> =
+            geo_point(&rtree, GreatCircle, candidates.clone(), &origin, Some(&boundaries), true)
+                .map(|bucket| bucket.into_iter().map(|(docid, _)| docid).collect())
+                .collect();
+        assert_eq!(buckets, vec![vec![0], vec![5], vec![3], vec![4, 1], vec![2]]);
+
+        let buckets: Vec<Vec<_>> =
+            geo_point(&rtree, GreatCircle, candidates, &origin, Some(&[1_000]), false)
+                .map(|bucket| bucket.into_iter().map(|(docid, _)| docid).collect())
+                .collect();
+        assert_eq!(buckets, vec![vec![3, 4, 1, 2], vec![0, 5]]);
+    }
+
+    #[test]
+    fn ascending_geo_point_is_the_same_as_geo_point() {
+        let points: Vec<_> = (0..300)
+            .map(|i| [((i * 37) % 180) as f64 - 90., ((i * 91) % 360) as f64 - 180.])
+            .collect();
+        let rtree = Rc::new(build_rtree(&points));
+        // one document out of three is not a candidate
+        let candidates: RoaringBitmap = (0..300).filter(|docid| docid % 3 != 0).collect();
+        let origin = [48.8566, 2.3522];
+
+        let expected: Vec<_> =
+            geo_point(&rtree, GreatCircle, candidates.clone(), &[origin], None, true).collect();
+        let mut iter = AscendingGeoPoint::new(rtree.clone(), candidates.clone(), origin);
+        // start with small fetches to make sure that restarting the iterator works
+        iter.fetch_size = 1;
+        let buckets: Vec<_> = iter.collect();
+        assert_eq!(buckets.len(), candidates.len() as usize);
+        assert_eq!(buckets, expected);
+    }
+
+    #[test]
+    fn ascending_geo_point_stops_at_the_last_candidate() {
+        // the points are going north of the origin, in distance order.
+        let points: Vec<_> = (0..200).map(|i| [45.0 + i as f64 * 0.01, 3.0]).collect();
+        let rtree = Rc::new(build_rtree(&points));
+        let candidates: RoaringBitmap = [2, 5, 9].iter().copied().collect();
+
+        let mut iter = AscendingGeoPoint::new(rtree, candidates, [45.0, 3.0]);
+        let docids: Vec<_> = iter.by_ref().flatten().map(|(docid, _)| docid).collect();
+        assert_eq!(docids, vec![2, 5, 9]);
+        assert_eq!(iter.visited, 10);
+    }
+
+    #[test]
+    fn geo_point_skips_invalid_coordinates() {
+        let mut points: Vec<_> = [[48.8566, 2.3522], [45.7640, 4.8357], [43.2965, 5.3698]]
+            .iter()
+            .enumerate()
+            .map(|(docid, point)| GeoPoint::new(lat_lng_to_xyz(point), (docid as u32, *point)))
+            .collect();
+        // a point stored with invalid coordinates.
+        points.push(GeoPoint::new(lat_lng_to_xyz(&[48.8570, 2.3530]), (3, [f64::NAN, 2.3530])));
+        let rtree = Rc::new(RTree::bulk_load(points));
+        let candidates: RoaringBitmap = (0..4).collect();
+        let origin = [48.8566, 2.3522];
+
+        let origins: &[&[[f64; 2]]] = &[&[origin], &[origin, [45.7640, 4.8357]]];
+        for (origins, ascending) in origins.iter().flat_map(|o| [(o, true), (o, false)]) {
+            let docids: RoaringBitmap =
+                geo_point(&rtree, GreatCircle, candidates.clone(), origins, None, ascending)
+                    .flatten()
+                    .map(|(docid, _)| docid)
+                    .collect();
+            assert_eq!(docids, (0..3).collect::<RoaringBitmap>());
+        }
+
+        let docids: RoaringBitmap = AscendingGeoPoint::new(rtree, candidates, origin)
+            .flatten()
+            .map(|(docid, _)| docid)
+            .collect();
+        assert_eq!(docids, (0..3).collect::<RoaringBitmap>());
+    }
+
+    #[test]
+    fn planar_distance_agrees_with_great_circle_on_nearby_points() {
+        let origin = [48.8566, 2.3522];
+        // the points are every 100m from the origin up to 2km, in all directions.
+        let points: Vec<_> = (1..=20)
+            .map(|i| {
+                let (distance, angle) = (i as f64 * 100.0, i as f64 * 2.4);
+                let lat = origin[0] + distance * angle.cos() / 111_195.0;
+                let lng =
+                    origin[1] + distance * angle.sin() / (111_195.0 * origin[0].to_radians().cos());
+                [lat, lng]
+            })
+            .collect();
+        let rtree = Rc::new(build_rtree(&points));
+        let candidates: RoaringBitmap = (0..20).collect();
+
+        for point in &points {
+            let great_circle = GreatCircle.distance(&origin, point);
+            let planar = Planar.distance(&origin, point);
+            assert!(
+                (great_circle - planar).abs() < great_circle * 1e-3,
+                "{} != {}",
+                great_circle,
+                planar
+            );
+        }
+
+        for &metric in &[GreatCircle, Planar] {
+            let buckets: Vec<_> =
+                geo_point(&rtree, metric, candidates.clone(), &[origin], None, true)
+                    .flatten()
+                    .collect();
+            let docids: Vec<_> = buckets.iter().map(|(docid, _)| *docid).collect();
+            assert_eq!(docids, (0..20).collect::<Vec<_>>());
+
+            if metric == GreatCircle {
+                let streamed: Vec<_> =
+                    AscendingGeoPoint::new(rtree.clone(), candidates.clone(), origin)
+                        .flatten()
+                        .collect();
+                assert_eq!(streamed, buckets);
+            }
+
+            let docids: Vec<_> =
+                geo_point(&rtree, metric, candidates.clone(), &[origin], None, false)
+                    .flatten()
+                    .map(|(docid, _)| docid)
+                    .collect();
+            assert_eq!(docids, (0..20).rev().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn geo_point_is_ordered_by_the_planar_distance() {
+        // the first point is the closest on the sphere, the second one is the closest on
+        // the plane.
+        let rtree = build_rtree(&[[50.0, 80.0], [0.0, 0.0]]);
+        let candidates: RoaringBitmap = (0..2).collect();
+        let origin = [50.0, 0.0];
+
+        let docids = |metric, origins: &[[f64; 2]], ascending| -> Vec<_> {
+            geo_point(&rtree, metric, candidates.clone(), origins, None, ascending)
+                .flatten()
+                .map(|(docid, _)| docid)
+                .collect()
+        };
+        assert_eq!(docids(GreatCircle, &[origin], true), vec![0, 1]);
+        assert_eq!(docids(Planar, &[origin], true), vec![1, 0]);
+        assert_eq!(docids(Planar, &[origin], false), vec![0, 1]);
+        // the second origin is far from both points and doesn't change their order.
+        assert_eq!(docids(Planar, &[origin, [-50.0, -120.0]], true), vec![1, 0]);
+
+        let buckets =
+            geo_point(&rtree, Planar, candidates.clone(), &[origin], Some(&[5_600_000]), true)
+                .map(|bucket| bucket.into_iter().map(|(docid, _)| docid).collect())
+                .collect::<Vec<Vec<_>>>();
+        assert_eq!(buckets, vec![vec![1], vec![0]]);
+    }
+}
@@ -1,20 +1,35 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
+use heed::types::ByteSlice;
+use heed::Database;
 use roaring::RoaringBitmap;
 
 use self::asc_desc::AscDesc;
 use self::attribute::Attribute;
 use self::exactness::Exactness;
 use self::initial::Initial;
+use self::prefix_completion::PrefixCompletion;
+use self::profiling::{CriteriaProfile, ProfilingCriterion};
 use self::proximity::Proximity;
 use self::r#final::Final;
-use self::typo::Typo;
+use self::score::Score;
+use self::typo::{AttributesTypoTolerance, Typo};
+use self::word_count::WordCount;
 use self::words::Words;
 use super::query_tree::{Operation, PrimitiveQueryPart, Query, QueryKind};
+use crate::error::UserError;
+use crate::heed_codec::FieldIdWordCountCodec;
+use crate::proximity::MAX_DISTANCE;
 use crate::search::criteria::geo::Geo;
 use crate::search::{word_derivations, WordDerivationsCache};
-use crate::{AscDesc as AscDescName, DocumentId, FieldId, Index, Member, Result};
+use crate::{
+    absolute_from_relative_position, relative_from_absolute_position, AscDesc as AscDescName,
+    CboRoaringBitmapCodec, DisabledDatabases, DocumentId, FieldId, Index, Member, MissingValues,
+    Result, StrBEU32Codec,
+};
 
 mod asc_desc;
 mod attribute;
@@ -22,8 +37,12 @@ mod exactness;
 pub mod r#final;
 mod geo;
 mod initial;
+mod prefix_completion;
+mod profiling;
 mod proximity;
+pub mod score;
 mod typo;
+mod word_count;
 mod words;
 
 pub trait Criterion {
@@ -48,6 +67,9 @@ pub struct CriterionResult {
 pub struct CriterionParameters<'a> {
     wdcache: &'a mut WordDerivationsCache,
     excluded_candidates: &'a RoaringBitmap,
+    /// The distances, in meters, between the returned documents and the
+    /// geo sort origin, only filled when a geo sort criterion is used.
+    geo_distances: &'a mut HashMap<DocumentId, f64>,
 }
 
 /// Either a set of candidates that defines the candidates
@@ -67,6 +89,8 @@ impl Default for Candidates {
 
 pub trait Context<'c> {
     fn documents_ids(&self) -> heed::Result<RoaringBitmap>;
+    /// The total number of words positions of the documents, `None` if it isn't stored.
+    fn documents_words_count(&self) -> heed::Result<Option<u64>>;
     fn word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
     fn exact_word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
     fn word_prefix_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>>;
@@ -96,12 +120,23 @@ pub trait Context<'c> {
     ) -> heed::Result<Box<dyn Iterator<Item = heed::Result<((&'c str, u32), RoaringBitmap)>> + 'c>>;
     fn synonyms(&self, word: &str) -> heed::Result<Option<Vec<Vec<String>>>>;
     fn searchable_fields_ids(&self) -> Result<Vec<FieldId>>;
+    /// The weights of the searchable fields, empty if they are only ranked by their order.
+    fn searchable_fields_weights(&self) -> Result<HashMap<FieldId, u16>>;
     fn field_id_word_count_docids(
         &self,
         field_id: FieldId,
         word_count: u8,
     ) -> heed::Result<Option<RoaringBitmap>>;
+    /// The documents ids of each number of words of the field, by increasing number of words.
+    fn field_id_word_counts_docids(
+        &self,
+        field_id: FieldId,
+    ) -> heed::Result<Vec<(u8, RoaringBitmap)>>;
     fn word_position_docids(&self, word: &str, pos: u32) -> heed::Result<Option<RoaringBitmap>>;
+    fn attributes_typo_tolerance(&self) -> &AttributesTypoTolerance;
+    fn exact_words(&self) -> Option<&fst::Set<Cow<[u8]>>>;
+    /// The attributes the search is restricted to, `None` if it isn't restricted.
+    fn restricted_fields_ids(&self) -> Option<&HashSet<FieldId>>;
 }
 
 pub struct CriteriaBuilder<'t> {
@@ -109,6 +144,12 @@ pub struct CriteriaBuilder<'t> {
     index: &'t Index,
     words_fst: fst::Set<Cow<'t, [u8]>>,
     words_prefixes_fst: fst::Set<Cow<'t, [u8]>>,
+    attributes_typo_tolerance: AttributesTypoTolerance,
+    exact_words: Option<fst::Set<Cow<'t, [u8]>>>,
+    restricted_fields_ids: Option<HashSet<FieldId>>,
+    sort_missing_values: MissingValues,
+    max_proximity: Option<u8>,
+    disabled_databases: DisabledDatabases,
 }
 
 impl<'c> Context<'c> for CriteriaBuilder<'c> {
@@ -116,6 +157,10 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
         self.index.documents_ids(self.rtxn)
     }
 
+    fn documents_words_count(&self) -> heed::Result<Option<u64>> {
+        self.index.documents_words_count(self.rtxn)
+    }
+
     fn word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
         self.index.word_docids.get(self.rtxn, &word)
     }
@@ -138,6 +183,10 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
         right: &str,
         proximity: u8,
     ) -> heed::Result<Option<RoaringBitmap>> {
+        if self.disabled_databases.proximity {
+            let db = self.index.word_position_docids;
+            return self.pair_proximity_docids_from_positions(left, right, db, proximity);
+        }
         let key = (left, right, proximity);
         self.index.word_pair_proximity_docids.get(self.rtxn, &key)
     }
@@ -148,6 +197,10 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
         right: &str,
         proximity: u8,
     ) -> heed::Result<Option<RoaringBitmap>> {
+        if self.disabled_databases.proximity {
+            let db = self.index.word_prefix_position_docids;
+            return self.pair_proximity_docids_from_positions(left, right, db, proximity);
+        }
         let key = (left, right, proximity);
         self.index.word_prefix_pair_proximity_docids.get(self.rtxn, &key)
     }
@@ -198,10 +251,18 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
     }
 
     fn searchable_fields_ids(&self) -> Result<Vec<FieldId>> {
-        match self.index.searchable_fields_ids(self.rtxn)? {
-            Some(searchable_fields_ids) => Ok(searchable_fields_ids),
-            None => Ok(self.index.fields_ids_map(self.rtxn)?.ids().collect()),
+        let mut fields_ids = match self.index.searchable_fields_ids(self.rtxn)? {
+            Some(searchable_fields_ids) => searchable_fields_ids,
+            None => self.index.fields_ids_map(self.rtxn)?.ids().collect(),
+        };
+        if let Some(restricted) = &self.restricted_fields_ids {
+            fields_ids.retain(|fid| restricted.contains(fid));
         }
+        Ok(fields_ids)
+    }
+
+    fn searchable_fields_weights(&self) -> Result<HashMap<FieldId, u16>> {
+        self.index.searchable_fields_ids_weights(self.rtxn)
     }
 
     fn field_id_word_count_docids(
@@ -213,17 +274,123 @@ impl<'c> Context<'c> for CriteriaBuilder<'c> {
         self.index.field_id_word_count_docids.get(self.rtxn, &key)
     }
 
+    fn field_id_word_counts_docids(
+        &self,
+        field_id: FieldId,
+    ) -> heed::Result<Vec<(u8, RoaringBitmap)>> {
+        self.index
+            .field_id_word_count_docids
+            .remap_key_type::<ByteSlice>()
+            .prefix_iter(self.rtxn, &field_id.to_be_bytes())?
+            .remap_key_type::<FieldIdWordCountCodec>()
+            .map(|result| result.map(|((_field_id, word_count), docids)| (word_count, docids)))
+            .collect()
+    }
+
     fn word_position_docids(&self, word: &str, pos: u32) -> heed::Result<Option<RoaringBitmap>> {
         let key = (word, pos);
         self.index.word_position_docids.get(self.rtxn, &key)
     }
+
+    fn attributes_typo_tolerance(&self) -> &AttributesTypoTolerance {
+        &self.attributes_typo_tolerance
+    }
+
+    fn exact_words(&self) -> Option<&fst::Set<Cow<[u8]>>> {
+        self.exact_words.as_ref()
+    }
+
+    fn restricted_fields_ids(&self) -> Option<&HashSet<FieldId>> {
+        self.restricted_fields_ids.as_ref()
+    }
 }
 
 impl<'t> CriteriaBuilder<'t> {
     pub fn new(rtxn: &'t heed::RoTxn<'t>, index: &'t Index) -> Result<Self> {
         let words_fst = index.words_fst(rtxn)?;
         let words_prefixes_fst = index.words_prefixes_fst(rtxn)?;
-        Ok(Self { rtxn, index, words_fst, words_prefixes_fst })
+        let attributes_typo_tolerance = AttributesTypoTolerance::new(rtxn, index)?;
+        let exact_words = index.exact_words(rtxn)?;
+        let disabled_databases = index.disabled_databases(rtxn)?;
+        Ok(Self {
+            rtxn,
+            index,
+            words_fst,
+            words_prefixes_fst,
+            attributes_typo_tolerance,
+            exact_words,
+            restricted_fields_ids: None,
+            sort_missing_values: MissingValues::default(),
+            max_proximity: None,
+            disabled_databases,
+        })
+    }
+
+    /// Computes the documents where the best proximity between `left` and `right` is exactly
+    /// `proximity`, from the word positions, when the proximity databases are disabled.
+    fn pair_proximity_docids_from_positions(
+        &self,
+        left: &str,
+        right: &str,
+        right_db: Database<StrBEU32Codec, CboRoaringBitmapCodec>,
+        proximity: u8,
+    ) -> heed::Result<Option<RoaringBitmap>> {
+        // Only the proximities between 1 and 7 are stored in the proximity databases.
+        if proximity == 0 || proximity >= MAX_DISTANCE as u8 {
+            return Ok(None);
+        }
+
+        let mut exact = RoaringBitmap::new();
+        let mut closer = RoaringBitmap::new();
+        let range = (left, u32::min_value())..=(left, u32::max_value());
+        for result in self.index.word_position_docids.range(self.rtxn, &range)? {
+            let ((_, position), left_docids) = result?;
+            let (field_id, relative) = relative_from_absolute_position(position);
+            for prox in 1..=proximity as u16 {
+                let mut docids = RoaringBitmap::new();
+                // The right word is after the left one.
+                if let Some(relative) = relative.checked_add(prox) {
+                    let key = (right, absolute_from_relative_position(field_id, relative));
+                    if let Some(right_docids) = right_db.get(self.rtxn, &key)? {
+                        docids |= right_docids;
+                    }
+                }
+                // The right word is before the left one, which costs one more.
+                if let Some(relative) = relative.checked_sub(prox - 1).filter(|_| prox > 1) {
+                    let key = (right, absolute_from_relative_position(field_id, relative));
+                    if let Some(right_docids) = right_db.get(self.rtxn, &key)? {
+                        docids |= right_docids;
+                    }
+                }
+                docids &= &left_docids;
+                if prox == proximity as u16 {
+                    exact |= docids;
+                } else {
+                    closer |= docids;
+                }
+            }
+        }
+
+        exact -= closer;
+        Ok(Some(exact).filter(|docids| !docids.is_empty()))
+    }
+
+    /// Only matches the query words found in these attributes.
+    pub fn restrict_fields_ids(&mut self, fields_ids: HashSet<FieldId>) -> &mut Self {
+        self.restricted_fields_ids = Some(fields_ids);
+        self
+    }
+
+    /// Places the documents without any value for a sort criterion before or after the other ones.
+    pub fn sort_missing_values(&mut self, missing_values: MissingValues) -> &mut Self {
+        self.sort_missing_values = missing_values;
+        self
+    }
+
+    /// Returns the documents beyond this proximity together from the proximity criterion.
+    pub fn max_proximity(&mut self, proximity: u8) -> &mut Self {
+        self.max_proximity = Some(proximity);
+        self
     }
 
     pub fn build(
@@ -231,55 +398,124 @@ impl<'t> CriteriaBuilder<'t> {
         query_tree: Option<Operation>,
         primitive_query: Option<Vec<PrimitiveQueryPart>>,
         filtered_candidates: Option<RoaringBitmap>,
+        ranking_rules: Vec<crate::Criterion>,
         sort_criteria: Option<Vec<AscDescName>>,
+        geo_buckets: Option<Vec<usize>>,
+        profiling: bool,
     ) -> Result<Final<'t>> {
         use crate::criterion::Criterion as Name;
 
         let primitive_query = primitive_query.unwrap_or_default();
 
+        let profile =
+            if profiling { Some(Rc::new(RefCell::new(CriteriaProfile::default()))) } else { None };
+        // wraps the criterion to record the time spent in it, when profiling is enabled.
+        let profiled =
+            |name: String, criterion: Box<dyn Criterion + 't>| -> Box<dyn Criterion + 't> {
+                match &profile {
+                    Some(profile) => {
+                        Box::new(ProfilingCriterion::new(name, criterion, profile.clone()))
+                    }
+                    None => criterion,
+                }
+            };
+
         let mut criterion =
             Box::new(Initial::new(query_tree, filtered_candidates)) as Box<dyn Criterion>;
-        for name in self.index.criteria(&self.rtxn)? {
+        for name in ranking_rules {
+            // the sort criterion is profiled for each of the sort criteria it expands to.
+            let is_sort = matches!(name, Name::Sort);
+            let criterion_name = name.to_string();
             criterion = match name {
                 Name::Words => Box::new(Words::new(self, criterion)),
                 Name::Typo => Box::new(Typo::new(self, criterion)),
                 Name::Sort => match sort_criteria {
                     Some(ref sort_criteria) => {
                         for asc_desc in sort_criteria {
+                            let sort_name = match asc_desc {
+                                AscDescName::Asc(member) => format!("{}:asc", member),
+                                AscDescName::Desc(member) => format!("{}:desc", member),
+                            };
+                            let is_geo = asc_desc.field().is_none();
+                            if is_geo && self.disabled_databases.geo {
+                                return Err(UserError::CriterionDatabaseDisabled {
+                                    criterion: sort_name,
+                                    database: "geo",
+                                }
+                                .into());
+                            }
                             criterion = match asc_desc {
-                                AscDescName::Asc(Member::Field(field)) => Box::new(AscDesc::asc(
-                                    &self.index,
-                                    &self.rtxn,
-                                    criterion,
-                                    field.to_string(),
-                                )?),
-                                AscDescName::Desc(Member::Field(field)) => Box::new(AscDesc::desc(
+                                AscDescName::Asc(Member::Field(field)) => Box::new(
+                                    AscDesc::asc(
+                                        &self.index,
+                                        &self.rtxn,
+                                        criterion,
+                                        field.to_string(),
+                                    )?
+                                    .with_missing_values(self.sort_missing_values),
+                                ),
+                                AscDescName::Desc(Member::Field(field)) => Box::new(
+                                    AscDesc::desc(
+                                        &self.index,
+                                        &self.rtxn,
+                                        criterion,
+                                        field.to_string(),
+                                    )?
+                                    .with_missing_values(self.sort_missing_values),
+                                ),
+                                AscDescName::Asc(Member::Geo(point)) => Box::new(Geo::asc(
                                     &self.index,
                                     &self.rtxn,
                                     criterion,
-                                    field.to_string(),
+                                    vec![*point],
+                                    geo_buckets.clone(),
                                 )?),
-                                AscDescName::Asc(Member::Geo(point)) => Box::new(Geo::asc(
+                                AscDescName::Desc(Member::Geo(point)) => Box::new(Geo::desc(
                                     &self.index,
                                     &self.rtxn,
                                     criterion,
-                                    point.clone(),
+                                    vec![*point],
+                                    geo_buckets.clone(),
                                 )?),
-                                AscDescName::Desc(Member::Geo(point)) => Box::new(Geo::desc(
+                                AscDescName::Asc(Member::GeoPoints(points)) => Box::new(Geo::asc(
                                     &self.index,
                                     &self.rtxn,
                                     criterion,
-                                    point.clone(),
+                                    points.clone(),
+                                    geo_buckets.clone(),
                                 )?),
+                                AscDescName::Desc(Member::GeoPoints(points)) => {
+                                    Box::new(Geo::desc(
+                                        &self.index,
+                                        &self.rtxn,
+                                        criterion,
+                                        points.clone(),
+                                        geo_buckets.clone(),
+                                    )?)
+                                }
                             };
+                            criterion = profiled(sort_name, criterion);
                         }
                         criterion
                     }
                     None => criterion,
                 },
-                Name::Proximity => Box::new(Proximity::new(self, criterion)),
+                Name::Proximity if self.disabled_databases.proximity => {
+                    return Err(UserError::CriterionDatabaseDisabled {
+                        criterion: criterion_name,
+                        database: "proximity",
+                    }
+                    .into())
+                }
+                Name::Proximity => match self.max_proximity {
+                    Some(max) => Box::new(Proximity::with_max_proximity(self, criterion, max)),
+                    None => Box::new(Proximity::new(self, criterion)),
+                },
                 Name::Attribute => Box::new(Attribute::new(self, criterion)),
                 Name::Exactness => Box::new(Exactness::new(self, criterion, &primitive_query)?),
+                Name::WordCount => Box::new(WordCount::new(self, criterion)),
+                Name::Score => Box::new(Score::new(self, criterion)),
+                Name::PrefixCompletion => Box::new(PrefixCompletion::new(self, criterion)),
                 Name::Asc(field) => {
                     Box::new(AscDesc::asc(&self.index, &self.rtxn, criterion, field)?)
                 }
@@ -287,9 +523,12 @@ impl<'t> CriteriaBuilder<'t> {
                     Box::new(AscDesc::desc(&self.index, &self.rtxn, criterion, field)?)
                 }
             };
+            if !is_sort {
+                criterion = profiled(criterion_name, criterion);
+            }
         }
 
-        Ok(Final::new(self, criterion))
+        Ok(Final::new(self, criterion).with_profile(profile))
     }
 }
 
@@ -335,7 +574,7 @@ pub fn resolve_query_tree<'t>(
                     // Get all the documents with the matching distance for each word pairs.
                     let mut bitmaps = Vec::with_capacity(winsize.pow(2));
                     for (offset, s1) in win.iter().enumerate() {
-                        for (dist, s2) in win.iter().skip(offset).enumerate() {
+                        for (dist, s2) in win.iter().skip(offset + 1).enumerate() {
                             match ctx.word_pair_proximity_docids(s1, s2, dist as u8 + 1)? {
                                 Some(m) => bitmaps.push(m),
                                 // If there are no document for this distance, there will be no
@@ -361,6 +600,9 @@ pub fn resolve_query_tree<'t>(
                         }
                     }
                 }
+                for word in words {
+                    candidates = restricted_docids(ctx, word, false, candidates)?;
+                }
                 Ok(candidates)
             }
             Or(_, ops) => {
@@ -378,6 +620,51 @@ pub fn resolve_query_tree<'t>(
     resolve_operation(ctx, query_tree, wdcache)
 }
 
+/// Returns the words, derivations included, that the query tree can match.
+fn query_tree_words(
+    ctx: &dyn Context,
+    query_tree: &Operation,
+    wdcache: &mut WordDerivationsCache,
+) -> Result<HashSet<String>> {
+    fn collect_words(
+        ctx: &dyn Context,
+        query_tree: &Operation,
+        wdcache: &mut WordDerivationsCache,
+        words: &mut HashSet<String>,
+    ) -> Result<()> {
+        match query_tree {
+            Operation::And(ops) | Operation::Or(_, ops) => {
+                for op in ops {
+                    collect_words(ctx, op, wdcache, words)?;
+                }
+            }
+            Operation::Phrase(phrase) => words.extend(phrase.iter().cloned()),
+            Operation::Query(query) => match &query.kind {
+                QueryKind::Exact { word, .. } if !query.prefix => {
+                    words.insert(word.clone());
+                }
+                QueryKind::Exact { word, .. } => {
+                    for (word, _) in index_word_derivations(ctx, word, true, 0, wdcache)? {
+                        words.insert(word.clone());
+                    }
+                }
+                QueryKind::Tolerant { typo, word } => {
+                    for (word, _) in
+                        index_word_derivations(ctx, word, query.prefix, *typo, wdcache)?
+                    {
+                        words.insert(word.clone());
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+
+    let mut words = HashSet::new();
+    collect_words(ctx, query_tree, wdcache, &mut words)?;
+    Ok(words)
+}
+
 fn all_word_pair_proximity_docids<T: AsRef<str>, U: AsRef<str>>(
     ctx: &dyn Context,
     left_words: &[(T, u8)],
@@ -385,8 +672,14 @@ fn all_word_pair_proximity_docids<T: AsRef<str>, U: AsRef<str>>(
     proximity: u8,
 ) -> Result<RoaringBitmap> {
     let mut docids = RoaringBitmap::new();
-    for (left, _l_typo) in left_words {
-        for (right, _r_typo) in right_words {
+    for (left, l_typo) in left_words {
+        if !is_typo_derivation_allowed(ctx, left.as_ref(), *l_typo) {
+            continue;
+        }
+        for (right, r_typo) in right_words {
+            if !is_typo_derivation_allowed(ctx, right.as_ref(), *r_typo) {
+                continue;
+            }
             let current_docids = ctx
                 .word_pair_proximity_docids(left.as_ref(), right.as_ref(), proximity)?
                 .unwrap_or_default();
@@ -402,23 +695,24 @@ fn query_docids(
     wdcache: &mut WordDerivationsCache,
 ) -> Result<RoaringBitmap> {
     match &query.kind {
-        QueryKind::Exact { word, original_typo } => {
+        QueryKind::Exact { word, original_typo, original_len } => {
             if query.prefix && ctx.in_prefix_cache(&word) {
                 let mut docids = ctx.word_prefix_docids(&word)?.unwrap_or_default();
                 // only add the exact docids if the word hasn't been derived
                 if *original_typo == 0 {
                     docids |= ctx.exact_word_prefix_docids(&word)?.unwrap_or_default();
                 }
-                Ok(docids)
+                restricted_docids(ctx, &word, true, docids)
             } else if query.prefix {
-                let words = word_derivations(&word, true, 0, ctx.words_fst(), wdcache)?;
+                let words = index_word_derivations(ctx, &word, true, 0, wdcache)?;
                 let mut docids = RoaringBitmap::new();
                 for (word, _typo) in words {
-                    docids |= ctx.word_docids(&word)?.unwrap_or_default();
+                    let mut current_docids = ctx.word_docids(&word)?.unwrap_or_default();
                     // only add the exact docids if the word hasn't been derived
                     if *original_typo == 0 {
-                        docids |= ctx.exact_word_docids(&word)?.unwrap_or_default();
+                        current_docids |= ctx.exact_word_docids(&word)?.unwrap_or_default();
                     }
+                    docids |= restricted_docids(ctx, &word, false, current_docids)?;
                 }
                 Ok(docids)
             } else {
@@ -426,25 +720,117 @@ fn query_docids(
                 // only add the exact docids if the word hasn't been derived
                 if *original_typo == 0 {
                     docids |= ctx.exact_word_docids(&word)?.unwrap_or_default();
+                } else {
+                    docids =
+                        typo_tolerated_docids(ctx, &word, *original_len, *original_typo, docids)?;
                 }
-                Ok(docids)
+                restricted_docids(ctx, &word, false, docids)
             }
         }
         QueryKind::Tolerant { typo, word } => {
-            let words = word_derivations(&word, query.prefix, *typo, ctx.words_fst(), wdcache)?;
+            let original_len = word.chars().count().min(u8::MAX as usize) as u8;
+            let words = index_word_derivations(ctx, &word, query.prefix, *typo, wdcache)?;
             let mut docids = RoaringBitmap::new();
-            for (word, typo) in words {
-                let mut current_docids = ctx.word_docids(&word)?.unwrap_or_default();
+            for (derived, typo) in words {
+                if !is_typo_derivation_allowed(ctx, derived, *typo) {
+                    continue;
+                }
+                let mut current_docids = ctx.word_docids(&derived)?.unwrap_or_default();
                 if *typo == 0 {
-                    current_docids |= ctx.exact_word_docids(&word)?.unwrap_or_default()
+                    current_docids |= ctx.exact_word_docids(&derived)?.unwrap_or_default()
+                } else {
+                    current_docids =
+                        typo_tolerated_docids(ctx, &derived, original_len, *typo, current_docids)?;
                 }
-                docids |= current_docids;
+                docids |= restricted_docids(ctx, &derived, false, current_docids)?;
             }
             Ok(docids)
         }
     }
 }
 
+/// Returns the words of the index derived from a query word, the typos on the
+/// first char are only allowed if the index allows them.
+fn index_word_derivations<'w>(
+    ctx: &dyn Context,
+    word: &str,
+    is_prefix: bool,
+    max_typo: u8,
+    wdcache: &'w mut WordDerivationsCache,
+) -> Result<&'w [(String, u8)]> {
+    let first_char_typo = ctx.attributes_typo_tolerance().allow_typo_on_first_char();
+    Ok(word_derivations(word, is_prefix, max_typo, first_char_typo, ctx.words_fst(), wdcache)?)
+}
+
+/// Returns `false` if the `word` is derived, with typos, from a query word while it
+/// is an exact word: the exact words must only be matched when they are typed exactly.
+fn is_typo_derivation_allowed(ctx: &dyn Context, word: &str, typo: u8) -> bool {
+    typo == 0 || !ctx.exact_words().map_or(false, |words| words.contains(word))
+}
+
+/// Restricts the `docids` of a `word`, derived with `typos` typos from a query word
+/// of `original_len` chars, to the documents where the word appears in an attribute
+/// that tolerates this number of typos.
+///
+/// The `word_docids` database doesn't tell from which attribute a document comes, the
+/// `word_position_docids` database does: the 16 high bits of the absolute positions
+/// of its keys are the field id of the attribute the word appears in.
+fn typo_tolerated_docids(
+    ctx: &dyn Context,
+    word: &str,
+    original_len: u8,
+    typos: u8,
+    docids: RoaringBitmap,
+) -> Result<RoaringBitmap> {
+    let tolerance = ctx.attributes_typo_tolerance();
+    if !tolerance.has_overrides() {
+        return Ok(docids);
+    }
+
+    let mut tolerated = RoaringBitmap::new();
+    for result in ctx.word_position_iterator(word, false)? {
+        let ((position_word, position), positions_docids) = result?;
+        // the range of positions of a word also contains the longer words it prefixes.
+        if position_word != word {
+            continue;
+        }
+        let (fid, _) = relative_from_absolute_position(position);
+        if tolerance.tolerates(fid, original_len, typos) {
+            tolerated |= positions_docids;
+        }
+    }
+
+    Ok(docids & tolerated)
+}
+
+/// Restricts the `docids` of a `word`, or of a prefix when `in_prefix_cache` is set,
+/// to the documents where the word appears in the attributes the search is restricted to.
+fn restricted_docids(
+    ctx: &dyn Context,
+    word: &str,
+    in_prefix_cache: bool,
+    docids: RoaringBitmap,
+) -> Result<RoaringBitmap> {
+    let fields_ids = match ctx.restricted_fields_ids() {
+        Some(fields_ids) => fields_ids,
+        None => return Ok(docids),
+    };
+
+    let mut restricted = RoaringBitmap::new();
+    for result in ctx.word_position_iterator(word, in_prefix_cache)? {
+        let ((position_word, position), positions_docids) = result?;
+        if position_word != word {
+            continue;
+        }
+        let (fid, _) = relative_from_absolute_position(position);
+        if fields_ids.contains(&fid) {
+            restricted |= positions_docids;
+        }
+    }
+
+    Ok(docids & restricted)
+}
+
 fn query_pair_proximity_docids(
     ctx: &dyn Context,
     left: &Query,
@@ -470,7 +856,7 @@ fn query_pair_proximity_docids(
                 )? {
                     Some(docids) => Ok(docids),
                     None => {
-                        let r_words = word_derivations(&right, true, 0, ctx.words_fst(), wdcache)?;
+                        let r_words = index_word_derivations(ctx, &right, true, 0, wdcache)?;
                         all_word_pair_proximity_docids(ctx, &[(left, 0)], &r_words, proximity)
                     }
                 }
@@ -481,11 +867,13 @@ fn query_pair_proximity_docids(
             }
         }
         (QueryKind::Tolerant { typo, word: left }, QueryKind::Exact { word: right, .. }) => {
-            let l_words =
-                word_derivations(&left, false, *typo, ctx.words_fst(), wdcache)?.to_owned();
+            let l_words = index_word_derivations(ctx, &left, false, *typo, wdcache)?.to_owned();
             if prefix {
                 let mut docids = RoaringBitmap::new();
-                for (left, _) in l_words {
+                for (left, typo) in l_words {
+                    if !is_typo_derivation_allowed(ctx, &left, typo) {
+                        continue;
+                    }
                     let current_docids = match ctx.word_prefix_pair_proximity_docids(
                         left.as_str(),
                         right.as_str(),
@@ -493,8 +881,7 @@ fn query_pair_proximity_docids(
                     )? {
                         Some(docids) => Ok(docids),
                         None => {
-                            let r_words =
-                                word_derivations(&right, true, 0, ctx.words_fst(), wdcache)?;
+                            let r_words = index_word_derivations(ctx, &right, true, 0, wdcache)?;
                             all_word_pair_proximity_docids(ctx, &[(left, 0)], &r_words, proximity)
                         }
                     }?;
@@ -506,16 +893,15 @@ fn query_pair_proximity_docids(
             }
         }
         (QueryKind::Exact { word: left, .. }, QueryKind::Tolerant { typo, word: right }) => {
-            let r_words = word_derivations(&right, prefix, *typo, ctx.words_fst(), wdcache)?;
+            let r_words = index_word_derivations(ctx, &right, prefix, *typo, wdcache)?;
             all_word_pair_proximity_docids(ctx, &[(left, 0)], &r_words, proximity)
         }
         (
             QueryKind::Tolerant { typo: l_typo, word: left },
             QueryKind::Tolerant { typo: r_typo, word: right },
         ) => {
-            let l_words =
-                word_derivations(&left, false, *l_typo, ctx.words_fst(), wdcache)?.to_owned();
-            let r_words = word_derivations(&right, prefix, *r_typo, ctx.words_fst(), wdcache)?;
+            let l_words = index_word_derivations(ctx, &left, false, *l_typo, wdcache)?.to_owned();
+            let r_words = index_word_derivations(ctx, &right, prefix, *r_typo, wdcache)?;
             all_word_pair_proximity_docids(ctx, &l_words, &r_words, proximity)
         }
     }
@@ -544,6 +930,7 @@ pub mod test {
         word_pair_proximity_docids: HashMap<(String, String, i32), RoaringBitmap>,
         word_prefix_pair_proximity_docids: HashMap<(String, String, i32), RoaringBitmap>,
         docid_words: HashMap<u32, Vec<String>>,
+        attributes_typo_tolerance: AttributesTypoTolerance,
     }
 
     impl<'c> Context<'c> for TestContext<'c> {
@@ -551,6 +938,10 @@ pub mod test {
             Ok(self.word_docids.iter().fold(RoaringBitmap::new(), |acc, (_, docids)| acc | docids))
         }
 
+        fn documents_words_count(&self) -> heed::Result<Option<u64>> {
+            Ok(None)
+        }
+
         fn word_docids(&self, word: &str) -> heed::Result<Option<RoaringBitmap>> {
             Ok(self.word_docids.get(&word.to_string()).cloned())
         }
@@ -631,6 +1022,10 @@ pub mod test {
             todo!()
         }
 
+        fn searchable_fields_weights(&self) -> Result<HashMap<FieldId, u16>> {
+            Ok(HashMap::new())
+        }
+
         fn word_position_docids(
             &self,
             _word: &str,
@@ -646,6 +1041,25 @@ pub mod test {
         ) -> heed::Result<Option<RoaringBitmap>> {
             todo!()
         }
+
+        fn field_id_word_counts_docids(
+            &self,
+            _field_id: FieldId,
+        ) -> heed::Result<Vec<(u8, RoaringBitmap)>> {
+            todo!()
+        }
+
+        fn attributes_typo_tolerance(&self) -> &AttributesTypoTolerance {
+            &self.attributes_typo_tolerance
+        }
+
+        fn exact_words(&self) -> Option<&fst::Set<Cow<[u8]>>> {
+            None
+        }
+
+        fn restricted_fields_ids(&self) -> Option<&HashSet<FieldId>> {
+            None
+        }
     }
 
     impl<'a> Default for TestContext<'a> {
@@ -758,6 +1172,7 @@ pub mod test {
                 word_pair_proximity_docids,
                 word_prefix_pair_proximity_docids,
                 docid_words,
+                attributes_typo_tolerance: AttributesTypoTolerance::default(),
             }
         }
     }
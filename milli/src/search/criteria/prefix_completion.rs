@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use std::mem::take;
+
+use log::debug;
+use roaring::RoaringBitmap;
+
+use super::{
+    index_word_derivations, resolve_query_tree, Context, Criterion, CriterionParameters,
+    CriterionResult,
+};
+use crate::search::query_tree::{Operation, Query};
+use crate::search::WordDerivationsCache;
+use crate::Result;
+
+/// Ranks the documents by the increasing number of characters that the words they contain
+/// add to the prefix words of the query: for the query `app`, the documents containing `app`
+/// come first, then the ones containing `apple` and then the ones containing `application`.
+///
+/// The completions are the words of the words FST that start with the prefix, the ones the
+/// `word_prefix_docids` database is made of, without any typo. A document is ranked by its
+/// shortest completion, the documents without any completion of a prefix word, e.g. matched
+/// through a typo or without any prefix word in the query, are returned in the last bucket.
+pub struct PrefixCompletion<'t> {
+    ctx: &'t dyn Context<'t>,
+    query_tree: Option<Operation>,
+    /// The remaining buckets, in reverse order of relevancy.
+    buckets: Vec<RoaringBitmap>,
+    bucket_candidates: RoaringBitmap,
+    parent: Box<dyn Criterion + 't>,
+}
+
+impl<'t> PrefixCompletion<'t> {
+    pub fn new(ctx: &'t dyn Context<'t>, parent: Box<dyn Criterion + 't>) -> Self {
+        PrefixCompletion {
+            ctx,
+            query_tree: None,
+            buckets: Vec::new(),
+            bucket_candidates: RoaringBitmap::new(),
+            parent,
+        }
+    }
+}
+
+impl<'t> Criterion for PrefixCompletion<'t> {
+    #[logging_timer::time("PrefixCompletion::{}")]
+    fn next(&mut self, params: &mut CriterionParameters) -> Result<Option<CriterionResult>> {
+        loop {
+            debug!("PrefixCompletion at state {:?}", self.buckets);
+
+            match self.buckets.pop() {
+                Some(mut candidates) => {
+                    candidates -= params.excluded_candidates;
+                    if candidates.is_empty() {
+                        continue;
+                    }
+
+                    return Ok(Some(CriterionResult {
+                        query_tree: self.query_tree.clone(),
+                        candidates: Some(candidates),
+                        filtered_candidates: None,
+                        bucket_candidates: Some(take(&mut self.bucket_candidates)),
+                    }));
+                }
+                None => match self.parent.next(params)? {
+                    Some(CriterionResult {
+                        query_tree: Some(query_tree),
+                        candidates,
+                        filtered_candidates,
+                        bucket_candidates,
+                    }) => {
+                        let mut candidates = match candidates {
+                            Some(candidates) => candidates,
+                            None => {
+                                resolve_query_tree(self.ctx, &query_tree, params.wdcache)?
+                                    - params.excluded_candidates
+                            }
+                        };
+
+                        if let Some(filtered_candidates) = filtered_candidates {
+                            candidates &= filtered_candidates;
+                        }
+
+                        match bucket_candidates {
+                            Some(bucket_candidates) => self.bucket_candidates |= bucket_candidates,
+                            None => self.bucket_candidates |= &candidates,
+                        }
+
+                        let mut buckets =
+                            completion_buckets(self.ctx, &query_tree, candidates, params.wdcache)?;
+                        buckets.reverse();
+                        self.buckets = buckets;
+                        self.query_tree = Some(query_tree);
+                    }
+                    Some(CriterionResult {
+                        query_tree: None,
+                        candidates,
+                        filtered_candidates,
+                        bucket_candidates,
+                    }) => {
+                        return Ok(Some(CriterionResult {
+                            query_tree: None,
+                            candidates,
+                            filtered_candidates,
+                            bucket_candidates,
+                        }));
+                    }
+                    None => return Ok(None),
+                },
+            }
+        }
+    }
+}
+
+/// Splits the candidates by the length of their shortest completion of a prefix word,
+/// the candidates without any completion are in the last bucket.
+fn completion_buckets(
+    ctx: &dyn Context,
+    query_tree: &Operation,
+    mut candidates: RoaringBitmap,
+    wdcache: &mut WordDerivationsCache,
+) -> Result<Vec<RoaringBitmap>> {
+    let mut prefixes = Vec::new();
+    prefix_words(query_tree, &mut prefixes);
+
+    let mut completions: BTreeMap<usize, RoaringBitmap> = BTreeMap::new();
+    for prefix in prefixes {
+        let prefix_len = prefix.chars().count();
+        for (word, _) in index_word_derivations(ctx, prefix, true, 0, wdcache)? {
+            if let Some(docids) = ctx.word_docids(word)? {
+                let added_chars = word.chars().count() - prefix_len;
+                *completions.entry(added_chars).or_default() |= docids & &candidates;
+            }
+        }
+    }
+
+    let mut buckets = Vec::with_capacity(completions.len() + 1);
+    for (_, mut bucket) in completions {
+        // the documents are only in the bucket of their shortest completion.
+        bucket &= &candidates;
+        candidates -= &bucket;
+        buckets.push(bucket);
+    }
+    buckets.push(candidates);
+
+    Ok(buckets)
+}
+
+/// Collects the prefix words of the query tree, the words of the phrases are never prefixes.
+fn prefix_words<'o>(query_tree: &'o Operation, words: &mut Vec<&'o str>) {
+    match query_tree {
+        Operation::And(ops) | Operation::Or(_, ops) => {
+            for op in ops {
+                prefix_words(op, words);
+            }
+        }
+        Operation::Query(Query { prefix: true, kind }) => words.push(kind.word()),
+        Operation::Query(_) | Operation::Phrase(_) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::tests::TempIndex;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig};
+    use crate::{Search, SearchResult};
+
+    #[test]
+    fn shorter_completions_first() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "title": "application form" },
+            { "id": 1, "title": "an apple" },
+            { "id": 2, "title": "the app" },
+            { "id": 3, "title": "apple pie and applications" },
+            { "id": 4, "title": "aple" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = Search::new(&rtxn, &index);
+        search.query("app");
+        search.ranking_rules(vec!["words".to_string(), "prefixCompletion".to_string()]);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        // a document is ranked by its shortest completion.
+        assert_eq!(documents_ids, vec![2, 1, 3, 0]);
+
+        search.query("apple");
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        // the documents matched through a typo don't complete the prefix.
+        assert_eq!(documents_ids, vec![1, 3, 0, 4]);
+
+        // without prefix word the documents keep their order.
+        search.query("apple").with_prefix_search(false);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 3, 4]);
+    }
+}
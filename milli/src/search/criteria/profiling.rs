@@ -0,0 +1,60 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use super::{Criterion, CriterionParameters, CriterionResult};
+use crate::Result;
+
+/// The time spent in the criteria of a search.
+#[derive(Debug, Default)]
+pub struct CriteriaProfile {
+    /// The time spent in each criterion, in the order of the criteria chain.
+    timings: Vec<(String, Duration)>,
+    /// The time spent in the parents of each criterion currently being called.
+    parents_elapsed: Vec<Duration>,
+}
+
+impl CriteriaProfile {
+    pub fn timings(&self) -> &[(String, Duration)] {
+        &self.timings
+    }
+}
+
+/// A criterion that forwards the calls to the criterion it wraps and records
+/// the time spent in it, excluding the time spent in its parent criteria.
+pub struct ProfilingCriterion<'t> {
+    position: usize,
+    inner: Box<dyn Criterion + 't>,
+    profile: Rc<RefCell<CriteriaProfile>>,
+}
+
+impl<'t> ProfilingCriterion<'t> {
+    pub fn new(
+        name: String,
+        inner: Box<dyn Criterion + 't>,
+        profile: Rc<RefCell<CriteriaProfile>>,
+    ) -> ProfilingCriterion<'t> {
+        let position = profile.borrow().timings.len();
+        profile.borrow_mut().timings.push((name, Duration::default()));
+        ProfilingCriterion { position, inner, profile }
+    }
+}
+
+impl<'t> Criterion for ProfilingCriterion<'t> {
+    fn next(&mut self, params: &mut CriterionParameters) -> Result<Option<CriterionResult>> {
+        self.profile.borrow_mut().parents_elapsed.push(Duration::default());
+        let before = Instant::now();
+        let result = self.inner.next(params);
+        let elapsed = before.elapsed();
+
+        let mut profile = self.profile.borrow_mut();
+        let parents_elapsed = profile.parents_elapsed.pop().unwrap_or_default();
+        profile.timings[self.position].1 += elapsed.saturating_sub(parents_elapsed);
+        // we are the parent of the criterion that is currently calling us.
+        if let Some(child_parents_elapsed) = profile.parents_elapsed.last_mut() {
+            *child_parents_elapsed += elapsed;
+        }
+
+        result
+    }
+}
@@ -10,7 +10,7 @@ use super::{
     CriterionParameters, CriterionResult,
 };
 use crate::search::query_tree::{maximum_proximity, Operation, Query, QueryKind};
-use crate::search::{build_dfa, WordDerivationsCache};
+use crate::search::{DerivationsDfa, WordDerivationsCache};
 use crate::{Position, Result};
 
 type Cache = HashMap<(Operation, u8), Vec<(Query, Query, RoaringBitmap)>>;
@@ -32,6 +32,8 @@ pub struct Proximity<'t> {
     parent: Box<dyn Criterion + 't>,
     candidates_cache: Cache,
     plane_sweep_cache: Option<btree_map::IntoIter<u8, RoaringBitmap>>,
+    /// The proximity beyond which the remaining candidates are returned in a single bucket.
+    cutoff: Option<u8>,
 }
 
 impl<'t> Proximity<'t> {
@@ -44,8 +46,19 @@ impl<'t> Proximity<'t> {
             parent,
             candidates_cache: Cache::new(),
             plane_sweep_cache: None,
+            cutoff: None,
         }
     }
+
+    /// Only ranks the candidates up to the `max_proximity`, the farther
+    /// candidates are returned together in a last bucket.
+    pub fn with_max_proximity(
+        ctx: &'t dyn Context<'t>,
+        parent: Box<dyn Criterion + 't>,
+        max_proximity: u8,
+    ) -> Self {
+        Proximity { cutoff: Some(max_proximity), ..Proximity::new(ctx, parent) }
+    }
 }
 
 impl<'t> Criterion for Proximity<'t> {
@@ -65,12 +78,25 @@ impl<'t> Criterion for Proximity<'t> {
             );
 
             match &mut self.state {
+                Some((max_prox, query_tree, allowed_candidates))
+                    if self.cutoff.is_some()
+                        && self.proximity > *max_prox
+                        && !allowed_candidates.is_empty() =>
+                {
+                    let candidates = take(allowed_candidates);
+                    return Ok(Some(CriterionResult {
+                        query_tree: Some(query_tree.clone()),
+                        candidates: Some(candidates),
+                        filtered_candidates: None,
+                        bucket_candidates: Some(take(&mut self.bucket_candidates)),
+                    }));
+                }
                 Some((max_prox, _, allowed_candidates))
                     if allowed_candidates.is_empty() || self.proximity > *max_prox =>
                 {
                     self.state = None; // reset state
                 }
-                Some((_, query_tree, allowed_candidates)) => {
+                Some((max_prox, query_tree, allowed_candidates)) => {
                     let mut new_candidates = if allowed_candidates.len() <= CANDIDATES_THRESHOLD
                         && self.proximity > PROXIMITY_THRESHOLD
                     {
@@ -78,6 +104,9 @@ impl<'t> Criterion for Proximity<'t> {
                             match cache.next() {
                                 Some((p, candidates)) => {
                                     self.proximity = p;
+                                    if p > *max_prox {
+                                        continue;
+                                    }
                                     candidates
                                 }
                                 None => {
@@ -141,8 +170,11 @@ impl<'t> Criterion for Proximity<'t> {
                             None => self.bucket_candidates |= &candidates,
                         }
 
-                        let maximum_proximity = maximum_proximity(&query_tree);
-                        self.state = Some((maximum_proximity as u8, query_tree, candidates));
+                        let mut maximum_proximity = maximum_proximity(&query_tree) as u8;
+                        if let Some(cutoff) = self.cutoff {
+                            maximum_proximity = maximum_proximity.min(cutoff);
+                        }
+                        self.state = Some((maximum_proximity, query_tree, candidates));
                         self.proximity = 0;
                         self.plane_sweep_cache = None;
                     }
@@ -467,6 +499,7 @@ fn resolve_plane_sweep_candidates(
     fn resolve_operation<'a>(
         query_tree: &'a Operation,
         rocache: &mut HashMap<&'a Operation, Vec<(Position, u8, Position)>>,
+        first_char_typo: bool,
         words_positions: &HashMap<String, RoaringBitmap>,
     ) -> Result<Vec<(Position, u8, Position)>> {
         use Operation::{And, Or, Phrase};
@@ -479,7 +512,8 @@ fn resolve_plane_sweep_candidates(
             And(ops) => {
                 let mut groups_positions = Vec::with_capacity(ops.len());
                 for operation in ops {
-                    let positions = resolve_operation(operation, rocache, words_positions)?;
+                    let positions =
+                        resolve_operation(operation, rocache, first_char_typo, words_positions)?;
                     groups_positions.push(positions);
                 }
                 plane_sweep(groups_positions, false)?
@@ -498,7 +532,7 @@ fn resolve_plane_sweep_candidates(
             Or(_, ops) => {
                 let mut result = Vec::new();
                 for op in ops {
-                    result.extend(resolve_operation(op, rocache, words_positions)?)
+                    result.extend(resolve_operation(op, rocache, first_char_typo, words_positions)?)
                 }
 
                 result.sort_unstable();
@@ -509,16 +543,23 @@ fn resolve_plane_sweep_candidates(
                 match kind {
                     QueryKind::Exact { word, .. } => {
                         if *prefix {
-                            let iter = word_derivations(word, true, 0, &words_positions)
-                                .flat_map(|positions| positions.iter().map(|p| (p, 0, p)));
+                            let iter =
+                                word_derivations(word, true, 0, first_char_typo, &words_positions)
+                                    .flat_map(|positions| positions.iter().map(|p| (p, 0, p)));
                             result.extend(iter);
                         } else if let Some(positions) = words_positions.get(word) {
                             result.extend(positions.iter().map(|p| (p, 0, p)));
                         }
                     }
                     QueryKind::Tolerant { typo, word } => {
-                        let iter = word_derivations(word, *prefix, *typo, &words_positions)
-                            .flat_map(|positions| positions.iter().map(|p| (p, 0, p)));
+                        let iter = word_derivations(
+                            word,
+                            *prefix,
+                            *typo,
+                            first_char_typo,
+                            &words_positions,
+                        )
+                        .flat_map(|positions| positions.iter().map(|p| (p, 0, p)));
                         result.extend(iter);
                     }
                 }
@@ -536,25 +577,27 @@ fn resolve_plane_sweep_candidates(
         word: &str,
         is_prefix: bool,
         max_typo: u8,
+        first_char_typo: bool,
         words_positions: &'a HashMap<String, RoaringBitmap>,
     ) -> impl Iterator<Item = &'a RoaringBitmap> {
-        let dfa = build_dfa(word, max_typo, is_prefix);
+        let dfa = DerivationsDfa::new(word, max_typo, is_prefix, first_char_typo);
         words_positions.iter().filter_map(move |(document_word, positions)| {
-            use levenshtein_automata::Distance;
-            match dfa.eval(document_word) {
-                Distance::Exact(_) => Some(positions),
-                Distance::AtLeast(_) => None,
-            }
+            dfa.eval(document_word).map(|_| positions)
         })
     }
 
+    let first_char_typo = ctx.attributes_typo_tolerance().allow_typo_on_first_char();
     let mut resolve_operation_cache = HashMap::new();
     let mut candidates = BTreeMap::new();
     for docid in allowed_candidates {
         let words_positions = ctx.docid_words_positions(docid)?;
         resolve_operation_cache.clear();
-        let positions =
-            resolve_operation(query_tree, &mut resolve_operation_cache, &words_positions)?;
+        let positions = resolve_operation(
+            query_tree,
+            &mut resolve_operation_cache,
+            first_char_typo,
+            &words_positions,
+        )?;
         let best_proximity = positions.into_iter().min_by_key(|(_, proximity, _)| *proximity);
         let best_proximity = best_proximity.map(|(_, proximity, _)| proximity).unwrap_or(7);
         candidates.entry(best_proximity).or_insert_with(RoaringBitmap::new).insert(docid);
@@ -0,0 +1,224 @@
+use std::cmp::Reverse;
+use std::mem::take;
+
+use log::debug;
+use ordered_float::OrderedFloat;
+use roaring::RoaringBitmap;
+
+use super::{
+    query_tree_words, resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult,
+};
+use crate::search::query_tree::Operation;
+use crate::search::WordDerivationsCache;
+use crate::{DocumentId, Result};
+
+/// The term frequency saturation of the BM25 formula.
+const K1: f64 = 1.2;
+/// The document length normalization of the BM25 formula.
+const B: f64 = 0.75;
+/// The number of documents the average document length is computed from when
+/// the total number of words of the index is not stored.
+const AVERAGE_LENGTH_SAMPLE: usize = 1000;
+
+/// Computes the BM25 scores of the documents for the words matched by a query tree.
+///
+/// The length of a document is its number of indexed words, all the searchable attributes
+/// included, and the average length is computed from the total number of words counted at
+/// indexing time. The indexes that were filled before it was counted fall back to the first
+/// `AVERAGE_LENGTH_SAMPLE` documents of the index. Each word derivation of the query, typos
+/// and prefixes included, counts as a term.
+pub struct Bm25 {
+    documents_count: f64,
+    average_length: f64,
+}
+
+impl Bm25 {
+    pub fn new(ctx: &dyn Context) -> Result<Bm25> {
+        let documents_ids = ctx.documents_ids()?;
+        let (sampled, total_length) = match ctx.documents_words_count()? {
+            Some(words_count) => (documents_ids.len(), words_count),
+            None => {
+                let mut sampled = 0;
+                let mut total_length = 0;
+                for docid in documents_ids.iter().take(AVERAGE_LENGTH_SAMPLE) {
+                    total_length += document_length(ctx, docid)?;
+                    sampled += 1;
+                }
+                (sampled, total_length)
+            }
+        };
+
+        let average_length = if sampled == 0 { 1.0 } else { total_length as f64 / sampled as f64 };
+        Ok(Bm25 { documents_count: documents_ids.len() as f64, average_length })
+    }
+
+    /// Returns the words the query tree can match with their inverse document frequency.
+    pub fn terms(
+        &self,
+        ctx: &dyn Context,
+        query_tree: &Operation,
+        wdcache: &mut WordDerivationsCache,
+    ) -> Result<Vec<(String, f64)>> {
+        let mut terms = Vec::new();
+        for word in query_tree_words(ctx, query_tree, wdcache)? {
+            let mut docids = ctx.word_docids(&word)?.unwrap_or_default();
+            docids |= ctx.exact_word_docids(&word)?.unwrap_or_default();
+            if docids.is_empty() {
+                continue;
+            }
+
+            let frequency = docids.len() as f64;
+            let idf = (1.0 + (self.documents_count - frequency + 0.5) / (frequency + 0.5)).ln();
+            terms.push((word, idf));
+        }
+        Ok(terms)
+    }
+
+    /// Returns the score of the document for the terms returned by [`Bm25::terms`].
+    pub fn score(
+        &self,
+        ctx: &dyn Context,
+        terms: &[(String, f64)],
+        docid: DocumentId,
+    ) -> Result<f64> {
+        let words_positions = ctx.docid_words_positions(docid)?;
+        let length: u64 = words_positions.values().map(|positions| positions.len()).sum();
+        let normalization = K1 * (1.0 - B + B * length as f64 / self.average_length);
+
+        let mut score = 0.0;
+        for (word, idf) in terms {
+            if let Some(positions) = words_positions.get(word) {
+                let frequency = positions.len() as f64;
+                score += idf * frequency * (K1 + 1.0) / (frequency + normalization);
+            }
+        }
+        Ok(score)
+    }
+}
+
+fn document_length(ctx: &dyn Context, docid: DocumentId) -> Result<u64> {
+    let words_positions = ctx.docid_words_positions(docid)?;
+    Ok(words_positions.values().map(|positions| positions.len()).sum())
+}
+
+/// Ranks the documents of each bucket by decreasing BM25 score, the documents with
+/// the same score are returned together.
+///
+/// The score is computed for every candidate of the bucket, this criterion is
+/// meant to be placed after the criteria that split the candidates in small buckets.
+pub struct Score<'t> {
+    ctx: &'t dyn Context<'t>,
+    bm25: Option<Bm25>,
+    query_tree: Option<Operation>,
+    /// The remaining buckets, in reverse order of relevancy.
+    buckets: Vec<RoaringBitmap>,
+    bucket_candidates: RoaringBitmap,
+    parent: Box<dyn Criterion + 't>,
+}
+
+impl<'t> Score<'t> {
+    pub fn new(ctx: &'t dyn Context<'t>, parent: Box<dyn Criterion + 't>) -> Self {
+        Score {
+            ctx,
+            bm25: None,
+            query_tree: None,
+            buckets: Vec::new(),
+            bucket_candidates: RoaringBitmap::new(),
+            parent,
+        }
+    }
+}
+
+impl<'t> Criterion for Score<'t> {
+    #[logging_timer::time("Score::{}")]
+    fn next(&mut self, params: &mut CriterionParameters) -> Result<Option<CriterionResult>> {
+        loop {
+            debug!("Score at state {:?}", self.buckets);
+
+            match self.buckets.pop() {
+                Some(mut candidates) => {
+                    candidates -= params.excluded_candidates;
+                    if candidates.is_empty() {
+                        continue;
+                    }
+
+                    return Ok(Some(CriterionResult {
+                        query_tree: self.query_tree.clone(),
+                        candidates: Some(candidates),
+                        filtered_candidates: None,
+                        bucket_candidates: Some(take(&mut self.bucket_candidates)),
+                    }));
+                }
+                None => match self.parent.next(params)? {
+                    Some(CriterionResult {
+                        query_tree: Some(query_tree),
+                        candidates,
+                        filtered_candidates,
+                        bucket_candidates,
+                    }) => {
+                        let mut candidates = match candidates {
+                            Some(candidates) => candidates,
+                            None => {
+                                resolve_query_tree(self.ctx, &query_tree, params.wdcache)?
+                                    - params.excluded_candidates
+                            }
+                        };
+
+                        if let Some(filtered_candidates) = filtered_candidates {
+                            candidates &= filtered_candidates;
+                        }
+
+                        match bucket_candidates {
+                            Some(bucket_candidates) => self.bucket_candidates |= bucket_candidates,
+                            None => self.bucket_candidates |= &candidates,
+                        }
+
+                        // the average document length is only computed once per search.
+                        let bm25 = match self.bm25.take() {
+                            Some(bm25) => bm25,
+                            None => Bm25::new(self.ctx)?,
+                        };
+                        let terms = bm25.terms(self.ctx, &query_tree, params.wdcache)?;
+                        let mut scores = Vec::with_capacity(candidates.len() as usize);
+                        for docid in &candidates {
+                            let score = bm25.score(self.ctx, &terms, docid)?;
+                            scores.push((Reverse(OrderedFloat(score)), docid));
+                        }
+                        scores.sort_unstable();
+                        self.bm25 = Some(bm25);
+
+                        // the best bucket is the last one, to be popped first.
+                        let mut buckets: Vec<RoaringBitmap> = Vec::new();
+                        let mut last_score = None;
+                        for (score, docid) in scores {
+                            match buckets.last_mut() {
+                                Some(bucket) if last_score == Some(score) => {
+                                    bucket.insert(docid);
+                                }
+                                _ => buckets.push(std::iter::once(docid).collect()),
+                            }
+                            last_score = Some(score);
+                        }
+                        buckets.reverse();
+                        self.buckets = buckets;
+                        self.query_tree = Some(query_tree);
+                    }
+                    Some(CriterionResult {
+                        query_tree: None,
+                        candidates,
+                        filtered_candidates,
+                        bucket_candidates,
+                    }) => {
+                        return Ok(Some(CriterionResult {
+                            query_tree: None,
+                            candidates,
+                            filtered_candidates,
+                            bucket_candidates,
+                        }));
+                    }
+                    None => return Ok(None),
+                },
+            }
+        }
+    }
+}
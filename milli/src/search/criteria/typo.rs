@@ -1,4 +1,3 @@
-use std::borrow::Cow;
 use std::collections::HashMap;
 use std::mem::take;
 
@@ -6,16 +5,71 @@ use log::debug;
 use roaring::RoaringBitmap;
 
 use super::{
-    query_docids, resolve_query_tree, Candidates, Context, Criterion, CriterionParameters,
-    CriterionResult,
+    index_word_derivations, is_typo_derivation_allowed, query_docids, resolve_query_tree,
+    Candidates, Context, Criterion, CriterionParameters, CriterionResult,
 };
 use crate::search::query_tree::{maximum_typo, Operation, Query, QueryKind};
-use crate::search::{word_derivations, WordDerivationsCache};
-use crate::Result;
+use crate::search::WordDerivationsCache;
+use crate::{AttributeTypoTolerance, FieldId, Index, Result, TypoBudget};
 
 /// Maximum number of typo for a word of any length.
 const MAX_TYPOS_PER_WORD: u8 = 2;
 
+/// The typo tolerance of the attributes: the global minimum word lengths and typo budget,
+/// whether the typos on the first char are forbidden and the overrides defined for some
+/// attributes.
+#[derive(Debug, Default, Clone)]
+pub struct AttributesTypoTolerance {
+    /// The global minimum word lengths for one and two typos.
+    global: (u8, u8),
+    budget: TypoBudget,
+    exact_first_char: bool,
+    overrides: HashMap<FieldId, AttributeTypoTolerance>,
+}
+
+impl AttributesTypoTolerance {
+    pub fn new(rtxn: &heed::RoTxn, index: &Index) -> Result<Self> {
+        let one = index.min_word_len_one_typo(rtxn)?;
+        let two = index.min_word_len_two_typos(rtxn)?;
+        let budget = index.typo_budget(rtxn)?;
+        let exact_first_char = !index.allow_typo_on_first_char(rtxn)?;
+        let fields_ids_map = index.fields_ids_map(rtxn)?;
+        let overrides = index
+            .attributes_typo_tolerance(rtxn)?
+            .into_iter()
+            .filter_map(|(name, tolerance)| fields_ids_map.id(&name).map(|fid| (fid, tolerance)))
+            .collect();
+
+        Ok(AttributesTypoTolerance { global: (one, two), budget, exact_first_char, overrides })
+    }
+
+    /// Returns `true` if a query word with two typos can match a word
+    /// with a different first char.
+    pub fn allow_typo_on_first_char(&self) -> bool {
+        !self.exact_first_char
+    }
+
+    /// Returns `true` if some attributes override the global typo tolerance.
+    pub fn has_overrides(&self) -> bool {
+        !self.overrides.is_empty()
+    }
+
+    /// Returns `true` if a query word of `word_len` chars can match
+    /// with `typos` typos in the attribute `fid`.
+    pub fn tolerates(&self, fid: FieldId, word_len: u8, typos: u8) -> bool {
+        let max_typos = match self.overrides.get(&fid) {
+            Some(tolerance) if !tolerance.enabled => 0,
+            Some(tolerance) => {
+                let thresholds =
+                    (tolerance.min_word_len_one_typo, tolerance.min_word_len_two_typos);
+                TypoBudget::Thresholds.max_typos(word_len, thresholds)
+            }
+            None => self.budget.max_typos(word_len, self.global),
+        };
+        typos <= max_typos
+    }
+}
+
 pub struct Typo<'t> {
     ctx: &'t dyn Context<'t>,
     /// (max_typos, query_tree, candidates)
@@ -66,10 +120,9 @@ impl<'t> Criterion for Typo<'t> {
                     self.state = None; // reset state
                 }
                 Some((_, query_tree, candidates_authorization)) => {
-                    let fst = self.ctx.words_fst();
                     let new_query_tree = match self.typos {
                         typos if typos < MAX_TYPOS_PER_WORD => alterate_query_tree(
-                            &fst,
+                            self.ctx,
                             query_tree.clone(),
                             self.typos,
                             params.wdcache,
@@ -78,7 +131,7 @@ impl<'t> Criterion for Typo<'t> {
                             // When typos >= MAX_TYPOS_PER_WORD, no more alteration of the query tree is possible,
                             // we keep the altered query tree
                             *query_tree = alterate_query_tree(
-                                &fst,
+                                self.ctx,
                                 query_tree.clone(),
                                 self.typos,
                                 params.wdcache,
@@ -180,14 +233,15 @@ impl<'t> Criterion for Typo<'t> {
 /// Modify the query tree by replacing every tolerant query by an Or operation
 /// containing all of the corresponding exact words in the words FST. Each tolerant
 /// query will only be replaced by exact query with up to `number_typos` maximum typos.
+/// The exact words of the index are never derived with typos.
 fn alterate_query_tree(
-    words_fst: &fst::Set<Cow<[u8]>>,
+    ctx: &dyn Context,
     mut query_tree: Operation,
     number_typos: u8,
     wdcache: &mut WordDerivationsCache,
 ) -> Result<Operation> {
     fn recurse(
-        words_fst: &fst::Set<Cow<[u8]>>,
+        ctx: &dyn Context,
         operation: &mut Operation,
         number_typos: u8,
         wdcache: &mut WordDerivationsCache,
@@ -196,7 +250,7 @@ fn alterate_query_tree(
 
         match operation {
             And(ops) | Or(_, ops) => {
-                ops.iter_mut().try_for_each(|op| recurse(words_fst, op, number_typos, wdcache))
+                ops.iter_mut().try_for_each(|op| recurse(ctx, op, number_typos, wdcache))
             }
             // Because Phrases don't allow typos, no alteration can be done.
             Phrase(_words) => return Ok(()),
@@ -207,20 +261,24 @@ fn alterate_query_tree(
                     if number_typos == 0 {
                         *operation = Operation::Query(Query {
                             prefix: q.prefix,
-                            kind: QueryKind::Exact { original_typo: 0, word: word.clone() },
+                            kind: QueryKind::exact(word.clone()),
                         });
                     } else {
                         let typo = *typo.min(&number_typos);
-                        let words = word_derivations(word, q.prefix, typo, words_fst, wdcache)?;
+                        let words = index_word_derivations(ctx, word, q.prefix, typo, wdcache)?;
                         let queries = words
                             .iter()
-                            .map(|(word, typo)| {
+                            .filter(|(derived, typo)| {
+                                is_typo_derivation_allowed(ctx, derived, *typo)
+                            })
+                            .map(|(derived, typo)| {
                                 Operation::Query(Query {
                                     prefix: false,
-                                    kind: QueryKind::Exact {
-                                        original_typo: *typo,
-                                        word: word.to_string(),
-                                    },
+                                    kind: QueryKind::exact_with_typo(
+                                        *typo,
+                                        word,
+                                        derived.to_string(),
+                                    ),
                                 })
                             })
                             .collect();
@@ -234,7 +292,7 @@ fn alterate_query_tree(
         }
     }
 
-    recurse(words_fst, &mut query_tree, number_typos, wdcache)?;
+    recurse(ctx, &mut query_tree, number_typos, wdcache)?;
     Ok(query_tree)
 }
 
@@ -358,6 +416,7 @@ mod test {
         let mut criterion_parameters = CriterionParameters {
             wdcache: &mut WordDerivationsCache::new(),
             excluded_candidates: &RoaringBitmap::new(),
+            geo_distances: &mut HashMap::new(),
         };
 
         let parent = Initial::new(query_tree, facet_candidates);
@@ -393,6 +452,7 @@ mod test {
         let mut criterion_parameters = CriterionParameters {
             wdcache: &mut WordDerivationsCache::new(),
             excluded_candidates: &RoaringBitmap::new(),
+            geo_distances: &mut HashMap::new(),
         };
         let parent = Initial::new(Some(query_tree), facet_candidates);
         let mut criteria = Typo::new(&context, Box::new(parent));
@@ -446,7 +506,7 @@ mod test {
                         vec![
                             Operation::Query(Query {
                                 prefix: false,
-                                kind: QueryKind::exact_with_typo(1, "word".to_string()),
+                                kind: QueryKind::exact_with_typo(1, "world", "word".to_string()),
                             }),
                             Operation::Query(Query {
                                 prefix: false,
@@ -473,6 +533,7 @@ mod test {
         let mut criterion_parameters = CriterionParameters {
             wdcache: &mut WordDerivationsCache::new(),
             excluded_candidates: &RoaringBitmap::new(),
+            geo_distances: &mut HashMap::new(),
         };
         let parent = Initial::new(query_tree, Some(facet_candidates.clone()));
         let mut criteria = Typo::new(&context, Box::new(parent));
@@ -517,6 +578,7 @@ mod test {
         let mut criterion_parameters = CriterionParameters {
             wdcache: &mut WordDerivationsCache::new(),
             excluded_candidates: &RoaringBitmap::new(),
+            geo_distances: &mut HashMap::new(),
         };
         let parent = Initial::new(Some(query_tree), Some(facet_candidates.clone()));
         let mut criteria = Typo::new(&context, Box::new(parent));
@@ -570,7 +632,7 @@ mod test {
                         vec![
                             Operation::Query(Query {
                                 prefix: false,
-                                kind: QueryKind::exact_with_typo(1, "word".to_string()),
+                                kind: QueryKind::exact_with_typo(1, "world", "word".to_string()),
                             }),
                             Operation::Query(Query {
                                 prefix: false,
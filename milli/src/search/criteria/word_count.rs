@@ -0,0 +1,163 @@
+use std::mem::take;
+
+use log::debug;
+use roaring::RoaringBitmap;
+
+use super::{
+    query_tree_words, resolve_query_tree, Context, Criterion, CriterionParameters, CriterionResult,
+};
+use crate::search::query_tree::Operation;
+use crate::search::WordDerivationsCache;
+use crate::{absolute_from_relative_position, Result};
+
+/// The biggest number of words of an attribute stored in the `field_id_word_count_docids`
+/// database, the attributes with more words are not counted at indexing time.
+pub const MAX_COUNTED_WORDS: u8 = 10;
+
+/// Ranks the documents by the increasing number of words of the shortest attribute
+/// that matches the query, the documents where the query words make up a bigger part
+/// of an attribute being considered better.
+///
+/// The number of words of the attributes comes from the `field_id_word_count_docids` database,
+/// that only knows about the attributes of at most `MAX_COUNTED_WORDS` words: the documents whose
+/// matched attributes are all longer are returned together in the last bucket.
+pub struct WordCount<'t> {
+    ctx: &'t dyn Context<'t>,
+    query_tree: Option<Operation>,
+    /// The remaining buckets, in reverse order of relevancy.
+    buckets: Vec<RoaringBitmap>,
+    bucket_candidates: RoaringBitmap,
+    parent: Box<dyn Criterion + 't>,
+}
+
+impl<'t> WordCount<'t> {
+    pub fn new(ctx: &'t dyn Context<'t>, parent: Box<dyn Criterion + 't>) -> Self {
+        WordCount {
+            ctx,
+            query_tree: None,
+            buckets: Vec::new(),
+            bucket_candidates: RoaringBitmap::new(),
+            parent,
+        }
+    }
+}
+
+impl<'t> Criterion for WordCount<'t> {
+    #[logging_timer::time("WordCount::{}")]
+    fn next(&mut self, params: &mut CriterionParameters) -> Result<Option<CriterionResult>> {
+        loop {
+            debug!("WordCount at state {:?}", self.buckets);
+
+            match self.buckets.pop() {
+                Some(mut candidates) => {
+                    candidates -= params.excluded_candidates;
+                    if candidates.is_empty() {
+                        continue;
+                    }
+
+                    return Ok(Some(CriterionResult {
+                        query_tree: self.query_tree.clone(),
+                        candidates: Some(candidates),
+                        filtered_candidates: None,
+                        bucket_candidates: Some(take(&mut self.bucket_candidates)),
+                    }));
+                }
+                None => match self.parent.next(params)? {
+                    Some(CriterionResult {
+                        query_tree: Some(query_tree),
+                        candidates,
+                        filtered_candidates,
+                        bucket_candidates,
+                    }) => {
+                        let mut candidates = match candidates {
+                            Some(candidates) => candidates,
+                            None => {
+                                resolve_query_tree(self.ctx, &query_tree, params.wdcache)?
+                                    - params.excluded_candidates
+                            }
+                        };
+
+                        if let Some(filtered_candidates) = filtered_candidates {
+                            candidates &= filtered_candidates;
+                        }
+
+                        match bucket_candidates {
+                            Some(bucket_candidates) => self.bucket_candidates |= bucket_candidates,
+                            None => self.bucket_candidates |= &candidates,
+                        }
+
+                        let mut buckets =
+                            word_count_buckets(self.ctx, &query_tree, candidates, params.wdcache)?;
+                        buckets.reverse();
+                        self.buckets = buckets;
+                        self.query_tree = Some(query_tree);
+                    }
+                    Some(CriterionResult {
+                        query_tree: None,
+                        candidates,
+                        filtered_candidates,
+                        bucket_candidates,
+                    }) => {
+                        return Ok(Some(CriterionResult {
+                            query_tree: None,
+                            candidates,
+                            filtered_candidates,
+                            bucket_candidates,
+                        }));
+                    }
+                    None => return Ok(None),
+                },
+            }
+        }
+    }
+}
+
+/// Splits the candidates by the number of words of their shortest matched attribute,
+/// the candidates without any counted matched attribute are in the last bucket.
+fn word_count_buckets(
+    ctx: &dyn Context,
+    query_tree: &Operation,
+    mut candidates: RoaringBitmap,
+    wdcache: &mut WordDerivationsCache,
+) -> Result<Vec<RoaringBitmap>> {
+    let words = query_tree_words(ctx, query_tree, wdcache)?;
+    let fields_ids = ctx.searchable_fields_ids()?;
+
+    let mut buckets = vec![RoaringBitmap::new(); MAX_COUNTED_WORDS as usize];
+    for &field_id in &fields_ids {
+        // the documents with a query word at the positions before the word count, they
+        // are only looked up once for the increasing word counts of the field.
+        let mut matched = RoaringBitmap::new();
+        let mut matched_positions = 0;
+        for (word_count, docids) in ctx.field_id_word_counts_docids(field_id)? {
+            if word_count == 0 || word_count > MAX_COUNTED_WORDS {
+                continue;
+            }
+            let field_candidates = docids & &candidates;
+            if field_candidates.is_empty() {
+                continue;
+            }
+
+            // the words of an attribute of `word_count` words are all at the first positions.
+            for position in matched_positions..word_count as u16 {
+                let position = absolute_from_relative_position(field_id, position);
+                for word in &words {
+                    if let Some(docids) = ctx.word_position_docids(word, position)? {
+                        matched |= docids;
+                    }
+                }
+            }
+            matched_positions = matched_positions.max(word_count as u16);
+            buckets[word_count as usize - 1] |= field_candidates & &matched;
+        }
+    }
+
+    // a document is in the bucket of its shortest matched attribute.
+    for bucket in &mut buckets {
+        *bucket &= &candidates;
+        candidates -= &*bucket;
+    }
+    buckets.push(candidates);
+
+    Ok(buckets)
+}
@@ -0,0 +1,247 @@
+use std::ops::Bound::{Excluded, Included, Unbounded};
+
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+
+use crate::error::UserError;
+use crate::search::facet::Filter;
+use crate::{DocumentId, FieldId, Index, Result};
+
+/// A field the documents are sorted by and whether they are sorted in ascending order.
+pub type SortField = (String, bool);
+
+/// The value a document is sorted by, the documents having a number are sorted before
+/// the ones having a string, then before the ones having a boolean, and the documents
+/// without any value are always sorted last.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum SortValue {
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Missing,
+}
+
+/// The position of the last document of a page in a search sorted by some fields,
+/// the documents with the same sort values are ordered by their internal ids.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchCursor {
+    fields: Vec<SortField>,
+    values: Vec<SortValue>,
+    docid: DocumentId,
+}
+
+impl SearchCursor {
+    /// Creates the cursor pointing right after this document.
+    pub fn new(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        fields: Vec<SortField>,
+        docid: DocumentId,
+    ) -> Result<SearchCursor> {
+        let fields_ids_map = index.fields_ids_map(rtxn)?;
+        let mut values = Vec::with_capacity(fields.len());
+        for (name, is_ascending) in &fields {
+            let field_id = fields_ids_map.id(name);
+            values.push(sort_value(rtxn, index, field_id, docid, *is_ascending)?);
+        }
+        Ok(SearchCursor { fields, values, docid })
+    }
+
+    /// Encodes the cursor into an opaque string.
+    pub fn encode(&self) -> String {
+        let bytes = serde_json::to_vec(self).unwrap();
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Decodes a cursor, it must have been created for a search sorted by the same fields.
+    pub fn decode(cursor: &str, fields: &[SortField]) -> Result<SearchCursor> {
+        let invalid_cursor = || UserError::InvalidSearchCursor { cursor: cursor.to_string() };
+        let bytes: Option<Vec<u8>> = cursor
+            .as_bytes()
+            .chunks(2)
+            .map(|hex| {
+                std::str::from_utf8(hex).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            })
+            .collect();
+        let bytes = bytes.ok_or_else(invalid_cursor)?;
+        let cursor: SearchCursor = serde_json::from_slice(&bytes).map_err(|_| invalid_cursor())?;
+
+        if cursor.fields != fields || cursor.values.len() != fields.len() {
+            return Err(invalid_cursor().into());
+        }
+        Ok(cursor)
+    }
+
+    /// Returns the candidates that are sorted after the document of this cursor.
+    ///
+    /// The candidates are split by the facet databases of each sort field, the cost only
+    /// depends on the number of facet values and not on the number of candidates.
+    pub fn candidates_after(
+        &self,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        candidates: &RoaringBitmap,
+    ) -> Result<RoaringBitmap> {
+        let fields_ids_map = index.fields_ids_map(rtxn)?;
+
+        let mut after = RoaringBitmap::new();
+        // the candidates with the same sort values as the document of the cursor so far.
+        let mut equal = candidates.clone();
+        for ((name, is_ascending), value) in self.fields.iter().zip(&self.values) {
+            if equal.is_empty() {
+                break;
+            }
+            let (field_after, field_equal) = match fields_ids_map.id(name) {
+                Some(field_id) => {
+                    split_by_sort_value(rtxn, index, field_id, &equal, value, *is_ascending)?
+                }
+                // no document has a value, they are all missing and sorted last.
+                None if *value == SortValue::Missing => (RoaringBitmap::new(), equal),
+                None => (equal, RoaringBitmap::new()),
+            };
+            after |= field_after;
+            equal = field_equal;
+        }
+
+        equal.remove_range(..=self.docid);
+        after |= equal;
+
+        Ok(after)
+    }
+}
+
+/// Splits the documents into the ones that are sorted after this sort value of the field and
+/// the ones that have the same sort value. A document is sorted by its smallest value in
+/// ascending order and by its biggest one in descending order, like in `sort_value`.
+fn split_by_sort_value(
+    rtxn: &heed::RoTxn,
+    index: &Index,
+    field_id: FieldId,
+    docids: &RoaringBitmap,
+    value: &SortValue,
+    is_ascending: bool,
+) -> Result<(RoaringBitmap, RoaringBitmap)> {
+    let numbers = index.number_faceted_documents_ids(rtxn, field_id)? & docids;
+    let strings = (index.string_faceted_documents_ids(rtxn, field_id)? & docids) - &numbers;
+    // the booleans are sorted like the `AscDesc` criterion does, `false` first in ascending
+    // order, a document with both booleans is sorted by the first one.
+    let (first_boolean, second_boolean) = if is_ascending { (false, true) } else { (true, false) };
+    let first_booleans = (index.boolean_faceted_documents_ids(rtxn, field_id, first_boolean)?
+        & docids)
+        - &numbers
+        - &strings;
+    let second_booleans = (index.boolean_faceted_documents_ids(rtxn, field_id, second_boolean)?
+        & docids)
+        - &numbers
+        - &strings
+        - &first_booleans;
+    let booleans = &first_booleans | &second_booleans;
+    let missing = docids - &numbers - &strings - &booleans;
+
+    match value {
+        SortValue::Number(number) => {
+            let db = index.facet_id_f64_docids;
+            // the documents with a value before the cursor one and up to it.
+            let (before, up_to) = if is_ascending {
+                (
+                    Filter::number_range_docids(
+                        rtxn,
+                        db,
+                        field_id,
+                        Included(f64::MIN),
+                        Excluded(*number),
+                    )?,
+                    Filter::number_range_docids(
+                        rtxn,
+                        db,
+                        field_id,
+                        Included(f64::MIN),
+                        Included(*number),
+                    )?,
+                )
+            } else {
+                (
+                    Filter::number_range_docids(
+                        rtxn,
+                        db,
+                        field_id,
+                        Excluded(*number),
+                        Included(f64::MAX),
+                    )?,
+                    Filter::number_range_docids(
+                        rtxn,
+                        db,
+                        field_id,
+                        Included(*number),
+                        Included(f64::MAX),
+                    )?,
+                )
+            };
+            let after = (&numbers - &up_to) | strings | booleans | missing;
+            let equal = (numbers & up_to) - before;
+            Ok((after, equal))
+        }
+        SortValue::String(string) => {
+            let db = index.facet_id_string_docids;
+            let string = string.as_str();
+            let (before, up_to) = if is_ascending {
+                (
+                    Filter::string_range_docids(rtxn, db, field_id, Unbounded, Excluded(string))?,
+                    Filter::string_range_docids(rtxn, db, field_id, Unbounded, Included(string))?,
+                )
+            } else {
+                (
+                    Filter::string_range_docids(rtxn, db, field_id, Excluded(string), Unbounded)?,
+                    Filter::string_range_docids(rtxn, db, field_id, Included(string), Unbounded)?,
+                )
+            };
+            let after = (&strings - &up_to) | booleans | missing;
+            let equal = (strings & up_to) - before;
+            Ok((after, equal))
+        }
+        SortValue::Boolean(boolean) if *boolean == first_boolean => {
+            Ok((second_booleans | missing, first_booleans))
+        }
+        SortValue::Boolean(_) => Ok((missing, second_booleans)),
+        SortValue::Missing => Ok((RoaringBitmap::new(), missing)),
+    }
+}
+
+/// Returns the value the document is sorted by, like the `AscDesc` criterion the smallest
+/// value is used in ascending order and the biggest one in descending order.
+fn sort_value(
+    rtxn: &heed::RoTxn,
+    index: &Index,
+    field_id: Option<FieldId>,
+    docid: DocumentId,
+    is_ascending: bool,
+) -> Result<SortValue> {
+    let field_id = match field_id {
+        Some(field_id) => field_id,
+        None => return Ok(SortValue::Missing),
+    };
+
+    let left = (field_id, docid, f64::MIN);
+    let right = (field_id, docid, f64::MAX);
+    let mut iter = index.field_id_docid_facet_f64s.range(rtxn, &(left..=right))?;
+    let entry = if is_ascending { iter.next() } else { iter.last() };
+    if let Some(((_, _, value), ())) = entry.transpose()? {
+        return Ok(SortValue::Number(value));
+    }
+
+    let left = (field_id, docid, "");
+    let right = (field_id, docid.saturating_add(1), "");
+    let mut iter = index.field_id_docid_facet_strings.range(rtxn, &(left..right))?;
+    let entry = if is_ascending { iter.next() } else { iter.last() };
+    if let Some(((_, _, value), _)) = entry.transpose()? {
+        return Ok(SortValue::String(value.to_string()));
+    }
+
+    let booleans = if is_ascending { [false, true] } else { [true, false] };
+    for boolean in booleans {
+        if index.boolean_faceted_documents_ids(rtxn, field_id, boolean)?.contains(docid) {
+            return Ok(SortValue::Boolean(boolean));
+        }
+    }
+    Ok(SortValue::Missing)
+}
@@ -16,26 +16,29 @@ const DOCID_SIZE: usize = size_of::<DocumentId>();
 /// A distinct implementer that is backed by facets.
 ///
 /// On each iteration, the facet values for the
-/// distinct attribute of the first document are retrieved. The document ids for these facet values
-/// are then retrieved and taken out of the the candidate and added to the excluded set. We take
-/// care to keep the document we are currently on, and remove it from the excluded list. The next
-/// iterations will never contain any occurence of a document with the same distinct value as a
-/// document from previous iterations.
+/// distinct attributes of the first document are retrieved. The document ids sharing a value with
+/// it for every distinct attribute are then retrieved and taken out of the the candidate and added
+/// to the excluded set. We take care to keep the document we are currently on, and remove it from
+/// the excluded list. The next iterations will never contain any occurence of a document with the
+/// same distinct values as a document from previous iterations.
+///
+/// A distinct attribute missing from a document is considered as a value of its own, shared with
+/// the other documents missing it. A document missing all the distinct attributes is always kept.
 pub struct FacetDistinct<'a> {
-    distinct: FieldId,
+    distinct: Vec<FieldId>,
     index: &'a Index,
     txn: &'a heed::RoTxn<'a>,
 }
 
 impl<'a> FacetDistinct<'a> {
-    pub fn new(distinct: FieldId, index: &'a Index, txn: &'a heed::RoTxn<'a>) -> Self {
+    pub fn new(distinct: Vec<FieldId>, index: &'a Index, txn: &'a heed::RoTxn<'a>) -> Self {
         Self { distinct, index, txn }
     }
 }
 
 pub struct FacetDistinctIter<'a> {
     candidates: RoaringBitmap,
-    distinct: FieldId,
+    distinct: Vec<FieldId>,
     excluded: RoaringBitmap,
     index: &'a Index,
     iter_offset: usize,
@@ -43,47 +46,76 @@ pub struct FacetDistinctIter<'a> {
 }
 
 impl<'a> FacetDistinctIter<'a> {
-    fn facet_string_docids(&self, key: &str) -> heed::Result<Option<RoaringBitmap>> {
+    fn facet_string_docids(&self, fid: FieldId, key: &str) -> heed::Result<Option<RoaringBitmap>> {
         self.index
             .facet_id_string_docids
-            .get(self.txn, &(self.distinct, key))
+            .get(self.txn, &(fid, key))
             .map(|result| result.map(|(_original, docids)| docids))
     }
 
-    fn facet_number_docids(&self, key: f64) -> heed::Result<Option<RoaringBitmap>> {
+    fn facet_number_docids(&self, fid: FieldId, key: f64) -> heed::Result<Option<RoaringBitmap>> {
         // get facet docids on level 0
-        self.index.facet_id_f64_docids.get(self.txn, &(self.distinct, 0, key, key))
+        self.index.facet_id_f64_docids.get(self.txn, &(fid, 0, key, key))
     }
 
-    fn distinct_string(&mut self, id: DocumentId) -> Result<()> {
-        let iter = facet_string_values(id, self.distinct, self.index, self.txn)?;
+    /// Returns the documents sharing at least one facet string, facet number or boolean with
+    /// the given document for this field, or `None` if the document has no value for it.
+    fn field_docids(&self, fid: FieldId, id: DocumentId) -> Result<Option<RoaringBitmap>> {
+        let mut docids = None;
 
-        for item in iter {
+        for item in facet_string_values(id, fid, self.index, self.txn)? {
             let ((_, _, value), _) = item?;
-            let facet_docids =
-                self.facet_string_docids(value)?.ok_or(InternalError::DatabaseMissingEntry {
+            let facet_docids = self.facet_string_docids(fid, value)?.ok_or(
+                InternalError::DatabaseMissingEntry {
                     db_name: db_name::FACET_ID_STRING_DOCIDS,
                     key: None,
-                })?;
-            self.excluded |= facet_docids;
+                },
+            )?;
+            *docids.get_or_insert_with(RoaringBitmap::new) |= facet_docids;
         }
 
-        self.excluded.remove(id);
+        for item in facet_number_values(id, fid, self.index, self.txn)? {
+            let ((_, _, value), _) = item?;
+            let facet_docids = self.facet_number_docids(fid, value)?.ok_or(
+                InternalError::DatabaseMissingEntry {
+                    db_name: db_name::FACET_ID_F64_DOCIDS,
+                    key: None,
+                },
+            )?;
+            *docids.get_or_insert_with(RoaringBitmap::new) |= facet_docids;
+        }
 
-        Ok(())
+        for boolean in [false, true] {
+            let facet_docids = self.index.boolean_faceted_documents_ids(self.txn, fid, boolean)?;
+            if facet_docids.contains(id) {
+                *docids.get_or_insert_with(RoaringBitmap::new) |= facet_docids;
+            }
+        }
+
+        Ok(docids)
     }
 
-    fn distinct_number(&mut self, id: DocumentId) -> Result<()> {
-        let iter = facet_number_values(id, self.distinct, self.index, self.txn)?;
+    fn distinct_document(&mut self, id: DocumentId) -> Result<()> {
+        let mut shared: Option<RoaringBitmap> = None;
+        let mut missing = Vec::new();
+        for &fid in &self.distinct {
+            match self.field_docids(fid, id)? {
+                Some(docids) => {
+                    shared = Some(match shared {
+                        Some(shared) => shared & docids,
+                        None => docids,
+                    })
+                }
+                None => missing.push(fid),
+            }
+        }
 
-        for item in iter {
-            let ((_, _, value), _) = item?;
-            let facet_docids =
-                self.facet_number_docids(value)?.ok_or(InternalError::DatabaseMissingEntry {
-                    db_name: db_name::FACET_ID_F64_DOCIDS,
-                    key: None,
-                })?;
-            self.excluded |= facet_docids;
+        if let Some(mut shared) = shared {
+            // the documents having a value for a field the document is missing are different.
+            for fid in missing {
+                shared -= self.index.faceted_documents_ids(self.txn, fid)?;
+            }
+            self.excluded |= shared;
         }
 
         self.excluded.remove(id);
@@ -102,8 +134,7 @@ impl<'a> FacetDistinctIter<'a> {
         match candidates_iter.next() {
             Some(id) => {
                 // We distinct the document id on its facet strings and facet numbers.
-                self.distinct_string(id)?;
-                self.distinct_number(id)?;
+                self.distinct_document(id)?;
 
                 // The first document of each iteration is kept, since the next call to
                 // `difference_with` will filter out all the documents for that facet value. By
@@ -177,7 +208,7 @@ impl<'a> Distinct for FacetDistinct<'a> {
     fn distinct(&mut self, candidates: RoaringBitmap, excluded: RoaringBitmap) -> Self::Iter {
         FacetDistinctIter {
             candidates,
-            distinct: self.distinct,
+            distinct: self.distinct.clone(),
             excluded,
             index: self.index,
             iter_offset: 0,
@@ -197,7 +228,7 @@ mod test {
             fn $name() {
                 let (index, fid, candidates) = generate_index($distinct);
                 let txn = index.read_txn().unwrap();
-                let mut map_distinct = FacetDistinct::new(fid, &index, &txn);
+                let mut map_distinct = FacetDistinct::new(vec![fid], &index, &txn);
                 let excluded = RoaringBitmap::new();
                 let mut iter = map_distinct.distinct(candidates.clone(), excluded);
                 let count = validate_distinct_candidates(iter.by_ref(), fid, &index);
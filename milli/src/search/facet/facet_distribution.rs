@@ -1,4 +1,5 @@
-use std::collections::{BTreeMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashSet};
 use std::ops::Bound::Unbounded;
 use std::{fmt, mem};
 
@@ -25,6 +26,8 @@ pub struct FacetDistribution<'a> {
     facets: Option<HashSet<String>>,
     candidates: Option<RoaringBitmap>,
     max_values_per_facet: usize,
+    order_by_count: bool,
+    zero_count_facets: HashSet<String>,
     rtxn: &'a heed::RoTxn<'a>,
     index: &'a Index,
 }
@@ -35,6 +38,8 @@ impl<'a> FacetDistribution<'a> {
             facets: None,
             candidates: None,
             max_values_per_facet: DEFAULT_VALUES_PER_FACET,
+            order_by_count: false,
+            zero_count_facets: HashSet::new(),
             rtxn,
             index,
         }
@@ -50,6 +55,30 @@ impl<'a> FacetDistribution<'a> {
         self
     }
 
+    /// Returns the `max_values_per_facet` most frequent values of each facet, the values
+    /// with the same count are ordered lexicographically. By default the first values in
+    /// lexicographic order are returned.
+    pub fn order_by_count(&mut self, value: bool) -> &mut Self {
+        self.order_by_count = value;
+        self
+    }
+
+    /// Returns all the values of these facets, the values that none of the candidates contain
+    /// are returned with a count of 0. The values are still limited to `max_values_per_facet`.
+    pub fn with_zero_counts<I: IntoIterator<Item = A>, A: AsRef<str>>(
+        &mut self,
+        names: I,
+    ) -> &mut Self {
+        self.zero_count_facets = names.into_iter().map(|s| s.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Restricts the distribution to these documents, the docids of each facet value are
+    /// directly intersected with them. The candidates are usually the `candidates` of a
+    /// [`SearchResult`](crate::SearchResult), which are already filtered, as no filter
+    /// is evaluated here.
+    ///
+    /// By default the distribution is computed over all the documents of the index.
     pub fn candidates(&mut self, candidates: RoaringBitmap) -> &mut Self {
         self.candidates = Some(candidates);
         self
@@ -62,6 +91,7 @@ impl<'a> FacetDistribution<'a> {
         field_id: FieldId,
         facet_type: FacetType,
         candidates: &RoaringBitmap,
+        max_values: usize,
         distribution: &mut BTreeMap<String, u64>,
     ) -> heed::Result<()> {
         match facet_type {
@@ -82,8 +112,7 @@ impl<'a> FacetDistribution<'a> {
                         let ((_, _, value), ()) = result?;
                         *distribution.entry(value.to_string()).or_insert(0) += 1;
 
-                        if distribution.len() - distribution_prelength == self.max_values_per_facet
-                        {
+                        if distribution.len() - distribution_prelength == max_values {
                             break;
                         }
                     }
@@ -109,7 +138,7 @@ impl<'a> FacetDistribution<'a> {
                             .or_insert_with(|| (original_value, 0));
                         *count += 1;
 
-                        if normalized_distribution.len() == self.max_values_per_facet {
+                        if normalized_distribution.len() == max_values {
                             break;
                         }
                     }
@@ -126,24 +155,27 @@ impl<'a> FacetDistribution<'a> {
     }
 
     /// There is too much documents, we use the facet levels to move throught
-    /// the facet values, to find the candidates and values associated.
+    /// the facet values, to find the candidates and values associated. The `values`
+    /// already given are counted in the `max_values_per_facet` of the field.
     fn facet_numbers_distribution_from_facet_levels(
         &self,
         field_id: FieldId,
         candidates: &RoaringBitmap,
-        distribution: &mut BTreeMap<String, u64>,
-    ) -> heed::Result<()> {
+        values: &mut usize,
+        f: &mut dyn FnMut(&str, u64) -> Result<()>,
+    ) -> Result<()> {
         let iter =
             FacetNumberIter::new_non_reducing(self.rtxn, self.index, field_id, candidates.clone())?;
 
         for result in iter {
+            if *values == self.max_values_per_facet {
+                break;
+            }
             let (value, mut docids) = result?;
             docids &= candidates;
             if !docids.is_empty() {
-                distribution.insert(value.to_string(), docids.len());
-            }
-            if distribution.len() == self.max_values_per_facet {
-                break;
+                f(&value.to_string(), docids.len())?;
+                *values += 1;
             }
         }
 
@@ -154,19 +186,21 @@ impl<'a> FacetDistribution<'a> {
         &self,
         field_id: FieldId,
         candidates: &RoaringBitmap,
-        distribution: &mut BTreeMap<String, u64>,
-    ) -> heed::Result<()> {
+        values: &mut usize,
+        f: &mut dyn FnMut(&str, u64) -> Result<()>,
+    ) -> Result<()> {
         let iter =
             FacetStringIter::new_non_reducing(self.rtxn, self.index, field_id, candidates.clone())?;
 
         for result in iter {
+            if *values == self.max_values_per_facet {
+                break;
+            }
             let (_normalized, original, mut docids) = result?;
             docids &= candidates;
             if !docids.is_empty() {
-                distribution.insert(original.to_string(), docids.len());
-            }
-            if distribution.len() == self.max_values_per_facet {
-                break;
+                f(original, docids.len())?;
+                *values += 1;
             }
         }
 
@@ -178,20 +212,29 @@ impl<'a> FacetDistribution<'a> {
     fn facet_values_from_raw_facet_database(
         &self,
         field_id: FieldId,
-    ) -> heed::Result<BTreeMap<String, u64>> {
-        let mut distribution = BTreeMap::new();
+        f: &mut dyn FnMut(&str, u64) -> Result<()>,
+    ) -> Result<()> {
+        // the facet databases still contain the soft deleted documents.
+        let soft_deleted_documents_ids = self.index.soft_deleted_documents_ids(self.rtxn)?;
 
         let db = self.index.facet_id_f64_docids;
         let range = FacetNumberRange::new(self.rtxn, db, field_id, 0, Unbounded, Unbounded)?;
 
+        let mut count = 0;
         for result in range {
-            let ((_, _, value, _), docids) = result?;
-            distribution.insert(value.to_string(), docids.len());
-            if distribution.len() == self.max_values_per_facet {
+            if count == self.max_values_per_facet {
                 break;
             }
+            let ((_, _, value, _), mut docids) = result?;
+            docids -= &soft_deleted_documents_ids;
+            if !docids.is_empty() {
+                f(&value.to_string(), docids.len())?;
+                count += 1;
+            }
         }
 
+        // the strings are ordered by normalized value in the database, they share the
+        // `max_values_per_facet` of the field with the numbers.
         let iter = self
             .index
             .facet_id_string_docids
@@ -199,64 +242,404 @@ impl<'a> FacetDistribution<'a> {
             .prefix_iter(self.rtxn, &field_id.to_be_bytes())?
             .remap_key_type::<FacetStringLevelZeroCodec>();
 
-        let mut normalized_distribution = BTreeMap::new();
         for result in iter {
-            let ((_, normalized_value), (original_value, docids)) = result?;
-            normalized_distribution.insert(normalized_value, (original_value, docids.len()));
-            if normalized_distribution.len() == self.max_values_per_facet {
+            if count == self.max_values_per_facet {
                 break;
             }
+            let ((_, _normalized), (original, mut docids)) = result?;
+            docids -= &soft_deleted_documents_ids;
+            if !docids.is_empty() {
+                f(original, docids.len())?;
+                count += 1;
+            }
         }
 
-        let iter = normalized_distribution
-            .into_iter()
-            .map(|(_normalized, (original, count))| (original.to_string(), count));
-        distribution.extend(iter);
-
-        Ok(distribution)
+        Ok(())
     }
 
-    fn facet_values(&self, field_id: FieldId) -> heed::Result<BTreeMap<String, u64>> {
+    /// Calls `f` with the values of the field and their counts, the numbers then the strings.
+    /// Only the distribution computed document by document is collected before `f` is called.
+    fn for_each_facet_value(
+        &self,
+        field_id: FieldId,
+        f: &mut dyn FnMut(&str, u64) -> Result<()>,
+    ) -> Result<()> {
         use FacetType::{Number, String};
 
         match self.candidates {
-            Some(ref candidates) => {
-                // Classic search, candidates were specified, we must return facet values only related
-                // to those candidates. We also enter here for facet strings for performance reasons.
+            // Classic search, candidates were specified, we must return facet values only related
+            // to those candidates. We also enter here for facet strings for performance reasons.
+            Some(ref candidates) if candidates.len() <= CANDIDATES_THRESHOLD => {
                 let mut distribution = BTreeMap::new();
-                if candidates.len() <= CANDIDATES_THRESHOLD {
+                for facet_type in [Number, String] {
                     self.facet_distribution_from_documents(
                         field_id,
-                        Number,
+                        facet_type,
                         candidates,
+                        self.max_values_per_facet,
                         &mut distribution,
                     )?;
+                }
+                for (value, count) in distribution {
+                    f(&value, count)?;
+                }
+                Ok(())
+            }
+            Some(ref candidates) => {
+                let mut values = 0;
+                self.facet_numbers_distribution_from_facet_levels(
+                    field_id,
+                    candidates,
+                    &mut values,
+                    f,
+                )?;
+                self.facet_strings_distribution_from_facet_levels(
+                    field_id,
+                    candidates,
+                    &mut values,
+                    f,
+                )
+            }
+            None => self.facet_values_from_raw_facet_database(field_id, f),
+        }
+    }
+
+    fn facet_values(&self, field_id: FieldId) -> Result<BTreeMap<String, u64>> {
+        let mut distribution = BTreeMap::new();
+        self.for_each_facet_value(field_id, &mut |value, count| {
+            distribution.insert(value.to_string(), count);
+            Ok(())
+        })?;
+        Ok(distribution)
+    }
+
+    /// Keeps the most frequent facet values of the field, it goes through all
+    /// the values but only keeps `max_values_per_facet` of them in memory.
+    fn facet_values_by_count(&self, field_id: FieldId) -> heed::Result<Vec<(String, u64)>> {
+        use FacetType::{Number, String};
+
+        let mut top_values = TopValues::new(self.max_values_per_facet);
+        match self.candidates {
+            Some(ref candidates) if candidates.len() <= CANDIDATES_THRESHOLD => {
+                // the counts are computed document by document, we must see all
+                // the documents before knowing which values are the most frequent.
+                let mut distribution = BTreeMap::new();
+                for facet_type in [Number, String] {
                     self.facet_distribution_from_documents(
                         field_id,
-                        String,
+                        facet_type,
                         candidates,
+                        usize::MAX,
                         &mut distribution,
                     )?;
+                }
+                for (value, count) in distribution {
+                    top_values.insert(&value, count);
+                }
+            }
+            Some(ref candidates) => {
+                let iter = FacetNumberIter::new_non_reducing(
+                    self.rtxn,
+                    self.index,
+                    field_id,
+                    candidates.clone(),
+                )?;
+                for result in iter {
+                    let (value, mut docids) = result?;
+                    docids &= candidates;
+                    if !docids.is_empty() {
+                        top_values.insert(&value.to_string(), docids.len());
+                    }
+                }
+
+                let iter = FacetStringIter::new_non_reducing(
+                    self.rtxn,
+                    self.index,
+                    field_id,
+                    candidates.clone(),
+                )?;
+                for result in iter {
+                    let (_normalized, original, mut docids) = result?;
+                    docids &= candidates;
+                    if !docids.is_empty() {
+                        top_values.insert(original, docids.len());
+                    }
+                }
+            }
+            None => {
+                let soft_deleted_documents_ids =
+                    self.index.soft_deleted_documents_ids(self.rtxn)?;
+                let db = self.index.facet_id_f64_docids;
+                let range =
+                    FacetNumberRange::new(self.rtxn, db, field_id, 0, Unbounded, Unbounded)?;
+                for result in range {
+                    let ((_, _, value, _), mut docids) = result?;
+                    docids -= &soft_deleted_documents_ids;
+                    if !docids.is_empty() {
+                        top_values.insert(&value.to_string(), docids.len());
+                    }
+                }
+
+                let iter = self
+                    .index
+                    .facet_id_string_docids
+                    .remap_key_type::<ByteSlice>()
+                    .prefix_iter(self.rtxn, &field_id.to_be_bytes())?
+                    .remap_key_type::<FacetStringLevelZeroCodec>();
+                for result in iter {
+                    let ((_, _normalized), (original, mut docids)) = result?;
+                    docids -= &soft_deleted_documents_ids;
+                    if !docids.is_empty() {
+                        top_values.insert(original, docids.len());
+                    }
+                }
+            }
+        }
+
+        Ok(top_values.into_sorted_vec())
+    }
+
+    /// Goes through all the values of the facet database, each one of them is counted
+    /// even when none of the candidates contain it.
+    fn facet_values_with_zero_counts(&self, field_id: FieldId) -> heed::Result<Vec<(String, u64)>> {
+        // the facet databases still contain the soft deleted documents,
+        // the values only they contain are skipped.
+        let soft_deleted_documents_ids = self.index.soft_deleted_documents_ids(self.rtxn)?;
+        let count = |mut docids: RoaringBitmap| {
+            docids -= &soft_deleted_documents_ids;
+            match self.candidates {
+                _ if docids.is_empty() => None,
+                Some(ref candidates) => Some((docids & candidates).len()),
+                None => Some(docids.len()),
+            }
+        };
+        // the most frequent values are only known once all the values are counted.
+        let max_values = if self.order_by_count { usize::MAX } else { self.max_values_per_facet };
+
+        let mut distribution = BTreeMap::new();
+        let db = self.index.facet_id_f64_docids;
+        let range = FacetNumberRange::new(self.rtxn, db, field_id, 0, Unbounded, Unbounded)?;
+        for result in range {
+            if distribution.len() == max_values {
+                break;
+            }
+            let ((_, _, value, _), docids) = result?;
+            if let Some(count) = count(docids) {
+                distribution.insert(value.to_string(), count);
+            }
+        }
+
+        // the numbers and the strings are capped together, a string written like
+        // a number is counted with it.
+        let iter = self
+            .index
+            .facet_id_string_docids
+            .remap_key_type::<ByteSlice>()
+            .prefix_iter(self.rtxn, &field_id.to_be_bytes())?
+            .remap_key_type::<FacetStringLevelZeroCodec>();
+        for result in iter {
+            let ((_, _normalized), (original, docids)) = result?;
+            if let Some(count) = count(docids) {
+                match distribution.get_mut(original) {
+                    Some(total) => *total += count,
+                    None if distribution.len() == max_values => break,
+                    None => {
+                        distribution.insert(original.to_string(), count);
+                    }
+                }
+            }
+        }
+
+        if self.order_by_count {
+            let mut top_values = TopValues::new(self.max_values_per_facet);
+            for (value, count) in distribution {
+                top_values.insert(&value, count);
+            }
+            Ok(top_values.into_sorted_vec())
+        } else {
+            Ok(distribution.into_iter().collect())
+        }
+    }
+
+    /// Same as `execute` but the values of each facet are returned in order, by count
+    /// descending when `order_by_count` is set, lexicographically otherwise.
+    pub fn execute_ordered(&self) -> Result<BTreeMap<String, Vec<(String, u64)>>> {
+        let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+        let fields = self.faceted_fields()?;
+
+        let mut distribution = BTreeMap::new();
+        for (fid, name) in fields_ids_map.iter() {
+            if crate::is_faceted(name, &fields) {
+                let values = if crate::is_faceted(name, &self.zero_count_facets) {
+                    self.facet_values_with_zero_counts(fid)?
+                } else if self.order_by_count {
+                    self.facet_values_by_count(fid)?
                 } else {
-                    self.facet_numbers_distribution_from_facet_levels(
-                        field_id,
-                        candidates,
-                        &mut distribution,
-                    )?;
-                    self.facet_strings_distribution_from_facet_levels(
-                        field_id,
-                        candidates,
-                        &mut distribution,
-                    )?;
+                    self.facet_values(fid)?.into_iter().collect()
+                };
+                distribution.insert(name.to_string(), values);
+            }
+        }
+
+        Ok(distribution)
+    }
+
+    /// Same as `execute` but `f` is called with each facet, value and count as they are
+    /// computed instead of collecting the distribution of every facet. The facets are
+    /// visited one by one, the numbers of a facet are given before its strings and the
+    /// values are not sorted lexicographically unless `order_by_count` or the zero counts
+    /// are requested for it.
+    pub fn execute_streaming<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&str, &str, u64) -> Result<()>,
+    {
+        let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+        let fields = self.faceted_fields()?;
+
+        for (fid, name) in fields_ids_map.iter() {
+            if crate::is_faceted(name, &fields) {
+                let values = if crate::is_faceted(name, &self.zero_count_facets) {
+                    self.facet_values_with_zero_counts(fid)?
+                } else if self.order_by_count {
+                    self.facet_values_by_count(fid)?
+                } else {
+                    self.for_each_facet_value(fid, &mut |value, count| f(name, value, count))?;
+                    continue;
+                };
+                for (value, count) in values {
+                    f(name, &value, count)?;
                 }
-                Ok(distribution)
             }
-            None => self.facet_values_from_raw_facet_database(field_id),
         }
+
+        Ok(())
     }
 
     pub fn execute(&self) -> Result<BTreeMap<String, BTreeMap<String, u64>>> {
+        let distribution = self.execute_ordered()?;
+        Ok(distribution
+            .into_iter()
+            .map(|(name, values)| (name, values.into_iter().collect()))
+            .collect())
+    }
+
+    /// Same as `execute` but the counts are estimated from a sample of about `sample_size`
+    /// candidates, or documents if no candidates were specified, when there are more of them.
+    /// The counts of the sample are scaled to the number of candidates. The candidates are
+    /// sampled by hashing them with the seed, a seed always gives the same sample of the
+    /// same candidates.
+    ///
+    /// The values missing from the sample are missing from the distribution.
+    pub fn execute_sampled(&self, sample_size: u64, seed: u64) -> Result<SampledFacetDistribution> {
+        let candidates = match self.candidates {
+            Some(ref candidates) => candidates.clone(),
+            None => self.index.documents_ids(self.rtxn)?,
+        };
+        if candidates.len() <= sample_size {
+            return Ok(SampledFacetDistribution {
+                distribution: self.execute()?,
+                approximate: false,
+            });
+        }
+
+        let sample = sample_candidates(&candidates, sample_size, seed);
+        let scale = candidates.len() as f64 / sample.len().max(1) as f64;
+        let sampled = FacetDistribution {
+            facets: self.facets.clone(),
+            candidates: Some(sample),
+            max_values_per_facet: self.max_values_per_facet,
+            order_by_count: self.order_by_count,
+            zero_count_facets: self.zero_count_facets.clone(),
+            rtxn: self.rtxn,
+            index: self.index,
+        };
+
+        let distribution = sampled
+            .execute()?
+            .into_iter()
+            .map(|(name, values)| {
+                let values = values
+                    .into_iter()
+                    .map(|(value, count)| (value, (count as f64 * scale).round() as u64))
+                    .collect();
+                (name, values)
+            })
+            .collect();
+
+        Ok(SampledFacetDistribution { distribution, approximate: true })
+    }
+
+    /// Returns the lowest and highest numeric values of each facet over the candidates,
+    /// or over all the documents if no candidates were specified. The facets without any
+    /// numeric value for these documents are skipped.
+    pub fn compute_stats(&self) -> Result<BTreeMap<String, FacetStats>> {
+        let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+        let fields = self.faceted_fields()?;
+        let candidates = match self.candidates {
+            Some(ref candidates) => candidates.clone(),
+            None => self.index.documents_ids(self.rtxn)?,
+        };
+
+        let mut stats = BTreeMap::new();
+        for (fid, name) in fields_ids_map.iter() {
+            if crate::is_faceted(name, &fields) {
+                // the facet levels are ordered, the first and last values that
+                // intersect the candidates are the extreme ones.
+                let mut iter =
+                    FacetNumberIter::new_reducing(self.rtxn, self.index, fid, candidates.clone())?;
+                let min = iter.next().transpose()?;
+                let mut iter = FacetNumberIter::new_reverse_reducing(
+                    self.rtxn,
+                    self.index,
+                    fid,
+                    candidates.clone(),
+                )?;
+                let max = iter.next().transpose()?;
+
+                if let (Some((min, _)), Some((max, _))) = (min, max) {
+                    stats.insert(name.to_string(), FacetStats { min, max });
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Returns the number of candidates, or of documents if no candidates were specified,
+    /// faceted with `false` and with `true` for each facet. The booleans are not part of the
+    /// strings returned by `execute`, the facets without any boolean are skipped.
+    pub fn compute_booleans(&self) -> Result<BTreeMap<String, BTreeMap<bool, u64>>> {
         let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+        let fields = self.faceted_fields()?;
+
+        let soft_deleted_documents_ids = self.index.soft_deleted_documents_ids(self.rtxn)?;
+
+        let mut distribution = BTreeMap::new();
+        for (fid, name) in fields_ids_map.iter() {
+            if crate::is_faceted(name, &fields) {
+                let mut counts = BTreeMap::new();
+                for boolean in [false, true] {
+                    let docids =
+                        self.index.boolean_faceted_documents_ids(self.rtxn, fid, boolean)?;
+                    let count = match self.candidates {
+                        Some(ref candidates) => (docids & candidates).len(),
+                        None => (docids - &soft_deleted_documents_ids).len(),
+                    };
+                    if count != 0 {
+                        counts.insert(boolean, count);
+                    }
+                }
+                if !counts.is_empty() {
+                    distribution.insert(name.to_string(), counts);
+                }
+            }
+        }
+
+        Ok(distribution)
+    }
+
+    /// Returns the facets that must be returned, the filterable fields by default.
+    fn faceted_fields(&self) -> Result<HashSet<String>> {
         let filterable_fields = self.index.filterable_fields(self.rtxn)?;
 
         let fields = match self.facets {
@@ -277,27 +660,97 @@ impl<'a> FacetDistribution<'a> {
             None => filterable_fields,
         };
 
-        let mut distribution = BTreeMap::new();
-        for (fid, name) in fields_ids_map.iter() {
-            if crate::is_faceted(name, &fields) {
-                let values = self.facet_values(fid)?;
-                distribution.insert(name.to_string(), values);
+        Ok(fields)
+    }
+}
+
+/// A facet distribution whose counts may be estimated from a sample of the candidates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampledFacetDistribution {
+    pub distribution: BTreeMap<String, BTreeMap<String, u64>>,
+    /// `true` if the counts are estimated, `false` if there were not more candidates than
+    /// the size of the sample and the counts are exact.
+    pub approximate: bool,
+}
+
+/// Keeps each candidate with a probability of `size / candidates.len()`, the candidates are
+/// kept when their hash with the seed is low enough.
+fn sample_candidates(candidates: &RoaringBitmap, size: u64, seed: u64) -> RoaringBitmap {
+    let ratio = size as f64 / candidates.len() as f64;
+    let threshold = (ratio * u64::MAX as f64) as u64;
+    candidates
+        .iter()
+        .filter(|&docid| splitmix64(seed ^ splitmix64(docid as u64)) < threshold)
+        .collect()
+}
+
+/// The SplitMix64 mixing function, it spreads the close values over all the 64 bits.
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// The lowest and highest values of a numeric facet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FacetStats {
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Keeps the most frequent facet values seen so far, the values with the
+/// same count are ordered lexicographically.
+pub(super) struct TopValues {
+    max: usize,
+    /// A min-heap whose top is the least frequent value that is kept.
+    heap: BinaryHeap<Reverse<(u64, Reverse<String>)>>,
+}
+
+impl TopValues {
+    pub(super) fn new(max: usize) -> TopValues {
+        TopValues { max, heap: BinaryHeap::new() }
+    }
+
+    pub(super) fn insert(&mut self, value: &str, count: u64) {
+        if self.heap.len() < self.max {
+            self.heap.push(Reverse((count, Reverse(value.to_string()))));
+        } else if let Some(mut least) = self.heap.peek_mut() {
+            let Reverse((least_count, Reverse(least_value))) = &*least;
+            if count > *least_count || (count == *least_count && value < least_value.as_str()) {
+                *least = Reverse((count, Reverse(value.to_string())));
             }
         }
+    }
 
-        Ok(distribution)
+    /// Returns the values by count descending.
+    pub(super) fn into_sorted_vec(self) -> Vec<(String, u64)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((count, Reverse(value)))| (value, count))
+            .collect()
     }
 }
 
 impl fmt::Debug for FacetDistribution<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let FacetDistribution { facets, candidates, max_values_per_facet, rtxn: _, index: _ } =
-            self;
+        let FacetDistribution {
+            facets,
+            candidates,
+            max_values_per_facet,
+            order_by_count,
+            zero_count_facets,
+            rtxn: _,
+            index: _,
+        } = self;
 
         f.debug_struct("FacetDistribution")
             .field("facets", facets)
             .field("candidates", candidates)
             .field("max_values_per_facet", max_values_per_facet)
+            .field("order_by_count", order_by_count)
+            .field("zero_count_facets", zero_count_facets)
             .finish()
     }
 }
@@ -0,0 +1,124 @@
+use std::fmt;
+use std::ops::Bound::{Included, Unbounded};
+
+use roaring::RoaringBitmap;
+
+use super::facet_distribution::TopValues;
+use super::facet_string::FacetStringLevelZeroRange;
+use super::DEFAULT_VALUES_PER_FACET;
+use crate::error::UserError;
+use crate::{Index, Result};
+
+/// Searches the string values of a filterable field, to autocomplete a filter for example.
+pub struct FacetSearch<'a> {
+    field: String,
+    query: Option<String>,
+    candidates: Option<RoaringBitmap>,
+    max_values: usize,
+    rtxn: &'a heed::RoTxn<'a>,
+    index: &'a Index,
+}
+
+impl<'a> FacetSearch<'a> {
+    pub fn new(
+        rtxn: &'a heed::RoTxn,
+        index: &'a Index,
+        field: impl Into<String>,
+    ) -> FacetSearch<'a> {
+        FacetSearch {
+            field: field.into(),
+            query: None,
+            candidates: None,
+            max_values: DEFAULT_VALUES_PER_FACET,
+            rtxn,
+            index,
+        }
+    }
+
+    /// Only returns the values starting with this prefix, the prefix is lowercased
+    /// like the facet values. By default all the values of the field are returned.
+    pub fn query(&mut self, query: impl Into<String>) -> &mut Self {
+        self.query = Some(query.into());
+        self
+    }
+
+    /// Only counts the documents of the values among these candidates, the values
+    /// that none of them contain are not returned.
+    pub fn candidates(&mut self, candidates: RoaringBitmap) -> &mut Self {
+        self.candidates = Some(candidates);
+        self
+    }
+
+    pub fn max_values(&mut self, max: usize) -> &mut Self {
+        self.max_values = max;
+        self
+    }
+
+    /// Returns the `max_values` most frequent values matching the query with their number
+    /// of documents, by count descending, the values with the same count are ordered
+    /// lexicographically. The values are returned as they were written in the documents.
+    pub fn execute(&self) -> Result<Vec<(String, u64)>> {
+        let filterable_fields = self.index.filterable_fields(self.rtxn)?;
+        if !crate::is_faceted(&self.field, &filterable_fields) {
+            return Err(UserError::InvalidFacetSearchAttribute {
+                field: self.field.clone(),
+                valid_fields: filterable_fields.into_iter().collect(),
+            }
+            .into());
+        }
+
+        let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+        let field_id = match fields_ids_map.id(&self.field) {
+            Some(field_id) => field_id,
+            // no document contains this filterable field yet.
+            None => return Ok(Vec::new()),
+        };
+
+        // the facet strings are stored trimmed and lowercased, ordered lexicographically.
+        let prefix = self.query.as_deref().map_or_else(String::new, |q| q.trim().to_lowercase());
+        let db = self.index.facet_id_string_docids;
+        let iter = FacetStringLevelZeroRange::new(
+            self.rtxn,
+            db,
+            field_id,
+            Included(prefix.as_str()),
+            Unbounded,
+        )?;
+
+        // the facet databases still contain the soft deleted documents.
+        let soft_deleted_documents_ids = match self.candidates {
+            Some(_) => RoaringBitmap::new(),
+            None => self.index.soft_deleted_documents_ids(self.rtxn)?,
+        };
+
+        let mut top_values = TopValues::new(self.max_values);
+        for result in iter {
+            let (normalized, original, mut docids) = result?;
+            if !normalized.starts_with(&prefix) {
+                break;
+            }
+            match &self.candidates {
+                Some(candidates) => docids &= candidates,
+                None => docids -= &soft_deleted_documents_ids,
+            }
+            if !docids.is_empty() {
+                top_values.insert(original, docids.len());
+            }
+        }
+
+        Ok(top_values.into_sorted_vec())
+    }
+}
+
+impl fmt::Debug for FacetSearch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let FacetSearch { field, query, candidates, max_values, rtxn: _, index: _ } = self;
+
+        f.debug_struct("FacetSearch")
+            .field("field", field)
+            .field("query", query)
+            .field("candidates", candidates)
+            .field("max_values", max_values)
+            .finish()
+    }
+}
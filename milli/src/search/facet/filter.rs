@@ -1,25 +1,88 @@
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display};
-use std::ops::Bound::{self, Excluded, Included};
+use std::ops::Bound::{self, Excluded, Included, Unbounded};
 
+use charabia::TokenizerBuilder;
 use either::Either;
 pub use filter_parser::{Condition, Error as FPError, FilterCondition, Span, Token};
 use heed::types::DecodeIgnore;
 use log::debug;
 use roaring::RoaringBitmap;
+use rstar::{RTree, AABB};
 
+use super::facet_string::FacetStringLevelZeroRange;
 use super::FacetNumberRange;
 use crate::error::{Error, UserError};
 use crate::heed_codec::facet::{
     FacetLevelValueF64Codec, FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec,
 };
 use crate::{
-    distance_between_two_points, lat_lng_to_xyz, CboRoaringBitmapCodec, FieldId, Index, Result,
+    distance_between_two_points, lat_lng_to_xyz, planar_distance_between_two_points,
+    CboRoaringBitmapCodec, FieldId, GeoDistance, GeoPoint, Index, Result, EARTH_RADIUS,
 };
 
 /// The maximum number of filters the filter AST can process.
 const MAX_FILTER_DEPTH: usize = 2000;
 
+/// The canonical values of the raw string values, by field id then raw value.
+type FacetValueMappings = HashMap<FieldId, HashMap<String, String>>;
+
+/// The values loaded once for all the leaves of a filter evaluation.
+struct EvaluationCache<'c> {
+    mappings: FacetValueMappings,
+    /// Normalizes the strings with the tokenizer used to normalize the words of the
+    /// documents, this removes the case and the accents differences.
+    normalize: Box<dyn Fn(&str) -> String + 'c>,
+    /// The documents ids of the normalized strings of the normalized fields, by field id
+    /// then normalized string.
+    normalized_strings: RefCell<HashMap<FieldId, HashMap<String, RoaringBitmap>>>,
+}
+
+impl<'c> EvaluationCache<'c> {
+    fn new(rtxn: &heed::RoTxn, index: &Index) -> Result<Self> {
+        let tokenizer = TokenizerBuilder::default().build();
+        let normalize = move |value: &str| -> String {
+            tokenizer.tokenize(value.trim()).map(|token| token.lemma().to_string()).collect()
+        };
+        Ok(EvaluationCache {
+            mappings: index.facet_value_mappings_ids(rtxn)?,
+            normalize: Box::new(normalize),
+            normalized_strings: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the documents ids of the strings of the field that are normalized like the value.
+    ///
+    /// The facet strings are only stored lowercased, the strings of a field are all normalized
+    /// the first time the field is looked up and the next values are then looked up directly.
+    fn normalized_string_docids(
+        &self,
+        rtxn: &heed::RoTxn,
+        strings_db: heed::Database<FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec>,
+        field_id: FieldId,
+        value: &str,
+    ) -> Result<RoaringBitmap> {
+        let mut normalized_strings = self.normalized_strings.borrow_mut();
+        let strings = match normalized_strings.entry(field_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let mut strings: HashMap<String, RoaringBitmap> = HashMap::new();
+                let iter = FacetStringLevelZeroRange::new(
+                    rtxn, strings_db, field_id, Unbounded, Unbounded,
+                )?;
+                for result in iter {
+                    let (normalized, _original, docids) = result?;
+                    *strings.entry((self.normalize)(normalized)).or_default() |= docids;
+                }
+                entry.insert(strings)
+            }
+        };
+        Ok(strings.get(&(self.normalize)(value)).cloned().unwrap_or_default())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Filter<'a> {
     condition: FilterCondition<'a>,
@@ -31,6 +94,8 @@ enum FilterError<'a> {
     BadGeo(&'a str),
     BadGeoLat(f64),
     BadGeoLng(f64),
+    BadGeoBoundingBoxTopIsBelowBottom(f64, f64),
+    BadGeoPolygonTooFewPoints(usize),
     Reserved(&'a str),
     TooDeep,
 }
@@ -69,6 +134,8 @@ impl<'a> Display for FilterError<'a> {
             Self::BadGeo(keyword) => write!(f, "`{}` is a reserved keyword and thus can't be used as a filter expression. Use the _geoRadius(latitude, longitude, distance) built-in rule to filter on _geo field coordinates.", keyword),
             Self::BadGeoLat(lat) => write!(f, "Bad latitude `{}`. Latitude must be contained between -90 and 90 degrees. ", lat),
             Self::BadGeoLng(lng) => write!(f, "Bad longitude `{}`. Longitude must be contained between -180 and 180 degrees. ", lng),
+            Self::BadGeoBoundingBoxTopIsBelowBottom(top, bottom) => write!(f, "The top latitude `{}` is below the bottom latitude `{}`.", top, bottom),
+            Self::BadGeoPolygonTooFewPoints(count) => write!(f, "The polygon must have at least three distinct points but only `{}` were given.", count),
         }
     }
 }
@@ -268,6 +335,7 @@ impl<'a> Filter<'a> {
         index: &Index,
         numbers_db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
         strings_db: heed::Database<FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec>,
+        cache: &EvaluationCache,
         field_id: FieldId,
         operator: &Condition<'a>,
     ) -> Result<RoaringBitmap> {
@@ -282,9 +350,25 @@ impl<'a> Filter<'a> {
             Condition::LowerThanOrEqual(val) => (Included(f64::MIN), Included(val.parse()?)),
             Condition::Between { from, to } => (Included(from.parse()?), Included(to.parse()?)),
             Condition::Equal(val) => {
-                let (_original_value, string_docids) = strings_db
-                    .get(rtxn, &(field_id, &val.value().to_lowercase()))?
-                    .unwrap_or_default();
+                // the booleans are written without quotes, `"true"` is only a string.
+                if let Some(boolean) = Self::boolean_value(val) {
+                    return Ok(index.boolean_faceted_documents_ids(rtxn, field_id, boolean)?);
+                }
+
+                // the raw values of a mapped field are indexed as their canonical value.
+                let value = cache
+                    .mappings
+                    .get(&field_id)
+                    .and_then(|mapping| mapping.get(&val.value().trim().to_lowercase()))
+                    .map_or(val.value(), String::as_str);
+                let string_docids = if Self::is_normalized_field(rtxn, index, field_id)? {
+                    cache.normalized_string_docids(rtxn, strings_db, field_id, value)?
+                } else {
+                    let (_original_value, string_docids) = strings_db
+                        .get(rtxn, &(field_id, &value.to_lowercase()))?
+                        .unwrap_or_default();
+                    string_docids
+                };
                 let number = val.parse::<f64>().ok();
                 let number_docids = match number {
                     Some(n) => {
@@ -306,16 +390,27 @@ impl<'a> Filter<'a> {
                 return Ok(string_docids | number_docids);
             }
             Condition::NotEqual(val) => {
-                let all_numbers_ids = index.number_faceted_documents_ids(rtxn, field_id)?;
-                let all_strings_ids = index.string_faceted_documents_ids(rtxn, field_id)?;
+                let all_ids = index.faceted_documents_ids(rtxn, field_id)?;
                 let operator = Condition::Equal(val.clone());
                 let docids = Self::evaluate_operator(
-                    rtxn, index, numbers_db, strings_db, field_id, &operator,
+                    rtxn, index, numbers_db, strings_db, cache, field_id, &operator,
                 )?;
-                return Ok((all_numbers_ids | all_strings_ids) - docids);
+                return Ok(all_ids - docids);
             }
         };
 
+        Self::number_range_docids(rtxn, numbers_db, field_id, left, right)
+    }
+
+    /// Returns the documents ids that have a number of this field in the range, from the
+    /// biggest level of the field.
+    pub(crate) fn number_range_docids(
+        rtxn: &heed::RoTxn,
+        numbers_db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
+        field_id: FieldId,
+        left: Bound<f64>,
+        right: Bound<f64>,
+    ) -> Result<RoaringBitmap> {
         // Ask for the biggest value that can exist for this specific field, if it exists
         // that's fine if it don't, the value just before will be returned instead.
         let biggest_level = numbers_db
@@ -341,51 +436,392 @@ impl<'a> Filter<'a> {
         }
     }
 
+    /// Returns the documents ids that have a normalized string of this field in the range.
+    pub(crate) fn string_range_docids(
+        rtxn: &heed::RoTxn,
+        strings_db: heed::Database<FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec>,
+        field_id: FieldId,
+        left: Bound<&str>,
+        right: Bound<&str>,
+    ) -> Result<RoaringBitmap> {
+        let mut output = RoaringBitmap::new();
+        for result in FacetStringLevelZeroRange::new(rtxn, strings_db, field_id, left, right)? {
+            let (_normalized, _original, docids) = result?;
+            output |= docids;
+        }
+        Ok(output)
+    }
+
+    /// Returns the boolean written by an unquoted `true` or `false` value.
+    fn boolean_value(value: &Token) -> Option<bool> {
+        match value.value() {
+            _ if value.is_quoted() => None,
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the string values of the field must be compared
+    /// regardless of their case and accents.
+    fn is_normalized_field(rtxn: &heed::RoTxn, index: &Index, field_id: FieldId) -> Result<bool> {
+        let normalized_fields = index.normalized_filterable_fields(rtxn)?;
+        if normalized_fields.is_empty() {
+            return Ok(false);
+        }
+        let field_ids_map = index.fields_ids_map(rtxn)?;
+        Ok(field_ids_map
+            .name(field_id)
+            .map_or(false, |name| crate::is_faceted(name, &normalized_fields)))
+    }
+
+    /// Returns the id of the field if it is filterable, `None` if the field is filterable
+    /// but does not appear in any document, and an error if it is not filterable.
+    fn filterable_field_id(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        fid: &Token<'a>,
+    ) -> Result<Option<FieldId>> {
+        let filterable_fields = index.filterable_fields(rtxn)?;
+
+        if crate::is_faceted(fid.value(), &filterable_fields) {
+            let field_ids_map = index.fields_ids_map(rtxn)?;
+            Ok(field_ids_map.id(fid.value()))
+        } else {
+            match fid.lexeme() {
+                attribute @ "_geo" => {
+                    return Err(fid.as_external_error(FilterError::BadGeo(attribute)))?;
+                }
+                attribute if attribute.starts_with("_geoPoint(") => {
+                    return Err(fid.as_external_error(FilterError::BadGeo("_geoPoint")))?;
+                }
+                attribute @ "_geoDistance" => {
+                    return Err(fid.as_external_error(FilterError::Reserved(attribute)))?;
+                }
+                attribute => {
+                    return Err(fid.as_external_error(FilterError::AttributeNotFilterable {
+                        attribute,
+                        filterable_fields,
+                    }))?;
+                }
+            }
+        }
+    }
+
+    /// Returns the union of the documents ids that are equal to any of the values,
+    /// an empty list of values matches no document.
+    fn evaluate_in(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        numbers_db: heed::Database<FacetLevelValueF64Codec, CboRoaringBitmapCodec>,
+        strings_db: heed::Database<FacetStringLevelZeroCodec, FacetStringLevelZeroValueCodec>,
+        cache: &EvaluationCache,
+        field_id: FieldId,
+        els: &[Token<'a>],
+    ) -> Result<RoaringBitmap> {
+        let mut docids = RoaringBitmap::new();
+        for el in els {
+            let operator = Condition::Equal(el.clone());
+            docids |= Self::evaluate_operator(
+                rtxn, index, numbers_db, strings_db, cache, field_id, &operator,
+            )?;
+        }
+        Ok(docids)
+    }
+
+    /// Returns the documents ids that have a value for the field or for any of its nested
+    /// fields. The `null` values, empty arrays and empty objects are not faceted and thus
+    /// are considered as missing.
+    fn evaluate_exists(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        fid: &Token<'a>,
+    ) -> Result<RoaringBitmap> {
+        // we only make sure that the field is filterable, an object is flattened into its
+        // nested fields and doesn't have a field id.
+        Self::filterable_field_id(rtxn, index, fid)?;
+
+        let field_ids_map = index.fields_ids_map(rtxn)?;
+        let mut docids = RoaringBitmap::new();
+        for (field_id, name) in field_ids_map.iter() {
+            if crate::is_faceted_by(name, fid.value()) {
+                docids |= index.faceted_documents_ids(rtxn, field_id)?;
+            }
+        }
+        Ok(docids)
+    }
+
+    /// Returns the documents ids that contain the field but without any value: `null`, an
+    /// empty array or an empty object. A document with a value in one of the nested fields
+    /// is not empty, it `EXISTS`.
+    fn evaluate_is_empty(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        fid: &Token<'a>,
+    ) -> Result<RoaringBitmap> {
+        let field_ids_map = index.fields_ids_map(rtxn)?;
+        let mut docids = RoaringBitmap::new();
+        for (field_id, name) in field_ids_map.iter() {
+            if crate::is_faceted_by(name, fid.value()) {
+                docids |= index.empty_faceted_documents_ids(rtxn, field_id)?;
+            }
+        }
+        Ok(docids - Self::evaluate_exists(rtxn, index, fid)?)
+    }
+
+    /// Returns the documents ids with a string value of the field that contains the substring,
+    /// both compared lowercased or normalized if the field is a normalized filterable field.
+    ///
+    /// The facet strings are exact values, all the values of the field are scanned: the cost
+    /// is O(distinct values) and `CONTAINS` is better used on the low-cardinality fields. When
+    /// the `candidates` are known, only them are looked for and the scan stops once they all
+    /// match. The values of a mapped field are indexed as their canonical value, a canonical
+    /// value also matches when one of the raw values mapped to it contains the substring.
+    fn evaluate_contains(
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        cache: &EvaluationCache,
+        fid: &Token<'a>,
+        value: &Token<'a>,
+        candidates: Option<&RoaringBitmap>,
+    ) -> Result<RoaringBitmap> {
+        let field_id = match Self::filterable_field_id(rtxn, index, fid)? {
+            Some(field_id) => field_id,
+            None => return Ok(RoaringBitmap::new()),
+        };
+
+        let is_normalized = Self::is_normalized_field(rtxn, index, field_id)?;
+        let tokenizer = TokenizerBuilder::default().build();
+        let normalize = |value: &str| -> String {
+            if is_normalized {
+                tokenizer.tokenize(value).map(|token| token.lemma().to_string()).collect()
+            } else {
+                value.to_lowercase()
+            }
+        };
+
+        let substring = normalize(value.value());
+        let mapped: HashSet<_> = cache
+            .mappings
+            .get(&field_id)
+            .into_iter()
+            .flatten()
+            .filter(|(raw, _canonical)| normalize(raw).contains(&substring))
+            .map(|(_raw, canonical)| canonical.trim().to_lowercase())
+            .collect();
+        let mut docids = RoaringBitmap::new();
+        let iter = FacetStringLevelZeroRange::new(
+            rtxn,
+            index.facet_id_string_docids,
+            field_id,
+            Unbounded,
+            Unbounded,
+        )?;
+        for result in iter {
+            let (normalized, _original, value_docids) = result?;
+            if !normalize(normalized).contains(&substring) && !mapped.contains(normalized) {
+                continue;
+            }
+            match candidates {
+                Some(candidates) => {
+                    docids |= value_docids & candidates;
+                    if docids.len() == candidates.len() {
+                        break;
+                    }
+                }
+                None => docids |= value_docids,
+            }
+        }
+        Ok(docids)
+    }
+
     pub fn evaluate(&self, rtxn: &heed::RoTxn, index: &Index) -> Result<RoaringBitmap> {
+        // the operands that can't change the result are never evaluated,
+        // the attributes of the whole filter are checked beforehand.
+        Self::check_filterable(&self.condition, rtxn, index)?;
+
+        // all the documents ids of the databases, the soft deleted ones included.
+        let mut universe = index.documents_ids(rtxn)?;
+        universe |= index.soft_deleted_documents_ids(rtxn)?;
+
+        // the mappings and the normalized strings are loaded once for all the leaves.
+        let cache = EvaluationCache::new(rtxn, index)?;
+
+        let mut leaves = Vec::new();
+        Self::evaluate_memoized(&self.condition, rtxn, index, &cache, &universe, &mut leaves)
+    }
+
+    /// Makes sure that all the attributes the condition filters on are filterable.
+    fn check_filterable(
+        condition: &FilterCondition<'a>,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+    ) -> Result<()> {
+        match condition {
+            FilterCondition::Or(lhs, rhs) | FilterCondition::And(lhs, rhs) => {
+                Self::check_filterable(lhs, rtxn, index)?;
+                Self::check_filterable(rhs, rtxn, index)
+            }
+            FilterCondition::GeoLowerThan { point: [token, _], .. }
+            | FilterCondition::GeoGreaterThan { point: [token, _], .. } => {
+                Self::check_geo_filterable("_geoRadius", token, rtxn, index)
+            }
+            FilterCondition::GeoBoundingBox { top_right_point: [token, _], .. }
+            | FilterCondition::GeoOutsideBoundingBox { top_right_point: [token, _], .. } => {
+                Self::check_geo_filterable("_geoBoundingBox", token, rtxn, index)
+            }
+            FilterCondition::GeoPolygon { points }
+            | FilterCondition::GeoOutsidePolygon { points } => match points.first() {
+                Some([token, _]) => Self::check_geo_filterable("_geoPolygon", token, rtxn, index),
+                None => Ok(()),
+            },
+            leaf => {
+                if let Some(fid) = leaf.token_at_depth(0) {
+                    Self::filterable_field_id(rtxn, index, fid)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Makes sure that the `_geo` field is filterable, the error points to the token,
+    /// and that the geo database used by the `filter` is not disabled.
+    fn check_geo_filterable(
+        filter: &str,
+        token: &Token,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+    ) -> Result<()> {
+        let filterable_fields = index.filterable_fields(rtxn)?;
+        if !filterable_fields.contains("_geo") {
+            return Err(token.as_external_error(FilterError::AttributeNotFilterable {
+                attribute: "_geo",
+                filterable_fields,
+            }))?;
+        }
+        if index.disabled_databases(rtxn)?.geo {
+            return Err(UserError::CriterionDatabaseDisabled {
+                criterion: filter.to_string(),
+                database: "geo",
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Evaluates the condition, the leaves that appear several times are only evaluated once
+    /// and the right operand of an `AND` or an `OR` is skipped when the left one is enough.
+    fn evaluate_memoized(
+        condition: &FilterCondition<'a>,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        cache: &EvaluationCache,
+        universe: &RoaringBitmap,
+        leaves: &mut Vec<(FilterCondition<'a>, RoaringBitmap)>,
+    ) -> Result<RoaringBitmap> {
+        match condition {
+            FilterCondition::And(lhs, rhs) => {
+                let lhs = Self::evaluate_memoized(lhs, rtxn, index, cache, universe, leaves)?;
+                if lhs.is_empty() {
+                    return Ok(lhs);
+                }
+                let rhs = match rhs.as_ref() {
+                    // the substring scan only looks for the documents of the left operand.
+                    FilterCondition::Contains { fid, value } => {
+                        Self::evaluate_contains(rtxn, index, cache, fid, value, Some(&lhs))?
+                    }
+                    rhs => Self::evaluate_memoized(rhs, rtxn, index, cache, universe, leaves)?,
+                };
+                Ok(lhs & rhs)
+            }
+            FilterCondition::Or(lhs, rhs) => {
+                let lhs = Self::evaluate_memoized(lhs, rtxn, index, cache, universe, leaves)?;
+                if universe.is_subset(&lhs) {
+                    return Ok(lhs);
+                }
+                let rhs = Self::evaluate_memoized(rhs, rtxn, index, cache, universe, leaves)?;
+                Ok(lhs | rhs)
+            }
+            leaf => {
+                if let Some((_, docids)) = leaves.iter().find(|(l, _)| l == leaf) {
+                    return Ok(docids.clone());
+                }
+                let docids = Self::evaluate_condition(leaf, rtxn, index, cache)?;
+                leaves.push((leaf.clone(), docids.clone()));
+                Ok(docids)
+            }
+        }
+    }
+
+    /// Evaluates the condition, operand by operand.
+    fn evaluate_condition(
+        condition: &FilterCondition<'a>,
+        rtxn: &heed::RoTxn,
+        index: &Index,
+        cache: &EvaluationCache,
+    ) -> Result<RoaringBitmap> {
         let numbers_db = index.facet_id_f64_docids;
         let strings_db = index.facet_id_string_docids;
 
-        match &self.condition {
+        match condition {
             FilterCondition::Condition { fid, op } => {
-                let filterable_fields = index.filterable_fields(rtxn)?;
-
-                if crate::is_faceted(fid.value(), &filterable_fields) {
-                    let field_ids_map = index.fields_ids_map(rtxn)?;
-                    if let Some(fid) = field_ids_map.id(fid.value()) {
-                        Self::evaluate_operator(rtxn, index, numbers_db, strings_db, fid, &op)
-                    } else {
-                        return Ok(RoaringBitmap::new());
+                match Self::filterable_field_id(rtxn, index, fid)? {
+                    Some(fid) => Self::evaluate_operator(
+                        rtxn, index, numbers_db, strings_db, cache, fid, &op,
+                    ),
+                    None => Ok(RoaringBitmap::new()),
+                }
+            }
+            FilterCondition::In { fid, els } => {
+                match Self::filterable_field_id(rtxn, index, fid)? {
+                    Some(fid) => {
+                        Self::evaluate_in(rtxn, index, numbers_db, strings_db, cache, fid, els)
                     }
-                } else {
-                    match fid.lexeme() {
-                        attribute @ "_geo" => {
-                            return Err(fid.as_external_error(FilterError::BadGeo(attribute)))?;
-                        }
-                        attribute if attribute.starts_with("_geoPoint(") => {
-                            return Err(fid.as_external_error(FilterError::BadGeo("_geoPoint")))?;
-                        }
-                        attribute @ "_geoDistance" => {
-                            return Err(fid.as_external_error(FilterError::Reserved(attribute)))?;
-                        }
-                        attribute => {
-                            return Err(fid.as_external_error(
-                                FilterError::AttributeNotFilterable {
-                                    attribute,
-                                    filterable_fields,
-                                },
-                            ))?;
-                        }
+                    None => Ok(RoaringBitmap::new()),
+                }
+            }
+            FilterCondition::NotIn { fid, els } => {
+                match Self::filterable_field_id(rtxn, index, fid)? {
+                    Some(fid) => {
+                        let all_ids = index.faceted_documents_ids(rtxn, fid)?;
+                        let docids = Self::evaluate_in(
+                            rtxn, index, numbers_db, strings_db, cache, fid, els,
+                        )?;
+                        Ok(all_ids - docids)
+                    }
+                    None => Ok(RoaringBitmap::new()),
+                }
+            }
+            FilterCondition::Exists { fid } => Self::evaluate_exists(rtxn, index, fid),
+            FilterCondition::NotExists { fid } => {
+                let all_ids = index.documents_ids(rtxn)?;
+                let docids = Self::evaluate_exists(rtxn, index, fid)?;
+                Ok(all_ids - docids)
+            }
+            FilterCondition::IsEmpty { fid } => Self::evaluate_is_empty(rtxn, index, fid),
+            // the documents with a value in the field are the faceted ones, the others are
+            // either empty or don't contain the field.
+            FilterCondition::IsNotEmpty { fid } => Self::evaluate_exists(rtxn, index, fid),
+            FilterCondition::Contains { fid, value } => {
+                Self::evaluate_contains(rtxn, index, cache, fid, value, None)
+            }
+            FilterCondition::NotContains { fid, value } => {
+                match Self::filterable_field_id(rtxn, index, fid)? {
+                    Some(field_id) => {
+                        let all_ids = index.faceted_documents_ids(rtxn, field_id)?;
+                        let docids = Self::evaluate_contains(rtxn, index, cache, fid, value, None)?;
+                        Ok(all_ids - docids)
                     }
+                    None => Ok(RoaringBitmap::new()),
                 }
             }
             FilterCondition::Or(lhs, rhs) => {
-                let lhs = Self::evaluate(&(lhs.as_ref().clone()).into(), rtxn, index)?;
-                let rhs = Self::evaluate(&(rhs.as_ref().clone()).into(), rtxn, index)?;
+                let lhs = Self::evaluate_condition(lhs, rtxn, index, cache)?;
+                let rhs = Self::evaluate_condition(rhs, rtxn, index, cache)?;
                 Ok(lhs | rhs)
             }
             FilterCondition::And(lhs, rhs) => {
-                let lhs = Self::evaluate(&(lhs.as_ref().clone()).into(), rtxn, index)?;
-                let rhs = Self::evaluate(&(rhs.as_ref().clone()).into(), rtxn, index)?;
+                let lhs = Self::evaluate_condition(lhs, rtxn, index, cache)?;
+                let rhs = Self::evaluate_condition(rhs, rtxn, index, cache)?;
                 Ok(lhs & rhs)
             }
             FilterCondition::GeoLowerThan { point, radius } => {
@@ -410,13 +846,16 @@ impl<'a> Filter<'a> {
 
                     let xyz_base_point = lat_lng_to_xyz(&base_point);
 
-                    let result = rtree
-                        .nearest_neighbor_iter(&xyz_base_point)
-                        .take_while(|point| {
-                            distance_between_two_points(&base_point, &point.data.1) < radius
-                        })
-                        .map(|point| point.data.0)
-                        .collect();
+                    let result = match index.geo_distance(rtxn)? {
+                        GeoDistance::GreatCircle => rtree
+                            .nearest_neighbor_iter(&xyz_base_point)
+                            .take_while(|point| {
+                                distance_between_two_points(&base_point, &point.data.1) < radius
+                            })
+                            .map(|point| point.data.0)
+                            .collect(),
+                        GeoDistance::Planar => geo_planar_radius_docids(&rtree, base_point, radius),
+                    };
 
                     Ok(result)
                 } else {
@@ -427,11 +866,114 @@ impl<'a> Filter<'a> {
                 }
             }
             FilterCondition::GeoGreaterThan { point, radius } => {
-                let result = Self::evaluate(
-                    &FilterCondition::GeoLowerThan { point: point.clone(), radius: radius.clone() }
-                        .into(),
+                let result = Self::evaluate_condition(
+                    &FilterCondition::GeoLowerThan { point: point.clone(), radius: radius.clone() },
+                    rtxn,
+                    index,
+                    cache,
+                )?;
+                let geo_faceted_doc_ids = index.geo_faceted_documents_ids(rtxn)?;
+                Ok(geo_faceted_doc_ids - result)
+            }
+            FilterCondition::GeoBoundingBox { top_right_point, bottom_left_point } => {
+                let filterable_fields = index.filterable_fields(rtxn)?;
+                if filterable_fields.contains("_geo") {
+                    let top_right = parse_geo_point(top_right_point)?;
+                    let bottom_left = parse_geo_point(bottom_left_point)?;
+                    if top_right[0] < bottom_left[0] {
+                        return Err(top_right_point[0].as_external_error(
+                            FilterError::BadGeoBoundingBoxTopIsBelowBottom(
+                                top_right[0],
+                                bottom_left[0],
+                            ),
+                        ))?;
+                    }
+                    let rtree = match index.geo_rtree(rtxn)? {
+                        Some(rtree) => rtree,
+                        None => return Ok(RoaringBitmap::new()),
+                    };
+
+                    // When the right longitude is smaller than the left one the box crosses
+                    // the antimeridian, we split it into two boxes, one on each side of it.
+                    if top_right[1] < bottom_left[1] {
+                        let east =
+                            geo_bounding_box_docids(&rtree, [top_right[0], 180.0], bottom_left);
+                        let west =
+                            geo_bounding_box_docids(&rtree, top_right, [bottom_left[0], -180.0]);
+                        Ok(east | west)
+                    } else {
+                        Ok(geo_bounding_box_docids(&rtree, top_right, bottom_left))
+                    }
+                } else {
+                    return Err(top_right_point[0].as_external_error(
+                        FilterError::AttributeNotFilterable {
+                            attribute: "_geo",
+                            filterable_fields,
+                        },
+                    ))?;
+                }
+            }
+            FilterCondition::GeoOutsideBoundingBox { top_right_point, bottom_left_point } => {
+                let result = Self::evaluate_condition(
+                    &FilterCondition::GeoBoundingBox {
+                        top_right_point: top_right_point.clone(),
+                        bottom_left_point: bottom_left_point.clone(),
+                    },
+                    rtxn,
+                    index,
+                    cache,
+                )?;
+                let geo_faceted_doc_ids = index.geo_faceted_documents_ids(rtxn)?;
+                Ok(geo_faceted_doc_ids - result)
+            }
+            FilterCondition::GeoPolygon { points } => {
+                let filterable_fields = index.filterable_fields(rtxn)?;
+                if filterable_fields.contains("_geo") {
+                    let mut polygon = points
+                        .iter()
+                        .map(|point| parse_geo_point(point))
+                        .collect::<Result<Vec<_>>>()?;
+                    // the ring is closed whether or not it ends with its first point.
+                    if polygon.len() > 1 && polygon.first() == polygon.last() {
+                        polygon.pop();
+                    }
+                    if polygon.len() < 3 {
+                        return Err(points[0][0].as_external_error(
+                            FilterError::BadGeoPolygonTooFewPoints(polygon.len()),
+                        ))?;
+                    }
+                    let rtree = match index.geo_rtree(rtxn)? {
+                        Some(rtree) => rtree,
+                        None => return Ok(RoaringBitmap::new()),
+                    };
+
+                    // the rtree narrows the candidates down to the bounding box of the polygon.
+                    let mut top_right = [f64::MIN, f64::MIN];
+                    let mut bottom_left = [f64::MAX, f64::MAX];
+                    for &[lat, lng] in &polygon {
+                        top_right = [top_right[0].max(lat), top_right[1].max(lng)];
+                        bottom_left = [bottom_left[0].min(lat), bottom_left[1].min(lng)];
+                    }
+
+                    Ok(geo_bounding_box_points(&rtree, top_right, bottom_left)
+                        .filter(|point| point_in_polygon(point.data.1, &polygon))
+                        .map(|point| point.data.0)
+                        .collect())
+                } else {
+                    return Err(points[0][0].as_external_error(
+                        FilterError::AttributeNotFilterable {
+                            attribute: "_geo",
+                            filterable_fields,
+                        },
+                    ))?;
+                }
+            }
+            FilterCondition::GeoOutsidePolygon { points } => {
+                let result = Self::evaluate_condition(
+                    &FilterCondition::GeoPolygon { points: points.clone() },
                     rtxn,
                     index,
+                    cache,
                 )?;
                 let geo_faceted_doc_ids = index.geo_faceted_documents_ids(rtxn)?;
                 Ok(geo_faceted_doc_ids - result)
@@ -440,6 +982,152 @@ impl<'a> Filter<'a> {
     }
 }
 
+/// Parses a `[latitude, longitude]` pair of tokens and makes sure that
+/// the coordinates are contained in the valid ranges.
+fn parse_geo_point(point: &[Token]) -> Result<[f64; 2]> {
+    let lat: f64 = point[0].parse()?;
+    let lng: f64 = point[1].parse()?;
+    if !(-90.0..=90.0).contains(&lat) {
+        return Err(point[0].as_external_error(FilterError::BadGeoLat(lat)))?;
+    }
+    if !(-180.0..=180.0).contains(&lng) {
+        return Err(point[1].as_external_error(FilterError::BadGeoLng(lng)))?;
+    }
+    Ok([lat, lng])
+}
+
+/// Returns the documents ids of the points that are contained in the box defined by
+/// the two corners, the left longitude must be lower than or equal to the right one.
+fn geo_bounding_box_docids(
+    rtree: &RTree<GeoPoint>,
+    top_right: [f64; 2],
+    bottom_left: [f64; 2],
+) -> RoaringBitmap {
+    geo_bounding_box_points(rtree, top_right, bottom_left).map(|point| point.data.0).collect()
+}
+
+/// Returns the documents whose planar distance to the base point is lower than the radius.
+///
+/// The planar distances are not ordered like the cartesian points of the rtree, the points
+/// are selected in the box of the latitudes and longitudes that can be within the radius,
+/// and their distance is checked while they are visited.
+fn geo_planar_radius_docids(
+    rtree: &RTree<GeoPoint>,
+    base_point: [f64; 2],
+    radius: f64,
+) -> RoaringBitmap {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    let [lat, lng] = base_point.map(f64::to_radians);
+    let delta_lat = radius / EARTH_RADIUS;
+    let (top, bottom) = ((lat + delta_lat).min(FRAC_PI_2), (lat - delta_lat).max(-FRAC_PI_2));
+    // The longitudes are scaled by the cosinus of the mean latitude of the two points,
+    // it is the smallest for the mean latitude the farthest from the equator.
+    let mean_lat = ((lat + top) / 2.0).abs().max(((lat + bottom) / 2.0).abs());
+    let delta_lng = radius / (EARTH_RADIUS * mean_lat.cos());
+    let (top, bottom) = (top.to_degrees(), bottom.to_degrees());
+
+    // The boxes that cross the antimeridian are split into two boxes, one on each side of it.
+    let boxes = if delta_lng >= PI || delta_lng.is_nan() {
+        vec![([top, 180.0], [bottom, -180.0])]
+    } else {
+        let (left, right) = ((lng - delta_lng).to_degrees(), (lng + delta_lng).to_degrees());
+        if left < -180.0 {
+            vec![([top, 180.0], [bottom, left + 360.0]), ([top, right], [bottom, -180.0])]
+        } else if right > 180.0 {
+            vec![([top, 180.0], [bottom, left]), ([top, right - 360.0], [bottom, -180.0])]
+        } else {
+            vec![([top, right], [bottom, left])]
+        }
+    };
+
+    boxes
+        .into_iter()
+        .flat_map(|(top_right, bottom_left)| geo_bounding_box_points(rtree, top_right, bottom_left))
+        .filter(|point| planar_distance_between_two_points(&base_point, &point.data.1) < radius)
+        .map(|point| point.data.0)
+        .collect()
+}
+
+/// Returns the points that are contained in the box defined by the two corners.
+///
+/// The rtree stores cartesian coordinates, we first select the points contained in the
+/// envelope of the box projected on the sphere and then check the original coordinates.
+fn geo_bounding_box_points<'r>(
+    rtree: &'r RTree<GeoPoint>,
+    top_right: [f64; 2],
+    bottom_left: [f64; 2],
+) -> impl Iterator<Item = &'r GeoPoint> + 'r {
+    // The cartesian points are not exactly the same once projected, we slightly
+    // enlarge the envelope and let the exact check below do the filtering.
+    const EPSILON: f64 = 1e-9;
+
+    let [top, right] = top_right.map(f64::to_radians);
+    let [bottom, left] = bottom_left.map(f64::to_radians);
+
+    // The range of the cosinus (resp. sinus) over an angle interval is reached
+    // either at the bounds of the interval or at the extremums contained inside it.
+    let range = |f: fn(f64) -> f64, from: f64, to: f64, extremums: &[(f64, f64)]| {
+        let (mut min, mut max) = (f(from).min(f(to)), f(from).max(f(to)));
+        for &(angle, value) in extremums {
+            if (from..=to).contains(&angle) {
+                min = min.min(value);
+                max = max.max(value);
+            }
+        }
+        (min, max)
+    };
+
+    use std::f64::consts::{FRAC_PI_2, PI};
+    let (cos_lat_min, cos_lat_max) = range(f64::cos, bottom, top, &[(0.0, 1.0)]);
+    let (cos_lng_min, cos_lng_max) =
+        range(f64::cos, left, right, &[(0.0, 1.0), (PI, -1.0), (-PI, -1.0)]);
+    let (sin_lng_min, sin_lng_max) =
+        range(f64::sin, left, right, &[(FRAC_PI_2, 1.0), (-FRAC_PI_2, -1.0)]);
+
+    // x = cos(lat) * cos(lng), y = cos(lat) * sin(lng) and cos(lat) is always positive.
+    let scale = |min: f64, max: f64| {
+        let min = if min < 0.0 { cos_lat_max * min } else { cos_lat_min * min };
+        let max = if max > 0.0 { cos_lat_max * max } else { cos_lat_min * max };
+        (min, max)
+    };
+    let (x_min, x_max) = scale(cos_lng_min, cos_lng_max);
+    let (y_min, y_max) = scale(sin_lng_min, sin_lng_max);
+    let (z_min, z_max) = (bottom.sin(), top.sin());
+
+    let envelope = AABB::from_corners(
+        [x_min - EPSILON, y_min - EPSILON, z_min - EPSILON],
+        [x_max + EPSILON, y_max + EPSILON, z_max + EPSILON],
+    );
+
+    rtree.locate_in_envelope(&envelope).filter(move |point| {
+        let [lat, lng] = point.data.1;
+        (bottom_left[0]..=top_right[0]).contains(&lat)
+            && (bottom_left[1]..=top_right[1]).contains(&lng)
+    })
+}
+
+/// Returns `true` if the point is inside the polygon, a ray going east from the point
+/// crosses the edges of the polygon an odd number of times. The coordinates are used
+/// as planar ones, the polygon must not cross the antimeridian.
+fn point_in_polygon([lat, lng]: [f64; 2], polygon: &[[f64; 2]]) -> bool {
+    let mut inside = false;
+    let mut previous = polygon[polygon.len() - 1];
+    for &current in polygon {
+        let ([previous_lat, previous_lng], [current_lat, current_lng]) = (previous, current);
+        if (previous_lat > lat) != (current_lat > lat) {
+            let crossing_lng = previous_lng
+                + (lat - previous_lat) * (current_lng - previous_lng)
+                    / (current_lat - previous_lat);
+            if lng < crossing_lng {
+                inside = !inside;
+            }
+        }
+        previous = current;
+    }
+    inside
+}
+
 impl<'a> From<FilterCondition<'a>> for Filter<'a> {
     fn from(fc: FilterCondition<'a>) -> Self {
         Self { condition: fc }
@@ -453,9 +1141,10 @@ mod tests {
     use big_s::S;
     use either::Either;
     use heed::EnvOpenOptions;
-    use maplit::hashset;
+    use maplit::{btreemap, hashmap, hashset};
 
     use super::*;
+    use crate::index::tests::TempIndex;
     use crate::update::{self, IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
     use crate::Index;
 
@@ -586,6 +1275,12 @@ mod tests {
             "Attribute `_geo` is not filterable. Available filterable attributes are: `title`."
         ));
 
+        let filter = Filter::from_str("_geoBoundingBox([50, 5], [45, 0])").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(error.to_string().starts_with(
+            "Attribute `_geo` is not filterable. Available filterable attributes are: `title`."
+        ));
+
         let filter = Filter::from_str("name = 12").unwrap().unwrap();
         let error = filter.evaluate(&rtxn, &index).unwrap_err();
         assert!(error.to_string().starts_with(
@@ -663,37 +1358,143 @@ mod tests {
     }
 
     #[test]
-    fn geo_radius_error() {
-        let path = tempfile::tempdir().unwrap();
-        let mut options = EnvOpenOptions::new();
-        options.map_size(10 * 1024 * 1024); // 10 MB
-        let index = Index::new(options, &path).unwrap();
+    fn normalized_filterable_fields() {
+        let index = TempIndex::new();
 
         let config = IndexerConfig::default();
-        // Set the filterable fields to be the channel.
         let mut wtxn = index.write_txn().unwrap();
         let mut builder = Settings::new(&mut wtxn, &index, &config);
-        builder.set_searchable_fields(vec![S("_geo"), S("price")]); // to keep the fields order
-        builder.set_filterable_fields(hashset! { S("_geo"), S("price") });
+        builder.set_filterable_fields(hashset! { S("brand"), S("model") });
         builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "brand": "Citroën", "model": "Été" },
+            { "id": 1, "brand": "CITROEN", "model": "ete" },
+            { "id": 2, "brand": "Renault", "model": "Clio" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
         wtxn.commit().unwrap();
 
-        let rtxn = index.read_txn().unwrap();
+        let evaluate = |index: &TempIndex, filter: &str| -> Vec<u32> {
+            let rtxn = index.read_txn().unwrap();
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(&rtxn, index).unwrap().into_iter().collect()
+        };
 
-        // georadius have a bad latitude
-        let filter = Filter::from_str("_geoRadius(-100, 150, 10)").unwrap().unwrap();
-        let error = filter.evaluate(&rtxn, &index).unwrap_err();
-        assert!(
-            error.to_string().starts_with(
-                "Bad latitude `-100`. Latitude must be contained between -90 and 90 degrees."
-            ),
-            "{}",
-            error.to_string()
+        // by default only the case is ignored
+        assert_eq!(evaluate(&index, "brand = citroen"), vec![1]);
+        assert_eq!(evaluate(&index, "brand = CITROËN"), vec![0]);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_normalized_filterable_fields(hashset! { S("brand") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        assert_eq!(evaluate(&index, "brand = citroen"), vec![0, 1]);
+        assert_eq!(evaluate(&index, "brand = CITROËN"), vec![0, 1]);
+        assert_eq!(evaluate(&index, "brand != Citroën"), vec![2]);
+        assert_eq!(evaluate(&index, "brand IN [reNAULT, 'citroën']"), vec![0, 1, 2]);
+        // the normalized strings of the field are reused by the next leaves of the filter.
+        assert_eq!(
+            evaluate(&index, "brand IN [peugeot, citroen] AND brand != renault"),
+            vec![0, 1]
         );
+        assert_eq!(evaluate(&index, "brand = peugeot OR brand = RENAULT"), vec![2]);
 
-        // georadius have a bad latitude
-        let filter = Filter::from_str("_geoRadius(-90.0000001, 150, 10)").unwrap().unwrap();
-        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        // the model is not normalized and its accents are still compared
+        assert_eq!(evaluate(&index, "model = ÉTÉ"), vec![0]);
+        assert_eq!(evaluate(&index, "model = ete"), vec![1]);
+    }
+
+    #[test]
+    fn facet_value_mappings() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("country") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "country": "USA" },
+            { "id": 1, "country": "U.S.A." },
+            { "id": 2, "country": "United States" },
+            { "id": 3, "country": "France" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // the documents are reindexed with the mapped values.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        let mapping = hashmap! {
+            S("usa") => S("United States"),
+            S("U.S.A.") => S("United States"),
+        };
+        builder.set_facet_value_mappings(hashmap! { S("country") => mapping });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let distribution = index.facets_distribution(&rtxn).execute().unwrap();
+        let countries = btreemap! { S("France") => 1, S("United States") => 3 };
+        assert_eq!(distribution["country"], countries);
+
+        let evaluate = |filter: &str| -> Vec<u32> {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(&rtxn, &index).unwrap().into_iter().collect()
+        };
+        assert_eq!(evaluate("country = 'United States'"), vec![0, 1, 2]);
+        // the raw values are mapped too.
+        assert_eq!(evaluate("country = USA"), vec![0, 1, 2]);
+        assert_eq!(evaluate("country IN [France, 'u.s.a.']"), vec![0, 1, 2, 3]);
+        assert_eq!(evaluate("country != 'united states'"), vec![3]);
+        assert_eq!(evaluate("country CONTAINS 'u.s'"), vec![0, 1, 2]);
+        assert_eq!(evaluate("country CONTAINS fra"), vec![3]);
+    }
+
+    #[test]
+    fn geo_radius_error() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let config = IndexerConfig::default();
+        // Set the filterable fields to be the channel.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("_geo"), S("price")]); // to keep the fields order
+        builder.set_filterable_fields(hashset! { S("_geo"), S("price") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        // georadius have a bad latitude
+        let filter = Filter::from_str("_geoRadius(-100, 150, 10)").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(
+            error.to_string().starts_with(
+                "Bad latitude `-100`. Latitude must be contained between -90 and 90 degrees."
+            ),
+            "{}",
+            error.to_string()
+        );
+
+        // georadius have a bad latitude
+        let filter = Filter::from_str("_geoRadius(-90.0000001, 150, 10)").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
         assert!(error.to_string().contains(
             "Bad latitude `-90.0000001`. Latitude must be contained between -90 and 90 degrees."
         ));
@@ -717,6 +1518,505 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn geo_bounding_box() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("_geo") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": "paris", "_geo": { "lat": 48.8566, "lng": 2.3522 } },
+            { "id": "lyon", "_geo": { "lat": 45.7640, "lng": 4.8357 } },
+            { "id": "suva", "_geo": { "lat": -18.1416, "lng": 178.4419 } },
+            { "id": "apia", "_geo": { "lat": -13.8507, "lng": -171.7513 } },
+            { "id": "tokyo", "_geo": { "lat": 35.6762, "lng": 139.6503 } },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        // a box around the north of France
+        let filter = Filter::from_str("_geoBoundingBox([50, 5], [45, 0])").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0, 1]);
+
+        // everything that is not in this box
+        let filter = Filter::from_str("NOT _geoBoundingBox([50, 5], [45, 0])").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![2, 3, 4]);
+
+        // a box crossing the antimeridian
+        let filter = Filter::from_str("_geoBoundingBox([-10, -170], [-20, 175])").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![2, 3]);
+
+        // a box containing nothing
+        let filter = Filter::from_str("_geoBoundingBox([10, 10], [0, 0])").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert!(bitmap.is_empty());
+
+        // the top latitude is below the bottom one
+        let filter = Filter::from_str("_geoBoundingBox([45, 5], [50, 0])").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("The top latitude `45` is below the bottom latitude `50`."),
+            "{}",
+            error
+        );
+
+        // bad coordinates
+        let filter = Filter::from_str("_geoBoundingBox([95, 5], [45, 0])").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(error.to_string().starts_with(
+            "Bad latitude `95`. Latitude must be contained between -90 and 90 degrees."
+        ));
+        let filter = Filter::from_str("_geoBoundingBox([50, 5], [45, -181])").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(error.to_string().starts_with(
+            "Bad longitude `-181`. Longitude must be contained between -180 and 180 degrees."
+        ));
+    }
+
+    #[test]
+    fn geo_radius_planar() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("_geo") });
+        builder.set_geo_distance(GeoDistance::Planar);
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "_geo": { "lat": 50.0, "lng": 80.0 } },
+            { "id": 1, "_geo": { "lat": 0.0, "lng": 0.0 } },
+            { "id": "suva", "_geo": { "lat": -18.1416, "lng": 178.4419 } },
+            { "id": "apia", "_geo": { "lat": -13.8507, "lng": -171.7513 } },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let docids = |filter: &str| -> Vec<u32> {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(&rtxn, &index).unwrap().into_iter().collect()
+        };
+
+        // the first point is at about 5 428km on the sphere and 5 718km on the plane,
+        // the second one is at about 5 560km.
+        assert_eq!(docids("_geoRadius(50, 0, 5600000)"), vec![1]);
+        assert_eq!(docids("_geoRadius(50, 0, 5800000)"), vec![0, 1]);
+
+        // apia is at about 1 152km of suva, on the other side of the antimeridian.
+        assert_eq!(docids("_geoRadius(-18.1416, 178.4419, 1140000)"), vec![2]);
+        assert_eq!(docids("_geoRadius(-18.1416, 178.4419, 1160000)"), vec![2, 3]);
+        assert_eq!(docids("_geoRadius(-13.8507, -171.7513, 1160000)"), vec![2, 3]);
+    }
+
+    #[test]
+    fn geo_polygon() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("_geo") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "_geo": { "lat": 2, "lng": 2 } },
+            { "id": 1, "_geo": { "lat": 20, "lng": 20 } },
+            { "id": 2, "_geo": { "lat": 8, "lng": 8 } },
+            { "id": 3, "_geo": { "lat": 4.999, "lng": 8 } },
+            { "id": 4, "_geo": { "lat": 5.001, "lng": 8 } },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        // an L shaped polygon whose bounding box contains the point `[8, 8]`
+        // and whose edge passes between the points `[4.999, 8]` and `[5.001, 8]`.
+        let polygon = "[[0, 0], [0, 10], [5, 10], [5, 5], [10, 5], [10, 0]]";
+        let filter = Filter::from_str(&format!("_geoPolygon({})", polygon)).unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0, 3]);
+
+        let filter = Filter::from_str(&format!("NOT _geoPolygon({})", polygon)).unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![1, 2, 4]);
+
+        // the same polygon explicitly closed
+        let closed = "[[0, 0], [0, 10], [5, 10], [5, 5], [10, 5], [10, 0], [0, 0]]";
+        let filter = Filter::from_str(&format!("_geoPolygon({})", closed)).unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0, 3]);
+
+        // a closed ring of only two distinct points
+        let filter = Filter::from_str("_geoPolygon([[0, 0], [0, 10], [0, 0]])").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(
+            error.to_string().starts_with(
+                "The polygon must have at least three distinct points but only `2` were given."
+            ),
+            "{}",
+            error
+        );
+
+        // bad coordinates
+        let filter = Filter::from_str("_geoPolygon([[0, 0], [95, 10], [10, 0]])").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(error.to_string().starts_with(
+            "Bad latitude `95`. Latitude must be contained between -90 and 90 degrees."
+        ));
+    }
+
+    #[test]
+    fn filter_in() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("tags"), S("sizes"), S("dog") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "tags": ["urgent", "wip"], "sizes": [36, 38], "dog": { "race": "bernese" } },
+            { "id": 1, "tags": ["blocked"], "sizes": [40], "dog": { "race": "borzoi" } },
+            { "id": 2, "tags": ["done"], "sizes": 42 },
+            { "id": 3 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let filter = Filter::from_str("tags IN [urgent, blocked, wip]").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0, 1]);
+
+        let filter = Filter::from_str("sizes IN [38, 42, 44]").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0, 2]);
+
+        let filter = Filter::from_str("dog.race IN ['borzoi', 'chusky']").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![1]);
+
+        // only the documents with a value for the field can be returned by a negation
+        let filter = Filter::from_str("NOT tags IN [urgent, blocked]").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![2]);
+
+        let filter = Filter::from_str("tags IN []").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert!(bitmap.is_empty());
+
+        let filter = Filter::from_str("name IN []").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(
+            error.to_string().starts_with("Attribute `name` is not filterable."),
+            "{}",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn memoized_evaluation() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("genre"), S("year"), S("tags") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "genre": "rock", "year": 1970, "tags": ["live"] },
+            { "id": 1, "genre": "rock", "year": 1995 },
+            { "id": 2, "genre": "jazz", "year": 1959, "tags": [] },
+            { "id": 3, "genre": "jazz", "year": 2001, "tags": ["live", "remaster"] },
+            { "id": 4, "genre": "pop", "year": 1984 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // the soft deleted documents are still part of the facet databases.
+        let docids: RoaringBitmap = std::iter::once(4).collect();
+        index.soft_delete_documents(&mut wtxn, &docids).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let filters = [
+            "(genre = rock AND year > 1980) OR (genre = rock AND tags = live)",
+            "(genre = jazz AND year < 1960) OR (genre = jazz AND tags EXISTS) OR genre = pop",
+            "genre = metal AND (year > 1980 OR tags = live)",
+            "genre EXISTS OR year > 3000",
+            "NOT genre EXISTS OR genre = pop",
+            "(year > 1960 OR genre = rock) AND (year > 1960 OR tags IS EMPTY)",
+            "(genre = rock OR genre = jazz OR genre = pop) AND NOT (genre = rock AND NOT year < 1990)",
+        ];
+        for filter in filters.iter() {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            let naive = Filter::evaluate_condition(&filter.condition, &rtxn, &index).unwrap();
+            assert_eq!(filter.evaluate(&rtxn, &index).unwrap(), naive, "{:?}", filter);
+        }
+
+        // the skipped operands are still checked.
+        let filter = Filter::from_str("genre EXISTS OR title = hello").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(error.to_string().starts_with("Attribute `title` is not filterable."));
+    }
+
+    #[test]
+    fn filter_is_empty() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("images"), S("details") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "images": ["a.png", "b.png"], "details": { "sizes": [1] } },
+            { "id": 1, "images": [], "details": { "sizes": [] } },
+            { "id": 2, "details": {} },
+            { "id": 3, "images": null, "details": [{ "sizes": {} }] },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        // a non-empty array, an empty array and a missing field.
+        let filter = Filter::from_str("images EXISTS").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0]);
+
+        let filter = Filter::from_str("images IS EMPTY").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![1, 3]);
+
+        // a missing field is not empty either.
+        let filter = Filter::from_str("images IS NOT EMPTY").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0]);
+
+        let filter = Filter::from_str("images NOT EXISTS").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        // the nested fields are found through the objects and the arrays of objects.
+        let filter = Filter::from_str("details.sizes IS EMPTY").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![1, 3]);
+
+        let filter = Filter::from_str("details IS EMPTY").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+        drop(rtxn);
+
+        // the deleted documents are removed from the empty fields.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("1");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let filter = Filter::from_str("images IS EMPTY").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn filter_contains() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("name"), S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "iPhone Pro", "price": 1000 },
+            { "id": 1, "name": ["Pixel", "Pixel 7 PROFESSIONAL"], "price": 600 },
+            { "id": 2, "name": "Galaxy", "price": 800 },
+            { "id": 3, "name": "Surface pro", "price": 1200 },
+            { "id": 4, "price": 10 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let filter = Filter::from_str("name CONTAINS pro").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0, 1, 3]);
+
+        let filter = Filter::from_str("name CONTAINS 'E PR'").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0, 3]);
+
+        // the candidates of the left operand bound the scan.
+        let filter = Filter::from_str("price > 700 AND name CONTAINS pro").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0, 3]);
+
+        // only the documents with the field are negated.
+        let filter = Filter::from_str("NOT name CONTAINS pro").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![2]);
+
+        let filter = Filter::from_str("name CONTAINS watch").unwrap().unwrap();
+        assert!(filter.evaluate(&rtxn, &index).unwrap().is_empty());
+
+        let filter = Filter::from_str("title CONTAINS pro").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(error.to_string().starts_with("Attribute `title` is not filterable."));
+    }
+
+    #[test]
+    fn filter_booleans() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("available") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "available": true },
+            { "id": 1, "available": "true" },
+            { "id": 2, "available": false },
+            { "id": 3, "available": [true, "false"] },
+            { "id": 4 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let evaluate = |filter: &str| -> Vec<u32> {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            filter.evaluate(&rtxn, &index).unwrap().into_iter().collect()
+        };
+
+        assert_eq!(evaluate("available = true"), vec![0, 3]);
+        assert_eq!(evaluate("available = \"true\""), vec![1]);
+        assert_eq!(evaluate("available = 'true'"), vec![1]);
+        assert_eq!(evaluate("available = false"), vec![2]);
+        assert_eq!(evaluate("available = 'false'"), vec![3]);
+        assert_eq!(evaluate("available IN [false, 'true']"), vec![1, 2]);
+
+        // the booleans are negated among the documents with the field.
+        assert_eq!(evaluate("available != true"), vec![1, 2]);
+        assert_eq!(evaluate("NOT available = \"true\""), vec![0, 2, 3]);
+        assert_eq!(evaluate("available EXISTS"), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn filter_exists() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("price"), S("details") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "price": 12, "details": { "warranty": { "years": 2 } } },
+            { "id": 1, "price": "free", "details": { "warranty": 1, "colour": "red" } },
+            { "id": 2, "details": { "colour": "blue" } },
+            { "id": 3, "price": null },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let filter = Filter::from_str("price EXISTS").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0, 1]);
+
+        let filter = Filter::from_str("price NOT EXISTS").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![2, 3]);
+
+        // the warranty is an object in the first document and a number in the second one
+        let filter = Filter::from_str("details.warranty EXISTS").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0, 1]);
+
+        let filter = Filter::from_str("details.warranty NOT EXISTS").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![2, 3]);
+
+        let filter = Filter::from_str("NOT details EXISTS").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![3]);
+
+        // a field that doesn't exist in any document
+        let filter = Filter::from_str("details.size NOT EXISTS").unwrap().unwrap();
+        let bitmap = filter.evaluate(&rtxn, &index).unwrap();
+        assert_eq!(bitmap.into_iter().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+
+        let filter = Filter::from_str("name NOT EXISTS").unwrap().unwrap();
+        let error = filter.evaluate(&rtxn, &index).unwrap_err();
+        assert!(
+            error.to_string().starts_with("Attribute `name` is not filterable."),
+            "{}",
+            error.to_string()
+        );
+    }
+
     #[test]
     fn filter_depth() {
         // generates a big (2 MiB) filter with too much of ORs.
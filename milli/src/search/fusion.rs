@@ -0,0 +1,64 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use ordered_float::OrderedFloat;
+
+use crate::DocumentId;
+
+/// The constant added to the ranks, it lowers the weight of the first ranks
+/// compared to the next ones.
+const RRF_K: f64 = 60.0;
+
+/// Merges two rankings of the documents of the same index, e.g. the results of a prefix
+/// and of an exact search, with the reciprocal rank fusion: a document gets `1 / (k + rank)`
+/// from each ranking it appears in and the documents are sorted by decreasing sum.
+///
+/// The documents are only returned once, a document appearing several times in a ranking only
+/// counts at its best rank. The documents with the same score are ordered by their best rank
+/// then by their rank in the first ranking.
+pub fn reciprocal_rank_fusion(first: &[DocumentId], second: &[DocumentId]) -> Vec<DocumentId> {
+    // the score, best rank and position in the first ranking of each document.
+    let mut documents: HashMap<DocumentId, (f64, usize, usize)> = HashMap::new();
+    for (ranking, ids) in [first, second].iter().enumerate() {
+        let mut seen = HashSet::new();
+        for (rank, &docid) in ids.iter().enumerate() {
+            if !seen.insert(docid) {
+                continue;
+            }
+
+            let position = if ranking == 0 { rank } else { first.len() + rank };
+            let (score, best_rank, _) = documents.entry(docid).or_insert((0.0, rank, position));
+            *score += 1.0 / (RRF_K + rank as f64 + 1.0);
+            *best_rank = (*best_rank).min(rank);
+        }
+    }
+
+    let mut documents: Vec<_> = documents.into_iter().collect();
+    documents.sort_unstable_by_key(|&(_, (score, best_rank, position))| {
+        (Reverse(OrderedFloat(score)), best_rank, position)
+    });
+    documents.into_iter().map(|(docid, _)| docid).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn promotes_the_documents_ranked_in_both() {
+        let prefix = [10, 1, 2, 3];
+        let exact = [20, 1, 21, 2];
+        // 1 is second in both rankings, it beats the first documents of each ranking.
+        let fused = reciprocal_rank_fusion(&prefix, &exact);
+        assert_eq!(fused, vec![1, 2, 10, 20, 21, 3]);
+    }
+
+    #[test]
+    fn deduplicates_the_documents() {
+        let fused = reciprocal_rank_fusion(&[1, 2, 1], &[2, 3, 3]);
+        assert_eq!(fused, vec![2, 1, 3]);
+
+        assert_eq!(reciprocal_rank_fusion(&[], &[4, 5]), vec![4, 5]);
+        assert!(reciprocal_rank_fusion(&[], &[]).is_empty());
+    }
+}
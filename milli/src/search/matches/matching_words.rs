@@ -1,12 +1,11 @@
 use std::cmp::{min, Reverse};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::ops::{Index, IndexMut};
 
 use charabia::Token;
-use levenshtein_automata::{Distance, DFA};
 
-use crate::search::build_dfa;
+use crate::search::DerivationsDfa;
 
 type IsPrefix = bool;
 
@@ -30,6 +29,21 @@ impl MatchingWords {
     pub fn match_token<'a, 'b>(&'a self, token: &'b Token<'b>) -> MatchesIter<'a, 'b> {
         MatchesIter { inner: Box::new(self.inner.iter()), token }
     }
+
+    /// Returns the ids of the query words whose terms are all matched by one of the tokens,
+    /// the terms of the multi-word synonyms and split words don't need to be consecutive.
+    pub fn matched_ids(&self, tokens: &[Token]) -> BTreeSet<PrimitiveWordId> {
+        let mut matched_ids = BTreeSet::new();
+        for (matching_words, ids) in &self.inner {
+            let matched = matching_words
+                .iter()
+                .all(|word| tokens.iter().any(|token| word.match_token(token).is_some()));
+            if matched {
+                matched_ids.extend(ids.iter().copied());
+            }
+        }
+        matched_ids
+    }
 }
 
 /// Iterator over terms that match the given token,
@@ -68,7 +82,7 @@ pub type PrimitiveWordId = u8;
 
 /// Structure used to match a specific term.
 pub struct MatchingWord {
-    pub dfa: DFA,
+    pub dfa: DerivationsDfa,
     pub word: String,
     pub typo: u8,
     pub prefix: IsPrefix,
@@ -92,7 +106,18 @@ impl PartialEq for MatchingWord {
 
 impl MatchingWord {
     pub fn new(word: String, typo: u8, prefix: IsPrefix) -> Self {
-        let dfa = build_dfa(&word, typo, prefix);
+        Self::with_first_char_typo(word, typo, prefix, true)
+    }
+
+    /// Creates a matching word that only matches the tokens with a different first char
+    /// when `first_char_typo` is `true`, like the derivations of the query word.
+    pub fn with_first_char_typo(
+        word: String,
+        typo: u8,
+        prefix: IsPrefix,
+        first_char_typo: bool,
+    ) -> Self {
+        let dfa = DerivationsDfa::new(&word, typo, prefix, first_char_typo);
 
         Self { dfa, word, typo, prefix }
     }
@@ -100,12 +125,14 @@ impl MatchingWord {
     /// Returns the lenght in chars of the match in case of the token matches the term.
     pub fn match_token(&self, token: &Token) -> Option<usize> {
         match self.dfa.eval(token.lemma()) {
-            Distance::Exact(t) if t <= self.typo => {
+            Some(t) if t <= self.typo => {
                 if self.prefix {
                     let len = bytes_to_highlight(token.lemma(), &self.word);
                     Some(token.original_lengths(len).0)
                 } else {
-                    Some(token.original_lengths(token.lemma().len()).0)
+                    // the whole token is matched, its original text can be longer than its
+                    // lemma, e.g. the digits of a number written with digit group separators.
+                    Some(token.char_end - token.char_start)
                 }
             }
             _otherwise => None,
@@ -400,6 +427,7 @@ mod tests {
                 .next(),
             None
         );
+        // a typo on the first char costs two typos.
         assert_eq!(
             matching_words
                 .match_token(&Token {
@@ -410,7 +438,7 @@ mod tests {
                     ..Default::default()
                 })
                 .next(),
-            Some(MatchType::Full { char_len: 5, ids: &[2] })
+            None
         );
         assert_eq!(
             matching_words
@@ -425,4 +453,30 @@ mod tests {
             Some(MatchType::Full { char_len: 4, ids: &[2] })
         );
     }
+
+    #[test]
+    fn matching_words_first_char_typo() {
+        let token = Token {
+            kind: TokenKind::Word,
+            lemma: Cow::Borrowed("borld"),
+            char_end: "borld".chars().count(),
+            byte_end: "borld".len(),
+            ..Default::default()
+        };
+
+        let matching_words = MatchingWords::new(vec![(
+            vec![MatchingWord::with_first_char_typo("world".to_string(), 2, false, true)],
+            vec![0],
+        )]);
+        assert_eq!(
+            matching_words.match_token(&token).next(),
+            Some(MatchType::Full { char_len: 5, ids: &[0] })
+        );
+
+        let matching_words = MatchingWords::new(vec![(
+            vec![MatchingWord::with_first_char_typo("world".to_string(), 2, false, false)],
+            vec![0],
+        )]);
+        assert_eq!(matching_words.match_token(&token).next(), None);
+    }
 }
@@ -5,6 +5,8 @@ use matching_words::{MatchType, PartialMatch, PrimitiveWordId};
 pub use matching_words::{MatchingWord, MatchingWords};
 use serde::Serialize;
 
+use crate::{Index, Result, Segmentation};
+
 pub mod matching_words;
 
 const DEFAULT_CROP_MARKER: &'static str = "…";
@@ -14,6 +16,7 @@ const DEFAULT_HIGHLIGHT_SUFFIX: &'static str = "</em>";
 pub struct MatcherBuilder<'a, A> {
     matching_words: MatchingWords,
     tokenizer: Tokenizer<'a, A>,
+    segmentation: Segmentation,
     crop_marker: Option<String>,
     highlight_prefix: Option<String>,
     highlight_suffix: Option<String>,
@@ -24,23 +27,39 @@ impl<'a, A> MatcherBuilder<'a, A> {
         Self {
             matching_words,
             tokenizer,
+            segmentation: Segmentation::default(),
             crop_marker: None,
             highlight_prefix: None,
             highlight_suffix: None,
         }
     }
 
-    pub fn crop_marker(&mut self, marker: String) -> &Self {
+    /// Splits the texts like the documents of the index are split: with its separators,
+    /// non-separators, dictionary, digit group separators and maximum word length.
+    /// default value if not called: the texts are only split by the tokenizer
+    pub fn index_segmentation(&mut self, rtxn: &heed::RoTxn, index: &Index) -> Result<&mut Self> {
+        self.segmentation = Segmentation::new(rtxn, index)?;
+        Ok(self)
+    }
+
+    /// The marker inserted where the text is cropped, an empty marker inserts nothing.
+    /// default value if not called: `…`
+    pub fn crop_marker(&mut self, marker: String) -> &mut Self {
         self.crop_marker = Some(marker);
         self
     }
 
-    pub fn highlight_prefix(&mut self, prefix: String) -> &Self {
+    /// The tag inserted before each match, when both the prefix and the suffix
+    /// are empty the matches are not highlighted.
+    /// default value if not called: `<em>`
+    pub fn highlight_prefix(&mut self, prefix: String) -> &mut Self {
         self.highlight_prefix = Some(prefix);
         self
     }
 
-    pub fn highlight_suffix(&mut self, suffix: String) -> &Self {
+    /// The tag inserted after each match.
+    /// default value if not called: `</em>`
+    pub fn highlight_suffix(&mut self, suffix: String) -> &mut Self {
         self.highlight_suffix = Some(suffix);
         self
     }
@@ -63,6 +82,7 @@ impl<'a, A> MatcherBuilder<'a, A> {
             text,
             matching_words: &self.matching_words,
             tokenizer: &self.tokenizer,
+            segmentation: &self.segmentation,
             crop_marker,
             highlight_prefix,
             highlight_suffix,
@@ -74,12 +94,40 @@ impl<'a, A> MatcherBuilder<'a, A> {
 #[derive(Copy, Clone, Default)]
 pub struct FormatOptions {
     pub highlight: bool,
+    /// The number of words of the crop window.
     pub crop: Option<usize>,
+    pub crop_strategy: CropStrategy,
+    /// The maximum number of matches highlighted, the next ones are left as is.
+    ///
+    /// The crop window is still placed according to all the matches and may contain more
+    /// matches than the highlighted ones, the first matches of the window are highlighted.
+    pub max_highlights: Option<usize>,
 }
 
 impl FormatOptions {
+    /// Merges the options, the crop strategy of `self` is kept.
     pub fn merge(self, other: Self) -> Self {
-        Self { highlight: self.highlight || other.highlight, crop: self.crop.or(other.crop) }
+        Self {
+            highlight: self.highlight || other.highlight,
+            crop: self.crop.or(other.crop),
+            crop_strategy: self.crop_strategy,
+            max_highlights: self.max_highlights.or(other.max_highlights),
+        }
+    }
+}
+
+/// Where the crop window is placed in the text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CropStrategy {
+    /// Around the interval of matches containing the most distinct query words.
+    Centered,
+    /// Starting at the first match of the text.
+    FirstMatch,
+}
+
+impl Default for CropStrategy {
+    fn default() -> Self {
+        Self::Centered
     }
 }
 
@@ -104,6 +152,7 @@ pub struct Matcher<'t, 'm, A> {
     text: &'t str,
     matching_words: &'m MatchingWords,
     tokenizer: &'m Tokenizer<'m, A>,
+    segmentation: &'m Segmentation,
     crop_marker: &'m str,
     highlight_prefix: &'m str,
     highlight_suffix: &'m str,
@@ -166,7 +215,28 @@ impl<'t, A: AsRef<[u8]>> Matcher<'t, '_, A> {
             false
         }
 
-        let tokens: Vec<_> = self.tokenizer.tokenize(self.text).collect();
+        let tokens = match self.segmentation.prepare(self.text) {
+            Cow::Borrowed(text) => {
+                self.segmentation.merge_tokens(text, self.tokenizer.tokenize(text))
+            }
+            // the separators of the prepared text are replaced by spaces of the same length, its
+            // tokens are at the same offsets in the original text but can't borrow it.
+            Cow::Owned(text) => self
+                .segmentation
+                .merge_tokens(&text, self.tokenizer.tokenize(&text))
+                .into_iter()
+                .map(|token| Token {
+                    kind: token.kind,
+                    lemma: Cow::Owned(token.lemma().to_string()),
+                    char_start: token.char_start,
+                    char_end: token.char_end,
+                    byte_start: token.byte_start,
+                    byte_end: token.byte_end,
+                    char_map: token.char_map,
+                    ..Default::default()
+                })
+                .collect(),
+        };
         let mut matches = Vec::new();
 
         let mut words_positions = tokens
@@ -221,20 +291,34 @@ impl<'t, A: AsRef<[u8]>> Matcher<'t, '_, A> {
         self
     }
 
-    /// Returns boundaries of the words that match the query.
+    /// Returns the bounds, in bytes, of the parts of the text that match the query,
+    /// without cropping nor highlighting the text.
+    ///
+    /// Each word of a split or concatenated match and of a phrase has its own bounds.
     pub fn matches(&mut self) -> Vec<MatchBounds> {
         match &self.matches {
             None => self.compute_matches().matches(),
             Some((tokens, matches)) => matches
                 .iter()
-                .map(|m| MatchBounds {
-                    start: tokens[m.token_position].byte_start,
-                    length: m.match_len,
+                .map(|m| {
+                    let token = &tokens[m.token_position];
+                    let byte_end = self.match_byte_end(token, m.match_len);
+                    MatchBounds { start: token.byte_start, length: byte_end - token.byte_start }
                 })
                 .collect(),
         }
     }
 
+    /// Returns the byte index of the end of a match of `match_len` chars in the token,
+    /// a prefix match only covers the beginning of the token.
+    fn match_byte_end(&self, token: &Token, match_len: usize) -> usize {
+        self.text[token.byte_start..]
+            .char_indices()
+            .enumerate()
+            .find(|(i, _)| *i == match_len)
+            .map_or(token.byte_end, |(_, (i, _))| i + token.byte_start)
+    }
+
     /// Returns the bounds in byte index of the crop window.
     fn crop_bounds(&self, tokens: &[Token], matches: &[Match], crop_size: usize) -> (usize, usize) {
         // if there is no match, we start from the beginning of the string by default.
@@ -321,6 +405,40 @@ impl<'t, A: AsRef<[u8]>> Matcher<'t, '_, A> {
         (crop_byte_start, crop_byte_end)
     }
 
+    /// Returns the bounds in byte index of the crop window starting at the first match.
+    fn first_match_crop_bounds(
+        &self,
+        tokens: &[Token],
+        matches: &[Match],
+        crop_size: usize,
+    ) -> (usize, usize) {
+        // if there is no match, we start from the beginning of the string by default.
+        let first_match_token_position = matches.first().map(|m| m.token_position).unwrap_or(0);
+        let crop_byte_start = tokens.get(first_match_token_position).map_or(0, |t| t.byte_start);
+        let crop_byte_end = tokens[first_match_token_position.min(tokens.len())..]
+            .iter()
+            .filter(|t| !t.is_separator())
+            .take(crop_size)
+            .last()
+            .map_or(self.text.len(), |t| t.byte_end);
+
+        (crop_byte_start, crop_byte_end)
+    }
+
+    /// Returns the matches contained in the crop window starting at the first match.
+    fn first_match_interval<'a>(&self, matches: &'a [Match], crop_size: usize) -> &'a [Match] {
+        match matches.first() {
+            Some(first) => {
+                let len = matches
+                    .iter()
+                    .take_while(|m| m.word_position - first.word_position < crop_size)
+                    .count();
+                &matches[..len]
+            }
+            None => matches,
+        }
+    }
+
     /// Compute the score of a match interval:
     /// 1) count unique matches
     /// 2) calculate distance between matches
@@ -401,7 +519,12 @@ impl<'t, A: AsRef<[u8]>> Matcher<'t, '_, A> {
     }
 
     // Returns the formatted version of the original text.
-    pub fn format(&mut self, format_options: FormatOptions) -> Cow<'t, str> {
+    pub fn format(&mut self, mut format_options: FormatOptions) -> Cow<'t, str> {
+        // highlighting with empty markers doesn't change the text.
+        if self.highlight_prefix.is_empty() && self.highlight_suffix.is_empty() {
+            format_options.highlight = false;
+        }
+
         if !format_options.highlight && format_options.crop.is_none() {
             // compute matches is not needed if no highlight nor crop is requested.
             Cow::Borrowed(self.text)
@@ -409,16 +532,24 @@ impl<'t, A: AsRef<[u8]>> Matcher<'t, '_, A> {
             match &self.matches {
                 Some((tokens, matches)) => {
                     let matches = match format_options.crop {
-                        Some(crop_size) if crop_size > 0 => {
-                            self.find_best_match_interval(matches, crop_size)
-                        }
+                        Some(crop_size) if crop_size > 0 => match format_options.crop_strategy {
+                            CropStrategy::Centered => {
+                                self.find_best_match_interval(matches, crop_size)
+                            }
+                            CropStrategy::FirstMatch => {
+                                self.first_match_interval(matches, crop_size)
+                            }
+                        },
                         _ => matches,
                     };
 
                     let (byte_start, byte_end) = match format_options.crop {
-                        Some(crop_size) if crop_size > 0 => {
-                            self.crop_bounds(tokens, matches, crop_size)
-                        }
+                        Some(crop_size) if crop_size > 0 => match format_options.crop_strategy {
+                            CropStrategy::Centered => self.crop_bounds(tokens, matches, crop_size),
+                            CropStrategy::FirstMatch => {
+                                self.first_match_crop_bounds(tokens, matches, crop_size)
+                            }
+                        },
                         _ => (0, self.text.len()),
                     };
 
@@ -432,19 +563,19 @@ impl<'t, A: AsRef<[u8]>> Matcher<'t, '_, A> {
                     let mut byte_index = byte_start;
 
                     if format_options.highlight {
+                        let highlighted = match format_options.max_highlights {
+                            Some(max) => &matches[..max.min(matches.len())],
+                            None => matches,
+                        };
                         // insert highlight markers around matches.
-                        for m in matches {
+                        for m in highlighted {
                             let token = &tokens[m.token_position];
 
                             if byte_index < token.byte_start {
                                 formatted.push(&self.text[byte_index..token.byte_start]);
                             }
 
-                            let highlight_byte_index = self.text[token.byte_start..]
-                                .char_indices()
-                                .enumerate()
-                                .find(|(i, _)| *i == m.match_len)
-                                .map_or(token.byte_end, |(_, (i, _))| i + token.byte_start);
+                            let highlight_byte_index = self.match_byte_end(token, m.match_len);
                             formatted.push(self.highlight_prefix);
                             formatted.push(&self.text[token.byte_start..highlight_byte_index]);
                             formatted.push(self.highlight_suffix);
@@ -483,9 +614,12 @@ impl<'t, A: AsRef<[u8]>> Matcher<'t, '_, A> {
 #[cfg(test)]
 mod tests {
     use charabia::TokenizerBuilder;
+    use maplit::btreeset;
 
     use super::*;
+    use crate::index::tests::TempIndex;
     use crate::search::matches::matching_words::MatchingWord;
+    use crate::update::{IndexerConfig, Settings};
 
     fn matching_words() -> MatchingWords {
         let matching_words = vec![
@@ -509,7 +643,7 @@ mod tests {
 
         let builder = MatcherBuilder::from_matching_words(matching_words);
 
-        let format_options = FormatOptions { highlight: false, crop: None };
+        let format_options = FormatOptions { highlight: false, crop: None, ..Default::default() };
 
         // Text without any match.
         let text = "A quick brown fox can not jump 32 feet, right? Brr, it is cold!";
@@ -536,7 +670,7 @@ mod tests {
 
         let builder = MatcherBuilder::from_matching_words(matching_words);
 
-        let format_options = FormatOptions { highlight: true, crop: None };
+        let format_options = FormatOptions { highlight: true, crop: None, ..Default::default() };
 
         // empty text.
         let text = "";
@@ -581,7 +715,7 @@ mod tests {
 
         let builder = MatcherBuilder::from_matching_words(matching_words);
 
-        let format_options = FormatOptions { highlight: true, crop: None };
+        let format_options = FormatOptions { highlight: true, crop: None, ..Default::default() };
 
         // Text containing prefix match.
         let text = "Ŵôřlḑôle";
@@ -608,7 +742,8 @@ mod tests {
 
         let builder = MatcherBuilder::from_matching_words(matching_words);
 
-        let format_options = FormatOptions { highlight: false, crop: Some(10) };
+        let format_options =
+            FormatOptions { highlight: false, crop: Some(10), ..Default::default() };
 
         // empty text.
         let text = "";
@@ -699,7 +834,8 @@ mod tests {
 
         let builder = MatcherBuilder::from_matching_words(matching_words);
 
-        let format_options = FormatOptions { highlight: true, crop: Some(10) };
+        let format_options =
+            FormatOptions { highlight: true, crop: Some(10), ..Default::default() };
 
         // empty text.
         let text = "";
@@ -755,19 +891,22 @@ mod tests {
         let text = "void void split the world void void.";
 
         // set a smaller crop size
-        let format_options = FormatOptions { highlight: false, crop: Some(2) };
+        let format_options =
+            FormatOptions { highlight: false, crop: Some(2), ..Default::default() };
         let mut matcher = builder.build(text);
         // because crop size < query size, partially format matches.
         assert_eq!(&matcher.format(format_options), "…split the…");
 
         // set a smaller crop size
-        let format_options = FormatOptions { highlight: false, crop: Some(1) };
+        let format_options =
+            FormatOptions { highlight: false, crop: Some(1), ..Default::default() };
         let mut matcher = builder.build(text);
         // because crop size < query size, partially format matches.
         assert_eq!(&matcher.format(format_options), "…split…");
 
         // set  crop size to 0
-        let format_options = FormatOptions { highlight: false, crop: Some(0) };
+        let format_options =
+            FormatOptions { highlight: false, crop: Some(0), ..Default::default() };
         let mut matcher = builder.build(text);
         // because crop size is 0, crop is ignored.
         assert_eq!(&matcher.format(format_options), "void void split the world void void.");
@@ -801,7 +940,7 @@ mod tests {
         builder.highlight_prefix("_".to_string());
         builder.highlight_suffix("_".to_string());
 
-        let format_options = FormatOptions { highlight: true, crop: None };
+        let format_options = FormatOptions { highlight: true, crop: None, ..Default::default() };
 
         let text = "the do or die can't be he do and or isn't he";
         let mut matcher = builder.build(text);
@@ -812,4 +951,213 @@ mod tests {
             &matcher.matches
         );
     }
+
+    #[test]
+    fn format_crop_strategies() {
+        let matching_words = matching_words();
+        let builder = MatcherBuilder::from_matching_words(matching_words);
+
+        let mut words: Vec<_> = ["lorem", "ipsum", "dolor", "sit", "amet"]
+            .iter()
+            .cycle()
+            .take(200)
+            .map(|w| w.to_string())
+            .collect();
+        words[100] = "split".to_string();
+        words[103] = "the".to_string();
+        words[150] = "world".to_string();
+        let text = words.join(" ");
+
+        let crop_words = |formatted: &str| -> Vec<String> {
+            formatted.trim_matches('…').split(' ').map(|w| w.to_string()).collect()
+        };
+
+        // the window is placed around `split` and `the`, the interval with the most matches.
+        let format_options = FormatOptions {
+            highlight: false,
+            crop: Some(10),
+            crop_strategy: CropStrategy::Centered,
+            ..Default::default()
+        };
+        let mut matcher = builder.build(&text);
+        let centered = matcher.format(format_options).to_string();
+        let centered_words = crop_words(&centered);
+        assert_eq!(centered_words.len(), 10);
+        assert!(centered_words[0] != "split");
+        assert!(centered_words.contains(&"split".to_string()));
+        assert!(centered_words.contains(&"the".to_string()));
+
+        // the window starts at `split`, the first match, and has the same length.
+        let format_options = FormatOptions {
+            highlight: false,
+            crop: Some(10),
+            crop_strategy: CropStrategy::FirstMatch,
+            ..Default::default()
+        };
+        let mut matcher = builder.build(&text);
+        let first_match = matcher.format(format_options).to_string();
+        assert_eq!(first_match, format!("…{}…", words[100..110].join(" ")));
+        assert_eq!(crop_words(&first_match).len(), 10);
+
+        // the window ends with the text when the first match is close to its end.
+        let format_options = FormatOptions {
+            highlight: true,
+            crop: Some(10),
+            crop_strategy: CropStrategy::FirstMatch,
+            ..Default::default()
+        };
+        let text = format!("world {}", words[196..].join(" "));
+        let mut matcher = builder.build(&text);
+        assert_eq!(&matcher.format(format_options), "<em>world</em> ipsum dolor sit amet");
+
+        // without any match the window starts at the beginning of the text.
+        let text = words[..20].join(" ");
+        let mut matcher = builder.build(&text);
+        assert_eq!(&matcher.format(format_options), &format!("{}…", words[..10].join(" ")));
+    }
+
+    #[test]
+    fn format_max_highlights() {
+        let matching_words = matching_words();
+        let builder = MatcherBuilder::from_matching_words(matching_words);
+
+        let text = "lorem split the world, the split ipsum dolor";
+        let format_options =
+            FormatOptions { highlight: true, max_highlights: Some(2), ..Default::default() };
+        let mut matcher = builder.build(text);
+        assert_eq!(
+            &matcher.format(format_options),
+            "lorem <em>split</em> <em>the</em> world, the split ipsum dolor"
+        );
+        // all the matches are still returned.
+        let mut matcher = builder.build(text);
+        assert_eq!(matcher.matches().len(), 5);
+
+        // the crop window contains more matches than the highlighted ones.
+        let format_options = FormatOptions {
+            highlight: true,
+            crop: Some(4),
+            crop_strategy: CropStrategy::FirstMatch,
+            max_highlights: Some(2),
+        };
+        let mut matcher = builder.build(text);
+        assert_eq!(&matcher.format(format_options), "…<em>split</em> <em>the</em> world, the…");
+
+        let format_options =
+            FormatOptions { highlight: true, max_highlights: Some(0), ..Default::default() };
+        let mut matcher = builder.build(text);
+        assert_eq!(&matcher.format(format_options), text);
+    }
+
+    #[test]
+    fn format_custom_markers() {
+        let matching_words = matching_words();
+
+        let mut builder = MatcherBuilder::from_matching_words(matching_words);
+        builder
+            .crop_marker("[...]".to_string())
+            .highlight_prefix("<mark>".to_string())
+            .highlight_suffix("</mark>".to_string());
+
+        let text = "Natalie risk her future to build a world with the boy she loves.";
+        let mut matcher = builder.build(text);
+        let format_options = FormatOptions { highlight: true, crop: None, ..Default::default() };
+        assert_eq!(
+            &matcher.format(format_options),
+            "Natalie risk her future to build a <mark>world</mark> with <mark>the</mark> boy she loves."
+        );
+
+        let mut matcher = builder.build(text);
+        let format_options = FormatOptions { highlight: true, crop: Some(4), ..Default::default() };
+        assert_eq!(
+            &matcher.format(format_options),
+            "[...]a <mark>world</mark> with <mark>the</mark>[...]"
+        );
+    }
+
+    #[test]
+    fn format_empty_markers() {
+        let matching_words = matching_words();
+
+        let mut builder = MatcherBuilder::from_matching_words(matching_words);
+        builder
+            .crop_marker(String::new())
+            .highlight_prefix(String::new())
+            .highlight_suffix(String::new());
+
+        // highlighting with empty markers returns the text as is.
+        let text = "Natalie risk her future to build a world with the boy she loves.";
+        let mut matcher = builder.build(text);
+        let format_options = FormatOptions { highlight: true, crop: None, ..Default::default() };
+        let formatted = matcher.format(format_options);
+        assert!(matches!(formatted, Cow::Borrowed(_)));
+        assert_eq!(&formatted, &text);
+
+        // cropping with an empty marker only returns the crop window.
+        let mut matcher = builder.build(text);
+        let format_options = FormatOptions { highlight: true, crop: Some(4), ..Default::default() };
+        assert_eq!(&matcher.format(format_options), "a world with the");
+    }
+
+    #[test]
+    fn matches_bounds() {
+        let matching_words = vec![
+            (vec![MatchingWord::new("wessfali".to_string(), 1, true)], vec![0]),
+            (vec![MatchingWord::new("world".to_string(), 1, true)], vec![1]),
+            (
+                vec![
+                    MatchingWord::new("do".to_string(), 0, false),
+                    MatchingWord::new("or".to_string(), 0, false),
+                ],
+                vec![2],
+            ),
+        ];
+
+        let matching_words = MatchingWords::new(matching_words);
+        let builder = MatcherBuilder::from_matching_words(matching_words);
+
+        // the bounds are in bytes, not in chars.
+        let text = "Westfália and Ŵôřlḑôle";
+        let mut matcher = builder.build(text);
+        let bounds = matcher.matches();
+        assert_eq!(
+            bounds,
+            vec![MatchBounds { start: 0, length: 9 }, MatchBounds { start: 15, length: 10 }]
+        );
+        assert_eq!(&text[0..9], "Westfáli");
+        assert_eq!(&text[15..25], "Ŵôřlḑ");
+
+        // each word of a split match has its own bounds.
+        let text = "the dõ or die";
+        let mut matcher = builder.build(text);
+        assert_eq!(
+            matcher.matches(),
+            vec![MatchBounds { start: 4, length: 3 }, MatchBounds { start: 8, length: 2 }]
+        );
+    }
+
+    #[test]
+    fn format_with_index_segmentation() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_digit_group_separators(btreeset! { ',' });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let matching_words = vec![
+            (vec![MatchingWord::new("1000".to_string(), 0, false)], vec![0]),
+            (vec![MatchingWord::new("pounds".to_string(), 0, false)], vec![1]),
+        ];
+        let matching_words = MatchingWords::new(matching_words);
+        let mut builder = MatcherBuilder::from_matching_words(matching_words);
+        let format_options = FormatOptions { highlight: true, ..Default::default() };
+
+        // the texts are split like the documents of the index.
+        let rtxn = index.read_txn().unwrap();
+        builder.index_segmentation(&rtxn, &index).unwrap();
+        let mut matcher = builder.build("1,000 pounds");
+        assert_eq!(&matcher.format(format_options), "<em>1,000</em> <em>pounds</em>");
+    }
 }
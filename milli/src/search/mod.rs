@@ -1,29 +1,49 @@
 use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::collections::hash_map::{Entry, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt;
 use std::mem::take;
 use std::result::Result as StdResult;
 use std::str::Utf8Error;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-use charabia::TokenizerBuilder;
+use charabia::{Token, TokenKind, TokenizerBuilder};
+use cursor::{SearchCursor, SortField};
 use distinct::{Distinct, DocIter, FacetDistinct, NoopDistinct};
 use fst::automaton::Str;
 use fst::{Automaton, IntoStreamer, Streamer};
 use levenshtein_automata::{LevenshteinAutomatonBuilder as LevBuilder, DFA};
 use log::debug;
 use once_cell::sync::Lazy;
+use ordered_float::OrderedFloat;
 use roaring::bitmap::RoaringBitmap;
+use serde_json::{Map, Value};
 
-pub use self::facet::{FacetDistribution, FacetNumberIter, Filter, DEFAULT_VALUES_PER_FACET};
+pub use self::facet::{
+    FacetDistribution, FacetNumberIter, FacetSearch, FacetStats, Filter, SampledFacetDistribution,
+    DEFAULT_VALUES_PER_FACET,
+};
 use self::fst_utils::{Complement, Intersection, StartsWith, Union};
+pub use self::fusion::reciprocal_rank_fusion;
 pub use self::matches::{
-    FormatOptions, MatchBounds, Matcher, MatcherBuilder, MatchingWord, MatchingWords,
+    CropStrategy, FormatOptions, MatchBounds, Matcher, MatcherBuilder, MatchingWord, MatchingWords,
+};
+pub use self::multi::{multi_search, MultiSearchHit};
+pub use self::query_tree::QueryTreeNode;
+use self::query_tree::{
+    split_scoped_words, Operation, PrimitiveQuery, PrimitiveQueryPart, QueryRestrictions,
+    QueryTreeBuilder,
 };
-use self::query_tree::QueryTreeBuilder;
-use crate::error::UserError;
+use crate::error::{InternalError, UserError};
 use crate::search::criteria::r#final::{Final, FinalResult};
-use crate::{AscDesc, Criterion, DocumentId, Index, Member, Result};
+use crate::search::criteria::resolve_query_tree;
+use crate::search::criteria::score::Bm25;
+use crate::{
+    relative_from_absolute_position, AscDesc, Criterion, DocumentId, FieldId, FieldsIdsMap, Index,
+    Member, MissingValues, Result, Segmentation,
+};
 
 // Building these factories is not free.
 static LEVDIST0: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(0, true));
@@ -31,22 +51,66 @@ static LEVDIST1: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(1, true));
 static LEVDIST2: Lazy<LevBuilder> = Lazy::new(|| LevBuilder::new(2, true));
 
 mod criteria;
+mod cursor;
 mod distinct;
 mod facet;
 mod fst_utils;
+mod fusion;
 mod matches;
+mod multi;
 mod query_tree;
 
+/// Defines which words of the query can be ignored when
+/// there is not enough documents matching all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TermsMatchingStrategy {
+    /// Remove the last words first.
+    Last,
+    /// Every word of the query must match.
+    All,
+    /// Remove the words matching the most documents first.
+    Frequency,
+    /// At least this number of query words must match, in any combination. A phrase counts
+    /// as one word and must always match, a word matched through a typo, a synonym or a
+    /// concatenation with its neighbour counts like a word matched exactly. The search fails
+    /// when a long query has too many combinations of the words that could match.
+    AtLeast(usize),
+}
+
+impl Default for TermsMatchingStrategy {
+    fn default() -> Self {
+        Self::Last
+    }
+}
+
 pub struct Search<'a> {
     query: Option<String>,
     // this should be linked to the String in the query
     filter: Option<Filter<'a>>,
+    boosts: Vec<(Filter<'a>, f64)>,
+    candidates: Option<RoaringBitmap>,
     offset: usize,
     limit: usize,
+    cursor: Option<String>,
     sort_criteria: Option<Vec<AscDesc>>,
-    optional_words: bool,
+    sort_missing_values: MissingValues,
+    ranking_rules: Option<Vec<String>>,
+    geo_buckets: Option<Vec<usize>>,
+    max_proximity: Option<u8>,
+    terms_matching_strategy: TermsMatchingStrategy,
     authorize_typos: bool,
+    prefix_search: bool,
+    restricted_attributes: Option<Vec<String>>,
+    selected_fields: Option<Vec<String>>,
+    json_documents: bool,
     words_limit: usize,
+    profiling: bool,
+    matched_words: bool,
+    distinct_count: bool,
+    estimate_total_hits: bool,
+    scores: bool,
+    deadline: Option<Instant>,
+    cancellation: Option<&'a AtomicBool>,
     rtxn: &'a heed::RoTxn<'a>,
     index: &'a Index,
 }
@@ -56,12 +120,30 @@ impl<'a> Search<'a> {
         Search {
             query: None,
             filter: None,
+            boosts: Vec::new(),
+            candidates: None,
             offset: 0,
             limit: 20,
+            cursor: None,
             sort_criteria: None,
-            optional_words: true,
+            sort_missing_values: MissingValues::default(),
+            ranking_rules: None,
+            geo_buckets: None,
+            max_proximity: None,
+            terms_matching_strategy: TermsMatchingStrategy::default(),
             authorize_typos: true,
+            prefix_search: true,
+            restricted_attributes: None,
+            selected_fields: None,
+            json_documents: false,
             words_limit: 10,
+            profiling: false,
+            matched_words: false,
+            distinct_count: false,
+            estimate_total_hits: false,
+            scores: false,
+            deadline: None,
+            cancellation: None,
             rtxn,
             index,
         }
@@ -82,13 +164,67 @@ impl<'a> Search<'a> {
         self
     }
 
+    /// Resumes the search right after the last document of a previous page, the cursor is the
+    /// `next_cursor` of the previous result. Only the searches that entirely order the documents
+    /// by sortable attributes can be resumed, the candidates are the documents after the cursor.
+    pub fn cursor(&mut self, cursor: impl Into<String>) -> &mut Search<'a> {
+        self.cursor = Some(cursor.into());
+        self
+    }
+
+    /// Sorts the documents by these criteria in place of the `sort` ranking rule, the documents
+    /// that are equal on a criterion are ordered by the next one and then by their internal ids.
     pub fn sort_criteria(&mut self, criteria: Vec<AscDesc>) -> &mut Search<'a> {
         self.sort_criteria = Some(criteria);
         self
     }
 
+    /// Places the documents without any value for a field of the sort criteria before or after
+    /// the documents having one, they are placed last by default. The documents placed first
+    /// can't be paginated with a cursor.
+    pub fn sort_missing_values(&mut self, missing_values: MissingValues) -> &mut Search<'a> {
+        self.sort_missing_values = missing_values;
+        self
+    }
+
+    /// Replaces the ranking rules of the index for this search only, the rules are
+    /// named like in the settings and the custom ranking rules must use sortable attributes.
+    pub fn ranking_rules(&mut self, rules: Vec<String>) -> &mut Search<'a> {
+        self.ranking_rules = Some(rules);
+        self
+    }
+
+    /// Groups the documents sorted by a `_geoPoint` into buckets of equal rank, the
+    /// boundaries are distances in meters and must be strictly increasing. The documents
+    /// further than the last boundary are all returned in the same final bucket.
+    ///
+    /// By default every document is ranked by its own distance.
+    pub fn geo_buckets(&mut self, boundaries: Vec<usize>) -> &mut Search<'a> {
+        self.geo_buckets = Some(boundaries);
+        self
+    }
+
+    /// Stops the `proximity` ranking rule at this proximity, the documents whose query words
+    /// are farther apart are returned together in a last bucket and ranked by the next rules.
+    ///
+    /// The proximity of a document is the sum of the distances between its consecutive query
+    /// words, a lower cutoff bounds the work done on broad queries but ranks the documents
+    /// with spread out words less precisely.
+    pub fn max_proximity(&mut self, proximity: u8) -> &mut Search<'a> {
+        self.max_proximity = Some(proximity);
+        self
+    }
+
+    /// If set to `false` all the query words must match, which is the same as
+    /// using [`TermsMatchingStrategy::All`], otherwise the last words can be ignored.
     pub fn optional_words(&mut self, value: bool) -> &mut Search<'a> {
-        self.optional_words = value;
+        self.terms_matching_strategy =
+            if value { TermsMatchingStrategy::Last } else { TermsMatchingStrategy::All };
+        self
+    }
+
+    pub fn terms_matching_strategy(&mut self, value: TermsMatchingStrategy) -> &mut Search<'a> {
+        self.terms_matching_strategy = value;
         self
     }
 
@@ -97,6 +233,69 @@ impl<'a> Search<'a> {
         self
     }
 
+    /// If set to `false` the last query word must be found entirely in the documents,
+    /// it is not matched as the prefix of the longer words anymore.
+    pub fn with_prefix_search(&mut self, value: bool) -> &mut Search<'a> {
+        self.prefix_search = value;
+        self
+    }
+
+    /// Reports the query words that matched each of the returned documents, a word
+    /// matched through a typo or a synonym is reported as it was written in the query.
+    pub fn with_matched_words(&mut self, value: bool) -> &mut Search<'a> {
+        self.matched_words = value;
+        self
+    }
+
+    /// Counts the candidates left once deduplicated on the distinct fields in
+    /// [`SearchResult::distinct_count`]. The distinct is applied again on all the
+    /// candidates, its cost grows with their number and not with the requested page.
+    pub fn with_distinct_count(&mut self, value: bool) -> &mut Search<'a> {
+        self.distinct_count = value;
+        self
+    }
+
+    /// Returns an upper bound of the number of hits in [`SearchResult::estimated_total_hits`],
+    /// the number of documents matching the query words and the filters before they are ranked.
+    ///
+    /// The estimate doesn't depend on the buckets of the ranking rules that were fetched to fill
+    /// the page, but it counts the documents that the ranking rules leave out, like the ones
+    /// without the proximity required by the words, and the documents deduplicated by the
+    /// distinct fields.
+    pub fn with_estimated_total_hits(&mut self, value: bool) -> &mut Search<'a> {
+        self.estimate_total_hits = value;
+        self
+    }
+
+    /// Computes the BM25 score of each returned document, to compare the documents
+    /// of several indexes. The order of the documents doesn't depend on the scores
+    /// unless the `score` ranking rule is used.
+    pub fn with_scores(&mut self, value: bool) -> &mut Search<'a> {
+        self.scores = value;
+        self
+    }
+
+    /// Only searches the query words in these attributes, they must be searchable.
+    pub fn restrict_searchable_attributes(&mut self, attributes: &[&str]) -> &mut Search<'a> {
+        self.restricted_attributes = Some(attributes.iter().map(|a| a.to_string()).collect());
+        self
+    }
+
+    /// Returns the documents with only these fields in [`SearchResult::selected_documents`],
+    /// regardless of the displayed attributes. A nested field like `doggo.name` only keeps
+    /// this part of the object it belongs to.
+    pub fn with_fields(&mut self, fields: &[&str]) -> &mut Search<'a> {
+        self.selected_fields = Some(fields.iter().map(|f| f.to_string()).collect());
+        self
+    }
+
+    /// Returns the JSON of the documents stored at indexing time in
+    /// [`SearchResult::json_documents`], the index must store the JSON of the documents.
+    pub fn with_json_documents(&mut self, value: bool) -> &mut Search<'a> {
+        self.json_documents = value;
+        self
+    }
+
     pub fn words_limit(&mut self, value: usize) -> &mut Search<'a> {
         self.words_limit = value;
         self
@@ -107,50 +306,228 @@ impl<'a> Search<'a> {
         self
     }
 
+    /// Ranks higher the documents matching the filter, without excluding the other ones.
+    ///
+    /// The boosts are applied after all the ranking rules, in place of the final tie-break
+    /// on the document ids: the documents that every ranking rule considers equally relevant
+    /// are ordered by decreasing sum of the weights of the boosts they match, a document
+    /// matching no boost weighs zero and a negative weight lowers the documents. A boosted
+    /// document never goes above a document that a ranking rule considers more relevant.
+    pub fn with_boost(&mut self, filter: Filter<'a>, weight: f64) -> &mut Search<'a> {
+        self.boosts.push((filter, weight));
+        self
+    }
+
+    /// Only returns the documents among these candidates, e.g. the documents a user is allowed
+    /// to see. They are intersected with the documents matching the filter and the query.
+    pub fn with_candidates(&mut self, candidates: RoaringBitmap) -> &mut Search<'a> {
+        self.candidates = Some(candidates);
+        self
+    }
+
+    /// Stops ranking the documents once the deadline is reached, the documents ranked so far
+    /// are returned and [`SearchResult::timed_out`] is set. The deadline is only checked
+    /// between the buckets of the criteria, a single bucket can take longer to compute.
+    pub fn with_deadline(&mut self, deadline: Instant) -> &mut Search<'a> {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Aborts the search with a [`UserError::SearchAborted`] error once the flag is set, it is
+    /// checked after the filters are evaluated and between the buckets of the criteria.
+    pub fn with_cancellation(&mut self, flag: &'a AtomicBool) -> &mut Search<'a> {
+        self.cancellation = Some(flag);
+        self
+    }
+
+    /// Records the time spent in each criterion, the timings are
+    /// returned in the [`SearchResult::criteria_timings`] field.
+    pub fn with_profiling(&mut self, value: bool) -> &mut Search<'a> {
+        self.profiling = value;
+        self
+    }
+
+    /// Returns the searchable attributes, all the attributes are searchable by default.
+    fn searchable_attributes(&self) -> Result<BTreeSet<String>> {
+        match self.index.searchable_fields(self.rtxn)? {
+            Some(fields) => Ok(fields.into_iter().map(String::from).collect()),
+            None => {
+                let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+                Ok(fields_ids_map.iter().map(|(_, name)| name.to_string()).collect())
+            }
+        }
+    }
+
+    fn check_cancellation(&self) -> Result<()> {
+        match self.cancellation {
+            Some(flag) if flag.load(Ordering::Relaxed) => Err(UserError::SearchAborted.into()),
+            _ => Ok(()),
+        }
+    }
+
     fn is_typo_authorized(&self) -> Result<bool> {
         let index_authorizes_typos = self.index.authorize_typos(self.rtxn)?;
         // only authorize typos if both the index and the query allow it.
         Ok(self.authorize_typos && index_authorizes_typos)
     }
 
+    /// Builds the query tree of the query with its typos, synonyms, prefixes and phrases,
+    /// without executing the search. Returns `None` when there is no query word.
+    ///
+    /// The tree is wrapped in a [`QueryTreeNode::Restricted`] node when the query has negative,
+    /// scoped or stop words restricting the candidates.
+    pub fn build_query_tree_debug(&self) -> Result<Option<QueryTreeNode>> {
+        let (query_tree, restrictions) = self.build_query_tree()?;
+        let tree = query_tree.map(|(query_tree, _, _)| QueryTreeNode::from(&query_tree));
+        if restrictions == QueryRestrictions::default() {
+            return Ok(tree);
+        }
+
+        let QueryRestrictions { negative_words, scoped_words, stop_words, last_stop_word_prefix } =
+            restrictions;
+        Ok(Some(QueryTreeNode::Restricted {
+            tree: tree.map(Box::new),
+            negative_words,
+            scoped_words,
+            stop_words,
+            last_stop_word_prefix,
+        }))
+    }
+
+    fn build_query_tree(
+        &self,
+    ) -> Result<(Option<(Operation, PrimitiveQuery, MatchingWords)>, QueryRestrictions)> {
+        let query = match self.query.as_ref() {
+            Some(query) => query,
+            None => return Ok((None, QueryRestrictions::default())),
+        };
+
+        let mut builder = QueryTreeBuilder::new(self.rtxn, self.index)?;
+        builder.terms_matching_strategy(self.terms_matching_strategy);
+
+        builder.authorize_typos(self.is_typo_authorized()?);
+        builder.prefix_search(self.prefix_search);
+
+        builder.words_limit(self.words_limit);
+        // We make sure that the analyzer is aware of the stop words
+        // this ensures that the query builder is able to properly remove them.
+        let mut tokbuilder = TokenizerBuilder::new();
+        let stop_words = self.index.stop_words(self.rtxn)?;
+        if let Some(ref stop_words) = stop_words {
+            tokbuilder.stop_words(stop_words);
+        }
+
+        let tokenizer = tokbuilder.build();
+        let segmentation = Segmentation::new(self.rtxn, self.index)?;
+        let (query, scoped_words) = split_scoped_words(query);
+        builder.scoped_words(scoped_words);
+        let query = segmentation.prepare(&query);
+        let tokens = segmentation.merge_tokens(&query, tokenizer.tokenize(&query));
+        builder.build(tokens.into_iter())
+    }
+
     pub fn execute(&self) -> Result<SearchResult> {
         // We create the query tree by spliting the query into tokens.
         let before = Instant::now();
-        let (query_tree, primitive_query, matching_words) = match self.query.as_ref() {
-            Some(query) => {
-                let mut builder = QueryTreeBuilder::new(self.rtxn, self.index)?;
-                builder.optional_words(self.optional_words);
-
-                builder.authorize_typos(self.is_typo_authorized()?);
-
-                builder.words_limit(self.words_limit);
-                // We make sure that the analyzer is aware of the stop words
-                // this ensures that the query builder is able to properly remove them.
-                let mut tokbuilder = TokenizerBuilder::new();
-                let stop_words = self.index.stop_words(self.rtxn)?;
-                if let Some(ref stop_words) = stop_words {
-                    tokbuilder.stop_words(stop_words);
-                }
-
-                let tokenizer = tokbuilder.build();
-                let tokens = tokenizer.tokenize(query);
-                builder
-                    .build(tokens)?
-                    .map_or((None, None, None), |(qt, pq, mw)| (Some(qt), Some(pq), Some(mw)))
-            }
-            None => (None, None, None),
-        };
+        let (query_tree, restrictions) = self.build_query_tree()?;
+        let (query_tree, primitive_query, matching_words) =
+            query_tree.map_or((None, None, None), |(qt, pq, mw)| (Some(qt), Some(pq), Some(mw)));
 
         debug!("query tree: {:?} took {:.02?}", query_tree, before.elapsed());
 
+        // The ids of the matching words are the positions of the words in the primitive query.
+        let query_words: Vec<String> = match &primitive_query {
+            Some(primitive_query) if self.matched_words => primitive_query
+                .iter()
+                .flat_map(|part| match part {
+                    PrimitiveQueryPart::Word(word, _) => vec![word.clone()],
+                    PrimitiveQueryPart::Phrase(words) => words.clone(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
         // We create the original candidates with the facet conditions results.
         let before = Instant::now();
-        let filtered_candidates = match &self.filter {
+        let mut filtered_candidates = match &self.filter {
             Some(condition) => Some(condition.evaluate(self.rtxn, self.index)?),
             None => None,
         };
+        if let Some(allowed) = &self.candidates {
+            filtered_candidates = match filtered_candidates {
+                Some(candidates) => Some(candidates & allowed),
+                None => Some(allowed.clone()),
+            };
+        }
+
+        // The documents containing a negative word are removed from the candidates
+        // before ranking, the negative words must match exactly.
+        if !restrictions.negative_words.is_empty() {
+            let mut negative_docids = RoaringBitmap::new();
+            for word in &restrictions.negative_words {
+                negative_docids |= self.index.word_docids.get(self.rtxn, word)?.unwrap_or_default();
+                negative_docids |=
+                    self.index.exact_word_docids.get(self.rtxn, word)?.unwrap_or_default();
+            }
+            let candidates = match filtered_candidates {
+                Some(candidates) => candidates,
+                None => self.index.documents_ids(self.rtxn)?,
+            };
+            filtered_candidates = Some(candidates - negative_docids);
+        }
+
+        // The documents must contain the scoped words in the searchable attribute
+        // they are scoped to, the scoped words must match exactly.
+        if !restrictions.scoped_words.is_empty() {
+            let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+            let searchable_fields = self.searchable_attributes()?;
+            let mut candidates = match filtered_candidates {
+                Some(candidates) => candidates,
+                None => self.index.documents_ids(self.rtxn)?,
+            };
+            for (attribute, word) in &restrictions.scoped_words {
+                let attribute_lowercase = attribute.to_lowercase();
+                let field = searchable_fields
+                    .iter()
+                    .find(|name| name.to_lowercase() == attribute_lowercase);
+                match field.map(|name| fields_ids_map.id(name)) {
+                    Some(Some(fid)) => {
+                        candidates &= attribute_word_docids(self.rtxn, self.index, word, fid)?
+                    }
+                    // no document contains this searchable attribute yet.
+                    Some(None) => candidates.clear(),
+                    None => {
+                        return Err(UserError::InvalidSearchableAttribute {
+                            field: attribute.to_string(),
+                            valid_fields: searchable_fields,
+                        })?
+                    }
+                }
+            }
+            filtered_candidates = Some(candidates);
+        }
+
+        // A query only made of stop words matches the documents containing all of them,
+        // they are only found in their own database. A last stop word that is a prefix
+        // also matches the documents containing a longer word it starts.
+        if !restrictions.stop_words.is_empty() {
+            let mut candidates = match filtered_candidates {
+                Some(candidates) => candidates,
+                None => self.index.documents_ids(self.rtxn)?,
+            };
+            let last = restrictions.stop_words.len() - 1;
+            for (i, word) in restrictions.stop_words.iter().enumerate() {
+                let mut docids = stop_word_docids(self.rtxn, self.index, word)?;
+                if i == last && restrictions.last_stop_word_prefix {
+                    docids |= prefix_docids(self.rtxn, self.index, word)?;
+                }
+                candidates &= docids;
+            }
+            filtered_candidates = Some(candidates);
+        }
 
         debug!("facet candidates: {:?} took {:.02?}", filtered_candidates, before.elapsed());
+        self.check_cancellation()?;
 
         // We check that we are allowed to use the sort criteria, we check
         // that they are declared in the sortable fields.
@@ -164,7 +541,7 @@ impl<'a> Search<'a> {
                             valid_fields: sortable_fields.into_iter().collect(),
                         })?
                     }
-                    Member::Geo(_) if !sortable_fields.contains("_geo") => {
+                    Member::Geo(_) | Member::GeoPoints(_) if !sortable_fields.contains("_geo") => {
                         return Err(UserError::InvalidSortableAttribute {
                             field: "_geo".to_string(),
                             valid_fields: sortable_fields.into_iter().collect(),
@@ -175,78 +552,370 @@ impl<'a> Search<'a> {
             }
         }
 
+        if let Some(boundaries) = &self.geo_buckets {
+            let increasing = boundaries.windows(2).all(|w| w[0] < w[1]);
+            if boundaries.is_empty() || !increasing {
+                return Err(UserError::InvalidGeoBuckets { boundaries: boundaries.clone() }.into());
+            }
+        }
+
+        if let Some(fields) = &self.selected_fields {
+            let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+            for field in fields {
+                // the objects of a nested field are not always in the fields ids map.
+                if !fields_ids_map.iter().any(|(_, name)| crate::is_faceted_by(name, field)) {
+                    return Err(UserError::InvalidSelectedAttribute {
+                        field: field.to_string(),
+                        valid_fields: fields_ids_map
+                            .iter()
+                            .map(|(_, name)| name.to_string())
+                            .collect(),
+                    })?;
+                }
+            }
+        }
+
+        if self.json_documents && !self.index.store_documents_json(self.rtxn)? {
+            return Err(UserError::DocumentsJsonNotStored.into());
+        }
+
+        // The ranking rules of the search are used in place of the ones of the index.
+        let ranking_rules = match &self.ranking_rules {
+            Some(names) => {
+                let sortable_fields = self.index.sortable_fields(self.rtxn)?;
+                let mut ranking_rules = Vec::with_capacity(names.len());
+                for name in names {
+                    let criterion: Criterion = name.parse()?;
+                    match criterion.field_name() {
+                        Some(field) if !crate::is_faceted(field, &sortable_fields) => {
+                            return Err(UserError::InvalidSortableAttribute {
+                                field: field.to_string(),
+                                valid_fields: sortable_fields.into_iter().collect(),
+                            })?
+                        }
+                        _ => ranking_rules.push(criterion),
+                    }
+                }
+                ranking_rules
+            }
+            None => self.index.criteria(self.rtxn)?,
+        };
+
         // We check that the sort ranking rule exists and throw an
         // error if we try to use it and that it doesn't.
-        let sort_ranking_rule_missing = !self.index.criteria(self.rtxn)?.contains(&Criterion::Sort);
+        let sort_ranking_rule_missing = !ranking_rules.contains(&Criterion::Sort);
         let empty_sort_criteria = self.sort_criteria.as_ref().map_or(true, |s| s.is_empty());
         if sort_ranking_rule_missing && !empty_sort_criteria {
             return Err(UserError::SortRankingRuleMissing.into());
         }
 
-        let criteria_builder = criteria::CriteriaBuilder::new(self.rtxn, self.index)?;
+        // The documents already returned in the previous pages are removed from the candidates.
+        let cursor_sort_fields = self.cursor_sort_fields(&query_tree, &ranking_rules)?;
+        if let Some(cursor) = &self.cursor {
+            let fields =
+                cursor_sort_fields.as_ref().ok_or(UserError::CursorPaginationUnsupported)?;
+            let cursor = SearchCursor::decode(cursor, fields)?;
+            let candidates = match filtered_candidates {
+                Some(candidates) => candidates,
+                None => self.index.documents_ids(self.rtxn)?,
+            };
+            filtered_candidates =
+                Some(cursor.candidates_after(self.rtxn, self.index, &candidates)?);
+        }
+
+        let mut criteria_builder = criteria::CriteriaBuilder::new(self.rtxn, self.index)?;
+        if let Some(attributes) = &self.restricted_attributes {
+            let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+            let searchable_fields = self.searchable_attributes()?;
+            let mut fields_ids = HashSet::new();
+            for attribute in attributes {
+                if !searchable_fields.contains(attribute) {
+                    return Err(UserError::InvalidSearchableAttribute {
+                        field: attribute.to_string(),
+                        valid_fields: searchable_fields,
+                    })?;
+                }
+                // the attributes that no document contains yet don't have any field id.
+                fields_ids.extend(fields_ids_map.id(attribute));
+            }
+            criteria_builder.restrict_fields_ids(fields_ids);
+        }
+        criteria_builder.sort_missing_values(self.sort_missing_values);
+        if let Some(proximity) = self.max_proximity {
+            criteria_builder.max_proximity(proximity);
+        }
+        let scored_query_tree = if self.scores { query_tree.clone() } else { None };
+
+        // every hit matches the query tree and the filters before the ranking rules split them.
+        let estimated_total_hits = if self.estimate_total_hits {
+            let mut candidates = match &filtered_candidates {
+                Some(candidates) => candidates.clone(),
+                None => self.index.documents_ids(self.rtxn)?,
+            };
+            if let Some(query_tree) = &query_tree {
+                let mut wdcache = WordDerivationsCache::new();
+                candidates &= resolve_query_tree(&criteria_builder, query_tree, &mut wdcache)?;
+            }
+            candidates -= self.index.soft_deleted_documents_ids(self.rtxn)?;
+            Some(candidates.len())
+        } else {
+            None
+        };
+
         let criteria = criteria_builder.build(
             query_tree,
             primitive_query,
             filtered_candidates,
+            ranking_rules,
             self.sort_criteria.clone(),
+            self.geo_buckets.clone(),
+            self.profiling,
         )?;
 
-        match self.index.distinct_field(self.rtxn)? {
-            None => self.perform_sort(NoopDistinct, matching_words.unwrap_or_default(), criteria),
-            Some(name) => {
-                let field_ids_map = self.index.fields_ids_map(self.rtxn)?;
-                match field_ids_map.id(name) {
-                    Some(fid) => {
-                        let distinct = FacetDistinct::new(fid, self.index, self.rtxn);
-                        self.perform_sort(distinct, matching_words.unwrap_or_default(), criteria)
+        let mut boosts = Vec::with_capacity(self.boosts.len());
+        for (filter, weight) in &self.boosts {
+            boosts.push((filter.evaluate(self.rtxn, self.index)?, *weight));
+        }
+
+        let distinct_fields = self.index.distinct_fields(self.rtxn)?;
+        let mut result = if distinct_fields.is_empty() {
+            let matching_words = matching_words.unwrap_or_default();
+            self.perform_sort(NoopDistinct, false, matching_words, criteria, &boosts)?
+        } else {
+            // the fields that are not in the fields ids map are missing from every document.
+            let field_ids_map = self.index.fields_ids_map(self.rtxn)?;
+            let fids: Vec<_> =
+                distinct_fields.iter().filter_map(|name| field_ids_map.id(name)).collect();
+            if fids.is_empty() {
+                return Ok(SearchResult::default());
+            }
+            let distinct = FacetDistinct::new(fids, self.index, self.rtxn);
+            self.perform_sort(
+                distinct,
+                self.distinct_count,
+                matching_words.unwrap_or_default(),
+                criteria,
+                &boosts,
+            )?
+        };
+        result.estimated_total_hits = estimated_total_hits;
+
+        // There is no next page when this one is not full.
+        if let Some(fields) = cursor_sort_fields {
+            if let (Some(&docid), true) =
+                (result.documents_ids.last(), result.documents_ids.len() == self.limit)
+            {
+                result.next_cursor =
+                    Some(SearchCursor::new(self.rtxn, self.index, fields, docid)?.encode());
+            }
+        }
+
+        if let Some(query_tree) = scored_query_tree {
+            let bm25 = Bm25::new(&criteria_builder)?;
+            let terms =
+                bm25.terms(&criteria_builder, &query_tree, &mut WordDerivationsCache::new())?;
+            for &docid in &result.documents_ids {
+                result.scores.insert(docid, bm25.score(&criteria_builder, &terms, docid)?);
+            }
+        }
+
+        if let Some(fields) = &self.selected_fields {
+            let fields_ids_map = self.index.fields_ids_map(self.rtxn)?;
+            for (docid, obkv) in
+                self.index.documents(self.rtxn, result.documents_ids.iter().copied())?
+            {
+                let document = select_fields(&fields_ids_map, obkv, fields)?;
+                result.selected_documents.insert(docid, document);
+            }
+        }
+
+        if self.json_documents {
+            for &docid in &result.documents_ids {
+                if let Some(json) = self.index.document_json(self.rtxn, docid)? {
+                    result.json_documents.insert(docid, json.to_vec());
+                }
+            }
+        }
+
+        if self.matched_words {
+            for &docid in &result.documents_ids {
+                let words =
+                    self.document_matched_words(docid, &result.matching_words, &query_words)?;
+                result.matched_words.insert(docid, words);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Returns the fields that entirely define the order of the documents, `None` when the
+    /// documents are ranked by relevancy and the search can't be resumed with a cursor.
+    fn cursor_sort_fields(
+        &self,
+        query_tree: &Option<Operation>,
+        ranking_rules: &[Criterion],
+    ) -> Result<Option<Vec<SortField>>> {
+        if query_tree.is_some()
+            || self.geo_buckets.is_some()
+            || self.sort_missing_values == MissingValues::First
+            || !self.boosts.is_empty()
+            || !self.index.distinct_fields(self.rtxn)?.is_empty()
+        {
+            return Ok(None);
+        }
+
+        let mut fields = Vec::new();
+        for rule in ranking_rules {
+            match rule {
+                Criterion::Asc(field) => fields.push((field.clone(), true)),
+                Criterion::Desc(field) => fields.push((field.clone(), false)),
+                Criterion::Sort => {
+                    for asc_desc in self.sort_criteria.iter().flatten() {
+                        match asc_desc {
+                            AscDesc::Asc(Member::Field(field)) => {
+                                fields.push((field.clone(), true))
+                            }
+                            AscDesc::Desc(Member::Field(field)) => {
+                                fields.push((field.clone(), false))
+                            }
+                            // the distances to the geo points are not stored.
+                            _ => return Ok(None),
+                        }
                     }
-                    None => Ok(SearchResult::default()),
+                }
+                // without query words the other ranking rules keep the order of the documents.
+                _ => (),
+            }
+        }
+
+        Ok(Some(fields))
+    }
+
+    /// Returns the query words matched by the words indexed for this document, in the query order.
+    fn document_matched_words(
+        &self,
+        docid: DocumentId,
+        matching_words: &MatchingWords,
+        query_words: &[String],
+    ) -> Result<Vec<String>> {
+        let mut tokens = Vec::new();
+        for result in self.index.docid_word_positions.prefix_iter(self.rtxn, &(docid, ""))? {
+            let ((_, word), _) = result?;
+            tokens.push(Token {
+                kind: TokenKind::Word,
+                lemma: Cow::Borrowed(word),
+                char_end: word.chars().count(),
+                byte_end: word.len(),
+                ..Default::default()
+            });
+        }
+
+        let mut words = Vec::new();
+        for id in matching_words.matched_ids(&tokens) {
+            if let Some(word) = query_words.get(id as usize) {
+                // a word can appear several times in the query.
+                if !words.contains(word) {
+                    words.push(word.clone());
                 }
             }
         }
+        Ok(words)
     }
 
+    /// Returns the requested page of documents, the `count_distinct` parameter
+    /// indicates that the distinct documents of all the candidates must be counted.
+    /// The buckets of the criteria are split by the weights of the `boosts`.
     fn perform_sort<D: Distinct>(
         &self,
         mut distinct: D,
+        count_distinct: bool,
         matching_words: MatchingWords,
         mut criteria: Final,
+        boosts: &[(RoaringBitmap, f64)],
     ) -> Result<SearchResult> {
-        let mut offset = self.offset;
+        // The documents after the max total hits are never returned, the
+        // criteria are only called until the requested window is filled.
+        let (mut offset, limit) = match self.index.pagination_max_total_hits(self.rtxn)? {
+            Some(max_total_hits) if self.offset >= max_total_hits => (0, 0),
+            Some(max_total_hits) => (self.offset, self.limit.min(max_total_hits - self.offset)),
+            None => (self.offset, self.limit),
+        };
         let mut initial_candidates = RoaringBitmap::new();
-        let mut excluded_candidates = RoaringBitmap::new();
+        // The soft deleted documents are still in the databases until they are purged.
+        let soft_deleted = self.index.soft_deleted_documents_ids(self.rtxn)?;
+        let mut excluded_candidates = soft_deleted.clone();
         let mut documents_ids = Vec::new();
+        let mut timed_out = false;
 
-        while let Some(FinalResult { candidates, bucket_candidates, .. }) =
-            criteria.next(&excluded_candidates)?
-        {
-            debug!("Number of candidates found {}", candidates.len());
+        loop {
+            self.check_cancellation()?;
+            if self.deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                timed_out = true;
+                break;
+            }
+            let FinalResult { candidates, bucket_candidates, .. } =
+                match criteria.next(&excluded_candidates)? {
+                    Some(result) => result,
+                    None => break,
+                };
 
-            let excluded = take(&mut excluded_candidates);
-            let mut candidates = distinct.distinct(candidates, excluded);
+            debug!("Number of candidates found {}", candidates.len());
 
             initial_candidates |= bucket_candidates;
 
-            if offset != 0 {
-                let discarded = candidates.by_ref().take(offset).count();
-                offset = offset.saturating_sub(discarded);
-            }
+            for candidates in boosted_buckets(candidates, boosts) {
+                let excluded = take(&mut excluded_candidates);
+                let mut candidates = distinct.distinct(candidates, excluded);
 
-            for candidate in candidates.by_ref().take(self.limit - documents_ids.len()) {
-                documents_ids.push(candidate?);
-            }
+                if offset != 0 {
+                    let discarded = candidates.by_ref().take(offset).count();
+                    offset = offset.saturating_sub(discarded);
+                }
+
+                for candidate in candidates.by_ref().take(limit - documents_ids.len()) {
+                    documents_ids.push(candidate?);
+                }
 
-            excluded_candidates |= candidates.into_excluded();
+                excluded_candidates |= candidates.into_excluded();
+            }
 
-            if documents_ids.len() == self.limit {
+            if documents_ids.len() == limit {
                 break;
             }
         }
 
+        // The distinct is applied again on all the candidates to count them,
+        // the candidates of the buckets that were not fetched included.
+        let distinct_count = if count_distinct {
+            let mut count = 0;
+            for docid in distinct.distinct(initial_candidates.clone(), soft_deleted.clone()) {
+                docid?;
+                count += 1;
+            }
+            Some(count)
+        } else {
+            None
+        };
+
+        let geo_distances = documents_ids
+            .iter()
+            .filter_map(|&docid| criteria.geo_distance(docid).map(|distance| (docid, distance)))
+            .collect();
+
         Ok(SearchResult {
             matching_words,
             candidates: initial_candidates - excluded_candidates,
+            distinct_count,
+            estimated_total_hits: None,
             documents_ids,
+            geo_distances,
+            criteria_timings: criteria.criteria_timings(),
+            matched_words: HashMap::new(),
+            scores: HashMap::new(),
+            selected_documents: HashMap::new(),
+            json_documents: HashMap::new(),
+            next_cursor: None,
+            timed_out,
         })
     }
 }
@@ -256,24 +925,60 @@ impl fmt::Debug for Search<'_> {
         let Search {
             query,
             filter,
+            boosts,
+            candidates,
             offset,
             limit,
+            cursor,
             sort_criteria,
-            optional_words,
+            sort_missing_values,
+            ranking_rules,
+            geo_buckets,
+            max_proximity,
+            terms_matching_strategy,
             authorize_typos,
+            prefix_search,
+            restricted_attributes,
+            selected_fields,
+            json_documents,
             words_limit,
+            profiling,
+            matched_words,
+            distinct_count,
+            estimate_total_hits,
+            scores,
+            deadline,
+            cancellation,
             rtxn: _,
             index: _,
         } = self;
         f.debug_struct("Search")
             .field("query", query)
             .field("filter", filter)
+            .field("boosts", boosts)
+            .field("candidates", candidates)
             .field("offset", offset)
             .field("limit", limit)
+            .field("cursor", cursor)
             .field("sort_criteria", sort_criteria)
-            .field("optional_words", optional_words)
+            .field("sort_missing_values", sort_missing_values)
+            .field("ranking_rules", ranking_rules)
+            .field("geo_buckets", geo_buckets)
+            .field("max_proximity", max_proximity)
+            .field("terms_matching_strategy", terms_matching_strategy)
             .field("authorize_typos", authorize_typos)
+            .field("prefix_search", prefix_search)
+            .field("restricted_attributes", restricted_attributes)
+            .field("selected_fields", selected_fields)
+            .field("json_documents", json_documents)
             .field("words_limit", words_limit)
+            .field("profiling", profiling)
+            .field("matched_words", matched_words)
+            .field("distinct_count", distinct_count)
+            .field("estimate_total_hits", estimate_total_hits)
+            .field("scores", scores)
+            .field("deadline", deadline)
+            .field("cancellation", cancellation)
             .finish()
     }
 }
@@ -282,16 +987,208 @@ impl fmt::Debug for Search<'_> {
 pub struct SearchResult {
     pub matching_words: MatchingWords,
     pub candidates: RoaringBitmap,
+    /// The number of candidates left once deduplicated on the distinct fields, `None` unless
+    /// requested with [`Search::with_distinct_count`] and there is a distinct field.
+    pub distinct_count: Option<u64>,
+    /// An upper bound of the number of hits, `None` unless the estimate is requested
+    /// with [`Search::with_estimated_total_hits`].
+    pub estimated_total_hits: Option<u64>,
     // TODO those documents ids should be associated with their criteria scores.
     pub documents_ids: Vec<DocumentId>,
+    /// The distances, in meters, between the returned documents and the `_geoPoint`
+    /// sort origin, empty when the search is not sorted by a geo point.
+    pub geo_distances: HashMap<DocumentId, f64>,
+    /// The time spent in each criterion, in the order of the criteria,
+    /// empty when the search is not profiled.
+    pub criteria_timings: Vec<(String, Duration)>,
+    /// The query words matched by each of the returned documents,
+    /// empty when the matched words are not requested.
+    pub matched_words: HashMap<DocumentId, Vec<String>>,
+    /// The BM25 scores of the returned documents, empty when the scores are not requested.
+    /// Without query the documents don't have any score.
+    pub scores: HashMap<DocumentId, f64>,
+    /// The returned documents as obkvs only made of the fields selected with
+    /// [`Search::with_fields`], empty when no field is selected.
+    pub selected_documents: HashMap<DocumentId, Vec<u8>>,
+    /// The JSON of the returned documents, empty when it is not requested.
+    pub json_documents: HashMap<DocumentId, Vec<u8>>,
+    /// The cursor to fetch the next page with, `None` when this page is the last one
+    /// or when the documents are not entirely ordered by sortable attributes.
+    pub next_cursor: Option<String>,
+    /// Whether the deadline of [`Search::with_deadline`] was reached, the documents
+    /// and the candidates are then only the ones ranked before the deadline.
+    pub timed_out: bool,
 }
 
 pub type WordDerivationsCache = HashMap<(String, bool, u8), Vec<(String, u8)>>;
 
+/// Writes a new obkv with only the selected fields of a document, the top-level
+/// fields that are only partially selected keep the selected parts of their value.
+fn select_fields(
+    fields_ids_map: &FieldsIdsMap,
+    obkv: obkv::KvReaderU16,
+    fields: &[String],
+) -> Result<Vec<u8>> {
+    let mut writer = obkv::KvWriterU16::memory();
+    for (fid, value) in obkv.iter() {
+        let name = match fields_ids_map.name(fid) {
+            Some(name) => name,
+            None => continue,
+        };
+        let paths: Vec<_> = fields.iter().filter_map(|field| strip_field(field, name)).collect();
+        if paths.is_empty() {
+            continue;
+        }
+        if paths.contains(&"") {
+            writer.insert(fid, value)?;
+        } else {
+            let value: Value = serde_json::from_slice(value).map_err(InternalError::SerdeJson)?;
+            if let Some(value) = select_paths(&value, &paths) {
+                let value = serde_json::to_vec(&value).map_err(InternalError::SerdeJson)?;
+                writer.insert(fid, value)?;
+            }
+        }
+    }
+    Ok(writer.into_inner()?)
+}
+
+/// Returns the rest of the path once the key is removed, empty when the path is the key,
+/// `None` when the path doesn't go through the key.
+fn strip_field<'p>(path: &'p str, key: &str) -> Option<&'p str> {
+    match path.strip_prefix(key)? {
+        "" => Some(""),
+        rest => rest.strip_prefix('.'),
+    }
+}
+
+/// Keeps the parts of the value at the end of the paths, the arrays keep the selected
+/// parts of each of their objects. `None` when no path is found in this value.
+fn select_paths(value: &Value, paths: &[&str]) -> Option<Value> {
+    match value {
+        Value::Object(object) => {
+            let mut selected = Map::new();
+            for (key, value) in object {
+                let rests: Vec<_> =
+                    paths.iter().filter_map(|path| strip_field(path, key)).collect();
+                if rests.contains(&"") {
+                    selected.insert(key.clone(), value.clone());
+                } else if !rests.is_empty() {
+                    if let Some(value) = select_paths(value, &rests) {
+                        selected.insert(key.clone(), value);
+                    }
+                }
+            }
+            (!selected.is_empty()).then(|| Value::Object(selected))
+        }
+        Value::Array(values) => {
+            let selected: Vec<_> =
+                values.iter().filter_map(|value| select_paths(value, paths)).collect();
+            (!selected.is_empty()).then(|| Value::Array(selected))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the documents containing the word in the given attribute.
+fn attribute_word_docids(
+    rtxn: &heed::RoTxn,
+    index: &Index,
+    word: &str,
+    fid: FieldId,
+) -> heed::Result<RoaringBitmap> {
+    let range = (word, u32::min_value())..=(word, u32::max_value());
+    let mut docids = RoaringBitmap::new();
+    for result in index.word_position_docids.range(rtxn, &range)? {
+        let ((_, position), positions_docids) = result?;
+        if relative_from_absolute_position(position).0 == fid {
+            docids |= positions_docids;
+        }
+    }
+    // the stop words are only found in their own database.
+    if let Some(stop_word_docids) = index.stop_word_docids.get(rtxn, &(word, fid as u32))? {
+        docids |= stop_word_docids;
+    }
+    Ok(docids)
+}
+
+/// Returns the documents ids containing the stop word in any of their attributes.
+fn stop_word_docids(rtxn: &heed::RoTxn, index: &Index, word: &str) -> heed::Result<RoaringBitmap> {
+    let range = (word, u32::min_value())..=(word, u32::max_value());
+    let mut docids = RoaringBitmap::new();
+    for result in index.stop_word_docids.range(rtxn, &range)? {
+        let ((stop_word, _), field_docids) = result?;
+        // the longer stop words starting with this one can be in the range too.
+        if stop_word == word {
+            docids |= field_docids;
+        }
+    }
+    Ok(docids)
+}
+
+/// Returns the documents containing a word starting with the prefix, the prefix databases are
+/// used when the prefix is one of the indexed prefixes.
+fn prefix_docids(rtxn: &heed::RoTxn, index: &Index, prefix: &str) -> heed::Result<RoaringBitmap> {
+    if let Some(docids) = index.word_prefix_docids.get(rtxn, prefix)? {
+        let exact_docids = index.exact_word_prefix_docids.get(rtxn, prefix)?;
+        return Ok(docids | exact_docids.unwrap_or_default());
+    }
+
+    let mut docids = RoaringBitmap::new();
+    for result in index.word_docids.prefix_iter(rtxn, prefix)? {
+        let (_word, word_docids) = result?;
+        docids |= word_docids;
+    }
+    for result in index.exact_word_docids.prefix_iter(rtxn, prefix)? {
+        let (_word, word_docids) = result?;
+        docids |= word_docids;
+    }
+    Ok(docids)
+}
+
+/// Splits a bucket of equally relevant documents by decreasing sum of the weights of the
+/// boosts they match, the documents with the same sum stay ordered by their ids.
+fn boosted_buckets(
+    candidates: RoaringBitmap,
+    boosts: &[(RoaringBitmap, f64)],
+) -> Vec<RoaringBitmap> {
+    if boosts.is_empty() {
+        return vec![candidates];
+    }
+
+    // each boost splits the groups in the documents it matches and the others.
+    let mut groups = vec![(0.0, candidates)];
+    for (docids, weight) in boosts {
+        let mut split_groups = Vec::with_capacity(groups.len() * 2);
+        for (sum, group) in groups {
+            let matching = &group & docids;
+            if matching.is_empty() {
+                split_groups.push((sum, group));
+            } else {
+                let others = group - &matching;
+                split_groups.push((sum + *weight, matching));
+                if !others.is_empty() {
+                    split_groups.push((sum, others));
+                }
+            }
+        }
+        groups = split_groups;
+    }
+
+    let mut buckets: BTreeMap<_, RoaringBitmap> = BTreeMap::new();
+    for (sum, group) in groups {
+        *buckets.entry(Reverse(OrderedFloat(sum))).or_default() |= group;
+    }
+    buckets.into_iter().map(|(_, bucket)| bucket).collect()
+}
+
+/// Returns the words of the fst matching the query word with at most `max_typo` typos. A typo on
+/// the first char costs two typos and is only allowed with `first_char_typo`. The cache must
+/// only be shared by the derivations computed with the same `first_char_typo`.
 pub fn word_derivations<'c>(
     word: &str,
     is_prefix: bool,
     max_typo: u8,
+    first_char_typo: bool,
     fst: &fst::Set<Cow<[u8]>>,
     cache: &'c mut WordDerivationsCache,
 ) -> StdResult<&'c [(String, u8)], Utf8Error> {
@@ -312,8 +1209,8 @@ pub fn word_derivations<'c>(
                     derived_words.push((word.to_string(), 0));
                 }
             } else {
-                if max_typo == 1 {
-                    let dfa = build_dfa(word, 1, is_prefix);
+                if max_typo == 1 || !first_char_typo {
+                    let dfa = build_dfa(word, max_typo, is_prefix);
                     let starts = StartsWith(Str::new(get_first(word)));
                     let mut stream =
                         fst.search_with_state(Intersection(starts, &dfa)).into_stream();
@@ -373,10 +1270,61 @@ pub fn build_dfa(word: &str, typos: u8, is_prefix: bool) -> DFA {
     }
 }
 
+/// The automata of the derivations of a query word, to check the words of a document with the
+/// same rules as [`word_derivations`]: a typo on the first char costs two typos and is only
+/// allowed with `first_char_typo`.
+pub struct DerivationsDfa {
+    first_char: Option<char>,
+    dfa: DFA,
+    /// The automaton of the words starting with another char, only built when a typo is
+    /// allowed on the first char.
+    first_char_dfa: Option<DFA>,
+}
+
+impl DerivationsDfa {
+    pub fn new(word: &str, max_typo: u8, is_prefix: bool, first_char_typo: bool) -> Self {
+        let first_char_dfa = if max_typo >= 2 && first_char_typo {
+            Some(build_dfa(word, 1, is_prefix))
+        } else {
+            None
+        };
+        DerivationsDfa {
+            first_char: word.chars().next(),
+            dfa: build_dfa(word, max_typo, is_prefix),
+            first_char_dfa,
+        }
+    }
+
+    /// Returns the number of typos of the word, `None` if it isn't a derivation of the query word.
+    pub fn eval(&self, word: &str) -> Option<u8> {
+        use levenshtein_automata::Distance;
+
+        if word.chars().next() == self.first_char {
+            match self.dfa.eval(word) {
+                Distance::Exact(typo) => Some(typo),
+                Distance::AtLeast(_) => None,
+            }
+        } else {
+            match self.first_char_dfa.as_ref()?.eval(word) {
+                Distance::Exact(_) => Some(2),
+                Distance::AtLeast(_) => None,
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use big_s::S;
+    use maplit::{hashmap, hashset};
+
     use super::*;
     use crate::index::tests::TempIndex;
+    use crate::update::{
+        DeleteDocuments, IndexDocuments, IndexDocumentsConfig, IndexDocumentsMethod, IndexerConfig,
+        Settings,
+    };
+    use crate::Error;
 
     #[test]
     fn test_is_authorized_typos() {
@@ -403,11 +1351,490 @@ mod test {
         assert!(!search.is_typo_authorized().unwrap());
     }
 
+    #[test]
+    fn test_geo_distances() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_sortable_fields(hashset! { S("_geo") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "_geo": { "lat": 43.2965, "lng": 5.3698 } },
+            { "id": 1, "_geo": { "lat": 48.8566, "lng": 2.3522 } },
+            { "id": 2 },
+            { "id": 3, "_geo": { "lat": 50.6292, "lng": 3.0573 } },
+            { "id": 4, "_geo": { "lat": 45.7640, "lng": 4.8357 } },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        // without any geo sort there is no distance to return.
+        let SearchResult { documents_ids, geo_distances, .. } =
+            Search::new(&rtxn, &index).execute().unwrap();
+        assert_eq!(documents_ids.len(), 5);
+        assert!(geo_distances.is_empty());
+
+        let mut search = Search::new(&rtxn, &index);
+        search.sort_criteria(vec![AscDesc::Asc(Member::Geo([48.8566, 2.3522]))]);
+        let SearchResult { documents_ids, geo_distances, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, &[1, 3, 4, 0, 2]);
+
+        // the document without any `_geo` field is returned last and has no distance.
+        assert_eq!(geo_distances.len(), 4);
+        assert_eq!(geo_distances.get(&2), None);
+        assert!(geo_distances[&1] < 1.0);
+
+        let distances: Vec<_> =
+            documents_ids.iter().filter_map(|docid| geo_distances.get(docid)).collect();
+        assert!(distances.windows(2).all(|w| w[0] <= w[1]), "{:?}", distances);
+    }
+
+    #[test]
+    fn test_negative_words() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "title": "running shoes" },
+            { "id": 1, "title": "red shoes" },
+            { "id": 2, "title": "running socks" },
+            { "id": 3, "title": "blue hat" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let search = |query: &str| {
+            let mut search = Search::new(&rtxn, &index);
+            search.query(query);
+            search.terms_matching_strategy(TermsMatchingStrategy::All);
+            let mut documents_ids = search.execute().unwrap().documents_ids;
+            documents_ids.sort_unstable();
+            documents_ids
+        };
+
+        assert_eq!(search("shoes"), &[0, 1]);
+        assert_eq!(search("shoes -running"), &[1]);
+        // a query with only negative words matches everything but these words.
+        assert_eq!(search("-running"), &[1, 3]);
+        assert_eq!(search("-running -shoes"), &[3]);
+        // a word both negated and searched is not excluded.
+        assert_eq!(search("shoes -shoes"), &[0, 1]);
+        // a lone `-` doesn't negate anything.
+        assert_eq!(search("shoes - running"), &[0]);
+    }
+
+    #[test]
+    fn test_only_stop_words() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_stop_words(vec![S("the"), S("of")].into_iter().collect());
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "the the" },
+            { "id": 1, "title": "the lord of the rings" },
+            { "id": 2, "title": "lord of war" },
+            { "id": 3, "title": "rings" },
+            { "id": 4, "title": "the theory of everything" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let search = |query: &str| {
+            let mut search = Search::new(&rtxn, &index);
+            search.query(query);
+            search.terms_matching_strategy(TermsMatchingStrategy::All);
+            let mut documents_ids = search.execute().unwrap().documents_ids;
+            documents_ids.sort_unstable();
+            documents_ids
+        };
+
+        // a query only made of stop words matches them literally.
+        assert_eq!(search("the the"), &[0, 1, 4]);
+        assert_eq!(search("of the "), &[1, 4]);
+        assert_eq!(search("-lord the"), &[0, 4]);
+        // the last word also matches the longer words it starts.
+        assert_eq!(search("the"), &[0, 1, 4]);
+        assert_eq!(search("of the"), &[1, 4]);
+        // the stop words are ignored as soon as there is another word.
+        assert_eq!(search("the lord"), search("lord"));
+        assert_eq!(search("the lord of the rings"), &[1]);
+        assert_eq!(search("rings of the "), &[1, 3]);
+
+        // the deleted documents are removed from the stop words.
+        drop(rtxn);
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("0");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let docids = stop_word_docids(&rtxn, &index, "the").unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), &[1, 4]);
+    }
+
+    #[test]
+    fn test_invalid_geo_buckets() {
+        let index = TempIndex::new();
+        let rtxn = index.read_txn().unwrap();
+
+        for boundaries in [vec![], vec![100, 100], vec![1000, 100]] {
+            let mut search = Search::new(&rtxn, &index);
+            search.geo_buckets(boundaries);
+            let error = search.execute().unwrap_err();
+            assert!(matches!(error, crate::Error::UserError(UserError::InvalidGeoBuckets { .. })));
+        }
+
+        let mut search = Search::new(&rtxn, &index);
+        search.geo_buckets(vec![100, 1000]);
+        assert!(search.execute().is_ok());
+    }
+
+    #[test]
+    fn test_criteria_timings() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_sortable_fields(hashset! { S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "hello world", "price": 12 },
+            { "id": 1, "name": "hello", "price": 3 },
+            { "id": 2, "name": "world", "price": 7 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let mut search = Search::new(&rtxn, &index);
+        search.query("hello world");
+        let SearchResult { criteria_timings, .. } = search.execute().unwrap();
+        assert!(criteria_timings.is_empty());
+
+        // the sort criterion is replaced by the sort criteria of the search.
+        search.sort_criteria(vec![AscDesc::Asc(Member::Field(S("price")))]);
+        search.with_profiling(true);
+        let SearchResult { criteria_timings, .. } = search.execute().unwrap();
+        let names: Vec<_> = criteria_timings.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, &["words", "typo", "proximity", "attribute", "price:asc", "exactness"]);
+    }
+
+    #[test]
+    fn test_ranking_rules_override() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_sortable_fields(hashset! { S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "hello world", "price": 12 },
+            { "id": 1, "name": "hello", "price": 3 },
+            { "id": 2, "name": "world hello", "price": 7 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let mut search = Search::new(&rtxn, &index);
+        search.query("hello world");
+        search.terms_matching_strategy(TermsMatchingStrategy::All);
+        search.with_profiling(true);
+
+        // the proximity comes first with the ranking rules of the index.
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 2]);
+
+        search.ranking_rules(vec![S("words"), S("price:asc"), S("proximity")]);
+        let SearchResult { documents_ids, criteria_timings, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![2, 0]);
+        let names: Vec<_> = criteria_timings.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, &["words", "price:asc", "proximity"]);
+
+        search.ranking_rules(vec![S("words"), S("price:desc"), S("proximity")]);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 2]);
+
+        // the sort ranking rule can be promoted above the proximity.
+        search.ranking_rules(vec![S("words"), S("sort"), S("proximity")]);
+        search.sort_criteria(vec![AscDesc::Asc(Member::Field(S("price")))]);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![2, 0]);
+
+        // the sort criteria can't be used without the sort ranking rule.
+        search.ranking_rules(vec![S("words"), S("proximity")]);
+        let error = search.execute().unwrap_err();
+        assert!(matches!(error, Error::UserError(UserError::SortRankingRuleMissing)));
+
+        search.ranking_rules(vec![S("words"), S("wrong")]);
+        let error = search.execute().unwrap_err();
+        assert!(matches!(error, Error::UserError(UserError::CriterionError(_))));
+
+        search.ranking_rules(vec![S("words"), S("name:asc")]);
+        let error = search.execute().unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UserError(UserError::InvalidSortableAttribute { field, .. }) if field == "name"
+        ));
+    }
+
+    #[test]
+    fn test_query_tree_debug() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_synonyms(hashmap! { S("hello") => vec![S("bonjour")] });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = Search::new(&rtxn, &index);
+        assert_eq!(search.build_query_tree_debug().unwrap(), None);
+
+        // the last word is a prefix with one typo allowed.
+        search.query("wrold");
+        let tree = search.build_query_tree_debug().unwrap().unwrap();
+        let expected = QueryTreeNode::Tolerant { word: S("wrold"), typos: 1, prefix: true };
+        assert_eq!(tree, expected);
+        assert_eq!(
+            serde_json::to_value(&tree).unwrap(),
+            serde_json::json!({ "type": "tolerant", "word": "wrold", "typos": 1, "prefix": true }),
+        );
+
+        search.query("hello");
+        search.with_prefix_search(false);
+        let tree = search.build_query_tree_debug().unwrap().unwrap();
+        let expected = QueryTreeNode::Or {
+            word_branch: false,
+            children: vec![
+                QueryTreeNode::Exact { word: S("bonjour"), original_typos: 0, prefix: false },
+                QueryTreeNode::Tolerant { word: S("hello"), typos: 1, prefix: false },
+            ],
+        };
+        assert_eq!(tree, expected);
+
+        // the negative words are returned along with the tree.
+        search.query("hello -world");
+        let tree = search.build_query_tree_debug().unwrap().unwrap();
+        let expected = QueryTreeNode::Restricted {
+            tree: Some(Box::new(expected)),
+            negative_words: vec![S("world")],
+            scoped_words: vec![],
+            stop_words: vec![],
+            last_stop_word_prefix: false,
+        };
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn test_prefix_search_toggle() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "green apple" },
+            { "id": 1, "name": "green app" },
+            { "id": 2, "name": "red apps" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let mut search = Search::new(&rtxn, &index);
+        search.query("app");
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 0, 2]);
+
+        search.with_prefix_search(false);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1]);
+
+        // the typos are still allowed on the last word.
+        search.query("applr");
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0]);
+
+        // the last word is still removed first when there are not enough documents.
+        search.query("green app");
+        search.terms_matching_strategy(TermsMatchingStrategy::Last);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 0]);
+
+        search.terms_matching_strategy(TermsMatchingStrategy::All);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1]);
+    }
+
+    #[test]
+    fn test_scoped_words() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("title"), S("body"), S("sub_title")]);
+        builder.set_stop_words(vec![S("the")].into_iter().collect());
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "the rust book", "body": "learn the language" },
+            { "id": 1, "title": "car care", "body": "rust removal and polish", "sub_title": "rust" },
+            { "id": 2, "title": "rustic recipes", "body": "cooking in the countryside" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let mut search = Search::new(&rtxn, &index);
+        search.query("rust ");
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1]);
+
+        search.query("title:rust");
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0]);
+
+        search.query("Body:rust polish");
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1]);
+
+        // the other words are still searched in all the attributes.
+        search.query("title:rust language");
+        search.terms_matching_strategy(TermsMatchingStrategy::All);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0]);
+
+        search.terms_matching_strategy(TermsMatchingStrategy::default());
+
+        // the attributes are not tokenized like the words.
+        search.query("sub_title:rust");
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1]);
+
+        // the stop words are also scoped to their attribute.
+        search.query("body:the");
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 2]);
+
+        search.query("title:the rust");
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0]);
+
+        search.query("id:rust");
+        let error = search.execute().unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UserError(UserError::InvalidSearchableAttribute { field, .. }) if field == "id"
+        ));
+    }
+
+    #[test]
+    fn test_restrict_searchable_attributes() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_searchable_fields(vec![S("title"), S("body"), S("tags")]);
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "the rust book", "body": "learn the language" },
+            { "id": 1, "title": "car care", "body": "rust removal and polish" },
+            { "id": 2, "title": "cooking", "body": "recipes", "author": "rust" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let mut search = Search::new(&rtxn, &index);
+        search.query("rust");
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1]);
+
+        search.restrict_searchable_attributes(&["title"]);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0]);
+
+        search.restrict_searchable_attributes(&["body"]);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1]);
+
+        // no document contains the tags yet.
+        search.restrict_searchable_attributes(&["tags"]);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert!(documents_ids.is_empty());
+
+        search.restrict_searchable_attributes(&["title", "author"]);
+        let error = search.execute().unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UserError(UserError::InvalidSearchableAttribute { field, .. }) if field == "author"
+        ));
+    }
+
     #[test]
     fn test_one_typos_tolerance() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
         let mut cache = HashMap::new();
-        let found = word_derivations("zealend", false, 1, &fst, &mut cache).unwrap();
+        let found = word_derivations("zealend", false, 1, true, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[("zealand".to_string(), 1)]);
     }
@@ -416,7 +1843,7 @@ mod test {
     fn test_one_typos_first_letter() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
         let mut cache = HashMap::new();
-        let found = word_derivations("sealand", false, 1, &fst, &mut cache).unwrap();
+        let found = word_derivations("sealand", false, 1, true, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[]);
     }
@@ -425,7 +1852,7 @@ mod test {
     fn test_two_typos_tolerance() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
         let mut cache = HashMap::new();
-        let found = word_derivations("zealemd", false, 2, &fst, &mut cache).unwrap();
+        let found = word_derivations("zealemd", false, 2, true, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[("zealand".to_string(), 2)]);
     }
@@ -434,16 +1861,28 @@ mod test {
     fn test_two_typos_first_letter() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
         let mut cache = HashMap::new();
-        let found = word_derivations("sealand", false, 2, &fst, &mut cache).unwrap();
+        let found = word_derivations("sealand", false, 2, true, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[("zealand".to_string(), 2)]);
     }
 
+    #[test]
+    fn test_exact_first_char() {
+        let fst = fst::Set::from_iter(["abd", "xbc"].iter()).unwrap().map_data(Cow::Owned).unwrap();
+        let mut cache = HashMap::new();
+        let found = word_derivations("abc", false, 2, true, &fst, &mut cache).unwrap();
+        assert_eq!(found, &[("abd".to_string(), 1), ("xbc".to_string(), 2)]);
+
+        let mut cache = HashMap::new();
+        let found = word_derivations("abc", false, 2, false, &fst, &mut cache).unwrap();
+        assert_eq!(found, &[("abd".to_string(), 1)]);
+    }
+
     #[test]
     fn test_prefix() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
         let mut cache = HashMap::new();
-        let found = word_derivations("ze", true, 0, &fst, &mut cache).unwrap();
+        let found = word_derivations("ze", true, 0, true, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[("zealand".to_string(), 0)]);
     }
@@ -452,7 +1891,7 @@ mod test {
     fn test_bad_prefix() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
         let mut cache = HashMap::new();
-        let found = word_derivations("se", true, 0, &fst, &mut cache).unwrap();
+        let found = word_derivations("se", true, 0, true, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[]);
     }
@@ -461,8 +1900,752 @@ mod test {
     fn test_prefix_with_typo() {
         let fst = fst::Set::from_iter(["zealand"].iter()).unwrap().map_data(Cow::Owned).unwrap();
         let mut cache = HashMap::new();
-        let found = word_derivations("zae", true, 1, &fst, &mut cache).unwrap();
+        let found = word_derivations("zae", true, 1, true, &fst, &mut cache).unwrap();
 
         assert_eq!(found, &[("zealand".to_string(), 1)]);
     }
+
+    #[test]
+    fn test_at_least_terms_matching_strategy() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "cheap yellow ferrari" },
+            { "id": 1, "name": "yellow truck" },
+            { "id": 2, "name": "cheap yellow speedy ferrari truck" },
+            { "id": 3, "name": "speedy ferrari truck cheap" },
+            { "id": 4, "name": "cheep green ferrari" },
+            { "id": 5, "name": "cheep ferrari speedy" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        // the documents matching 2 words are not returned, whichever words are matched,
+        // and the typo on "cheap" counts as a matched word.
+        let mut search = Search::new(&rtxn, &index);
+        search.query("cheap yellow speedy ferrari truck ");
+        search.terms_matching_strategy(TermsMatchingStrategy::AtLeast(3));
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![2, 3, 0, 5]);
+
+        search.terms_matching_strategy(TermsMatchingStrategy::AtLeast(2));
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids[..4], [2, 3, 0, 5]);
+        assert_eq!(documents_ids.len(), 6);
+
+        // when more words are required than the query contains all of them must match.
+        search.terms_matching_strategy(TermsMatchingStrategy::AtLeast(10));
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![2]);
+    }
+
+    #[test]
+    fn test_at_least_terms_matching_strategy_long_query() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "text": "the quick brown fox jumps over lazy dogs" },
+            { "id": 1, "text": "the brown jumps over lazy dogs" },
+            { "id": 2, "text": "quick brown fox jumps over" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        // any 6 of the 8 words can match, the first words are not required.
+        let mut search = Search::new(&rtxn, &index);
+        search.query("the quick brown fox jumps over lazy dogs");
+        search.terms_matching_strategy(TermsMatchingStrategy::AtLeast(6));
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1]);
+
+        // there are too many combinations of 2 of the 8 words.
+        search.terms_matching_strategy(TermsMatchingStrategy::AtLeast(2));
+        let error = search.execute().unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UserError(UserError::TooManyAtLeastCombinations { min_words: 2, words: 8 })
+        ));
+    }
+
+    #[test]
+    fn test_cursor_pagination() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_sortable_fields(hashset! { S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "hello", "price": 12 },
+            { "id": 1, "name": "hello", "price": 3 },
+            { "id": 2, "name": "hello", "price": [7, 1] },
+            { "id": 3, "name": "hello", "price": 7 },
+            { "id": 4, "name": "hello", "price": "cheap" },
+            { "id": 5, "name": "hello" },
+            { "id": 6, "name": "hello", "price": 3 },
+            { "id": 7, "name": "hello", "price": "free" },
+            { "id": 8, "name": "hello", "price": 25 },
+            { "id": 9, "name": "hello" },
+            { "id": 10, "name": "hello", "price": 7 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        for asc_desc in
+            [AscDesc::Asc(Member::Field(S("price"))), AscDesc::Desc(Member::Field(S("price")))]
+        {
+            let search = |offset: usize, cursor: Option<String>| {
+                let mut search = Search::new(&rtxn, &index);
+                search.sort_criteria(vec![asc_desc.clone()]).limit(3).offset(offset);
+                if let Some(cursor) = cursor {
+                    search.cursor(cursor);
+                }
+                search.execute().unwrap()
+            };
+
+            let mut next_cursor = None;
+            for page in 0..4 {
+                let expected = search(page * 3, None).documents_ids;
+                let SearchResult { documents_ids, next_cursor: cursor, .. } =
+                    search(0, next_cursor.take());
+                assert_eq!(documents_ids, expected);
+                next_cursor = cursor;
+            }
+            // the last page is not full.
+            assert!(next_cursor.is_none());
+        }
+
+        let mut search = Search::new(&rtxn, &index);
+        search.sort_criteria(vec![AscDesc::Asc(Member::Field(S("price")))]);
+        search.limit(3);
+        let SearchResult { next_cursor, .. } = search.execute().unwrap();
+        let next_cursor = next_cursor.unwrap();
+
+        // the cursor can't be used with another sort.
+        let mut other_sort = Search::new(&rtxn, &index);
+        other_sort.sort_criteria(vec![AscDesc::Desc(Member::Field(S("price")))]);
+        other_sort.cursor(next_cursor.clone());
+        let error = other_sort.execute().unwrap_err();
+        assert!(matches!(error, Error::UserError(UserError::InvalidSearchCursor { .. })));
+
+        search.cursor("not a cursor");
+        let error = search.execute().unwrap_err();
+        assert!(matches!(error, Error::UserError(UserError::InvalidSearchCursor { .. })));
+
+        // the documents are ranked by relevancy when there is a query.
+        search.cursor(next_cursor);
+        search.query("hello");
+        let error = search.execute().unwrap_err();
+        assert!(matches!(error, Error::UserError(UserError::CursorPaginationUnsupported)));
+    }
+
+    #[test]
+    fn test_cursor_pagination_booleans() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_sortable_fields(hashset! { S("available") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "available": true },
+            { "id": 1, "available": false },
+            { "id": 2, "available": [true, false] },
+            { "id": 3, "available": "soon" },
+            { "id": 4 },
+            { "id": 5, "available": true },
+            { "id": 6, "available": 1 },
+            { "id": 7, "available": false },
+            { "id": 8, "available": true },
+            { "id": 9, "available": false },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        for asc_desc in [
+            AscDesc::Asc(Member::Field(S("available"))),
+            AscDesc::Desc(Member::Field(S("available"))),
+        ] {
+            let search = |limit: usize, offset: usize, cursor: Option<String>| {
+                let mut search = Search::new(&rtxn, &index);
+                search.sort_criteria(vec![asc_desc.clone()]).limit(limit).offset(offset);
+                if let Some(cursor) = cursor {
+                    search.cursor(cursor);
+                }
+                search.execute().unwrap()
+            };
+
+            // the pages of two documents split the groups of equal booleans.
+            let all = search(10, 0, None).documents_ids;
+            let mut paged = Vec::new();
+            let mut next_cursor = None;
+            for page in 0..5 {
+                let SearchResult { documents_ids, next_cursor: cursor, .. } =
+                    search(2, 0, next_cursor.take());
+                assert_eq!(documents_ids, search(2, page * 2, None).documents_ids);
+                paged.extend(documents_ids);
+                next_cursor = cursor;
+            }
+            assert_eq!(paged, all);
+            // the last page is full, the page after it is empty.
+            let after_last = search(2, 0, next_cursor).documents_ids;
+            assert!(after_last.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_matched_words() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_synonyms(hashmap! { S("nyc") => vec![S("new york")] });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "hello world" },
+            { "id": 1, "name": "hello" },
+            { "id": 2, "name": "new york city" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let mut search = Search::new(&rtxn, &index);
+        search.query("hello worlf");
+        let SearchResult { matched_words, .. } = search.execute().unwrap();
+        assert!(matched_words.is_empty());
+
+        // the typo is reported as the original query word.
+        search.with_matched_words(true);
+        let SearchResult { documents_ids, matched_words, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1]);
+        assert_eq!(matched_words[&0], vec![S("hello"), S("worlf")]);
+        assert_eq!(matched_words[&1], vec![S("hello")]);
+
+        search.query("nyc");
+        let SearchResult { documents_ids, matched_words, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![2]);
+        assert_eq!(matched_words[&2], vec![S("nyc")]);
+    }
+
+    #[test]
+    fn test_estimated_total_hits() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("genre") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "hello world", "genre": "rock" },
+            { "id": 1, "name": "hello", "genre": "rock" },
+            { "id": 2, "name": "hello there", "genre": "jazz" },
+            { "id": 3, "name": "hello again", "genre": "pop" },
+            { "id": 4, "name": "goodbye", "genre": "pop" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+
+        let mut search = Search::new(&rtxn, &index);
+        search.query("hello").limit(1);
+        let SearchResult { estimated_total_hits, .. } = search.execute().unwrap();
+        assert_eq!(estimated_total_hits, None);
+
+        // the documents matching a single word are in the next buckets, they are
+        // estimated even if only the first bucket is fetched.
+        search.query("hello world").with_estimated_total_hits(true);
+        let SearchResult { estimated_total_hits, .. } = search.execute().unwrap();
+        assert_eq!(estimated_total_hits, Some(4));
+
+        search.limit(10).with_estimated_total_hits(false);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert!(estimated_total_hits.unwrap() >= documents_ids.len() as u64);
+
+        // the filters are applied to the estimate.
+        let filter = Filter::from_str("genre = rock").unwrap().unwrap();
+        search.filter(filter).limit(1).with_estimated_total_hits(true);
+        let SearchResult { estimated_total_hits, .. } = search.execute().unwrap();
+        assert_eq!(estimated_total_hits, Some(2));
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_distinct_field(S("genre"));
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // the documents deduplicated by the distinct field are still estimated.
+        let rtxn = index.read_txn().unwrap();
+        let mut search = Search::new(&rtxn, &index);
+        search.query("hello").limit(1).with_distinct_count(true);
+        let SearchResult { distinct_count, .. } = search.execute().unwrap();
+        assert_eq!(distinct_count, Some(3));
+
+        search.with_estimated_total_hits(true);
+        let SearchResult { distinct_count, estimated_total_hits, .. } = search.execute().unwrap();
+        assert_eq!(distinct_count, Some(3));
+        assert_eq!(estimated_total_hits, Some(4));
+    }
+
+    #[test]
+    fn test_bm25_scores() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "the cat" },
+            { "id": 1, "name": "cat cat cat and other animals" },
+            { "id": 2, "name": "a cat in a very long sentence about many other animals living on the farm" },
+            { "id": 3, "name": "dog" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = Search::new(&rtxn, &index);
+        search.query("cat");
+        let SearchResult { documents_ids: unscored_ids, scores, .. } = search.execute().unwrap();
+        assert!(scores.is_empty());
+
+        // the scores don't change the order of the documents.
+        search.with_scores(true);
+        let SearchResult { documents_ids, scores, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, unscored_ids);
+        assert_eq!(scores.len(), 3);
+        // more occurrences of the word score higher, and so does a shorter document.
+        assert!(scores[&1] > scores[&0]);
+        assert!(scores[&0] > scores[&2]);
+
+        search.ranking_rules(vec![S("words"), S("score")]);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_deadline() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let values: Vec<_> = (0..1000)
+            .map(|i| serde_json::json!({ "id": i, "text": format!("hello world {}", i % 7) }))
+            .collect();
+        let content = documents!(values);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let documents_ids = index.documents_ids(&rtxn).unwrap();
+        let mut search = Search::new(&rtxn, &index);
+        search.query("hello world");
+        let SearchResult { documents_ids: ids, timed_out, .. } = search.execute().unwrap();
+        assert!(!timed_out);
+        assert_eq!(ids.len(), 20);
+
+        // the deadline is already reached, the partial page is still valid.
+        search.with_deadline(Instant::now());
+        let SearchResult { documents_ids: ids, timed_out, .. } = search.execute().unwrap();
+        assert!(timed_out);
+        assert!(ids.len() <= 20);
+        assert!(ids.iter().all(|id| documents_ids.contains(*id)));
+
+        search.with_deadline(Instant::now() + Duration::from_secs(3600));
+        let SearchResult { documents_ids: ids, timed_out, .. } = search.execute().unwrap();
+        assert!(!timed_out);
+        assert_eq!(ids.len(), 20);
+    }
+
+    /// Sets the flag once the first bucket has been distincted.
+    struct CancellingDistinct<'a>(&'a AtomicBool);
+
+    impl Distinct for CancellingDistinct<'_> {
+        type Iter = <NoopDistinct as Distinct>::Iter;
+
+        fn distinct(&mut self, candidates: RoaringBitmap, excluded: RoaringBitmap) -> Self::Iter {
+            self.0.store(true, Ordering::Relaxed);
+            NoopDistinct.distinct(candidates, excluded)
+        }
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "text": "hello" },
+            { "id": 1, "text": "hallo" },
+            { "id": 2, "text": "hellu" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let flag = AtomicBool::new(false);
+        let rtxn = index.read_txn().unwrap();
+        let mut search = Search::new(&rtxn, &index);
+        search.query("hello").with_cancellation(&flag);
+        assert_eq!(search.execute().unwrap().documents_ids.len(), 3);
+
+        flag.store(true, Ordering::Relaxed);
+        let error = search.execute().unwrap_err();
+        assert!(matches!(error, Error::UserError(UserError::SearchAborted)));
+
+        // the flag is set after the first bucket, the second one is never fetched.
+        flag.store(false, Ordering::Relaxed);
+        let (query_tree, _) = search.build_query_tree().unwrap();
+        let (query_tree, primitive_query, matching_words) = query_tree.unwrap();
+        let criteria_builder = criteria::CriteriaBuilder::new(&rtxn, &index).unwrap();
+        let ranking_rules = index.criteria(&rtxn).unwrap();
+        let criteria = criteria_builder
+            .build(Some(query_tree), Some(primitive_query), None, ranking_rules, None, None, false)
+            .unwrap();
+        let distinct = CancellingDistinct(&flag);
+        let error =
+            search.perform_sort(distinct, false, matching_words, criteria, &[]).unwrap_err();
+        assert!(matches!(error, Error::UserError(UserError::SearchAborted)));
+        assert!(flag.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_candidates_mask() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("color") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "text": "hello world", "color": "red" },
+            { "id": 1, "text": "hello", "color": "blue" },
+            { "id": 2, "text": "world", "color": "red" },
+            { "id": 3, "text": "hello there", "color": "red" },
+            { "id": 4, "text": "bye", "color": "blue" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let allowed: RoaringBitmap = [0, 1, 2].iter().copied().collect();
+        let search = |query: Option<&str>, filter: Option<&'static str>| {
+            let mut search = Search::new(&rtxn, &index);
+            search.with_candidates(allowed.clone());
+            if let Some(query) = query {
+                search.query(query);
+            }
+            if let Some(filter) = filter {
+                search.filter(Filter::from_str(filter).unwrap().unwrap());
+            }
+            let SearchResult { mut documents_ids, candidates, .. } = search.execute().unwrap();
+            assert!(candidates.is_subset(&allowed));
+            documents_ids.sort_unstable();
+            documents_ids
+        };
+
+        assert_eq!(search(None, None), vec![0, 1, 2]);
+        assert_eq!(search(Some("hello"), None), vec![0, 1]);
+        assert_eq!(search(None, Some("color = red")), vec![0, 2]);
+        assert_eq!(search(Some("hello"), Some("color = red")), vec![0]);
+        assert_eq!(search(Some("bye"), None), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_selected_fields() {
+        let index = TempIndex::new();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin", "doggo": { "name": "bob", "age": 4 }, "city": "paris" },
+            { "id": 1, "name": "jean", "doggo": [{ "name": "max" }, { "age": 2 }] },
+            { "id": 2, "name": "kevina", "doggo": "rex" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // the displayed attributes don't restrict the selected fields.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_displayed_fields(vec![S("id")]);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let all_fields: Vec<_> = fields_ids_map.ids().collect();
+        let mut search = Search::new(&rtxn, &index);
+        search.query("kevin");
+        let SearchResult { selected_documents, .. } = search.execute().unwrap();
+        assert!(selected_documents.is_empty());
+
+        search.query("").with_fields(&["name", "doggo.name"]);
+        let SearchResult { documents_ids, selected_documents, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1, 2]);
+        let documents: Vec<_> = documents_ids
+            .iter()
+            .map(|docid| {
+                let obkv = obkv::KvReaderU16::new(&selected_documents[docid]);
+                crate::obkv_to_json(&all_fields, &fields_ids_map, obkv).unwrap()
+            })
+            .map(Value::Object)
+            .collect();
+        assert_eq!(
+            documents,
+            vec![
+                serde_json::json!({ "name": "kevin", "doggo": { "name": "bob" } }),
+                serde_json::json!({ "name": "jean", "doggo": [{ "name": "max" }] }),
+                // the value of doggo isn't an object.
+                serde_json::json!({ "name": "kevina" }),
+            ]
+        );
+
+        search.with_fields(&["doggo.color"]);
+        assert!(matches!(
+            search.execute(),
+            Err(Error::UserError(UserError::InvalidSelectedAttribute { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_boost() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("stock"), S("color") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "title": "hello", "stock": "no", "color": "red" },
+            { "id": 1, "title": "hello", "stock": "yes", "color": "blue" },
+            { "id": 2, "title": "hello", "stock": "no", "color": "blue" },
+            { "id": 3, "title": "helo", "stock": "yes", "color": "red" },
+            { "id": 4, "title": "hello", "stock": "yes", "color": "red" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = Search::new(&rtxn, &index);
+        search.query("hello");
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1, 2, 4, 3]);
+
+        // the document with a typo is less relevant, it isn't boosted above the other ones.
+        search.with_boost(Filter::from_str("stock = yes").unwrap().unwrap(), 1.0);
+        let SearchResult { documents_ids, candidates, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 4, 0, 2, 3]);
+        assert_eq!(candidates.len(), 5);
+
+        // the weights of the boosts matched by a document are added.
+        search.with_boost(Filter::from_str("color = red").unwrap().unwrap(), 2.0);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![4, 0, 1, 2, 3]);
+
+        search.offset(1).limit(2);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1]);
+
+        // a negative weight lowers the documents among the equally relevant ones.
+        let mut search = Search::new(&rtxn, &index);
+        search.query("hello");
+        search.with_boost(Filter::from_str("color = red").unwrap().unwrap(), -1.0);
+        let SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 2, 0, 4, 3]);
+    }
+
+    #[test]
+    fn test_json_documents() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 0, "name": "kevin", "price": 1.5, "tags": ["a", "b"] }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // the JSON of the documents is not stored by default.
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.store_documents_json(&rtxn).unwrap());
+        assert!(index.document_json(&rtxn, 0).unwrap().is_none());
+        let mut search = Search::new(&rtxn, &index);
+        let SearchResult { json_documents, .. } = search.execute().unwrap();
+        assert!(json_documents.is_empty());
+        search.with_json_documents(true);
+        assert!(matches!(
+            search.execute(),
+            Err(Error::UserError(UserError::DocumentsJsonNotStored))
+        ));
+        drop(rtxn);
+
+        // the documents already indexed are stored when the setting is enabled.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_store_documents_json(true);
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([{ "id": 1, "name": "jean", "price": 12 }]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = Search::new(&rtxn, &index);
+        search.with_json_documents(true);
+        let SearchResult { documents_ids, json_documents, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1]);
+        assert_eq!(json_documents[&0], br#"{"id":0,"name":"kevin","price":1.5,"tags":["a","b"]}"#);
+        assert_eq!(json_documents[&1], br#"{"id":1,"name":"jean","price":12}"#);
+        let document: Value = serde_json::from_slice(&json_documents[&1]).unwrap();
+        assert_eq!(document, serde_json::json!({ "id": 1, "name": "jean", "price": 12 }));
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_store_documents_json();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.document_json(&rtxn, 0).unwrap().is_none());
+        assert!(index.documents_json.is_empty(&rtxn).unwrap());
+    }
+
+    #[test]
+    fn test_json_documents_keep_their_bytes() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_store_documents_json(true);
+        builder.execute(|_| ()).unwrap();
+
+        let first = r#"{"price" : 1.0, "id":1,  "name":"kévin", "rank": 1e2}"#;
+        let second = r#"{ "id": 2, "tags": [ "a",  "b" ] , "price": -0.50 }"#;
+        let json = format!("[\n  {},\n  {}\n]", first, second);
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        let mut documents = crate::documents::DocumentBatchBuilder::new(&mut cursor).unwrap();
+        documents.extend_from_json(json.as_bytes()).unwrap();
+        documents.finish().unwrap();
+        cursor.set_position(0);
+        let content = crate::documents::DocumentBatchReader::from_reader(cursor).unwrap();
+
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = Search::new(&rtxn, &index);
+        search.with_json_documents(true);
+        let SearchResult { documents_ids, json_documents, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1]);
+        assert_eq!(json_documents[&0], first.as_bytes());
+        assert_eq!(json_documents[&1], second.as_bytes());
+        drop(rtxn);
+
+        // a document merged with its new version has no bytes of its own, its JSON is
+        // converted from the obkv store.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 2, "price": 3 }]);
+        let indexing_config = IndexDocumentsConfig {
+            update_method: IndexDocumentsMethod::UpdateDocuments,
+            ..Default::default()
+        };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.document_json(&rtxn, 0).unwrap().unwrap(), first.as_bytes());
+        assert_eq!(
+            index.document_json(&rtxn, 1).unwrap().unwrap(),
+            br#"{"price":3,"id":2,"tags":["a","b"]}"#
+        );
+    }
 }
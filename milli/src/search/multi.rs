@@ -0,0 +1,176 @@
+use std::cmp::Ordering;
+use std::mem;
+use std::vec::IntoIter;
+
+use heed::RoTxn;
+
+use crate::{DocumentId, Index, Result, Search};
+
+/// A document returned by [`multi_search`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultiSearchHit {
+    /// The position of the index of the document in the searched indexes.
+    pub index: usize,
+    pub docid: DocumentId,
+}
+
+/// Runs the same search on several indexes, e.g. the shards of a bigger collection of
+/// documents, and merges their ranked results into a single page of `limit` documents
+/// starting at `offset`.
+///
+/// Every index is searched with the read transaction given along with it, the documents of the
+/// hits must be fetched with the same transactions to be the ones that were ranked. The indexes
+/// don't share an environment and their transactions are independent: it is the caller's
+/// responsibility to open them on a consistent snapshot of the collection, e.g. all of them
+/// before any write is committed on the indexes, otherwise the page can mix documents from
+/// before and after an update.
+///
+/// `configure` sets up the search of each index, its offset and limit are ignored. The
+/// results of the indexes are merged by comparing the keys returned by `sort_key` with
+/// `compare`, they are not sorted again and the results of each index must already be in this
+/// order, e.g. by sorting the searches on the same field. The merge is stable: the documents
+/// with equal keys are returned in the order of their indexes then of their rank.
+pub fn multi_search<K>(
+    indexes: &[(&Index, &RoTxn)],
+    offset: usize,
+    limit: usize,
+    mut configure: impl FnMut(&mut Search),
+    mut sort_key: impl FnMut(&Index, &RoTxn, DocumentId) -> Result<K>,
+    mut compare: impl FnMut(&K, &K) -> Ordering,
+) -> Result<Vec<MultiSearchHit>> {
+    // a document of the requested page is at most at `offset + limit` in its index.
+    let end = offset.saturating_add(limit);
+    let mut results = Vec::with_capacity(indexes.len());
+    for &(index, rtxn) in indexes {
+        let mut search = Search::new(rtxn, index);
+        configure(&mut search);
+        search.offset(0).limit(end);
+        results.push(search.execute()?.documents_ids.into_iter());
+    }
+
+    // the key of the best remaining document of every index.
+    let mut next_head = |position: usize, docids: &mut IntoIter<DocumentId>| {
+        let (index, rtxn) = indexes[position];
+        match docids.next() {
+            Some(docid) => sort_key(index, rtxn, docid).map(|key| Some((key, docid))),
+            None => Ok(None),
+        }
+    };
+    let mut heads = Vec::with_capacity(indexes.len());
+    for (position, docids) in results.iter_mut().enumerate() {
+        heads.push(next_head(position, docids)?);
+    }
+
+    let mut hits = Vec::new();
+    for rank in 0..end {
+        // on equal keys the first index wins, which keeps the merge stable.
+        let mut best: Option<usize> = None;
+        for (position, head) in heads.iter().enumerate() {
+            if let Some((key, _)) = head {
+                let better = match best.and_then(|best| heads[best].as_ref()) {
+                    Some((best_key, _)) => compare(key, best_key) == Ordering::Less,
+                    None => true,
+                };
+                if better {
+                    best = Some(position);
+                }
+            }
+        }
+
+        let position = match best {
+            Some(position) => position,
+            None => break,
+        };
+        let next = next_head(position, &mut results[position])?;
+        let (_, docid) = mem::replace(&mut heads[position], next).unwrap();
+        if rank >= offset {
+            hits.push(MultiSearchHit { index: position, docid });
+        }
+    }
+
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use maplit::hashset;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::documents::DocumentBatchReader;
+    use crate::index::tests::TempIndex;
+    use crate::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
+    use crate::{AscDesc, Member};
+
+    fn shard(content: DocumentBatchReader<std::io::Cursor<Vec<u8>>>) -> TempIndex {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_sortable_fields(hashset! { S("price") });
+        builder.execute(|_| ()).unwrap();
+
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+        index
+    }
+
+    fn field(index: &Index, rtxn: &RoTxn, docid: DocumentId, name: &str) -> Result<Value> {
+        let fid = index.fields_ids_map(rtxn)?.id(name).unwrap();
+        let (_, document) = index.documents(rtxn, Some(docid))?.pop().unwrap();
+        Ok(serde_json::from_slice(document.get(fid).unwrap()).unwrap())
+    }
+
+    fn search_ids(indexes: &[&Index], offset: usize, limit: usize) -> Vec<String> {
+        let rtxns: Vec<_> = indexes.iter().map(|index| index.read_txn().unwrap()).collect();
+        let indexes: Vec<_> = indexes.iter().copied().zip(&rtxns).collect();
+        let hits = multi_search(
+            &indexes,
+            offset,
+            limit,
+            |search| {
+                search.sort_criteria(vec![AscDesc::Asc(Member::Field(S("price")))]);
+            },
+            |index, rtxn, docid| Ok(field(index, rtxn, docid, "price")?.as_u64().unwrap()),
+            |a, b| a.cmp(b),
+        )
+        .unwrap();
+
+        hits.into_iter()
+            .map(|MultiSearchHit { index, docid }| {
+                let (index, rtxn) = indexes[index];
+                field(index, rtxn, docid, "id").unwrap().as_str().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interleaves_the_shards() {
+        let first = shard(documents!([
+            { "id": "a1", "price": 1 },
+            { "id": "a4", "price": 40 },
+            { "id": "a2", "price": 20 },
+            { "id": "a5", "price": 50 },
+        ]));
+        let second = shard(documents!([
+            { "id": "b3", "price": 30 },
+            { "id": "b2", "price": 20 },
+            { "id": "b6", "price": 60 },
+        ]));
+        let indexes = [&*first, &*second];
+
+        // the equal prices are returned in the order of the indexes.
+        let all = search_ids(&indexes, 0, 20);
+        assert_eq!(all, ["a1", "a2", "b2", "b3", "a4", "a5", "b6"]);
+
+        assert_eq!(search_ids(&indexes, 0, 3), ["a1", "a2", "b2"]);
+        assert_eq!(search_ids(&indexes, 2, 3), ["b2", "b3", "a4"]);
+        assert_eq!(search_ids(&indexes, 5, 10), ["a5", "b6"]);
+        assert!(search_ids(&indexes, 7, 10).is_empty());
+    }
+}
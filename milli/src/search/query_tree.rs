@@ -1,14 +1,18 @@
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::{cmp, fmt, mem};
 
-use charabia::classifier::ClassifiedTokenIter;
-use charabia::{SeparatorKind, TokenKind};
+use charabia::{SeparatorKind, Token, TokenKind};
 use fst::Set;
+use itertools::Itertools;
 use roaring::RoaringBitmap;
+use serde::Serialize;
 use slice_group_by::GroupBy;
 
 use crate::search::matches::matching_words::{MatchingWord, PrimitiveWordId};
-use crate::{Index, MatchingWords, Result};
+use crate::search::TermsMatchingStrategy;
+use crate::{Index, MatchingWords, Result, TypoBudget, UserError};
 
 type IsOptionalWord = bool;
 type IsPrefix = bool;
@@ -49,6 +53,61 @@ impl fmt::Debug for Operation {
     }
 }
 
+/// A serializable representation of a query tree, returned by
+/// [`Search::build_query_tree_debug`](crate::Search::build_query_tree_debug).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum QueryTreeNode {
+    /// All the children must match.
+    And { children: Vec<QueryTreeNode> },
+    /// Any of the children can match, `word_branch` is set when the branches are the
+    /// alternatives of the query made by removing its last words.
+    #[serde(rename_all = "camelCase")]
+    Or { word_branch: bool, children: Vec<QueryTreeNode> },
+    /// The words must be found consecutively.
+    Phrase { words: Vec<String> },
+    /// A word matched with at most `typos` typos.
+    Tolerant { word: String, typos: u8, prefix: bool },
+    /// A word matched exactly, derived from a query word with `original_typos` typos.
+    #[serde(rename_all = "camelCase")]
+    Exact { word: String, original_typos: u8, prefix: bool },
+    /// The query tree of the searched words, if any, and the words of the query that restrict
+    /// its candidates: the negative words, the scoped words as `(attribute, word)` and the
+    /// stop words of a query only made of stop words, the last one can be a prefix.
+    #[serde(rename_all = "camelCase")]
+    Restricted {
+        tree: Option<Box<QueryTreeNode>>,
+        negative_words: Vec<String>,
+        scoped_words: Vec<(String, String)>,
+        stop_words: Vec<String>,
+        last_stop_word_prefix: bool,
+    },
+}
+
+impl From<&Operation> for QueryTreeNode {
+    fn from(operation: &Operation) -> QueryTreeNode {
+        let children = |ops: &[Operation]| ops.iter().map(QueryTreeNode::from).collect();
+        match operation {
+            Operation::And(ops) => QueryTreeNode::And { children: children(ops) },
+            Operation::Or(word_branch, ops) => {
+                QueryTreeNode::Or { word_branch: *word_branch, children: children(ops) }
+            }
+            Operation::Phrase(words) => QueryTreeNode::Phrase { words: words.clone() },
+            Operation::Query(Query { prefix, kind: QueryKind::Tolerant { typo, word } }) => {
+                QueryTreeNode::Tolerant { word: word.clone(), typos: *typo, prefix: *prefix }
+            }
+            Operation::Query(Query {
+                prefix,
+                kind: QueryKind::Exact { original_typo, word, .. },
+            }) => QueryTreeNode::Exact {
+                word: word.clone(),
+                original_typos: *original_typo,
+                prefix: *prefix,
+            },
+        }
+    }
+}
+
 impl Operation {
     fn and(mut ops: Vec<Self>) -> Self {
         if ops.len() == 1 {
@@ -90,18 +149,28 @@ pub struct Query {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum QueryKind {
-    Tolerant { typo: u8, word: String },
-    Exact { original_typo: u8, word: String },
+    Tolerant {
+        typo: u8,
+        word: String,
+    },
+    /// An exact word, that may have been derived from a query word of
+    /// `original_len` chars with `original_typo` typos.
+    Exact {
+        original_typo: u8,
+        original_len: u8,
+        word: String,
+    },
 }
 
 impl QueryKind {
     pub fn exact(word: String) -> Self {
-        QueryKind::Exact { original_typo: 0, word }
+        let original_len = word.chars().count().min(u8::MAX as usize) as u8;
+        QueryKind::Exact { original_typo: 0, original_len, word }
     }
 
-    #[cfg(test)]
-    pub fn exact_with_typo(original_typo: u8, word: String) -> Self {
-        QueryKind::Exact { original_typo, word }
+    pub fn exact_with_typo(original_typo: u8, original: &str, word: String) -> Self {
+        let original_len = original.chars().count().min(u8::MAX as usize) as u8;
+        QueryKind::Exact { original_typo, original_len, word }
     }
 
     pub fn tolerant(typo: u8, word: String) -> Self {
@@ -151,17 +220,23 @@ trait Context {
     }
     /// Returns the minimum word len for 1 and 2 typos.
     fn min_word_len_for_typo(&self) -> heed::Result<(u8, u8)>;
+    /// Returns how the number of typos allowed on a word is computed.
+    fn typo_budget(&self) -> heed::Result<TypoBudget>;
     fn exact_words(&self) -> Option<&fst::Set<Cow<[u8]>>>;
+    /// Returns `true` if a query word can match a word with a different first char.
+    fn allow_typo_on_first_char(&self) -> heed::Result<bool>;
 }
 
 /// The query tree builder is the interface to build a query tree.
 pub struct QueryTreeBuilder<'a> {
     rtxn: &'a heed::RoTxn<'a>,
     index: &'a Index,
-    optional_words: bool,
+    terms_matching_strategy: TermsMatchingStrategy,
     authorize_typos: bool,
+    prefix_search: bool,
     words_limit: Option<usize>,
     exact_words: Option<fst::Set<Cow<'a, [u8]>>>,
+    scoped_words: Vec<(String, Range<usize>)>,
 }
 
 impl<'a> Context for QueryTreeBuilder<'a> {
@@ -178,14 +253,30 @@ impl<'a> Context for QueryTreeBuilder<'a> {
     }
 
     fn min_word_len_for_typo(&self) -> heed::Result<(u8, u8)> {
-        let one = self.index.min_word_len_one_typo(&self.rtxn)?;
-        let two = self.index.min_word_len_two_typos(&self.rtxn)?;
+        let mut one = self.index.min_word_len_one_typo(&self.rtxn)?;
+        let mut two = self.index.min_word_len_two_typos(&self.rtxn)?;
+        // The query tree must allow the typos tolerated by the most lenient attribute,
+        // the derivations are then restricted to the attributes that tolerate them
+        // when the query tree is resolved.
+        let tolerances = self.index.attributes_typo_tolerance(&self.rtxn)?;
+        for tolerance in tolerances.values().filter(|t| t.enabled) {
+            one = one.min(tolerance.min_word_len_one_typo);
+            two = two.min(tolerance.min_word_len_two_typos);
+        }
         Ok((one, two))
     }
 
+    fn typo_budget(&self) -> heed::Result<TypoBudget> {
+        self.index.typo_budget(self.rtxn)
+    }
+
     fn exact_words(&self) -> Option<&fst::Set<Cow<[u8]>>> {
         self.exact_words.as_ref()
     }
+
+    fn allow_typo_on_first_char(&self) -> heed::Result<bool> {
+        self.index.allow_typo_on_first_char(self.rtxn)
+    }
 }
 
 impl<'a> QueryTreeBuilder<'a> {
@@ -195,10 +286,12 @@ impl<'a> QueryTreeBuilder<'a> {
         Ok(Self {
             rtxn,
             index,
-            optional_words: true,
+            terms_matching_strategy: TermsMatchingStrategy::default(),
             authorize_typos: true,
+            prefix_search: true,
             words_limit: None,
             exact_words: index.exact_words(rtxn)?,
+            scoped_words: Vec::new(),
         })
     }
 
@@ -207,7 +300,16 @@ impl<'a> QueryTreeBuilder<'a> {
     /// (the criterion `words` will be ignored).
     /// default value if not called: `true`
     pub fn optional_words(&mut self, optional_words: bool) -> &mut Self {
-        self.optional_words = optional_words;
+        self.terms_matching_strategy =
+            if optional_words { TermsMatchingStrategy::Last } else { TermsMatchingStrategy::All };
+        self
+    }
+
+    /// Defines which words are removed first from the query tree when the
+    /// `words` criterion needs more documents.
+    /// default value if not called: `TermsMatchingStrategy::Last`
+    pub fn terms_matching_strategy(&mut self, strategy: TermsMatchingStrategy) -> &mut Self {
+        self.terms_matching_strategy = strategy;
         self
     }
 
@@ -220,6 +322,14 @@ impl<'a> QueryTreeBuilder<'a> {
         self
     }
 
+    /// if `prefix_search` is set to `false` the last query word is not considered
+    /// as a prefix and only matches the words that are equal to it, typos aside.
+    /// default value if not called: `true`
+    pub fn prefix_search(&mut self, prefix_search: bool) -> &mut Self {
+        self.prefix_search = prefix_search;
+        self
+    }
+
     /// Limit words and phrases that will be taken for query building.
     /// Any beyond `words_limit` will be ignored.
     pub fn words_limit(&mut self, words_limit: usize) -> &mut Self {
@@ -227,31 +337,60 @@ impl<'a> QueryTreeBuilder<'a> {
         self
     }
 
+    /// The attributes of the scoped words of the query, with the byte ranges of their words
+    /// in the tokenized text, as returned by [`split_scoped_words`].
+    pub fn scoped_words(&mut self, scoped_words: Vec<(String, Range<usize>)>) -> &mut Self {
+        self.scoped_words = scoped_words;
+        self
+    }
+
     /// Build the query tree:
-    /// - if `optional_words` is set to `false` the query tree will be
+    /// - if the `terms_matching_strategy` is `All` the query tree will be
     ///   generated forcing all query words to be present in each matching documents
     ///   (the criterion `words` will be ignored)
     /// - if `authorize_typos` is set to `false` the query tree will be generated
     ///   forcing all query words to match documents without any typo
     ///   (the criterion `typo` will be ignored)
-    pub fn build<A: AsRef<[u8]>>(
+    ///
+    /// The negative words of the query, the ones prefixed by a `-`, are not part of the
+    /// query tree and are returned aside, the documents containing them must be excluded.
+    /// The scoped words, written `attribute:word`, are also returned aside, the documents
+    /// must contain them in their attribute.
+    pub fn build<'t>(
         &self,
-        query: ClassifiedTokenIter<A>,
-    ) -> Result<Option<(Operation, PrimitiveQuery, MatchingWords)>> {
+        query: impl Iterator<Item = Token<'t>>,
+    ) -> Result<(Option<(Operation, PrimitiveQuery, MatchingWords)>, QueryRestrictions)> {
         let stop_words = self.index.stop_words(self.rtxn)?;
-        let primitive_query = create_primitive_query(query, stop_words, self.words_limit);
+        let (mut primitive_query, restrictions) =
+            create_primitive_query(query, &self.scoped_words, stop_words, self.words_limit);
+        if !self.prefix_search {
+            for part in primitive_query.iter_mut() {
+                if let PrimitiveQueryPart::Word(_, prefix) = part {
+                    *prefix = false;
+                }
+            }
+        }
+        // the words too short to be indexed as prefixes only match themselves.
+        let min_word_size_for_prefix = self.index.min_word_size_for_prefix(self.rtxn)? as usize;
+        for part in primitive_query.iter_mut() {
+            if let PrimitiveQueryPart::Word(word, prefix) = part {
+                if word.chars().count() < min_word_size_for_prefix {
+                    *prefix = false;
+                }
+            }
+        }
         if !primitive_query.is_empty() {
             let qt = create_query_tree(
                 self,
-                self.optional_words,
+                self.terms_matching_strategy,
                 self.authorize_typos,
                 &primitive_query,
             )?;
             let matching_words =
                 create_matching_words(self, self.authorize_typos, &primitive_query)?;
-            Ok(Some((qt, primitive_query, matching_words)))
+            Ok((Some((qt, primitive_query, matching_words)), restrictions))
         } else {
-            Ok(None)
+            Ok((None, restrictions))
         }
     }
 }
@@ -284,6 +423,7 @@ pub struct TypoConfig<'a> {
     pub max_typos: u8,
     pub word_len_one_typo: u8,
     pub word_len_two_typo: u8,
+    pub budget: TypoBudget,
     pub exact_words: Option<&'a fst::Set<Cow<'a, [u8]>>>,
 }
 
@@ -292,43 +432,66 @@ pub struct TypoConfig<'a> {
 fn typos<'a>(word: String, authorize_typos: bool, config: TypoConfig<'a>) -> QueryKind {
     if authorize_typos && !config.exact_words.map_or(false, |s| s.contains(&word)) {
         let count = word.chars().count().min(u8::MAX as usize) as u8;
-        if count < config.word_len_one_typo {
-            QueryKind::exact(word)
-        } else if count < config.word_len_two_typo {
-            QueryKind::tolerant(1.min(config.max_typos), word)
-        } else {
-            QueryKind::tolerant(2.min(config.max_typos), word)
+        let thresholds = (config.word_len_one_typo, config.word_len_two_typo);
+        match config.budget.max_typos(count, thresholds) {
+            0 => QueryKind::exact(word),
+            typos => QueryKind::tolerant(typos.min(config.max_typos), word),
         }
     } else {
         QueryKind::exact(word)
     }
 }
 
+/// Fetch the synonyms of the provided words and the synonyms of these synonyms,
+/// up to `MAX_SYNONYMS_DEPTH` levels, each distinct expansion being returned once.
+///
+/// The original words are never returned, which makes cyclic definitions terminate early.
+fn expand_synonyms(ctx: &impl Context, words: &[&str]) -> heed::Result<Option<Vec<Vec<String>>>> {
+    const MAX_SYNONYMS_DEPTH: usize = 3;
+
+    let mut synonyms = match ctx.synonyms(words)? {
+        Some(synonyms) => synonyms,
+        None => return Ok(None),
+    };
+
+    let mut seen = HashSet::new();
+    seen.insert(words.iter().map(|word| word.to_string()).collect::<Vec<_>>());
+    synonyms.retain(|synonym| seen.insert(synonym.clone()));
+
+    let mut current = synonyms.clone();
+    for _ in 1..MAX_SYNONYMS_DEPTH {
+        let mut next = Vec::new();
+        for synonym in &current {
+            for synonym in ctx.synonyms(synonym)?.unwrap_or_default() {
+                if seen.insert(synonym.clone()) {
+                    next.push(synonym);
+                }
+            }
+        }
+
+        if next.is_empty() {
+            break;
+        }
+        synonyms.extend(next.iter().cloned());
+        current = next;
+    }
+
+    Ok(Some(synonyms))
+}
+
 /// Fetch synonyms from the `Context` for the provided word
-/// and create the list of operations for the query tree
+/// and create the list of operations for the query tree,
+/// the multi-word synonyms must be found as a phrase.
 fn synonyms(ctx: &impl Context, word: &[&str]) -> heed::Result<Option<Vec<Operation>>> {
-    let synonyms = ctx.synonyms(word)?;
-
-    Ok(synonyms.map(|synonyms| {
-        synonyms
-            .into_iter()
-            .map(|synonym| {
-                let words = synonym
-                    .into_iter()
-                    .map(|word| {
-                        Operation::Query(Query { prefix: false, kind: QueryKind::exact(word) })
-                    })
-                    .collect();
-                Operation::and(words)
-            })
-            .collect()
-    }))
+    let synonyms = expand_synonyms(ctx, word)?;
+
+    Ok(synonyms.map(|synonyms| synonyms.into_iter().map(Operation::phrase).collect()))
 }
 
 /// Main function that creates the final query tree from the primitive query.
 fn create_query_tree(
     ctx: &impl Context,
-    optional_words: bool,
+    terms_matching_strategy: TermsMatchingStrategy,
     authorize_typos: bool,
     query: &[PrimitiveQueryPart],
 ) -> Result<Operation> {
@@ -350,8 +513,13 @@ fn create_query_tree(
                 }
                 let (word_len_one_typo, word_len_two_typo) = ctx.min_word_len_for_typo()?;
                 let exact_words = ctx.exact_words();
-                let config =
-                    TypoConfig { max_typos: 2, word_len_one_typo, word_len_two_typo, exact_words };
+                let config = TypoConfig {
+                    max_typos: 2,
+                    word_len_one_typo,
+                    word_len_two_typo,
+                    budget: ctx.typo_budget()?,
+                    exact_words,
+                };
                 children.push(Operation::Query(Query {
                     prefix,
                     kind: typos(word, authorize_typos, config),
@@ -408,6 +576,7 @@ fn create_query_tree(
                                 max_typos: 1,
                                 word_len_one_typo,
                                 word_len_two_typo,
+                                budget: ctx.typo_budget()?,
                                 exact_words,
                             };
                             let query = Query {
@@ -432,31 +601,46 @@ fn create_query_tree(
         Ok(Operation::and(op_children))
     }
 
-    /// Create a new branch removing the last non-phrase query parts.
+    /// Create a new branch for every non-phrase query part removed, the parts are
+    /// removed in the order defined by the strategy until `min_parts` are left.
     fn optional_word(
         ctx: &impl Context,
         authorize_typos: bool,
         query: PrimitiveQuery,
+        strategy: TermsMatchingStrategy,
+        min_parts: usize,
     ) -> Result<Operation> {
-        let number_phrases = query.iter().filter(|p| p.is_phrase()).count();
+        // The positions of the words in the order they must be removed.
+        let mut removal_order: Vec<_> = query
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(i, part)| match part {
+                PrimitiveQueryPart::Word(word, _) => Some((i, word.as_str())),
+                PrimitiveQueryPart::Phrase(_) => None,
+            })
+            .collect();
+
+        if strategy == TermsMatchingStrategy::Frequency {
+            let mut counts = HashMap::new();
+            for &(_, word) in &removal_order {
+                counts.insert(word, ctx.word_documents_count(word)?.unwrap_or(0));
+            }
+            // the sort is stable, the last words are removed first on equal counts.
+            removal_order.sort_by_key(|(_, word)| cmp::Reverse(counts[word]));
+        }
+
+        let number_phrases = query.len() - removal_order.len();
         let mut operation_children = Vec::new();
 
-        let start = number_phrases + (number_phrases == 0) as usize;
+        let start = (number_phrases + (number_phrases == 0) as usize).max(min_parts);
         for len in start..=query.len() {
-            let mut word_count = len - number_phrases;
+            let removed = &removal_order[..query.len() - len];
             let query: Vec<_> = query
                 .iter()
-                .filter(|p| {
-                    if p.is_phrase() {
-                        true
-                    } else if word_count != 0 {
-                        word_count -= 1;
-                        true
-                    } else {
-                        false
-                    }
-                })
-                .cloned()
+                .enumerate()
+                .filter(|(i, _)| removed.iter().all(|(pos, _)| pos != i))
+                .map(|(_, part)| part.clone())
                 .collect();
 
             let ngrams = ngrams(ctx, authorize_typos, &query)?;
@@ -466,13 +650,80 @@ fn create_query_tree(
         Ok(Operation::or(true, operation_children))
     }
 
-    if optional_words {
-        optional_word(ctx, authorize_typos, query.to_vec())
-    } else {
-        ngrams(ctx, authorize_typos, query)
+    /// Create a branch for every number of query parts from `min_parts` to all of them,
+    /// each branch is an alternative of all the combinations of this number of parts.
+    /// The queries with too many combinations of their words are refused.
+    fn at_least_words(
+        ctx: &impl Context,
+        authorize_typos: bool,
+        query: &[PrimitiveQueryPart],
+        min_parts: usize,
+    ) -> Result<Operation> {
+        // The phrases must always match, only the words can be left out.
+        let words_positions: Vec<_> = query
+            .iter()
+            .enumerate()
+            .filter(|(_, part)| !part.is_phrase())
+            .map(|(i, _)| i)
+            .collect();
+        let number_phrases = query.len() - words_positions.len();
+        let start = min_parts.max(number_phrases).max(1).min(query.len());
+
+        let combinations = (start..=query.len())
+            .map(|len| binomial(words_positions.len(), len - number_phrases))
+            .fold(0, usize::saturating_add);
+        if combinations > MAX_AT_LEAST_COMBINATIONS {
+            return Err(UserError::TooManyAtLeastCombinations {
+                min_words: start - number_phrases,
+                words: words_positions.len(),
+            }
+            .into());
+        }
+
+        let mut operation_children = Vec::new();
+        for len in start..=query.len() {
+            let mut alternatives = Vec::new();
+            for kept in words_positions.iter().combinations(len - number_phrases) {
+                let query: Vec<_> = query
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, part)| part.is_phrase() || kept.contains(&i))
+                    .map(|(_, part)| part.clone())
+                    .collect();
+                alternatives.push(ngrams(ctx, authorize_typos, &query)?);
+            }
+            operation_children.push(Operation::or(false, alternatives));
+        }
+
+        Ok(Operation::or(true, operation_children))
+    }
+
+    match terms_matching_strategy {
+        TermsMatchingStrategy::All => ngrams(ctx, authorize_typos, query),
+        TermsMatchingStrategy::AtLeast(min_parts) => {
+            at_least_words(ctx, authorize_typos, query, min_parts)
+        }
+        strategy => optional_word(ctx, authorize_typos, query.to_vec(), strategy, 0),
     }
 }
 
+/// The maximum number of combinations of the query words the `AtLeast` strategy
+/// creates a branch for, each one of them is a whole tree of ngrams.
+const MAX_AT_LEAST_COMBINATIONS: usize = 100;
+
+/// The number of combinations of `k` elements among `n`, saturated on overflow.
+fn binomial(n: usize, k: usize) -> usize {
+    let k = k.min(n - k);
+    let mut result: usize = 1;
+    for i in 0..k {
+        result = match result.checked_mul(n - i) {
+            Some(product) => product / (i + 1),
+            None => return usize::MAX,
+        };
+    }
+    result
+}
+
 /// Main function that matchings words used for crop and highlight.
 fn create_matching_words(
     ctx: &impl Context,
@@ -491,7 +742,7 @@ fn create_matching_words(
             // 1. try to split word in 2
             // 2. try to fetch synonyms
             PrimitiveQueryPart::Word(word, prefix) => {
-                if let Some(synonyms) = ctx.synonyms(&[word.as_str()])? {
+                if let Some(synonyms) = expand_synonyms(ctx, &[word.as_str()])? {
                     for synonym in synonyms {
                         let synonym = synonym
                             .into_iter()
@@ -509,12 +760,20 @@ fn create_matching_words(
 
                 let (word_len_one_typo, word_len_two_typo) = ctx.min_word_len_for_typo()?;
                 let exact_words = ctx.exact_words();
-                let config =
-                    TypoConfig { max_typos: 2, word_len_one_typo, word_len_two_typo, exact_words };
+                let config = TypoConfig {
+                    max_typos: 2,
+                    word_len_one_typo,
+                    word_len_two_typo,
+                    budget: ctx.typo_budget()?,
+                    exact_words,
+                };
 
                 let matching_word = match typos(word, authorize_typos, config) {
                     QueryKind::Exact { word, .. } => MatchingWord::new(word, 0, prefix),
-                    QueryKind::Tolerant { typo, word } => MatchingWord::new(word, typo, prefix),
+                    QueryKind::Tolerant { typo, word } => {
+                        let first_char_typo = ctx.allow_typo_on_first_char()?;
+                        MatchingWord::with_first_char_typo(word, typo, prefix, first_char_typo)
+                    }
                 };
                 matching_words.push((vec![matching_word], vec![id]));
             }
@@ -574,7 +833,7 @@ fn create_matching_words(
                                 .map(|i| id + i as PrimitiveWordId)
                                 .collect();
 
-                            if let Some(synonyms) = ctx.synonyms(&words)? {
+                            if let Some(synonyms) = expand_synonyms(ctx, &words)? {
                                 for synonym in synonyms {
                                     let synonym = synonym
                                         .into_iter()
@@ -591,6 +850,7 @@ fn create_matching_words(
                                 max_typos: 1,
                                 word_len_one_typo,
                                 word_len_two_typo,
+                                budget: ctx.typo_budget()?,
                                 exact_words,
                             };
                             let matching_word = match typos(word, authorize_typos, config) {
@@ -598,7 +858,12 @@ fn create_matching_words(
                                     MatchingWord::new(word, 0, is_prefix)
                                 }
                                 QueryKind::Tolerant { typo, word } => {
-                                    MatchingWord::new(word, typo, is_prefix)
+                                    MatchingWord::with_first_char_typo(
+                                        word,
+                                        typo,
+                                        is_prefix,
+                                        ctx.allow_typo_on_first_char()?,
+                                    )
                                 }
                             };
                             matching_words.push((vec![matching_word], ids));
@@ -623,7 +888,7 @@ fn create_matching_words(
 
 pub type PrimitiveQuery = Vec<PrimitiveQueryPart>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PrimitiveQueryPart {
     Phrase(Vec<String>),
     Word(String, IsPrefix),
@@ -646,47 +911,102 @@ impl PrimitiveQueryPart {
     }
 }
 
+/// The words of the query that restrict the candidates instead of being ranked.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct QueryRestrictions {
+    /// The words, prefixed by a `-`, that the documents must not contain.
+    pub negative_words: Vec<String>,
+    /// The attributes and the words, written `attribute:word`, that must be found in them.
+    pub scoped_words: Vec<(String, String)>,
+    /// The stop words of a query only made of stop words, that the documents must all contain.
+    pub stop_words: Vec<String>,
+    /// Whether the last of the `stop_words` is a prefix, it then matches the stop word
+    /// or any longer word it starts.
+    pub last_stop_word_prefix: bool,
+}
+
 /// Create primitive query from tokenized query string,
 /// the primitive query is an intermediate state to build the query tree.
-fn create_primitive_query<A>(
-    query: ClassifiedTokenIter<A>,
+///
+/// The words directly preceded by a `-`, at the beginning of the query or after a
+/// whitespace, are negative words: they are returned aside, without being part of
+/// the primitive query. A word both negated and searched is not considered negative.
+///
+/// The words in the byte ranges of the `scoped_words` are scoped to their attribute, they are
+/// still part of the primitive query, but never as a prefix. A scoped stop word is not part of
+/// the primitive query but keeps its attribute.
+///
+/// When the query is only made of stop words, the primitive query is empty and the
+/// stop words are returned aside, the documents must contain them literally. The last one
+/// can also match the longer words it starts when it is a prefix.
+fn create_primitive_query<'t>(
+    query: impl Iterator<Item = Token<'t>>,
+    scoped_words_ranges: &[(String, Range<usize>)],
     stop_words: Option<Set<&[u8]>>,
     words_limit: Option<usize>,
-) -> PrimitiveQuery
-where
-    A: AsRef<[u8]>,
-{
+) -> (PrimitiveQuery, QueryRestrictions) {
     let mut primitive_query = Vec::new();
+    let mut negative_words = Vec::new();
+    let mut scoped_words = Vec::new();
+    let mut query_stop_words = Vec::new();
     let mut phrase = Vec::new();
     let mut quoted = false;
+    let mut negative = false;
+    let mut after_whitespace = true;
 
     let parts_limit = words_limit.unwrap_or(usize::MAX);
 
     let mut peekable = query.peekable();
     while let Some(token) = peekable.next() {
-        // early return if word limit is exceeded
+        // stop if word limit is exceeded
         if primitive_query.len() >= parts_limit {
-            return primitive_query;
+            break;
         }
 
         match token.kind {
             TokenKind::Word | TokenKind::StopWord => {
-                // 1. if the word is quoted we push it in a phrase-buffer waiting for the ending quote,
-                // 2. if the word is not the last token of the query and is not a stop_word we push it as a non-prefix word,
-                // 3. if the word is the last token of the query we push it as a prefix word.
-                if quoted {
+                // 1. if the word is negated we push it in the negative words, unless it is a stop_word,
+                // 2. if the word is quoted we push it in a phrase-buffer waiting for the ending quote,
+                // 3. if the word is not the last token of the query and is not a stop_word we push it as a non-prefix word,
+                // 4. if the word is the last token of the query we push it as a prefix word.
+                let scope = scoped_words_ranges
+                    .iter()
+                    .find(|(_, range)| range.contains(&token.byte_start))
+                    .map(|(attribute, _)| attribute.clone());
+                let is_stop_word =
+                    stop_words.as_ref().map_or(false, |swords| swords.contains(token.lemma()));
+                if is_stop_word && !negative && !quoted {
+                    query_stop_words.push(token.lemma().to_string());
+                }
+                let mut pushed = false;
+                if negative {
+                    if matches!(token.kind, TokenKind::Word) {
+                        negative_words.push(token.lemma().to_string());
+                    }
+                } else if quoted {
                     phrase.push(token.lemma().to_string());
                 } else if peekable.peek().is_some() {
-                    if !stop_words.as_ref().map_or(false, |swords| swords.contains(token.lemma())) {
+                    if !is_stop_word {
                         primitive_query
                             .push(PrimitiveQueryPart::Word(token.lemma().to_string(), false));
+                        pushed = true;
                     }
                 } else {
-                    primitive_query.push(PrimitiveQueryPart::Word(token.lemma().to_string(), true));
+                    let prefix = scope.is_none();
+                    primitive_query
+                        .push(PrimitiveQueryPart::Word(token.lemma().to_string(), prefix));
+                    pushed = true;
                 }
+                // the stop words are not pushed but must still be found in the attribute.
+                if let Some(scope) = scope.filter(|_| pushed || (is_stop_word && !negative)) {
+                    scoped_words.push((scope, token.lemma().to_string()));
+                }
+                negative = false;
+                after_whitespace = false;
             }
             TokenKind::Separator(separator_kind) => {
-                let quote_count = token.lemma().chars().filter(|&s| s == '"').count();
+                let separator = token.lemma();
+                let quote_count = separator.chars().filter(|&s| s == '"').count();
                 // swap quoted state if we encounter a double quote
                 if quote_count % 2 != 0 {
                     quoted = !quoted;
@@ -696,6 +1016,12 @@ where
                 {
                     primitive_query.push(PrimitiveQueryPart::Phrase(mem::take(&mut phrase)));
                 }
+                // a `-` negates the next word, but not when it joins two words (e.g. `t-shirt`).
+                let before_dashes = separator.trim_end_matches('-');
+                negative = !quoted
+                    && before_dashes.len() < separator.len()
+                    && (after_whitespace || before_dashes.ends_with(char::is_whitespace));
+                after_whitespace = separator.ends_with(char::is_whitespace);
             }
             _ => (),
         }
@@ -706,7 +1032,81 @@ where
         primitive_query.push(PrimitiveQueryPart::Phrase(mem::take(&mut phrase)));
     }
 
-    primitive_query
+    // a word that is also searched is not excluded.
+    negative_words.retain(|negative| {
+        !primitive_query.iter().any(|part| match part {
+            PrimitiveQueryPart::Word(word, _) => word == negative,
+            PrimitiveQueryPart::Phrase(words) => words.contains(negative),
+        })
+    });
+
+    // a query only made of stop words is not a placeholder search, the stop words
+    // are searched literally, a last word that is a prefix also matches the longer
+    // words it starts.
+    let only_stop_words = primitive_query.iter().all(|part| match part {
+        PrimitiveQueryPart::Word(word, _) => query_stop_words.contains(word),
+        PrimitiveQueryPart::Phrase(_) => false,
+    });
+    let mut last_stop_word_prefix = false;
+    if only_stop_words {
+        last_stop_word_prefix =
+            matches!(primitive_query.last(), Some(PrimitiveQueryPart::Word(_, true)));
+        primitive_query.clear();
+    } else {
+        query_stop_words.clear();
+    }
+
+    let restrictions = QueryRestrictions {
+        negative_words,
+        scoped_words,
+        stop_words: query_stop_words,
+        last_stop_word_prefix,
+    };
+    (primitive_query, restrictions)
+}
+
+/// Splits the scoped words, written `attribute:word`, out of the raw query before it is
+/// tokenized, the attributes can then contain any character: underscores, dots or accents.
+///
+/// The attributes and their `:` are replaced by spaces of the same length, the byte offsets
+/// of the words don't change, and are returned with the byte ranges of their words. An
+/// attribute starts the query or follows a whitespace and is directly followed by its word,
+/// a quoted or negated attribute is searched as a word.
+pub fn split_scoped_words(query: &str) -> (Cow<str>, Vec<(String, Range<usize>)>) {
+    let mut scoped_words = Vec::new();
+    let mut quoted = false;
+    let mut offset = 0;
+    for chunk in query.split_whitespace() {
+        let start = offset + query[offset..].find(chunk).unwrap_or(0);
+        offset = start + chunk.len();
+
+        if !quoted {
+            if let Some((attribute, word)) = chunk.split_once(':') {
+                let is_attribute = !attribute.is_empty()
+                    && !attribute.starts_with('-')
+                    && !attribute.contains('"');
+                let is_word = !word.is_empty() && !word.starts_with(&[':', '"', '-'][..]);
+                if is_attribute && is_word {
+                    let word_start = start + attribute.len() + 1;
+                    scoped_words.push((attribute.to_string(), word_start..offset));
+                }
+            }
+        }
+        if chunk.matches('"').count() % 2 != 0 {
+            quoted = !quoted;
+        }
+    }
+
+    if scoped_words.is_empty() {
+        return (Cow::Borrowed(query), scoped_words);
+    }
+
+    let mut prepared = query.to_string();
+    for (attribute, range) in &scoped_words {
+        let attribute_start = range.start - attribute.len() - 1;
+        prepared.replace_range(attribute_start..range.start, &" ".repeat(attribute.len() + 1));
+    }
+    (Cow::Owned(prepared), scoped_words)
 }
 
 /// Returns the maximum number of typos that this Operation allows.
@@ -753,17 +1153,21 @@ mod test {
     }
 
     impl TestContext {
-        fn build<A: AsRef<[u8]>>(
+        fn build<'t>(
             &self,
-            optional_words: bool,
+            terms_matching_strategy: TermsMatchingStrategy,
             authorize_typos: bool,
             words_limit: Option<usize>,
-            query: ClassifiedTokenIter<A>,
+            query: impl Iterator<Item = Token<'t>>,
         ) -> Result<Option<(Operation, PrimitiveQuery)>> {
-            let primitive_query = create_primitive_query(query, None, words_limit);
+            let (primitive_query, _) = create_primitive_query(query, &[], None, words_limit);
             if !primitive_query.is_empty() {
-                let qt =
-                    create_query_tree(self, optional_words, authorize_typos, &primitive_query)?;
+                let qt = create_query_tree(
+                    self,
+                    terms_matching_strategy,
+                    authorize_typos,
+                    &primitive_query,
+                )?;
                 Ok(Some((qt, primitive_query)))
             } else {
                 Ok(None)
@@ -785,9 +1189,17 @@ mod test {
             Ok((DEFAULT_MIN_WORD_LEN_ONE_TYPO, DEFAULT_MIN_WORD_LEN_TWO_TYPOS))
         }
 
+        fn typo_budget(&self) -> heed::Result<TypoBudget> {
+            Ok(TypoBudget::default())
+        }
+
         fn exact_words(&self) -> Option<&fst::Set<Cow<[u8]>>> {
             self.exact_words.as_ref()
         }
+
+        fn allow_typo_on_first_char(&self) -> heed::Result<bool> {
+            Ok(true)
+        }
     }
 
     impl Default for TestContext {
@@ -877,8 +1289,10 @@ mod test {
             ],
         );
 
-        let (query_tree, _) =
-            TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::All, true, None, tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -908,8 +1322,10 @@ mod test {
             ],
         );
 
-        let (query_tree, _) =
-            TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::All, true, None, tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -930,16 +1346,7 @@ mod test {
                                 prefix: false,
                                 kind: QueryKind::exact("hi".to_string()),
                             }),
-                            Operation::And(vec![
-                                Operation::Query(Query {
-                                    prefix: false,
-                                    kind: QueryKind::exact("good".to_string()),
-                                }),
-                                Operation::Query(Query {
-                                    prefix: false,
-                                    kind: QueryKind::exact("morning".to_string()),
-                                }),
-                            ]),
+                            Operation::Phrase(vec!["good".to_string(), "morning".to_string()]),
                             Operation::Query(Query {
                                 prefix: false,
                                 kind: QueryKind::tolerant(1, "hello".to_string()),
@@ -971,8 +1378,10 @@ mod test {
             ],
         );
 
-        let (query_tree, _) =
-            TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::All, true, None, tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -1018,19 +1427,10 @@ mod test {
                                 prefix: false,
                                 kind: QueryKind::exact("nyc".to_string()),
                             }),
-                            Operation::And(vec![
-                                Operation::Query(Query {
-                                    prefix: false,
-                                    kind: QueryKind::exact("new".to_string()),
-                                }),
-                                Operation::Query(Query {
-                                    prefix: false,
-                                    kind: QueryKind::exact("york".to_string()),
-                                }),
-                                Operation::Query(Query {
-                                    prefix: false,
-                                    kind: QueryKind::exact("city".to_string()),
-                                }),
+                            Operation::Phrase(vec![
+                                "new".to_string(),
+                                "york".to_string(),
+                                "city".to_string(),
                             ]),
                             Operation::Query(Query {
                                 prefix: false,
@@ -1050,19 +1450,109 @@ mod test {
                             prefix: false,
                             kind: QueryKind::exact("nyc".to_string()),
                         }),
-                        Operation::And(vec![
-                            Operation::Query(Query {
-                                prefix: false,
-                                kind: QueryKind::exact("new".to_string()),
-                            }),
+                        Operation::Phrase(vec!["new".to_string(), "york".to_string()]),
+                        Operation::Query(Query {
+                            prefix: false,
+                            kind: QueryKind::tolerant(1, "newyorkcity".to_string()),
+                        }),
+                    ],
+                ),
+            ],
+        );
+
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::All, true, None, tokens)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(expected, query_tree);
+    }
+
+    #[test]
+    fn one_to_many_synonyms() {
+        let query = "ny restaurants ";
+        let tokens = query.tokenize();
+
+        let context = TestContext {
+            synonyms: hashmap! {
+                vec![String::from("ny")] => vec![vec![String::from("new"), String::from("york")]],
+            },
+            ..TestContext::default()
+        };
+
+        let expected = Operation::Or(
+            false,
+            vec![
+                Operation::And(vec![
+                    Operation::Or(
+                        false,
+                        vec![
+                            Operation::Phrase(vec!["new".to_string(), "york".to_string()]),
                             Operation::Query(Query {
                                 prefix: false,
-                                kind: QueryKind::exact("york".to_string()),
+                                kind: QueryKind::exact("ny".to_string()),
                             }),
-                        ]),
+                        ],
+                    ),
+                    Operation::Query(Query {
+                        prefix: false,
+                        kind: QueryKind::tolerant(2, "restaurants".to_string()),
+                    }),
+                ]),
+                Operation::Query(Query {
+                    prefix: false,
+                    kind: QueryKind::tolerant(1, "nyrestaurants".to_string()),
+                }),
+            ],
+        );
+
+        let (query_tree, _) =
+            context.build(TermsMatchingStrategy::All, true, None, tokens).unwrap().unwrap();
+
+        assert_eq!(expected, query_tree);
+    }
+
+    #[test]
+    fn many_to_one_synonyms() {
+        let query = "new york ";
+        let tokens = query.tokenize();
+
+        // the synonyms of the synonyms are also fetched.
+        let context = TestContext {
+            synonyms: hashmap! {
+                vec![String::from("new"), String::from("york")] => vec![vec![String::from("ny")]],
+                vec![String::from("ny")] => vec![vec![String::from("nyc")]],
+            },
+            ..TestContext::default()
+        };
+
+        let expected = Operation::Or(
+            false,
+            vec![
+                Operation::And(vec![
+                    Operation::Query(Query {
+                        prefix: false,
+                        kind: QueryKind::exact("new".to_string()),
+                    }),
+                    Operation::Query(Query {
+                        prefix: false,
+                        kind: QueryKind::exact("york".to_string()),
+                    }),
+                ]),
+                Operation::Or(
+                    false,
+                    vec![
                         Operation::Query(Query {
                             prefix: false,
-                            kind: QueryKind::tolerant(1, "newyorkcity".to_string()),
+                            kind: QueryKind::exact("ny".to_string()),
+                        }),
+                        Operation::Query(Query {
+                            prefix: false,
+                            kind: QueryKind::exact("nyc".to_string()),
+                        }),
+                        Operation::Query(Query {
+                            prefix: false,
+                            kind: QueryKind::tolerant(1, "newyork".to_string()),
                         }),
                     ],
                 ),
@@ -1070,11 +1560,45 @@ mod test {
         );
 
         let (query_tree, _) =
-            TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+            context.build(TermsMatchingStrategy::All, true, None, tokens).unwrap().unwrap();
 
         assert_eq!(expected, query_tree);
     }
 
+    #[test]
+    fn cyclic_synonyms() {
+        let context = TestContext {
+            synonyms: hashmap! {
+                vec![String::from("car")] => vec![vec![String::from("auto")]],
+                vec![String::from("auto")] => vec![vec![String::from("vehicle")]],
+                vec![String::from("vehicle")] => vec![vec![String::from("car")]],
+                // a chain longer than the maximum expansion depth
+                vec![String::from("one")] => vec![vec![String::from("two")]],
+                vec![String::from("two")] => vec![vec![String::from("three")]],
+                vec![String::from("three")] => vec![vec![String::from("four")]],
+                vec![String::from("four")] => vec![vec![String::from("five")]],
+            },
+            ..TestContext::default()
+        };
+
+        let exact = |word: &str| {
+            Operation::Query(Query { prefix: false, kind: QueryKind::exact(word.to_string()) })
+        };
+
+        let tokens = "car ".tokenize();
+        let expected = Operation::Or(false, vec![exact("auto"), exact("vehicle"), exact("car")]);
+        let (query_tree, _) =
+            context.build(TermsMatchingStrategy::All, true, None, tokens).unwrap().unwrap();
+        assert_eq!(expected, query_tree);
+
+        let tokens = "one ".tokenize();
+        let expected =
+            Operation::Or(false, vec![exact("two"), exact("three"), exact("four"), exact("one")]);
+        let (query_tree, _) =
+            context.build(TermsMatchingStrategy::All, true, None, tokens).unwrap().unwrap();
+        assert_eq!(expected, query_tree);
+    }
+
     #[test]
     fn ngrams() {
         let query = "n grams ";
@@ -1100,8 +1624,10 @@ mod test {
             ],
         );
 
-        let (query_tree, _) =
-            TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::All, true, None, tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -1137,8 +1663,10 @@ mod test {
             ],
         );
 
-        let (query_tree, _) =
-            TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::All, true, None, tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -1153,8 +1681,10 @@ mod test {
             Operation::Query(Query { prefix: false, kind: QueryKind::exact("wooop".to_string()) }),
         ]);
 
-        let (query_tree, _) =
-            TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::All, true, None, tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -1169,8 +1699,10 @@ mod test {
             Operation::Phrase(vec!["wooop".to_string(), "wooop".to_string()]),
         ]);
 
-        let (query_tree, _) =
-            TestContext::default().build(false, true, None, tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::All, true, None, tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -1252,20 +1784,150 @@ mod test {
                 ),
             ],
         );
-        let (query_tree, _) =
-            TestContext::default().build(true, true, None, tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::Last, true, None, tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
 
+    #[test]
+    fn optional_word_frequency() {
+        let context = TestContext {
+            postings: hashmap! {
+                String::from("cheap")   => (0..1000).collect(),
+                String::from("red")     => (0..5000).collect(),
+                String::from("ferrari") => (0..50).collect(),
+            },
+            ..TestContext::default()
+        };
+        // the trailing space makes sure that the last word isn't a prefix.
+        let build = |strategy, query: &str| {
+            let query = format!("{} ", query);
+            let (query_tree, _) =
+                context.build(strategy, true, None, query.tokenize()).unwrap().unwrap();
+            query_tree
+        };
+
+        // the most common word, "red", is removed first and then "cheap".
+        let expected = Operation::Or(
+            true,
+            vec![
+                build(TermsMatchingStrategy::All, "ferrari"),
+                build(TermsMatchingStrategy::All, "cheap ferrari"),
+                build(TermsMatchingStrategy::All, "cheap red ferrari"),
+            ],
+        );
+        assert_eq!(expected, build(TermsMatchingStrategy::Frequency, "cheap red ferrari"));
+
+        // while the last words are removed first by default.
+        let expected = Operation::Or(
+            true,
+            vec![
+                build(TermsMatchingStrategy::All, "cheap"),
+                build(TermsMatchingStrategy::All, "cheap red"),
+                build(TermsMatchingStrategy::All, "cheap red ferrari"),
+            ],
+        );
+        assert_eq!(expected, build(TermsMatchingStrategy::Last, "cheap red ferrari"));
+    }
+
+    #[test]
+    fn at_least_words() {
+        let context = TestContext::default();
+        let build = |strategy, query: &str| {
+            let query = format!("{} ", query);
+            let (query_tree, _) =
+                context.build(strategy, true, None, query.tokenize()).unwrap().unwrap();
+            query_tree
+        };
+
+        // any 2 of the 3 words can match.
+        let expected = Operation::or(
+            true,
+            vec![
+                Operation::or(
+                    false,
+                    vec![
+                        build(TermsMatchingStrategy::All, "cheap red"),
+                        build(TermsMatchingStrategy::All, "cheap ferrari"),
+                        build(TermsMatchingStrategy::All, "red ferrari"),
+                    ],
+                ),
+                Operation::or(false, vec![build(TermsMatchingStrategy::All, "cheap red ferrari")]),
+            ],
+        );
+        assert_eq!(expected, build(TermsMatchingStrategy::AtLeast(2), "cheap red ferrari"));
+
+        // any 7 of the 8 words can match, not only the first ones.
+        let words = ["the", "quick", "brown", "fox", "jumps", "over", "lazy", "dogs"];
+        let alternatives = (0..words.len()).rev().map(|removed| {
+            let mut kept = words.to_vec();
+            kept.remove(removed);
+            build(TermsMatchingStrategy::All, &kept.join(" "))
+        });
+        let expected = Operation::or(
+            true,
+            vec![
+                Operation::or(false, alternatives.collect()),
+                Operation::or(false, vec![build(TermsMatchingStrategy::All, &words.join(" "))]),
+            ],
+        );
+        assert_eq!(expected, build(TermsMatchingStrategy::AtLeast(7), &words.join(" ")));
+
+        // there are too many combinations of the 10 words.
+        let query = format!("{} and cats ", words.join(" "));
+        let result = context.build(TermsMatchingStrategy::AtLeast(2), true, None, query.tokenize());
+        assert!(matches!(
+            result,
+            Err(crate::Error::UserError(UserError::TooManyAtLeastCombinations {
+                min_words: 2,
+                words: 10
+            }))
+        ));
+    }
+
+    #[test]
+    fn optional_word_frequency_with_phrase() {
+        let context = TestContext {
+            postings: hashmap! {
+                String::from("cheap")   => (0..1000).collect(),
+                String::from("red")     => (0..5000).collect(),
+                String::from("ferrari") => (0..50).collect(),
+            },
+            ..TestContext::default()
+        };
+        // the trailing space makes sure that the last word isn't a prefix.
+        let build = |strategy, query: &str| {
+            let query = format!("{} ", query);
+            let (query_tree, _) =
+                context.build(strategy, true, None, query.tokenize()).unwrap().unwrap();
+            query_tree
+        };
+
+        // phrases are never removed.
+        let expected = Operation::Or(
+            true,
+            vec![
+                build(TermsMatchingStrategy::All, r#""red""#),
+                build(TermsMatchingStrategy::All, r#""red" ferrari"#),
+                build(TermsMatchingStrategy::All, r#"cheap "red" ferrari"#),
+            ],
+        );
+        assert_eq!(expected, build(TermsMatchingStrategy::Frequency, r#"cheap "red" ferrari"#));
+    }
+
     #[test]
     fn optional_word_phrase() {
         let query = "\"hey my\"";
         let tokens = query.tokenize();
 
         let expected = Operation::Phrase(vec!["hey".to_string(), "my".to_string()]);
-        let (query_tree, _) =
-            TestContext::default().build(true, true, None, tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::Last, true, None, tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -1333,8 +1995,10 @@ mod test {
                 ]),
             ],
         );
-        let (query_tree, _) =
-            TestContext::default().build(true, true, None, tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::Last, true, None, tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -1363,12 +2027,140 @@ mod test {
                 }),
             ],
         );
-        let (query_tree, _) =
-            TestContext::default().build(false, false, None, tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::All, false, None, tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
 
+    #[test]
+    fn negative_words() {
+        let negative_words = |query: &str| {
+            let (primitive_query, restrictions) =
+                create_primitive_query(query.tokenize(), &[], None, None);
+            (primitive_query.len(), restrictions.negative_words)
+        };
+
+        assert_eq!(negative_words("shoes -running"), (1, vec!["running".to_string()]));
+        assert_eq!(
+            negative_words("-running shoes -red"),
+            (1, vec!["running".to_string(), "red".to_string()])
+        );
+        // a dash joining two words is not a negation.
+        assert_eq!(negative_words("t-shirt"), (2, vec![]));
+        assert_eq!(negative_words("shoes -"), (1, vec![]));
+        assert_eq!(negative_words("shoes - running"), (2, vec![]));
+        assert_eq!(negative_words("\"red -shoes\""), (1, vec![]));
+        assert_eq!(negative_words("shoes -shoes"), (1, vec![]));
+    }
+
+    #[test]
+    fn scoped_words() {
+        let stop_words = fst::Set::from_iter(&["the"]).unwrap();
+        let stop_words = fst::Set::new(stop_words.as_fst().as_bytes()).unwrap();
+        let scoped_words = |query: &str| {
+            let (query, scoped_words_ranges) = split_scoped_words(query);
+            let (primitive_query, restrictions) = create_primitive_query(
+                query.tokenize(),
+                &scoped_words_ranges,
+                Some(stop_words.clone()),
+                None,
+            );
+            (primitive_query, restrictions.scoped_words)
+        };
+
+        let word = |word: &str, prefix| PrimitiveQueryPart::Word(word.to_string(), prefix);
+        let scoped = |attribute: &str, word: &str| (attribute.to_string(), word.to_string());
+
+        assert_eq!(
+            scoped_words("title:rust book"),
+            (vec![word("rust", false), word("book", true)], vec![scoped("title", "rust")])
+        );
+        // the last scoped word is not a prefix.
+        assert_eq!(
+            scoped_words("book title:rust"),
+            (vec![word("book", false), word("rust", false)], vec![scoped("title", "rust")])
+        );
+        // the attribute must be directly followed by the word.
+        assert_eq!(
+            scoped_words("title: rust"),
+            (vec![word("title", false), word("rust", true)], vec![])
+        );
+        assert_eq!(scoped_words("title:"), (vec![word("title", false)], vec![]));
+        assert_eq!(
+            scoped_words("\"title:rust\""),
+            (
+                vec![PrimitiveQueryPart::Phrase(vec!["title".to_string(), "rust".to_string()])],
+                vec![]
+            )
+        );
+        // the attributes are not tokenized.
+        assert_eq!(
+            scoped_words("product_name:rust"),
+            (vec![word("rust", false)], vec![scoped("product_name", "rust")])
+        );
+        assert_eq!(
+            scoped_words("details.size:xl"),
+            (vec![word("xl", false)], vec![scoped("details.size", "xl")])
+        );
+        assert_eq!(
+            scoped_words("título:rust book"),
+            (vec![word("rust", false), word("book", true)], vec![scoped("título", "rust")])
+        );
+        // a scoped stop word is not searched but keeps its attribute.
+        assert_eq!(
+            scoped_words("title:the rust"),
+            (vec![word("rust", true)], vec![scoped("title", "the")])
+        );
+    }
+
+    #[test]
+    fn only_stop_words() {
+        let stop_words = fst::Set::from_iter(&["a", "of", "the"]).unwrap();
+        let stop_words = fst::Set::new(stop_words.as_fst().as_bytes()).unwrap();
+        let stop_words = |query: &str| {
+            let (primitive_query, restrictions) =
+                create_primitive_query(query.tokenize(), &[], Some(stop_words.clone()), None);
+            (primitive_query, restrictions.stop_words, restrictions.last_stop_word_prefix)
+        };
+
+        let word = |word: &str, prefix| PrimitiveQueryPart::Word(word.to_string(), prefix);
+
+        assert_eq!(
+            stop_words("the the"),
+            (vec![], vec!["the".to_string(), "the".to_string()], true)
+        );
+        assert_eq!(
+            stop_words("the the "),
+            (vec![], vec!["the".to_string(), "the".to_string()], false)
+        );
+        assert_eq!(stop_words("the"), (vec![], vec!["the".to_string()], true));
+        assert_eq!(
+            stop_words("of the "),
+            (vec![], vec!["of".to_string(), "the".to_string()], false)
+        );
+        // the stop words are still ignored when the query contains another word.
+        assert_eq!(
+            stop_words("the lord of the rings"),
+            (vec![word("lord", false), word("rings", true)], vec![], false)
+        );
+        assert_eq!(
+            stop_words("lord of the"),
+            (vec![word("lord", false), word("the", true)], vec![], false)
+        );
+        assert_eq!(
+            stop_words("\"the the\""),
+            (
+                vec![PrimitiveQueryPart::Phrase(vec!["the".to_string(), "the".to_string()])],
+                vec![],
+                false
+            )
+        );
+        assert_eq!(stop_words("the -lord"), (vec![], vec!["the".to_string()], false));
+    }
+
     #[test]
     fn words_limit() {
         let query = "\"hey my\" good friend";
@@ -1379,8 +2171,10 @@ mod test {
             Operation::Query(Query { prefix: false, kind: QueryKind::exact("good".to_string()) }),
         ]);
 
-        let (query_tree, _) =
-            TestContext::default().build(false, false, Some(2), tokens).unwrap().unwrap();
+        let (query_tree, _) = TestContext::default()
+            .build(TermsMatchingStrategy::All, false, Some(2), tokens)
+            .unwrap()
+            .unwrap();
 
         assert_eq!(expected, query_tree);
     }
@@ -1392,6 +2186,7 @@ mod test {
             max_typos: 2,
             word_len_one_typo: 5,
             word_len_two_typo: 7,
+            budget: TypoBudget::Thresholds,
             exact_words: Some(&exact_words),
         };
 
@@ -1411,6 +2206,38 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_length_typo_budget() {
+        let config = TypoConfig {
+            max_typos: 2,
+            word_len_one_typo: DEFAULT_MIN_WORD_LEN_ONE_TYPO,
+            word_len_two_typo: DEFAULT_MIN_WORD_LEN_TWO_TYPOS,
+            budget: TypoBudget::Length { chars_per_typo: 4, max_typos: 2 },
+            exact_words: None,
+        };
+
+        assert_eq!(
+            typos("car".to_string(), true, config.clone()),
+            QueryKind::exact("car".to_string())
+        );
+
+        assert_eq!(
+            typos("rust".to_string(), true, config.clone()),
+            QueryKind::Tolerant { typo: 1, word: "rust".to_string() }
+        );
+
+        assert_eq!(
+            typos("keyboard".to_string(), true, config.clone()),
+            QueryKind::Tolerant { typo: 2, word: "keyboard".to_string() }
+        );
+
+        // the budget is capped by its maximum number of typos.
+        assert_eq!(
+            typos("verylongword".to_string(), true, config.clone()),
+            QueryKind::Tolerant { typo: 2, word: "verylongword".to_string() }
+        );
+    }
+
     #[test]
     fn disable_typo_on_word() {
         let query = "goodbye";
@@ -1419,7 +2246,8 @@ mod test {
         let exact_words = fst::Set::from_iter(Some("goodbye")).unwrap().into_fst().into_inner();
         let exact_words = Some(fst::Set::new(exact_words).unwrap().map_data(Cow::Owned).unwrap());
         let context = TestContext { exact_words, ..Default::default() };
-        let (query_tree, _) = context.build(false, true, Some(2), tokens).unwrap().unwrap();
+        let (query_tree, _) =
+            context.build(TermsMatchingStrategy::All, true, Some(2), tokens).unwrap().unwrap();
 
         assert!(matches!(
             query_tree,
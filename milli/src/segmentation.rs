@@ -0,0 +1,301 @@
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use charabia::{Token, TokenKind};
+use heed::RoTxn;
+
+use crate::{Index, LongWords, MaxWordLength, Result};
+
+/// The user defined changes to the segmentation of the tokenizer: the characters to split the
+/// text on or not, and the words never split. They are applied around the tokenizer the same
+/// way to the documents and to the queries.
+#[derive(Debug, Clone, Default)]
+pub struct Segmentation {
+    separators: BTreeSet<char>,
+    non_separators: BTreeSet<char>,
+    dictionary: BTreeSet<String>,
+    digit_group_separators: BTreeSet<char>,
+    max_word_length: Option<MaxWordLength>,
+}
+
+impl Segmentation {
+    pub fn new(rtxn: &RoTxn, index: &Index) -> Result<Segmentation> {
+        Ok(Segmentation {
+            separators: index.separators(rtxn)?,
+            non_separators: index.non_separators(rtxn)?,
+            dictionary: index.dictionary(rtxn)?,
+            digit_group_separators: index.digit_group_separators(rtxn)?,
+            max_word_length: index.max_word_length(rtxn)?,
+        })
+    }
+
+    /// Replaces the additional separators by spaces, before the text is tokenized.
+    ///
+    /// The separators inside the words of the dictionary are kept and the replaced
+    /// separators keep their length, the bytes offsets of the text don't change.
+    pub fn prepare<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        if !text.contains(|c| self.separators.contains(&c)) {
+            return Cow::Borrowed(text);
+        }
+
+        let kept = self.dictionary_ranges(text);
+        let mut prepared = String::with_capacity(text.len());
+        for (i, c) in text.char_indices() {
+            if self.separators.contains(&c) && !kept.iter().any(|range| range.contains(&i)) {
+                prepared.extend(std::iter::repeat(' ').take(c.len_utf8()));
+            } else {
+                prepared.push(c);
+            }
+        }
+        Cow::Owned(prepared)
+    }
+
+    /// Merges the tokens of the words of the dictionary, then the separators
+    /// only made of non-separators with the words around them. The digit group
+    /// separators are removed from the numbers and the words longer than the
+    /// maximum word length are then truncated or skipped.
+    pub fn merge_tokens<'t>(
+        &self,
+        text: &str,
+        tokens: impl Iterator<Item = Token<'t>>,
+    ) -> Vec<Token<'t>> {
+        let kept = self.dictionary_ranges(text);
+        let mut merged: Vec<Token> = Vec::new();
+        // the last token ends with a non-separator, the next word must be appended to it.
+        let mut glued = false;
+        // the last token is a word of the dictionary that isn't complete yet.
+        let mut in_dictionary_word: Option<&Range<usize>> = None;
+
+        for token in tokens {
+            match in_dictionary_word {
+                Some(range) if token.byte_end <= range.end => {
+                    if let Some(last) = merged.last_mut() {
+                        append(last, &token);
+                    }
+                    continue;
+                }
+                _ => in_dictionary_word = None,
+            }
+            if let Some(range) = kept.iter().find(|range| range.start == token.byte_start) {
+                merged.push(Token { kind: TokenKind::Word, ..token });
+                in_dictionary_word = Some(range);
+                glued = false;
+                continue;
+            }
+
+            match token.kind {
+                TokenKind::Separator(_)
+                    if !self.non_separators.is_empty()
+                        && token.lemma().chars().all(|c| self.non_separators.contains(&c)) =>
+                {
+                    match merged.last_mut() {
+                        Some(last) if last.is_word() => append(last, &token),
+                        _ => merged.push(Token { kind: TokenKind::Word, ..token }),
+                    }
+                    glued = true;
+                }
+                TokenKind::Word | TokenKind::StopWord if glued => {
+                    // the previous token is always a word when glued.
+                    if let Some(last) = merged.last_mut() {
+                        append(last, &token);
+                    }
+                }
+                _ => {
+                    merged.push(token);
+                    glued = false;
+                }
+            }
+        }
+
+        if !self.digit_group_separators.is_empty() {
+            merged = self.group_digits(text, merged);
+        }
+        if let Some(max_word_length) = self.max_word_length {
+            for token in merged.iter_mut() {
+                limit_length(token, max_word_length);
+            }
+        }
+        merged
+    }
+
+    /// Merges the groups of digits of the numbers into a single word, with or without
+    /// a separator token between the groups: `1,000` and `1 000` both become `1000`.
+    fn group_digits<'t>(&self, text: &str, tokens: Vec<Token<'t>>) -> Vec<Token<'t>> {
+        let mut grouped: Vec<Token> = Vec::with_capacity(tokens.len());
+        for mut token in tokens {
+            if !token.is_word() {
+                grouped.push(token);
+                continue;
+            }
+
+            // the previous word and this one are the groups of the same number.
+            if let [.., previous, separator] = grouped.as_slice() {
+                let number = &text[previous.byte_start..token.byte_end];
+                let is_separated = matches!(separator.kind, TokenKind::Separator(_));
+                if let Some(digits) = self.ungroup_digits(number).filter(|_| is_separated) {
+                    grouped.pop();
+                    if let Some(previous) = grouped.last_mut() {
+                        previous.lemma = Cow::Owned(digits);
+                        previous.char_end = token.char_end;
+                        previous.byte_end = token.byte_end;
+                    }
+                    continue;
+                }
+            }
+
+            if let Some(digits) = self.ungroup_digits(&text[token.byte_start..token.byte_end]) {
+                token.lemma = Cow::Owned(digits);
+            }
+            grouped.push(token);
+        }
+        grouped
+    }
+
+    /// Returns the digits of a number whose groups are all separated by the same separator,
+    /// the first group must have from 1 to 3 digits and the others exactly 3 digits.
+    fn ungroup_digits(&self, text: &str) -> Option<String> {
+        let mut separators = text.chars().filter(|c| !c.is_ascii_digit());
+        let separator = separators.next()?;
+        if !self.digit_group_separators.contains(&separator) || separators.any(|c| c != separator) {
+            return None;
+        }
+
+        let mut groups = text.split(separator);
+        let first_len = groups.next().map_or(0, str::len);
+        if !(1..=3).contains(&first_len) || groups.any(|group| group.len() != 3) {
+            return None;
+        }
+        Some(text.chars().filter(char::is_ascii_digit).collect())
+    }
+
+    /// Returns the bytes ranges of the words of the dictionary found in the text, they are
+    /// compared regardless of the ASCII case and must not be surrounded by alphanumerics.
+    fn dictionary_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for (start, _) in text.char_indices() {
+            if ranges.last().map_or(false, |range| start < range.end) {
+                continue;
+            }
+            let is_boundary = !text[..start].ends_with(char::is_alphanumeric);
+            // the longest word of the dictionary is kept.
+            let longest = self
+                .dictionary
+                .iter()
+                .filter(|word| {
+                    let end = start + word.len();
+                    is_boundary
+                        && text.get(start..end).map_or(false, |s| s.eq_ignore_ascii_case(word))
+                        && !text[end..].starts_with(char::is_alphanumeric)
+                })
+                .map(|word| word.len())
+                .max();
+            if let Some(len) = longest {
+                ranges.push(start..start + len);
+            }
+        }
+        ranges
+    }
+}
+
+/// Truncates or skips a word longer than the maximum word length, a skipped
+/// word becomes an unknown token that keeps its position but is never indexed.
+fn limit_length(token: &mut Token, MaxWordLength { max_chars, long_words }: MaxWordLength) {
+    let is_word = token.is_word() || token.kind == TokenKind::StopWord;
+    if !is_word || token.lemma().chars().count() <= max_chars {
+        return;
+    }
+    match long_words {
+        LongWords::Truncate => {
+            token.lemma = Cow::Owned(token.lemma().chars().take(max_chars).collect());
+        }
+        LongWords::Skip => token.kind = TokenKind::Unknown,
+    }
+}
+
+fn append<'t>(token: &mut Token<'t>, next: &Token) {
+    token.lemma = Cow::Owned(format!("{}{}", token.lemma(), next.lemma()));
+    token.kind = TokenKind::Word;
+    token.char_end = next.char_end;
+    token.byte_end = next.byte_end;
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use charabia::{SeparatorKind, Tokenize};
+    use maplit::btreeset;
+
+    use super::*;
+
+    fn words(segmentation: &Segmentation, text: &str) -> Vec<String> {
+        let text = segmentation.prepare(text);
+        let text: &str = &text;
+        let tokens = segmentation.merge_tokens(text, text.tokenize());
+        tokens.iter().filter(|t| t.is_word()).map(|t| t.lemma().to_string()).collect()
+    }
+
+    #[test]
+    fn custom_separators() {
+        let segmentation = Segmentation { separators: btreeset! { '_' }, ..Default::default() };
+        assert_eq!(words(&segmentation, "ab_cd"), ["ab", "cd"]);
+
+        let text = segmentation.prepare("ab_cd");
+        let text: &str = &text;
+        let tokens = segmentation.merge_tokens(text, text.tokenize());
+        assert!(tokens.iter().any(|t| t.kind == TokenKind::Separator(SeparatorKind::Soft)));
+    }
+
+    #[test]
+    fn custom_non_separators() {
+        let segmentation =
+            Segmentation { non_separators: btreeset! { '-', '+' }, ..Default::default() };
+        let words = words(&segmentation, "a t-shirt, c++ and -hello");
+        assert_eq!(words, ["a", "t-shirt", "c++", "and", "-hello"]);
+    }
+
+    #[test]
+    fn dictionary() {
+        let segmentation = Segmentation {
+            separators: btreeset! { '.' },
+            dictionary: btreeset! { S("node.js"), S("c++") },
+            ..Default::default()
+        };
+        let words = words(&segmentation, "Node.js or C++ but not node.jsx or c.js");
+        assert_eq!(words, ["node.js", "or", "c++", "but", "not", "node", "jsx", "or", "c", "js"]);
+    }
+
+    #[test]
+    fn digit_group_separators() {
+        let segmentation = Segmentation {
+            digit_group_separators: btreeset! { ',', '.', ' ' },
+            ..Default::default()
+        };
+        assert_eq!(words(&segmentation, "1,000 or 1 000 000"), ["1000", "or", "1000000"]);
+        assert_eq!(words(&segmentation, "12.345"), ["12345"]);
+
+        // the numbers that are not grouped by three digits are kept as is.
+        let default = Segmentation::default();
+        for text in &["1.2.3", "12,34", "1234 567", "2 times 100"] {
+            assert_eq!(words(&segmentation, text), words(&default, text));
+        }
+    }
+
+    #[test]
+    fn max_word_length() {
+        let long_word = "a".repeat(2000);
+        let text = format!("hello {} world", long_word);
+
+        let max_word_length = MaxWordLength { max_chars: 8, long_words: LongWords::Truncate };
+        let segmentation =
+            Segmentation { max_word_length: Some(max_word_length), ..Default::default() };
+        assert_eq!(words(&segmentation, &text), ["hello", "aaaaaaaa", "world"]);
+
+        let max_word_length = MaxWordLength { max_chars: 8, long_words: LongWords::Skip };
+        let segmentation =
+            Segmentation { max_word_length: Some(max_word_length), ..Default::default() };
+        assert_eq!(words(&segmentation, &text), ["hello", "world"]);
+        // the words of the limit length are kept.
+        assert_eq!(words(&segmentation, "abcdefgh"), ["abcdefgh"]);
+    }
+}
@@ -22,6 +22,7 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
             exact_word_docids,
             word_prefix_docids,
             exact_word_prefix_docids,
+            stop_word_docids,
             docid_word_positions,
             word_pair_proximity_docids,
             word_prefix_pair_proximity_docids,
@@ -33,6 +34,7 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
             field_id_docid_facet_f64s,
             field_id_docid_facet_strings,
             documents,
+            documents_json,
         } = self.index;
 
         // We retrieve the number of documents ids that we are deleting.
@@ -44,8 +46,11 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
         self.index.put_words_prefixes_fst(self.wtxn, &fst::Set::default())?;
         self.index.put_external_documents_ids(self.wtxn, &ExternalDocumentsIds::default())?;
         self.index.put_documents_ids(self.wtxn, &RoaringBitmap::default())?;
+        self.index.put_soft_deleted_documents_ids(self.wtxn, &RoaringBitmap::default())?;
+        self.index.put_documents_words_count(self.wtxn, 0)?;
         self.index.put_field_distribution(self.wtxn, &FieldDistribution::default())?;
         self.index.delete_geo_rtree(self.wtxn)?;
+        self.index.delete_geo_rtree_insertions(self.wtxn)?;
         self.index.delete_geo_faceted_documents_ids(self.wtxn)?;
 
         // We clean all the faceted documents ids.
@@ -53,6 +58,9 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
         for field_id in faceted_fields {
             self.index.put_number_faceted_documents_ids(self.wtxn, field_id, &empty)?;
             self.index.put_string_faceted_documents_ids(self.wtxn, field_id, &empty)?;
+            self.index.put_boolean_faceted_documents_ids(self.wtxn, field_id, false, &empty)?;
+            self.index.put_boolean_faceted_documents_ids(self.wtxn, field_id, true, &empty)?;
+            self.index.put_empty_faceted_documents_ids(self.wtxn, field_id, &empty)?;
         }
 
         // Clear the other databases.
@@ -60,6 +68,7 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
         exact_word_docids.clear(self.wtxn)?;
         word_prefix_docids.clear(self.wtxn)?;
         exact_word_prefix_docids.clear(self.wtxn)?;
+        stop_word_docids.clear(self.wtxn)?;
         docid_word_positions.clear(self.wtxn)?;
         word_pair_proximity_docids.clear(self.wtxn)?;
         word_prefix_pair_proximity_docids.clear(self.wtxn)?;
@@ -71,6 +80,7 @@ impl<'t, 'u, 'i> ClearDocuments<'t, 'u, 'i> {
         field_id_docid_facet_f64s.clear(self.wtxn)?;
         field_id_docid_facet_strings.clear(self.wtxn)?;
         documents.clear(self.wtxn)?;
+        documents_json.clear(self.wtxn)?;
 
         Ok(number_of_documents)
     }
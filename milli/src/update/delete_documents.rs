@@ -5,6 +5,7 @@ use fst::IntoStreamer;
 use heed::types::{ByteSlice, Str};
 use heed::{BytesDecode, BytesEncode, Database};
 use roaring::RoaringBitmap;
+use rstar::RTree;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use time::OffsetDateTime;
@@ -26,6 +27,7 @@ pub struct DeleteDocuments<'t, 'u, 'i> {
     index: &'i Index,
     external_documents_ids: ExternalDocumentsIds<'static>,
     documents_ids: RoaringBitmap,
+    soft_deletion: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -46,9 +48,16 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             index,
             external_documents_ids,
             documents_ids: RoaringBitmap::new(),
+            soft_deletion: false,
         })
     }
 
+    /// Only marks the documents as deleted instead of removing them from all the databases,
+    /// which is much faster. The soft deleted documents are purged by the next hard deletion.
+    pub fn soft_deletion(&mut self, soft_deletion: bool) {
+        self.soft_deletion = soft_deletion;
+    }
+
     pub fn delete_document(&mut self, docid: u32) {
         self.documents_ids.insert(docid);
     }
@@ -63,14 +72,20 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         Some(docid)
     }
 
-    pub fn execute(self) -> Result<DocumentDeletionResult> {
+    pub fn execute(mut self) -> Result<DocumentDeletionResult> {
         self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
         // We retrieve the current documents ids that are in the database.
         let mut documents_ids = self.index.documents_ids(self.wtxn)?;
+        let mut soft_deleted_documents_ids = self.index.soft_deleted_documents_ids(self.wtxn)?;
         let current_documents_ids_len = documents_ids.len();
 
-        // We can and must stop removing documents in a database that is empty.
-        if documents_ids.is_empty() {
+        // The documents that are already soft deleted can't be deleted twice.
+        self.documents_ids -= &soft_deleted_documents_ids;
+
+        // We can and must stop removing documents in a database that is empty,
+        // the same goes when there is nothing to delete nor to purge.
+        let nothing_to_delete = documents_ids.is_empty() || self.documents_ids.is_empty();
+        if nothing_to_delete && soft_deleted_documents_ids.is_empty() {
             return Ok(DocumentDeletionResult {
                 deleted_documents: 0,
                 remaining_documents: current_documents_ids_len,
@@ -114,6 +129,7 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             exact_word_docids,
             word_prefix_docids,
             exact_word_prefix_docids,
+            stop_word_docids,
             docid_word_positions,
             word_pair_proximity_docids,
             field_id_word_count_docids,
@@ -125,6 +141,7 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             field_id_docid_facet_f64s,
             field_id_docid_facet_strings,
             documents,
+            documents_json,
         } = self.index;
 
         // Number of fields for each document that has been deleted.
@@ -133,6 +150,46 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
         // Retrieve the words and the external documents ids contained in the documents.
         let mut words = Vec::new();
         let mut external_ids = Vec::new();
+        // The number of words positions of the deleted documents, the soft deleted ones
+        // were already removed from the documents words count.
+        let mut words_count = 0;
+
+        if self.soft_deletion {
+            // The documents content stays in the database until the documents are purged.
+            let mut deleted_documents_ids = RoaringBitmap::new();
+            for docid in &self.documents_ids {
+                if let Some(obkv) = documents.get(self.wtxn, &BEU32::new(docid))? {
+                    let external_id =
+                        count_document_fields(obkv, id_field, &mut fields_ids_distribution_diff)?;
+                    external_ids.extend(external_id);
+                    deleted_documents_ids.insert(docid);
+                }
+                for result in docid_word_positions.prefix_iter(self.wtxn, &(docid, ""))? {
+                    let (_key, positions) = result?;
+                    words_count += positions.len();
+                }
+            }
+
+            remove_from_field_distribution(self.wtxn, self.index, fields_ids_distribution_diff)?;
+            remove_external_ids(self.wtxn, self.index, external_ids)?;
+            remove_words_count(self.wtxn, self.index, words_count)?;
+
+            soft_deleted_documents_ids |= &deleted_documents_ids;
+            self.index.put_soft_deleted_documents_ids(self.wtxn, &soft_deleted_documents_ids)?;
+
+            return Ok(DocumentDeletionResult {
+                deleted_documents: deleted_documents_ids.len(),
+                remaining_documents: documents_ids.len(),
+            });
+        }
+
+        let deleted_documents = self.documents_ids.len();
+
+        // The soft deleted documents are purged along with the deleted ones, they are
+        // not part of the documents ids nor the external documents ids anymore.
+        self.documents_ids |= &soft_deleted_documents_ids;
+        self.index.put_soft_deleted_documents_ids(self.wtxn, &RoaringBitmap::new())?;
+
         for docid in &self.documents_ids {
             // We create an iterator to be able to get the content and delete the document
             // content itself. It's faster to acquire a cursor to get and delete,
@@ -140,30 +197,25 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             let key = BEU32::new(docid);
             let mut iter = documents.range_mut(self.wtxn, &(key..=key))?;
             if let Some((_key, obkv)) = iter.next().transpose()? {
-                for (field_id, _) in obkv.iter() {
-                    *fields_ids_distribution_diff.entry(field_id).or_default() += 1;
-                }
-
-                if let Some(content) = obkv.get(id_field) {
-                    let external_id = match serde_json::from_slice(content).unwrap() {
-                        Value::String(string) => SmallString32::from(string.as_str()),
-                        Value::Number(number) => SmallString32::from(number.to_string()),
-                        document_id => {
-                            return Err(UserError::InvalidDocumentId { document_id }.into())
-                        }
-                    };
-                    external_ids.push(external_id);
+                if !soft_deleted_documents_ids.contains(docid) {
+                    let external_id =
+                        count_document_fields(obkv, id_field, &mut fields_ids_distribution_diff)?;
+                    external_ids.extend(external_id);
                 }
                 // safety: we don't keep references from inside the LMDB database.
                 unsafe { iter.del_current()? };
             }
             drop(iter);
+            documents_json.delete(self.wtxn, &key)?;
 
             // We iterate through the words positions of the document id,
             // retrieve the word and delete the positions.
             let mut iter = docid_word_positions.prefix_iter_mut(self.wtxn, &(docid, ""))?;
             while let Some(result) = iter.next() {
-                let ((_docid, word), _positions) = result?;
+                let ((_docid, word), positions) = result?;
+                if !soft_deleted_documents_ids.contains(docid) {
+                    words_count += positions.len();
+                }
                 // This boolean will indicate if we must remove this word from the words FST.
                 words.push((SmallString32::from(word), false));
                 // safety: we don't keep references from inside the LMDB database.
@@ -171,36 +223,9 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             }
         }
 
-        let mut field_distribution = self.index.field_distribution(self.wtxn)?;
-
-        // We use pre-calculated number of fields occurrences that needs to be deleted
-        // to reflect deleted documents.
-        // If all field occurrences are removed, delete the entry from distribution.
-        // Otherwise, insert new number of occurrences (current_count - count_diff).
-        for (field_id, count_diff) in fields_ids_distribution_diff {
-            let field_name = fields_ids_map.name(field_id).unwrap();
-            if let Entry::Occupied(mut entry) = field_distribution.entry(field_name.to_string()) {
-                match entry.get().checked_sub(count_diff) {
-                    Some(0) | None => entry.remove(),
-                    Some(count) => entry.insert(count),
-                };
-            }
-        }
-
-        self.index.put_field_distribution(self.wtxn, &field_distribution)?;
-
-        // We create the FST map of the external ids that we must delete.
-        external_ids.sort_unstable();
-        let external_ids_to_delete = fst::Set::from_iter(external_ids)?;
-
-        // We acquire the current external documents ids map...
-        let mut new_external_documents_ids = self.index.external_documents_ids(self.wtxn)?;
-        // ...and remove the to-delete external ids.
-        new_external_documents_ids.delete_ids(external_ids_to_delete)?;
-
-        // We write the new external ids into the main database.
-        let new_external_documents_ids = new_external_documents_ids.into_static();
-        self.index.put_external_documents_ids(self.wtxn, &new_external_documents_ids)?;
+        remove_from_field_distribution(self.wtxn, self.index, fields_ids_distribution_diff)?;
+        remove_external_ids(self.wtxn, self.index, external_ids)?;
+        remove_words_count(self.wtxn, self.index, words_count)?;
 
         // Maybe we can improve the get performance of the words
         // if we sort the words first, keeping the LMDB pages in cache.
@@ -286,6 +311,25 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
             self.index.put_words_prefixes_fst(self.wtxn, &new_words_prefixes_fst)?;
         }
 
+        // We delete the documents ids from the stop words docids, there are
+        // only a few stop words and their documents ids are not referenced anywhere else.
+        let mut iter = stop_word_docids.iter_mut(self.wtxn)?;
+        while let Some(result) = iter.next() {
+            let ((word, field_id), mut docids) = result?;
+            let previous_len = docids.len();
+            docids -= &self.documents_ids;
+            if docids.is_empty() {
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.del_current()? };
+            } else if docids.len() != previous_len {
+                let word = word.to_string();
+                // safety: we don't keep references from inside the LMDB database.
+                unsafe { iter.put_current(&(word.as_str(), field_id), &docids)? };
+            }
+        }
+
+        drop(iter);
+
         // We delete the documents ids from the word prefix pair proximity database docids
         // and remove the empty pairs too.
         let db = word_prefix_pair_proximity_docids.remap_key_type::<ByteSlice>();
@@ -390,9 +434,20 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
                 .cloned()
                 .map(|point| (point, point.data.0))
                 .unzip();
-            points_to_remove.iter().for_each(|point| {
-                rtree.remove(&point);
-            });
+            // removing most of the points one by one leaves a badly balanced tree.
+            if points_to_remove.len() * 2 > rtree.size() {
+                let remaining = rtree
+                    .iter()
+                    .filter(|&point| !self.documents_ids.contains(point.data.0))
+                    .cloned()
+                    .collect();
+                rtree = RTree::bulk_load(remaining);
+                self.index.put_geo_rtree_insertions(self.wtxn, 0)?;
+            } else {
+                points_to_remove.iter().for_each(|point| {
+                    rtree.remove(&point);
+                });
+            }
             geo_faceted_doc_ids -= docids_to_remove;
 
             self.index.put_geo_rtree(self.wtxn, &rtree)?;
@@ -439,13 +494,100 @@ impl<'t, 'u, 'i> DeleteDocuments<'t, 'u, 'i> {
                 &self.documents_ids,
                 |(_fid, docid, _value)| docid,
             )?;
+
+            // Remove docids from the boolean faceted documents ids
+            for boolean in [false, true] {
+                let mut docids =
+                    self.index.boolean_faceted_documents_ids(self.wtxn, field_id, boolean)?;
+                docids -= &self.documents_ids;
+                self.index
+                    .put_boolean_faceted_documents_ids(self.wtxn, field_id, boolean, &docids)?;
+            }
+
+            // Remove docids from the documents ids without any facet value
+            let mut docids = self.index.empty_faceted_documents_ids(self.wtxn, field_id)?;
+            docids -= &self.documents_ids;
+            self.index.put_empty_faceted_documents_ids(self.wtxn, field_id, &docids)?;
         }
 
-        Ok(DocumentDeletionResult {
-            deleted_documents: self.documents_ids.len(),
-            remaining_documents: documents_ids.len(),
-        })
+        Ok(DocumentDeletionResult { deleted_documents, remaining_documents: documents_ids.len() })
+    }
+}
+
+/// Counts the fields of a deleted document in the fields distribution diff
+/// and returns its external id.
+fn count_document_fields(
+    obkv: obkv::KvReaderU16,
+    id_field: FieldId,
+    fields_ids_distribution_diff: &mut HashMap<FieldId, u64>,
+) -> Result<Option<SmallString32>> {
+    for (field_id, _) in obkv.iter() {
+        *fields_ids_distribution_diff.entry(field_id).or_default() += 1;
+    }
+
+    match obkv.get(id_field) {
+        Some(content) => match serde_json::from_slice(content).unwrap() {
+            Value::String(string) => Ok(Some(SmallString32::from(string.as_str()))),
+            Value::Number(number) => Ok(Some(SmallString32::from(number.to_string()))),
+            document_id => Err(UserError::InvalidDocumentId { document_id, position: None }.into()),
+        },
+        None => Ok(None),
+    }
+}
+
+fn remove_from_field_distribution(
+    wtxn: &mut heed::RwTxn,
+    index: &Index,
+    fields_ids_distribution_diff: HashMap<FieldId, u64>,
+) -> Result<()> {
+    let fields_ids_map = index.fields_ids_map(wtxn)?;
+    let mut field_distribution = index.field_distribution(wtxn)?;
+
+    // We use pre-calculated number of fields occurrences that needs to be deleted
+    // to reflect deleted documents.
+    // If all field occurrences are removed, delete the entry from distribution.
+    // Otherwise, insert new number of occurrences (current_count - count_diff).
+    for (field_id, count_diff) in fields_ids_distribution_diff {
+        let field_name = fields_ids_map.name(field_id).unwrap();
+        if let Entry::Occupied(mut entry) = field_distribution.entry(field_name.to_string()) {
+            match entry.get().checked_sub(count_diff) {
+                Some(0) | None => entry.remove(),
+                Some(count) => entry.insert(count),
+            };
+        }
     }
+
+    index.put_field_distribution(wtxn, &field_distribution)?;
+    Ok(())
+}
+
+fn remove_external_ids(
+    wtxn: &mut heed::RwTxn,
+    index: &Index,
+    mut external_ids: Vec<SmallString32>,
+) -> Result<()> {
+    // We create the FST map of the external ids that we must delete.
+    external_ids.sort_unstable();
+    let external_ids_to_delete = fst::Set::from_iter(external_ids)?;
+
+    // We acquire the current external documents ids map...
+    let mut new_external_documents_ids = index.external_documents_ids(wtxn)?;
+    // ...and remove the to-delete external ids.
+    new_external_documents_ids.delete_ids(external_ids_to_delete)?;
+
+    // We write the new external ids into the main database.
+    let new_external_documents_ids = new_external_documents_ids.into_static();
+    index.put_external_documents_ids(wtxn, &new_external_documents_ids)?;
+    Ok(())
+}
+
+/// Removes the words positions of the deleted documents from the documents words count,
+/// it isn't maintained for the documents indexed before it was counted.
+fn remove_words_count(wtxn: &mut heed::RwTxn, index: &Index, words_count: u64) -> Result<()> {
+    if let Some(count) = index.documents_words_count(wtxn)? {
+        index.put_documents_words_count(wtxn, count.saturating_sub(words_count))?;
+    }
+    Ok(())
 }
 
 fn remove_from_word_prefix_docids(
@@ -1058,4 +1200,104 @@ mod tests {
 
         wtxn.commit().unwrap();
     }
+
+    #[test]
+    fn search_should_not_return_soft_deleted_documents() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let config = IndexerConfig::default();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_primary_key(S("docid"));
+        builder.set_filterable_fields(hashset! { S("label") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            {"docid":"1_4","label":"sign"},
+            {"docid":"1_5","label":"letter"},
+            {"docid":"1_7","label":"abstract,cartoon,design,pattern"},
+            {"docid":"1_36","label":"drawing,painting,pattern"},
+            {"docid":"1_37","label":"art,drawing,outdoor"}
+        ]);
+        insert_documents(&mut wtxn, &index, content);
+
+        let external_document_ids = index.external_documents_ids(&wtxn).unwrap();
+        let docids: RoaringBitmap = ["1_4", "1_36"]
+            .iter()
+            .map(|id| external_document_ids.get(id.as_bytes()).unwrap())
+            .collect();
+        let result = index.soft_delete_documents(&mut wtxn, &docids).unwrap();
+        assert_eq!(result.deleted_documents, 2);
+        assert_eq!(result.remaining_documents, 3);
+
+        // The words of the documents are still in the databases...
+        assert!(index.word_docids.get(&wtxn, "sign").unwrap().is_some());
+
+        // ...but they are never returned.
+        let results = index.search(&wtxn).query("sign").execute().unwrap();
+        assert!(results.documents_ids.is_empty());
+        assert!(results.candidates.is_empty());
+
+        let results = index.search(&wtxn).query("drawing").execute().unwrap();
+        assert_eq!(results.candidates.len(), 1);
+
+        let filter = Filter::from_str("label = sign").unwrap().unwrap();
+        let results = index.search(&wtxn).filter(filter).execute().unwrap();
+        assert!(results.documents_ids.is_empty());
+
+        let results = index.search(&wtxn).execute().unwrap();
+        assert_eq!(results.documents_ids.len(), 3);
+        assert!(results.documents_ids.iter().all(|id| !docids.contains(*id)));
+
+        assert!(index.documents(&wtxn, docids.iter()).is_err());
+        assert_eq!(index.all_documents(&wtxn).unwrap().count(), 3);
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 3);
+        assert!(index.external_documents_ids(&wtxn).unwrap().get("1_4").is_none());
+
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn purge_soft_deleted_documents() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "kevin" },
+            { "id": 1, "name": "kevina" },
+            { "id": 2, "name": "benoit" }
+        ]);
+        insert_documents(&mut wtxn, &index, content);
+
+        let docids: RoaringBitmap = (0..2).collect();
+        index.soft_delete_documents(&mut wtxn, &docids).unwrap();
+        assert_eq!(index.soft_deleted_documents_ids(&wtxn).unwrap(), docids);
+
+        // The soft deleted ids are not reused by the new documents.
+        let content = documents!([ { "id": 0, "name": "kevin" } ]);
+        insert_documents(&mut wtxn, &index, content);
+        let external_document_ids = index.external_documents_ids(&wtxn).unwrap();
+        assert_eq!(external_document_ids.get("0"), Some(3));
+
+        // The purge removes the documents from the databases and frees their ids.
+        assert_eq!(index.purge_soft_deleted_documents(&mut wtxn).unwrap(), 2);
+        assert!(index.soft_deleted_documents_ids(&wtxn).unwrap().is_empty());
+        assert!(index.word_docids.get(&wtxn, "kevina").unwrap().is_none());
+        assert!(index.documents.get(&wtxn, &BEU32::new(1)).unwrap().is_none());
+        let docids = index.word_docids.get(&wtxn, "kevin").unwrap().unwrap();
+        assert_eq!(docids.iter().collect::<Vec<_>>(), vec![3]);
+
+        let content = documents!([ { "id": 1, "name": "kevina" } ]);
+        insert_documents(&mut wtxn, &index, content);
+        let external_document_ids = index.external_documents_ids(&wtxn).unwrap();
+        assert_eq!(external_document_ids.get("1"), Some(0));
+
+        wtxn.commit().unwrap();
+    }
 }
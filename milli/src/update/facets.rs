@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::fs::File;
 use std::num::{NonZeroU8, NonZeroUsize};
 use std::{cmp, mem};
@@ -25,6 +26,7 @@ pub struct Facets<'t, 'u, 'i> {
     pub(crate) chunk_compression_level: Option<u32>,
     level_group_size: NonZeroUsize,
     min_level_size: NonZeroUsize,
+    fields_ids: Option<HashSet<FieldId>>,
 }
 
 impl<'t, 'u, 'i> Facets<'t, 'u, 'i> {
@@ -36,6 +38,7 @@ impl<'t, 'u, 'i> Facets<'t, 'u, 'i> {
             chunk_compression_level: None,
             level_group_size: NonZeroUsize::new(4).unwrap(),
             min_level_size: NonZeroUsize::new(5).unwrap(),
+            fields_ids: None,
         }
     }
 
@@ -49,11 +52,20 @@ impl<'t, 'u, 'i> Facets<'t, 'u, 'i> {
         self
     }
 
+    /// Only computes the levels of these faceted fields, the levels of the other ones are kept.
+    pub(crate) fn fields_ids(&mut self, fields_ids: HashSet<FieldId>) -> &mut Self {
+        self.fields_ids = Some(fields_ids);
+        self
+    }
+
     #[logging_timer::time("Facets::{}")]
     pub fn execute(self) -> Result<()> {
         self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
         // We get the faceted fields to be able to create the facet levels.
-        let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+        let mut faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
+        if let Some(fields_ids) = &self.fields_ids {
+            faceted_fields.retain(|field_id| fields_ids.contains(field_id));
+        }
 
         debug!("Computing and writing the facet values levels docids into LMDB on disk...");
 
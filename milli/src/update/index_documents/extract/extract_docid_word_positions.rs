@@ -1,29 +1,38 @@
 use std::collections::HashSet;
 use std::convert::TryInto;
 use std::fs::File;
+use std::iter::FromIterator;
 use std::{io, mem, str};
 
 use charabia::{SeparatorKind, Token, TokenKind, TokenizerBuilder};
 use roaring::RoaringBitmap;
 use serde_json::Value;
 
-use super::helpers::{concat_u32s_array, create_sorter, sorter_into_reader, GrenadParameters};
+use super::helpers::{
+    concat_u32s_array, create_sorter, merge_roaring_bitmaps, serialize_roaring_bitmap,
+    sorter_into_reader, GrenadParameters,
+};
 use crate::error::{InternalError, SerializationError};
-use crate::{absolute_from_relative_position, FieldId, Result, MAX_POSITION_PER_ATTRIBUTE};
+use crate::{
+    absolute_from_relative_position, FieldId, Result, Segmentation, MAX_POSITION_PER_ATTRIBUTE,
+};
 
 /// Extracts the word and positions where this word appear and
 /// prefixes it by the document id.
 ///
-/// Returns the generated internal documents ids and a grenad reader
-/// with the list of extracted words from the given chunk of documents.
+/// Returns the generated internal documents ids, a grenad reader
+/// with the list of extracted words from the given chunk of documents
+/// and a grenad reader with the stop words, the field ids they are found in and the documents
+/// ids containing them.
 #[logging_timer::time]
 pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
     obkv_documents: grenad::Reader<R>,
     indexer: GrenadParameters,
     searchable_fields: &Option<HashSet<FieldId>>,
     stop_words: Option<&fst::Set<&[u8]>>,
+    segmentation: &Segmentation,
     max_positions_per_attributes: Option<u32>,
-) -> Result<(RoaringBitmap, grenad::Reader<File>)> {
+) -> Result<(RoaringBitmap, grenad::Reader<File>, grenad::Reader<File>)> {
     let max_positions_per_attributes = max_positions_per_attributes
         .map_or(MAX_POSITION_PER_ATTRIBUTE, |max| max.min(MAX_POSITION_PER_ATTRIBUTE));
     let max_memory = indexer.max_memory_by_thread();
@@ -34,10 +43,18 @@ pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
         indexer.chunk_compression_type,
         indexer.chunk_compression_level,
         indexer.max_nb_chunks,
-        max_memory,
+        max_memory.map(|x| x / 2),
+    );
+    let mut stop_word_docids_sorter = create_sorter(
+        merge_roaring_bitmaps,
+        indexer.chunk_compression_type,
+        indexer.chunk_compression_level,
+        indexer.max_nb_chunks,
+        max_memory.map(|x| x / 2),
     );
 
     let mut key_buffer = Vec::new();
+    let mut docid_buffer = Vec::new();
     let mut field_buffer = String::new();
     let mut builder = TokenizerBuilder::new();
     if let Some(stop_words) = stop_words {
@@ -56,6 +73,7 @@ pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
         documents_ids.push(document_id);
         key_buffer.clear();
         key_buffer.extend_from_slice(&document_id.to_be_bytes());
+        serialize_roaring_bitmap(&RoaringBitmap::from_iter(Some(document_id)), &mut docid_buffer)?;
 
         for (field_id, field_bytes) in obkv.iter() {
             if searchable_fields.as_ref().map_or(true, |sf| sf.contains(&field_id)) {
@@ -63,12 +81,23 @@ pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
                     serde_json::from_slice(field_bytes).map_err(InternalError::SerdeJson)?;
                 field_buffer.clear();
                 if let Some(field) = json_to_string(&value, &mut field_buffer) {
-                    let tokens = process_tokens(tokenizer.tokenize(field))
+                    let field = segmentation.prepare(field);
+                    let tokens = segmentation.merge_tokens(&field, tokenizer.tokenize(&field));
+                    let tokens = process_tokens(tokens.into_iter())
                         .take_while(|(p, _)| (*p as u32) < max_positions_per_attributes);
 
                     for (index, token) in tokens {
+                        let is_stop_word = token.kind == TokenKind::StopWord;
                         let token = token.lemma().trim();
-                        if !token.is_empty() {
+                        if is_stop_word {
+                            // the stop words keep their position but are only
+                            // indexed in their own database.
+                            if !token.is_empty() {
+                                let mut key = token.as_bytes().to_vec();
+                                key.extend_from_slice(&(field_id as u32).to_be_bytes());
+                                stop_word_docids_sorter.insert(&key, &docid_buffer)?;
+                            }
+                        } else if !token.is_empty() {
                             key_buffer.truncate(mem::size_of::<u32>());
                             key_buffer.extend_from_slice(token.as_bytes());
 
@@ -85,7 +114,9 @@ pub fn extract_docid_word_positions<R: io::Read + io::Seek>(
         }
     }
 
-    sorter_into_reader(docid_word_positions_sorter, indexer).map(|reader| (documents_ids, reader))
+    let docid_word_positions = sorter_into_reader(docid_word_positions_sorter, indexer)?;
+    let stop_word_docids = sorter_into_reader(stop_word_docids_sorter, indexer)?;
+    Ok((documents_ids, docid_word_positions, stop_word_docids))
 }
 
 /// Transform a JSON value into a string that can be indexed.
@@ -167,5 +198,5 @@ fn process_tokens<'a>(
             }
             Some((*offset, token))
         })
-        .filter(|(_, t)| t.is_word())
+        .filter(|(_, t)| t.is_word() || t.kind == TokenKind::StopWord)
 }
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io;
 use std::mem::size_of;
@@ -15,12 +15,19 @@ use crate::{DocumentId, FieldId, Result};
 ///
 /// Returns the generated grenad reader containing the docid the fid and the orginal value as key
 /// and the normalized value as value extracted from the given chunk of documents.
+/// The booleans are returned in a third reader keyed by the fid, the docid and the boolean,
+/// the fourth reader is keyed by the fid and the docid of the fields without any value.
+///
+/// The string values found in the `facet_value_mappings` of their field, by normalized value,
+/// are replaced by their canonical value.
 #[logging_timer::time]
 pub fn extract_fid_docid_facet_values<R: io::Read + io::Seek>(
     obkv_documents: grenad::Reader<R>,
     indexer: GrenadParameters,
     faceted_fields: &HashSet<FieldId>,
-) -> Result<(grenad::Reader<File>, grenad::Reader<File>)> {
+    facet_value_mappings: &HashMap<FieldId, HashMap<String, String>>,
+) -> Result<(grenad::Reader<File>, grenad::Reader<File>, grenad::Reader<File>, grenad::Reader<File>)>
+{
     let max_memory = indexer.max_memory_by_thread();
 
     let mut fid_docid_facet_numbers_sorter = create_sorter(
@@ -28,7 +35,7 @@ pub fn extract_fid_docid_facet_values<R: io::Read + io::Seek>(
         indexer.chunk_compression_type,
         indexer.chunk_compression_level,
         indexer.max_nb_chunks,
-        max_memory.map(|m| m / 2),
+        max_memory.map(|m| m / 4),
     );
 
     let mut fid_docid_facet_strings_sorter = create_sorter(
@@ -36,7 +43,23 @@ pub fn extract_fid_docid_facet_values<R: io::Read + io::Seek>(
         indexer.chunk_compression_type,
         indexer.chunk_compression_level,
         indexer.max_nb_chunks,
-        max_memory.map(|m| m / 2),
+        max_memory.map(|m| m / 4),
+    );
+
+    let mut fid_docid_facet_bools_sorter = create_sorter(
+        keep_first,
+        indexer.chunk_compression_type,
+        indexer.chunk_compression_level,
+        indexer.max_nb_chunks,
+        max_memory.map(|m| m / 4),
+    );
+
+    let mut fid_docid_facet_empties_sorter = create_sorter(
+        keep_first,
+        indexer.chunk_compression_type,
+        indexer.chunk_compression_level,
+        indexer.max_nb_chunks,
+        max_memory.map(|m| m / 4),
     );
 
     let mut key_buffer = Vec::new();
@@ -48,7 +71,15 @@ pub fn extract_fid_docid_facet_values<R: io::Read + io::Seek>(
             if faceted_fields.contains(&field_id) {
                 let value =
                     serde_json::from_slice(field_bytes).map_err(InternalError::SerdeJson)?;
-                let (numbers, strings) = extract_facet_values(&value);
+                let (numbers, mut strings, bools) = extract_facet_values(&value);
+                if let Some(mapping) = facet_value_mappings.get(&field_id) {
+                    for (normalized, original) in strings.iter_mut() {
+                        if let Some(canonical) = mapping.get(normalized.as_str()) {
+                            *normalized = canonical.trim().to_lowercase();
+                            *original = canonical.clone();
+                        }
+                    }
+                }
 
                 key_buffer.clear();
 
@@ -56,6 +87,14 @@ pub fn extract_fid_docid_facet_values<R: io::Read + io::Seek>(
                 key_buffer.extend_from_slice(&field_id.to_be_bytes());
                 key_buffer.extend_from_slice(&docid_bytes);
 
+                // the field is in the document but without any value, e.g. an empty array
+                if numbers.is_empty()
+                    && bools.is_empty()
+                    && strings.iter().all(|(n, _)| n.is_empty())
+                {
+                    fid_docid_facet_empties_sorter.insert(&key_buffer, ().as_bytes())?;
+                }
+
                 // insert facet numbers in sorter
                 for number in numbers {
                     key_buffer.truncate(size_of::<FieldId>() + size_of::<DocumentId>());
@@ -73,26 +112,36 @@ pub fn extract_fid_docid_facet_values<R: io::Read + io::Seek>(
                     key_buffer.extend_from_slice(normalized.as_bytes());
                     fid_docid_facet_strings_sorter.insert(&key_buffer, original.as_bytes())?;
                 }
+
+                // insert facet booleans in sorter
+                for boolean in bools {
+                    key_buffer.truncate(size_of::<FieldId>() + size_of::<DocumentId>());
+                    key_buffer.push(boolean as u8);
+                    fid_docid_facet_bools_sorter.insert(&key_buffer, ().as_bytes())?;
+                }
             }
         }
     }
 
     Ok((
         sorter_into_reader(fid_docid_facet_numbers_sorter, indexer.clone())?,
-        sorter_into_reader(fid_docid_facet_strings_sorter, indexer)?,
+        sorter_into_reader(fid_docid_facet_strings_sorter, indexer.clone())?,
+        sorter_into_reader(fid_docid_facet_bools_sorter, indexer.clone())?,
+        sorter_into_reader(fid_docid_facet_empties_sorter, indexer)?,
     ))
 }
 
-fn extract_facet_values(value: &Value) -> (Vec<f64>, Vec<(String, String)>) {
+fn extract_facet_values(value: &Value) -> (Vec<f64>, Vec<(String, String)>, Vec<bool>) {
     fn inner_extract_facet_values(
         value: &Value,
         can_recurse: bool,
         output_numbers: &mut Vec<f64>,
         output_strings: &mut Vec<(String, String)>,
+        output_bools: &mut Vec<bool>,
     ) {
         match value {
             Value::Null => (),
-            Value::Bool(b) => output_strings.push((b.to_string(), b.to_string())),
+            Value::Bool(b) => output_bools.push(*b),
             Value::Number(number) => {
                 if let Some(float) = number.as_f64() {
                     output_numbers.push(float);
@@ -105,7 +154,13 @@ fn extract_facet_values(value: &Value) -> (Vec<f64>, Vec<(String, String)>) {
             Value::Array(values) => {
                 if can_recurse {
                     for value in values {
-                        inner_extract_facet_values(value, false, output_numbers, output_strings);
+                        inner_extract_facet_values(
+                            value,
+                            false,
+                            output_numbers,
+                            output_strings,
+                            output_bools,
+                        );
                     }
                 }
             }
@@ -115,7 +170,14 @@ fn extract_facet_values(value: &Value) -> (Vec<f64>, Vec<(String, String)>) {
 
     let mut facet_number_values = Vec::new();
     let mut facet_string_values = Vec::new();
-    inner_extract_facet_values(value, true, &mut facet_number_values, &mut facet_string_values);
+    let mut facet_bool_values = Vec::new();
+    inner_extract_facet_values(
+        value,
+        true,
+        &mut facet_number_values,
+        &mut facet_string_values,
+        &mut facet_bool_values,
+    );
 
-    (facet_number_values, facet_string_values)
+    (facet_number_values, facet_string_values, facet_bool_values)
 }
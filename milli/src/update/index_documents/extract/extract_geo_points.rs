@@ -9,14 +9,25 @@ use super::helpers::{create_writer, writer_into_reader, GrenadParameters};
 use crate::error::GeoError;
 use crate::{FieldId, InternalError, Result};
 
-/// Extracts the geographical coordinates contained in each document under the `_geo` field.
+/// The ids of the flattened fields the geo point of a document can be read from, either
+/// `_geo.lat` and `_geo.lng` or the `_geo.type` and `_geo.coordinates` of a GeoJSON point.
+#[derive(Debug, Clone, Copy)]
+pub struct GeoFieldsIds {
+    pub lat: FieldId,
+    pub lng: FieldId,
+    pub geojson_type: FieldId,
+    pub geojson_coordinates: FieldId,
+}
+
+/// Extracts the geographical coordinates contained in each document under the `_geo` field,
+/// as `{ "lat": 45, "lng": 5 }` or as a GeoJSON `{ "type": "Point", "coordinates": [5, 45] }`.
 ///
 /// Returns the generated grenad reader containing the docid as key associated to the (latitude, longitude)
 pub fn extract_geo_points<R: io::Read + io::Seek>(
     obkv_documents: grenad::Reader<R>,
     indexer: GrenadParameters,
     primary_key_id: FieldId,
-    (lat_fid, lng_fid): (FieldId, FieldId),
+    geo_fields_ids: GeoFieldsIds,
 ) -> Result<grenad::Reader<File>> {
     let mut writer = create_writer(
         indexer.chunk_compression_type,
@@ -27,18 +38,38 @@ pub fn extract_geo_points<R: io::Read + io::Seek>(
     let mut cursor = obkv_documents.into_cursor()?;
     while let Some((docid_bytes, value)) = cursor.move_on_next()? {
         let obkv = obkv::KvReader::new(value);
-        // since we only needs the primary key when we throw an error we create this getter to
-        // lazily get it when needed
-        let primary_key = || -> Value {
-            let primary_key = obkv.get(primary_key_id).unwrap();
-            serde_json::from_slice(primary_key).unwrap()
-        };
+        if let Some((lat, lng)) = extract_geo_point(obkv, primary_key_id, geo_fields_ids)? {
+            let bytes: [u8; 16] = concat_arrays![lat.to_ne_bytes(), lng.to_ne_bytes()];
+            writer.insert(docid_bytes, bytes)?;
+        }
+    }
 
-        // first we get the two fields
-        let lat = obkv.get(lat_fid);
-        let lng = obkv.get(lng_fid);
+    Ok(writer_into_reader(writer)?)
+}
+
+/// Returns the (latitude, longitude) of a flattened document, `None` if it doesn't have a
+/// geo point and an error if its geo point is invalid.
+pub fn extract_geo_point(
+    obkv: obkv::KvReader<FieldId>,
+    primary_key_id: FieldId,
+    geo_fields_ids: GeoFieldsIds,
+) -> Result<Option<(f64, f64)>> {
+    let GeoFieldsIds { lat: lat_fid, lng: lng_fid, geojson_type, geojson_coordinates } =
+        geo_fields_ids;
 
-        if let Some((lat, lng)) = lat.zip(lng) {
+    // since we only needs the primary key when we throw an error we create this getter to
+    // lazily get it when needed
+    let primary_key = || -> Value {
+        let primary_key = obkv.get(primary_key_id).unwrap();
+        serde_json::from_slice(primary_key).unwrap()
+    };
+
+    // first we get the two fields
+    let lat = obkv.get(lat_fid);
+    let lng = obkv.get(lng_fid);
+
+    let point = match (lat, lng) {
+        (Some(lat), Some(lng)) => {
             // then we extract the values
             let lat = extract_float_from_value(
                 serde_json::from_slice(lat).map_err(InternalError::SerdeJson)?,
@@ -49,17 +80,52 @@ pub fn extract_geo_points<R: io::Read + io::Seek>(
                 serde_json::from_slice(lng).map_err(InternalError::SerdeJson)?,
             )
             .map_err(|lng| GeoError::BadLongitude { document_id: primary_key(), value: lng })?;
-
-            let bytes: [u8; 16] = concat_arrays![lat.to_ne_bytes(), lng.to_ne_bytes()];
-            writer.insert(docid_bytes, bytes)?;
-        } else if lat.is_none() && lng.is_some() {
-            return Err(GeoError::MissingLatitude { document_id: primary_key() })?;
-        } else if lat.is_some() && lng.is_none() {
-            return Err(GeoError::MissingLongitude { document_id: primary_key() })?;
+            (lat, lng)
         }
+        (None, Some(_)) => return Err(GeoError::MissingLatitude { document_id: primary_key() })?,
+        (Some(_), None) => return Err(GeoError::MissingLongitude { document_id: primary_key() })?,
+        (None, None) => match obkv.get(geojson_type) {
+            Some(geometry) => {
+                let geometry: Value =
+                    serde_json::from_slice(geometry).map_err(InternalError::SerdeJson)?;
+                if geometry != Value::from("Point") {
+                    return Err(GeoError::UnsupportedGeometry {
+                        document_id: primary_key(),
+                        geometry,
+                    })?;
+                }
+                let coordinates = match obkv.get(geojson_coordinates) {
+                    Some(coordinates) => {
+                        serde_json::from_slice(coordinates).map_err(InternalError::SerdeJson)?
+                    }
+                    None => Value::Null,
+                };
+                extract_lat_lng_from_coordinates(coordinates).map_err(|value| {
+                    GeoError::BadCoordinates { document_id: primary_key(), value }
+                })?
+            }
+            None => return Ok(None),
+        },
+    };
+
+    let (lat, lng) = point;
+    // a non-finite number is never contained in these ranges.
+    if !(-90.0..=90.0).contains(&lat) {
+        return Err(GeoError::LatitudeOutOfRange { document_id: primary_key(), value: lat })?;
+    }
+    if !(-180.0..=180.0).contains(&lng) {
+        return Err(GeoError::LongitudeOutOfRange { document_id: primary_key(), value: lng })?;
     }
+    Ok(Some(point))
+}
 
-    Ok(writer_into_reader(writer)?)
+/// Returns the latitude and longitude of GeoJSON coordinates, that are in the reverse order.
+fn extract_lat_lng_from_coordinates(coordinates: Value) -> StdResult<(f64, f64), Value> {
+    let point = match coordinates.as_array().map(Vec::as_slice) {
+        Some([lng, lat]) => lat.as_f64().zip(lng.as_f64()),
+        _ => None,
+    };
+    point.ok_or(coordinates)
 }
 
 fn extract_float_from_value(value: Value) -> StdResult<f64, Value> {
@@ -8,7 +8,7 @@ mod extract_word_docids;
 mod extract_word_pair_proximity_docids;
 mod extract_word_position_docids;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 
 use crossbeam_channel::Sender;
@@ -21,6 +21,7 @@ use self::extract_facet_string_docids::extract_facet_string_docids;
 use self::extract_fid_docid_facet_values::extract_fid_docid_facet_values;
 use self::extract_fid_word_count_docids::extract_fid_word_count_docids;
 use self::extract_geo_points::extract_geo_points;
+pub(crate) use self::extract_geo_points::{extract_geo_point, GeoFieldsIds};
 use self::extract_word_docids::extract_word_docids;
 use self::extract_word_pair_proximity_docids::extract_word_pair_proximity_docids;
 use self::extract_word_position_docids::extract_word_position_docids;
@@ -29,7 +30,7 @@ use super::helpers::{
     merge_roaring_bitmaps, CursorClonableMmap, GrenadParameters, MergeFn, MergeableReader,
 };
 use super::{helpers, TypedChunk};
-use crate::{FieldId, Result};
+use crate::{DisabledDatabases, FieldId, Result, Segmentation};
 
 /// Extract data for each databases from obkv documents in parallel.
 /// Send data in grenad file over provided Sender.
@@ -40,11 +41,14 @@ pub(crate) fn data_from_obkv_documents(
     lmdb_writer_sx: Sender<Result<TypedChunk>>,
     searchable_fields: Option<HashSet<FieldId>>,
     faceted_fields: HashSet<FieldId>,
+    facet_value_mappings: HashMap<FieldId, HashMap<String, String>>,
     primary_key_id: FieldId,
-    geo_fields_ids: Option<(FieldId, FieldId)>,
+    geo_fields_ids: Option<GeoFieldsIds>,
     stop_words: Option<fst::Set<&[u8]>>,
+    segmentation: Segmentation,
     max_positions_per_attributes: Option<u32>,
     exact_attributes: HashSet<FieldId>,
+    disabled_databases: DisabledDatabases,
 ) -> Result<()> {
     original_obkv_chunks
         .par_bridge()
@@ -62,9 +66,11 @@ pub(crate) fn data_from_obkv_documents(
                 lmdb_writer_sx.clone(),
                 &searchable_fields,
                 &faceted_fields,
+                &facet_value_mappings,
                 primary_key_id,
                 geo_fields_ids,
                 &stop_words,
+                &segmentation,
                 max_positions_per_attributes,
             )
         })
@@ -75,15 +81,17 @@ pub(crate) fn data_from_obkv_documents(
         (docid_fid_facet_numbers_chunks, docid_fid_facet_strings_chunks),
     ) = result?;
 
-    spawn_extraction_task::<_, _, Vec<grenad::Reader<File>>>(
-        docid_word_positions_chunks.clone(),
-        indexer.clone(),
-        lmdb_writer_sx.clone(),
-        extract_word_pair_proximity_docids,
-        merge_cbo_roaring_bitmaps,
-        TypedChunk::WordPairProximityDocids,
-        "word-pair-proximity-docids",
-    );
+    if !disabled_databases.proximity {
+        spawn_extraction_task::<_, _, Vec<grenad::Reader<File>>>(
+            docid_word_positions_chunks.clone(),
+            indexer.clone(),
+            lmdb_writer_sx.clone(),
+            extract_word_pair_proximity_docids,
+            merge_cbo_roaring_bitmaps,
+            TypedChunk::WordPairProximityDocids,
+            "word-pair-proximity-docids",
+        );
+    }
 
     spawn_extraction_task::<_, _, Vec<grenad::Reader<File>>>(
         docid_word_positions_chunks.clone(),
@@ -141,6 +149,45 @@ pub(crate) fn data_from_obkv_documents(
     Ok(())
 }
 
+/// Extract the facet databases of the given faceted fields only from the flattened documents,
+/// the chunks are returned to be written in the index in this order.
+pub(crate) fn facet_data_from_flattened_documents(
+    flattened_documents: grenad::Reader<File>,
+    indexer: GrenadParameters,
+    faceted_fields: &HashSet<FieldId>,
+    facet_value_mappings: &HashMap<FieldId, HashMap<String, String>>,
+) -> Result<Vec<TypedChunk>> {
+    let (
+        docid_fid_facet_numbers_chunk,
+        docid_fid_facet_strings_chunk,
+        docid_fid_facet_bools_chunk,
+        docid_fid_facet_empties_chunk,
+    ) = extract_fid_docid_facet_values(
+        flattened_documents,
+        indexer,
+        faceted_fields,
+        facet_value_mappings,
+    )?;
+    let docid_fid_facet_numbers_chunk =
+        unsafe { as_cloneable_grenad(&docid_fid_facet_numbers_chunk)? };
+    let docid_fid_facet_strings_chunk =
+        unsafe { as_cloneable_grenad(&docid_fid_facet_strings_chunk)? };
+
+    let facet_number_docids =
+        extract_facet_number_docids(docid_fid_facet_numbers_chunk.clone(), indexer)?;
+    let facet_string_docids =
+        extract_facet_string_docids(docid_fid_facet_strings_chunk.clone(), indexer)?;
+
+    Ok(vec![
+        TypedChunk::FieldIdDocidFacetNumbers(docid_fid_facet_numbers_chunk),
+        TypedChunk::FieldIdDocidFacetStrings(docid_fid_facet_strings_chunk),
+        TypedChunk::FieldIdDocidFacetBools(docid_fid_facet_bools_chunk),
+        TypedChunk::FieldIdDocidFacetEmpties(docid_fid_facet_empties_chunk),
+        TypedChunk::FieldIdFacetNumberDocids(facet_number_docids),
+        TypedChunk::FieldIdFacetStringDocids(facet_string_docids),
+    ])
+}
+
 /// Spawn a new task to extract data for a specific DB using extract_fn.
 /// Generated grenad chunks are merged using the merge_fn.
 /// The result of merged chunks is serialized as TypedChunk using the serialize_fn
@@ -195,17 +242,22 @@ fn send_original_documents_data(
 /// Extract chunked data and send it into lmdb_writer_sx sender:
 /// - documents_ids
 /// - docid_word_positions
+/// - stop_word_docids
 /// - docid_fid_facet_numbers
 /// - docid_fid_facet_strings
+/// - docid_fid_facet_bools
+/// - docid_fid_facet_empties
 fn send_and_extract_flattened_documents_data(
     flattened_documents_chunk: Result<grenad::Reader<File>>,
     indexer: GrenadParameters,
     lmdb_writer_sx: Sender<Result<TypedChunk>>,
     searchable_fields: &Option<HashSet<FieldId>>,
     faceted_fields: &HashSet<FieldId>,
+    facet_value_mappings: &HashMap<FieldId, HashMap<String, String>>,
     primary_key_id: FieldId,
-    geo_fields_ids: Option<(FieldId, FieldId)>,
+    geo_fields_ids: Option<GeoFieldsIds>,
     stop_words: &Option<fst::Set<&[u8]>>,
+    segmentation: &Segmentation,
     max_positions_per_attributes: Option<u32>,
 ) -> Result<(
     grenad::Reader<CursorClonableMmap>,
@@ -230,13 +282,15 @@ fn send_and_extract_flattened_documents_data(
     let (docid_word_positions_chunk, docid_fid_facet_values_chunks): (Result<_>, Result<_>) =
         rayon::join(
             || {
-                let (documents_ids, docid_word_positions_chunk) = extract_docid_word_positions(
-                    flattened_documents_chunk.clone(),
-                    indexer.clone(),
-                    searchable_fields,
-                    stop_words.as_ref(),
-                    max_positions_per_attributes,
-                )?;
+                let (documents_ids, docid_word_positions_chunk, stop_word_docids_chunk) =
+                    extract_docid_word_positions(
+                        flattened_documents_chunk.clone(),
+                        indexer.clone(),
+                        searchable_fields,
+                        stop_words.as_ref(),
+                        segmentation,
+                        max_positions_per_attributes,
+                    )?;
 
                 // send documents_ids to DB writer
                 let _ = lmdb_writer_sx.send(Ok(TypedChunk::NewDocumentsIds(documents_ids)));
@@ -247,15 +301,23 @@ fn send_and_extract_flattened_documents_data(
                 let _ = lmdb_writer_sx
                     .send(Ok(TypedChunk::DocidWordPositions(docid_word_positions_chunk.clone())));
 
+                // send stop_word_docids_chunk to DB writer
+                let _ = lmdb_writer_sx.send(Ok(TypedChunk::StopWordDocids(stop_word_docids_chunk)));
+
                 Ok(docid_word_positions_chunk)
             },
             || {
-                let (docid_fid_facet_numbers_chunk, docid_fid_facet_strings_chunk) =
-                    extract_fid_docid_facet_values(
-                        flattened_documents_chunk.clone(),
-                        indexer.clone(),
-                        faceted_fields,
-                    )?;
+                let (
+                    docid_fid_facet_numbers_chunk,
+                    docid_fid_facet_strings_chunk,
+                    docid_fid_facet_bools_chunk,
+                    docid_fid_facet_empties_chunk,
+                ) = extract_fid_docid_facet_values(
+                    flattened_documents_chunk.clone(),
+                    indexer.clone(),
+                    faceted_fields,
+                    facet_value_mappings,
+                )?;
 
                 // send docid_fid_facet_numbers_chunk to DB writer
                 let docid_fid_facet_numbers_chunk =
@@ -273,6 +335,14 @@ fn send_and_extract_flattened_documents_data(
                     docid_fid_facet_strings_chunk.clone(),
                 )));
 
+                // send docid_fid_facet_bools_chunk to DB writer
+                let _ = lmdb_writer_sx
+                    .send(Ok(TypedChunk::FieldIdDocidFacetBools(docid_fid_facet_bools_chunk)));
+
+                // send docid_fid_facet_empties_chunk to DB writer
+                let _ = lmdb_writer_sx
+                    .send(Ok(TypedChunk::FieldIdDocidFacetEmpties(docid_fid_facet_empties_chunk)));
+
                 Ok((docid_fid_facet_numbers_chunk, docid_fid_facet_strings_chunk))
             },
         );
@@ -85,6 +85,15 @@ pub fn keep_latest_obkv<'a>(_key: &[u8], obkvs: &[Cow<'a, [u8]>]) -> Result<Cow<
     Ok(obkvs.last().unwrap().clone())
 }
 
+/// Only keeps the json of a document when it isn't merged with other versions of it, the
+/// json of a merged document is left empty.
+pub fn keep_unmerged_json<'a>(_key: &[u8], jsons: &[Cow<'a, [u8]>]) -> Result<Cow<'a, [u8]>> {
+    match jsons {
+        [json] => Ok(json.clone()),
+        _ => Ok(Cow::Borrowed(&[])),
+    }
+}
+
 /// Merge all the obks in the order we see them.
 pub fn merge_obkvs<'a>(_key: &[u8], obkvs: &[Cow<'a, [u8]>]) -> Result<Cow<'a, [u8]>> {
     Ok(obkvs
@@ -116,6 +125,64 @@ pub fn merge_two_obkvs(base: obkv::KvReaderU16, update: obkv::KvReaderU16, buffe
     writer.finish().unwrap();
 }
 
+/// Merge all the obkvs in the order we see them, the arrays found
+/// in both versions of a field are unioned without duplicates.
+pub fn merge_obkvs_and_arrays<'a>(_key: &[u8], obkvs: &[Cow<'a, [u8]>]) -> Result<Cow<'a, [u8]>> {
+    Ok(obkvs
+        .into_iter()
+        .cloned()
+        .reduce(|acc, current| {
+            let first = obkv::KvReader::new(&acc);
+            let second = obkv::KvReader::new(&current);
+            let mut buffer = Vec::new();
+            merge_two_obkvs_and_arrays(first, second, &mut buffer);
+            Cow::from(buffer)
+        })
+        .unwrap())
+}
+
+/// Merge two obkvs like `merge_two_obkvs` does, except that when the base and the
+/// update values of a field are both arrays, the update values that are not already
+/// in the base array are appended to it. A scalar value always replaces an array
+/// value and an array value always replaces a scalar one.
+pub fn merge_two_obkvs_and_arrays(
+    base: obkv::KvReaderU16,
+    update: obkv::KvReaderU16,
+    buffer: &mut Vec<u8>,
+) {
+    use itertools::merge_join_by;
+    use itertools::EitherOrBoth::{Both, Left, Right};
+    use serde_json::Value;
+
+    buffer.clear();
+
+    let mut writer = obkv::KvWriter::new(buffer);
+    for eob in merge_join_by(base.iter(), update.iter(), |(b, _), (u, _)| b.cmp(u)) {
+        match eob {
+            Both((k, base_value), (_, update_value)) => {
+                match (
+                    serde_json::from_slice::<Value>(base_value),
+                    serde_json::from_slice(update_value),
+                ) {
+                    (Ok(Value::Array(mut values)), Ok(Value::Array(new_values))) => {
+                        for value in new_values {
+                            if !values.contains(&value) {
+                                values.push(value);
+                            }
+                        }
+                        let value = serde_json::to_vec(&values).unwrap();
+                        writer.insert(k, value).unwrap();
+                    }
+                    _ => writer.insert(k, update_value).unwrap(),
+                }
+            }
+            Left((k, v)) | Right((k, v)) => writer.insert(k, v).unwrap(),
+        }
+    }
+
+    writer.finish().unwrap();
+}
+
 pub fn merge_cbo_roaring_bitmaps<'a>(
     _key: &[u8],
     values: &[Cow<'a, [u8]>],
@@ -14,8 +14,9 @@ pub use grenad_helpers::{
 };
 pub use merge_functions::{
     concat_u32s_array, keep_first, keep_first_prefix_value_merge_roaring_bitmaps, keep_latest_obkv,
-    merge_cbo_roaring_bitmaps, merge_obkvs, merge_roaring_bitmaps, merge_two_obkvs,
-    roaring_bitmap_from_u32s_array, serialize_roaring_bitmap, MergeFn,
+    keep_unmerged_json, merge_cbo_roaring_bitmaps, merge_obkvs, merge_obkvs_and_arrays,
+    merge_roaring_bitmaps, merge_two_obkvs, roaring_bitmap_from_u32s_array,
+    serialize_roaring_bitmap, MergeFn,
 };
 
 pub fn valid_lmdb_key(key: impl AsRef<[u8]>) -> bool {
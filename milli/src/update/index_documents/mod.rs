@@ -9,7 +9,7 @@ use std::iter::FromIterator;
 use std::num::{NonZeroU32, NonZeroUsize};
 
 use crossbeam_channel::{Receiver, Sender};
-use heed::types::Str;
+use heed::types::{ByteSlice, Str};
 use heed::Database;
 use log::debug;
 use roaring::RoaringBitmap;
@@ -24,14 +24,20 @@ pub use self::helpers::{
     ClonableMmap, MergeFn,
 };
 use self::helpers::{grenad_obkv_into_chunks, GrenadParameters};
+pub(crate) use self::transform::validate_document_id;
 pub use self::transform::{Transform, TransformOutput};
 use crate::documents::DocumentBatchReader;
+use crate::error::InternalError;
+use crate::index::db_name;
 pub use crate::update::index_documents::helpers::CursorClonableMmap;
 use crate::update::{
     self, Facets, IndexerConfig, UpdateIndexingStep, WordPrefixDocids,
     WordPrefixPairProximityDocids, WordPrefixPositionDocids, WordsPrefixesFst,
 };
-use crate::{Index, Result, RoaringBitmapCodec, UserError};
+use crate::{
+    obkv_to_json_bytes, FieldId, FieldsIdsMap, Index, Result, RoaringBitmapCodec, Segmentation,
+    UserError,
+};
 
 static MERGED_DATABASE_COUNT: usize = 7;
 static PREFIX_DATABASE_COUNT: usize = 5;
@@ -43,6 +49,38 @@ pub struct DocumentAdditionResult {
     pub indexed_documents: u64,
     /// The total number of documents in the index after the update
     pub number_of_documents: u64,
+    /// What the update would have changed in the index, only computed in dry run mode
+    pub summary: Option<DocumentAdditionSummary>,
+    /// The documents that were not indexed, only filled when the invalid documents are skipped
+    pub rejected_documents: Vec<RejectedDocument>,
+}
+
+/// A document skipped because it is invalid, e.g. because of its id or its geo point.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectedDocument {
+    /// The index of the batch the document was added with, among the non-empty batches
+    /// added to the builder
+    pub batch: usize,
+    /// The position of the document in the batch it was added with
+    pub position: usize,
+    /// The error that rejected the document
+    pub reason: String,
+}
+
+impl RejectedDocument {
+    fn new(batch: usize, position: usize, error: UserError) -> RejectedDocument {
+        RejectedDocument { batch, position, reason: error.to_string() }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentAdditionSummary {
+    /// The number of documents that are not in the index yet
+    pub added: u64,
+    /// The number of documents that replace or update a document of the index
+    pub updated: u64,
+    /// The fields that are not in the index yet, sorted by name
+    pub new_fields: Vec<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -55,6 +93,33 @@ pub enum IndexDocumentsMethod {
     /// Merge the previous version of the document with the new version,
     /// replacing old attributes values with the new ones and add the new attributes.
     UpdateDocuments,
+
+    /// Merge the previous version of the document with the new version like
+    /// `UpdateDocuments` does, except that when both versions of an attribute are
+    /// arrays, the new values that are not already in the old array are appended to it.
+    ///
+    /// When only one of the versions is an array, the new value replaces the old one,
+    /// whether it is a scalar replacing an array or an array replacing a scalar.
+    UpdateDocumentsMergeArrays,
+}
+
+/// What to do with the documents of a batch that have the same primary key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DuplicatePrimaryKeys {
+    /// The batch is rejected with the id and the positions of the first two documents that
+    /// share it, only the next documents are rejected when the invalid documents are skipped.
+    Error,
+    /// The first document is kept, the next ones with the same id are ignored.
+    KeepFirst,
+    /// The documents are applied in the order of the batch, the last one replaces the
+    /// previous ones or is merged into them, depending on the update method.
+    KeepLast,
+}
+
+impl Default for DuplicatePrimaryKeys {
+    fn default() -> Self {
+        Self::KeepLast
+    }
 }
 
 impl Default for IndexDocumentsMethod {
@@ -71,6 +136,8 @@ pub struct IndexDocuments<'t, 'u, 'i, 'a, F> {
     transform: Option<Transform<'a, 'i>>,
     progress: F,
     added_documents: u64,
+    /// The fields ids map and primary key to restore at the end of a dry run.
+    previous_state: Option<(FieldsIdsMap, Option<String>)>,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -83,6 +150,11 @@ pub struct IndexDocumentsConfig {
     pub words_positions_min_level_size: Option<NonZeroU32>,
     pub update_method: IndexDocumentsMethod,
     pub autogenerate_docids: bool,
+    pub duplicate_primary_keys: DuplicatePrimaryKeys,
+    /// Skips the documents with an invalid id or geo point and reports them in the result,
+    /// instead of rejecting the whole update.
+    pub skip_invalid_documents: bool,
+    pub dry_run: bool,
 }
 
 impl<'t, 'u, 'i, 'a, F> IndexDocuments<'t, 'u, 'i, 'a, F>
@@ -96,13 +168,24 @@ where
         config: IndexDocumentsConfig,
         progress: F,
     ) -> Result<IndexDocuments<'t, 'u, 'i, 'a, F>> {
-        let transform = Some(Transform::new(
+        let mut transform = Transform::new(
             wtxn,
             &index,
             indexer_config,
             config.update_method,
             config.autogenerate_docids,
-        )?);
+        )?;
+        transform.duplicate_primary_keys = config.duplicate_primary_keys;
+        transform.skip_invalid_documents = config.skip_invalid_documents;
+        let transform = Some(transform);
+
+        let previous_state = if config.dry_run {
+            let fields_ids_map = index.fields_ids_map(wtxn)?;
+            let primary_key = index.primary_key(wtxn)?.map(String::from);
+            Some((fields_ids_map, primary_key))
+        } else {
+            None
+        };
 
         Ok(IndexDocuments {
             transform,
@@ -112,6 +195,7 @@ where
             wtxn,
             index,
             added_documents: 0,
+            previous_state,
         })
     }
 
@@ -144,15 +228,21 @@ where
 
     #[logging_timer::time("IndexDocuments::{}")]
     pub fn execute(mut self) -> Result<DocumentAdditionResult> {
+        if self.config.dry_run {
+            return self.execute_dry_run();
+        }
+        let mut transform = self.transform.take().expect("Invalid document addition state");
+        let rejected_documents = std::mem::take(&mut transform.rejected_documents);
         if self.added_documents == 0 {
             let number_of_documents = self.index.number_of_documents(self.wtxn)?;
-            return Ok(DocumentAdditionResult { indexed_documents: 0, number_of_documents });
+            return Ok(DocumentAdditionResult {
+                indexed_documents: 0,
+                number_of_documents,
+                summary: None,
+                rejected_documents,
+            });
         }
-        let output = self
-            .transform
-            .take()
-            .expect("Invalid document addition state")
-            .output_from_sorter(self.wtxn, &self.progress)?;
+        let output = transform.output_from_sorter(self.wtxn, &self.progress)?;
 
         let new_facets = output.compute_real_facets(self.wtxn, self.index)?;
         self.index.put_faceted_fields(self.wtxn, &new_facets)?;
@@ -172,7 +262,41 @@ where
         let indexed_documents = output.documents_count as u64;
         let number_of_documents = self.execute_raw(output)?;
 
-        Ok(DocumentAdditionResult { indexed_documents, number_of_documents })
+        Ok(DocumentAdditionResult {
+            indexed_documents,
+            number_of_documents,
+            summary: None,
+            rejected_documents,
+        })
+    }
+
+    /// Computes what the added documents would change in the index without indexing them.
+    ///
+    /// Reading the documents stored the new fields and the primary key in the transaction,
+    /// they are restored to leave the index as it was before the update.
+    fn execute_dry_run(mut self) -> Result<DocumentAdditionResult> {
+        let mut transform = self.transform.take().expect("Invalid document addition state");
+        let rejected_documents = std::mem::take(&mut transform.rejected_documents);
+        let (fields_ids_map, primary_key) =
+            self.previous_state.take().expect("Invalid document addition state");
+
+        let summary = transform.summary(&fields_ids_map);
+
+        self.index.put_fields_ids_map(self.wtxn, &fields_ids_map)?;
+        match primary_key {
+            Some(primary_key) => self.index.put_primary_key(self.wtxn, &primary_key)?,
+            None => {
+                self.index.delete_primary_key(self.wtxn)?;
+            }
+        }
+
+        let number_of_documents = self.index.number_of_documents(self.wtxn)?;
+        Ok(DocumentAdditionResult {
+            indexed_documents: 0,
+            number_of_documents,
+            summary: Some(summary),
+            rejected_documents,
+        })
     }
 
     /// Returns the total number of documents in the index after the update.
@@ -191,6 +315,7 @@ where
             documents_count,
             original_documents,
             flattened_documents,
+            json_documents,
         } = output;
 
         // The fields_ids_map is put back to the store now so the rest of the transaction sees an
@@ -233,28 +358,13 @@ where
             self.index.searchable_fields_ids(self.wtxn)?.map(HashSet::from_iter);
         // get filterable fields for facet databases
         let faceted_fields = self.index.faceted_fields_ids(self.wtxn)?;
-        // get the fid of the `_geo.lat` and `_geo.lng` fields.
-        let geo_fields_ids = match self.index.fields_ids_map(self.wtxn)?.id("_geo") {
-            Some(gfid) => {
-                let is_sortable = self.index.sortable_fields_ids(self.wtxn)?.contains(&gfid);
-                let is_filterable = self.index.filterable_fields_ids(self.wtxn)?.contains(&gfid);
-                // if `_geo` is faceted then we get the `lat` and `lng`
-                if is_sortable || is_filterable {
-                    let field_ids = self
-                        .index
-                        .fields_ids_map(self.wtxn)?
-                        .insert("_geo.lat")
-                        .zip(self.index.fields_ids_map(self.wtxn)?.insert("_geo.lng"))
-                        .ok_or(UserError::AttributeLimitReached)?;
-                    Some(field_ids)
-                } else {
-                    None
-                }
-            }
-            None => None,
-        };
+        let facet_value_mappings = self.index.facet_value_mappings_ids(self.wtxn)?;
+        let disabled_databases = self.index.disabled_databases(self.wtxn)?;
+        let geo_fields_ids =
+            faceted_geo_fields_ids(self.index, self.wtxn, &mut fields_ids_map.clone())?;
 
         let stop_words = self.index.stop_words(self.wtxn)?;
+        let segmentation = Segmentation::new(self.wtxn, self.index)?;
         let exact_attributes = self.index.exact_attributes_ids(self.wtxn)?;
 
         // Run extraction pipeline in parallel.
@@ -291,11 +401,14 @@ where
                         lmdb_writer_sx.clone(),
                         searchable_fields,
                         faceted_fields,
+                        facet_value_mappings,
                         primary_key_id,
                         geo_fields_ids,
                         stop_words,
+                        segmentation,
                         self.indexer_config.max_positions_per_attributes,
                         exact_attributes,
+                        disabled_databases,
                     )
                 });
 
@@ -354,8 +467,13 @@ where
                 otherwise => otherwise,
             };
 
-            let (docids, is_merged_database) =
-                write_typed_chunk_into_index(typed_chunk, &self.index, self.wtxn, index_is_empty)?;
+            let (docids, is_merged_database) = write_typed_chunk_into_index(
+                typed_chunk,
+                &self.index,
+                self.wtxn,
+                index_is_empty,
+                self.indexer_config.geo_rtree_rebuild_threshold,
+            )?;
             if !docids.is_empty() {
                 final_documents_ids |= docids;
                 let documents_seen_count = final_documents_ids.len();
@@ -377,6 +495,28 @@ where
             }
         }
 
+        // The json of the documents is written once their obkv is, the documents that don't
+        // have their json bytes get it converted from their obkv.
+        if let Some(json_documents) = json_documents {
+            let documents = self.index.documents.remap_key_type::<ByteSlice>();
+            let documents_json = self.index.documents_json.remap_key_type::<ByteSlice>();
+            let mut cursor = grenad::Reader::new(json_documents)?.into_cursor()?;
+            while let Some((key, json)) = cursor.move_on_next()? {
+                if json.is_empty() {
+                    let obkv = documents.get(self.wtxn, key)?.ok_or(
+                        InternalError::DatabaseMissingEntry {
+                            db_name: db_name::DOCUMENTS,
+                            key: None,
+                        },
+                    )?;
+                    let json = obkv_to_json_bytes(&fields_ids_map, obkv)?;
+                    documents_json.put(self.wtxn, key, &json)?;
+                } else {
+                    documents_json.put(self.wtxn, key, json)?;
+                }
+            }
+        }
+
         // We write the field distribution into the main database
         self.index.put_field_distribution(self.wtxn, &field_distribution)?;
 
@@ -399,6 +539,53 @@ where
         Ok(all_documents_ids.len())
     }
 
+    /// Only builds the facet databases of the given fields from the documents of the output,
+    /// the documents are already indexed and their other databases are kept as they are.
+    #[logging_timer::time("IndexDocuments::{}")]
+    pub(crate) fn execute_facets(
+        self,
+        output: TransformOutput,
+        faceted_fields: HashSet<FieldId>,
+    ) -> Result<()> {
+        self.index.put_fields_ids_map(self.wtxn, &output.fields_ids_map)?;
+
+        let params = GrenadParameters {
+            chunk_compression_type: self.indexer_config.chunk_compression_type,
+            chunk_compression_level: self.indexer_config.chunk_compression_level,
+            max_memory: self.indexer_config.max_memory,
+            max_nb_chunks: self.indexer_config.max_nb_chunks,
+        };
+        let flattened_documents = grenad::Reader::new(output.flattened_documents)?;
+        let facet_value_mappings = self.index.facet_value_mappings_ids(self.wtxn)?;
+        let typed_chunks = extract::facet_data_from_flattened_documents(
+            flattened_documents,
+            params,
+            &faceted_fields,
+            &facet_value_mappings,
+        )?;
+        for typed_chunk in typed_chunks {
+            write_typed_chunk_into_index(
+                typed_chunk,
+                self.index,
+                self.wtxn,
+                false,
+                self.indexer_config.geo_rtree_rebuild_threshold,
+            )?;
+        }
+
+        let mut builder = Facets::new(self.wtxn, self.index);
+        builder.chunk_compression_type = self.indexer_config.chunk_compression_type;
+        builder.chunk_compression_level = self.indexer_config.chunk_compression_level;
+        if let Some(value) = self.config.facet_level_group_size {
+            builder.level_group_size(value);
+        }
+        if let Some(value) = self.config.facet_min_level_size {
+            builder.min_level_size(value);
+        }
+        builder.fields_ids(faceted_fields);
+        builder.execute()
+    }
+
     #[logging_timer::time("IndexDocuments::{}")]
     pub fn execute_prefix_databases(
         self,
@@ -431,6 +618,64 @@ where
             total_databases: TOTAL_POSTING_DATABASE_COUNT,
         });
 
+        self.execute_words_prefixes_databases(
+            databases_seen,
+            word_docids,
+            exact_word_docids,
+            word_pair_proximity_docids,
+            word_position_docids,
+        )
+    }
+
+    /// Only rebuilds the words prefixes fst and the prefix databases from the word databases
+    /// of the index, the documents and the other databases are not impacted. It is used when
+    /// the minimum word size of the prefixes changed.
+    #[logging_timer::time("IndexDocuments::{}")]
+    pub(crate) fn execute_words_prefixes(self) -> Result<()>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        // No document changed, all the prefixes kept by the new prefixes fst stay the same
+        // and the new ones are computed from the word databases.
+        let empty_reader = || -> Result<_> {
+            let writer = create_writer(
+                self.indexer_config.chunk_compression_type,
+                self.indexer_config.chunk_compression_level,
+                tempfile::tempfile()?,
+            );
+            let reader = writer_into_reader(writer)?;
+            unsafe { as_cloneable_grenad(&reader) }
+        };
+
+        let word_docids = empty_reader()?;
+        let exact_word_docids = empty_reader()?;
+        let word_pair_proximity_docids = empty_reader()?;
+        let word_position_docids = empty_reader()?;
+        self.execute_words_prefixes_databases(
+            MERGED_DATABASE_COUNT + 1,
+            Some(word_docids),
+            Some(exact_word_docids),
+            Some(word_pair_proximity_docids),
+            Some(word_position_docids),
+        )
+    }
+
+    fn execute_words_prefixes_databases(
+        self,
+        mut databases_seen: usize,
+        word_docids: Option<grenad::Reader<CursorClonableMmap>>,
+        exact_word_docids: Option<grenad::Reader<CursorClonableMmap>>,
+        word_pair_proximity_docids: Option<grenad::Reader<CursorClonableMmap>>,
+        word_position_docids: Option<grenad::Reader<CursorClonableMmap>>,
+    ) -> Result<()>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        // the words prefixes fst stays empty and the prefix databases are never filled.
+        if self.index.disabled_databases(self.wtxn)?.prefix {
+            return Ok(());
+        }
+
         let previous_words_prefixes_fst =
             self.index.words_prefixes_fst(self.wtxn)?.map_data(|cow| cow.into_owned())?;
 
@@ -442,6 +687,7 @@ where
         if let Some(value) = self.config.max_prefix_length {
             builder.max_prefix_length(value);
         }
+        builder.min_word_size(self.index.min_word_size_for_prefix(self.wtxn)? as usize);
         builder.execute()?;
 
         let current_prefix_fst = self.index.words_prefixes_fst(self.wtxn)?;
@@ -555,6 +801,42 @@ where
     }
 }
 
+/// Rebuilds the databases of the index from the documents it stores, using the current settings,
+/// and returns the number of reindexed documents.
+pub(crate) fn reindex_stored_documents<F>(
+    wtxn: &mut heed::RwTxn,
+    index: &Index,
+    indexer_config: &IndexerConfig,
+    progress: F,
+) -> Result<u64>
+where
+    F: Fn(UpdateIndexingStep) + Sync,
+{
+    if index.number_of_documents(wtxn)? == 0 {
+        return Ok(0);
+    }
+
+    let fields_ids_map = index.fields_ids_map(wtxn)?;
+    let transform =
+        Transform::new(wtxn, index, indexer_config, IndexDocumentsMethod::ReplaceDocuments, false)?;
+    let output = transform.remap_index_documents(wtxn, fields_ids_map.clone(), fields_ids_map)?;
+
+    let new_facets = output.compute_real_facets(wtxn, index)?;
+    index.put_faceted_fields(wtxn, &new_facets)?;
+
+    // The documents are kept in the transform output, the whole database can be cleared.
+    update::ClearDocuments::new(wtxn, index).execute()?;
+
+    let indexing_builder = IndexDocuments::new(
+        wtxn,
+        index,
+        indexer_config,
+        IndexDocumentsConfig::default(),
+        progress,
+    )?;
+    indexing_builder.execute_raw(output)
+}
+
 /// Run the word prefix docids update operation.
 fn execute_word_prefix_docids(
     txn: &mut heed::RwTxn,
@@ -581,6 +863,37 @@ fn execute_word_prefix_docids(
     Ok(())
 }
 
+/// Returns the ids of the `_geo.lat` and `_geo.lng` fields and of the GeoJSON fields when the
+/// `_geo` field is sortable or filterable, they are inserted in the fields ids map if missing.
+fn faceted_geo_fields_ids(
+    index: &Index,
+    rtxn: &heed::RoTxn,
+    fields_ids_map: &mut FieldsIdsMap,
+) -> Result<Option<extract::GeoFieldsIds>> {
+    if fields_ids_map.id("_geo").is_none() || index.disabled_databases(rtxn)?.geo {
+        return Ok(None);
+    }
+    let is_sortable = index.sortable_fields(rtxn)?.contains("_geo");
+    let is_filterable = index.filterable_fields(rtxn)?.contains("_geo");
+    if !is_sortable && !is_filterable {
+        return Ok(None);
+    }
+
+    geo_fields_ids(fields_ids_map).map(Some)
+}
+
+/// Returns the ids of the `_geo.lat` and `_geo.lng` fields and of the GeoJSON fields,
+/// they are inserted in the fields ids map if missing.
+fn geo_fields_ids(fields_ids_map: &mut FieldsIdsMap) -> Result<extract::GeoFieldsIds> {
+    let mut insert = |name| fields_ids_map.insert(name).ok_or(UserError::AttributeLimitReached);
+    Ok(extract::GeoFieldsIds {
+        lat: insert("_geo.lat")?,
+        lng: insert("_geo.lng")?,
+        geojson_type: insert("_geo.type")?,
+        geojson_coordinates: insert("_geo.coordinates")?,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
@@ -591,8 +904,9 @@ mod tests {
 
     use super::*;
     use crate::documents::DocumentBatchBuilder;
+    use crate::index::tests::TempIndex;
     use crate::update::DeleteDocuments;
-    use crate::HashMap;
+    use crate::{DisabledDatabases, HashMap};
 
     #[test]
     fn simple_document_replacement() {
@@ -733,6 +1047,64 @@ mod tests {
         drop(rtxn);
     }
 
+    #[test]
+    fn document_merge_arrays() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        // The arrays of the documents sent in the same batch are unioned.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "tags": ["red", "blue"], "colour": "red" },
+            { "id": 1, "tags": ["blue", "green"], "colour": ["red", "blue"] }
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig {
+            update_method: IndexDocumentsMethod::UpdateDocumentsMergeArrays,
+            ..Default::default()
+        };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The array replaced the scalar colour.
+        let rtxn = index.read_txn().unwrap();
+        let docs = index.documents(&rtxn, Some(0)).unwrap();
+        let (_, doc) = docs[0];
+        let mut doc_iter = doc.iter();
+        assert_eq!(doc_iter.next(), Some((0, &b"1"[..])));
+        assert_eq!(doc_iter.next(), Some((1, &br#"["red","blue","green"]"#[..])));
+        assert_eq!(doc_iter.next(), Some((2, &br#"["red","blue"]"#[..])));
+        assert_eq!(doc_iter.next(), None);
+        drop(rtxn);
+
+        // The arrays of the stored document are unioned with the new ones.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([ { "id": 1, "tags": ["yellow", "red"], "colour": "blue" } ]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // The scalar replaced the colour array.
+        let rtxn = index.read_txn().unwrap();
+        let count = index.number_of_documents(&rtxn).unwrap();
+        assert_eq!(count, 1);
+        let docs = index.documents(&rtxn, Some(0)).unwrap();
+        let (_, doc) = docs[0];
+        let mut doc_iter = doc.iter();
+        assert_eq!(doc_iter.next(), Some((0, &b"1"[..])));
+        assert_eq!(doc_iter.next(), Some((1, &br#"["red","blue","green","yellow"]"#[..])));
+        assert_eq!(doc_iter.next(), Some((2, &br#""blue""#[..])));
+        assert_eq!(doc_iter.next(), None);
+    }
+
     #[test]
     fn not_auto_generated_documents_ids() {
         let path = tempfile::tempdir().unwrap();
@@ -904,15 +1276,39 @@ mod tests {
         // First we send 1 document with an invalid id.
         let mut wtxn = index.write_txn().unwrap();
         // There is a space in the document id.
-        let content = documents!([ { "id": "brume bleue", "name": "kevin" } ]);
+        let content = documents!([
+            { "id": "kevin", "name": "kevin" },
+            { "id": "brume bleue", "name": "kevin" },
+        ]);
         let config = IndexerConfig::default();
         let indexing_config = IndexDocumentsConfig::default();
         let mut builder =
             IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
                 .unwrap();
-        assert!(builder.add_documents(content).is_err());
+        let error = builder.add_documents(content).unwrap_err();
+        assert!(matches!(
+            error,
+            crate::Error::UserError(UserError::InvalidDocumentId { position: Some(1), .. })
+        ));
+        assert!(error.to_string().starts_with(
+            "Document identifier `\"brume bleue\"` of the document at position 1 in the batch is invalid."
+        ));
         wtxn.commit().unwrap();
 
+        // The floats and the too long ids are invalid too.
+        for id in [serde_json::json!(12.5), serde_json::json!("a".repeat(512))] {
+            let mut wtxn = index.write_txn().unwrap();
+            let content = documents!([ { "id": id, "name": "kevin" } ]);
+            let mut builder =
+                IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
+                    .unwrap();
+            let error = builder.add_documents(content).unwrap_err();
+            assert!(matches!(
+                error,
+                crate::Error::UserError(UserError::InvalidDocumentId { position: Some(0), .. })
+            ));
+        }
+
         // First we send 1 document with a valid id.
         let mut wtxn = index.write_txn().unwrap();
         // There is a space in the document id.
@@ -930,6 +1326,40 @@ mod tests {
         drop(rtxn);
     }
 
+    #[test]
+    fn numeric_and_string_documents_ids_are_equivalent() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([ { "id": 42, "name": "kevin" } ]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        // The string id replaces the document with the numeric id.
+        let content = documents!([ { "id": " 42 ", "name": "kevina" } ]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 1);
+        let docid = index.external_documents_ids(&rtxn).unwrap().get("42").unwrap();
+        let (_, document) = index.documents(&rtxn, Some(docid)).unwrap().remove(0);
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let name = document.get(fields_ids_map.id("name").unwrap()).unwrap();
+        assert_eq!(name, br#""kevina""#);
+    }
+
     #[test]
     fn complex_documents() {
         let path = tempfile::tempdir().unwrap();
@@ -1097,6 +1527,184 @@ mod tests {
         assert_eq!(documents_ids, vec![0, 1, 2, 3]);
     }
 
+    #[test]
+    fn index_geojson_points() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset!(S("_geo")));
+        builder.set_sortable_fields(hashset!(S("_geo")));
+        builder.execute(|_| ()).unwrap();
+
+        // the GeoJSON coordinates are the longitude then the latitude.
+        let documents = documents!([
+          { "id": 0, "_geo": { "lat": 48.8566, "lng": 2.3522 } },
+          { "id": 1, "_geo": { "type": "Point", "coordinates": [2.3522, 48.8566] } },
+          { "id": 2, "_geo": { "lat": 45.764, "lng": 4.8357 } },
+          { "id": 3, "_geo": { "type": "Point", "coordinates": [4.8357, 45.764] } },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(documents).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = crate::Search::new(&rtxn, &index);
+        search
+            .filter(crate::Filter::from_str("_geoRadius(48.8566, 2.3522, 1000)").unwrap().unwrap());
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1]);
+
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.sort_criteria(vec![crate::AscDesc::Asc(crate::Member::Geo([45.764, 4.8357]))]);
+        let crate::SearchResult { mut documents_ids, geo_distances, .. } =
+            search.execute().unwrap();
+        documents_ids[..2].sort_unstable();
+        documents_ids[2..].sort_unstable();
+        assert_eq!(documents_ids, vec![2, 3, 0, 1]);
+        assert_eq!(geo_distances[&0], geo_distances[&1]);
+        assert_eq!(geo_distances[&2], geo_distances[&3]);
+    }
+
+    #[test]
+    fn geojson_error() {
+        let index = TempIndex::new();
+
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset!(S("_geo")));
+        builder.execute(|_| ()).unwrap();
+
+        let documents = documents!([
+          { "id": 0, "_geo": { "type": "LineString", "coordinates": [[2.35, 48.85], [4.83, 45.76]] } }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
+                .unwrap();
+        builder.add_documents(documents).unwrap();
+        let error = builder.execute().unwrap_err();
+        assert_eq!(
+            &error.to_string(),
+            r#"Could not index the geometry in the document with the id: `0`. Only the GeoJSON `Point` geometry is supported but instead got `"LineString"`."#
+        );
+
+        let documents = documents!([
+          { "id": 0, "_geo": { "type": "Point", "coordinates": [2.35] } }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(documents).unwrap();
+        let error = builder.execute().unwrap_err();
+        assert_eq!(
+            &error.to_string(),
+            r#"Could not parse the coordinates in the document with the id: `0`. Was expecting a `[longitude, latitude]` array of numbers but instead got `2.35`."#
+        );
+    }
+
+    #[test]
+    fn incremental_geo_updates() {
+        use std::collections::HashMap;
+
+        // returns the external ids sorted by increasing distance to the `[0, 0]` point.
+        fn geo_sorted_ids(index: &Index) -> Vec<String> {
+            let rtxn = index.read_txn().unwrap();
+            let mut search = crate::Search::new(&rtxn, index);
+            search.sort_criteria(vec![crate::AscDesc::Asc(crate::Member::Geo([0., 0.]))]);
+            let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+            let external_ids = index.external_documents_ids(&rtxn).unwrap().to_hash_map();
+            let external_ids: HashMap<_, _> =
+                external_ids.into_iter().map(|(k, v)| (v, k)).collect();
+            documents_ids.iter().map(|docid| external_ids[docid].clone()).collect()
+        }
+
+        fn add_documents(index: &Index, config: &IndexerConfig, documents: serde_json::Value) {
+            let mut wtxn = index.write_txn().unwrap();
+            let indexing_config = IndexDocumentsConfig::default();
+            let mut builder =
+                IndexDocuments::new(&mut wtxn, index, config, indexing_config, |_| ()).unwrap();
+            builder.add_documents(documents!(documents)).unwrap();
+            builder.execute().unwrap();
+            wtxn.commit().unwrap();
+        }
+
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset!(S("_geo")));
+        builder.set_sortable_fields(hashset!(S("_geo")));
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        add_documents(
+            &index,
+            &config,
+            serde_json::json!([
+                { "id": 0, "_geo": { "lat": 0, "lng": 0 } },
+                { "id": 1, "_geo": { "lat": 1, "lng": 0 } },
+                { "id": 2, "_geo": { "lat": 2, "lng": 0 } },
+            ]),
+        );
+        assert_eq!(geo_sorted_ids(&index), ["0", "1", "2"]);
+        assert_eq!(index.geo_rtree_insertions(&index.read_txn().unwrap()).unwrap(), 0);
+
+        // the small batches are inserted one by one in the tree.
+        add_documents(
+            &index,
+            &config,
+            serde_json::json!([{ "id": 3, "_geo": { "lat": 3, "lng": 0 } }]),
+        );
+        add_documents(
+            &index,
+            &config,
+            serde_json::json!([{ "id": 4, "_geo": { "lat": 0.5, "lng": 0 } }]),
+        );
+        assert_eq!(geo_sorted_ids(&index), ["0", "4", "1", "2", "3"]);
+        assert_eq!(index.geo_rtree_insertions(&index.read_txn().unwrap()).unwrap(), 2);
+
+        // moving a point makes the tree grow past the points it was built with.
+        add_documents(
+            &index,
+            &config,
+            serde_json::json!([{ "id": 0, "_geo": { "lat": 5, "lng": 0 } }]),
+        );
+        assert_eq!(geo_sorted_ids(&index), ["4", "1", "2", "3", "0"]);
+        assert_eq!(index.geo_rtree_insertions(&index.read_txn().unwrap()).unwrap(), 0);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.delete_external_id("1");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+        assert_eq!(geo_sorted_ids(&index), ["4", "2", "3", "0"]);
+
+        // a threshold of zero always rebuilds the tree.
+        let config = IndexerConfig { geo_rtree_rebuild_threshold: Some(0), ..Default::default() };
+        add_documents(
+            &index,
+            &config,
+            serde_json::json!([{ "id": 5, "_geo": { "lat": 1.5, "lng": 0 } }]),
+        );
+        assert_eq!(geo_sorted_ids(&index), ["4", "5", "2", "3", "0"]);
+        assert_eq!(index.geo_rtree_insertions(&index.read_txn().unwrap()).unwrap(), 0);
+
+        let rtxn = index.read_txn().unwrap();
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.filter(crate::Filter::from_str("_geoRadius(0, 0, 250000)").unwrap().unwrap());
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        let external_ids = index.external_documents_ids(&rtxn).unwrap();
+        let mut expected: Vec<_> =
+            ["2", "4", "5"].iter().map(|id| external_ids.get(id).unwrap()).collect();
+        expected.sort_unstable();
+        assert_eq!(documents_ids, expected);
+    }
+
     #[test]
     fn geo_error() {
         let path = tempfile::tempdir().unwrap();
@@ -1182,6 +1790,45 @@ mod tests {
             &error.to_string(),
             r#"Could not parse longitude in the document with the id: `0`. Was expecting a number but instead got `"hello"`."#
         );
+
+        let documents = documents!([
+          { "id": 0, "_geo": { "lat": 9999, "lng": 42 } }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
+                .unwrap();
+        builder.add_documents(documents).unwrap();
+        let error = builder.execute().unwrap_err();
+        assert_eq!(
+            &error.to_string(),
+            r#"Could not index the latitude in the document with the id: `0`. Latitude must be contained between -90 and 90 degrees but instead got `9999`."#
+        );
+
+        let documents = documents!([
+          { "id": 0, "_geo": { "lat": 12, "lng": -180.5 } }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
+                .unwrap();
+        builder.add_documents(documents).unwrap();
+        let error = builder.execute().unwrap_err();
+        assert_eq!(
+            &error.to_string(),
+            r#"Could not index the longitude in the document with the id: `0`. Longitude must be contained between -180 and 180 degrees but instead got `-180.5`."#
+        );
+
+        let documents = documents!([
+          { "id": 0, "_geo": { "lat": "NaN", "lng": 42 } }
+        ]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
+                .unwrap();
+        builder.add_documents(documents).unwrap();
+        let error = builder.execute().unwrap_err();
+        assert_eq!(
+            &error.to_string(),
+            r#"Could not index the latitude in the document with the id: `0`. Latitude must be contained between -90 and 90 degrees but instead got `NaN`."#
+        );
     }
 
     #[test]
@@ -1929,4 +2576,416 @@ mod tests {
 
         assert_eq!(ids.len(), map.len());
     }
+
+    #[test]
+    fn primary_key_inference() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+        let indexing_config = IndexDocumentsConfig::default();
+
+        // the fields only containing `id` can't be chosen.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "identity": 1, "video_id_number": 2, "title": "hello" }]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
+                .unwrap();
+        let error = builder.add_documents(content).unwrap_err();
+        assert!(matches!(
+            error,
+            crate::Error::UserError(UserError::AmbiguousPrimaryKey { ref candidates })
+                if candidates == &[S("identity"), S("video_id_number")]
+        ));
+        drop(builder);
+        drop(wtxn);
+
+        // the lexicographically first field ending with `id` is chosen.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "userId": 1, "productId": 2, "title": "hello" }]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config.clone(), |_| ())
+                .unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.primary_key(&rtxn).unwrap(), Some("productId"));
+
+        // the next batches keep the same primary key, even with an `id` field.
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 3, "userId": 1, "productId": 4 }]);
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.primary_key(&rtxn).unwrap(), Some("productId"));
+        let external_documents_ids = index.external_documents_ids(&rtxn).unwrap();
+        assert!(external_documents_ids.get("4").is_some());
+    }
+
+    #[test]
+    fn dry_run_summary() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "name": "kevin" },
+            { "id": 2, "name": "kevina" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let batch = || {
+            documents!([
+                { "id": 2, "name": "kevina", "age": 23 },
+                { "id": 3, "name": "benoit", "address": { "city": "Paris" } },
+                { "id": 4, "name": "bernard" }
+            ])
+        };
+
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config = IndexDocumentsConfig { dry_run: true, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(batch()).unwrap();
+        let result = builder.execute().unwrap();
+        let summary = result.summary.unwrap();
+        assert_eq!(result.indexed_documents, 0);
+        assert_eq!(result.number_of_documents, 2);
+        assert_eq!(summary.added, 2);
+        assert_eq!(summary.updated, 1);
+
+        // nothing was written in the index.
+        let fields_ids_map = index.fields_ids_map(&wtxn).unwrap();
+        assert_eq!(fields_ids_map.names().collect::<Vec<_>>(), ["id", "name"]);
+        assert_eq!(index.number_of_documents(&wtxn).unwrap(), 2);
+        wtxn.commit().unwrap();
+
+        // the real run on the same batch does what the summary announced.
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(batch()).unwrap();
+        let result = builder.execute().unwrap();
+        assert!(result.summary.is_none());
+        assert_eq!(result.number_of_documents, 2 + summary.added);
+        assert_eq!(result.indexed_documents, summary.added + summary.updated);
+
+        let fields_ids_map = index.fields_ids_map(&wtxn).unwrap();
+        let mut new_fields: Vec<_> =
+            fields_ids_map.names().filter(|name| !["id", "name"].contains(name)).collect();
+        new_fields.sort_unstable();
+        assert_eq!(new_fields, summary.new_fields);
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn dry_run_on_an_empty_index() {
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([{ "id": 1, "name": "kevin" }]);
+        let indexing_config = IndexDocumentsConfig { dry_run: true, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        let summary = builder.execute().unwrap().summary.unwrap();
+        assert_eq!(
+            summary,
+            DocumentAdditionSummary { added: 1, updated: 0, new_fields: vec![S("id"), S("name")] }
+        );
+
+        // the inferred primary key is not kept.
+        assert_eq!(index.primary_key(&wtxn).unwrap(), None);
+        assert!(index.fields_ids_map(&wtxn).unwrap().names().next().is_none());
+        wtxn.commit().unwrap();
+    }
+
+    #[test]
+    fn disabled_databases() {
+        let build_index = |disabled_databases| {
+            let path = tempfile::tempdir().unwrap();
+            let mut options = EnvOpenOptions::new();
+            options.map_size(10 * 1024 * 1024); // 10 MB
+            let index =
+                Index::new_with_disabled_databases(options, &path, disabled_databases).unwrap();
+
+            let config = IndexerConfig::default();
+            let mut wtxn = index.write_txn().unwrap();
+            let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+            builder.set_sortable_fields(hashset!(S("_geo")));
+            builder.set_filterable_fields(hashset!(S("_geo")));
+            builder.set_criteria(vec![S("words"), S("typo"), S("sort"), S("exactness")]);
+            builder.execute(|_| ()).unwrap();
+
+            let content = documents!([
+                { "id": 0, "title": "hello world", "_geo": { "lat": 48.8, "lng": 2.3 } },
+                { "id": 1, "title": "hello there", "_geo": { "lat": 45.7, "lng": 4.8 } },
+                { "id": 2, "title": "help me" },
+            ]);
+            let indexing_config =
+                IndexDocumentsConfig { words_prefix_threshold: Some(1), ..Default::default() };
+            let mut builder =
+                IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+            builder.add_documents(content).unwrap();
+            builder.execute().unwrap();
+            wtxn.commit().unwrap();
+            (index, path)
+        };
+
+        // the databases are filled by default.
+        let (index, _path) = build_index(DisabledDatabases::default());
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.word_pair_proximity_docids.is_empty(&rtxn).unwrap());
+        assert!(!index.word_prefix_docids.is_empty(&rtxn).unwrap());
+        assert!(!index.words_prefixes_fst(&rtxn).unwrap().is_empty());
+        assert!(index.geo_rtree(&rtxn).unwrap().is_some());
+        let filter = crate::Filter::from_str("_geoRadius(48.8, 2.3, 1000)").unwrap().unwrap();
+        assert_eq!(filter.evaluate(&rtxn, &index).unwrap(), RoaringBitmap::from_iter([0]));
+        drop(rtxn);
+
+        let disabled_databases = DisabledDatabases { proximity: true, prefix: true, geo: true };
+        let (index, path) = build_index(disabled_databases);
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.word_pair_proximity_docids.is_empty(&rtxn).unwrap());
+        assert!(index.word_prefix_pair_proximity_docids.is_empty(&rtxn).unwrap());
+        assert!(index.word_prefix_docids.is_empty(&rtxn).unwrap());
+        assert!(index.word_prefix_position_docids.is_empty(&rtxn).unwrap());
+        assert!(index.words_prefixes_fst(&rtxn).unwrap().is_empty());
+        assert!(index.geo_rtree(&rtxn).unwrap().is_none());
+
+        // the prefixes are still found without the prefix databases.
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.query("hel");
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1, 2]);
+
+        search.query("hello world");
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0, 1]);
+
+        // the phrases are resolved from the word positions without the proximity databases.
+        search.query("\"hello world\"");
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![0]);
+
+        search.query("\"world hello\"");
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert!(documents_ids.is_empty());
+
+        // the ranking rules relying on a disabled database are rejected.
+        search.ranking_rules(vec![S("words"), S("proximity")]);
+        let error = search.execute().unwrap_err();
+        assert!(matches!(
+            error,
+            crate::Error::UserError(UserError::CriterionDatabaseDisabled {
+                database: "proximity",
+                ..
+            })
+        ));
+
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.sort_criteria(vec![crate::AscDesc::Asc(crate::Member::Geo([48.8, 2.3]))]);
+        let error = search.execute().unwrap_err();
+        assert!(matches!(
+            error,
+            crate::Error::UserError(UserError::CriterionDatabaseDisabled { database: "geo", .. })
+        ));
+
+        // the geo filters are rejected too instead of matching no document.
+        for filter in ["_geoRadius(48.8, 2.3, 1000)", "NOT _geoBoundingBox([50, 5], [45, 0])"] {
+            let filter = crate::Filter::from_str(filter).unwrap().unwrap();
+            let error = filter.evaluate(&rtxn, &index).unwrap_err();
+            assert!(matches!(
+                error,
+                crate::Error::UserError(UserError::CriterionDatabaseDisabled {
+                    database: "geo",
+                    ..
+                })
+            ));
+        }
+        drop(rtxn);
+
+        // the disabled databases can't be changed once documents are indexed.
+        index.prepare_for_closing().wait();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index =
+            Index::new_with_disabled_databases(options.clone(), &path, disabled_databases).unwrap();
+        index.prepare_for_closing().wait();
+        let error =
+            Index::new_with_disabled_databases(options, &path, DisabledDatabases::default())
+                .unwrap_err();
+        assert!(matches!(
+            error,
+            crate::Error::UserError(UserError::DisabledDatabasesCannotBeChanged)
+        ));
+    }
+
+    fn index_duplicated_ids(policy: DuplicatePrimaryKeys) -> (TempIndex, Result<u64>) {
+        let index = TempIndex::new();
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 1, "name": "kevin" },
+            { "id": 2, "name": "bob" },
+            { "id": 1, "name": "jean" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config =
+            IndexDocumentsConfig { duplicate_primary_keys: policy, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        let result = builder.add_documents(content);
+        if result.is_ok() {
+            builder.execute().unwrap();
+            wtxn.commit().unwrap();
+        }
+        (index, result)
+    }
+
+    fn stored_name(index: &Index, external_id: &str) -> String {
+        let rtxn = index.read_txn().unwrap();
+        let name = index.fields_ids_map(&rtxn).unwrap().id("name").unwrap();
+        let (_, obkv) = index.document_by_external_id(&rtxn, external_id).unwrap().unwrap();
+        serde_json::from_slice(obkv.get(name).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn duplicate_primary_keys_keep_last() {
+        let (index, result) = index_duplicated_ids(DuplicatePrimaryKeys::KeepLast);
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(stored_name(&index, "1"), "jean");
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 2);
+    }
+
+    #[test]
+    fn duplicate_primary_keys_keep_first() {
+        let (index, result) = index_duplicated_ids(DuplicatePrimaryKeys::KeepFirst);
+        // the ignored document is not counted.
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(stored_name(&index, "1"), "kevin");
+        assert_eq!(stored_name(&index, "2"), "bob");
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 2);
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.query("jean");
+        assert!(search.execute().unwrap().documents_ids.is_empty());
+    }
+
+    #[test]
+    fn duplicate_primary_keys_error() {
+        let (index, result) = index_duplicated_ids(DuplicatePrimaryKeys::Error);
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error,
+            crate::Error::UserError(UserError::DuplicateDocumentId {
+                ref document_id,
+                positions: (0, 2),
+            }) if document_id == "1"
+        ));
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 0);
+    }
+
+    fn index_with_invalid_documents(
+        skip_invalid_documents: bool,
+    ) -> (TempIndex, Result<DocumentAdditionResult>) {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = update::Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset!(S("_geo")));
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 1, "name": "kevin", "_geo": { "lat": 48.8, "lng": 2.3 } },
+            { "id": "bad id!", "name": "bob" },
+            { "name": "without id" },
+            { "id": 4, "name": "jean", "_geo": { "lat": 100, "lng": 2.3 } },
+            { "id": 5, "name": "lucas" },
+        ]);
+        let indexing_config = IndexDocumentsConfig { skip_invalid_documents, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        let other_content = documents!([{ "id": "bad id 2!" }, { "id": 6, "name": "tamo" }]);
+        let result = builder
+            .add_documents(content)
+            .and_then(|_| builder.add_documents(other_content))
+            .and_then(|_| builder.execute());
+        if result.is_ok() {
+            wtxn.commit().unwrap();
+        }
+        (index, result)
+    }
+
+    #[test]
+    fn skip_invalid_documents() {
+        let (index, result) = index_with_invalid_documents(true);
+        let result = result.unwrap();
+        assert_eq!(result.indexed_documents, 3);
+
+        let batches: Vec<_> = result.rejected_documents.iter().map(|d| d.batch).collect();
+        assert_eq!(batches, [0, 0, 0, 1]);
+        let positions: Vec<_> = result.rejected_documents.iter().map(|d| d.position).collect();
+        assert_eq!(positions, [1, 2, 3, 0]);
+        let reasons: Vec<_> = result.rejected_documents.iter().map(|d| &d.reason).collect();
+        assert!(reasons[0]
+            .starts_with("Document identifier `\"bad id!\"` of the document at position 1"));
+        assert!(reasons[1].starts_with("Document doesn't have a `id` attribute"));
+        assert!(reasons[2]
+            .starts_with("Could not index the latitude in the document with the id: `4`."));
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 3);
+        let external_ids = index.external_documents_ids(&rtxn).unwrap();
+        assert!(external_ids.get("1").is_some());
+        assert!(external_ids.get("4").is_none());
+        assert!(external_ids.get("5").is_some());
+        assert!(external_ids.get("6").is_some());
+
+        // the GeoJSON fields the geo points were looked for in are not persisted.
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        assert!(fields_ids_map.id("_geo.lat").is_some());
+        assert!(fields_ids_map.id("_geo.type").is_none());
+        assert!(fields_ids_map.id("_geo.coordinates").is_none());
+
+        let mut search = crate::Search::new(&rtxn, &index);
+        search.filter(crate::Filter::from_str("_geoRadius(48.8, 2.3, 1000)").unwrap().unwrap());
+        let crate::SearchResult { documents_ids, .. } = search.execute().unwrap();
+        assert_eq!(documents_ids, vec![external_ids.get("1").unwrap()]);
+    }
+
+    #[test]
+    fn fail_on_invalid_documents() {
+        let (index, result) = index_with_invalid_documents(false);
+        assert!(matches!(
+            result.unwrap_err(),
+            crate::Error::UserError(UserError::InvalidDocumentId { position: Some(1), .. })
+        ));
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 0);
+    }
 }
@@ -13,8 +13,15 @@ use roaring::RoaringBitmap;
 use serde_json::{Map, Value};
 use smartstring::SmartString;
 
-use super::helpers::{create_sorter, create_writer, keep_latest_obkv, merge_obkvs, MergeFn};
-use super::{IndexDocumentsMethod, IndexerConfig};
+use super::extract::{extract_geo_point, GeoFieldsIds};
+use super::helpers::{
+    create_sorter, create_writer, keep_latest_obkv, keep_unmerged_json, merge_obkvs,
+    merge_obkvs_and_arrays, MergeFn,
+};
+use super::{
+    faceted_geo_fields_ids, geo_fields_ids, DocumentAdditionSummary, DuplicatePrimaryKeys,
+    IndexDocumentsMethod, IndexerConfig, RejectedDocument,
+};
 use crate::documents::{DocumentBatchReader, DocumentsBatchIndex};
 use crate::error::{Error, InternalError, UserError};
 use crate::index::db_name;
@@ -26,6 +33,9 @@ use crate::{
 
 const DEFAULT_PRIMARY_KEY_NAME: &str = "id";
 
+/// The maximum length, in bytes, of a document id.
+const MAX_DOCUMENT_ID_LENGTH: usize = 511;
+
 pub struct TransformOutput {
     pub primary_key: String,
     pub fields_ids_map: FieldsIdsMap,
@@ -36,6 +46,9 @@ pub struct TransformOutput {
     pub documents_count: usize,
     pub original_documents: File,
     pub flattened_documents: File,
+    /// The json bytes of the documents when the index stores them, empty for the documents
+    /// that must be converted from their obkv.
+    pub json_documents: Option<File>,
 }
 
 /// Extract the external ids, deduplicate and compute the new internal documents ids
@@ -51,15 +64,21 @@ pub struct Transform<'a, 'i> {
     indexer_settings: &'a IndexerConfig,
     pub autogenerate_docids: bool,
     pub index_documents_method: IndexDocumentsMethod,
+    pub duplicate_primary_keys: DuplicatePrimaryKeys,
+    pub skip_invalid_documents: bool,
     available_documents_ids: AvailableDocumentsIds,
 
     original_sorter: grenad::Sorter<MergeFn>,
     flattened_sorter: grenad::Sorter<MergeFn>,
+    json_sorter: Option<grenad::Sorter<MergeFn>>,
     replaced_documents_ids: RoaringBitmap,
     new_documents_ids: RoaringBitmap,
     // To increase the cache locality and the heap usage we use smartstring.
     new_external_documents_ids_builder: FxHashMap<SmartString<smartstring::Compact>, u64>,
     documents_count: usize,
+    batches_count: usize,
+    /// The documents that were not read because they are invalid.
+    pub rejected_documents: Vec<RejectedDocument>,
 }
 
 /// Create a mapping between the field ids found in the document batch and the one that were
@@ -85,16 +104,29 @@ fn create_fields_mapping(
         .collect()
 }
 
-/// Look for a key containing the [DEFAULT_PRIMARY_KEY_NAME] in the fields.
+/// Look for the primary key in the fields, the field named [DEFAULT_PRIMARY_KEY_NAME] is
+/// preferred and then the lexicographically first field ending with it, regardless of the case.
+/// When there is none, returns the fields that only contain it, sorted lexicographically.
 /// It doesn't look in the subfield because we don't want to enable the
 /// primary key inference on nested objects.
-fn find_primary_key(index: &DocumentsBatchIndex) -> Option<&str> {
-    index
-        .iter()
-        .sorted_by_key(|(k, _)| *k)
-        .map(|(_, v)| v)
-        .find(|v| v.to_lowercase().contains(DEFAULT_PRIMARY_KEY_NAME))
-        .map(String::as_str)
+fn find_primary_key(index: &DocumentsBatchIndex) -> std::result::Result<&str, Vec<&str>> {
+    let fields: Vec<_> = index.iter().map(|(_, name)| name.as_str()).sorted().collect();
+
+    if let Some(name) =
+        fields.iter().copied().find(|name| name.to_lowercase() == DEFAULT_PRIMARY_KEY_NAME)
+    {
+        return Ok(name);
+    }
+    if let Some(name) =
+        fields.iter().copied().find(|name| name.to_lowercase().ends_with(DEFAULT_PRIMARY_KEY_NAME))
+    {
+        return Ok(name);
+    }
+
+    Err(fields
+        .into_iter()
+        .filter(|name| name.to_lowercase().contains(DEFAULT_PRIMARY_KEY_NAME))
+        .collect())
 }
 
 impl<'a, 'i> Transform<'a, 'i> {
@@ -110,15 +142,19 @@ impl<'a, 'i> Transform<'a, 'i> {
         let merge_function = match index_documents_method {
             IndexDocumentsMethod::ReplaceDocuments => keep_latest_obkv,
             IndexDocumentsMethod::UpdateDocuments => merge_obkvs,
+            IndexDocumentsMethod::UpdateDocumentsMergeArrays => merge_obkvs_and_arrays,
         };
 
+        let store_documents_json = index.store_documents_json(wtxn)?;
+        let sorters_count = if store_documents_json { 3 } else { 2 };
+
         // We initialize the sorter with the user indexing settings.
         let original_sorter = create_sorter(
             merge_function,
             indexer_settings.chunk_compression_type,
             indexer_settings.chunk_compression_level,
             indexer_settings.max_nb_chunks,
-            indexer_settings.max_memory.map(|mem| mem / 2),
+            indexer_settings.max_memory.map(|mem| mem / sorters_count),
         );
 
         // We initialize the sorter with the user indexing settings.
@@ -127,9 +163,26 @@ impl<'a, 'i> Transform<'a, 'i> {
             indexer_settings.chunk_compression_type,
             indexer_settings.chunk_compression_level,
             indexer_settings.max_nb_chunks,
-            indexer_settings.max_memory.map(|mem| mem / 2),
+            indexer_settings.max_memory.map(|mem| mem / sorters_count),
         );
-        let documents_ids = index.documents_ids(wtxn)?;
+
+        // The json of a document is the last one sent when the documents are replaced,
+        // the json of documents merged together doesn't exist.
+        let json_sorter = store_documents_json.then(|| {
+            let merge_function = match index_documents_method {
+                IndexDocumentsMethod::ReplaceDocuments => keep_latest_obkv,
+                _ => keep_unmerged_json,
+            };
+            create_sorter(
+                merge_function,
+                indexer_settings.chunk_compression_type,
+                indexer_settings.chunk_compression_level,
+                indexer_settings.max_nb_chunks,
+                indexer_settings.max_memory.map(|mem| mem / sorters_count),
+            )
+        });
+        // The soft deleted documents ids can't be reused until they are purged.
+        let documents_ids = index.documents_ids(wtxn)? | index.soft_deleted_documents_ids(wtxn)?;
 
         Ok(Transform {
             index,
@@ -139,11 +192,16 @@ impl<'a, 'i> Transform<'a, 'i> {
             available_documents_ids: AvailableDocumentsIds::from_documents_ids(&documents_ids),
             original_sorter,
             flattened_sorter,
+            json_sorter,
             index_documents_method,
+            duplicate_primary_keys: DuplicatePrimaryKeys::default(),
+            skip_invalid_documents: false,
             replaced_documents_ids: RoaringBitmap::new(),
             new_documents_ids: RoaringBitmap::new(),
             new_external_documents_ids_builder: FxHashMap::default(),
             documents_count: 0,
+            batches_count: 0,
+            rejected_documents: Vec::new(),
         })
     }
 
@@ -162,11 +220,17 @@ impl<'a, 'i> Transform<'a, 'i> {
 
         let mapping = create_fields_mapping(&mut self.fields_ids_map, fields_index)?;
 
-        let alternative_name = self
-            .index
-            .primary_key(wtxn)?
-            .or_else(|| find_primary_key(fields_index))
-            .map(String::from);
+        let alternative_name = match self.index.primary_key(wtxn)? {
+            Some(primary_key) => Some(primary_key.to_string()),
+            None => match find_primary_key(fields_index) {
+                Ok(name) => Some(name.to_string()),
+                Err(candidates) if !candidates.is_empty() && !self.autogenerate_docids => {
+                    let candidates = candidates.into_iter().map(String::from).collect();
+                    return Err(UserError::AmbiguousPrimaryKey { candidates }.into());
+                }
+                Err(_) => None,
+            },
+        };
 
         let (primary_key_id, primary_key_name) = compute_primary_key_pair(
             self.index.primary_key(wtxn)?,
@@ -176,14 +240,29 @@ impl<'a, 'i> Transform<'a, 'i> {
         )?;
 
         let primary_key_id_nested = primary_key_name.contains('.');
+        let batch = self.batches_count;
+        self.batches_count += 1;
+
+        // the geo points are only checked here when the invalid documents are skipped. The
+        // missing geo fields must not be added to the fields ids map of the index, their ids
+        // are looked up in a copy of it, again each time the documents add new fields to it.
+        let check_geo_points = self.skip_invalid_documents
+            && faceted_geo_fields_ids(self.index, wtxn, &mut self.fields_ids_map.clone())?
+                .is_some();
+        let mut geo_fields_ids_cache: Option<(usize, GeoFieldsIds)> = None;
 
         let mut flattened_document = None;
         let mut obkv_buffer = Vec::new();
         let mut flattened_obkv_buffer = Vec::new();
         let mut documents_count = 0;
+        // the documents ignored because they are invalid or their id was already seen.
+        let mut skipped_count = 0;
+        // the position of the first document of the batch with each external id.
+        let mut batch_external_ids: HashMap<String, usize> = HashMap::new();
         let mut external_id_buffer = Vec::new();
         let mut field_buffer: Vec<(u16, Cow<[u8]>)> = Vec::new();
-        while let Some((addition_index, document)) = reader.next_document_with_index()? {
+        while let Some((addition_index, document, raw)) = reader.next_document_with_raw()? {
+            let position = documents_count + skipped_count;
             let mut field_buffer_cache = drop_and_reuse(field_buffer);
             if self.indexer_settings.log_every_n.map_or(false, |len| documents_count % len == 0) {
                 progress_callback(UpdateIndexingStep::RemapDocumentAddition {
@@ -203,6 +282,7 @@ impl<'a, 'i> Transform<'a, 'i> {
             // document. If none is found, and we were told to generate missing document ids, then
             // we create the missing field, and update the new document.
             let mut uuid_buffer = [0; uuid::adapter::Hyphenated::LENGTH];
+            let fields_count = field_buffer_cache.len();
             let external_id = if primary_key_id_nested {
                 let mut field_buffer_cache = field_buffer_cache.clone();
                 self.flatten_from_field_mapping(
@@ -223,7 +303,8 @@ impl<'a, 'i> Transform<'a, 'i> {
                     &mut field_buffer_cache,
                     &mut external_id_buffer,
                     self.autogenerate_docids,
-                )?
+                    position,
+                )
             } else {
                 update_primary_key(
                     document,
@@ -234,8 +315,22 @@ impl<'a, 'i> Transform<'a, 'i> {
                     &mut field_buffer_cache,
                     &mut external_id_buffer,
                     self.autogenerate_docids,
-                )?
+                    position,
+                )
+            };
+            let external_id = match external_id {
+                Ok(external_id) => external_id,
+                Err(Error::UserError(error)) if self.skip_invalid_documents => {
+                    self.rejected_documents.push(RejectedDocument::new(batch, position, error));
+                    skipped_count += 1;
+                    field_buffer = drop_and_reuse(field_buffer_cache);
+                    external_id_buffer.clear();
+                    continue;
+                }
+                Err(error) => return Err(error),
             };
+            // the json bytes don't contain the generated ids.
+            let raw = raw.filter(|_| field_buffer_cache.len() == fields_count);
 
             // Insertion in a obkv need to be done with keys ordered. For now they are ordered
             // according to the document addition key order, so we sort it according to the
@@ -248,6 +343,69 @@ impl<'a, 'i> Transform<'a, 'i> {
                 writer.insert(*k, v)?;
             }
 
+            let flattened_buffer = match flattened_document {
+                Some(_) => None,
+                None => self.flatten_from_fields_ids_map(KvReader::new(&obkv_buffer))?,
+            };
+            let flattened = match (flattened_document, &flattened_buffer) {
+                (Some(flatten), _) => flatten.as_slice(),
+                (None, Some(buffer)) => buffer.as_slice(),
+                (None, None) => obkv_buffer.as_slice(),
+            };
+
+            // the extraction of the geo points rejects the whole batch, the invalid ones must
+            // be found before.
+            if check_geo_points {
+                let fields_count = self.fields_ids_map.len();
+                let geo_fields_ids = match geo_fields_ids_cache {
+                    Some((count, geo_fields_ids)) if count == fields_count => geo_fields_ids,
+                    _ => {
+                        let geo_fields_ids = geo_fields_ids(&mut self.fields_ids_map.clone())?;
+                        geo_fields_ids_cache = Some((fields_count, geo_fields_ids));
+                        geo_fields_ids
+                    }
+                };
+                let document = KvReader::new(flattened);
+                if let Err(error) = extract_geo_point(document, primary_key_id, geo_fields_ids) {
+                    match error {
+                        Error::UserError(error) => self
+                            .rejected_documents
+                            .push(RejectedDocument::new(batch, position, error)),
+                        error => return Err(error),
+                    }
+                    skipped_count += 1;
+                    field_buffer = drop_and_reuse(field_buffer_cache);
+                    external_id_buffer.clear();
+                    obkv_buffer.clear();
+                    continue;
+                }
+            }
+
+            if self.duplicate_primary_keys != DuplicatePrimaryKeys::KeepLast {
+                match batch_external_ids.entry(external_id.to_string()) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(position);
+                    }
+                    Entry::Occupied(entry) => {
+                        if self.duplicate_primary_keys == DuplicatePrimaryKeys::Error {
+                            let document_id = entry.key().clone();
+                            let positions = (*entry.get(), position);
+                            let error = UserError::DuplicateDocumentId { document_id, positions };
+                            if !self.skip_invalid_documents {
+                                return Err(error.into());
+                            }
+                            self.rejected_documents
+                                .push(RejectedDocument::new(batch, position, error));
+                        }
+                        skipped_count += 1;
+                        field_buffer = drop_and_reuse(field_buffer_cache);
+                        external_id_buffer.clear();
+                        obkv_buffer.clear();
+                        continue;
+                    }
+                }
+            }
+
             let (docid, should_insert_original_document) =
                 match external_documents_ids.get(&*external_id) {
                     // if the document is in the db but has already been inserted
@@ -288,6 +446,9 @@ impl<'a, 'i> Transform<'a, 'i> {
                     })?;
 
                 self.original_sorter.insert(&docid.to_be_bytes(), base_obkv)?;
+                if let Some(json_sorter) = &mut self.json_sorter {
+                    json_sorter.insert(&docid.to_be_bytes(), &[])?;
+                }
                 match self.flatten_from_fields_ids_map(KvReader::new(&base_obkv))? {
                     Some(buffer) => self.flattened_sorter.insert(docid.to_be_bytes(), &buffer)?,
                     None => self.flattened_sorter.insert(docid.to_be_bytes(), base_obkv)?,
@@ -298,18 +459,12 @@ impl<'a, 'i> Transform<'a, 'i> {
 
             // We use the extracted/generated user id as the key for this document.
             self.original_sorter.insert(&docid.to_be_bytes(), obkv_buffer.clone())?;
+            if let Some(json_sorter) = &mut self.json_sorter {
+                json_sorter.insert(&docid.to_be_bytes(), raw.unwrap_or_default())?;
+            }
             documents_count += 1;
 
-            if let Some(flatten) = flattened_document {
-                self.flattened_sorter.insert(docid.to_be_bytes(), &flatten)?;
-            } else {
-                match self.flatten_from_fields_ids_map(KvReader::new(&obkv_buffer))? {
-                    Some(buffer) => self.flattened_sorter.insert(docid.to_be_bytes(), &buffer)?,
-                    None => {
-                        self.flattened_sorter.insert(docid.to_be_bytes(), obkv_buffer.clone())?
-                    }
-                }
-            }
+            self.flattened_sorter.insert(docid.to_be_bytes(), flattened)?;
 
             progress_callback(UpdateIndexingStep::RemapDocumentAddition {
                 documents_seen: documents_count,
@@ -332,6 +487,24 @@ impl<'a, 'i> Transform<'a, 'i> {
         Ok(documents_count)
     }
 
+    /// Returns the number of new and replaced documents read so far and the names of the
+    /// fields they introduce compared to the given fields ids map.
+    pub fn summary(&self, previous_fields_ids_map: &FieldsIdsMap) -> DocumentAdditionSummary {
+        let mut new_fields: Vec<_> = self
+            .fields_ids_map
+            .names()
+            .filter(|name| previous_fields_ids_map.id(name).is_none())
+            .map(String::from)
+            .collect();
+        new_fields.sort_unstable();
+
+        DocumentAdditionSummary {
+            added: self.new_documents_ids.len(),
+            updated: self.replaced_documents_ids.len(),
+            new_fields,
+        }
+    }
+
     // Flatten a document from the fields ids map contained in self and insert the new
     // created fields. Returns `None` if the document doesn't need to be flattened.
     fn flatten_from_fields_ids_map(&mut self, obkv: KvReader<FieldId>) -> Result<Option<Vec<u8>>> {
@@ -368,7 +541,7 @@ impl<'a, 'i> Transform<'a, 'i> {
             }
         }
 
-        let flattened = flatten_serde_json::flatten(&doc);
+        let flattened = flatten_keeping_empty_values(&doc);
 
         // Once we have the flattened version we insert all the new generated fields_ids
         // (if any) in the fields ids map and serialize the value.
@@ -420,7 +593,7 @@ impl<'a, 'i> Transform<'a, 'i> {
             }
         }
 
-        let flattened = flatten_serde_json::flatten(&doc);
+        let flattened = flatten_keeping_empty_values(&doc);
 
         // Once we have the flattened version we insert all the new generated fields_ids
         // (if any) in the fields ids map and serialize the value.
@@ -607,6 +780,21 @@ impl<'a, 'i> Transform<'a, 'i> {
         let mut flattened_documents = writer.into_inner()?;
         flattened_documents.seek(SeekFrom::Start(0))?;
 
+        let json_documents = match self.json_sorter {
+            Some(json_sorter) => {
+                let mut writer = create_writer(
+                    self.indexer_settings.chunk_compression_type,
+                    self.indexer_settings.chunk_compression_level,
+                    tempfile::tempfile()?,
+                );
+                json_sorter.write_into_stream_writer(&mut writer)?;
+                let mut json_documents = writer.into_inner()?;
+                json_documents.seek(SeekFrom::Start(0))?;
+                Some(json_documents)
+            }
+            None => None,
+        };
+
         let mut new_external_documents_ids_builder: Vec<_> =
             self.new_external_documents_ids_builder.into_iter().collect();
 
@@ -629,24 +817,50 @@ impl<'a, 'i> Transform<'a, 'i> {
             documents_count: self.documents_count,
             original_documents,
             flattened_documents,
+            json_documents,
         })
     }
 
     /// Returns a `TransformOutput` with a file that contains the documents of the index
     /// with the attributes reordered accordingly to the `FieldsIdsMap` given as argument.
-    // TODO this can be done in parallel by using the rayon `ThreadPool`.
     pub fn remap_index_documents(
+        self,
+        wtxn: &mut heed::RwTxn,
+        old_fields_ids_map: FieldsIdsMap,
+        new_fields_ids_map: FieldsIdsMap,
+    ) -> Result<TransformOutput> {
+        let documents_ids = self.index.documents_ids(wtxn)?;
+        self.remap_documents(wtxn, old_fields_ids_map, new_fields_ids_map, documents_ids)
+    }
+
+    /// Returns a `TransformOutput` with a file that contains the given documents of the index,
+    /// these documents replace their current version when indexed.
+    pub fn reindex_documents(
+        self,
+        wtxn: &mut heed::RwTxn,
+        documents_ids: RoaringBitmap,
+    ) -> Result<TransformOutput> {
+        let fields_ids_map = self.index.fields_ids_map(wtxn)?;
+        let mut output =
+            self.remap_documents(wtxn, fields_ids_map.clone(), fields_ids_map, documents_ids)?;
+        // The documents are already in the index, they must be deleted before being indexed.
+        output.replaced_documents_ids = std::mem::take(&mut output.new_documents_ids);
+        Ok(output)
+    }
+
+    // TODO this can be done in parallel by using the rayon `ThreadPool`.
+    fn remap_documents(
         self,
         wtxn: &mut heed::RwTxn,
         old_fields_ids_map: FieldsIdsMap,
         mut new_fields_ids_map: FieldsIdsMap,
+        documents_ids: RoaringBitmap,
     ) -> Result<TransformOutput> {
         // There already has been a document addition, the primary key should be set by now.
         let primary_key =
             self.index.primary_key(wtxn)?.ok_or(UserError::MissingPrimaryKey)?.to_string();
         let field_distribution = self.index.field_distribution(wtxn)?;
         let external_documents_ids = self.index.external_documents_ids(wtxn)?;
-        let documents_ids = self.index.documents_ids(wtxn)?;
         let documents_count = documents_ids.len() as usize;
 
         // We create a final writer to write the new documents in order from the sorter.
@@ -663,10 +877,21 @@ impl<'a, 'i> Transform<'a, 'i> {
             tempfile::tempfile()?,
         );
 
+        // The json of the documents is kept as it is stored.
+        let mut json_writer = match self.index.store_documents_json(wtxn)? {
+            true => Some(create_writer(
+                self.indexer_settings.chunk_compression_type,
+                self.indexer_settings.chunk_compression_level,
+                tempfile::tempfile()?,
+            )),
+            false => None,
+        };
+
         let mut obkv_buffer = Vec::new();
-        for result in self.index.documents.iter(wtxn)? {
-            let (docid, obkv) = result?;
-            let docid = docid.get();
+        for docid in &documents_ids {
+            let obkv = self.index.documents.get(wtxn, &BEU32::new(docid))?.ok_or(
+                InternalError::DatabaseMissingEntry { db_name: db_name::DOCUMENTS, key: None },
+            )?;
 
             obkv_buffer.clear();
             let mut obkv_writer = obkv::KvWriter::<_, FieldId>::new(&mut obkv_buffer);
@@ -696,7 +921,7 @@ impl<'a, 'i> Transform<'a, 'i> {
                 doc.insert(key.to_string(), value);
             }
 
-            let flattened = flatten_serde_json::flatten(&doc);
+            let flattened = flatten_keeping_empty_values(&doc);
 
             // Once we have the flattened version we can convert it back to obkv and
             // insert all the new generated fields_ids (if any) in the fields ids map.
@@ -715,6 +940,11 @@ impl<'a, 'i> Transform<'a, 'i> {
                 writer.insert(fid, &value)?;
             }
             flattened_writer.insert(docid.to_be_bytes(), &buffer)?;
+
+            if let Some(json_writer) = &mut json_writer {
+                let json = self.index.documents_json.get(wtxn, &BEU32::new(docid))?;
+                json_writer.insert(docid.to_be_bytes(), json.unwrap_or_default())?;
+            }
         }
 
         // Once we have written all the documents, we extract
@@ -725,6 +955,15 @@ impl<'a, 'i> Transform<'a, 'i> {
         let mut flattened_documents = flattened_writer.into_inner()?;
         flattened_documents.seek(SeekFrom::Start(0))?;
 
+        let json_documents = match json_writer {
+            Some(json_writer) => {
+                let mut json_documents = json_writer.into_inner()?;
+                json_documents.seek(SeekFrom::Start(0))?;
+                Some(json_documents)
+            }
+            None => None,
+        };
+
         Ok(TransformOutput {
             primary_key,
             fields_ids_map: new_fields_ids_map,
@@ -735,6 +974,7 @@ impl<'a, 'i> Transform<'a, 'i> {
             documents_count,
             original_documents,
             flattened_documents,
+            json_documents,
         })
     }
 }
@@ -775,10 +1015,12 @@ fn compute_primary_key_pair(
     }
 }
 
-fn validate_document_id(document_id: &str) -> Option<&str> {
+/// Returns the document id without the surrounding whitespaces, if it is valid.
+pub(crate) fn validate_document_id(document_id: &str) -> Option<&str> {
     let document_id = document_id.trim();
     Some(document_id).filter(|id| {
         !id.is_empty()
+            && id.len() <= MAX_DOCUMENT_ID_LENGTH
             && id.chars().all(|c| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_'))
     })
 }
@@ -786,6 +1028,39 @@ fn validate_document_id(document_id: &str) -> Option<&str> {
 /// Drops all the value of type `U` in vec, and reuses the allocation to create a `Vec<T>`.
 ///
 /// The size and alignment of T and U must match.
+/// Flattens the document like `flatten_serde_json::flatten` but keeps a `null` under the key
+/// of each empty array and empty object, the flattening drops them and the `IS EMPTY` filter
+/// must know in which fields they were.
+fn flatten_keeping_empty_values(doc: &Map<String, Value>) -> Map<String, Value> {
+    fn insert_empty_values(flattened: &mut Map<String, Value>, key: &str, value: &Value) {
+        match value {
+            Value::Array(values) if values.is_empty() => {
+                flattened.entry(key).or_insert(Value::Null);
+            }
+            Value::Object(object) if object.is_empty() => {
+                flattened.entry(key).or_insert(Value::Null);
+            }
+            Value::Array(values) => {
+                for value in values {
+                    insert_empty_values(flattened, key, value);
+                }
+            }
+            Value::Object(object) => {
+                for (nested_key, value) in object {
+                    insert_empty_values(flattened, &format!("{}.{}", key, nested_key), value);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut flattened = flatten_serde_json::flatten(doc);
+    for (key, value) in doc {
+        insert_empty_values(&mut flattened, key, value);
+    }
+    flattened
+}
+
 fn drop_and_reuse<U, T>(mut vec: Vec<U>) -> Vec<T> {
     debug_assert_eq!(std::mem::align_of::<U>(), std::mem::align_of::<T>());
     debug_assert_eq!(std::mem::size_of::<U>(), std::mem::size_of::<T>());
@@ -803,24 +1078,23 @@ fn update_primary_key<'a>(
     field_buffer_cache: &mut Vec<(u16, Cow<'a, [u8]>)>,
     mut external_id_buffer: &'a mut Vec<u8>,
     autogenerate_docids: bool,
+    position: usize,
 ) -> Result<Cow<'a, str>> {
     match field_buffer_cache.iter_mut().find(|(id, _)| *id == primary_key_id) {
         Some((_, bytes)) => {
+            let invalid_document_id = |document_id| UserError::InvalidDocumentId {
+                document_id,
+                position: Some(position),
+            };
             let value = match serde_json::from_slice(bytes).map_err(InternalError::SerdeJson)? {
                 Value::String(string) => match validate_document_id(&string) {
                     Some(s) if s.len() == string.len() => string,
                     Some(s) => s.to_string(),
-                    None => {
-                        return Err(UserError::InvalidDocumentId {
-                            document_id: Value::String(string),
-                        }
-                        .into())
-                    }
+                    None => return Err(invalid_document_id(Value::String(string)).into()),
                 },
-                Value::Number(number) => number.to_string(),
-                content => {
-                    return Err(UserError::InvalidDocumentId { document_id: content.clone() }.into())
-                }
+                // the integers are stored as their string form, `42` and `"42"` are the same id.
+                Value::Number(number) if !number.is_f64() => number.to_string(),
+                content => return Err(invalid_document_id(content).into()),
             };
             serde_json::to_writer(external_id_buffer, &value).map_err(InternalError::SerdeJson)?;
             Ok(Cow::Owned(value))
@@ -933,19 +1207,36 @@ mod test {
         use crate::documents::DocumentsBatchIndex;
         use crate::update::index_documents::transform::find_primary_key;
 
+        fn batch_index(fields: &[&str]) -> DocumentsBatchIndex {
+            let mut map = BiHashMap::new();
+            for (i, field) in fields.iter().enumerate() {
+                map.insert(i as u16, S(field));
+            }
+            DocumentsBatchIndex(map)
+        }
+
+        #[test]
+        fn primary_key_infered_on_exact_id() {
+            let index = batch_index(&["realId", "title", "ID", "fakeId"]);
+            assert_eq!(find_primary_key(&index), Ok("ID"));
+        }
+
         #[test]
-        fn primary_key_infered_on_first_field() {
+        fn primary_key_infered_on_lexicographic_order() {
             // We run the test multiple times to change the order in which the fields are iterated upon.
             for _ in 1..50 {
-                let mut map = BiHashMap::new();
-                map.insert(1, S("fakeId"));
-                map.insert(2, S("fakeId"));
-                map.insert(3, S("fakeId"));
-                map.insert(4, S("fakeId"));
-                map.insert(0, S("realId"));
-
-                assert_eq!(find_primary_key(&DocumentsBatchIndex(map)), Some("realId"));
+                let index = batch_index(&["userId", "title", "realId", "fakeId", "identity"]);
+                assert_eq!(find_primary_key(&index), Ok("fakeId"));
             }
         }
+
+        #[test]
+        fn primary_key_not_infered() {
+            let index = batch_index(&["title", "identity", "video_id_number"]);
+            assert_eq!(find_primary_key(&index), Err(vec!["identity", "video_id_number"]));
+
+            let index = batch_index(&["title", "description"]);
+            assert_eq!(find_primary_key(&index), Err(vec![]));
+        }
     }
 }
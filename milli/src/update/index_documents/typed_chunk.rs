@@ -1,4 +1,6 @@
 use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fs::File;
 use std::io;
@@ -7,6 +9,7 @@ use grenad::MergerBuilder;
 use heed::types::ByteSlice;
 use heed::{BytesDecode, RwTxn};
 use roaring::RoaringBitmap;
+use rstar::RTree;
 
 use super::helpers::{
     self, merge_ignore_values, roaring_bitmap_from_u32s_array, serialize_roaring_bitmap,
@@ -16,17 +19,20 @@ use super::{ClonableMmap, MergeFn};
 use crate::heed_codec::facet::{decode_prefix_string, encode_prefix_string};
 use crate::update::index_documents::helpers::as_cloneable_grenad;
 use crate::{
-    lat_lng_to_xyz, BoRoaringBitmapCodec, CboRoaringBitmapCodec, DocumentId, GeoPoint, Index,
-    Result,
+    lat_lng_to_xyz, try_split_array_at, BoRoaringBitmapCodec, CboRoaringBitmapCodec, DocumentId,
+    FieldId, GeoPoint, Index, Result,
 };
 
 pub(crate) enum TypedChunk {
     DocidWordPositions(grenad::Reader<CursorClonableMmap>),
     FieldIdDocidFacetStrings(grenad::Reader<CursorClonableMmap>),
     FieldIdDocidFacetNumbers(grenad::Reader<CursorClonableMmap>),
+    FieldIdDocidFacetBools(grenad::Reader<File>),
+    FieldIdDocidFacetEmpties(grenad::Reader<File>),
     Documents(grenad::Reader<CursorClonableMmap>),
     FieldIdWordcountDocids(grenad::Reader<File>),
     NewDocumentsIds(RoaringBitmap),
+    StopWordDocids(grenad::Reader<File>),
     WordDocids {
         word_docids_reader: grenad::Reader<File>,
         exact_word_docids_reader: grenad::Reader<File>,
@@ -45,10 +51,13 @@ pub(crate) fn write_typed_chunk_into_index(
     index: &Index,
     wtxn: &mut RwTxn,
     index_is_empty: bool,
+    geo_rtree_rebuild_threshold: Option<usize>,
 ) -> Result<(RoaringBitmap, bool)> {
     let mut is_merged_database = false;
     match typed_chunk {
         TypedChunk::DocidWordPositions(docid_word_positions_iter) => {
+            // the number of words positions added to the documents.
+            let words_count = Cell::new(0);
             write_entries_into_database(
                 docid_word_positions_iter,
                 &index.docid_word_positions,
@@ -57,19 +66,32 @@ pub(crate) fn write_typed_chunk_into_index(
                 |value, buffer| {
                     // ensure that values are unique and ordered
                     let positions = roaring_bitmap_from_u32s_array(value);
+                    words_count.set(words_count.get() + positions.len());
                     BoRoaringBitmapCodec::serialize_into(&positions, buffer);
                     Ok(buffer)
                 },
                 |new_values, db_values, buffer| {
                     let new_values = roaring_bitmap_from_u32s_array(new_values);
                     let positions = match BoRoaringBitmapCodec::bytes_decode(db_values) {
-                        Some(db_values) => new_values | db_values,
+                        Some(db_values) => {
+                            let merged = new_values | &db_values;
+                            words_count.set(words_count.get() + merged.len() - db_values.len());
+                            merged
+                        }
                         None => new_values, // should not happen
                     };
                     BoRoaringBitmapCodec::serialize_into(&positions, buffer);
                     Ok(())
                 },
             )?;
+            match index.documents_words_count(wtxn)? {
+                Some(count) => index.put_documents_words_count(wtxn, count + words_count.get())?,
+                // the words are counted from the first documents of the index.
+                None if index_is_empty => {
+                    index.put_documents_words_count(wtxn, words_count.get())?
+                }
+                None => (),
+            }
         }
         TypedChunk::Documents(obkv_documents_iter) => {
             let mut cursor = obkv_documents_iter.into_cursor()?;
@@ -91,6 +113,17 @@ pub(crate) fn write_typed_chunk_into_index(
         TypedChunk::NewDocumentsIds(documents_ids) => {
             return Ok((documents_ids, is_merged_database))
         }
+        TypedChunk::StopWordDocids(stop_word_docids_iter) => {
+            // the chunks of the same stop words are sent separately and must always be merged.
+            write_entries_into_database(
+                stop_word_docids_iter,
+                &index.stop_word_docids,
+                wtxn,
+                false,
+                |value, _buffer| Ok(value),
+                merge_roaring_bitmaps,
+            )?;
+        }
         TypedChunk::WordDocids { word_docids_reader, exact_word_docids_reader } => {
             let word_docids_iter = unsafe { as_cloneable_grenad(&word_docids_reader) }?;
             append_entries_into_database(
@@ -177,6 +210,37 @@ pub(crate) fn write_typed_chunk_into_index(
                 }
             }
         }
+        TypedChunk::FieldIdDocidFacetBools(fid_docid_facet_bool) => {
+            let mut bools_docids: HashMap<(FieldId, bool), RoaringBitmap> = HashMap::new();
+            let mut cursor = fid_docid_facet_bool.into_cursor()?;
+            while let Some((key, _value)) = cursor.move_on_next()? {
+                let (fid, rest) = try_split_array_at(key).unwrap();
+                let (docid, rest) = try_split_array_at(rest).unwrap();
+                let fid = FieldId::from_be_bytes(fid);
+                let docid = DocumentId::from_be_bytes(docid);
+                let boolean = rest == [1];
+                bools_docids.entry((fid, boolean)).or_default().insert(docid);
+            }
+            for ((fid, boolean), docids) in bools_docids {
+                let docids = docids | index.boolean_faceted_documents_ids(wtxn, fid, boolean)?;
+                index.put_boolean_faceted_documents_ids(wtxn, fid, boolean, &docids)?;
+            }
+        }
+        TypedChunk::FieldIdDocidFacetEmpties(fid_docid_facet_empty) => {
+            let mut empties_docids: HashMap<FieldId, RoaringBitmap> = HashMap::new();
+            let mut cursor = fid_docid_facet_empty.into_cursor()?;
+            while let Some((key, _value)) = cursor.move_on_next()? {
+                let (fid, rest) = try_split_array_at(key).unwrap();
+                let (docid, _) = try_split_array_at(rest).unwrap();
+                let fid = FieldId::from_be_bytes(fid);
+                let docid = DocumentId::from_be_bytes(docid);
+                empties_docids.entry(fid).or_default().insert(docid);
+            }
+            for (fid, docids) in empties_docids {
+                let docids = docids | index.empty_faceted_documents_ids(wtxn, fid)?;
+                index.put_empty_faceted_documents_ids(wtxn, fid, &docids)?;
+            }
+        }
         TypedChunk::FieldIdFacetStringDocids(facet_id_string_docids) => {
             append_entries_into_database(
                 facet_id_string_docids,
@@ -197,7 +261,7 @@ pub(crate) fn write_typed_chunk_into_index(
             is_merged_database = true;
         }
         TypedChunk::GeoPoints(geo_points) => {
-            let mut rtree = index.geo_rtree(wtxn)?.unwrap_or_default();
+            let mut points = Vec::new();
             let mut geo_faceted_docids = index.geo_faceted_documents_ids(wtxn)?;
 
             let mut cursor = geo_points.into_cursor()?;
@@ -211,10 +275,36 @@ pub(crate) fn write_typed_chunk_into_index(
                 let point = [f64::from_ne_bytes(lat), f64::from_ne_bytes(lng)];
                 let xyz_point = lat_lng_to_xyz(&point);
 
-                rtree.insert(GeoPoint::new(xyz_point, (docid, point)));
+                points.push(GeoPoint::new(xyz_point, (docid, point)));
                 geo_faceted_docids.insert(docid);
             }
-            index.put_geo_rtree(wtxn, &rtree)?;
+
+            let rtree = index.geo_rtree(wtxn)?;
+            let previous_insertions = index.geo_rtree_insertions(wtxn)?;
+            let insertions = previous_insertions + points.len() as u64;
+            // the tree is bulk loaded when it is first built and when too many points were
+            // inserted one by one, by default more than the points it was last built with.
+            // Either way the whole tree is deserialized and written back to the database.
+            let threshold = match geo_rtree_rebuild_threshold {
+                Some(threshold) => threshold as u64,
+                None => rtree
+                    .as_ref()
+                    .map_or(0, |rtree| (rtree.size() as u64).saturating_sub(previous_insertions)),
+            };
+            match rtree {
+                Some(mut rtree) if insertions <= threshold => {
+                    for point in points {
+                        rtree.insert(point);
+                    }
+                    index.put_geo_rtree(wtxn, &rtree)?;
+                    index.put_geo_rtree_insertions(wtxn, insertions)?;
+                }
+                rtree => {
+                    points.extend(rtree.iter().flat_map(|rtree| rtree.iter()).cloned());
+                    index.put_geo_rtree(wtxn, &RTree::bulk_load(points))?;
+                    index.put_geo_rtree_insertions(wtxn, 0)?;
+                }
+            }
             index.put_geo_faceted_documents_ids(wtxn, &geo_faceted_docids)?;
         }
     }
@@ -11,6 +11,11 @@ pub struct IndexerConfig {
     pub chunk_compression_level: Option<u32>,
     pub thread_pool: Option<ThreadPool>,
     pub max_positions_per_attributes: Option<u32>,
+    /// The number of points inserted one by one in the geo rtree after which it is
+    /// entirely rebuilt, defaults to the number of points it was last built with.
+    /// The rtree is stored in a single entry and still read and written entirely for
+    /// each batch, the insertions only avoid bulk loading all of its points again.
+    pub geo_rtree_rebuild_threshold: Option<usize>,
 }
 
 impl Default for IndexerConfig {
@@ -24,6 +29,7 @@ impl Default for IndexerConfig {
             chunk_compression_level: None,
             thread_pool: None,
             max_positions_per_attributes: None,
+            geo_rtree_rebuild_threshold: None,
         }
     }
 }
@@ -0,0 +1,244 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::{self, Seek, SeekFrom};
+
+use heed::RoTxn;
+
+use crate::documents::{DocumentBatchBuilder, DocumentBatchReader};
+use crate::error::UserError;
+use crate::update::{
+    DocumentAdditionResult, IndexDocuments, IndexDocumentsConfig, IndexDocumentsMethod,
+    IndexerConfig, UpdateIndexingStep,
+};
+use crate::{obkv_to_json_bytes, DocumentId, Index, Result};
+
+/// What to do with a document of the source index whose primary key is already used by a
+/// document of the destination index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// The document of the destination index is kept.
+    Skip,
+    /// The document of the source index replaces the one of the destination index.
+    Overwrite,
+}
+
+impl Default for CollisionPolicy {
+    fn default() -> Self {
+        Self::Overwrite
+    }
+}
+
+/// Adds the documents of a source index to the destination index, e.g. to consolidate the
+/// shards of a collection of documents.
+///
+/// The documents are read from the obkv stored in the source index, their fields are sent by
+/// name and take the fields ids of the destination index. They are then indexed with the
+/// settings of the destination index like any other document addition.
+pub struct MergeIndexes<'t, 'u, 'i, 'a> {
+    wtxn: &'t mut heed::RwTxn<'i, 'u>,
+    index: &'i Index,
+    indexer_config: &'a IndexerConfig,
+    source: &'a Index,
+    source_rtxn: &'a RoTxn<'a>,
+    collision_policy: CollisionPolicy,
+}
+
+impl<'t, 'u, 'i, 'a> MergeIndexes<'t, 'u, 'i, 'a> {
+    pub fn new(
+        wtxn: &'t mut heed::RwTxn<'i, 'u>,
+        index: &'i Index,
+        indexer_config: &'a IndexerConfig,
+        source: &'a Index,
+        source_rtxn: &'a RoTxn<'a>,
+    ) -> MergeIndexes<'t, 'u, 'i, 'a> {
+        MergeIndexes {
+            wtxn,
+            index,
+            indexer_config,
+            source,
+            source_rtxn,
+            collision_policy: CollisionPolicy::default(),
+        }
+    }
+
+    pub fn collision_policy(&mut self, policy: CollisionPolicy) {
+        self.collision_policy = policy;
+    }
+
+    /// Indexes the documents of the source index in the destination index. The indexes must
+    /// have the same primary key, an index without primary key takes the one of the other.
+    pub fn execute<F>(self, progress: F) -> Result<DocumentAdditionResult>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        let MergeIndexes { wtxn, index, indexer_config, source, source_rtxn, collision_policy } =
+            self;
+
+        let primary_key = index.primary_key(wtxn)?.map(String::from);
+        match (primary_key, source.primary_key(source_rtxn)?) {
+            (Some(primary_key), Some(source_primary_key)) if primary_key != source_primary_key => {
+                return Err(UserError::PrimaryKeyCannotBeChanged(primary_key).into());
+            }
+            (None, Some(source_primary_key)) => {
+                // an index without primary key doesn't contain any document.
+                index.put_primary_key(wtxn, source_primary_key)?;
+            }
+            _ => (),
+        }
+
+        // the documents of the source are skipped by internal id, they are only known by their
+        // external id in the destination.
+        let skipped: HashSet<DocumentId> = match collision_policy {
+            CollisionPolicy::Skip => {
+                let external_ids = index.external_documents_ids(wtxn)?;
+                source
+                    .external_documents_ids(source_rtxn)?
+                    .to_hash_map()
+                    .into_iter()
+                    .filter(|(external_id, _)| external_ids.get(external_id).is_some())
+                    .map(|(_, docid)| docid)
+                    .collect()
+            }
+            CollisionPolicy::Overwrite => HashSet::new(),
+        };
+
+        let fields_ids_map = source.fields_ids_map(source_rtxn)?;
+        let mut file = tempfile::tempfile()?;
+        let mut builder = DocumentBatchBuilder::new(&mut file).map_err(documents_error)?;
+        for result in source.all_documents(source_rtxn)? {
+            let (docid, obkv) = result?;
+            if skipped.contains(&docid) {
+                continue;
+            }
+            // the JSON stored by the source is kept as it is.
+            let json = match source.document_json(source_rtxn, docid)? {
+                Some(json) => Cow::Borrowed(json),
+                None => Cow::Owned(obkv_to_json_bytes(&fields_ids_map, obkv)?),
+            };
+            builder.extend_from_json(&json[..]).map_err(documents_error)?;
+        }
+        builder.finish().map_err(documents_error)?;
+        file.seek(SeekFrom::Start(0))?;
+        let documents = DocumentBatchReader::from_reader(file).map_err(documents_error)?;
+
+        let config = IndexDocumentsConfig {
+            update_method: IndexDocumentsMethod::ReplaceDocuments,
+            ..Default::default()
+        };
+        let mut builder = IndexDocuments::new(wtxn, index, indexer_config, config, progress)?;
+        builder.add_documents(documents)?;
+        builder.execute()
+    }
+}
+
+/// The documents are written from valid JSON objects, only the temporary file can fail.
+fn documents_error(error: crate::documents::Error) -> crate::Error {
+    match error {
+        crate::documents::Error::Io(error) => error.into(),
+        error => io::Error::new(io::ErrorKind::Other, error).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use big_s::S;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::index::tests::TempIndex;
+
+    fn index_with(content: DocumentBatchReader<io::Cursor<Vec<u8>>>) -> TempIndex {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let mut wtxn = index.write_txn().unwrap();
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+        index
+    }
+
+    fn merged_documents(policy: CollisionPolicy) -> Vec<Value> {
+        let index = index_with(documents!([
+            { "id": 1, "name": "kevin" },
+            { "id": 2, "name": "jean" },
+        ]));
+        let source = index_with(documents!([
+            { "id": 2, "age": 20, "name": "bob" },
+            { "id": 3, "age": 30 },
+        ]));
+
+        let config = IndexerConfig::default();
+        let source_rtxn = source.read_txn().unwrap();
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = MergeIndexes::new(&mut wtxn, &index, &config, &source, &source_rtxn);
+        builder.collision_policy(policy);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+        let fields: Vec<_> = fields_ids_map.ids().collect();
+        let mut documents: Vec<_> = index
+            .all_documents(&rtxn)
+            .unwrap()
+            .map(|result| {
+                let (_, obkv) = result.unwrap();
+                Value::Object(crate::obkv_to_json(&fields, &fields_ids_map, obkv).unwrap())
+            })
+            .collect();
+        documents.sort_by_key(|document| document["id"].as_u64());
+
+        // the merged documents are searchable in the destination index.
+        let mut search = index.search(&rtxn);
+        search.query("bob");
+        let found = !search.execute().unwrap().documents_ids.is_empty();
+        assert_eq!(found, policy == CollisionPolicy::Overwrite);
+
+        documents
+    }
+
+    #[test]
+    fn merge_overwrite() {
+        let documents = merged_documents(CollisionPolicy::Overwrite);
+        assert_eq!(
+            documents,
+            vec![
+                json!({ "id": 1, "name": "kevin" }),
+                json!({ "id": 2, "age": 20, "name": "bob" }),
+                json!({ "id": 3, "age": 30 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_skip() {
+        let documents = merged_documents(CollisionPolicy::Skip);
+        assert_eq!(
+            documents,
+            vec![
+                json!({ "id": 1, "name": "kevin" }),
+                json!({ "id": 2, "name": "jean" }),
+                json!({ "id": 3, "age": 30 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_different_primary_keys() {
+        let index = index_with(documents!([{ "id": 1, "name": "kevin" }]));
+        let source = index_with(documents!([{ "uid": 1, "name": "jean" }]));
+
+        let config = IndexerConfig::default();
+        let source_rtxn = source.read_txn().unwrap();
+        let mut wtxn = index.write_txn().unwrap();
+        let builder = MergeIndexes::new(&mut wtxn, &index, &config, &source, &source_rtxn);
+        let error = builder.execute(|_| ()).unwrap_err();
+        assert!(matches!(
+            error,
+            crate::Error::UserError(UserError::PrimaryKeyCannotBeChanged(ref key)) if *key == S("id")
+        ));
+    }
+}
@@ -2,10 +2,13 @@ pub use self::available_documents_ids::AvailableDocumentsIds;
 pub use self::clear_documents::ClearDocuments;
 pub use self::delete_documents::{DeleteDocuments, DocumentDeletionResult};
 pub use self::facets::Facets;
+pub(crate) use self::index_documents::{reindex_stored_documents, validate_document_id};
 pub use self::index_documents::{
-    DocumentAdditionResult, IndexDocuments, IndexDocumentsConfig, IndexDocumentsMethod,
+    DocumentAdditionResult, DocumentAdditionSummary, DuplicatePrimaryKeys, IndexDocuments,
+    IndexDocumentsConfig, IndexDocumentsMethod, RejectedDocument,
 };
 pub use self::indexer_config::IndexerConfig;
+pub use self::merge_indexes::{CollisionPolicy, MergeIndexes};
 pub use self::settings::{Setting, Settings};
 pub use self::update_step::UpdateIndexingStep;
 pub use self::word_prefix_docids::WordPrefixDocids;
@@ -19,6 +22,7 @@ mod delete_documents;
 mod facets;
 mod index_documents;
 mod indexer_config;
+mod merge_indexes;
 mod settings;
 mod update_step;
 mod word_prefix_docids;
@@ -3,17 +3,23 @@ use std::result::Result as StdResult;
 
 use charabia::{Tokenizer, TokenizerBuilder};
 use itertools::Itertools;
+use roaring::RoaringBitmap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use time::OffsetDateTime;
 
 use super::index_documents::{IndexDocumentsConfig, Transform};
 use super::IndexerConfig;
 use crate::criterion::Criterion;
-use crate::error::UserError;
-use crate::index::{DEFAULT_MIN_WORD_LEN_ONE_TYPO, DEFAULT_MIN_WORD_LEN_TWO_TYPOS};
-use crate::update::index_documents::IndexDocumentsMethod;
+use crate::error::{InternalError, UserError};
+use crate::index::{
+    AttributeTypoTolerance, GeoDistance, MaxWordLength, TypoBudget, DEFAULT_MIN_WORD_LEN_ONE_TYPO,
+    DEFAULT_MIN_WORD_LEN_TWO_TYPOS,
+};
+use crate::update::index_documents::{
+    create_writer, write_into_lmdb_database, writer_into_reader, IndexDocumentsMethod,
+};
 use crate::update::{ClearDocuments, IndexDocuments, UpdateIndexingStep};
-use crate::{FieldsIdsMap, Index, Result};
+use crate::{FieldsIdsMap, Index, Result, Segmentation};
 
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub enum Setting<T> {
@@ -90,22 +96,54 @@ pub struct Settings<'a, 't, 'u, 'i> {
     indexer_config: &'a IndexerConfig,
 
     searchable_fields: Setting<Vec<String>>,
+    /// The weights of the searchable fields, the attribute criterion ranks the fields
+    /// with a higher weight first, whatever their order.
+    searchable_fields_weights: Setting<HashMap<String, u16>>,
     displayed_fields: Setting<Vec<String>>,
     filterable_fields: Setting<HashSet<String>>,
+    /// Filterable fields whose values are compared regardless of their case and accents.
+    normalized_filterable_fields: Setting<HashSet<String>>,
+    /// The canonical values the raw string values of the faceted fields are
+    /// replaced by, by field name then raw value.
+    facet_value_mappings: Setting<HashMap<String, HashMap<String, String>>>,
     sortable_fields: Setting<HashSet<String>>,
     criteria: Setting<Vec<String>>,
     stop_words: Setting<BTreeSet<String>>,
-    distinct_field: Setting<String>,
+    distinct_fields: Setting<Vec<String>>,
     synonyms: Setting<HashMap<String, Vec<String>>>,
     primary_key: Setting<String>,
     authorize_typos: Setting<bool>,
     min_word_len_two_typos: Setting<u8>,
     min_word_len_one_typo: Setting<u8>,
+    /// Typo tolerance overrides, by attribute name.
+    attributes_typo_tolerance: Setting<HashMap<String, AttributeTypoTolerance>>,
+    /// How the number of typos allowed on a query word is computed.
+    typo_budget: Setting<TypoBudget>,
+    /// Whether a query word can match a word with a different first char.
+    allow_typo_on_first_char: Setting<bool>,
+    /// Words on which typo tolerance is disabled: they are not derived with typos
+    /// and a query word with typos never matches them.
     exact_words: Setting<BTreeSet<String>>,
     /// Attributes on which typo tolerance is disabled.
     exact_attributes: Setting<HashSet<String>>,
     max_values_per_facet: Setting<usize>,
     pagination_max_total_hits: Setting<usize>,
+    /// Characters the documents and the queries are split on, in addition to the default ones.
+    separators: Setting<BTreeSet<char>>,
+    /// Default separators that are kept inside the words.
+    non_separators: Setting<BTreeSet<char>>,
+    /// Words that are never split by the tokenizer, e.g. `c++` or `node.js`.
+    dictionary: Setting<BTreeSet<String>>,
+    /// The thousands separators of the numbers, `,` for `1,000` or `.` for `1.000`.
+    digit_group_separators: Setting<BTreeSet<char>>,
+    /// Maximum number of chars of the indexed words.
+    max_word_length: Setting<MaxWordLength>,
+    /// Minimum number of chars of the words indexed as prefixes and searched as prefixes.
+    min_word_size_for_prefix: Setting<u8>,
+    /// How the distances of the `_geoPoint` sort and of the `_geoRadius` filter are computed.
+    geo_distance: Setting<GeoDistance>,
+    /// Whether the JSON of the documents is written at indexing time to be returned by the search.
+    store_documents_json: Setting<bool>,
 }
 
 impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
@@ -118,31 +156,58 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
             wtxn,
             index,
             searchable_fields: Setting::NotSet,
+            searchable_fields_weights: Setting::NotSet,
             displayed_fields: Setting::NotSet,
             filterable_fields: Setting::NotSet,
+            normalized_filterable_fields: Setting::NotSet,
+            facet_value_mappings: Setting::NotSet,
             sortable_fields: Setting::NotSet,
             criteria: Setting::NotSet,
             stop_words: Setting::NotSet,
-            distinct_field: Setting::NotSet,
+            distinct_fields: Setting::NotSet,
             synonyms: Setting::NotSet,
             primary_key: Setting::NotSet,
             authorize_typos: Setting::NotSet,
             exact_words: Setting::NotSet,
             min_word_len_two_typos: Setting::NotSet,
             min_word_len_one_typo: Setting::NotSet,
+            attributes_typo_tolerance: Setting::NotSet,
+            typo_budget: Setting::NotSet,
+            allow_typo_on_first_char: Setting::NotSet,
             exact_attributes: Setting::NotSet,
             max_values_per_facet: Setting::NotSet,
             pagination_max_total_hits: Setting::NotSet,
+            separators: Setting::NotSet,
+            non_separators: Setting::NotSet,
+            dictionary: Setting::NotSet,
+            digit_group_separators: Setting::NotSet,
+            max_word_length: Setting::NotSet,
+            min_word_size_for_prefix: Setting::NotSet,
+            geo_distance: Setting::NotSet,
+            store_documents_json: Setting::NotSet,
             indexer_config,
         }
     }
 
     pub fn reset_searchable_fields(&mut self) {
         self.searchable_fields = Setting::Reset;
+        self.searchable_fields_weights = Setting::Reset;
     }
 
     pub fn set_searchable_fields(&mut self, names: Vec<String>) {
         self.searchable_fields = Setting::Set(names);
+        self.searchable_fields_weights = Setting::Reset;
+    }
+
+    /// Sets the searchable fields along with their weights, the fields sharing the same
+    /// weight are ranked by their order. The first weight of a duplicated field is kept.
+    pub fn set_weighted_searchable_fields(&mut self, fields: Vec<(String, u16)>) {
+        let mut weights = HashMap::new();
+        for (name, weight) in &fields {
+            weights.entry(name.clone()).or_insert(*weight);
+        }
+        self.searchable_fields = Setting::Set(fields.into_iter().map(|(name, _)| name).collect());
+        self.searchable_fields_weights = Setting::Set(weights);
     }
 
     pub fn reset_displayed_fields(&mut self) {
@@ -161,6 +226,22 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.filterable_fields = Setting::Set(names);
     }
 
+    pub fn reset_normalized_filterable_fields(&mut self) {
+        self.normalized_filterable_fields = Setting::Reset;
+    }
+
+    pub fn set_normalized_filterable_fields(&mut self, names: HashSet<String>) {
+        self.normalized_filterable_fields = Setting::Set(names);
+    }
+
+    pub fn reset_facet_value_mappings(&mut self) {
+        self.facet_value_mappings = Setting::Reset;
+    }
+
+    pub fn set_facet_value_mappings(&mut self, mappings: HashMap<String, HashMap<String, String>>) {
+        self.facet_value_mappings = Setting::Set(mappings);
+    }
+
     pub fn set_sortable_fields(&mut self, names: HashSet<String>) {
         self.sortable_fields = Setting::Set(names);
     }
@@ -187,11 +268,18 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
     }
 
     pub fn reset_distinct_field(&mut self) {
-        self.distinct_field = Setting::Reset;
+        self.distinct_fields = Setting::Reset;
     }
 
     pub fn set_distinct_field(&mut self, distinct_field: String) {
-        self.distinct_field = Setting::Set(distinct_field);
+        self.distinct_fields = Setting::Set(vec![distinct_field]);
+    }
+
+    /// Deduplicates the documents on the combination of the values of these fields,
+    /// a document missing some of the fields is only deduplicated on the others.
+    pub fn set_distinct_fields(&mut self, distinct_fields: Vec<String>) {
+        self.distinct_fields =
+            if distinct_fields.is_empty() { Setting::Reset } else { Setting::Set(distinct_fields) }
     }
 
     pub fn reset_synonyms(&mut self) {
@@ -234,6 +322,33 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.min_word_len_one_typo = Setting::Reset;
     }
 
+    pub fn set_attributes_typo_tolerance(
+        &mut self,
+        tolerances: HashMap<String, AttributeTypoTolerance>,
+    ) {
+        self.attributes_typo_tolerance = Setting::Set(tolerances);
+    }
+
+    pub fn reset_attributes_typo_tolerance(&mut self) {
+        self.attributes_typo_tolerance = Setting::Reset;
+    }
+
+    pub fn set_typo_budget(&mut self, budget: TypoBudget) {
+        self.typo_budget = Setting::Set(budget);
+    }
+
+    pub fn reset_typo_budget(&mut self) {
+        self.typo_budget = Setting::Reset;
+    }
+
+    pub fn set_allow_typo_on_first_char(&mut self, flag: bool) {
+        self.allow_typo_on_first_char = Setting::Set(flag);
+    }
+
+    pub fn reset_allow_typo_on_first_char(&mut self) {
+        self.allow_typo_on_first_char = Setting::Reset;
+    }
+
     pub fn set_exact_words(&mut self, words: BTreeSet<String>) {
         self.exact_words = Setting::Set(words);
     }
@@ -262,6 +377,70 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.pagination_max_total_hits = Setting::Set(value);
     }
 
+    pub fn set_separators(&mut self, separators: BTreeSet<char>) {
+        self.separators = Setting::Set(separators);
+    }
+
+    pub fn reset_separators(&mut self) {
+        self.separators = Setting::Reset;
+    }
+
+    pub fn set_non_separators(&mut self, non_separators: BTreeSet<char>) {
+        self.non_separators = Setting::Set(non_separators);
+    }
+
+    pub fn reset_non_separators(&mut self) {
+        self.non_separators = Setting::Reset;
+    }
+
+    pub fn set_dictionary(&mut self, words: BTreeSet<String>) {
+        self.dictionary = Setting::Set(words);
+    }
+
+    pub fn reset_dictionary(&mut self) {
+        self.dictionary = Setting::Reset;
+    }
+
+    pub fn set_digit_group_separators(&mut self, separators: BTreeSet<char>) {
+        self.digit_group_separators = Setting::Set(separators);
+    }
+
+    pub fn reset_digit_group_separators(&mut self) {
+        self.digit_group_separators = Setting::Reset;
+    }
+
+    pub fn set_max_word_length(&mut self, max_word_length: MaxWordLength) {
+        self.max_word_length = Setting::Set(max_word_length);
+    }
+
+    pub fn reset_max_word_length(&mut self) {
+        self.max_word_length = Setting::Reset;
+    }
+
+    pub fn set_min_word_size_for_prefix(&mut self, size: u8) {
+        self.min_word_size_for_prefix = Setting::Set(size);
+    }
+
+    pub fn reset_min_word_size_for_prefix(&mut self) {
+        self.min_word_size_for_prefix = Setting::Reset;
+    }
+
+    pub fn set_geo_distance(&mut self, geo_distance: GeoDistance) {
+        self.geo_distance = Setting::Set(geo_distance);
+    }
+
+    pub fn reset_geo_distance(&mut self) {
+        self.geo_distance = Setting::Reset;
+    }
+
+    pub fn set_store_documents_json(&mut self, flag: bool) {
+        self.store_documents_json = Setting::Set(flag);
+    }
+
+    pub fn reset_store_documents_json(&mut self) {
+        self.store_documents_json = Setting::Reset;
+    }
+
     pub fn reset_pagination_max_total_hits(&mut self) {
         self.pagination_max_total_hits = Setting::Reset;
     }
@@ -312,6 +491,135 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         Ok(())
     }
 
+    /// Reindexes the given documents only, the other documents of the index are
+    /// not impacted by the settings update.
+    fn reindex_documents<F>(&mut self, cb: &F, documents_ids: RoaringBitmap) -> Result<()>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        if documents_ids.is_empty() {
+            return Ok(());
+        }
+
+        let transform = Transform::new(
+            self.wtxn,
+            &self.index,
+            &self.indexer_config,
+            IndexDocumentsMethod::ReplaceDocuments,
+            false,
+        )?;
+        let output = transform.reindex_documents(self.wtxn, documents_ids)?;
+
+        let indexing_builder = IndexDocuments::new(
+            self.wtxn,
+            self.index,
+            &self.indexer_config,
+            IndexDocumentsConfig::default(),
+            &cb,
+        )?;
+        indexing_builder.execute_raw(output)?;
+
+        Ok(())
+    }
+
+    /// When fields were only added to the faceted fields, only the facet databases of the new
+    /// faceted fields are built from the stored documents, the other databases don't change.
+    fn index_new_facets<F>(&mut self, cb: &F, old_faceted_fields: HashSet<String>) -> Result<()>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        if self.index.number_of_documents(&self.wtxn)? == 0 {
+            return Ok(());
+        }
+
+        let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+        let user_defined_facets = self.index.user_defined_faceted_fields(self.wtxn)?;
+        let new_facets: HashSet<String> = fields_ids_map
+            .names()
+            .filter(|&field| crate::is_faceted(field, &user_defined_facets))
+            .map(String::from)
+            .collect();
+        self.index.put_faceted_fields(self.wtxn, &new_facets)?;
+
+        let fields_ids: HashSet<_> = new_facets
+            .difference(&old_faceted_fields)
+            .filter_map(|name| fields_ids_map.id(name))
+            .collect();
+        if fields_ids.is_empty() {
+            return Ok(());
+        }
+
+        let transform = Transform::new(
+            self.wtxn,
+            &self.index,
+            &self.indexer_config,
+            IndexDocumentsMethod::ReplaceDocuments,
+            false,
+        )?;
+        let documents_ids = self.index.documents_ids(self.wtxn)?;
+        let output = transform.reindex_documents(self.wtxn, documents_ids)?;
+
+        let indexing_builder = IndexDocuments::new(
+            self.wtxn,
+            self.index,
+            &self.indexer_config,
+            IndexDocumentsConfig::default(),
+            &cb,
+        )?;
+        indexing_builder.execute_facets(output, fields_ids)
+    }
+
+    /// Only rebuilds the words prefixes fst and the prefix databases, the words of the
+    /// documents don't change.
+    fn index_words_prefixes<F>(&mut self, cb: &F) -> Result<()>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        if self.index.number_of_documents(&self.wtxn)? == 0 {
+            return Ok(());
+        }
+
+        let indexing_builder = IndexDocuments::new(
+            self.wtxn,
+            self.index,
+            &self.indexer_config,
+            IndexDocumentsConfig::default(),
+            &cb,
+        )?;
+        indexing_builder.execute_words_prefixes()
+    }
+
+    /// When words were only added to the stop words, only the documents that contain them
+    /// must be reindexed. The words that are no more stop words could be in any document.
+    fn reindex_stop_words<F>(
+        &mut self,
+        cb: &F,
+        old_fields_ids_map: FieldsIdsMap,
+        old_stop_words: BTreeSet<String>,
+    ) -> Result<()>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        let new_stop_words = self.current_stop_words()?;
+
+        if old_stop_words.is_subset(&new_stop_words) {
+            let mut documents_ids = RoaringBitmap::new();
+            for word in new_stop_words.difference(&old_stop_words) {
+                if let Some(docids) = self.index.word_docids.get(self.wtxn, word)? {
+                    documents_ids |= docids;
+                }
+                if let Some(docids) = self.index.exact_word_docids.get(self.wtxn, word)? {
+                    documents_ids |= docids;
+                }
+            }
+            // the word databases still contain the soft deleted documents.
+            documents_ids -= self.index.soft_deleted_documents_ids(self.wtxn)?;
+            self.reindex_documents(cb, documents_ids)
+        } else {
+            self.reindex(cb, old_fields_ids_map)
+        }
+    }
+
     fn update_displayed(&mut self) -> Result<bool> {
         match self.displayed_fields {
             Setting::Set(ref fields) => {
@@ -328,12 +636,21 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
     }
 
     fn update_distinct_field(&mut self) -> Result<bool> {
-        match self.distinct_field {
-            Setting::Set(ref attr) => {
-                self.index.put_distinct_field(self.wtxn, &attr)?;
-            }
+        match self.distinct_fields {
+            Setting::Set(ref fields) => match fields.as_slice() {
+                [field] => {
+                    self.index.put_distinct_field(self.wtxn, field)?;
+                    self.index.delete_distinct_fields(self.wtxn)?;
+                }
+                fields => {
+                    let fields: Vec<_> = fields.iter().map(String::as_str).collect();
+                    self.index.put_distinct_fields(self.wtxn, &fields)?;
+                    self.index.delete_distinct_field(self.wtxn)?;
+                }
+            },
             Setting::Reset => {
                 self.index.delete_distinct_field(self.wtxn)?;
+                self.index.delete_distinct_fields(self.wtxn)?;
             }
             Setting::NotSet => return Ok(false),
         }
@@ -378,6 +695,20 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         Ok(true)
     }
 
+    /// The weights are only used at search time, changing them doesn't require a reindex.
+    fn update_searchable_weights(&mut self) -> Result<()> {
+        match self.searchable_fields_weights {
+            Setting::Set(ref weights) => {
+                self.index.put_searchable_fields_weights(self.wtxn, weights)?;
+            }
+            Setting::Reset => {
+                self.index.delete_searchable_fields_weights(self.wtxn)?;
+            }
+            Setting::NotSet => (),
+        }
+        Ok(())
+    }
+
     fn update_stop_words(&mut self) -> Result<bool> {
         match self.stop_words {
             Setting::Set(ref stop_words) => {
@@ -402,12 +733,25 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         }
     }
 
-    fn update_synonyms(&mut self) -> Result<bool> {
+    fn current_stop_words(&self) -> Result<BTreeSet<String>> {
+        match self.index.stop_words(self.wtxn)? {
+            Some(stop_words) => Ok(stop_words.stream().into_strs()?.into_iter().collect()),
+            None => Ok(BTreeSet::new()),
+        }
+    }
+
+    fn update_synonyms(&mut self) -> Result<()> {
         match self.synonyms {
             Setting::Set(ref synonyms) => {
-                fn normalize(tokenizer: &Tokenizer<&[u8]>, text: &str) -> Vec<String> {
-                    tokenizer
-                        .tokenize(text)
+                fn normalize(
+                    tokenizer: &Tokenizer<&[u8]>,
+                    segmentation: &Segmentation,
+                    text: &str,
+                ) -> Vec<String> {
+                    let text = segmentation.prepare(text);
+                    segmentation
+                        .merge_tokens(&text, tokenizer.tokenize(&text))
+                        .into_iter()
                         .filter_map(|token| {
                             if token.is_word() {
                                 Some(token.lemma().to_string())
@@ -424,13 +768,16 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
                     builder.stop_words(stop_words);
                 }
                 let tokenizer = builder.build();
+                // the synonyms are split like the queries are.
+                let segmentation = Segmentation::new(self.wtxn, self.index)?;
 
                 let mut new_synonyms = HashMap::new();
                 for (word, synonyms) in synonyms {
                     // Normalize both the word and associated synonyms.
-                    let normalized_word = normalize(&tokenizer, word);
-                    let normalized_synonyms =
-                        synonyms.iter().map(|synonym| normalize(&tokenizer, synonym));
+                    let normalized_word = normalize(&tokenizer, &segmentation, word);
+                    let normalized_synonyms = synonyms
+                        .iter()
+                        .map(|synonym| normalize(&tokenizer, &segmentation, synonym));
 
                     // Store the normalized synonyms under the normalized word,
                     // merging the possible duplicate words.
@@ -448,14 +795,14 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
 
                 if new_synonyms != old_synonyms {
                     self.index.put_synonyms(self.wtxn, &new_synonyms)?;
-                    Ok(true)
-                } else {
-                    Ok(false)
                 }
             }
-            Setting::Reset => Ok(self.index.delete_synonyms(self.wtxn)?),
-            Setting::NotSet => Ok(false),
+            Setting::Reset => {
+                self.index.delete_synonyms(self.wtxn)?;
+            }
+            Setting::NotSet => (),
         }
+        Ok(())
     }
 
     fn update_exact_attributes(&mut self) -> Result<bool> {
@@ -473,6 +820,121 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         }
     }
 
+    /// Returns `true` if the separators changed and the documents must be tokenized again.
+    fn update_separators(&mut self) -> Result<bool> {
+        let old_separators = self.index.separators(self.wtxn)?;
+        match self.separators {
+            Setting::Set(ref separators) => self.index.put_separators(self.wtxn, separators)?,
+            Setting::Reset => {
+                self.index.delete_separators(self.wtxn)?;
+            }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(self.index.separators(self.wtxn)? != old_separators)
+    }
+
+    /// Returns `true` if the non-separators changed and the documents must be tokenized again.
+    fn update_non_separators(&mut self) -> Result<bool> {
+        let old_non_separators = self.index.non_separators(self.wtxn)?;
+        match self.non_separators {
+            Setting::Set(ref non_separators) => {
+                self.index.put_non_separators(self.wtxn, non_separators)?
+            }
+            Setting::Reset => {
+                self.index.delete_non_separators(self.wtxn)?;
+            }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(self.index.non_separators(self.wtxn)? != old_non_separators)
+    }
+
+    /// Returns `true` if the dictionary changed and the documents must be tokenized again.
+    fn update_dictionary(&mut self) -> Result<bool> {
+        let old_dictionary = self.index.dictionary(self.wtxn)?;
+        match self.dictionary {
+            Setting::Set(ref words) => self.index.put_dictionary(self.wtxn, words)?,
+            Setting::Reset => {
+                self.index.delete_dictionary(self.wtxn)?;
+            }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(self.index.dictionary(self.wtxn)? != old_dictionary)
+    }
+
+    /// Returns `true` if the digit group separators changed and the documents must be tokenized again.
+    fn update_digit_group_separators(&mut self) -> Result<bool> {
+        let old_separators = self.index.digit_group_separators(self.wtxn)?;
+        match self.digit_group_separators {
+            Setting::Set(ref separators) => {
+                self.index.put_digit_group_separators(self.wtxn, separators)?
+            }
+            Setting::Reset => {
+                self.index.delete_digit_group_separators(self.wtxn)?;
+            }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(self.index.digit_group_separators(self.wtxn)? != old_separators)
+    }
+
+    /// Returns `true` if the facet value mappings changed, the facet values
+    /// of the documents are mapped when they are extracted.
+    fn update_facet_value_mappings(&mut self) -> Result<bool> {
+        let old_mappings = self.index.facet_value_mappings(self.wtxn)?;
+        match self.facet_value_mappings {
+            Setting::Set(ref mappings) => {
+                self.index.put_facet_value_mappings(self.wtxn, mappings)?;
+            }
+            Setting::Reset => {
+                self.index.delete_facet_value_mappings(self.wtxn)?;
+            }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(self.index.facet_value_mappings(self.wtxn)? != old_mappings)
+    }
+
+    /// Returns `true` if the maximum word length changed and the documents must be tokenized again.
+    fn update_max_word_length(&mut self) -> Result<bool> {
+        let old_max_word_length = self.index.max_word_length(self.wtxn)?;
+        match self.max_word_length {
+            Setting::Set(max_word_length) => {
+                self.index.put_max_word_length(self.wtxn, max_word_length)?
+            }
+            Setting::Reset => {
+                self.index.delete_max_word_length(self.wtxn)?;
+            }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(self.index.max_word_length(self.wtxn)? != old_max_word_length)
+    }
+
+    /// Returns `true` if the minimum word size for prefix changed and the prefixes must be
+    /// computed again, the documents don't need to be reindexed.
+    fn update_min_word_size_for_prefix(&mut self) -> Result<bool> {
+        let old_size = self.index.min_word_size_for_prefix(self.wtxn)?;
+        match self.min_word_size_for_prefix {
+            Setting::Set(size) => self.index.put_min_word_size_for_prefix(self.wtxn, size)?,
+            Setting::Reset => {
+                self.index.delete_min_word_size_for_prefix(self.wtxn)?;
+            }
+            Setting::NotSet => return Ok(false),
+        }
+        Ok(self.index.min_word_size_for_prefix(self.wtxn)? != old_size)
+    }
+
+    /// The normalization is done at search time, changing this setting doesn't require a reindex.
+    fn update_normalized_filterable(&mut self) -> Result<()> {
+        match self.normalized_filterable_fields {
+            Setting::Set(ref fields) => {
+                self.index.put_normalized_filterable_fields(self.wtxn, fields)?;
+            }
+            Setting::Reset => {
+                self.index.delete_normalized_filterable_fields(self.wtxn)?;
+            }
+            Setting::NotSet => (),
+        }
+        Ok(())
+    }
+
     fn update_filterable(&mut self) -> Result<()> {
         match self.filterable_fields {
             Setting::Set(ref fields) => {
@@ -600,6 +1062,51 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         Ok(())
     }
 
+    fn update_attributes_typo_tolerance(&mut self) -> Result<()> {
+        match self.attributes_typo_tolerance {
+            Setting::Set(ref tolerances) => {
+                for tolerance in tolerances.values() {
+                    let one = tolerance.min_word_len_one_typo;
+                    let two = tolerance.min_word_len_two_typos;
+                    if one > two {
+                        return Err(UserError::InvalidMinTypoWordLenSetting(one, two).into());
+                    }
+                }
+                self.index.put_attributes_typo_tolerance(&mut self.wtxn, tolerances)?;
+            }
+            Setting::Reset => {
+                self.index.delete_attributes_typo_tolerance(&mut self.wtxn)?;
+            }
+            Setting::NotSet => (),
+        }
+
+        Ok(())
+    }
+
+    fn update_typo_budget(&mut self) -> Result<()> {
+        match self.typo_budget {
+            Setting::Set(budget) => self.index.put_typo_budget(&mut self.wtxn, budget)?,
+            Setting::Reset => {
+                self.index.delete_typo_budget(&mut self.wtxn)?;
+            }
+            Setting::NotSet => (),
+        }
+
+        Ok(())
+    }
+
+    fn update_allow_typo_on_first_char(&mut self) -> Result<()> {
+        match self.allow_typo_on_first_char {
+            Setting::Set(flag) => self.index.put_allow_typo_on_first_char(&mut self.wtxn, flag)?,
+            Setting::Reset => {
+                self.index.delete_allow_typo_on_first_char(&mut self.wtxn)?;
+            }
+            Setting::NotSet => (),
+        }
+
+        Ok(())
+    }
+
     fn update_exact_words(&mut self) -> Result<()> {
         match self.exact_words {
             Setting::Set(ref mut words) => {
@@ -646,13 +1153,13 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         Ok(())
     }
 
-    fn update_pagination_max_total_hits(&mut self) -> Result<()> {
-        match self.pagination_max_total_hits {
-            Setting::Set(max) => {
-                self.index.put_pagination_max_total_hits(&mut self.wtxn, max)?;
+    fn update_geo_distance(&mut self) -> Result<()> {
+        match self.geo_distance {
+            Setting::Set(geo_distance) => {
+                self.index.put_geo_distance(&mut self.wtxn, geo_distance)?;
             }
             Setting::Reset => {
-                self.index.delete_pagination_max_total_hits(&mut self.wtxn)?;
+                self.index.delete_geo_distance(&mut self.wtxn)?;
             }
             Setting::NotSet => (),
         }
@@ -660,13 +1167,70 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         Ok(())
     }
 
-    pub fn execute<F>(mut self, progress_callback: F) -> Result<()>
-    where
-        F: Fn(UpdateIndexingStep) + Sync,
-    {
-        self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
+    /// The JSON of the documents already indexed is written or removed right away,
+    /// the documents don't have to be reindexed. The JSON they were sent with is lost,
+    /// it is converted from their obkv.
+    fn update_store_documents_json(&mut self) -> Result<()> {
+        let old_flag = self.index.store_documents_json(self.wtxn)?;
+        match self.store_documents_json {
+            Setting::Set(flag) => self.index.put_store_documents_json(self.wtxn, flag)?,
+            Setting::Reset => {
+                self.index.delete_store_documents_json(self.wtxn)?;
+            }
+            Setting::NotSet => return Ok(()),
+        }
 
-        let old_faceted_fields = self.index.user_defined_faceted_fields(&self.wtxn)?;
+        match (old_flag, self.index.store_documents_json(self.wtxn)?) {
+            (false, true) => {
+                // It is forbidden to keep a cursor and write in a database at the same time with
+                // LMDB therefore the JSON documents are written into a grenad file beforehand.
+                let fields_ids_map = self.index.fields_ids_map(self.wtxn)?;
+                let mut writer = create_writer(
+                    self.indexer_config.chunk_compression_type,
+                    self.indexer_config.chunk_compression_level,
+                    tempfile::tempfile()?,
+                );
+                for result in self.index.documents.iter(self.wtxn)? {
+                    let (docid, obkv) = result?;
+                    let json = crate::obkv_to_json_bytes(&fields_ids_map, obkv)?;
+                    writer.insert(docid.get().to_be_bytes(), json)?;
+                }
+
+                write_into_lmdb_database(
+                    self.wtxn,
+                    *self.index.documents_json.as_polymorph(),
+                    writer_into_reader(writer)?,
+                    |_, _| Err(InternalError::IndexingMergingKeys { process: "documents json" })?,
+                )?;
+            }
+            (true, false) => self.index.documents_json.clear(self.wtxn)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn update_pagination_max_total_hits(&mut self) -> Result<()> {
+        match self.pagination_max_total_hits {
+            Setting::Set(max) => {
+                self.index.put_pagination_max_total_hits(&mut self.wtxn, max)?;
+            }
+            Setting::Reset => {
+                self.index.delete_pagination_max_total_hits(&mut self.wtxn)?;
+            }
+            Setting::NotSet => (),
+        }
+
+        Ok(())
+    }
+
+    pub fn execute<F>(mut self, progress_callback: F) -> Result<()>
+    where
+        F: Fn(UpdateIndexingStep) + Sync,
+    {
+        self.index.set_updated_at(self.wtxn, &OffsetDateTime::now_utc())?;
+
+        let old_faceted_fields = self.index.user_defined_faceted_fields(&self.wtxn)?;
+        let old_real_faceted_fields = self.index.faceted_fields(&self.wtxn)?;
         let old_fields_ids_map = self.index.fields_ids_map(&self.wtxn)?;
 
         self.update_displayed()?;
@@ -677,28 +1241,63 @@ impl<'a, 't, 'u, 'i> Settings<'a, 't, 'u, 'i> {
         self.update_primary_key()?;
         self.update_authorize_typos()?;
         self.update_min_typo_word_len()?;
+        self.update_attributes_typo_tolerance()?;
+        self.update_typo_budget()?;
+        self.update_allow_typo_on_first_char()?;
         self.update_exact_words()?;
+        self.update_normalized_filterable()?;
         self.update_max_values_per_facet()?;
         self.update_pagination_max_total_hits()?;
+        self.update_geo_distance()?;
+        self.update_store_documents_json()?;
 
-        // If there is new faceted fields we indicate that we must reindex as we must
-        // index new fields as facets. It means that the distinct attribute,
+        // If the faceted fields changed we must index the new fields as facets, all the documents
+        // are reindexed when some were removed. It means that the distinct attribute,
         // an Asc/Desc criterion or a filtered attribute as be added or removed.
         let new_faceted_fields = self.index.user_defined_faceted_fields(&self.wtxn)?;
         let faceted_updated = old_faceted_fields != new_faceted_fields;
+        // The geo points are only extracted when the documents are indexed.
+        let only_added_facets = old_faceted_fields.is_subset(&new_faceted_fields)
+            && (old_faceted_fields.contains("_geo") || !new_faceted_fields.contains("_geo"));
+
+        let old_stop_words = self.current_stop_words()?;
 
         let stop_words_updated = self.update_stop_words()?;
-        let synonyms_updated = self.update_synonyms()?;
         let searchable_updated = self.update_searchable()?;
+        self.update_searchable_weights()?;
         let exact_attributes_updated = self.update_exact_attributes()?;
-
-        if stop_words_updated
-            || faceted_updated
-            || synonyms_updated
+        let separators_updated = self.update_separators()?;
+        let non_separators_updated = self.update_non_separators()?;
+        let dictionary_updated = self.update_dictionary()?;
+        let digit_group_separators_updated = self.update_digit_group_separators()?;
+        let max_word_length_updated = self.update_max_word_length()?;
+        // The synonyms are normalized with the new stop words and segmentation but are
+        // only used at search time, they don't require any reindexing.
+        self.update_synonyms()?;
+        let min_word_size_for_prefix_updated = self.update_min_word_size_for_prefix()?;
+        let facet_value_mappings_updated = self.update_facet_value_mappings()?;
+
+        if (faceted_updated && !only_added_facets)
             || searchable_updated
             || exact_attributes_updated
+            || separators_updated
+            || non_separators_updated
+            || dictionary_updated
+            || digit_group_separators_updated
+            || max_word_length_updated
+            || facet_value_mappings_updated
         {
             self.reindex(&progress_callback, old_fields_ids_map)?;
+        } else {
+            if faceted_updated {
+                self.index_new_facets(&progress_callback, old_real_faceted_fields)?;
+            }
+            if stop_words_updated {
+                self.reindex_stop_words(&progress_callback, old_fields_ids_map, old_stop_words)?;
+            }
+            if min_word_size_for_prefix_updated {
+                self.index_words_prefixes(&progress_callback)?;
+            }
         }
 
         Ok(())
@@ -715,8 +1314,10 @@ mod tests {
     use super::*;
     use crate::error::Error;
     use crate::index::tests::TempIndex;
-    use crate::update::IndexDocuments;
-    use crate::{Criterion, Filter, SearchResult};
+    use crate::update::{DeleteDocuments, IndexDocuments};
+    use crate::{
+        AscDesc, Criterion, Filter, LongWords, Member, SearchResult, TermsMatchingStrategy,
+    };
 
     #[test]
     fn set_and_reset_searchable_fields() {
@@ -992,6 +1593,70 @@ mod tests {
         assert_eq!(count, 4);
     }
 
+    #[test]
+    fn add_filterable_fields_without_reindexing() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("age") });
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "kevin", "age": 23, "dog": { "name": "bob", "age": 4 } },
+            { "id": 1, "name": "kevina", "age": 21, "dog": { "name": "max", "age": 2 } },
+            { "id": 2, "name": "benoit", "age": 34 },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let word_docids_before: Vec<_> =
+            index.word_docids.iter(&wtxn).unwrap().map(|r| r.unwrap()).collect();
+        let reindexed = std::sync::atomic::AtomicBool::new(false);
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("age"), S("name"), S("dog") });
+        builder.execute(|_| reindexed.store(true, std::sync::atomic::Ordering::Relaxed)).unwrap();
+        wtxn.commit().unwrap();
+
+        // the documents are not indexed again, only the new facets are built.
+        let rtxn = index.read_txn().unwrap();
+        assert!(!reindexed.into_inner());
+        let word_docids_after: Vec<_> =
+            index.word_docids.iter(&rtxn).unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(word_docids_before, word_docids_after);
+        let facets = index.faceted_fields(&rtxn).unwrap();
+        assert_eq!(facets, hashset! { S("age"), S("name"), S("dog"), S("dog.name"), S("dog.age") });
+
+        let filters = [
+            ("name = kevina", vec![1]),
+            ("dog.age > 3", vec![0]),
+            ("dog.name = max OR age > 30", vec![1, 2]),
+        ];
+        for (filter, expected) in filters.iter() {
+            let filter = Filter::from_str(filter).unwrap().unwrap();
+            let docids = filter.evaluate(&rtxn, &index).unwrap();
+            assert_eq!(docids.into_iter().collect::<Vec<_>>(), *expected);
+        }
+        drop(rtxn);
+
+        // removing a filterable field reindexes the documents.
+        let mut wtxn = index.write_txn().unwrap();
+        let reindexed = std::sync::atomic::AtomicBool::new(false);
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("name") });
+        builder.execute(|_| reindexed.store(true, std::sync::atomic::Ordering::Relaxed)).unwrap();
+        assert!(reindexed.into_inner());
+        let filter = Filter::from_str("name = kevina").unwrap().unwrap();
+        assert_eq!(filter.evaluate(&wtxn, &index).unwrap().len(), 1);
+        wtxn.commit().unwrap();
+    }
+
     #[test]
     fn set_asc_desc_field() {
         let path = tempfile::tempdir().unwrap();
@@ -1194,14 +1859,15 @@ mod tests {
         let expected = fst::Set::from_iter(&set).unwrap();
         assert_eq!(stop_words.as_fst().as_bytes(), expected.as_fst().as_bytes());
 
-        // when we search for something that is a non prefix stop_words it should be ignored
-        // thus we should get a placeholder search (all the results = 3)
+        // when we search for a query only made of stop words they are matched literally
         let result = index.search(&rtxn).query("the ").execute().unwrap();
-        assert_eq!(result.documents_ids.len(), 3);
+        assert_eq!(result.documents_ids.len(), 2);
         let result = index.search(&rtxn).query("i ").execute().unwrap();
-        assert_eq!(result.documents_ids.len(), 3);
+        assert_eq!(result.documents_ids.len(), 1);
         let result = index.search(&rtxn).query("are ").execute().unwrap();
-        assert_eq!(result.documents_ids.len(), 3);
+        assert_eq!(result.documents_ids.len(), 2);
+        // but they are not part of the word databases
+        assert!(index.word_docids.get(&rtxn, "the").unwrap().is_none());
 
         let result = index.search(&rtxn).query("dog").execute().unwrap();
         assert_eq!(result.documents_ids.len(), 2); // we have two maxims talking about doggos
@@ -1234,6 +1900,113 @@ mod tests {
         assert_eq!(result.documents_ids.len(), 1); // there is one benoit in our data
     }
 
+    #[test]
+    fn adding_stop_words_only_reindexes_impacted_documents() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let path = tempfile::tempdir().unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(10 * 1024 * 1024); // 10 MB
+        let index = Index::new(options, &path).unwrap();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "name": "kevin", "age": 23, "maxim": "I love dogs" },
+            { "name": "kevina", "age": 21, "maxim": "Doggos are the best" },
+            { "name": "benoit", "age": 34, "maxim": "The crepes are really good" },
+        ]);
+        let config = IndexerConfig::default();
+        let indexing_config =
+            IndexDocumentsConfig { autogenerate_docids: true, ..Default::default() };
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // Returns the number of documents that were reindexed by the settings update.
+        let update_settings = |update: &dyn Fn(&mut Settings)| {
+            let reindexed = AtomicUsize::new(0);
+            let mut wtxn = index.write_txn().unwrap();
+            let mut builder = Settings::new(&mut wtxn, &index, &config);
+            update(&mut builder);
+            builder
+                .execute(|step| {
+                    if let UpdateIndexingStep::IndexDocuments { total_documents, .. } = step {
+                        reindexed.fetch_max(total_documents, Ordering::Relaxed);
+                    }
+                })
+                .unwrap();
+            wtxn.commit().unwrap();
+            reindexed.into_inner()
+        };
+
+        // Only the first document contains "i".
+        let reindexed = update_settings(&|builder| builder.set_stop_words(btreeset! { S("i") }));
+        assert_eq!(reindexed, 1);
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.word_docids.get(&rtxn, "i").unwrap().is_none());
+        let result = index.search(&rtxn).query("love").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 1);
+        let result = index.search(&rtxn).query("dog").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 2);
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 3);
+        drop(rtxn);
+
+        // The synonyms don't require any reindexing.
+        let reindexed = update_settings(&|builder| {
+            builder.set_synonyms(hashmap! { S("crepes") => vec![S("blini")] })
+        });
+        assert_eq!(reindexed, 0);
+
+        // The removed stop words could be in any document.
+        let reindexed = update_settings(&|builder| builder.reset_stop_words());
+        assert_eq!(reindexed, 3);
+
+        let rtxn = index.read_txn().unwrap();
+        let result = index.search(&rtxn).query("i").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 1);
+    }
+
+    #[test]
+    fn adding_stop_words_keeps_the_soft_deleted_documents_deleted() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "maxim": "I love dogs" },
+            { "id": 1, "maxim": "I love cats" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+
+        let mut builder = DeleteDocuments::new(&mut wtxn, &index).unwrap();
+        builder.soft_deletion(true);
+        builder.delete_external_id("1");
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // both documents contain "i" but the soft deleted one must not be reindexed.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_stop_words(btreeset! { S("i") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.documents_ids(&rtxn).unwrap().len(), 1);
+        assert_eq!(index.number_of_documents(&rtxn).unwrap(), 1);
+        let result = index.search(&rtxn).query("love").execute().unwrap();
+        assert_eq!(result.documents_ids.len(), 1);
+        let result = index.search(&rtxn).query("cats").execute().unwrap();
+        assert!(result.documents_ids.is_empty());
+    }
+
     #[test]
     fn set_and_reset_synonyms() {
         let path = tempfile::tempdir().unwrap();
@@ -1301,6 +2074,26 @@ mod tests {
         assert!(result.documents_ids.is_empty());
     }
 
+    #[test]
+    fn synonyms_normalized_with_segmentation() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        // the digit group separators are set in the same update.
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_digit_group_separators(btreeset! { ',' });
+        builder.set_synonyms(hashmap! {
+            "1,000".to_string() => vec!["thousand".to_string()],
+        });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        let synonyms = index.synonyms(&rtxn).unwrap();
+        assert_eq!(synonyms, hashmap! { vec![S("1000")] => vec![vec![S("thousand")]] });
+    }
+
     #[test]
     fn setting_searchable_recomputes_other_settings() {
         let path = tempfile::tempdir().unwrap();
@@ -1532,6 +2325,123 @@ mod tests {
         assert!(builder.execute(|_| ()).is_err());
     }
 
+    #[test]
+    fn update_invalid_attributes_typo_tolerance() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut txn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut txn, &index, &config);
+        let tolerance = AttributeTypoTolerance {
+            enabled: true,
+            min_word_len_one_typo: 10,
+            min_word_len_two_typos: 7,
+        };
+        builder.set_attributes_typo_tolerance(hashmap! { S("sku") => tolerance });
+        assert!(builder.execute(|_| ()).is_err());
+    }
+
+    #[test]
+    fn update_typo_budget() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "name": "rust" },
+            { "id": 1, "name": "dust" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        // A word of 4 chars doesn't tolerate any typo with the default thresholds.
+        let rtxn = index.read_txn().unwrap();
+        let SearchResult { documents_ids, .. } =
+            index.search(&rtxn).query("rost").execute().unwrap();
+        assert!(documents_ids.is_empty());
+        drop(rtxn);
+
+        let budget = TypoBudget::Length { chars_per_typo: 4, max_typos: 2 };
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_typo_budget(budget);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.typo_budget(&rtxn).unwrap(), budget);
+        let SearchResult { documents_ids, .. } =
+            index.search(&rtxn).query("rost").execute().unwrap();
+        assert_eq!(documents_ids, vec![0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_typo_budget();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.typo_budget(&rtxn).unwrap(), TypoBudget::Thresholds);
+    }
+
+    #[test]
+    fn update_allow_typo_on_first_char() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        // the words of 3 chars tolerate two typos.
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_min_word_len_one_typo(1);
+        builder.set_min_word_len_two_typos(3);
+        builder.execute(|_| ()).unwrap();
+
+        let content = documents!([
+            { "id": 0, "name": "xbc" },
+            { "id": 1, "name": "abd" }
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.allow_typo_on_first_char(&rtxn).unwrap());
+        let SearchResult { documents_ids, .. } =
+            index.search(&rtxn).query("abc").execute().unwrap();
+        assert_eq!(documents_ids, vec![1, 0]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_allow_typo_on_first_char(false);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(!index.allow_typo_on_first_char(&rtxn).unwrap());
+        let SearchResult { documents_ids, .. } =
+            index.search(&rtxn).query("abc").execute().unwrap();
+        assert_eq!(documents_ids, vec![1]);
+        drop(rtxn);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_allow_typo_on_first_char();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.allow_typo_on_first_char(&rtxn).unwrap());
+    }
+
     #[test]
     fn update_exact_words_normalization() {
         let index = TempIndex::new();
@@ -1550,6 +2460,356 @@ mod tests {
         }
     }
 
+    #[test]
+    fn set_and_reset_separators() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "code": "ab/cd" },
+            { "id": 1, "code": "ab_cd" },
+            { "id": 2, "code": "t-shirt" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let search = |query: &str| {
+            let rtxn = index.read_txn().unwrap();
+            let mut documents_ids =
+                index.search(&rtxn).query(query).execute().unwrap().documents_ids;
+            documents_ids.sort_unstable();
+            documents_ids
+        };
+
+        // the underscore joins the words by default.
+        assert_eq!(search("cd "), vec![0]);
+        assert_eq!(search("shirt "), vec![2]);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_separators(btreeset! { '/', '_' });
+        builder.set_non_separators(btreeset! { '-' });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // the documents are tokenized again and the queries are tokenized the same way.
+        assert_eq!(search("cd "), vec![0, 1]);
+        assert_eq!(search("ab_cd "), vec![0, 1]);
+        assert_eq!(search("shirt "), Vec::<u32>::new());
+        assert_eq!(search("t-shirt "), vec![2]);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_separators();
+        builder.reset_non_separators();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.separators(&rtxn).unwrap().is_empty());
+        assert!(index.non_separators(&rtxn).unwrap().is_empty());
+        drop(rtxn);
+        assert_eq!(search("cd "), vec![0]);
+        assert_eq!(search("shirt "), vec![2]);
+    }
+
+    #[test]
+    fn set_digit_group_separators() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "price": "1000" },
+            { "id": 1, "price": "1 000" },
+            { "id": 2, "version": "1.2.3" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let search = |query: &str| {
+            let rtxn = index.read_txn().unwrap();
+            let mut search = index.search(&rtxn);
+            search.query(query).terms_matching_strategy(TermsMatchingStrategy::All);
+            let mut documents_ids = search.execute().unwrap().documents_ids;
+            documents_ids.sort_unstable();
+            documents_ids
+        };
+
+        assert_eq!(search("1000 "), vec![0]);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_digit_group_separators(btreeset! { ',', ' ', '.' });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // the documents and the queries are normalized the same way.
+        assert_eq!(search("1000 "), vec![0, 1]);
+        assert_eq!(search("1,000 "), vec![0, 1]);
+        assert_eq!(search("1 000 "), vec![0, 1]);
+        // the groups of a version are not made of three digits, it is kept as is.
+        assert_eq!(search("1.2.3 "), vec![2]);
+        assert_eq!(search("123 "), Vec::<u32>::new());
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_digit_group_separators();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert!(index.digit_group_separators(&rtxn).unwrap().is_empty());
+        drop(rtxn);
+        assert_eq!(search("1000 "), vec![0]);
+    }
+
+    #[test]
+    fn set_max_word_length() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+        let long_word = "a".repeat(2000);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "title": format!("hello {}", long_word) },
+            { "id": 1, "title": "hello world" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let search = |query: &str| {
+            let rtxn = index.read_txn().unwrap();
+            let mut documents_ids =
+                index.search(&rtxn).query(query).execute().unwrap().documents_ids;
+            documents_ids.sort_unstable();
+            documents_ids
+        };
+        let longest_word = || {
+            let rtxn = index.read_txn().unwrap();
+            let words = index.word_docids.iter(&rtxn).unwrap().map(|r| r.unwrap().0.len());
+            words.max().unwrap()
+        };
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        let max_word_length = MaxWordLength { max_chars: 16, long_words: LongWords::Truncate };
+        builder.set_max_word_length(max_word_length);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // the words starting with the same 16 chars are the same word.
+        assert_eq!(longest_word(), 16);
+        assert_eq!(search(&format!("{} ", long_word)), vec![0]);
+        assert_eq!(search(&format!("{}bbbb ", "a".repeat(16))), vec![0]);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        let max_word_length = MaxWordLength { max_chars: 16, long_words: LongWords::Skip };
+        builder.set_max_word_length(max_word_length);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // the long words are ignored in the documents and in the queries.
+        assert_eq!(longest_word(), "hello".len());
+        assert_eq!(search(&format!("hello {} ", long_word)), vec![0, 1]);
+        assert_eq!(search(&format!("{} world ", long_word)), vec![1]);
+    }
+
+    #[test]
+    fn set_min_word_size_for_prefix() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        // enough words starting with `abc` to index their prefixes.
+        let mut documents = vec![serde_json::json!({ "id": 0, "title": "ab" })];
+        for (i, (a, b)) in ('a'..='d').flat_map(|a| ('a'..='z').map(move |b| (a, b))).enumerate() {
+            documents.push(serde_json::json!({ "id": i + 1, "title": format!("abc{}{}", a, b) }));
+        }
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!(documents);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let search = |query: &str| {
+            let rtxn = index.read_txn().unwrap();
+            index.search(&rtxn).query(query).execute().unwrap().documents_ids.len()
+        };
+        let prefixes = || {
+            let rtxn = index.read_txn().unwrap();
+            let fst = index.words_prefixes_fst(&rtxn).unwrap();
+            fst.stream().into_strs().unwrap()
+        };
+        let prefix_docids = |prefix: &str| {
+            let rtxn = index.read_txn().unwrap();
+            index.word_prefix_docids.get(&rtxn, prefix).unwrap().map(|docids| docids.len())
+        };
+
+        assert_eq!(prefixes(), ["a", "ab", "abc"]);
+        assert_eq!(search("a"), 105);
+        assert_eq!(search("ab"), 105);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_min_word_size_for_prefix(3);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        // the 1 and 2 chars words only match themselves.
+        assert_eq!(prefixes(), ["abc"]);
+        assert_eq!(prefix_docids("ab"), None);
+        assert_eq!(prefix_docids("abc"), Some(104));
+        assert_eq!(search("a"), 0);
+        assert_eq!(search("ab"), 1);
+        assert_eq!(search("abc"), 104);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_min_word_size_for_prefix();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        assert_eq!(prefixes(), ["a", "ab", "abc"]);
+        assert_eq!(prefix_docids("ab"), Some(105));
+        assert_eq!(search("ab"), 105);
+    }
+
+    #[test]
+    fn set_geo_distance() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_filterable_fields(hashset! { S("_geo") });
+        builder.set_sortable_fields(hashset! { S("_geo") });
+        builder.execute(|_| ()).unwrap();
+
+        // the points are at about 110m, 5.5km, 900m and 1.1km north of the origin.
+        let content = documents!([
+            { "id": 0, "_geo": { "lat": 48.8576, "lng": 2.3522 } },
+            { "id": 1, "_geo": { "lat": 48.9061, "lng": 2.3522 } },
+            { "id": 2, "_geo": { "lat": 48.8647, "lng": 2.3522 } },
+            { "id": 3, "_geo": { "lat": 48.8665, "lng": 2.3522 } },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let search = || {
+            let rtxn = index.read_txn().unwrap();
+            let mut search = index.search(&rtxn);
+            search.filter(Filter::from_str("_geoRadius(48.8566, 2.3522, 1000)").unwrap().unwrap());
+            let result = search.execute().unwrap();
+            let mut search = index.search(&rtxn);
+            search.sort_criteria(vec![AscDesc::Asc(Member::Geo([48.8566, 2.3522]))]);
+            let SearchResult { documents_ids, geo_distances, .. } = search.execute().unwrap();
+            (result.documents_ids, documents_ids, geo_distances)
+        };
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.geo_distance(&rtxn).unwrap(), GeoDistance::GreatCircle);
+        drop(rtxn);
+        let (great_circle_radius, great_circle_sort, great_circle_distances) = search();
+        assert_eq!(great_circle_radius, vec![0, 2]);
+        assert_eq!(great_circle_sort, vec![0, 2, 3, 1]);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_geo_distance(GeoDistance::Planar);
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.geo_distance(&rtxn).unwrap(), GeoDistance::Planar);
+        drop(rtxn);
+        let (planar_radius, planar_sort, planar_distances) = search();
+        assert_eq!(planar_radius, great_circle_radius);
+        assert_eq!(planar_sort, great_circle_sort);
+        for (docid, distance) in &great_circle_distances {
+            let planar = planar_distances[docid];
+            assert!((planar - distance).abs() < distance * 1e-3, "{} != {}", planar, distance);
+        }
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_geo_distance();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        let rtxn = index.read_txn().unwrap();
+        assert_eq!(index.geo_distance(&rtxn).unwrap(), GeoDistance::GreatCircle);
+    }
+
+    #[test]
+    fn set_and_reset_dictionary() {
+        let index = TempIndex::new();
+        let config = IndexerConfig::default();
+
+        let mut wtxn = index.write_txn().unwrap();
+        let content = documents!([
+            { "id": 0, "title": "a server written with node.js" },
+            { "id": 1, "title": "a node of the graph" },
+        ]);
+        let indexing_config = IndexDocumentsConfig::default();
+        let mut builder =
+            IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+        builder.add_documents(content).unwrap();
+        builder.execute().unwrap();
+        wtxn.commit().unwrap();
+
+        let search = |query: &str| {
+            let rtxn = index.read_txn().unwrap();
+            let mut documents_ids =
+                index.search(&rtxn).query(query).execute().unwrap().documents_ids;
+            documents_ids.sort_unstable();
+            documents_ids
+        };
+
+        // the word is split on the dot by default.
+        assert_eq!(search("node "), vec![0, 1]);
+        assert_eq!(search("node.js "), vec![0]);
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.set_dictionary(btreeset! { S("node.js") });
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        assert_eq!(search("node "), vec![1]);
+        assert_eq!(search("node.js "), vec![0]);
+        assert_eq!(search("Node.js "), vec![0]);
+        assert_eq!(search("js "), Vec::<u32>::new());
+
+        let mut wtxn = index.write_txn().unwrap();
+        let mut builder = Settings::new(&mut wtxn, &index, &config);
+        builder.reset_dictionary();
+        builder.execute(|_| ()).unwrap();
+        wtxn.commit().unwrap();
+
+        assert_eq!(search("node "), vec![0, 1]);
+    }
+
     #[test]
     fn test_correct_settings_init() {
         let index = TempIndex::new();
@@ -1562,38 +2822,66 @@ mod tests {
             index: _,
             indexer_config: _,
             searchable_fields,
+            searchable_fields_weights,
             displayed_fields,
             filterable_fields,
+            normalized_filterable_fields,
+            facet_value_mappings,
             sortable_fields,
             criteria,
             stop_words,
-            distinct_field,
+            distinct_fields,
             synonyms,
             primary_key,
             authorize_typos,
             min_word_len_two_typos,
             min_word_len_one_typo,
+            attributes_typo_tolerance,
+            typo_budget,
+            allow_typo_on_first_char,
             exact_words,
             exact_attributes,
             max_values_per_facet,
             pagination_max_total_hits,
+            separators,
+            non_separators,
+            dictionary,
+            digit_group_separators,
+            max_word_length,
+            min_word_size_for_prefix,
+            geo_distance,
+            store_documents_json,
         } = builder;
 
         assert!(matches!(searchable_fields, Setting::NotSet));
+        assert!(matches!(searchable_fields_weights, Setting::NotSet));
         assert!(matches!(displayed_fields, Setting::NotSet));
         assert!(matches!(filterable_fields, Setting::NotSet));
+        assert!(matches!(normalized_filterable_fields, Setting::NotSet));
+        assert!(matches!(facet_value_mappings, Setting::NotSet));
         assert!(matches!(sortable_fields, Setting::NotSet));
         assert!(matches!(criteria, Setting::NotSet));
         assert!(matches!(stop_words, Setting::NotSet));
-        assert!(matches!(distinct_field, Setting::NotSet));
+        assert!(matches!(distinct_fields, Setting::NotSet));
         assert!(matches!(synonyms, Setting::NotSet));
         assert!(matches!(primary_key, Setting::NotSet));
         assert!(matches!(authorize_typos, Setting::NotSet));
         assert!(matches!(min_word_len_two_typos, Setting::NotSet));
         assert!(matches!(min_word_len_one_typo, Setting::NotSet));
+        assert!(matches!(attributes_typo_tolerance, Setting::NotSet));
+        assert!(matches!(typo_budget, Setting::NotSet));
+        assert!(matches!(allow_typo_on_first_char, Setting::NotSet));
         assert!(matches!(exact_words, Setting::NotSet));
         assert!(matches!(exact_attributes, Setting::NotSet));
         assert!(matches!(max_values_per_facet, Setting::NotSet));
         assert!(matches!(pagination_max_total_hits, Setting::NotSet));
+        assert!(matches!(separators, Setting::NotSet));
+        assert!(matches!(non_separators, Setting::NotSet));
+        assert!(matches!(dictionary, Setting::NotSet));
+        assert!(matches!(digit_group_separators, Setting::NotSet));
+        assert!(matches!(max_word_length, Setting::NotSet));
+        assert!(matches!(min_word_size_for_prefix, Setting::NotSet));
+        assert!(matches!(geo_distance, Setting::NotSet));
+        assert!(matches!(store_documents_json, Setting::NotSet));
     }
 }
@@ -10,6 +10,7 @@ pub struct WordsPrefixesFst<'t, 'u, 'i> {
     index: &'i Index,
     threshold: u32,
     max_prefix_length: usize,
+    min_word_size: usize,
 }
 
 impl<'t, 'u, 'i> WordsPrefixesFst<'t, 'u, 'i> {
@@ -17,7 +18,7 @@ impl<'t, 'u, 'i> WordsPrefixesFst<'t, 'u, 'i> {
         wtxn: &'t mut heed::RwTxn<'i, 'u>,
         index: &'i Index,
     ) -> WordsPrefixesFst<'t, 'u, 'i> {
-        WordsPrefixesFst { wtxn, index, threshold: 100, max_prefix_length: 4 }
+        WordsPrefixesFst { wtxn, index, threshold: 100, max_prefix_length: 4, min_word_size: 1 }
     }
 
     /// Set the number of words required to make a prefix be part of the words prefixes
@@ -40,6 +41,15 @@ impl<'t, 'u, 'i> WordsPrefixesFst<'t, 'u, 'i> {
         self
     }
 
+    /// Set the minimum number of chars of the prefixes, the shorter prefixes are never part
+    /// of the words prefixes datastructures.
+    ///
+    /// Default value is `1` char.
+    pub fn min_word_size(&mut self, value: usize) -> &mut Self {
+        self.min_word_size = value;
+        self
+    }
+
     #[logging_timer::time("WordsPrefixesFst::{}")]
     pub fn execute(self) -> Result<()> {
         let words_fst = self.index.words_fst(&self.wtxn)?;
@@ -64,6 +74,9 @@ impl<'t, 'u, 'i> WordsPrefixesFst<'t, 'u, 'i> {
                     Some(prefix) => prefix,
                     None => continue,
                 };
+                if prefix.chars().count() < self.min_word_size {
+                    continue;
+                }
 
                 // This is the first iteration of the loop,
                 // or the current word doesn't starts with the current prefix.
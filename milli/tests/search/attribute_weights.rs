@@ -0,0 +1,82 @@
+use big_s::S;
+use milli::update::{IndexerConfig, Settings};
+use milli::{Index, Search};
+use serde_json::json;
+
+use crate::search::setup_index_with_documents;
+
+fn setup_index() -> Index {
+    setup_index_with_documents(
+        |settings| {
+            settings.set_searchable_fields(vec![S("title"), S("body"), S("tags")]);
+            settings.set_criteria(vec![S("attribute")]);
+        },
+        json!([
+            { "id": 0, "title": "rust", "body": "a book", "tags": "misc" },
+            { "id": 1, "title": "a book", "body": "rust", "tags": "misc" },
+            { "id": 2, "title": "a book", "body": "misc", "tags": "rust" },
+        ]),
+    )
+}
+
+fn set_weights(index: &Index, fields: &[(&str, u16)]) {
+    let mut txn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+    let mut builder = Settings::new(&mut txn, index, &config);
+    let fields = fields.iter().map(|(name, weight)| (name.to_string(), *weight)).collect();
+    builder.set_weighted_searchable_fields(fields);
+    builder.execute(|_| ()).unwrap();
+    txn.commit().unwrap();
+}
+
+fn search_documents_ids(index: &Index, query: &str) -> Vec<u32> {
+    let txn = index.read_txn().unwrap();
+    let mut search = Search::new(&txn, index);
+    search.query(query);
+    search.execute().unwrap().documents_ids
+}
+
+#[test]
+fn weights_override_the_attributes_order() {
+    let index = setup_index();
+    assert_eq!(search_documents_ids(&index, "rust"), vec![0, 1, 2]);
+
+    set_weights(&index, &[("title", 1), ("body", 3), ("tags", 2)]);
+    assert_eq!(search_documents_ids(&index, "rust"), vec![1, 2, 0]);
+
+    set_weights(&index, &[("title", 1), ("body", 2), ("tags", 3)]);
+    assert_eq!(search_documents_ids(&index, "rust"), vec![2, 1, 0]);
+
+    let txn = index.read_txn().unwrap();
+    let weights = index.searchable_fields_weights(&txn).unwrap();
+    assert_eq!(weights.get("tags"), Some(&3));
+}
+
+#[test]
+fn attributes_of_the_same_weight_are_ranked_by_their_order() {
+    let index = setup_index();
+
+    set_weights(&index, &[("title", 1), ("body", 2), ("tags", 2)]);
+    assert_eq!(search_documents_ids(&index, "rust"), vec![1, 2, 0]);
+
+    set_weights(&index, &[("title", 1), ("tags", 2), ("body", 2)]);
+    assert_eq!(search_documents_ids(&index, "rust"), vec![2, 1, 0]);
+}
+
+#[test]
+fn setting_the_searchable_fields_removes_the_weights() {
+    let index = setup_index();
+    set_weights(&index, &[("title", 1), ("body", 3), ("tags", 2)]);
+
+    let mut txn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+    let mut builder = Settings::new(&mut txn, &index, &config);
+    builder.set_searchable_fields(vec![S("title"), S("body"), S("tags")]);
+    builder.execute(|_| ()).unwrap();
+    txn.commit().unwrap();
+
+    let txn = index.read_txn().unwrap();
+    assert!(index.searchable_fields_weights(&txn).unwrap().is_empty());
+    drop(txn);
+    assert_eq!(search_documents_ids(&index, "rust"), vec![0, 1, 2]);
+}
@@ -2,7 +2,8 @@ use std::collections::HashSet;
 
 use big_s::S;
 use milli::update::Settings;
-use milli::{Criterion, Search, SearchResult};
+use milli::{Criterion, Index, Search, SearchResult};
+use serde_json::json;
 use Criterion::*;
 
 use crate::search::{self, EXTERNAL_DOCUMENTS_IDS};
@@ -29,8 +30,10 @@ macro_rules! test_distinct {
             search.limit(EXTERNAL_DOCUMENTS_IDS.len());
             search.authorize_typos(true);
             search.optional_words(true);
+            search.with_distinct_count(true);
 
-            let SearchResult { documents_ids, candidates, .. } = search.execute().unwrap();
+            let SearchResult { documents_ids, candidates, distinct_count, .. } =
+                search.execute().unwrap();
 
             assert_eq!(candidates.len(), $n_res);
 
@@ -47,6 +50,8 @@ macro_rules! test_distinct {
                 })
                 .collect();
 
+            assert_eq!(distinct_count, Some(expected_external_ids.len() as u64));
+
             let documents_ids = search::internal_to_external_ids(&index, &documents_ids);
             assert_eq!(documents_ids, expected_external_ids);
         }
@@ -75,3 +80,102 @@ test_distinct!(distinct_string_criterion_words_attribute, tag, vec![Words, Attri
 test_distinct!(distinct_number_criterion_words_attribute, asc_desc_rank, vec![Words, Attribute], 7);
 test_distinct!(distinct_string_criterion_words_exactness, tag, vec![Words, Exactness], 3);
 test_distinct!(distinct_number_criterion_words_exactness, asc_desc_rank, vec![Words, Exactness], 7);
+
+/// Returns an index containing the documents and deduplicated on the given fields.
+fn index_with_distinct_fields(documents: serde_json::Value, distinct: Vec<String>) -> Index {
+    search::setup_index_with_documents(|settings| settings.set_distinct_fields(distinct), documents)
+}
+
+fn distinct_external_ids(index: &Index) -> Vec<String> {
+    let rtxn = index.read_txn().unwrap();
+    let SearchResult { documents_ids, .. } = Search::new(&rtxn, index).execute().unwrap();
+    let documents = index.documents(&rtxn, documents_ids).unwrap();
+    let fields_ids_map = index.fields_ids_map(&rtxn).unwrap();
+    let id = fields_ids_map.id("id").unwrap();
+    documents
+        .into_iter()
+        .map(|(_, document)| serde_json::from_slice(document.get(id).unwrap()).unwrap())
+        .collect()
+}
+
+#[test]
+fn distinct_on_two_fields() {
+    let index = index_with_distinct_fields(
+        json!([
+            { "id": "A", "brand": "apple", "model": "x" },
+            { "id": "B", "brand": "apple", "model": "x" },
+            { "id": "C", "brand": "apple", "model": "y" },
+            { "id": "D", "brand": "banana", "model": "x" },
+            { "id": "E", "brand": "banana", "model": "x" },
+            { "id": "F", "brand": "banana", "model": 2 },
+            { "id": "G", "brand": "banana", "model": 2 },
+        ]),
+        vec![S("brand"), S("model")],
+    );
+
+    let rtxn = index.read_txn().unwrap();
+    assert_eq!(index.distinct_fields(&rtxn).unwrap(), ["brand", "model"]);
+    assert_eq!(index.distinct_field(&rtxn).unwrap(), None);
+    assert_eq!(distinct_external_ids(&index), ["A", "C", "D", "F"]);
+}
+
+#[test]
+fn distinct_on_two_fields_with_missing_values() {
+    let index = index_with_distinct_fields(
+        json!([
+            { "id": "A", "brand": "apple", "model": "x" },
+            // the missing model is shared by the apple documents without model.
+            { "id": "B", "brand": "apple" },
+            { "id": "C", "brand": "apple" },
+            { "id": "D", "model": "x" },
+            { "id": "E", "model": "x" },
+            // the documents without any of the fields are all kept.
+            { "id": "F" },
+            { "id": "G" },
+        ]),
+        vec![S("brand"), S("model")],
+    );
+
+    assert_eq!(distinct_external_ids(&index), ["A", "B", "D", "F", "G"]);
+}
+
+#[test]
+fn distinct_count_over_all_candidates() {
+    let index = index_with_distinct_fields(
+        json!([
+            { "id": "A", "brand": "apple", "model": "x" },
+            { "id": "B", "brand": "apple", "model": "x" },
+            { "id": "C", "brand": "apple", "model": "y" },
+            { "id": "D", "brand": "banana", "model": "x" },
+            { "id": "E", "brand": "banana", "model": "x" },
+            { "id": "F", "brand": "banana" },
+            { "id": "G" },
+        ]),
+        vec![S("brand"), S("model")],
+    );
+
+    let rtxn = index.read_txn().unwrap();
+    let mut search = Search::new(&rtxn, &index);
+    search.limit(2);
+    let SearchResult { distinct_count, .. } = search.execute().unwrap();
+    // the distinct documents are only counted on demand.
+    assert_eq!(distinct_count, None);
+
+    search.with_distinct_count(true);
+    let SearchResult { documents_ids, distinct_count, .. } = search.execute().unwrap();
+    assert_eq!(documents_ids.len(), 2);
+    // A, C, D, F and G are the representatives of the distinct buckets.
+    assert_eq!(distinct_count, Some(5));
+
+    search.offset(4);
+    let SearchResult { documents_ids, distinct_count, .. } = search.execute().unwrap();
+    assert_eq!(documents_ids.len(), 1);
+    assert_eq!(distinct_count, Some(5));
+
+    // there is no distinct count without distinct fields.
+    let index = index_with_distinct_fields(json!([{ "id": "A" }, { "id": "B" }]), Vec::new());
+    let rtxn = index.read_txn().unwrap();
+    let SearchResult { distinct_count, .. } =
+        Search::new(&rtxn, &index).with_distinct_count(true).execute().unwrap();
+    assert_eq!(distinct_count, None);
+}
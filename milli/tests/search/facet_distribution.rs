@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::io::Cursor;
 
 use big_s::S;
@@ -5,7 +6,12 @@ use heed::EnvOpenOptions;
 use maplit::hashset;
 use milli::documents::{DocumentBatchBuilder, DocumentBatchReader};
 use milli::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
-use milli::{FacetDistribution, Index};
+use milli::{
+    FacetDistribution, FacetStats, Filter, Index, SampledFacetDistribution, Search, SearchResult,
+};
+use serde_json::json;
+
+use crate::search::setup_index_with_documents;
 
 #[test]
 fn test_facet_distribution_with_no_facet_values() {
@@ -75,3 +81,387 @@ fn test_facet_distribution_with_no_facet_values() {
     let result = distrib.execute().unwrap();
     assert_eq!(result["tags"].len(), 2);
 }
+
+#[test]
+fn test_facet_distribution_ordered_by_count() {
+    // the 1000 distinct colours appear in 1 to 10 documents.
+    let mut documents = Vec::new();
+    for i in 0..1000 {
+        for _ in 0..i % 10 + 1 {
+            let id = documents.len();
+            documents.push(json!({ "id": id, "colour": format!("c{:04}", i) }));
+        }
+    }
+    let index = setup_index_with_documents(
+        |settings| settings.set_filterable_fields(hashset! { S("colour") }),
+        documents.into(),
+    );
+
+    let txn = index.read_txn().unwrap();
+    let ordered = |values: &[(&str, u64)]| -> Vec<(String, u64)> {
+        values.iter().map(|(value, count)| (value.to_string(), *count)).collect()
+    };
+
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.facets(vec!["colour"]).max_values_per_facet(4);
+    let result = distrib.execute_ordered().unwrap();
+    assert_eq!(
+        result["colour"],
+        ordered(&[("c0000", 1), ("c0001", 2), ("c0002", 3), ("c0003", 4)])
+    );
+
+    // the ties are broken lexicographically
+    distrib.order_by_count(true);
+    let result = distrib.execute_ordered().unwrap();
+    assert_eq!(
+        result["colour"],
+        ordered(&[("c0009", 10), ("c0019", 10), ("c0029", 10), ("c0039", 10)])
+    );
+
+    // there is more candidates than the threshold, the facet levels are used
+    distrib.candidates(index.documents_ids(&txn).unwrap());
+    let result = distrib.execute_ordered().unwrap();
+    assert_eq!(
+        result["colour"],
+        ordered(&[("c0009", 10), ("c0019", 10), ("c0029", 10), ("c0039", 10)])
+    );
+
+    // the 55 first documents contain the 10 first colours
+    distrib.candidates((0..55).collect());
+    let result = distrib.execute_ordered().unwrap();
+    assert_eq!(
+        result["colour"],
+        ordered(&[("c0009", 10), ("c0008", 9), ("c0007", 8), ("c0006", 7)])
+    );
+
+    // the unordered distribution only contains the most frequent values
+    let result = distrib.execute().unwrap();
+    assert_eq!(result["colour"].keys().collect::<Vec<_>>(), ["c0006", "c0007", "c0008", "c0009"]);
+}
+
+#[test]
+fn test_facet_distribution_over_search_candidates() {
+    let index = setup_index_with_documents(
+        |settings| settings.set_filterable_fields(hashset! { S("genre"), S("price") }),
+        json!([
+            { "id": 0, "title": "hello world", "genre": "rock", "price": 10 },
+            { "id": 1, "title": "hello", "genre": "jazz", "price": 20 },
+            { "id": 2, "title": "hello again", "genre": "rock", "price": 30 },
+            { "id": 3, "title": "goodbye", "genre": "rock", "price": 40 },
+        ]),
+    );
+
+    let txn = index.read_txn().unwrap();
+    let mut search = Search::new(&txn, &index);
+    search.query("hello");
+    search.filter(Filter::from_str("price < 25").unwrap().unwrap());
+    let SearchResult { candidates, .. } = search.execute().unwrap();
+
+    // the filter of the search is not evaluated again, only the candidates are counted
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.facets(vec!["genre"]).candidates(candidates);
+    let result = distrib.execute().unwrap();
+    assert_eq!(result["genre"].get("rock"), Some(&1));
+    assert_eq!(result["genre"].get("jazz"), Some(&1));
+
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.facets(vec!["genre"]);
+    let result = distrib.execute().unwrap();
+    assert_eq!(result["genre"].get("rock"), Some(&3));
+}
+
+#[test]
+fn test_facet_stats() {
+    // enough prices to create several facet levels
+    let documents: Vec<_> = (0..500)
+        .map(|id| {
+            let genre = if id % 2 == 0 { "rock" } else { "jazz" };
+            json!({ "id": id, "genre": genre, "price": id as f64 / 2.0 })
+        })
+        .collect();
+    let index = setup_index_with_documents(
+        |settings| settings.set_filterable_fields(hashset! { S("genre"), S("price") }),
+        documents.into(),
+    );
+
+    let txn = index.read_txn().unwrap();
+
+    // the genre doesn't have any numeric value and is skipped
+    let distrib = FacetDistribution::new(&txn, &index);
+    let stats = distrib.compute_stats().unwrap();
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats["price"], FacetStats { min: 0.0, max: 249.5 });
+
+    let filter = Filter::from_str("genre = jazz AND price 10 TO 100").unwrap().unwrap();
+    let candidates = filter.evaluate(&txn, &index).unwrap();
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.candidates(candidates);
+    let stats = distrib.compute_stats().unwrap();
+    assert_eq!(stats["price"], FacetStats { min: 10.5, max: 99.5 });
+
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.candidates((1000..1010).collect());
+    assert!(distrib.compute_stats().unwrap().is_empty());
+}
+
+#[test]
+fn test_facet_distribution_with_zero_counts() {
+    // the 3 statuses and the 100 tags are spread over the documents.
+    let statuses = ["archived", "draft", "published"];
+    let documents: Vec<_> = (0..300)
+        .map(|id| {
+            json!({
+                "id": id,
+                "status": statuses[id % 3],
+                "tag": format!("t{:03}", id % 100),
+            })
+        })
+        .collect();
+    let index = setup_index_with_documents(
+        |settings| settings.set_filterable_fields(hashset! { S("status"), S("tag") }),
+        documents.into(),
+    );
+
+    let txn = index.read_txn().unwrap();
+    let filter = Filter::from_str("status = published AND tag = t002").unwrap().unwrap();
+    let candidates = filter.evaluate(&txn, &index).unwrap();
+    assert_eq!(candidates.len(), 1);
+
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.facets(vec!["status", "tag"]).candidates(candidates).max_values_per_facet(10);
+    let result = distrib.execute().unwrap();
+    assert_eq!(result["status"].len(), 1);
+    assert_eq!(result["tag"].len(), 1);
+
+    // the values absent from the candidates are returned with a count of 0.
+    distrib.with_zero_counts(vec!["status", "tag"]);
+    let result = distrib.execute().unwrap();
+    let statuses: Vec<_> = result["status"].iter().map(|(v, c)| (v.as_str(), *c)).collect();
+    assert_eq!(statuses, [("archived", 0), ("draft", 0), ("published", 1)]);
+    assert_eq!(result["tag"].len(), 10);
+    assert_eq!(result["tag"]["t000"], 0);
+    assert_eq!(result["tag"]["t002"], 1);
+
+    // the counted values come first when ordered by count.
+    distrib.order_by_count(true);
+    let result = distrib.execute_ordered().unwrap();
+    assert_eq!(result["tag"].len(), 10);
+    assert_eq!(result["tag"][0], (S("t002"), 1));
+    assert_eq!(result["tag"][1], (S("t000"), 0));
+}
+
+#[test]
+fn test_facet_distribution_with_zero_counts_of_numbers_and_strings() {
+    let index = setup_index_with_documents(
+        |settings| settings.set_filterable_fields(hashset! { S("size") }),
+        json!([
+            { "id": 0, "size": 1 },
+            { "id": 1, "size": 2 },
+            { "id": 2, "size": "2" },
+            { "id": 3, "size": "large" },
+            { "id": 4, "size": "medium" },
+        ]),
+    );
+
+    // the numbers and the strings share the maximum number of values.
+    let txn = index.read_txn().unwrap();
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.with_zero_counts(vec!["size"]).candidates((1..3).collect()).max_values_per_facet(3);
+    let result = distrib.execute().unwrap();
+    let sizes: Vec<_> = result["size"].iter().map(|(v, c)| (v.as_str(), *c)).collect();
+    assert_eq!(sizes, [("1", 0), ("2", 2), ("large", 0)]);
+}
+
+#[test]
+fn test_facet_booleans() {
+    let index = setup_index_with_documents(
+        |settings| settings.set_filterable_fields(hashset! { S("available"), S("genre") }),
+        json!([
+            { "id": 0, "genre": "rock", "available": true },
+            { "id": 1, "genre": "jazz", "available": "true" },
+            { "id": 2, "genre": "rock", "available": false },
+            { "id": 3, "genre": "jazz", "available": [true, false] },
+        ]),
+    );
+
+    let txn = index.read_txn().unwrap();
+
+    // the booleans are not counted with the string "true".
+    let distrib = FacetDistribution::new(&txn, &index);
+    let distribution = distrib.execute().unwrap();
+    assert_eq!(distribution["available"].len(), 1);
+    assert_eq!(distribution["available"]["true"], 1);
+
+    let booleans = distrib.compute_booleans().unwrap();
+    assert_eq!(booleans.len(), 1);
+    assert_eq!(booleans["available"].get(&true), Some(&2));
+    assert_eq!(booleans["available"].get(&false), Some(&2));
+
+    let filter = Filter::from_str("genre = jazz").unwrap().unwrap();
+    let candidates = filter.evaluate(&txn, &index).unwrap();
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.candidates(candidates);
+    let booleans = distrib.compute_booleans().unwrap();
+    assert_eq!(booleans["available"].get(&true), Some(&1));
+    assert_eq!(booleans["available"].get(&false), Some(&1));
+}
+
+#[test]
+fn test_facet_distribution_streaming() {
+    // enough documents to go through the facet levels with the candidates
+    let documents: Vec<_> = (0..4000)
+        .map(|id| {
+            let genre = ["rock", "jazz", "Blues"][id % 3];
+            json!({ "id": id, "genre": genre, "price": id % 250 })
+        })
+        .collect();
+    let index = setup_index_with_documents(
+        |settings| settings.set_filterable_fields(hashset! { S("genre"), S("price") }),
+        documents.into(),
+    );
+
+    let txn = index.read_txn().unwrap();
+    let streamed = |distrib: &FacetDistribution| {
+        let mut streamed = BTreeMap::new();
+        distrib
+            .execute_streaming(|facet, value, count| {
+                let values: &mut BTreeMap<String, u64> =
+                    streamed.entry(facet.to_string()).or_default();
+                assert!(values.insert(value.to_string(), count).is_none());
+                Ok(())
+            })
+            .unwrap();
+        streamed
+    };
+
+    let distrib = FacetDistribution::new(&txn, &index);
+    assert_eq!(streamed(&distrib), distrib.execute().unwrap());
+
+    // the small and the big sets of candidates are counted differently
+    for filter in ["price < 10", "price >= 10"] {
+        let filter = Filter::from_str(filter).unwrap().unwrap();
+        let candidates = filter.evaluate(&txn, &index).unwrap();
+        let mut distrib = FacetDistribution::new(&txn, &index);
+        distrib.candidates(candidates).max_values_per_facet(20);
+        assert_eq!(streamed(&distrib), distrib.execute().unwrap());
+    }
+
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.order_by_count(true).max_values_per_facet(2);
+    let distribution = distrib.execute().unwrap();
+    assert_eq!(distribution["genre"].len(), 2);
+    assert_eq!(streamed(&distrib), distribution);
+}
+
+#[test]
+fn test_facet_distribution_max_values_of_numbers_and_strings() {
+    // the even documents have a number and the odd ones a string
+    let documents: Vec<_> = (0..4000)
+        .map(|id| match id % 2 {
+            0 => json!({ "id": id, "size": id % 10 }),
+            _ => json!({ "id": id, "size": format!("s{}", id % 10) }),
+        })
+        .collect();
+    let index = setup_index_with_documents(
+        |settings| settings.set_filterable_fields(hashset! { S("size") }),
+        documents.into(),
+    );
+
+    let txn = index.read_txn().unwrap();
+    let expected: BTreeMap<_, _> =
+        ["0", "2", "4", "6", "8", "s1", "s3"].iter().map(|value| (S(value), 400)).collect();
+
+    // the numbers and the strings share the maximum number of values of the facet
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.max_values_per_facet(7);
+    assert_eq!(distrib.execute().unwrap()["size"], expected);
+
+    // there is more candidates than the threshold, the facet levels are used
+    distrib.candidates(index.documents_ids(&txn).unwrap());
+    assert_eq!(distrib.execute().unwrap()["size"], expected);
+}
+
+#[test]
+fn test_facet_distribution_sampled() {
+    // the genres are found in 50%, 30% and 20% of the documents.
+    let genre = ["rock", "rock", "rock", "rock", "rock", "jazz", "jazz", "jazz", "pop", "pop"];
+    let documents: Vec<_> = (0..10_000)
+        .map(|id| {
+            json!({
+                "id": id,
+                "genre": genre[id % 10],
+                "decade": 1950 + (id / 7) % 5 * 10,
+            })
+        })
+        .collect();
+    let index = setup_index_with_documents(
+        |settings| settings.set_filterable_fields(hashset! { S("genre"), S("decade") }),
+        documents.into(),
+    );
+
+    let txn = index.read_txn().unwrap();
+    let distrib = FacetDistribution::new(&txn, &index);
+    let exact = distrib.execute().unwrap();
+
+    let sampled = distrib.execute_sampled(2000, 42).unwrap();
+    assert!(sampled.approximate);
+    for (facet, values) in &exact {
+        assert_eq!(sampled.distribution[facet].len(), values.len());
+        for (value, &count) in values {
+            let estimated = sampled.distribution[facet][value] as f64;
+            let error = (estimated - count as f64).abs() / count as f64;
+            assert!(error < 0.15, "{} {}: {} estimated for {}", facet, value, estimated, count);
+        }
+    }
+
+    // the same seed gives the same sample.
+    assert_eq!(distrib.execute_sampled(2000, 42).unwrap(), sampled);
+
+    // the counts are exact when there are not enough candidates to sample them.
+    let filter = Filter::from_str("genre = pop").unwrap().unwrap();
+    let candidates = filter.evaluate(&txn, &index).unwrap();
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.candidates(candidates);
+    let SampledFacetDistribution { distribution, approximate } =
+        distrib.execute_sampled(2000, 42).unwrap();
+    assert!(!approximate);
+    assert_eq!(distribution, distrib.execute().unwrap());
+}
+
+#[test]
+fn test_facet_distribution_without_soft_deleted_documents() {
+    let index = setup_index_with_documents(
+        |settings| settings.set_filterable_fields(hashset! { S("genre"), S("price"), S("sold") }),
+        json!([
+            { "id": 0, "genre": "rock", "price": 10, "sold": true },
+            { "id": 1, "genre": "jazz", "price": 20, "sold": false },
+            { "id": 2, "genre": "rock", "price": 10, "sold": true },
+            { "id": 3, "genre": "pop", "price": 30, "sold": false },
+        ]),
+    );
+
+    let mut wtxn = index.write_txn().unwrap();
+    index.soft_delete_documents(&mut wtxn, &(0..2).collect()).unwrap();
+    wtxn.commit().unwrap();
+
+    // the soft deleted documents are still in the facet databases but are not counted.
+    let txn = index.read_txn().unwrap();
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    let result = distrib.execute().unwrap();
+    assert_eq!(result["genre"].iter().collect::<Vec<_>>(), [(&S("pop"), &1), (&S("rock"), &1)]);
+    assert_eq!(result["price"].iter().collect::<Vec<_>>(), [(&S("10"), &1), (&S("30"), &1)]);
+
+    distrib.order_by_count(true);
+    let result = distrib.execute_ordered().unwrap();
+    assert_eq!(result["genre"], [(S("pop"), 1), (S("rock"), 1)]);
+
+    let booleans = distrib.compute_booleans().unwrap();
+    assert_eq!(booleans["sold"].get(&true), Some(&1));
+    assert_eq!(booleans["sold"].get(&false), Some(&1));
+
+    // the values only contained by soft deleted documents are not returned with a zero count.
+    let mut distrib = FacetDistribution::new(&txn, &index);
+    distrib.with_zero_counts(vec!["genre"]).candidates((3..4).collect());
+    let result = distrib.execute().unwrap();
+    assert_eq!(result["genre"].iter().collect::<Vec<_>>(), [(&S("pop"), &1), (&S("rock"), &0)]);
+}
@@ -0,0 +1,107 @@
+use big_s::S;
+use maplit::hashset;
+use milli::{Error, FacetSearch, Filter, Index, UserError};
+use serde_json::json;
+
+use crate::search::setup_index_with_documents;
+
+fn setup_index() -> Index {
+    setup_index_with_documents(
+        |settings| settings.set_filterable_fields(hashset! { S("brand"), S("price") }),
+        json!([
+            { "id": 0, "brand": "Samsung", "price": 10 },
+            { "id": 1, "brand": "Sony", "price": 20 },
+            { "id": 2, "brand": "Sony", "price": 30 },
+            { "id": 3, "brand": "Sennheiser", "price": 40 },
+            { "id": 4, "brand": "Apple", "price": 50 },
+            { "id": 5, "brand": ["Sony", "Apple"], "price": 60 },
+            { "id": 6, "brand": "Samsung", "price": 70 },
+            { "id": 7, "brand": "Sonos", "price": 80 },
+        ]),
+    )
+}
+
+fn values(values: &[(&str, u64)]) -> Vec<(String, u64)> {
+    values.iter().map(|(value, count)| (value.to_string(), *count)).collect()
+}
+
+#[test]
+fn facet_search_by_prefix() {
+    let index = setup_index();
+    let rtxn = index.read_txn().unwrap();
+
+    // the ties are broken lexicographically.
+    let mut search = FacetSearch::new(&rtxn, &index, "brand");
+    search.query("s");
+    let result = search.execute().unwrap();
+    assert_eq!(result, values(&[("Sony", 3), ("Samsung", 2), ("Sennheiser", 1), ("Sonos", 1)]));
+
+    // the query is normalized like the facet values.
+    search.query(" SON");
+    let result = search.execute().unwrap();
+    assert_eq!(result, values(&[("Sony", 3), ("Sonos", 1)]));
+
+    search.query("son").max_values(1);
+    let result = search.execute().unwrap();
+    assert_eq!(result, values(&[("Sony", 3)]));
+
+    let mut search = FacetSearch::new(&rtxn, &index, "brand");
+    search.query("nokia");
+    assert!(search.execute().unwrap().is_empty());
+
+    // all the values are returned without any query.
+    let search = FacetSearch::new(&rtxn, &index, "brand");
+    assert_eq!(search.execute().unwrap().len(), 5);
+}
+
+#[test]
+fn facet_search_over_candidates() {
+    let index = setup_index();
+    let rtxn = index.read_txn().unwrap();
+
+    let filter = Filter::from_str("price >= 50").unwrap().unwrap();
+    let candidates = filter.evaluate(&rtxn, &index).unwrap();
+
+    let mut search = FacetSearch::new(&rtxn, &index, "brand");
+    search.query("s").candidates(candidates.clone());
+    let result = search.execute().unwrap();
+    assert_eq!(result, values(&[("Samsung", 1), ("Sonos", 1), ("Sony", 1)]));
+
+    let mut search = FacetSearch::new(&rtxn, &index, "brand");
+    search.candidates(candidates);
+    let result = search.execute().unwrap();
+    assert_eq!(result[0], (S("Apple"), 2));
+    assert_eq!(result.len(), 4);
+}
+
+#[test]
+fn facet_search_without_soft_deleted_documents() {
+    let index = setup_index();
+    let mut wtxn = index.write_txn().unwrap();
+    index.soft_delete_documents(&mut wtxn, &[1, 2, 7].iter().copied().collect()).unwrap();
+    wtxn.commit().unwrap();
+
+    let rtxn = index.read_txn().unwrap();
+    let mut search = FacetSearch::new(&rtxn, &index, "brand");
+    search.query("son");
+    let result = search.execute().unwrap();
+    assert_eq!(result, values(&[("Sony", 1)]));
+}
+
+#[test]
+fn facet_search_on_a_non_filterable_field() {
+    let index = setup_index();
+    let rtxn = index.read_txn().unwrap();
+
+    let search = FacetSearch::new(&rtxn, &index, "id");
+    let error = search.execute().unwrap_err();
+    assert!(matches!(
+        error,
+        Error::UserError(UserError::InvalidFacetSearchAttribute { ref field, .. }) if field == "id"
+    ));
+    assert_eq!(
+        error.to_string(),
+        "Attribute `id` is not filterable and its values can't be searched. \
+         Available filterable attributes are: `brand, price`."
+    );
+}
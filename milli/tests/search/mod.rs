@@ -12,12 +12,18 @@ use milli::{AscDesc, Criterion, DocumentId, Index, Member};
 use serde::Deserialize;
 use slice_group_by::GroupBy;
 
+mod attribute_weights;
 mod distinct;
 mod facet_distribution;
+mod facet_search;
 mod filters;
+mod pagination;
+mod phrase_search;
 mod query_criteria;
 mod sort;
+mod split_words;
 mod typo_tolerance;
+mod word_count;
 
 pub const TEST_QUERY: &'static str = "hello world america";
 
@@ -85,6 +91,43 @@ pub fn setup_search_index_with_criteria(criteria: &[Criterion]) -> Index {
     index
 }
 
+/// Returns an index configured by `settings` and containing the `documents`, a JSON array
+/// of objects indexed in a single batch.
+pub fn setup_index_with_documents(
+    settings: impl FnOnce(&mut Settings),
+    documents: serde_json::Value,
+) -> Index {
+    let path = tempfile::tempdir().unwrap();
+    let mut options = EnvOpenOptions::new();
+    options.map_size(1024 * 1024 * 1024); // 1 GB
+    let index = Index::new(options, &path).unwrap();
+
+    let mut wtxn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+    let mut builder = Settings::new(&mut wtxn, &index, &config);
+    settings(&mut builder);
+    builder.execute(|_| ()).unwrap();
+
+    let mut cursor = Cursor::new(Vec::new());
+    let mut documents_builder = DocumentBatchBuilder::new(&mut cursor).unwrap();
+    for document in documents.as_array().unwrap() {
+        let document = Cursor::new(serde_json::to_vec(document).unwrap());
+        documents_builder.extend_from_json(document).unwrap();
+    }
+    documents_builder.finish().unwrap();
+    cursor.set_position(0);
+
+    let indexing_config = IndexDocumentsConfig::default();
+    let mut builder =
+        IndexDocuments::new(&mut wtxn, &index, &config, indexing_config, |_| ()).unwrap();
+    let content = DocumentBatchReader::from_reader(cursor).unwrap();
+    builder.add_documents(content).unwrap();
+    builder.execute().unwrap();
+    wtxn.commit().unwrap();
+
+    index
+}
+
 pub fn internal_to_external_ids(index: &Index, internal_ids: &[DocumentId]) -> Vec<String> {
     let mut rtxn = index.read_txn().unwrap();
     let docid_map = index.external_documents_ids(&mut rtxn).unwrap();
@@ -147,9 +190,12 @@ pub fn expected_order(
                     new_groups
                         .extend(group.linear_group_by_key(|d| d.asc_desc_rank).map(Vec::from));
                 }
-                Criterion::Asc(_) | Criterion::Desc(_) | Criterion::Sort => {
-                    new_groups.push(group.clone())
-                }
+                Criterion::Asc(_)
+                | Criterion::Desc(_)
+                | Criterion::Sort
+                | Criterion::WordCount
+                | Criterion::Score
+                | Criterion::PrefixCompletion => new_groups.push(group.clone()),
             }
         }
         groups = std::mem::take(&mut new_groups);
@@ -0,0 +1,57 @@
+use milli::update::{IndexerConfig, Settings};
+use milli::{Index, Search, SearchResult};
+use serde_json::json;
+
+use crate::search::setup_index_with_documents;
+
+fn index_with_documents(count: usize) -> Index {
+    // the words are more or less close to create several proximity buckets.
+    let documents: Vec<_> = (0..count)
+        .map(|id| {
+            let title = format!("hello {}world", "and the ".repeat(id % 8));
+            json!({ "id": id, "title": title })
+        })
+        .collect();
+    setup_index_with_documents(|_| (), documents.into())
+}
+
+fn set_max_total_hits(index: &Index, max_total_hits: usize) {
+    let mut wtxn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+    let mut builder = Settings::new(&mut wtxn, index, &config);
+    builder.set_pagination_max_total_hits(max_total_hits);
+    builder.execute(|_| ()).unwrap();
+    wtxn.commit().unwrap();
+}
+
+fn search(index: &Index, offset: usize, limit: usize) -> SearchResult {
+    let rtxn = index.read_txn().unwrap();
+    let mut search = Search::new(&rtxn, index);
+    search.query("hello world").offset(offset).limit(limit);
+    search.execute().unwrap()
+}
+
+#[test]
+fn max_total_hits_short_circuits_the_ranking() {
+    let index = index_with_documents(100_000);
+
+    let first_page = search(&index, 0, 20);
+    let deep_page = search(&index, 995, 20);
+    assert_eq!(first_page.candidates.len(), 100_000);
+
+    set_max_total_hits(&index, 1000);
+
+    // the first page is unchanged and the number of candidates is still estimated.
+    let SearchResult { documents_ids, candidates, .. } = search(&index, 0, 20);
+    assert_eq!(documents_ids, first_page.documents_ids);
+    assert_eq!(candidates.len(), 100_000);
+
+    // the page is truncated at the max total hits.
+    let SearchResult { documents_ids, .. } = search(&index, 995, 20);
+    assert_eq!(documents_ids, &deep_page.documents_ids[..5]);
+
+    // the documents after the max total hits are never ranked.
+    let SearchResult { documents_ids, candidates, .. } = search(&index, 90_000, 20);
+    assert!(documents_ids.is_empty());
+    assert!(!candidates.is_empty());
+}
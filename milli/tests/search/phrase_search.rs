@@ -0,0 +1,49 @@
+use milli::{Index, Search, TermsMatchingStrategy};
+use serde_json::json;
+
+use crate::search::setup_index_with_documents;
+
+fn setup_index() -> Index {
+    setup_index_with_documents(
+        |_| (),
+        json!([
+            { "id": 0, "title": "red shoes for running" },
+            { "id": 1, "title": "shoes that are red" },
+            { "id": 2, "title": "red running shoes" },
+            { "id": 3, "title": "the big red shoes" },
+        ]),
+    )
+}
+
+fn search_documents_ids(index: &Index, query: &str) -> Vec<u32> {
+    let txn = index.read_txn().unwrap();
+    let mut search = Search::new(&txn, index);
+    search.query(query);
+    search.terms_matching_strategy(TermsMatchingStrategy::All);
+    let mut documents_ids = search.execute().unwrap().documents_ids;
+    documents_ids.sort_unstable();
+    documents_ids
+}
+
+#[test]
+fn two_words_phrase() {
+    let index = setup_index();
+
+    // without quotes the words can match anywhere in the documents
+    assert_eq!(search_documents_ids(&index, "red shoes"), vec![0, 1, 2, 3]);
+    // `red` must be immediately followed by `shoes`
+    assert_eq!(search_documents_ids(&index, "\"red shoes\""), vec![0, 3]);
+    // the words of a phrase are not reordered
+    assert_eq!(search_documents_ids(&index, "\"shoes red\""), Vec::<u32>::new());
+}
+
+#[test]
+fn three_words_phrase() {
+    let index = setup_index();
+
+    assert_eq!(search_documents_ids(&index, "\"red running shoes\""), vec![2]);
+    // all the words appear in the first document, but not adjacently
+    assert_eq!(search_documents_ids(&index, "\"running red shoes\""), Vec::<u32>::new());
+    // a phrase can be combined with other words
+    assert_eq!(search_documents_ids(&index, "\"red shoes\" running"), vec![0]);
+}
@@ -467,3 +467,30 @@ fn criteria_ascdesc() {
         assert_eq!(documents_ids, expected_document_ids);
     }
 }
+
+#[test]
+fn proximity_cutoff() {
+    // the query words are closer and closer in the last documents.
+    let index = search::setup_index_with_documents(
+        |settings| settings.set_criteria(vec![S("words"), S("proximity")]),
+        serde_json::json!([
+            { "id": 0, "text": "quick a b c d e fox" },
+            { "id": 1, "text": "quick a b c fox" },
+            { "id": 2, "text": "quick brown fox" },
+            { "id": 3, "text": "the quick fox jumps" },
+        ]),
+    );
+
+    let rtxn = index.read_txn().unwrap();
+    let mut search = Search::new(&rtxn, &index);
+    search.query("quick fox");
+    search.optional_words(false);
+
+    let SearchResult { documents_ids, .. } = search.execute().unwrap();
+    assert_eq!(documents_ids, vec![3, 2, 1, 0]);
+
+    // the documents beyond the cutoff are in the same bucket, ordered by their ids.
+    search.max_proximity(2);
+    let SearchResult { documents_ids, .. } = search.execute().unwrap();
+    assert_eq!(documents_ids, vec![3, 2, 0, 1]);
+}
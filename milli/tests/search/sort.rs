@@ -1,6 +1,9 @@
 use big_s::S;
+use maplit::hashset;
+use milli::update::{IndexerConfig, Settings};
 use milli::Criterion::{Attribute, Exactness, Proximity, Typo, Words};
-use milli::{AscDesc, Error, Member, Search, UserError};
+use milli::{AscDesc, Error, Filter, Member, MissingValues, Search, SearchResult, UserError};
+use serde_json::json;
 
 use crate::search::{self, EXTERNAL_DOCUMENTS_IDS};
 
@@ -21,3 +24,221 @@ fn sort_ranking_rule_missing() {
     let result = search.execute();
     assert!(matches!(result, Err(Error::UserError(UserError::SortRankingRuleMissing))));
 }
+
+#[test]
+fn geo_radius_filter_and_geo_sort() {
+    // 50 points going north of the origin, about 1.1km from each other.
+    let rank = |id: u32| (id * 7) % 50;
+    let documents: Vec<_> = (0..50)
+        .map(|id| {
+            let lat = 45.0 + rank(id) as f64 * 0.01;
+            json!({ "id": id, "_geo": { "lat": lat, "lng": 3.0 } })
+        })
+        .collect();
+    let index = search::setup_index_with_documents(
+        |settings| {
+            settings.set_filterable_fields(hashset! { S("_geo") });
+            settings.set_sortable_fields(hashset! { S("_geo") });
+        },
+        documents.into(),
+    );
+
+    // the 14 closest points are in the radius.
+    let mut expected: Vec<u32> = (0..50).filter(|&id| rank(id) < 14).collect();
+    expected.sort_by_key(|&id| rank(id));
+
+    let rtxn = index.read_txn().unwrap();
+    let mut search = Search::new(&rtxn, &index);
+    search.query("");
+    search.limit(50);
+    search.filter(Filter::from_str("_geoRadius(45.0, 3.0, 15500)").unwrap().unwrap());
+    search.sort_criteria(vec![AscDesc::Asc(Member::Geo([45.0, 3.0]))]);
+
+    let SearchResult { documents_ids, geo_distances, .. } = search.execute().unwrap();
+    assert_eq!(documents_ids, expected);
+    assert_eq!(geo_distances.len(), expected.len());
+    assert!(geo_distances.values().all(|&distance| distance < 15500.0));
+}
+
+#[test]
+fn geo_sort_without_query() {
+    // 50 points going north of the origin, the rank of a point is its
+    // distance order and they are not indexed in this order.
+    let rank = |id: u32| (id * 7) % 50;
+    let documents: Vec<_> = (0..50)
+        .map(|id| {
+            let lat = 45.0 + rank(id) as f64 * 0.01;
+            json!({ "id": id, "_geo": { "lat": lat, "lng": 3.0 } })
+        })
+        .collect();
+    let index = search::setup_index_with_documents(
+        |settings| {
+            settings.set_filterable_fields(hashset! { S("id") });
+            settings.set_sortable_fields(hashset! { S("_geo") });
+        },
+        documents.into(),
+    );
+
+    let mut expected: Vec<u32> = (0..50).collect();
+    expected.sort_by_key(|&id| rank(id));
+
+    let rtxn = index.read_txn().unwrap();
+    let mut search = Search::new(&rtxn, &index);
+    search.query("");
+    search.limit(50);
+    search.sort_criteria(vec![AscDesc::Asc(Member::Geo([45.0, 3.0]))]);
+
+    let SearchResult { documents_ids, geo_distances, .. } = search.execute().unwrap();
+    assert_eq!(documents_ids, expected);
+    let distances: Vec<_> = documents_ids.iter().map(|id| geo_distances[id]).collect();
+    assert!(distances.windows(2).all(|w| w[0] <= w[1]), "{:?}", distances);
+
+    search.filter(Filter::from_str("id < 25").unwrap().unwrap());
+    let SearchResult { documents_ids, .. } = search.execute().unwrap();
+    let expected: Vec<_> = expected.into_iter().filter(|&id| id < 25).collect();
+    assert_eq!(documents_ids, expected);
+}
+
+#[test]
+fn sort_by_several_attributes() {
+    let documents = [
+        (10, 3, "e"),
+        (5, 1, "d"),
+        (10, 5, "c"),
+        (5, 4, "b"),
+        (10, 3, "a"),
+        (5, 1, "f"),
+        (20, 2, "g"),
+    ];
+    let documents: Vec<_> = documents
+        .iter()
+        .enumerate()
+        .map(|(id, (price, rating, name))| {
+            json!({ "id": id, "price": price, "rating": rating, "name": name })
+        })
+        .collect();
+    let index = search::setup_index_with_documents(
+        |settings| settings.set_sortable_fields(hashset! { S("price"), S("rating"), S("name") }),
+        documents.into(),
+    );
+
+    let rtxn = index.read_txn().unwrap();
+    let sorted = |sort_criteria: Vec<AscDesc>| {
+        let mut search = Search::new(&rtxn, &index);
+        search.sort_criteria(sort_criteria);
+        search.execute().unwrap().documents_ids
+    };
+    let price = || Member::Field(S("price"));
+    let rating = || Member::Field(S("rating"));
+    let name = || Member::Field(S("name"));
+
+    // the documents with the same price are sorted by rating, then by internal id.
+    let documents_ids = sorted(vec![AscDesc::Asc(price()), AscDesc::Desc(rating())]);
+    assert_eq!(documents_ids, vec![3, 1, 5, 2, 0, 4, 6]);
+
+    // each key has its own direction.
+    let documents_ids = sorted(vec![AscDesc::Desc(price()), AscDesc::Asc(rating())]);
+    assert_eq!(documents_ids, vec![6, 0, 4, 2, 1, 5, 3]);
+
+    // a third key breaks the remaining ties.
+    let documents_ids =
+        sorted(vec![AscDesc::Asc(price()), AscDesc::Desc(rating()), AscDesc::Desc(name())]);
+    assert_eq!(documents_ids, vec![3, 5, 1, 2, 0, 4, 6]);
+
+    // the order of the keys matters.
+    let documents_ids = sorted(vec![AscDesc::Asc(rating()), AscDesc::Asc(price())]);
+    assert_eq!(documents_ids, vec![1, 5, 6, 0, 4, 3, 2]);
+}
+
+#[test]
+fn sort_by_nested_numeric_field() {
+    let index = search::setup_index_with_documents(
+        |settings| settings.set_sortable_fields(hashset! { S("metrics.rating") }),
+        json!([
+            { "id": 0, "metrics": { "rating": 4.2, "views": 10 } },
+            { "id": 1, "metrics": { "rating": 3.5, "views": 30 } },
+            { "id": 2, "metrics": { "rating": 4.8, "views": 20 } },
+            { "id": 3, "metrics": { "rating": 1 } },
+        ]),
+    );
+
+    let rtxn = index.read_txn().unwrap();
+    let mut search = Search::new(&rtxn, &index);
+    search.sort_criteria(vec![AscDesc::Desc(Member::Field(S("metrics.rating")))]);
+    let SearchResult { documents_ids, .. } = search.execute().unwrap();
+    assert_eq!(documents_ids, vec![2, 0, 1, 3]);
+
+    search.sort_criteria(vec![AscDesc::Asc(Member::Field(S("metrics.rating")))]);
+    let SearchResult { documents_ids, .. } = search.execute().unwrap();
+    assert_eq!(documents_ids, vec![3, 1, 0, 2]);
+
+    // the sibling and the parent of a sortable field are not sortable.
+    for field in ["metrics.views", "metrics"] {
+        search.sort_criteria(vec![AscDesc::Desc(Member::Field(S(field)))]);
+        let error = search.execute().unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UserError(UserError::InvalidSortableAttribute { field: ref f, .. }) if f == field
+        ));
+    }
+    drop(rtxn);
+
+    // declaring the parent object makes all of its nested fields sortable.
+    let mut wtxn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+    let mut builder = Settings::new(&mut wtxn, &index, &config);
+    builder.set_sortable_fields(hashset! { S("metrics") });
+    builder.execute(|_| ()).unwrap();
+    wtxn.commit().unwrap();
+
+    let rtxn = index.read_txn().unwrap();
+    let mut search = Search::new(&rtxn, &index);
+    search.sort_criteria(vec![AscDesc::Desc(Member::Field(S("metrics.views")))]);
+    let SearchResult { documents_ids, .. } = search.execute().unwrap();
+    assert_eq!(documents_ids, vec![1, 2, 0, 3]);
+}
+
+#[test]
+fn sort_missing_values_placement() {
+    // the documents with an even id don't have any price.
+    let documents: Vec<_> = (0..10)
+        .map(|id| {
+            if id % 2 == 0 {
+                json!({ "id": id, "name": "hello" })
+            } else {
+                json!({ "id": id, "name": "hello", "price": 100 - id })
+            }
+        })
+        .collect();
+    let index = search::setup_index_with_documents(
+        |settings| settings.set_sortable_fields(hashset! { S("price") }),
+        documents.into(),
+    );
+
+    let rtxn = index.read_txn().unwrap();
+    let sorted = |asc_desc: AscDesc, missing_values: Option<MissingValues>| {
+        let mut search = Search::new(&rtxn, &index);
+        search.query("hello").sort_criteria(vec![asc_desc]);
+        if let Some(missing_values) = missing_values {
+            search.sort_missing_values(missing_values);
+        }
+        search.execute().unwrap().documents_ids
+    };
+    let asc = || AscDesc::Asc(Member::Field(S("price")));
+    let desc = || AscDesc::Desc(Member::Field(S("price")));
+
+    // the missing values are placed last by default.
+    assert_eq!(sorted(asc(), None), vec![9, 7, 5, 3, 1, 0, 2, 4, 6, 8]);
+    assert_eq!(sorted(asc(), Some(MissingValues::Last)), vec![9, 7, 5, 3, 1, 0, 2, 4, 6, 8]);
+    assert_eq!(sorted(desc(), Some(MissingValues::Last)), vec![1, 3, 5, 7, 9, 0, 2, 4, 6, 8]);
+
+    assert_eq!(sorted(asc(), Some(MissingValues::First)), vec![0, 2, 4, 6, 8, 9, 7, 5, 3, 1]);
+    assert_eq!(sorted(desc(), Some(MissingValues::First)), vec![0, 2, 4, 6, 8, 1, 3, 5, 7, 9]);
+
+    // the missing values are placed first in every page.
+    let mut search = Search::new(&rtxn, &index);
+    search.query("hello").sort_criteria(vec![asc()]);
+    search.sort_missing_values(MissingValues::First).offset(3).limit(4);
+    let SearchResult { documents_ids, .. } = search.execute().unwrap();
+    assert_eq!(documents_ids, vec![6, 8, 9, 7]);
+}
@@ -0,0 +1,49 @@
+use milli::{Index, Search, TermsMatchingStrategy};
+use serde_json::json;
+
+use crate::search::setup_index_with_documents;
+
+fn setup_index() -> Index {
+    setup_index_with_documents(
+        |_| (),
+        json!([
+            { "id": 0, "title": "ice cream with chocolate" },
+            { "id": 1, "title": "the best icecream in town" },
+            { "id": 2, "title": "ice is nice with some cream" },
+            { "id": 3, "title": "a strawberry cake" },
+        ]),
+    )
+}
+
+fn search_documents_ids(index: &Index, query: &str) -> Vec<u32> {
+    let txn = index.read_txn().unwrap();
+    let mut search = Search::new(&txn, index);
+    search.query(query);
+    search.terms_matching_strategy(TermsMatchingStrategy::All);
+    search.execute().unwrap().documents_ids
+}
+
+#[test]
+fn split_query_word() {
+    let index = setup_index();
+
+    // the word is split in the two words of the index that are the most frequent,
+    // they must be adjacent like the words of a phrase.
+    let mut documents_ids = search_documents_ids(&index, "icecream");
+    documents_ids.sort_unstable();
+    assert_eq!(documents_ids, vec![0, 1]);
+}
+
+#[test]
+fn concatenate_query_words() {
+    let index = setup_index();
+
+    let documents_ids = search_documents_ids(&index, "ice cream");
+    assert_eq!(documents_ids.len(), 3);
+    // the concatenation counts as adjacent words for the proximity criterion,
+    // the document where the words are far apart is ranked last.
+    let mut best = documents_ids[..2].to_vec();
+    best.sort_unstable();
+    assert_eq!(best, vec![0, 1]);
+    assert_eq!(documents_ids[2], 2);
+}
@@ -1,8 +1,8 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 
 use heed::EnvOpenOptions;
 use milli::update::{IndexDocuments, IndexDocumentsConfig, IndexerConfig, Settings};
-use milli::{Criterion, Index, Search};
+use milli::{AttributeTypoTolerance, Criterion, Index, Search};
 use serde_json::json;
 use tempfile::tempdir;
 use Criterion::*;
@@ -172,6 +172,52 @@ fn test_typo_disabled_on_word() {
     assert_eq!(result.documents_ids.len(), 1);
 }
 
+#[test]
+fn test_typo_disabled_on_derived_word() {
+    let index = crate::search::setup_index_with_documents(
+        |_| (),
+        json!([
+            {
+                "id": 1usize,
+                "data": "a7iii",
+            },
+        ]),
+    );
+
+    let mut txn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+
+    let mut builder = Settings::new(&mut txn, &index, &config);
+    // typo is supported for 4 letters words
+    builder.set_min_word_len_one_typo(4);
+    builder.execute(|_| ()).unwrap();
+
+    let mut search = Search::new(&txn, &index);
+    search.query("a7ii");
+    search.authorize_typos(true);
+    let result = search.execute().unwrap();
+    assert_eq!(result.documents_ids.len(), 1);
+
+    let mut builder = Settings::new(&mut txn, &index, &config);
+    let mut exact_words = BTreeSet::new();
+    // `a7iii` can't be matched with a typo anymore
+    exact_words.insert("A7III".to_string());
+    builder.set_exact_words(exact_words);
+    builder.execute(|_| ()).unwrap();
+
+    let mut search = Search::new(&txn, &index);
+    search.query("a7ii");
+    search.authorize_typos(true);
+    let result = search.execute().unwrap();
+    assert_eq!(result.documents_ids.len(), 0);
+
+    let mut search = Search::new(&txn, &index);
+    search.query("a7iii");
+    search.authorize_typos(true);
+    let result = search.execute().unwrap();
+    assert_eq!(result.documents_ids.len(), 1);
+}
+
 #[test]
 fn test_disable_typo_on_attribute() {
     let criteria = [Typo];
@@ -209,3 +255,80 @@ fn test_disable_typo_on_attribute() {
     let result = search.execute().unwrap();
     assert_eq!(result.documents_ids.len(), 0);
 }
+
+#[test]
+fn test_typo_tolerance_override_on_attribute() {
+    let index = crate::search::setup_index_with_documents(
+        |_| (),
+        json!([
+            {
+                "id": 1usize,
+                "sku": "keyboard",
+                "description": "black",
+            },
+            {
+                "id": 2usize,
+                "sku": "kb-02",
+                "description": "a mechanical keyboard",
+            },
+        ]),
+    );
+
+    let mut txn = index.write_txn().unwrap();
+    let config = IndexerConfig::default();
+
+    // typo in `keyb(o)ard`, matches both documents with default typo settings
+    let mut search = Search::new(&txn, &index);
+    search.query("keybard");
+    search.authorize_typos(true);
+    let result = search.execute().unwrap();
+    assert_eq!(result.documents_ids.len(), 2);
+
+    let mut builder = Settings::new(&mut txn, &index, &config);
+    // disable typos on `sku`
+    let mut tolerances = HashMap::new();
+    tolerances.insert(
+        "sku".to_string(),
+        AttributeTypoTolerance {
+            enabled: false,
+            min_word_len_one_typo: 5,
+            min_word_len_two_typos: 9,
+        },
+    );
+    builder.set_attributes_typo_tolerance(tolerances);
+    builder.execute(|_| ()).unwrap();
+
+    // only the `description` of the second document tolerates the typo
+    let mut search = Search::new(&txn, &index);
+    search.query("keybard");
+    search.authorize_typos(true);
+    let result = search.execute().unwrap();
+    assert_eq!(result.documents_ids, vec![1]);
+
+    // the exact word still matches in `sku`
+    let mut search = Search::new(&txn, &index);
+    search.query("keyboard");
+    search.authorize_typos(true);
+    let result = search.execute().unwrap();
+    assert_eq!(result.documents_ids.len(), 2);
+
+    let mut builder = Settings::new(&mut txn, &index, &config);
+    // a word must now be 8 chars long to tolerate a typo in `description`
+    let mut tolerances = HashMap::new();
+    tolerances.insert(
+        "description".to_string(),
+        AttributeTypoTolerance {
+            enabled: true,
+            min_word_len_one_typo: 8,
+            min_word_len_two_typos: 10,
+        },
+    );
+    builder.set_attributes_typo_tolerance(tolerances);
+    builder.execute(|_| ()).unwrap();
+
+    let mut search = Search::new(&txn, &index);
+    search.query("keybard");
+    search.authorize_typos(true);
+    let result = search.execute().unwrap();
+    assert_eq!(result.documents_ids, vec![0]);
+}
@@ -0,0 +1,52 @@
+use big_s::S;
+use milli::{Index, Search};
+use serde_json::json;
+
+use crate::search::setup_index_with_documents;
+
+fn setup_index(criteria: Vec<String>) -> Index {
+    setup_index_with_documents(
+        |settings| {
+            settings.set_searchable_fields(vec![S("title"), S("description")]);
+            settings.set_criteria(criteria);
+        },
+        json!([
+            {
+                "id": 0,
+                "title": "the outdoor catalog",
+                "description": "our brand new hiking boots are waterproof and designed for the long trails of the mountains"
+            },
+            {
+                "id": 1,
+                "title": "mountain gear",
+                "description": "light hiking boots for the summer trails"
+            },
+            {
+                "id": 2,
+                "title": "hiking boots",
+                "description": "a pair of leather shoes with a thick sole and a lot of hiking boots features"
+            },
+        ]),
+    )
+}
+
+fn search_documents_ids(index: &Index, query: &str) -> Vec<u32> {
+    let txn = index.read_txn().unwrap();
+    let mut search = Search::new(&txn, index);
+    search.query(query);
+    search.execute().unwrap().documents_ids
+}
+
+#[test]
+fn short_title_before_long_description() {
+    let index = setup_index(vec![S("words")]);
+    assert_eq!(search_documents_ids(&index, "hiking boots"), vec![0, 1, 2]);
+
+    // the title of the third document is only made of the query words, the second document
+    // matches in a description of 7 words and the first one in a description too long to be counted.
+    let index = setup_index(vec![S("words"), S("wordCount")]);
+    assert_eq!(search_documents_ids(&index, "hiking boots"), vec![2, 1, 0]);
+
+    // the shortest matched attribute is used, not the first searchable one.
+    assert_eq!(search_documents_ids(&index, "trails"), vec![1, 0]);
+}